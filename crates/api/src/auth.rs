@@ -1,14 +1,19 @@
 use axum::{
-    extract::FromRequestParts,
+    extract::{FromRequestParts, Query},
     http::{header::AUTHORIZATION, request::Parts, StatusCode},
 };
+use db::MembershipRepository;
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use shared::types::{OrganizationId, PlatformAdminId, UserId};
+use shared::AppError;
 use sqlx::PgPool;
 use std::future::Future;
 
-use crate::state::AppState;
+use crate::{
+    error::{ApiError, ApiResult},
+    state::AppState,
+};
 
 /// JWT claims
 #[derive(Debug, Serialize, Deserialize)]
@@ -18,8 +23,16 @@ pub struct Claims {
     pub platform_admin: Option<bool>, // True if this is a platform admin token
     pub exp: usize,                   // Expiration time
     pub iat: usize,                   // Issued at
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>, // Restricts the token to one purpose (e.g. stream tickets)
 }
 
+/// Scope carried by short-lived stream tickets
+pub const STREAM_TICKET_SCOPE: &str = "stream";
+
+/// How long a stream ticket may be used to open a stream
+pub const STREAM_TICKET_TTL_SECONDS: i64 = 60;
+
 impl Claims {
     pub fn new(user_id: UserId, org_id: Option<OrganizationId>, expires_in_hours: i64) -> Self {
         let now = chrono::Utc::now();
@@ -29,6 +42,7 @@ impl Claims {
             platform_admin: None,
             exp: (now + chrono::Duration::hours(expires_in_hours)).timestamp() as usize,
             iat: now.timestamp() as usize,
+            scope: None,
         }
     }
 
@@ -40,6 +54,7 @@ impl Claims {
             platform_admin: Some(true),
             exp: (now + chrono::Duration::hours(expires_in_hours)).timestamp() as usize,
             iat: now.timestamp() as usize,
+            scope: None,
         }
     }
 
@@ -91,8 +106,29 @@ pub fn create_platform_admin_token(
     )
 }
 
-/// Verify and decode a JWT token
-pub fn verify_token(token: &str, secret: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+/// Create a short-lived ticket that can only be used to open a stream
+pub fn create_stream_ticket(
+    user_id: UserId,
+    org_id: OrganizationId,
+    secret: &str,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let now = chrono::Utc::now();
+    let claims = Claims {
+        sub: user_id.to_string(),
+        org_id: Some(org_id.to_string()),
+        platform_admin: None,
+        exp: (now + chrono::Duration::seconds(STREAM_TICKET_TTL_SECONDS)).timestamp() as usize,
+        iat: now.timestamp() as usize,
+        scope: Some(STREAM_TICKET_SCOPE.to_string()),
+    };
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(secret.as_bytes()),
+    )
+}
+
+fn decode_claims(token: &str, secret: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
     let token_data = decode::<Claims>(
         token,
         &DecodingKey::from_secret(secret.as_bytes()),
@@ -101,6 +137,30 @@ pub fn verify_token(token: &str, secret: &str) -> Result<Claims, jsonwebtoken::e
     Ok(token_data.claims)
 }
 
+/// Verify and decode a JWT token
+///
+/// Scoped tokens such as stream tickets are rejected here so they can't be
+/// used as session tokens.
+pub fn verify_token(token: &str, secret: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+    let claims = decode_claims(token, secret)?;
+    if claims.scope.is_some() {
+        return Err(jsonwebtoken::errors::ErrorKind::InvalidToken.into());
+    }
+    Ok(claims)
+}
+
+/// Verify and decode a stream ticket
+pub fn verify_stream_ticket(
+    token: &str,
+    secret: &str,
+) -> Result<Claims, jsonwebtoken::errors::Error> {
+    let claims = decode_claims(token, secret)?;
+    if claims.scope.as_deref() != Some(STREAM_TICKET_SCOPE) {
+        return Err(jsonwebtoken::errors::ErrorKind::InvalidToken.into());
+    }
+    Ok(claims)
+}
+
 /// Extractor for authenticated user
 pub struct AuthUser {
     pub user_id: UserId,
@@ -152,6 +212,24 @@ pub struct TenantContext {
     pub pool: PgPool,
 }
 
+impl TenantContext {
+    /// Whether `user_id` is an owner or admin of the organization
+    pub async fn is_manager(&self, user_id: UserId) -> ApiResult<bool> {
+        let memberships =
+            MembershipRepository::find_by_user_and_org(&self.pool, user_id, self.org_id).await?;
+
+        Ok(memberships.iter().any(|m| m.role.is_manager()))
+    }
+
+    /// Forbid anyone but an owner or admin of the organization
+    pub async fn require_manager(&self, user_id: UserId) -> ApiResult<()> {
+        if !self.is_manager(user_id).await? {
+            return Err(ApiError::from(AppError::Forbidden));
+        }
+        Ok(())
+    }
+}
+
 impl FromRequestParts<AppState> for TenantContext {
     type Rejection = (StatusCode, &'static str);
 
@@ -248,3 +326,82 @@ impl FromRequestParts<AppState> for PlatformAdminAuth {
         Box::pin(std::future::ready(auth_result))
     }
 }
+
+/// Extractor for long-lived streaming endpoints (Server-Sent Events).
+///
+/// Browsers' `EventSource` can't set an Authorization header, so besides a
+/// Bearer session token this accepts a `ticket` query parameter holding a
+/// short-lived stream ticket from `POST /auth/stream-ticket`. Session tokens
+/// are never accepted in the query string, where they would end up in access
+/// logs. The tenant is resolved from the token exactly as for
+/// [`TenantContext`].
+pub struct StreamAuth {
+    pub auth: AuthUser,
+    pub tenant: TenantContext,
+}
+
+#[derive(Deserialize)]
+struct StreamTicketQuery {
+    ticket: Option<String>,
+}
+
+impl FromRequestParts<AppState> for StreamAuth {
+    type Rejection = (StatusCode, &'static str);
+
+    fn from_request_parts<'life0, 'life1, 'async_trait>(
+        parts: &'life0 mut Parts,
+        state: &'life1 AppState,
+    ) -> std::pin::Pin<Box<dyn Future<Output = Result<Self, Self::Rejection>> + Send + 'async_trait>>
+    where
+        'life0: 'async_trait,
+        'life1: 'async_trait,
+        Self: 'async_trait,
+    {
+        let jwt_secret = state.jwt_secret.clone();
+        let tenant_pool_manager = state.tenant_pool_manager.clone();
+
+        Box::pin(async move {
+            let header_token = parts
+                .headers
+                .get(AUTHORIZATION)
+                .and_then(|h| h.to_str().ok())
+                .and_then(|h| h.strip_prefix("Bearer "));
+
+            let claims = match header_token {
+                Some(token) => verify_token(token, &jwt_secret)
+                    .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid token"))?,
+                None => {
+                    let Query(query) = Query::<StreamTicketQuery>::try_from_uri(&parts.uri)
+                        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid query string"))?;
+                    let ticket = query
+                        .ticket
+                        .ok_or((StatusCode::UNAUTHORIZED, "Missing access token"))?;
+                    verify_stream_ticket(&ticket, &jwt_secret)
+                        .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid stream ticket"))?
+                }
+            };
+
+            let user_id = claims
+                .user_id()
+                .ok_or((StatusCode::UNAUTHORIZED, "Invalid user ID in token"))?;
+
+            let org_id = claims.org_id().ok_or((
+                StatusCode::UNAUTHORIZED,
+                "Organization ID missing from token",
+            ))?;
+
+            let pool = tenant_pool_manager
+                .get_pool(org_id)
+                .await
+                .map_err(|_| (StatusCode::UNAUTHORIZED, "Invalid organization"))?;
+
+            Ok(StreamAuth {
+                auth: AuthUser {
+                    user_id,
+                    org_id: Some(org_id),
+                },
+                tenant: TenantContext { org_id, pool },
+            })
+        })
+    }
+}
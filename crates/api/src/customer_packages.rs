@@ -0,0 +1,603 @@
+//! Service packages: customer subscriptions to a manager's package plan,
+//! charged each billing period through the tenant's payment provider and
+//! granting walk credits. The plan sets the price and walks per period.
+//!
+//! Bookings the package covers redeem a credit instead of being charged. A
+//! credit comes back when its booking is cancelled without a fee, and credits
+//! left when a period ends expire. Packages that auto-create bookings schedule
+//! each period's walks with the preferred walker on the preferred day and
+//! time.
+//!
+//! Package charges aren't paid to a walker: each walk is earned by its
+//! booking's walker when a credit is redeemed for it.
+
+use chrono::{DateTime, Duration, Utc};
+use db::models::{
+    Booking, BookingPriceBreakdown, CreateBooking, CreateTransaction, CustomerPaymentMethod,
+    CustomerSubscription, PackageCredit, Transaction, TransactionStatus, UpdateTransaction,
+};
+use db::{
+    BookingRepository, CustomerPaymentMethodRepository, LocationRepository,
+    PaymentProviderRepository, ServiceAreaRepository, ServiceRepository, SubscriptionRepository,
+    TransactionRepository,
+};
+use integrations::gateway::PaymentState;
+use rust_decimal::Decimal;
+use shared::types::{BookingId, Money, ServiceId};
+use shared::AppError;
+
+use crate::{
+    auth::TenantContext,
+    error::{ApiError, ApiResult},
+    payments::{
+        charge_transaction, fee_breakdown, organization_currency, processing_fee, record_payment,
+        transaction_gateway,
+    },
+    routes::checkout::cents,
+    state::AppState,
+    tax, walker_earnings,
+};
+
+/// `charge` recorded in the metadata of a package period's payment
+pub const PACKAGE_CHARGE: &str = "package";
+
+/// How long a renewal run has to charge the subscriptions it claimed before
+/// another run may claim them again
+const RENEWAL_CLAIM_MINUTES: i64 = 60;
+
+/// Charge a new subscription's first period and start it.
+///
+/// The customer is present, so the charge is on-session. A subscription
+/// whose first payment fails is cancelled.
+pub async fn start(
+    state: &AppState,
+    tenant: &TenantContext,
+    subscription: &CustomerSubscription,
+    payment_method: &CustomerPaymentMethod,
+) -> ApiResult<CustomerSubscription> {
+    // Credits only pay for bookings of the package's service
+    if subscription.is_package() && subscription.service_id.is_none() {
+        return Err(ApiError::from(AppError::Validation(
+            "A package needs a service".to_string(),
+        )));
+    }
+
+    let now = Utc::now();
+    let transaction =
+        match charge_period(state, tenant, subscription, payment_method, now, false).await {
+            Ok(transaction) => transaction,
+            Err(e) => {
+                SubscriptionRepository::cancel_customer_subscription(
+                    &tenant.pool,
+                    tenant.org_id,
+                    subscription.id,
+                    true,
+                )
+                .await?;
+                return Err(e);
+            }
+        };
+
+    begin_period(tenant, subscription, now, transaction.id).await
+}
+
+/// Charge every subscription whose period has ended for the next one, and
+/// end those set to cancel at period end.
+///
+/// A failed payment leaves the subscription past due and is retried after
+/// each of [`PACKAGE_PAYMENT_RETRY_DAYS`](db::models::PACKAGE_PAYMENT_RETRY_DAYS);
+/// the subscription is cancelled when the last retry fails. Subscriptions
+/// are claimed before they're charged, so concurrent runs don't both charge
+/// one. Returns the number of subscriptions renewed or ended.
+pub async fn renew_due(
+    state: &AppState,
+    tenant: &TenantContext,
+    now: DateTime<Utc>,
+) -> ApiResult<usize> {
+    let mut due = SubscriptionRepository::claim_due_customer_subscriptions(
+        &tenant.pool,
+        tenant.org_id,
+        now,
+        now + Duration::minutes(RENEWAL_CLAIM_MINUTES),
+    )
+    .await?;
+    due.sort_by_key(|s| s.current_period_end);
+
+    let mut count = 0;
+    for subscription in &due {
+        let period_end = subscription.current_period_end.unwrap_or(now);
+
+        if subscription.cancel_at_period_end {
+            SubscriptionRepository::cancel_customer_subscription(
+                &tenant.pool,
+                tenant.org_id,
+                subscription.id,
+                true,
+            )
+            .await?;
+            SubscriptionRepository::expire_credits(
+                &tenant.pool,
+                tenant.org_id,
+                subscription.id,
+                period_end,
+            )
+            .await?;
+            count += 1;
+            continue;
+        }
+
+        let payment_method = match subscription.payment_method_id {
+            Some(id) => {
+                CustomerPaymentMethodRepository::find_by_id(&tenant.pool, tenant.org_id, id).await?
+            }
+            None => None,
+        };
+        let charged = match &payment_method {
+            Some(pm) => charge_period(state, tenant, subscription, pm, period_end, true).await,
+            None => Err(ApiError::from(AppError::Validation(
+                "No payment method for the subscription".to_string(),
+            ))),
+        };
+
+        match charged {
+            Ok(transaction) => {
+                begin_period(tenant, subscription, period_end, transaction.id).await?;
+            }
+            Err(e) => {
+                let (status, retry_at) = subscription.after_failed_payment(now);
+                tracing::warn!(
+                    "Payment for subscription {} failed (attempt {}), now {}: {}",
+                    subscription.id,
+                    subscription.failed_payment_attempts + 1,
+                    status,
+                    e.0
+                );
+                SubscriptionRepository::record_package_payment_failure(
+                    &tenant.pool,
+                    tenant.org_id,
+                    subscription.id,
+                    status,
+                    retry_at,
+                )
+                .await?;
+                if retry_at.is_none() {
+                    SubscriptionRepository::expire_credits(
+                        &tenant.pool,
+                        tenant.org_id,
+                        subscription.id,
+                        period_end,
+                    )
+                    .await?;
+                }
+            }
+        }
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+/// Pay for a new booking with a credit from the customer's package for its
+/// service, if they have one with credits left
+pub async fn redeem_for_booking(
+    tenant: &TenantContext,
+    booking: &Booking,
+) -> ApiResult<Option<PackageCredit>> {
+    let Some(package) = SubscriptionRepository::find_package_for_booking(
+        &tenant.pool,
+        tenant.org_id,
+        booking.customer_id,
+        *booking.service_id.as_uuid(),
+    )
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    redeem(tenant, &package, booking).await
+}
+
+/// Whether a booking was paid for with a package credit
+pub async fn is_credit_paid(tenant: &TenantContext, booking_id: BookingId) -> ApiResult<bool> {
+    Ok(
+        SubscriptionRepository::get_booking_redemption(&tenant.pool, tenant.org_id, booking_id)
+            .await?
+            .is_some(),
+    )
+}
+
+/// Settle the credit of a cancelled booking: returned if the cancellation
+/// was free, forfeited like a cancellation fee otherwise.
+///
+/// Returns whether the booking was paid for with a credit.
+pub async fn release_for_cancellation(
+    tenant: &TenantContext,
+    booking: &Booking,
+    free: bool,
+) -> ApiResult<bool> {
+    let Some(redemption) =
+        SubscriptionRepository::get_booking_redemption(&tenant.pool, tenant.org_id, booking.id)
+            .await?
+    else {
+        return Ok(false);
+    };
+
+    if free {
+        let returned = SubscriptionRepository::restore_credit(
+            &tenant.pool,
+            tenant.org_id,
+            redemption.subscription_id,
+            booking.id,
+        )
+        .await?;
+        if let Some(returned) = returned {
+            walker_earnings::record_credit_return(
+                &tenant.pool,
+                tenant.org_id,
+                &redemption,
+                &returned,
+            )
+            .await?;
+        }
+    }
+    Ok(true)
+}
+
+/// Spend a credit of `package` on a booking, earning the booking's walker
+/// their share of the walk
+async fn redeem(
+    tenant: &TenantContext,
+    package: &CustomerSubscription,
+    booking: &Booking,
+) -> ApiResult<Option<PackageCredit>> {
+    let Some(credit) =
+        SubscriptionRepository::redeem_credit(&tenant.pool, tenant.org_id, package.id, booking.id)
+            .await?
+    else {
+        return Ok(None);
+    };
+
+    walker_earnings::record_redemption(
+        &tenant.pool,
+        tenant.org_id,
+        booking,
+        &credit,
+        package.walks_per_period.unwrap_or(0),
+    )
+    .await?;
+    Ok(Some(credit))
+}
+
+/// Start a paid period: expire what's left of the last one, grant the new
+/// period's credits and schedule its walks
+async fn begin_period(
+    tenant: &TenantContext,
+    subscription: &CustomerSubscription,
+    period_start: DateTime<Utc>,
+    transaction_id: uuid::Uuid,
+) -> ApiResult<CustomerSubscription> {
+    let period_end = subscription.period_after(period_start);
+    let renewed = SubscriptionRepository::renew_customer_subscription(
+        &tenant.pool,
+        tenant.org_id,
+        subscription.id,
+        period_start,
+        period_end,
+    )
+    .await?
+    .ok_or_else(|| ApiError::from(AppError::NotFound("Subscription not found".to_string())))?;
+
+    SubscriptionRepository::expire_credits(
+        &tenant.pool,
+        tenant.org_id,
+        subscription.id,
+        period_start,
+    )
+    .await?;
+
+    let Some(walks) = renewed.walks_per_period else {
+        return Ok(renewed);
+    };
+    let granted = SubscriptionRepository::grant_credits(
+        &tenant.pool,
+        tenant.org_id,
+        subscription.id,
+        walks,
+        Some(transaction_id),
+        period_end,
+    )
+    .await?;
+
+    if granted.is_some() && renewed.auto_create_bookings {
+        // The period is paid for either way; unscheduled credits can still
+        // be booked by hand
+        match schedule_walks(tenant, &renewed, period_start, period_end).await {
+            Ok(count) => tracing::info!(
+                "Scheduled {} walks for subscription {}",
+                count,
+                subscription.id
+            ),
+            Err(e) => tracing::warn!(
+                "Failed to schedule walks for subscription {}: {}",
+                subscription.id,
+                e.0
+            ),
+        }
+    }
+
+    Ok(renewed)
+}
+
+/// Book the period's upcoming walks with the preferred walker, each paid for
+/// with a credit, until the credits run out. Times the walker already has
+/// booked are skipped.
+async fn schedule_walks(
+    tenant: &TenantContext,
+    subscription: &CustomerSubscription,
+    period_start: DateTime<Utc>,
+    period_end: DateTime<Utc>,
+) -> ApiResult<usize> {
+    let (Some(service_id), Some(walker_id), Some(location_id)) = (
+        subscription.service_id,
+        subscription.preferred_walker_id,
+        subscription.location_id,
+    ) else {
+        return Ok(0);
+    };
+
+    let service_id = ServiceId::from_uuid(service_id);
+    let service = ServiceRepository::find_by_id(&tenant.pool, tenant.org_id, service_id)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::NotFound("Service not found".to_string())))?;
+    let location = LocationRepository::find_by_id(&tenant.pool, tenant.org_id, location_id)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::NotFound("Location not found".to_string())))?;
+
+    // Priced like any booking, so walker earnings follow the area adjustment
+    let matching_areas = ServiceAreaRepository::find_walker_areas_for_location(
+        &tenant.pool,
+        tenant.org_id,
+        walker_id,
+        location.latitude,
+        location.longitude,
+    )
+    .await?;
+    let price_breakdown =
+        BookingPriceBreakdown::calculate(service.base_price_cents, &matching_areas);
+
+    let now = Utc::now();
+    let mut scheduled = 0;
+    for start in subscription
+        .walk_starts(period_start, period_end)
+        .into_iter()
+        .filter(|start| *start > now)
+    {
+        let balance =
+            SubscriptionRepository::credit_balance(&tenant.pool, tenant.org_id, subscription.id)
+                .await?;
+        if balance <= 0 {
+            break;
+        }
+
+        let booking = match BookingRepository::create(
+            &tenant.pool,
+            CreateBooking {
+                organization_id: tenant.org_id,
+                customer_id: subscription.user_id,
+                walker_id,
+                service_id,
+                location_id,
+                scheduled_start: start,
+                scheduled_end: start + Duration::minutes(service.duration_minutes as i64),
+                price_cents: price_breakdown.total_cents,
+                price_breakdown: Some(price_breakdown.clone()),
+                notes: Some(format!("Scheduled by {}", subscription.name)),
+                recurring_series_id: None,
+                occurrence_number: None,
+            },
+        )
+        .await
+        {
+            Ok(booking) => booking,
+            Err(e) => {
+                tracing::warn!(
+                    "Skipped walk at {} for subscription {}: {}",
+                    start,
+                    subscription.id,
+                    e
+                );
+                continue;
+            }
+        };
+
+        // Credits spent since the balance was read leave the walk unpaid
+        let redeemed = redeem(tenant, subscription, &booking).await?;
+        if redeemed.is_none() {
+            BookingRepository::cancel(&tenant.pool, tenant.org_id, booking.id).await?;
+            break;
+        }
+        scheduled += 1;
+    }
+
+    Ok(scheduled)
+}
+
+/// Charge a period of a subscription to its saved payment method. Only a
+/// completed payment counts.
+///
+/// Each attempt at a period is charged under one idempotency key and
+/// transaction, so an attempt retried after the charge went through gets the
+/// original payment back rather than billing the period again.
+async fn charge_period(
+    state: &AppState,
+    tenant: &TenantContext,
+    subscription: &CustomerSubscription,
+    payment_method: &CustomerPaymentMethod,
+    period_start: DateTime<Utc>,
+    off_session: bool,
+) -> ApiResult<Transaction> {
+    let metadata = serde_json::json!({
+        "charge": PACKAGE_CHARGE,
+        "customer_subscription_id": subscription.id.to_string(),
+        "period_start": period_start.to_rfc3339(),
+    });
+    let existing =
+        TransactionRepository::find_live_by_metadata(&tenant.pool, tenant.org_id, &metadata)
+            .await?;
+    if let Some(transaction) = existing.as_ref().filter(|t| t.is_successful()) {
+        return Ok(transaction.clone());
+    }
+
+    let provider = PaymentProviderRepository::get_primary(&tenant.pool, tenant.org_id)
+        .await?
+        .ok_or_else(|| {
+            ApiError::from(AppError::Validation(
+                "No payment provider configured".to_string(),
+            ))
+        })?;
+
+    // Packages for a service are charged in its currency
+    let currency = match subscription.service_id {
+        Some(service_id) => ServiceRepository::find_by_id(
+            &tenant.pool,
+            tenant.org_id,
+            ServiceId::from_uuid(service_id),
+        )
+        .await?
+        .map(|service| service.currency),
+        None => None,
+    };
+    let currency = match currency {
+        Some(currency) => currency,
+        None => organization_currency(state, tenant).await?,
+    };
+    let subtotal = Money::new(i64::from(subscription.price_cents), currency);
+
+    // Taxed where the walks take place, if known
+    let location = match subscription.location_id {
+        Some(id) => LocationRepository::find_by_id(&tenant.pool, tenant.org_id, id).await?,
+        None => None,
+    };
+    let tax = match &location {
+        Some(location) => Some(tax::quote(state, location, subtotal).await?),
+        None => None,
+    };
+    let tax_rate_percent = tax.as_ref().map_or(Decimal::ZERO, |t| t.rate_percent);
+    let processing_fee = processing_fee(
+        tenant,
+        provider.provider_type,
+        Some(payment_method),
+        currency,
+    )
+    .await?;
+    let fee_breakdown = fee_breakdown(tenant, subtotal, tax_rate_percent, processing_fee).await?;
+
+    let idempotency_key = format!(
+        "package_{}_{}_{}",
+        subscription.id,
+        period_start.timestamp_micros(),
+        subscription.failed_payment_attempts
+    );
+    let input = CreateTransaction {
+        booking_id: None,
+        customer_user_id: subscription.user_id,
+        provider_user_id: None,
+        payment_method_id: Some(payment_method.id),
+        provider_id: provider.id,
+        subtotal_cents: subscription.price_cents,
+        tip_cents: 0,
+        customer_fee_cents: cents(fee_breakdown.customer_fee)?,
+        provider_fee_cents: cents(fee_breakdown.provider_fee)?,
+        platform_fee_cents: cents(fee_breakdown.platform_fee)?,
+        tax_cents: cents(fee_breakdown.tax)?,
+        processing_fee_cents: cents(processing_fee.apply(fee_breakdown.total)?)?,
+        total_cents: cents(fee_breakdown.total)?,
+        provider_payout_cents: cents(fee_breakdown.provider_payout)?,
+        currency,
+        tax_rate_percent: tax.as_ref().map(|t| t.rate_percent),
+        tax_jurisdiction: tax.as_ref().map(|t| t.jurisdiction.clone()),
+        tax_calculation_id: tax.and_then(|t| t.calculation_id),
+        description: Some(subscription.name.clone()),
+        metadata: Some(metadata),
+        idempotency_key: Some(idempotency_key.clone()),
+        manual_capture: false,
+        off_session,
+    };
+    // An attempt that didn't finish is picked up where it left off, and sent
+    // exactly as it was the first time
+    let existing = match existing {
+        Some(transaction) => Some(transaction),
+        None => {
+            TransactionRepository::get_by_idempotency_key(
+                &tenant.pool,
+                tenant.org_id,
+                &idempotency_key,
+            )
+            .await?
+        }
+    };
+    let (transaction, payment_method) = match existing {
+        Some(transaction) => {
+            let payment_method = match transaction.payment_method_id {
+                Some(id) if id != payment_method.id => {
+                    CustomerPaymentMethodRepository::find_by_id(&tenant.pool, tenant.org_id, id)
+                        .await?
+                        .ok_or_else(|| {
+                            ApiError::from(AppError::NotFound(
+                                "Payment method not found".to_string(),
+                            ))
+                        })?
+                }
+                _ => payment_method.clone(),
+            };
+            (transaction, payment_method)
+        }
+        None => {
+            let transaction = TransactionRepository::create(&tenant.pool, tenant.org_id, input)
+                .await?
+                // Only created concurrently under the same key
+                .ok_or_else(|| {
+                    ApiError::from(AppError::Internal("Package charge not created".to_string()))
+                })?;
+            (transaction, payment_method.clone())
+        }
+    };
+
+    let (provider, gateway) = transaction_gateway(state, tenant, &transaction).await?;
+    let payment =
+        match charge_transaction(gateway.as_ref(), &transaction, Some(&payment_method)).await {
+            Ok(payment) => payment,
+            Err(e) => {
+                TransactionRepository::update(
+                    &tenant.pool,
+                    transaction.id,
+                    UpdateTransaction {
+                        status: Some(TransactionStatus::Failed),
+                        failure_message: Some(e.0.to_string()),
+                        ..Default::default()
+                    },
+                )
+                .await?;
+                return Err(e);
+            }
+        };
+
+    let transaction = record_payment(
+        state,
+        tenant,
+        &transaction,
+        provider.provider_type,
+        gateway.as_ref(),
+        &payment,
+    )
+    .await?;
+
+    if payment.state != PaymentState::Succeeded {
+        return Err(ApiError::from(AppError::ExternalApi(format!(
+            "Payment for {} is {:?}",
+            subscription.name, payment.state
+        ))));
+    }
+
+    Ok(transaction)
+}
@@ -0,0 +1,186 @@
+//! Chargebacks against the organization's payments.
+//!
+//! The provider opens and decides disputes and reports them through
+//! webhooks; each change is posted to the ledger and walker earnings, which
+//! are keyed by the dispute so repeated events post once. Evidence is
+//! assembled from the booking the payment was for and can be edited by a
+//! manager before it's submitted. Until then managers see a reminder on the
+//! dispute list as the due date comes within 7, 3 and 1 days, which stays
+//! until one of them dismisses it.
+
+use chrono::{DateTime, Utc};
+use db::models::{
+    Dispute, DisputeEvidence, DisputeStatus, EvidenceSources, TransactionStatus, UpdateDispute,
+};
+use db::{
+    BookingRepository, CancellationPolicyRepository, DisputeRepository, PaymentProviderRepository,
+    ServiceRepository, TransactionRepository, UserRepository, WalkTrailRepository,
+};
+use integrations::gateway::DisputeEvidenceRequest;
+use shared::AppError;
+use sqlx::PgPool;
+
+use crate::{
+    auth::TenantContext,
+    error::{ApiError, ApiResult},
+    ledger,
+    state::AppState,
+    walker_earnings,
+};
+
+/// Assemble evidence for a dispute from the booking its payment was for
+pub async fn assemble_evidence(
+    tenant: &TenantContext,
+    dispute: &Dispute,
+) -> ApiResult<DisputeEvidence> {
+    let transaction =
+        TransactionRepository::get_by_id(&tenant.pool, tenant.org_id, dispute.transaction_id)
+            .await?
+            .ok_or_else(|| ApiError::from(AppError::NotFound("Transaction".to_string())))?;
+
+    let booking = match transaction.related_booking_id() {
+        Some(booking_id) => {
+            BookingRepository::find_by_id(&tenant.pool, tenant.org_id, booking_id).await?
+        }
+        None => None,
+    };
+    let (service, trail, policy) = match &booking {
+        Some(booking) => (
+            ServiceRepository::find_by_id(&tenant.pool, tenant.org_id, booking.service_id).await?,
+            WalkTrailRepository::list_for_booking(&tenant.pool, tenant.org_id, booking.id).await?,
+            CancellationPolicyRepository::get_for_service(
+                &tenant.pool,
+                tenant.org_id,
+                booking.service_id,
+            )
+            .await?,
+        ),
+        None => (None, Vec::new(), None),
+    };
+    let customer =
+        UserRepository::find_by_id(&tenant.pool, tenant.org_id, transaction.customer_user_id)
+            .await?;
+
+    Ok(DisputeEvidence::assemble(EvidenceSources {
+        transaction: &transaction,
+        booking: booking.as_ref(),
+        service: service.as_ref(),
+        customer: customer.as_ref(),
+        trail: &trail,
+        policy: policy.as_ref(),
+    }))
+}
+
+/// Submit evidence to the provider that opened the dispute
+pub async fn submit_evidence(
+    state: &AppState,
+    tenant: &TenantContext,
+    dispute: &Dispute,
+    evidence: &DisputeEvidence,
+) -> ApiResult<Dispute> {
+    if dispute.status != DisputeStatus::NeedsResponse || dispute.evidence_submitted {
+        return Err(ApiError::from(AppError::Validation(
+            "Evidence can only be submitted once, while the dispute needs a response".to_string(),
+        )));
+    }
+    let provider_dispute_id = dispute
+        .stripe_dispute_id
+        .clone()
+        .or_else(|| dispute.square_dispute_id.clone())
+        .ok_or_else(|| {
+            ApiError::from(AppError::Validation(
+                "The dispute has no provider dispute ID".to_string(),
+            ))
+        })?;
+
+    let transaction =
+        TransactionRepository::get_by_id(&tenant.pool, tenant.org_id, dispute.transaction_id)
+            .await?
+            .ok_or_else(|| ApiError::from(AppError::NotFound("Transaction".to_string())))?;
+    let provider =
+        PaymentProviderRepository::get_by_id(&tenant.pool, tenant.org_id, transaction.provider_id)
+            .await?
+            .ok_or_else(|| ApiError::from(AppError::NotFound("Payment provider".to_string())))?;
+    let gateway = state.payment_gateways.for_provider(&provider)?;
+
+    gateway
+        .submit_dispute_evidence(&DisputeEvidenceRequest {
+            dispute_id: provider_dispute_id,
+            customer_name: evidence.customer_name.clone(),
+            customer_email: evidence.customer_email.clone(),
+            product_description: evidence.product_description.clone(),
+            service_date: evidence.service_date.clone(),
+            service_documentation: evidence.service_documentation.clone(),
+            customer_communication: evidence.customer_communication.clone(),
+            receipt: evidence.receipt.clone(),
+            cancellation_policy: evidence.cancellation_policy.clone(),
+            additional_documentation: evidence
+                .additional_documentation
+                .clone()
+                .unwrap_or_default(),
+        })
+        .await?;
+
+    DisputeRepository::mark_evidence_submitted(&tenant.pool, dispute.id, evidence)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::NotFound("Dispute".to_string())))
+}
+
+/// Apply a status the provider reported for a dispute, posting the outcome
+/// once it's decided. A decided dispute isn't reopened. Posting is repeated
+/// for reports that change nothing, so a retried report finishes posting.
+pub async fn apply_status(
+    pool: &PgPool,
+    dispute: &Dispute,
+    status: DisputeStatus,
+    outcome: Option<&str>,
+    evidence_due_by: Option<DateTime<Utc>>,
+) -> Result<Dispute, sqlx::Error> {
+    let due_by_changed = evidence_due_by.is_some() && evidence_due_by != dispute.evidence_due_by;
+    let updated = if dispute.is_resolved() || (dispute.status == status && !due_by_changed) {
+        dispute.clone()
+    } else {
+        let resolved = matches!(status, DisputeStatus::Won | DisputeStatus::Lost);
+        let update = UpdateDispute {
+            status: Some(status),
+            evidence_due_by,
+            resolved_at: resolved.then(Utc::now),
+            outcome: outcome.filter(|_| resolved).map(str::to_string),
+            ..Default::default()
+        };
+        DisputeRepository::update(pool, dispute.id, update)
+            .await?
+            .unwrap_or_else(|| dispute.clone())
+    };
+
+    ledger::post_dispute(pool, updated.organization_id, &updated).await?;
+    walker_earnings::record_dispute(pool, updated.organization_id, &updated).await?;
+
+    // A won dispute returns the payment to the merchant
+    if updated.status == DisputeStatus::Won {
+        TransactionRepository::update_status(
+            pool,
+            updated.transaction_id,
+            TransactionStatus::Succeeded,
+        )
+        .await?;
+    }
+    Ok(updated)
+}
+
+/// Dismiss the evidence reminder due at `now`, once a manager has seen it.
+/// The next reminder comes due as the due date nears.
+pub async fn dismiss_reminder(
+    tenant: &TenantContext,
+    dispute: &Dispute,
+    now: DateTime<Utc>,
+) -> ApiResult<Dispute> {
+    let Some(days) = dispute.reminder_due(now) else {
+        return Ok(dispute.clone());
+    };
+    Ok(
+        DisputeRepository::mark_reminded(&tenant.pool, tenant.org_id, dispute.id, days)
+            .await?
+            .unwrap_or_else(|| dispute.clone()),
+    )
+}
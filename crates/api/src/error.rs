@@ -7,6 +7,7 @@ use serde_json::json;
 use shared::AppError;
 
 /// API error wrapper that implements IntoResponse
+#[derive(Debug)]
 pub struct ApiError(pub AppError);
 
 impl IntoResponse for ApiError {
@@ -50,5 +51,29 @@ impl From<shared::DomainError> for ApiError {
     }
 }
 
+impl From<shared::types::MoneyError> for ApiError {
+    fn from(err: shared::types::MoneyError) -> Self {
+        Self(AppError::Validation(err.to_string()))
+    }
+}
+
+impl From<integrations::gateway::GatewayError> for ApiError {
+    fn from(err: integrations::gateway::GatewayError) -> Self {
+        use integrations::gateway::GatewayError;
+
+        match err {
+            GatewayError::Declined(code) => {
+                Self(AppError::Domain(shared::DomainError::PaymentDeclined(code)))
+            }
+            GatewayError::InvalidRequest(message) => Self(AppError::Validation(message)),
+            GatewayError::NotFound(id) => {
+                Self(AppError::NotFound(format!("Payment not found: {}", id)))
+            }
+            GatewayError::NotSupported(_) => Self(AppError::Validation(err.to_string())),
+            GatewayError::Provider { message, .. } => Self(AppError::ExternalApi(message)),
+        }
+    }
+}
+
 /// Result type for API handlers
 pub type ApiResult<T> = Result<T, ApiError>;
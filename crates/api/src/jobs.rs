@@ -0,0 +1,295 @@
+//! Background jobs that run alongside the API server.
+
+use std::time::Duration;
+
+use chrono::{Days, Utc};
+use db::{OrganizationRepository, PaymentProviderRepository, ReconciliationRepository};
+
+use crate::{
+    auth::TenantContext, customer_packages, error::ApiResult, payments, payouts, reconciliation,
+    routes::walk_trails, state::AppState, tax, tenant_billing, webhook_events,
+};
+
+/// How often held payments are checked for upcoming expiry
+const AUTHORIZATION_REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often holds on completed or cancelled bookings are captured or
+/// released again after a failure
+const HOLD_SETTLEMENT_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// How often the previous day is checked for providers still to reconcile
+const RECONCILIATION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often organizations are checked for a scheduled payout due today
+const SCHEDULED_PAYOUT_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How often sales and refunds the tax provider missed are reported again
+const TAX_REPORTING_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often past-due platform subscriptions are checked for a lapsed
+/// grace period
+const BILLING_DUNNING_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often customer subscriptions are checked for a period due to be
+/// charged
+const PACKAGE_RENEWAL_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often stored webhook events are checked for ones due to be processed
+const WEBHOOK_PROCESSING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often walk trail breadcrumbs past the retention period are purged
+const TRAIL_PURGE_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Organizations loaded per page when iterating tenants
+const ORGANIZATION_PAGE_SIZE: i64 = 100;
+
+/// Work done for each organization by a background job
+#[derive(Debug, Clone, Copy)]
+enum TenantJob {
+    BillingDunning,
+    RefreshAuthorizations,
+    Reconcile,
+    ProcessWebhooks,
+    PurgeTrails,
+    RenewPackages,
+    ReportTax,
+    ScheduledPayouts,
+    SettleHolds,
+}
+
+/// Start the payment and maintenance background jobs
+pub fn spawn_payment_jobs(state: AppState) {
+    spawn_job(
+        state.clone(),
+        TenantJob::RefreshAuthorizations,
+        AUTHORIZATION_REFRESH_INTERVAL,
+    );
+    spawn_job(
+        state.clone(),
+        TenantJob::SettleHolds,
+        HOLD_SETTLEMENT_INTERVAL,
+    );
+    spawn_job(state.clone(), TenantJob::Reconcile, RECONCILIATION_INTERVAL);
+    spawn_job(
+        state.clone(),
+        TenantJob::ProcessWebhooks,
+        WEBHOOK_PROCESSING_INTERVAL,
+    );
+    spawn_job(state.clone(), TenantJob::ReportTax, TAX_REPORTING_INTERVAL);
+    spawn_job(
+        state.clone(),
+        TenantJob::BillingDunning,
+        BILLING_DUNNING_INTERVAL,
+    );
+    spawn_job(state.clone(), TenantJob::PurgeTrails, TRAIL_PURGE_INTERVAL);
+    spawn_job(
+        state.clone(),
+        TenantJob::RenewPackages,
+        PACKAGE_RENEWAL_INTERVAL,
+    );
+    spawn_job(
+        state,
+        TenantJob::ScheduledPayouts,
+        SCHEDULED_PAYOUT_INTERVAL,
+    );
+}
+
+fn spawn_job(state: AppState, job: TenantJob, period: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            run_for_all_tenants(&state, job).await;
+        }
+    });
+}
+
+/// Run a job for every organization
+async fn run_for_all_tenants(state: &AppState, job: TenantJob) {
+    let mut offset = 0;
+    loop {
+        let organizations =
+            match OrganizationRepository::list(&state.pool, ORGANIZATION_PAGE_SIZE, offset).await {
+                Ok(organizations) => organizations,
+                Err(e) => {
+                    tracing::error!("Failed to list organizations: {}", e);
+                    return;
+                }
+            };
+
+        for organization in &organizations {
+            let pool = match state.tenant_pool_manager.get_pool(organization.id).await {
+                Ok(pool) => pool,
+                Err(e) => {
+                    tracing::warn!("No database for organization {}: {}", organization.id, e);
+                    continue;
+                }
+            };
+            let tenant = TenantContext {
+                org_id: organization.id,
+                pool,
+            };
+
+            let result = match job {
+                TenantJob::BillingDunning => billing_dunning(state, &tenant).await,
+                TenantJob::RefreshAuthorizations => refresh_authorizations(state, &tenant).await,
+                TenantJob::Reconcile => reconcile_previous_day(state, &tenant).await,
+                TenantJob::ProcessWebhooks => process_webhooks(state, &tenant).await,
+                TenantJob::PurgeTrails => purge_trails(state, &tenant).await,
+                TenantJob::RenewPackages => renew_packages(state, &tenant).await,
+                TenantJob::ReportTax => report_tax(state, &tenant).await,
+                TenantJob::ScheduledPayouts => scheduled_payouts(state, &tenant).await,
+                TenantJob::SettleHolds => settle_holds(state, &tenant).await,
+            };
+            if let Err(e) = result {
+                tracing::warn!(
+                    "{:?} job failed for organization {}: {}",
+                    job,
+                    organization.id,
+                    e.0
+                );
+            }
+        }
+
+        if (organizations.len() as i64) < ORGANIZATION_PAGE_SIZE {
+            return;
+        }
+        offset += ORGANIZATION_PAGE_SIZE;
+    }
+}
+
+/// Refresh expiring payment holds
+async fn refresh_authorizations(state: &AppState, tenant: &TenantContext) -> ApiResult<()> {
+    let count = payments::refresh_expiring_authorizations(state, tenant).await?;
+    if count > 0 {
+        tracing::info!(
+            "Refreshed {} payment authorizations for organization {}",
+            count,
+            tenant.org_id
+        );
+    }
+    Ok(())
+}
+
+/// Capture or release holds that failed to settle when their booking finished
+async fn settle_holds(state: &AppState, tenant: &TenantContext) -> ApiResult<()> {
+    let count = payments::settle_finished_bookings(state, tenant).await?;
+    if count > 0 {
+        tracing::info!(
+            "Settled {} held payments for organization {}",
+            count,
+            tenant.org_id
+        );
+    }
+    Ok(())
+}
+
+/// Restrict the organization's features once its platform subscription has
+/// been past due too long
+async fn billing_dunning(state: &AppState, tenant: &TenantContext) -> ApiResult<()> {
+    tenant_billing::restrict_if_overdue(state, tenant, Utc::now()).await?;
+    Ok(())
+}
+
+/// Process webhook events due a first attempt or a retry
+async fn process_webhooks(state: &AppState, tenant: &TenantContext) -> ApiResult<()> {
+    let count = webhook_events::process_due(state, tenant).await?;
+    if count > 0 {
+        tracing::info!(
+            "Processed {} webhook events for organization {}",
+            count,
+            tenant.org_id
+        );
+    }
+    Ok(())
+}
+
+/// Delete walk trail breadcrumbs older than the organization keeps them
+async fn purge_trails(state: &AppState, tenant: &TenantContext) -> ApiResult<()> {
+    walk_trails::purge_expired_trails(state, tenant).await?;
+    Ok(())
+}
+
+/// Charge customer subscriptions for their next period
+async fn renew_packages(state: &AppState, tenant: &TenantContext) -> ApiResult<()> {
+    let count = customer_packages::renew_due(state, tenant, Utc::now()).await?;
+    if count > 0 {
+        tracing::info!(
+            "Renewed {} customer subscriptions for organization {}",
+            count,
+            tenant.org_id
+        );
+    }
+    Ok(())
+}
+
+/// Report sales and refunds the tax provider hasn't recorded
+async fn report_tax(state: &AppState, tenant: &TenantContext) -> ApiResult<()> {
+    let count = tax::report_unreported(state, tenant).await?;
+    if count > 0 {
+        tracing::info!(
+            "Reported {} sales and refunds for tax for organization {}",
+            count,
+            tenant.org_id
+        );
+    }
+    Ok(())
+}
+
+/// Make today's (UTC) scheduled payouts
+async fn scheduled_payouts(state: &AppState, tenant: &TenantContext) -> ApiResult<()> {
+    let payouts = payouts::run_scheduled_payouts(state, tenant, Utc::now().date_naive()).await?;
+    for payout in &payouts {
+        tracing::info!(
+            "Created payout {} of {} cents ({}) covering {} transactions for organization {}",
+            payout.id,
+            payout.amount_cents,
+            payout.currency,
+            payout.transaction_count,
+            tenant.org_id
+        );
+    }
+    Ok(())
+}
+
+/// Reconcile yesterday (UTC) for each active provider not yet reconciled
+async fn reconcile_previous_day(state: &AppState, tenant: &TenantContext) -> ApiResult<()> {
+    let today = Utc::now().date_naive();
+    let yesterday = today - Days::new(1);
+    let period_start = yesterday.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+    let period_end = today.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+
+    let providers = PaymentProviderRepository::list_for_org(&tenant.pool, tenant.org_id).await?;
+    for provider in providers.iter().filter(|p| p.is_active) {
+        if ReconciliationRepository::has_completed_run(
+            &tenant.pool,
+            tenant.org_id,
+            provider.id,
+            period_start,
+            period_end,
+        )
+        .await?
+        {
+            continue;
+        }
+
+        let run = reconciliation::reconcile_provider(
+            state,
+            tenant,
+            provider,
+            period_start,
+            period_end,
+            None,
+        )
+        .await?;
+        if run.mismatch_count > 0 {
+            tracing::warn!(
+                "Reconciliation run {} found {} mismatches for organization {}",
+                run.id,
+                run.mismatch_count,
+                tenant.org_id
+            );
+        }
+    }
+    Ok(())
+}
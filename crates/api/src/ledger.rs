@@ -0,0 +1,116 @@
+//! Posting payment events to the double-entry ledger.
+//!
+//! Entries are keyed by the record they describe, so each function can be
+//! called whenever that record may have changed; only the first call posts.
+//! Failures are returned to the caller so the flow that triggered them fails
+//! and is retried, and the retry posts whatever is still missing.
+
+use db::models::{
+    Dispute, DisputeStatus, NewJournalEntry, Payout, Refund, RefundStatus, Transaction,
+    WalkerPayout,
+};
+use db::{LedgerRepository, TransactionRepository};
+use shared::types::OrganizationId;
+use sqlx::PgPool;
+
+/// Post a charge once its transaction has been captured
+pub async fn post_charge(
+    pool: &PgPool,
+    org_id: OrganizationId,
+    transaction: &Transaction,
+) -> Result<(), sqlx::Error> {
+    if transaction.captured_at.is_none() && !transaction.is_successful() {
+        return Ok(());
+    }
+    post(pool, org_id, NewJournalEntry::charge(transaction)).await
+}
+
+/// Post a refund once the provider has paid it
+pub async fn post_refund(
+    pool: &PgPool,
+    org_id: OrganizationId,
+    transaction: &Transaction,
+    refund: &Refund,
+) -> Result<(), sqlx::Error> {
+    if refund.status != RefundStatus::Succeeded {
+        return Ok(());
+    }
+    post(pool, org_id, NewJournalEntry::refund(transaction, refund)).await
+}
+
+/// Post a dispute's withdrawal and, once decided, its outcome
+pub async fn post_dispute(
+    pool: &PgPool,
+    org_id: OrganizationId,
+    dispute: &Dispute,
+) -> Result<(), sqlx::Error> {
+    post(pool, org_id, NewJournalEntry::dispute_opened(dispute)).await?;
+
+    match dispute.status {
+        DisputeStatus::Won => post(pool, org_id, NewJournalEntry::dispute_won(dispute)).await,
+        DisputeStatus::Lost => {
+            match TransactionRepository::get_by_id(pool, org_id, dispute.transaction_id).await? {
+                Some(transaction) => {
+                    post(
+                        pool,
+                        org_id,
+                        NewJournalEntry::dispute_lost(&transaction, dispute),
+                    )
+                    .await
+                }
+                None => Ok(()),
+            }
+        }
+        DisputeStatus::NeedsResponse | DisputeStatus::UnderReview => Ok(()),
+    }
+}
+
+/// Post a payout sent to the provider
+pub async fn post_payout(
+    pool: &PgPool,
+    org_id: OrganizationId,
+    payout: &Payout,
+) -> Result<(), sqlx::Error> {
+    post(pool, org_id, NewJournalEntry::payout(payout)).await
+}
+
+/// Return a failed payout's funds to the processor balance
+pub async fn post_payout_failed(
+    pool: &PgPool,
+    org_id: OrganizationId,
+    payout: &Payout,
+) -> Result<(), sqlx::Error> {
+    post(pool, org_id, NewJournalEntry::payout_failed(payout)).await
+}
+
+/// Post a transfer of a walker payout to the walker's connected account
+pub async fn post_walker_transfer(
+    pool: &PgPool,
+    org_id: OrganizationId,
+    payout: &WalkerPayout,
+) -> Result<(), sqlx::Error> {
+    post(pool, org_id, NewJournalEntry::walker_transfer(payout)).await
+}
+
+/// Return a reversed walker transfer's funds to the processor balance
+pub async fn post_walker_transfer_reversed(
+    pool: &PgPool,
+    org_id: OrganizationId,
+    payout: &WalkerPayout,
+) -> Result<(), sqlx::Error> {
+    post(
+        pool,
+        org_id,
+        NewJournalEntry::walker_transfer_reversed(payout),
+    )
+    .await
+}
+
+async fn post(
+    pool: &PgPool,
+    org_id: OrganizationId,
+    entry: NewJournalEntry,
+) -> Result<(), sqlx::Error> {
+    LedgerRepository::post(pool, org_id, &entry).await?;
+    Ok(())
+}
@@ -1,15 +1,28 @@
 pub mod auth;
+pub mod customer_packages;
+pub mod disputes;
 pub mod error;
+pub mod jobs;
+pub mod ledger;
 pub mod metrics;
+pub mod payments;
+pub mod payouts;
+pub mod reconciliation;
 pub mod routes;
 pub mod state;
+pub mod tax;
 pub mod tenant;
+pub mod tenant_billing;
+pub mod walker_earnings;
+pub mod webhook_events;
 
 pub use error::ApiError;
 pub use metrics::init_metrics;
 pub use state::AppState;
 
 use axum::{
+    body::Body,
+    http::Request,
     routing::{delete, get, post, put},
     Router,
 };
@@ -17,6 +30,7 @@ use tower_http::{
     cors::{Any, CorsLayer},
     trace::TraceLayer,
 };
+use tracing::Span;
 
 /// Create the application router
 pub fn create_app(state: AppState) -> Router {
@@ -39,6 +53,10 @@ pub fn create_app(state: AppState) -> Router {
         .route("/auth/validate", get(routes::auth::validate_token))
         .route("/auth/refresh", post(routes::auth::refresh_token))
         .route("/auth/session", get(routes::auth::session_info))
+        .route(
+            "/auth/stream-ticket",
+            post(routes::live_tracking::create_stream_ticket),
+        )
         // OAuth routes
         .route("/auth/google", post(routes::oauth::google_auth))
         .route("/auth/apple", post(routes::oauth::apple_auth))
@@ -78,6 +96,83 @@ pub fn create_app(state: AppState) -> Router {
             "/admin/branding",
             get(routes::admin_branding::get_branding).put(routes::admin_branding::update_branding),
         )
+        .route(
+            "/admin/walkers/live",
+            get(routes::live_tracking::stream_on_duty_walkers),
+        )
+        .route(
+            "/admin/currency",
+            get(routes::currency::get_currency_settings)
+                .put(routes::currency::update_currency_settings),
+        )
+        .route(
+            "/admin/walk-trails/settings",
+            get(routes::walk_trails::get_trail_settings)
+                .put(routes::walk_trails::update_trail_settings),
+        )
+        .route(
+            "/admin/cancellation-policies",
+            get(routes::cancellation_policies::list_cancellation_policies)
+                .put(routes::cancellation_policies::upsert_cancellation_policy),
+        )
+        .route(
+            "/admin/cancellation-policies/:id",
+            delete(routes::cancellation_policies::delete_cancellation_policy),
+        )
+        .route(
+            "/admin/processing-fees",
+            get(routes::processing_fees::list_processing_fees)
+                .put(routes::processing_fees::upsert_processing_fee),
+        )
+        .route(
+            "/admin/processing-fees/:id",
+            delete(routes::processing_fees::delete_processing_fee),
+        )
+        .route("/admin/ledger/balances", get(routes::ledger::get_balances))
+        .route("/admin/ledger/check", get(routes::ledger::check_ledger))
+        .route(
+            "/admin/reconciliation/runs",
+            get(routes::reconciliation::list_runs)
+                .post(routes::reconciliation::start_reconciliation),
+        )
+        .route(
+            "/admin/reconciliation/runs/:id",
+            get(routes::reconciliation::get_run),
+        )
+        .route(
+            "/admin/reconciliation/mismatches",
+            get(routes::reconciliation::list_open_mismatches),
+        )
+        .route(
+            "/admin/reconciliation/mismatches/:id/resolve",
+            post(routes::reconciliation::resolve_mismatch),
+        )
+        .route("/admin/disputes", get(routes::disputes::list_disputes))
+        .route("/admin/disputes/:id", get(routes::disputes::get_dispute))
+        .route(
+            "/admin/disputes/:id/evidence",
+            put(routes::disputes::save_evidence),
+        )
+        .route(
+            "/admin/disputes/:id/evidence/submit",
+            post(routes::disputes::submit_evidence),
+        )
+        .route(
+            "/admin/disputes/:id/reminder/dismiss",
+            post(routes::disputes::dismiss_reminder),
+        )
+        .route(
+            "/admin/webhook-events",
+            get(routes::webhook_events::list_webhook_events),
+        )
+        .route(
+            "/admin/webhook-events/:id",
+            get(routes::webhook_events::get_webhook_event),
+        )
+        .route(
+            "/admin/webhook-events/:id/replay",
+            post(routes::webhook_events::replay_webhook_event),
+        )
         // Dashboard metrics route (tenant admin)
         .route(
             "/admin/dashboard/metrics",
@@ -123,10 +218,20 @@ pub fn create_app(state: AppState) -> Router {
             "/bookings/:id/reschedule",
             post(routes::bookings::reschedule_booking),
         )
+        .route("/bookings/:id/start", post(routes::bookings::start_booking))
         .route(
             "/bookings/:id/complete",
             post(routes::bookings::complete_booking),
         )
+        .route("/bookings/:id/tip", post(routes::tips::tip_booking))
+        .route(
+            "/bookings/:id/trail",
+            get(routes::walk_trails::get_booking_trail),
+        )
+        .route(
+            "/bookings/:id/live",
+            get(routes::live_tracking::stream_booking_location),
+        )
         // Recurring booking routes
         .route(
             "/bookings/recurring",
@@ -250,6 +355,10 @@ pub fn create_app(state: AppState) -> Router {
             "/walkers/:id/on-duty",
             post(routes::travel_time::set_walker_duty_status),
         )
+        .route(
+            "/walkers/:id/balance",
+            get(routes::ledger::get_walker_balance),
+        )
         .route("/travel-time", get(routes::travel_time::get_travel_time))
         .route(
             "/availability/slots",
@@ -323,16 +432,28 @@ pub fn create_app(state: AppState) -> Router {
             "/admin/service-areas",
             get(routes::service_areas::list_all_service_areas),
         )
+        .route(
+            "/admin/service-areas/export",
+            get(routes::service_areas::export_all_service_areas),
+        )
         .route(
             "/admin/walkers/:walker_id/service-areas",
             get(routes::service_areas::get_walker_service_areas)
                 .post(routes::service_areas::create_walker_service_area),
         )
+        .route(
+            "/admin/walkers/:walker_id/service-areas/import",
+            post(routes::service_areas::import_walker_service_areas),
+        )
         .route(
             "/admin/service-areas/:area_id",
             put(routes::service_areas::update_service_area)
                 .delete(routes::service_areas::delete_service_area),
         )
+        .route(
+            "/admin/service-areas/:area_id/export",
+            get(routes::service_areas::export_service_area),
+        )
         // Feedback routes (for bug reports and feature requests)
         .route("/feedback", post(routes::feedback::submit_feedback))
         // Payment provider routes (Stripe/Square OAuth connections)
@@ -380,7 +501,21 @@ pub fn create_app(state: AppState) -> Router {
             "/checkout/:id/refund",
             post(routes::checkout::request_refund),
         )
+        .route("/checkout/:id/refunds", get(routes::checkout::list_refunds))
+        .route(
+            "/admin/transactions/:id/refunds",
+            post(routes::checkout::admin_refund),
+        )
+        .route(
+            "/admin/transactions/:id/ledger",
+            get(routes::ledger::list_transaction_entries),
+        )
+        .route(
+            "/admin/transactions/summary",
+            get(routes::checkout::get_transaction_summary),
+        )
         .route("/transactions", get(routes::checkout::list_transactions))
+        .route("/tips/summary", get(routes::tips::get_tip_summary))
         // Subscription routes
         .route(
             "/subscriptions/tenant",
@@ -395,6 +530,15 @@ pub fn create_app(state: AppState) -> Router {
             "/subscriptions/tiers",
             get(routes::subscriptions::list_fee_tiers),
         )
+        .route(
+            "/subscriptions/packages",
+            get(routes::subscriptions::list_package_plans)
+                .post(routes::subscriptions::create_package_plan),
+        )
+        .route(
+            "/subscriptions/packages/:id",
+            delete(routes::subscriptions::archive_package_plan),
+        )
         .route(
             "/subscriptions/customer",
             get(routes::subscriptions::list_customer_subscriptions)
@@ -417,17 +561,66 @@ pub fn create_app(state: AppState) -> Router {
             post(routes::payouts::request_instant_payout),
         )
         .route("/payouts/:id", get(routes::payouts::get_payout))
+        // Walker earnings routes
+        .route(
+            "/walkers/:id/revenue-splits",
+            get(routes::walker_earnings::list_revenue_splits)
+                .put(routes::walker_earnings::set_revenue_split),
+        )
+        .route(
+            "/walkers/:id/revenue-splits/:split_id",
+            delete(routes::walker_earnings::delete_revenue_split),
+        )
+        .route(
+            "/walkers/:id/earnings",
+            get(routes::walker_earnings::get_earnings_statement),
+        )
+        .route(
+            "/walkers/:id/payouts",
+            post(routes::walker_earnings::pay_walker),
+        )
+        .route(
+            "/walker-payouts",
+            get(routes::walker_earnings::list_walker_payouts),
+        )
+        .route(
+            "/walker-payouts/:id/mark-paid",
+            post(routes::walker_earnings::mark_walker_payout_paid),
+        )
+        .route(
+            "/walker/payout-account",
+            get(routes::walker_earnings::get_payout_account)
+                .post(routes::walker_earnings::connect_payout_account),
+        )
         // Webhook routes (no auth - verified by signature)
         .route(
             "/webhooks/stripe/:org_id",
             post(routes::webhooks::stripe_webhook),
         )
+        .route(
+            "/webhooks/stripe-connect",
+            post(routes::webhooks::stripe_connect_webhook),
+        )
+        .route(
+            "/webhooks/stripe-billing",
+            post(routes::webhooks::stripe_billing_webhook),
+        )
         .route(
             "/webhooks/square/:org_id",
             post(routes::webhooks::square_webhook),
         )
         // Add middleware
-        .layer(TraceLayer::new_for_http())
+        .layer(TraceLayer::new_for_http().make_span_with(request_span))
         .layer(cors)
         .with_state(state)
 }
+
+/// Request span without the query string, which can carry stream tickets
+fn request_span(request: &Request<Body>) -> Span {
+    tracing::debug_span!(
+        "request",
+        method = %request.method(),
+        path = %request.uri().path(),
+        version = ?request.version(),
+    )
+}
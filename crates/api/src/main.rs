@@ -51,15 +51,28 @@ async fn main() {
     let jwt_secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
     let google_maps_key = std::env::var("GOOGLE_MAPS_API_KEY").ok();
 
-    // GitHub configuration for feedback
+    // GitHub configuration for feedback, payment and tax provider credentials
     let config = AppConfig {
         github_token: std::env::var("GITHUB_TOKEN").ok(),
         github_feedback_repo: std::env::var("GITHUB_FEEDBACK_REPO").ok(),
+        stripe_secret_key: std::env::var("STRIPE_SECRET_KEY").ok(),
+        stripe_connect_webhook_secret: std::env::var("STRIPE_CONNECT_WEBHOOK_SECRET").ok(),
+        stripe_billing_webhook_secret: std::env::var("STRIPE_BILLING_WEBHOOK_SECRET").ok(),
+        square_sandbox: std::env::var("SQUARE_SANDBOX")
+            .map(|v| v == "true")
+            .unwrap_or(false),
+        taxjar_api_key: std::env::var("TAXJAR_API_KEY").ok(),
+        taxjar_sandbox: std::env::var("TAXJAR_SANDBOX")
+            .map(|v| v == "true")
+            .unwrap_or(false),
     };
 
     // Create app state
     let state = AppState::with_config(pool, jwt_secret, google_maps_key, metrics_handle, config);
 
+    // Re-authorize, capture or release payment holds before they expire
+    api::jobs::spawn_payment_jobs(state.clone());
+
     // Create the app
     let app = create_app(state);
 
@@ -0,0 +1,1212 @@
+//! Payment gateway selection and the payment steps shared by checkout,
+//! refunds, payouts and the booking lifecycle.
+//!
+//! Booking payments are authorized when the booking is confirmed (or at
+//! checkout) and captured when the walk is completed. Holds are released when
+//! a booking is cancelled (less any late cancellation fee), and re-authorized
+//! before the provider expires them.
+
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use db::models::{
+    Booking, BookingStatus, CardRegion, CreateRefund, CustomerPaymentMethod, PaymentMethodType,
+    PaymentProvider, PaymentProviderType, ProcessingFee, ProcessingFeeSchedule, Refund,
+    RefundAllocation, RefundSource, RefundStatus, Transaction, TransactionFeeBreakdown,
+    TransactionStatus, UpdateTransaction,
+};
+use db::{
+    CustomerPaymentMethodRepository, OrganizationRepository, PaymentProviderRepository,
+    ProcessingFeeScheduleRepository, RefundRepository, SubscriptionRepository,
+    TransactionRepository,
+};
+use integrations::gateway::{
+    AuthorizeRequest, CaptureMethod, GatewayError, GatewayPayment, GatewayRefund, PaymentGateway,
+    PaymentState, RefundReason, RefundRequest, SquareGateway, StripeGateway,
+};
+use integrations::{SquareClient, StripeClient};
+use rust_decimal::Decimal;
+use shared::types::{Currency, Money, UserId};
+use shared::{AppError, DomainError};
+
+use crate::{
+    auth::TenantContext,
+    error::{ApiError, ApiResult},
+    ledger,
+    state::AppState,
+    tax, walker_earnings,
+};
+
+/// Holds expiring within this window are re-authorized
+pub const REAUTHORIZE_WINDOW_HOURS: i64 = 24;
+
+/// Resolves the [`PaymentGateway`] for a tenant's payment provider
+#[derive(Clone, Default)]
+pub struct PaymentGateways {
+    stripe_secret_key: Option<String>,
+    square_sandbox: bool,
+    /// Used for every provider when set (tests)
+    override_gateway: Option<Arc<dyn PaymentGateway>>,
+}
+
+impl PaymentGateways {
+    pub fn new(stripe_secret_key: Option<String>, square_sandbox: bool) -> Self {
+        Self {
+            stripe_secret_key,
+            square_sandbox,
+            override_gateway: None,
+        }
+    }
+
+    /// Route every payment through `gateway`, e.g. a
+    /// [`FakeGateway`](integrations::gateway::FakeGateway) in tests
+    pub fn with_gateway(gateway: Arc<dyn PaymentGateway>) -> Self {
+        Self {
+            override_gateway: Some(gateway),
+            ..Default::default()
+        }
+    }
+
+    /// Gateway for charging through `provider`
+    pub fn for_provider(&self, provider: &PaymentProvider) -> ApiResult<Arc<dyn PaymentGateway>> {
+        if let Some(gateway) = &self.override_gateway {
+            return Ok(gateway.clone());
+        }
+
+        match provider.provider_type {
+            PaymentProviderType::Stripe | PaymentProviderType::Platform => {
+                let secret_key = self.stripe_secret_key.clone().ok_or_else(|| {
+                    ApiError::from(AppError::Internal("Stripe not configured".to_string()))
+                })?;
+
+                // Platform-default providers charge on the platform account
+                let connected_account = match provider.provider_type {
+                    PaymentProviderType::Platform => None,
+                    _ => provider
+                        .stripe_account_id
+                        .clone()
+                        .or_else(|| provider.merchant_id.clone()),
+                };
+
+                Ok(Arc::new(StripeGateway::new(
+                    StripeClient::new(secret_key, None),
+                    connected_account,
+                )))
+            }
+            PaymentProviderType::Square => {
+                let access_token = provider.access_token_encrypted.clone().ok_or_else(|| {
+                    ApiError::from(AppError::Internal("Square not configured".to_string()))
+                })?;
+                let location_id = provider
+                    .metadata
+                    .as_ref()
+                    .and_then(|m| m.get("location_id"))
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string);
+
+                Ok(Arc::new(SquareGateway::new(
+                    SquareClient::new(access_token, self.square_sandbox),
+                    location_id,
+                )))
+            }
+        }
+    }
+}
+
+/// Provider payment ID for a transaction.
+///
+/// Older Stripe transactions stored the client secret; the PaymentIntent ID
+/// is its prefix.
+pub fn external_payment_id(transaction: &Transaction) -> Option<&str> {
+    transaction
+        .external_payment_id
+        .as_deref()
+        .map(|id| id.split("_secret_").next().unwrap_or(id))
+}
+
+/// Transaction status matching a provider payment state
+pub fn transaction_status(state: PaymentState) -> TransactionStatus {
+    match state {
+        PaymentState::RequiresAction => TransactionStatus::Pending,
+        PaymentState::Processing => TransactionStatus::Processing,
+        PaymentState::Authorized => TransactionStatus::Authorized,
+        PaymentState::Succeeded => TransactionStatus::Succeeded,
+        PaymentState::Canceled => TransactionStatus::Canceled,
+        PaymentState::Failed => TransactionStatus::Failed,
+    }
+}
+
+/// Start the provider payment for a transaction.
+///
+/// With a saved payment method the payment is confirmed immediately;
+/// otherwise the returned payment carries a client secret for the browser.
+/// The request is built from the transaction alone, so charging it again
+/// sends the provider the same request under the same idempotency key and
+/// returns the original payment. `payment_method` must be the transaction's
+/// own.
+pub async fn charge_transaction(
+    gateway: &dyn PaymentGateway,
+    transaction: &Transaction,
+    payment_method: Option<&CustomerPaymentMethod>,
+) -> ApiResult<GatewayPayment> {
+    if payment_method.map(|pm| pm.id) != transaction.payment_method_id {
+        return Err(ApiError::from(AppError::Internal(format!(
+            "Payment method doesn't match transaction {}",
+            transaction.id
+        ))));
+    }
+
+    let capture_method = if transaction.manual_capture {
+        CaptureMethod::Manual
+    } else {
+        CaptureMethod::Automatic
+    };
+    let idempotency_key = transaction
+        .idempotency_key
+        .clone()
+        .unwrap_or_else(|| format!("txn_{}_charge", transaction.id));
+    let request = authorize_request(
+        transaction,
+        payment_method,
+        capture_method,
+        transaction.off_session,
+        idempotency_key,
+    );
+
+    Ok(gateway.authorize(&request).await?)
+}
+
+/// Place a fresh hold for an authorized transaction and release the old one.
+///
+/// Needs the saved payment method, since the customer isn't present.
+pub async fn reauthorize_transaction(
+    gateway: &dyn PaymentGateway,
+    transaction: &Transaction,
+    payment_method: &CustomerPaymentMethod,
+) -> ApiResult<GatewayPayment> {
+    let previous = external_payment_id(transaction);
+    // One key per hold being replaced, so a retried run doesn't double up
+    let generation = transaction
+        .authorized_at
+        .map(|at| at.timestamp())
+        .unwrap_or_default();
+
+    let request = authorize_request(
+        transaction,
+        Some(payment_method),
+        CaptureMethod::Manual,
+        true,
+        format!("txn_{}_reauthorize_{}", transaction.id, generation),
+    );
+    let payment = gateway.authorize(&request).await?;
+    if payment.state != PaymentState::Authorized {
+        return Err(ApiError::from(AppError::ExternalApi(format!(
+            "Re-authorization of transaction {} is {:?}",
+            transaction.id, payment.state
+        ))));
+    }
+
+    if let Some(previous) = previous.filter(|id| *id != payment.id) {
+        if let Err(e) = gateway.cancel(previous).await {
+            // The provider releases it on expiry anyway
+            tracing::warn!("Failed to release replaced hold {}: {}", previous, e);
+        }
+    }
+
+    Ok(payment)
+}
+
+fn authorize_request(
+    transaction: &Transaction,
+    payment_method: Option<&CustomerPaymentMethod>,
+    capture_method: CaptureMethod,
+    off_session: bool,
+    idempotency_key: String,
+) -> AuthorizeRequest {
+    let (method, customer) = match payment_method {
+        Some(pm) => (
+            pm.stripe_payment_method_id
+                .clone()
+                .or_else(|| pm.square_card_id.clone()),
+            pm.stripe_customer_id
+                .clone()
+                .or_else(|| pm.square_customer_id.clone()),
+        ),
+        None => (None, None),
+    };
+
+    AuthorizeRequest {
+        amount_cents: transaction.total_cents.into(),
+        currency: transaction.currency,
+        capture_method,
+        payment_method: method,
+        customer,
+        off_session,
+        application_fee_cents: Some(transaction.platform_fee_cents.into()),
+        idempotency_key,
+        reference: Some(transaction.id.to_string()),
+        description: transaction.description.clone(),
+        metadata: [
+            ("transaction_id".to_string(), transaction.id.to_string()),
+            (
+                "booking_id".to_string(),
+                transaction
+                    .booking_id
+                    .map(|id| id.to_string())
+                    .unwrap_or_default(),
+            ),
+        ]
+        .into_iter()
+        .collect(),
+    }
+}
+
+/// Send a recorded refund to the transaction's provider
+pub async fn refund_transaction(
+    gateway: &dyn PaymentGateway,
+    transaction: &Transaction,
+    refund: &Refund,
+) -> ApiResult<GatewayRefund> {
+    let payment_id = external_payment_id(transaction)
+        .ok_or_else(|| ApiError::from(AppError::Validation("No payment to refund".to_string())))?;
+
+    let request = RefundRequest {
+        payment_id: payment_id.to_string(),
+        amount_cents: refund.amount_cents.into(),
+        currency: transaction.currency,
+        application_fee_cents: refund.platform_fee_reversed_cents.into(),
+        reason: refund
+            .reason
+            .as_deref()
+            .map(RefundReason::from_text)
+            .unwrap_or(RefundReason::RequestedByCustomer),
+        // Stable if the same refund is retried
+        idempotency_key: format!("refund_{}", refund.id),
+    };
+
+    Ok(gateway.refund(&request).await?)
+}
+
+/// Check a refund of `amount_cents` fits in what's left of the transaction
+/// once refunds still pending at the provider are taken into account
+pub fn validate_refund_amount(
+    transaction: &Transaction,
+    pending_cents: i64,
+    amount_cents: i32,
+) -> Result<(), AppError> {
+    if !transaction.can_refund() {
+        return Err(AppError::Validation(format!(
+            "A {} transaction cannot be refunded",
+            transaction.status
+        )));
+    }
+    if amount_cents <= 0 {
+        return Err(AppError::Validation(
+            "Refund amount must be positive".to_string(),
+        ));
+    }
+
+    let remaining = i64::from(transaction.refundable_amount_cents()) - pending_cents;
+    if i64::from(amount_cents) > remaining {
+        return Err(AppError::Validation(format!(
+            "Refund of {} cents exceeds the {} cents remaining",
+            amount_cents,
+            remaining.max(0)
+        )));
+    }
+
+    Ok(())
+}
+
+/// Who is issuing a refund through the platform
+#[derive(Debug, Clone, Copy)]
+pub struct RefundInitiator {
+    pub user_id: UserId,
+    pub source: RefundSource,
+}
+
+/// Refund part or all of a transaction.
+///
+/// The refund is recorded as pending before it is sent to the provider, so
+/// every attempt is kept and retries reuse its idempotency key. It's then
+/// matched to the provider's refund, which the provider's webhook may have
+/// recorded first. The amount counts against the transaction once the
+/// provider accepts it; refunds the provider still has pending are settled
+/// by its webhooks.
+pub async fn issue_refund(
+    state: &AppState,
+    tenant: &TenantContext,
+    transaction: &Transaction,
+    amount_cents: i32,
+    reason: Option<String>,
+    initiator: RefundInitiator,
+) -> ApiResult<(Refund, Transaction)> {
+    let pending = RefundRepository::pending_total(&tenant.pool, transaction.id).await?;
+    validate_refund_amount(transaction, pending, amount_cents)?;
+
+    let refund = RefundRepository::create(
+        &tenant.pool,
+        tenant.org_id,
+        CreateRefund {
+            transaction_id: transaction.id,
+            amount_cents,
+            currency: transaction.currency,
+            reason,
+            status: RefundStatus::Pending,
+            source: initiator.source,
+            initiated_by: Some(initiator.user_id),
+            provider_refund_id: None,
+            allocation: RefundAllocation::for_refund(transaction, amount_cents),
+        },
+    )
+    .await?;
+
+    let (_, gateway) = transaction_gateway(state, tenant, transaction).await?;
+    let provider_refund = match refund_transaction(gateway.as_ref(), transaction, &refund).await {
+        Ok(provider_refund) => provider_refund,
+        Err(e) => {
+            RefundRepository::update_status(
+                &tenant.pool,
+                refund.id,
+                RefundStatus::Failed,
+                None,
+                Some(&e.0.to_string()),
+            )
+            .await?;
+            return Err(e);
+        }
+    };
+
+    let status = RefundStatus::from_provider(&provider_refund.status);
+    let (refund, changed) = RefundRepository::attach_provider_refund(
+        &tenant.pool,
+        tenant.org_id,
+        refund.id,
+        &provider_refund.id,
+        status,
+    )
+    .await?;
+
+    let refunded = if changed && status == RefundStatus::Succeeded {
+        TransactionRepository::record_refund(&tenant.pool, transaction.id, refund.amount_cents)
+            .await?
+            .unwrap_or_else(|| transaction.clone())
+    } else {
+        TransactionRepository::get_by_id(&tenant.pool, tenant.org_id, transaction.id)
+            .await?
+            .unwrap_or_else(|| transaction.clone())
+    };
+
+    // If posting fails, the provider's refund webhook posts it again
+    ledger::post_refund(&tenant.pool, tenant.org_id, transaction, &refund).await?;
+    walker_earnings::record_refund(&tenant.pool, tenant.org_id, transaction, &refund).await?;
+    tax::report_refund(state, tenant, transaction, &refund).await;
+    let transaction = refunded;
+
+    Ok((refund, transaction))
+}
+
+/// Apply a refund reported by the provider's webhooks.
+///
+/// Refunds are recorded by their provider refund ID the first time they're
+/// reported and counted against the transaction once they succeed, however
+/// many reports arrive at once. A report that arrives before a refund issued
+/// through the platform has been matched to the provider's refund is
+/// recorded here and merged with it when it is. Repeated reports post the
+/// refund again, so a report whose posting failed finishes it when the
+/// provider retries.
+pub async fn record_provider_refund(
+    state: &AppState,
+    tenant: &TenantContext,
+    transaction: &Transaction,
+    provider_refund_id: &str,
+    amount_cents: i32,
+    status: RefundStatus,
+) -> ApiResult<Refund> {
+    let amount_cents =
+        amount_cents.min(transaction.total_cents - transaction.refunded_amount_cents);
+    let created = RefundRepository::create_for_provider(
+        &tenant.pool,
+        tenant.org_id,
+        CreateRefund {
+            transaction_id: transaction.id,
+            amount_cents,
+            currency: transaction.currency,
+            reason: None,
+            status,
+            source: RefundSource::Provider,
+            initiated_by: None,
+            provider_refund_id: Some(provider_refund_id.to_string()),
+            allocation: RefundAllocation::for_refund(transaction, amount_cents),
+        },
+    )
+    .await?;
+
+    let (refund, changed) = match created {
+        Some(created) => (created, true),
+        None => {
+            let existing = RefundRepository::get_by_provider_refund_id(
+                &tenant.pool,
+                tenant.org_id,
+                provider_refund_id,
+            )
+            .await?
+            .ok_or_else(|| ApiError::from(AppError::NotFound("Refund not found".to_string())))?;
+            match RefundRepository::transition_status(&tenant.pool, existing.id, status).await? {
+                Some(updated) => (updated, true),
+                None => (existing, false),
+            }
+        }
+    };
+    if changed {
+        if status == RefundStatus::Succeeded {
+            TransactionRepository::record_refund(&tenant.pool, transaction.id, refund.amount_cents)
+                .await?;
+        }
+        tax::report_refund(state, tenant, transaction, &refund).await;
+    }
+
+    ledger::post_refund(&tenant.pool, tenant.org_id, transaction, &refund).await?;
+    walker_earnings::record_refund(&tenant.pool, tenant.org_id, transaction, &refund).await?;
+    Ok(refund)
+}
+
+/// Save a provider payment's ID and state on its transaction.
+///
+/// Authorized payments also record when the provider will release the hold.
+pub async fn record_payment(
+    state: &AppState,
+    tenant: &TenantContext,
+    transaction: &Transaction,
+    provider_type: PaymentProviderType,
+    gateway: &dyn PaymentGateway,
+    payment: &GatewayPayment,
+) -> ApiResult<Transaction> {
+    let mut update = UpdateTransaction {
+        status: Some(transaction_status(payment.state)),
+        ..Default::default()
+    };
+    match provider_type {
+        PaymentProviderType::Stripe | PaymentProviderType::Platform => {
+            update.stripe_payment_intent_id = Some(payment.id.clone());
+        }
+        PaymentProviderType::Square => update.square_payment_id = Some(payment.id.clone()),
+    }
+
+    TransactionRepository::update_external_id(&tenant.pool, transaction.id, &payment.id).await?;
+    let mut updated = TransactionRepository::update(&tenant.pool, transaction.id, update).await?;
+
+    if payment.state == PaymentState::Authorized && !transaction.is_authorized() {
+        let now = Utc::now();
+        updated = TransactionRepository::record_authorization(
+            &tenant.pool,
+            transaction.id,
+            &payment.id,
+            now,
+            now + gateway.authorization_validity(),
+        )
+        .await?;
+    }
+
+    let updated = updated.unwrap_or_else(|| transaction.clone());
+    let updated = record_reported_fee(tenant, gateway, updated).await?;
+    ledger::post_charge(&tenant.pool, tenant.org_id, &updated).await?;
+    walker_earnings::record_charge(&tenant.pool, tenant.org_id, &updated).await?;
+    tax::report_sale(state, tenant, &updated).await;
+    Ok(updated)
+}
+
+/// Capture the held payment for a completed booking.
+///
+/// A hold that has already expired is re-authorized first. Returns the
+/// updated transaction, or `None` if the booking has no held payment. A
+/// failed capture is recorded on the transaction and retried by
+/// [`settle_finished_bookings`].
+pub async fn capture_booking_payment(
+    state: &AppState,
+    tenant: &TenantContext,
+    booking: &Booking,
+) -> ApiResult<Option<Transaction>> {
+    let Some(transaction) = held_transaction(tenant, booking).await? else {
+        return Ok(None);
+    };
+    let (provider, gateway) = transaction_gateway(state, tenant, &transaction).await?;
+
+    let transaction = if transaction.authorization_expires_before(Utc::now()) {
+        reauthorize(state, tenant, &provider, gateway.as_ref(), &transaction).await?
+    } else {
+        transaction
+    };
+
+    let payment_id = external_payment_id(&transaction)
+        .ok_or_else(|| ApiError::from(AppError::Validation("No payment to capture".to_string())))?;
+    let result = gateway.capture(payment_id, None).await;
+    let payment = match result {
+        Ok(payment) => payment,
+        Err(e) => {
+            record_failure(tenant, &transaction, &e.to_string()).await?;
+            return Err(e.into());
+        }
+    };
+
+    let captured = if payment.state == PaymentState::Succeeded {
+        match TransactionRepository::record_capture(&tenant.pool, transaction.id).await? {
+            Some(captured) => Some(record_reported_fee(tenant, gateway.as_ref(), captured).await?),
+            None => None,
+        }
+    } else {
+        Some(
+            record_payment(
+                state,
+                tenant,
+                &transaction,
+                provider.provider_type,
+                gateway.as_ref(),
+                &payment,
+            )
+            .await?,
+        )
+    };
+    if let Some(captured) = &captured {
+        ledger::post_charge(&tenant.pool, tenant.org_id, captured).await?;
+        walker_earnings::record_charge(&tenant.pool, tenant.org_id, captured).await?;
+        tax::report_sale(state, tenant, captured).await;
+    }
+
+    Ok(captured)
+}
+
+/// Release the held payment for a cancelled booking.
+///
+/// Returns the updated transaction, or `None` if nothing was held. A failed
+/// release is recorded on the transaction and retried by
+/// [`settle_finished_bookings`].
+pub async fn release_booking_payment(
+    state: &AppState,
+    tenant: &TenantContext,
+    booking: &Booking,
+) -> ApiResult<Option<Transaction>> {
+    let Some(transaction) = held_transaction(tenant, booking).await? else {
+        return Ok(None);
+    };
+    let (_, gateway) = transaction_gateway(state, tenant, &transaction).await?;
+
+    let payment_id = external_payment_id(&transaction)
+        .ok_or_else(|| ApiError::from(AppError::Validation("No payment to release".to_string())))?;
+    if let Err(e) = gateway.cancel(payment_id).await {
+        record_failure(tenant, &transaction, &e.to_string()).await?;
+        return Err(e.into());
+    }
+
+    Ok(TransactionRepository::update_status(
+        &tenant.pool,
+        transaction.id,
+        TransactionStatus::Canceled,
+    )
+    .await?)
+}
+
+/// Take a late cancellation fee of `fee_cents` out of a cancelled booking's
+/// held payment, releasing the rest.
+///
+/// Returns `None` if nothing is held, or if the provider can't capture part of
+/// a hold; the hold is released in that case and the fee has to be charged
+/// separately.
+pub async fn capture_cancellation_fee(
+    state: &AppState,
+    tenant: &TenantContext,
+    booking: &Booking,
+    fee_cents: i32,
+) -> ApiResult<Option<Transaction>> {
+    let Some(transaction) = held_transaction(tenant, booking).await? else {
+        return Ok(None);
+    };
+    let (provider, gateway) = transaction_gateway(state, tenant, &transaction).await?;
+
+    let transaction = if transaction.authorization_expires_before(Utc::now()) {
+        reauthorize(state, tenant, &provider, gateway.as_ref(), &transaction).await?
+    } else {
+        transaction
+    };
+
+    let payment_method = match transaction.payment_method_id {
+        Some(id) => {
+            CustomerPaymentMethodRepository::find_by_id(&tenant.pool, tenant.org_id, id).await?
+        }
+        None => None,
+    };
+    let processing_fee = processing_fee(
+        tenant,
+        provider.provider_type,
+        payment_method.as_ref(),
+        transaction.currency,
+    )
+    .await?;
+    let tax_rate_percent = transaction.tax_rate_percent.unwrap_or_default();
+    let fee = Money::new(i64::from(fee_cents), transaction.currency);
+    let breakdown = fee_breakdown(tenant, fee, tax_rate_percent, processing_fee).await?;
+
+    let payment_id = external_payment_id(&transaction)
+        .ok_or_else(|| ApiError::from(AppError::Validation("No payment to capture".to_string())))?;
+    match gateway
+        .capture(payment_id, Some(breakdown.total.cents()))
+        .await
+    {
+        Ok(_) => {}
+        Err(GatewayError::NotSupported(_)) => {
+            release_booking_payment(state, tenant, booking).await?;
+            return Ok(None);
+        }
+        Err(e) => {
+            record_failure(tenant, &transaction, &e.to_string()).await?;
+            return Err(e.into());
+        }
+    }
+
+    let description = format!(
+        "Cancellation fee: {}",
+        transaction.description.as_deref().unwrap_or("booking")
+    );
+    let captured = match TransactionRepository::record_partial_capture(
+        &tenant.pool,
+        transaction.id,
+        &breakdown,
+        &description,
+    )
+    .await?
+    {
+        Some(captured) => Some(record_reported_fee(tenant, gateway.as_ref(), captured).await?),
+        None => None,
+    };
+    if let Some(captured) = &captured {
+        ledger::post_charge(&tenant.pool, tenant.org_id, captured).await?;
+        walker_earnings::record_charge(&tenant.pool, tenant.org_id, captured).await?;
+        tax::report_sale(state, tenant, captured).await;
+    }
+
+    Ok(captured)
+}
+
+/// Currency the organization prices new services and pays out in
+pub async fn organization_currency(
+    state: &AppState,
+    tenant: &TenantContext,
+) -> ApiResult<Currency> {
+    let org = OrganizationRepository::find_by_id(&state.pool, tenant.org_id)
+        .await?
+        .ok_or_else(|| ApiError::from(DomainError::TenantNotFound(tenant.org_id.to_string())))?;
+
+    Ok(org.settings.default_currency())
+}
+
+/// What the tenant's payment provider charges to process a payment in
+/// `currency`, from the tenant's fee schedules or the provider's list price.
+///
+/// Without a saved payment method the customer pays in the browser, almost
+/// always by card.
+pub async fn processing_fee(
+    tenant: &TenantContext,
+    provider_type: PaymentProviderType,
+    payment_method: Option<&CustomerPaymentMethod>,
+    currency: Currency,
+) -> ApiResult<ProcessingFee> {
+    let schedules = ProcessingFeeScheduleRepository::list_for_provider(
+        &tenant.pool,
+        tenant.org_id,
+        provider_type,
+    )
+    .await?;
+    let method = payment_method.map_or(PaymentMethodType::Card, |pm| pm.method_type);
+    let region = payment_method.map_or(CardRegion::Domestic, |pm| pm.card_region(currency));
+
+    Ok(ProcessingFeeSchedule::resolve(
+        &schedules,
+        provider_type,
+        method,
+        region,
+        currency,
+    ))
+}
+
+/// Fee breakdown for charging `subtotal` under the tenant's fee tier
+pub async fn fee_breakdown(
+    tenant: &TenantContext,
+    subtotal: Money,
+    tax_rate_percent: Decimal,
+    processing_fee: ProcessingFee,
+) -> ApiResult<TransactionFeeBreakdown> {
+    // Get the platform fee tier for this tenant
+    let fee_tier = SubscriptionRepository::get_org_fee_tier(&tenant.pool, tenant.org_id).await?;
+
+    Ok(TransactionFeeBreakdown::calculate(
+        subtotal,
+        fee_tier.customer_fee_percent,
+        fee_tier.provider_fee_percent,
+        tax_rate_percent,
+        processing_fee,
+    )?)
+}
+
+/// Settle holds that the provider will release within
+/// [`REAUTHORIZE_WINDOW_HOURS`]: re-authorize them for upcoming walks, capture
+/// them for completed walks, and release them for cancelled bookings.
+///
+/// Returns the number of holds handled.
+pub async fn refresh_expiring_authorizations(
+    state: &AppState,
+    tenant: &TenantContext,
+) -> ApiResult<usize> {
+    let cutoff = Utc::now() + Duration::hours(REAUTHORIZE_WINDOW_HOURS);
+    let expiring =
+        TransactionRepository::list_expiring_authorizations(&tenant.pool, tenant.org_id, cutoff)
+            .await?;
+
+    settle_holds(state, tenant, expiring).await
+}
+
+/// Retry holds left behind when a booking finished: capture them for
+/// completed walks and release them for cancelled bookings.
+///
+/// A held transaction on a finished booking means the capture or release
+/// failed when the booking changed; the failure is on the transaction.
+/// Returns the number of holds settled.
+pub async fn settle_finished_bookings(
+    state: &AppState,
+    tenant: &TenantContext,
+) -> ApiResult<usize> {
+    let unsettled =
+        TransactionRepository::list_held_for_finished_bookings(&tenant.pool, tenant.org_id).await?;
+
+    settle_holds(state, tenant, unsettled).await
+}
+
+async fn settle_holds(
+    state: &AppState,
+    tenant: &TenantContext,
+    transactions: Vec<Transaction>,
+) -> ApiResult<usize> {
+    let mut handled = 0;
+    for transaction in transactions {
+        let Some(booking_id) = transaction.booking_id else {
+            continue;
+        };
+        let Some(booking) =
+            db::BookingRepository::find_by_id(&tenant.pool, tenant.org_id, booking_id).await?
+        else {
+            continue;
+        };
+
+        let result = match booking.status {
+            BookingStatus::Pending | BookingStatus::Confirmed | BookingStatus::InProgress => {
+                match transaction_gateway(state, tenant, &transaction).await {
+                    Ok((provider, gateway)) => {
+                        reauthorize(state, tenant, &provider, gateway.as_ref(), &transaction)
+                            .await
+                            .map(|_| ())
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            BookingStatus::Completed => capture_booking_payment(state, tenant, &booking)
+                .await
+                .map(|_| ()),
+            BookingStatus::Cancelled | BookingStatus::NoShow => {
+                release_booking_payment(state, tenant, &booking)
+                    .await
+                    .map(|_| ())
+            }
+        };
+
+        match result {
+            Ok(()) => handled += 1,
+            Err(e) => tracing::warn!(
+                "Failed to settle held payment for transaction {}: {}",
+                transaction.id,
+                e.0
+            ),
+        }
+    }
+
+    Ok(handled)
+}
+
+/// The booking's transaction, if its funds are currently held
+async fn held_transaction(
+    tenant: &TenantContext,
+    booking: &Booking,
+) -> ApiResult<Option<Transaction>> {
+    Ok(
+        TransactionRepository::get_by_booking(&tenant.pool, tenant.org_id, booking.id)
+            .await?
+            .filter(Transaction::is_authorized),
+    )
+}
+
+/// The provider a transaction was made through and its gateway
+pub async fn transaction_gateway(
+    state: &AppState,
+    tenant: &TenantContext,
+    transaction: &Transaction,
+) -> ApiResult<(PaymentProvider, Arc<dyn PaymentGateway>)> {
+    let provider =
+        PaymentProviderRepository::get_by_id(&tenant.pool, tenant.org_id, transaction.provider_id)
+            .await?
+            .ok_or_else(|| {
+                ApiError::from(AppError::Internal("Payment provider not found".to_string()))
+            })?;
+    let gateway = state.payment_gateways.for_provider(&provider)?;
+
+    Ok((provider, gateway))
+}
+
+/// Replace a transaction's hold using its saved payment method
+async fn reauthorize(
+    state: &AppState,
+    tenant: &TenantContext,
+    provider: &PaymentProvider,
+    gateway: &dyn PaymentGateway,
+    transaction: &Transaction,
+) -> ApiResult<Transaction> {
+    let payment_method = match transaction.payment_method_id {
+        Some(id) => {
+            CustomerPaymentMethodRepository::find_by_id(&tenant.pool, tenant.org_id, id).await?
+        }
+        None => None,
+    }
+    .ok_or_else(|| {
+        ApiError::from(AppError::Validation(
+            "Cannot re-authorize a payment without a saved payment method".to_string(),
+        ))
+    })?;
+
+    let payment = match reauthorize_transaction(gateway, transaction, &payment_method).await {
+        Ok(payment) => payment,
+        Err(e) => {
+            record_failure(tenant, transaction, &e.0.to_string()).await?;
+            return Err(e);
+        }
+    };
+
+    let now = Utc::now();
+    let updated = TransactionRepository::record_authorization(
+        &tenant.pool,
+        transaction.id,
+        &payment.id,
+        now,
+        now + gateway.authorization_validity(),
+    )
+    .await?
+    .unwrap_or_else(|| transaction.clone());
+
+    // Keep the provider-specific reference in step with the new hold
+    record_payment(
+        state,
+        tenant,
+        &updated,
+        provider.provider_type,
+        gateway,
+        &payment,
+    )
+    .await
+}
+
+/// Replace a captured transaction's estimated processing fee with the fee
+/// the provider reports, once it has settled the payment. The estimate is
+/// kept if the provider can't say yet.
+async fn record_reported_fee(
+    tenant: &TenantContext,
+    gateway: &dyn PaymentGateway,
+    transaction: Transaction,
+) -> ApiResult<Transaction> {
+    if transaction.status != TransactionStatus::Succeeded || transaction.processing_fee_reported {
+        return Ok(transaction);
+    }
+    let Some(payment_id) = external_payment_id(&transaction) else {
+        return Ok(transaction);
+    };
+
+    let fee_cents = match gateway.processing_fee(payment_id).await {
+        Ok(Some(fee)) => match i32::try_from(fee) {
+            Ok(fee) => fee,
+            Err(_) => return Ok(transaction),
+        },
+        Ok(None) => return Ok(transaction),
+        Err(e) => {
+            tracing::warn!(
+                "Failed to fetch the processing fee for transaction {}: {}",
+                transaction.id,
+                e
+            );
+            return Ok(transaction);
+        }
+    };
+
+    Ok(
+        TransactionRepository::record_processing_fee(&tenant.pool, transaction.id, fee_cents)
+            .await?
+            .unwrap_or(transaction),
+    )
+}
+
+async fn record_failure(
+    tenant: &TenantContext,
+    transaction: &Transaction,
+    message: &str,
+) -> ApiResult<()> {
+    TransactionRepository::update(
+        &tenant.pool,
+        transaction.id,
+        UpdateTransaction {
+            failure_message: Some(message.to_string()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use db::models::PaymentMethodType;
+    use integrations::gateway::{FakeGateway, DECLINED_PAYMENT_METHOD};
+    use shared::types::{OrganizationId, UserId};
+    use shared::DomainError;
+    use uuid::Uuid;
+
+    fn transaction() -> Transaction {
+        Transaction {
+            id: Uuid::new_v4(),
+            organization_id: OrganizationId::new(),
+            booking_id: None,
+            customer_user_id: UserId::new(),
+            provider_user_id: Some(UserId::new()),
+            payment_method_id: None,
+            provider_id: Uuid::new_v4(),
+            subtotal_cents: 4000,
+            tip_cents: 500,
+            customer_fee_cents: 200,
+            provider_fee_cents: 200,
+            platform_fee_cents: 400,
+            tax_cents: 240,
+            processing_fee_cents: 140,
+            total_cents: 4940,
+            provider_payout_cents: 4300,
+            processing_fee_reported: false,
+            currency: Currency::USD,
+            status: TransactionStatus::Pending,
+            external_payment_id: None,
+            stripe_payment_intent_id: None,
+            stripe_charge_id: None,
+            stripe_transfer_id: None,
+            square_payment_id: None,
+            square_order_id: None,
+            tax_rate_percent: None,
+            tax_jurisdiction: None,
+            tax_calculation_id: None,
+            tax_reported_at: None,
+            refunded_amount_cents: 0,
+            authorized_at: None,
+            authorization_expires_at: None,
+            captured_at: None,
+            idempotency_key: None,
+            manual_capture: false,
+            off_session: false,
+            failure_code: None,
+            failure_message: None,
+            description: Some("30 minute walk".to_string()),
+            metadata: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// A transaction to be paid with `card`
+    fn paid_with(card: &CustomerPaymentMethod) -> Transaction {
+        Transaction {
+            payment_method_id: Some(card.id),
+            ..transaction()
+        }
+    }
+
+    fn saved_card(stripe_pm: &str) -> CustomerPaymentMethod {
+        CustomerPaymentMethod {
+            id: Uuid::new_v4(),
+            organization_id: OrganizationId::new(),
+            user_id: UserId::new(),
+            provider_type: PaymentProviderType::Stripe,
+            method_type: PaymentMethodType::Card,
+            stripe_payment_method_id: Some(stripe_pm.to_string()),
+            stripe_customer_id: Some("cus_123".to_string()),
+            square_card_id: None,
+            square_customer_id: None,
+            last_four: Some("4242".to_string()),
+            brand: Some("visa".to_string()),
+            exp_month: Some(12),
+            exp_year: Some(2030),
+            cardholder_name: None,
+            card_country: Some("US".to_string()),
+            bank_name: None,
+            account_last_four: None,
+            wallet_type: None,
+            is_default: true,
+            is_active: true,
+            billing_address: None,
+            metadata: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn refund(transaction: &Transaction, amount_cents: i32) -> Refund {
+        let allocation = RefundAllocation::for_refund(transaction, amount_cents);
+        Refund {
+            id: Uuid::new_v4(),
+            organization_id: transaction.organization_id,
+            transaction_id: transaction.id,
+            amount_cents,
+            currency: transaction.currency,
+            reason: None,
+            status: RefundStatus::Pending,
+            source: RefundSource::Customer,
+            initiated_by: Some(transaction.customer_user_id),
+            provider_refund_id: None,
+            subtotal_refunded_cents: allocation.subtotal_cents,
+            tip_refunded_cents: allocation.tip_cents,
+            customer_fee_refunded_cents: allocation.customer_fee_cents,
+            tax_refunded_cents: allocation.tax_cents,
+            platform_fee_reversed_cents: allocation.platform_fee_cents,
+            provider_payout_reversed_cents: allocation.provider_payout_cents,
+            failure_message: None,
+            tax_reported_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_checkout_with_saved_card_then_refund() {
+        let gateway = FakeGateway::new();
+        let card = saved_card("pm_visa");
+        let mut transaction = paid_with(&card);
+
+        let payment = charge_transaction(&gateway, &transaction, Some(&card))
+            .await
+            .unwrap();
+        assert_eq!(
+            transaction_status(payment.state),
+            TransactionStatus::Succeeded
+        );
+        assert_eq!(payment.amount_cents, 4940);
+
+        transaction.external_payment_id = Some(payment.id.clone());
+        transaction.status = TransactionStatus::Succeeded;
+        refund_transaction(&gateway, &transaction, &refund(&transaction, 2000))
+            .await
+            .unwrap();
+        transaction.refunded_amount_cents = 2000;
+
+        // More than the remaining 2940 is rejected by the provider
+        let over = refund_transaction(&gateway, &transaction, &refund(&transaction, 3000)).await;
+        assert!(matches!(over, Err(ApiError(AppError::Validation(_)))));
+        assert_eq!(gateway.refunds().len(), 1);
+    }
+
+    #[test]
+    fn test_refunds_limited_to_remaining_amount() {
+        let mut transaction = transaction();
+        assert!(validate_refund_amount(&transaction, 0, 100).is_err());
+
+        transaction.status = TransactionStatus::PartiallyRefunded;
+        transaction.refunded_amount_cents = 4000;
+        assert!(validate_refund_amount(&transaction, 0, 940).is_ok());
+        assert!(validate_refund_amount(&transaction, 0, 941).is_err());
+        // A pending refund still counts against the remaining amount
+        assert!(validate_refund_amount(&transaction, 500, 500).is_err());
+        assert!(validate_refund_amount(&transaction, 0, 0).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_checkout_without_card_needs_client_confirmation() {
+        let gateway = FakeGateway::new();
+        let mut transaction = transaction();
+
+        let payment = charge_transaction(&gateway, &transaction, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            transaction_status(payment.state),
+            TransactionStatus::Pending
+        );
+        assert!(payment.client_secret.is_some());
+
+        // Retrying checkout for the same transaction doesn't create a second charge
+        let retry = charge_transaction(&gateway, &transaction, None)
+            .await
+            .unwrap();
+        assert_eq!(retry.id, payment.id);
+
+        gateway.confirm_client_side(&payment.id).unwrap();
+        transaction.external_payment_id = payment.client_secret.clone();
+        let status = gateway
+            .payment_status(external_payment_id(&transaction).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            transaction_status(status.state),
+            TransactionStatus::Succeeded
+        );
+    }
+
+    #[tokio::test]
+    async fn test_declined_card() {
+        let gateway = FakeGateway::new();
+        let card = saved_card(DECLINED_PAYMENT_METHOD);
+        let mut transaction = paid_with(&card);
+        transaction.manual_capture = true;
+        transaction.off_session = true;
+
+        let result = charge_transaction(&gateway, &transaction, Some(&card)).await;
+
+        assert!(matches!(
+            result,
+            Err(ApiError(AppError::Domain(DomainError::PaymentDeclined(_))))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_hold_is_replaced_on_reauthorization() {
+        let gateway = FakeGateway::new();
+        let card = saved_card("pm_visa");
+        let mut transaction = paid_with(&card);
+        transaction.manual_capture = true;
+        transaction.off_session = true;
+
+        let hold = charge_transaction(&gateway, &transaction, Some(&card))
+            .await
+            .unwrap();
+        assert_eq!(
+            transaction_status(hold.state),
+            TransactionStatus::Authorized
+        );
+
+        transaction.status = TransactionStatus::Authorized;
+        transaction.external_payment_id = Some(hold.id.clone());
+        transaction.authorized_at = Some(Utc::now());
+
+        let renewed = reauthorize_transaction(&gateway, &transaction, &card)
+            .await
+            .unwrap();
+        assert_ne!(renewed.id, hold.id);
+        assert_eq!(renewed.state, PaymentState::Authorized);
+        assert_eq!(
+            gateway.payment(&hold.id).unwrap().state,
+            PaymentState::Canceled
+        );
+
+        let captured = gateway.capture(&renewed.id, None).await.unwrap();
+        assert_eq!(captured.captured_cents, 4940);
+    }
+}
@@ -0,0 +1,273 @@
+//! Paying out walker earnings to the organization's bank account.
+//!
+//! Scheduled payouts run on the days the organization's payout settings name
+//! and cover what transactions made before that day earned, less refunds and
+//! lost disputes. Transactions with an open dispute wait until it's decided,
+//! and refunds made after a transaction was paid out come off the next
+//! payout. Walkers' own earnings are paid out on the same schedule, before
+//! the organization's payout, and what was transferred to walkers comes off
+//! it. There is one scheduled payout per organization, day and currency,
+//! however many servers run the job, and it is sent with an idempotency key
+//! derived from that day, so one the provider didn't accept is sent again on
+//! the next run without risk of paying it twice.
+//!
+//! The provider reports how a payout progresses through webhooks. A payout
+//! that fails or is canceled returns its funds to the processor balance, and
+//! its transactions are paid out again by the next scheduled payout.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use db::models::{
+    CreatePayout, PaymentProviderType, Payout, PayoutBatch, PayoutStatus, UpdatePayout,
+};
+use db::{
+    DisputeRepository, PaymentProviderRepository, PayoutRepository, RefundRepository,
+    TransactionRepository, WalkerEarningRepository,
+};
+use integrations::gateway::{GatewayError, PayoutRequest, PayoutSpeed};
+use shared::AppError;
+use sqlx::PgPool;
+
+use crate::{
+    auth::TenantContext,
+    error::{ApiError, ApiResult},
+    ledger,
+    state::AppState,
+    walker_earnings,
+};
+
+/// Make the organization's scheduled payouts for `date`, one per currency,
+/// and retry any earlier ones the provider didn't accept. Returns the
+/// payouts created.
+pub async fn run_scheduled_payouts(
+    state: &AppState,
+    tenant: &TenantContext,
+    date: NaiveDate,
+) -> ApiResult<Vec<Payout>> {
+    // Instant payouts are requested by someone waiting on the result, so
+    // only scheduled ones are retried
+    let unsent = PayoutRepository::list_uninitiated(&tenant.pool, tenant.org_id).await?;
+    for payout in unsent.iter().filter(|p| !p.transaction_ids.is_empty()) {
+        send_or_log(state, tenant, payout).await;
+    }
+
+    let Some(settings) = PayoutRepository::get_settings(&tenant.pool, tenant.org_id).await? else {
+        return Ok(Vec::new());
+    };
+    if !settings.is_ready() || !settings.is_due_on(date) {
+        return Ok(Vec::new());
+    }
+
+    let period_end = date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+
+    // Walkers are paid their own share on the same schedule, first, so what
+    // was transferred to them comes off the organization's payout
+    let walker_payouts = walker_earnings::pay_walkers(state, tenant, period_end).await?;
+    for payout in &walker_payouts {
+        tracing::info!(
+            "Created walker payout {} of {} cents ({}) for walker {}",
+            payout.id,
+            payout.amount_cents,
+            payout.currency,
+            payout.walker_id
+        );
+    }
+
+    let transactions =
+        TransactionRepository::get_pending_for_payout(&tenant.pool, tenant.org_id, period_end)
+            .await?;
+
+    let mut amounts = Vec::with_capacity(transactions.len());
+    let mut refunds = Vec::new();
+    for transaction in &transactions {
+        // Refunds an earlier payout of the transaction deducted stay deducted
+        let transaction_refunds = RefundRepository::list_undeducted_by_transaction(
+            &tenant.pool,
+            tenant.org_id,
+            transaction.id,
+        )
+        .await?;
+        let dispute = DisputeRepository::get_by_transaction(&tenant.pool, transaction.id).await?;
+        if let Some(amount) =
+            PayoutBatch::transaction_amount(transaction, &transaction_refunds, dispute.as_ref())
+        {
+            amounts.push((transaction, amount));
+            refunds.extend(
+                transaction_refunds
+                    .into_iter()
+                    .filter(|r| r.status.is_outstanding()),
+            );
+        }
+    }
+    refunds.extend(
+        RefundRepository::list_undeducted_after_payout(&tenant.pool, tenant.org_id, period_end)
+            .await?,
+    );
+
+    let transferred =
+        WalkerEarningRepository::list_transferred_undeducted(&tenant.pool, tenant.org_id).await?;
+
+    let mut payouts = Vec::new();
+    for batch in PayoutBatch::collect(amounts, &refunds, &transferred) {
+        // Balances under the minimum carry over to the next payout
+        if batch.amount_cents <= 0 || batch.amount_cents < i64::from(settings.minimum_payout_cents)
+        {
+            continue;
+        }
+
+        let amount_cents = i32::try_from(batch.amount_cents).map_err(|_| {
+            ApiError::from(AppError::Internal(format!(
+                "Payout of {} cents is too large",
+                batch.amount_cents
+            )))
+        })?;
+        let input = CreatePayout {
+            amount_cents,
+            fee_cents: 0,
+            net_amount_cents: amount_cents,
+            currency: batch.currency,
+            period_start: batch.period_start,
+            period_end,
+            transaction_count: batch.transaction_ids.len() as i32,
+            transaction_ids: batch.transaction_ids,
+            refund_ids: batch.refund_ids,
+            walker_earning_ids: batch.walker_earning_ids,
+            scheduled_for: Some(date),
+        };
+        // Another server already made this payout
+        let Some(payout) = PayoutRepository::create(&tenant.pool, tenant.org_id, input).await?
+        else {
+            continue;
+        };
+        send_or_log(state, tenant, &payout).await;
+        payouts.push(payout);
+    }
+
+    Ok(payouts)
+}
+
+/// Send a payout to the organization's primary provider, post it to the
+/// ledger and record the provider's ID. The payout is only marked initiated
+/// once it's posted, so a failed post is sent again (under the same
+/// idempotency key) and posted on the next run.
+pub async fn send_payout(
+    state: &AppState,
+    tenant: &TenantContext,
+    payout: &Payout,
+    speed: PayoutSpeed,
+) -> ApiResult<()> {
+    let sent = initiate_provider_payout(state, tenant, payout, speed).await?;
+    ledger::post_payout(&tenant.pool, tenant.org_id, payout).await?;
+
+    let update = UpdatePayout {
+        stripe_payout_id: sent.stripe_payout_id,
+        square_payout_id: sent.square_payout_id,
+        status: sent.status,
+        initiated_at: Some(Utc::now()),
+        ..Default::default()
+    };
+    PayoutRepository::update(&tenant.pool, payout.id, update).await?;
+    Ok(())
+}
+
+/// Apply a status the provider reported for a payout. Reports that would
+/// move a finished payout back to pending or in transit arrived out of
+/// order and are ignored.
+pub async fn apply_provider_status(
+    pool: &PgPool,
+    payout: &Payout,
+    status: PayoutStatus,
+    failure_code: Option<&str>,
+    failure_message: Option<&str>,
+    arrival_date: Option<DateTime<Utc>>,
+) -> ApiResult<Option<Payout>> {
+    if payout.status.is_final() && !status.is_final() {
+        return Ok(None);
+    }
+
+    let mut updated =
+        PayoutRepository::update_status(pool, payout.id, status, failure_code, failure_message)
+            .await?;
+    if arrival_date.is_some() && arrival_date != payout.arrival_date {
+        let update = UpdatePayout {
+            arrival_date,
+            ..Default::default()
+        };
+        updated = PayoutRepository::update(pool, payout.id, update).await?;
+    }
+
+    if let Some(updated) = &updated {
+        // Only payouts that were sent were posted to the ledger
+        let returned = matches!(status, PayoutStatus::Failed | PayoutStatus::Canceled);
+        if returned && payout.initiated_at.is_some() {
+            ledger::post_payout_failed(pool, updated.organization_id, updated).await?;
+        }
+        if status != payout.status {
+            tracing::info!("Payout {} is now {}", payout.id, status);
+        }
+    }
+    Ok(updated)
+}
+
+async fn send_or_log(state: &AppState, tenant: &TenantContext, payout: &Payout) {
+    if let Err(e) = send_payout(state, tenant, payout, PayoutSpeed::Standard).await {
+        tracing::warn!(
+            "Failed to send payout {} for organization {}, will retry: {}",
+            payout.id,
+            tenant.org_id,
+            e.0
+        );
+    }
+}
+
+/// What the provider recorded for a payout
+#[derive(Default)]
+struct ProviderPayout {
+    stripe_payout_id: Option<String>,
+    square_payout_id: Option<String>,
+    /// Set when the payout won't be reported on by the provider
+    status: Option<PayoutStatus>,
+}
+
+async fn initiate_provider_payout(
+    state: &AppState,
+    tenant: &TenantContext,
+    payout: &Payout,
+    speed: PayoutSpeed,
+) -> Result<ProviderPayout, ApiError> {
+    // Get the primary payment provider
+    let provider = PaymentProviderRepository::get_primary(&tenant.pool, tenant.org_id)
+        .await?
+        .ok_or_else(|| {
+            ApiError::from(AppError::Internal(
+                "No payment provider configured".to_string(),
+            ))
+        })?;
+
+    let gateway = state.payment_gateways.for_provider(&provider)?;
+    let request = PayoutRequest {
+        amount_cents: payout.net_amount_cents.into(),
+        currency: payout.currency,
+        speed,
+        idempotency_key: payout.idempotency_key(),
+    };
+
+    match gateway.payout(&request).await {
+        Ok(provider_payout) => match provider.provider_type {
+            PaymentProviderType::Square => Ok(ProviderPayout {
+                square_payout_id: Some(provider_payout.id),
+                ..Default::default()
+            }),
+            PaymentProviderType::Stripe | PaymentProviderType::Platform => Ok(ProviderPayout {
+                stripe_payout_id: Some(provider_payout.id),
+                ..Default::default()
+            }),
+        },
+        // Square deposits the merchant's balance on its own schedule, so
+        // there's no provider payout to record or hear back about
+        Err(GatewayError::NotSupported(_)) => Ok(ProviderPayout {
+            status: Some(PayoutStatus::Manual),
+            ..Default::default()
+        }),
+        Err(e) => Err(e.into()),
+    }
+}
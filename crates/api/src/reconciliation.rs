@@ -0,0 +1,472 @@
+//! Reconciliation of local transactions against the payment provider.
+//!
+//! A run lists the provider's payments for a period and matches them to the
+//! transactions charged through that provider by payment ID. Amount, status
+//! and refund differences, and payments only one side knows about, are stored
+//! for an admin to review. Payments near the edges of the period are looked
+//! up individually before being reported missing, so a charge created a
+//! moment before its transaction isn't flagged.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use db::models::{
+    CreateReconciliationMismatch, PaymentProvider, ReconciliationCounts,
+    ReconciliationMismatchKind, ReconciliationRun, Transaction, TransactionStatus,
+};
+use db::{PaymentProviderRepository, ReconciliationRepository, TransactionRepository};
+use integrations::gateway::{
+    GatewayError, GatewayPayment, GatewayPaymentRecord, PaymentGateway, PaymentState,
+};
+use shared::types::UserId;
+
+use crate::{
+    auth::TenantContext,
+    error::ApiResult,
+    payments::{external_payment_id, transaction_status},
+    state::AppState,
+};
+
+/// Reconcile every active payment provider for `[from, to)`
+pub async fn reconcile_all(
+    state: &AppState,
+    tenant: &TenantContext,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    requested_by: Option<UserId>,
+) -> ApiResult<Vec<ReconciliationRun>> {
+    let providers = PaymentProviderRepository::list_for_org(&tenant.pool, tenant.org_id).await?;
+
+    let mut runs = Vec::new();
+    for provider in providers.iter().filter(|p| p.is_active) {
+        runs.push(reconcile_provider(state, tenant, provider, from, to, requested_by).await?);
+    }
+    Ok(runs)
+}
+
+/// Reconcile one provider for `[from, to)`.
+///
+/// Provider errors fail the run rather than the call, so the failure is
+/// visible to admins alongside completed runs.
+pub async fn reconcile_provider(
+    state: &AppState,
+    tenant: &TenantContext,
+    provider: &PaymentProvider,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    requested_by: Option<UserId>,
+) -> ApiResult<ReconciliationRun> {
+    let run = ReconciliationRepository::create_run(
+        &tenant.pool,
+        tenant.org_id,
+        provider.id,
+        from,
+        to,
+        requested_by,
+    )
+    .await?;
+
+    let result = async {
+        let gateway = state.payment_gateways.for_provider(provider)?;
+        let local = TransactionRepository::list_for_provider_between(
+            &tenant.pool,
+            tenant.org_id,
+            provider.id,
+            from,
+            to,
+        )
+        .await?;
+        let remote = gateway.list_payments(from, to).await?;
+
+        let matched = match_payments(&local, &remote);
+        let mut mismatches = matched.mismatches;
+        for transaction in matched.unmatched_local {
+            mismatches.extend(check_local(gateway.as_ref(), transaction).await?);
+        }
+        for record in matched.unmatched_remote {
+            mismatches.extend(check_remote(tenant, record).await?);
+        }
+
+        ApiResult::Ok((matched.counts, mismatches))
+    }
+    .await;
+
+    match result {
+        Ok((counts, mismatches)) => {
+            Ok(
+                ReconciliationRepository::complete_run(&tenant.pool, &run, counts, &mismatches)
+                    .await?,
+            )
+        }
+        Err(e) => {
+            tracing::warn!("Reconciliation run {} failed: {}", run.id, e.0);
+            Ok(
+                ReconciliationRepository::fail_run(&tenant.pool, run.id, &e.0.to_string())
+                    .await?
+                    .unwrap_or(run),
+            )
+        }
+    }
+}
+
+/// Result of matching local transactions to provider payments by ID
+struct MatchedPayments<'a> {
+    counts: ReconciliationCounts,
+    mismatches: Vec<CreateReconciliationMismatch>,
+    unmatched_local: Vec<&'a Transaction>,
+    unmatched_remote: Vec<&'a GatewayPaymentRecord>,
+}
+
+fn match_payments<'a>(
+    local: &'a [Transaction],
+    remote: &'a [GatewayPaymentRecord],
+) -> MatchedPayments<'a> {
+    let mut by_id: HashMap<&str, &GatewayPaymentRecord> =
+        remote.iter().map(|r| (r.id.as_str(), r)).collect();
+
+    let mut matched = MatchedPayments {
+        counts: ReconciliationCounts {
+            local_count: local.len() as i32,
+            remote_count: remote.len() as i32,
+            matched_count: 0,
+        },
+        mismatches: Vec::new(),
+        unmatched_local: Vec::new(),
+        unmatched_remote: Vec::new(),
+    };
+
+    for transaction in local {
+        match external_payment_id(transaction).and_then(|id| by_id.remove(id)) {
+            Some(record) => {
+                let differences = compare_record(transaction, record);
+                if differences.is_empty() {
+                    matched.counts.matched_count += 1;
+                }
+                matched.mismatches.extend(differences);
+            }
+            None => matched.unmatched_local.push(transaction),
+        }
+    }
+
+    matched.unmatched_remote = remote
+        .iter()
+        .filter(|r| by_id.contains_key(r.id.as_str()))
+        .collect();
+    matched
+}
+
+/// Look up a transaction the provider didn't list for the period
+async fn check_local(
+    gateway: &dyn PaymentGateway,
+    transaction: &Transaction,
+) -> ApiResult<Vec<CreateReconciliationMismatch>> {
+    let Some(payment_id) = external_payment_id(transaction) else {
+        // Checkouts that never reached the provider have nothing to match
+        return Ok(if reached_provider(transaction.status) {
+            vec![missing_remotely(transaction)]
+        } else {
+            Vec::new()
+        });
+    };
+
+    match gateway.payment_status(payment_id).await {
+        Ok(payment) => Ok(compare_payment(transaction, &payment)),
+        Err(GatewayError::NotFound(_)) => Ok(vec![missing_remotely(transaction)]),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Look up the transaction for a provider payment outside the period
+async fn check_remote(
+    tenant: &TenantContext,
+    record: &GatewayPaymentRecord,
+) -> ApiResult<Vec<CreateReconciliationMismatch>> {
+    match TransactionRepository::get_by_external_id(&tenant.pool, tenant.org_id, &record.id).await?
+    {
+        Some(transaction) => Ok(compare_record(&transaction, record)),
+        None => Ok(vec![CreateReconciliationMismatch {
+            kind: ReconciliationMismatchKind::MissingLocally,
+            transaction_id: None,
+            external_payment_id: Some(record.id.clone()),
+            local_amount_cents: None,
+            remote_amount_cents: Some(record.amount_cents),
+            local_status: None,
+            remote_status: Some(transaction_status(record.state).to_string()),
+        }]),
+    }
+}
+
+/// Differences between a transaction and a listed provider payment
+fn compare_record(
+    transaction: &Transaction,
+    record: &GatewayPaymentRecord,
+) -> Vec<CreateReconciliationMismatch> {
+    let mut mismatches = compare(
+        transaction,
+        &record.id,
+        record.state,
+        settled_amount(record.state, record.amount_cents, record.captured_cents),
+    );
+
+    if record.state == PaymentState::Succeeded
+        && i64::from(transaction.refunded_amount_cents) != record.refunded_cents
+    {
+        mismatches.push(CreateReconciliationMismatch {
+            kind: ReconciliationMismatchKind::RefundMismatch,
+            local_amount_cents: Some(i64::from(transaction.refunded_amount_cents)),
+            remote_amount_cents: Some(record.refunded_cents),
+            ..mismatch(transaction, &record.id, record.state)
+        });
+    }
+    mismatches
+}
+
+/// Differences between a transaction and a single provider payment lookup
+fn compare_payment(
+    transaction: &Transaction,
+    payment: &GatewayPayment,
+) -> Vec<CreateReconciliationMismatch> {
+    compare(
+        transaction,
+        &payment.id,
+        payment.state,
+        settled_amount(payment.state, payment.amount_cents, payment.captured_cents),
+    )
+}
+
+fn compare(
+    transaction: &Transaction,
+    payment_id: &str,
+    state: PaymentState,
+    amount_cents: i64,
+) -> Vec<CreateReconciliationMismatch> {
+    let mut mismatches = Vec::new();
+
+    if settled_status(transaction.status) != transaction_status(state) {
+        mismatches.push(CreateReconciliationMismatch {
+            kind: ReconciliationMismatchKind::StatusMismatch,
+            ..mismatch(transaction, payment_id, state)
+        });
+    }
+
+    // Amounts of payments that never went through don't matter
+    let charged = !matches!(state, PaymentState::Canceled | PaymentState::Failed);
+    if charged && i64::from(transaction.total_cents) != amount_cents {
+        mismatches.push(CreateReconciliationMismatch {
+            kind: ReconciliationMismatchKind::AmountMismatch,
+            remote_amount_cents: Some(amount_cents),
+            ..mismatch(transaction, payment_id, state)
+        });
+    }
+
+    mismatches
+}
+
+fn mismatch(
+    transaction: &Transaction,
+    payment_id: &str,
+    state: PaymentState,
+) -> CreateReconciliationMismatch {
+    CreateReconciliationMismatch {
+        kind: ReconciliationMismatchKind::StatusMismatch,
+        transaction_id: Some(transaction.id),
+        external_payment_id: Some(payment_id.to_string()),
+        local_amount_cents: Some(i64::from(transaction.total_cents)),
+        remote_amount_cents: None,
+        local_status: Some(transaction.status.to_string()),
+        remote_status: Some(transaction_status(state).to_string()),
+    }
+}
+
+fn missing_remotely(transaction: &Transaction) -> CreateReconciliationMismatch {
+    CreateReconciliationMismatch {
+        kind: ReconciliationMismatchKind::MissingRemotely,
+        transaction_id: Some(transaction.id),
+        external_payment_id: external_payment_id(transaction).map(str::to_string),
+        local_amount_cents: Some(i64::from(transaction.total_cents)),
+        remote_amount_cents: None,
+        local_status: Some(transaction.status.to_string()),
+        remote_status: None,
+    }
+}
+
+/// Amount the transaction should record: what was captured once the
+/// payment succeeded, since partial captures lower the total
+fn settled_amount(state: PaymentState, amount_cents: i64, captured_cents: i64) -> i64 {
+    if state == PaymentState::Succeeded {
+        captured_cents
+    } else {
+        amount_cents
+    }
+}
+
+/// Status the provider reports for a transaction. Refunds and disputes are
+/// tracked separately; the payment itself still succeeded.
+fn settled_status(status: TransactionStatus) -> TransactionStatus {
+    match status {
+        TransactionStatus::Refunded
+        | TransactionStatus::PartiallyRefunded
+        | TransactionStatus::Disputed => TransactionStatus::Succeeded,
+        status => status,
+    }
+}
+
+/// Whether a transaction without a provider payment ID should have one
+fn reached_provider(status: TransactionStatus) -> bool {
+    !matches!(
+        status,
+        TransactionStatus::Pending | TransactionStatus::Failed | TransactionStatus::Canceled
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::types::{Currency, OrganizationId};
+    use uuid::Uuid;
+
+    fn transaction(payment_id: &str, status: TransactionStatus) -> Transaction {
+        Transaction {
+            id: Uuid::new_v4(),
+            organization_id: OrganizationId::new(),
+            booking_id: None,
+            customer_user_id: UserId::new(),
+            provider_user_id: Some(UserId::new()),
+            payment_method_id: None,
+            provider_id: Uuid::new_v4(),
+            subtotal_cents: 4000,
+            tip_cents: 0,
+            customer_fee_cents: 200,
+            provider_fee_cents: 200,
+            platform_fee_cents: 400,
+            tax_cents: 0,
+            processing_fee_cents: 146,
+            total_cents: 4200,
+            provider_payout_cents: 3800,
+            processing_fee_reported: false,
+            currency: Currency::USD,
+            status,
+            external_payment_id: Some(payment_id.to_string()),
+            stripe_payment_intent_id: None,
+            stripe_charge_id: None,
+            stripe_transfer_id: None,
+            square_payment_id: None,
+            square_order_id: None,
+            tax_rate_percent: None,
+            tax_jurisdiction: None,
+            tax_calculation_id: None,
+            tax_reported_at: None,
+            refunded_amount_cents: 0,
+            authorized_at: None,
+            authorization_expires_at: None,
+            captured_at: None,
+            idempotency_key: None,
+            manual_capture: false,
+            off_session: false,
+            failure_code: None,
+            failure_message: None,
+            description: None,
+            metadata: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn record(id: &str, state: PaymentState, amount_cents: i64) -> GatewayPaymentRecord {
+        GatewayPaymentRecord {
+            id: id.to_string(),
+            state,
+            amount_cents,
+            captured_cents: if state == PaymentState::Succeeded {
+                amount_cents
+            } else {
+                0
+            },
+            refunded_cents: 0,
+            currency: "USD".to_string(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_matching_payments_reconcile() {
+        let mut refunded = transaction("pi_2", TransactionStatus::PartiallyRefunded);
+        refunded.refunded_amount_cents = 1000;
+        let local = vec![
+            transaction("pi_1", TransactionStatus::Succeeded),
+            refunded,
+            transaction("pi_3", TransactionStatus::Authorized),
+        ];
+        let mut remote = vec![
+            record("pi_1", PaymentState::Succeeded, 4200),
+            record("pi_2", PaymentState::Succeeded, 4200),
+            record("pi_3", PaymentState::Authorized, 4200),
+        ];
+        remote[1].refunded_cents = 1000;
+
+        let matched = match_payments(&local, &remote);
+        assert!(matched.mismatches.is_empty());
+        assert_eq!(matched.counts.matched_count, 3);
+        assert!(matched.unmatched_local.is_empty());
+        assert!(matched.unmatched_remote.is_empty());
+    }
+
+    #[test]
+    fn test_differences_are_reported() {
+        let local = vec![
+            transaction("pi_1", TransactionStatus::Authorized),
+            transaction("pi_2", TransactionStatus::Succeeded),
+            transaction("pi_3", TransactionStatus::Succeeded),
+            transaction("pi_local", TransactionStatus::Succeeded),
+        ];
+        let mut remote = vec![
+            record("pi_1", PaymentState::Succeeded, 4200),
+            record("pi_2", PaymentState::Succeeded, 3900),
+            record("pi_3", PaymentState::Succeeded, 4200),
+            record("pi_remote", PaymentState::Succeeded, 2500),
+        ];
+        remote[2].refunded_cents = 4200;
+
+        let matched = match_payments(&local, &remote);
+        let kinds: Vec<_> = matched.mismatches.iter().map(|m| m.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ReconciliationMismatchKind::StatusMismatch,
+                ReconciliationMismatchKind::AmountMismatch,
+                ReconciliationMismatchKind::RefundMismatch,
+            ]
+        );
+        assert_eq!(matched.counts.matched_count, 0);
+        assert_eq!(matched.unmatched_local.len(), 1);
+        assert_eq!(matched.unmatched_remote[0].id, "pi_remote");
+    }
+
+    #[test]
+    fn test_partial_capture_matches_captured_amount() {
+        let local = transaction("pi_1", TransactionStatus::Succeeded);
+        let mut remote = record("pi_1", PaymentState::Succeeded, 8400);
+        remote.captured_cents = 4200;
+
+        assert!(compare_record(&local, &remote).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unlisted_local_payment_is_looked_up() {
+        let gateway = integrations::gateway::FakeGateway::new();
+        let local = transaction("pi_gone", TransactionStatus::Succeeded);
+
+        let mismatches = check_local(&gateway, &local).await.unwrap();
+        assert_eq!(
+            mismatches[0].kind,
+            ReconciliationMismatchKind::MissingRemotely
+        );
+
+        let mut never_charged = transaction("", TransactionStatus::Pending);
+        never_charged.external_payment_id = None;
+        assert!(check_local(&gateway, &never_charged)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
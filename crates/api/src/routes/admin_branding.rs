@@ -114,6 +114,8 @@ pub async fn update_branding(
         logo_url: req.logo_url.or(current_settings.logo_url),
         favicon_url: req.favicon_url.or(current_settings.favicon_url),
         font_family: req.font_family.or(current_settings.font_family),
+        walk_trail_retention_days: current_settings.walk_trail_retention_days,
+        default_currency: current_settings.default_currency,
     };
 
     // Update the organization settings
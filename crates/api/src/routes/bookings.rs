@@ -2,15 +2,21 @@ use axum::{
     extract::{Path, Query, State},
     Json,
 };
-use chrono::DateTime;
-use db::models::{BookingStatus, CreateBooking};
-use db::{BookingRepository, LocationRepository, ServiceRepository, UserRepository};
+use chrono::{DateTime, Utc};
+use db::models::{Booking, BookingPriceBreakdown, BookingStatus, CreateBooking, TransactionStatus};
+use db::{
+    BookingRepository, CustomerPaymentMethodRepository, LocationRepository, ServiceAreaRepository,
+    ServiceRepository, TransactionRepository, UserRepository,
+};
+use domain::{BookingChange, CancellationFee, ChangeInitiator};
 use serde::{Deserialize, Serialize};
 use shared::{AppError, DomainError};
 
 use crate::{
     auth::{AuthUser, TenantContext},
+    customer_packages,
     error::{ApiError, ApiResult},
+    routes::checkout::BookingCharge,
     state::AppState,
 };
 
@@ -45,9 +51,29 @@ pub struct BookingResponse {
     pub scheduled_end: String,
     pub price_cents: i64,
     pub price_display: String,
+    pub price_breakdown: Option<BookingPriceBreakdown>,
     pub notes: Option<String>,
 }
 
+/// Response to a cancellation or reschedule, with any late change fee
+#[derive(Debug, Serialize)]
+pub struct BookingChangeResponse {
+    #[serde(flatten)]
+    pub booking: BookingResponse,
+    pub fee: ChangeFeeResponse,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangeFeeResponse {
+    pub fee_percent: u32,
+    pub fee_cents: i64,
+    pub fee_display: String,
+    /// Whether the fee was collected; it can't be when the customer has no
+    /// held payment or saved payment method, or the charge was declined. A
+    /// walk paid for with a package credit keeps the credit instead.
+    pub charged: bool,
+}
+
 /// Enriched booking response with resolved names for admin list view
 #[derive(Debug, Serialize)]
 pub struct BookingListItem {
@@ -142,6 +168,18 @@ pub async fn create_booking(
         return Err(ApiError::from(AppError::Forbidden));
     }
 
+    // Price the booking with the walker's service area adjustment
+    let matching_areas = ServiceAreaRepository::find_walker_areas_for_location(
+        &tenant.pool,
+        tenant.org_id,
+        walker_id,
+        location.latitude,
+        location.longitude,
+    )
+    .await?;
+    let price_breakdown =
+        BookingPriceBreakdown::calculate(service.base_price_cents, &matching_areas);
+
     // Calculate end time
     let end_time = start_time + chrono::Duration::minutes(service.duration_minutes as i64);
 
@@ -156,7 +194,8 @@ pub async fn create_booking(
             location_id,
             scheduled_start: start_time,
             scheduled_end: end_time,
-            price_cents: service.base_price_cents,
+            price_cents: price_breakdown.total_cents,
+            price_breakdown: Some(price_breakdown),
             notes: req.notes,
             recurring_series_id: None,
             occurrence_number: None,
@@ -171,6 +210,9 @@ pub async fn create_booking(
         }
     })?;
 
+    // Covered by the customer's service package, if they have credits left
+    customer_packages::redeem_for_booking(&tenant, &booking).await?;
+
     Ok(Json(BookingResponse {
         id: booking.id.to_string(),
         customer_id: booking.customer_id.to_string(),
@@ -182,6 +224,7 @@ pub async fn create_booking(
         scheduled_end: booking.scheduled_end.to_rfc3339(),
         price_cents: booking.price_cents,
         price_display: format!("${:.2}", booking.price_dollars()),
+        price_breakdown: booking.price_breakdown.map(|b| b.0),
         notes: booking.notes,
     }))
 }
@@ -216,12 +259,13 @@ pub async fn get_booking(
         scheduled_end: booking.scheduled_end.to_rfc3339(),
         price_cents: booking.price_cents,
         price_display: format!("${:.2}", booking.price_dollars()),
+        price_breakdown: booking.price_breakdown.map(|b| b.0),
         notes: booking.notes,
     }))
 }
 
 pub async fn confirm_booking(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     tenant: TenantContext,
     auth: AuthUser,
     Path(id): Path<String>,
@@ -245,6 +289,34 @@ pub async fn confirm_booking(
         )));
     }
 
+    // Hold the customer's saved payment method for the walk. A decline stops
+    // the confirmation so the walker isn't committed to an unpaid booking.
+    // Walks paid for with a package credit need no hold.
+    let existing =
+        TransactionRepository::get_by_booking(&tenant.pool, tenant.org_id, booking_id).await?;
+    if !existing.is_some_and(|t| t.status.is_live())
+        && !customer_packages::is_credit_paid(&tenant, booking_id).await?
+    {
+        if let Some(method) = CustomerPaymentMethodRepository::get_default(
+            &tenant.pool,
+            tenant.org_id,
+            booking.customer_id,
+        )
+        .await?
+        {
+            super::checkout::start_booking_payment(
+                &state,
+                &tenant,
+                &booking,
+                BookingCharge::Walk { tip_cents: 0 },
+                Some(&method),
+                true,
+                None,
+            )
+            .await?;
+        }
+    }
+
     let updated = BookingRepository::confirm(&tenant.pool, tenant.org_id, booking_id)
         .await?
         .ok_or_else(|| ApiError::from(DomainError::BookingNotFound(id)))?;
@@ -260,11 +332,14 @@ pub async fn confirm_booking(
         scheduled_end: updated.scheduled_end.to_rfc3339(),
         price_cents: updated.price_cents,
         price_display: format!("${:.2}", updated.price_dollars()),
+        price_breakdown: updated.price_breakdown.map(|b| b.0),
         notes: updated.notes,
     }))
 }
 
-pub async fn cancel_booking(
+/// POST /bookings/:id/start - Walker starts the walk; GPS updates are
+/// recorded as the booking's trail until it is completed
+pub async fn start_booking(
     State(_state): State<AppState>,
     tenant: TenantContext,
     auth: AuthUser,
@@ -278,18 +353,18 @@ pub async fn cancel_booking(
         .await?
         .ok_or_else(|| ApiError::from(DomainError::BookingNotFound(id.clone())))?;
 
-    // Customer or walker can cancel
-    if booking.customer_id != auth.user_id && booking.walker_id != auth.user_id {
+    // Only walker can start
+    if booking.walker_id != auth.user_id {
         return Err(ApiError::from(AppError::Forbidden));
     }
 
-    if !booking.can_cancel() {
+    if !booking.can_start() {
         return Err(ApiError::from(DomainError::InvalidStateTransition(
             booking.status.to_string(),
         )));
     }
 
-    let updated = BookingRepository::cancel(&tenant.pool, tenant.org_id, booking_id)
+    let updated = BookingRepository::start(&tenant.pool, tenant.org_id, booking_id)
         .await?
         .ok_or_else(|| ApiError::from(DomainError::BookingNotFound(id)))?;
 
@@ -304,18 +379,103 @@ pub async fn cancel_booking(
         scheduled_end: updated.scheduled_end.to_rfc3339(),
         price_cents: updated.price_cents,
         price_display: format!("${:.2}", updated.price_dollars()),
+        price_breakdown: updated.price_breakdown.map(|b| b.0),
         notes: updated.notes,
     }))
 }
 
+pub async fn cancel_booking(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> ApiResult<Json<BookingChangeResponse>> {
+    let booking_id = id
+        .parse()
+        .map_err(|_| ApiError::from(AppError::Validation("Invalid booking ID".to_string())))?;
+
+    let booking = BookingRepository::find_by_id(&tenant.pool, tenant.org_id, booking_id)
+        .await?
+        .ok_or_else(|| ApiError::from(DomainError::BookingNotFound(id.clone())))?;
+
+    // Customer or walker can cancel
+    if booking.customer_id != auth.user_id && booking.walker_id != auth.user_id {
+        return Err(ApiError::from(AppError::Forbidden));
+    }
+
+    if !booking.can_cancel() {
+        return Err(ApiError::from(DomainError::InvalidStateTransition(
+            booking.status.to_string(),
+        )));
+    }
+
+    let initiator = if auth.user_id == booking.walker_id && auth.user_id != booking.customer_id {
+        ChangeInitiator::Walker
+    } else {
+        ChangeInitiator::Customer
+    };
+    let fee = super::cancellation_policies::effective_policy(&tenant, booking.service_id)
+        .await?
+        .evaluate(
+            booking.price_cents,
+            booking.scheduled_start,
+            Utc::now(),
+            BookingChange::Cancel,
+            initiator,
+        );
+
+    let updated = BookingRepository::cancel(&tenant.pool, tenant.org_id, booking_id)
+        .await?
+        .ok_or_else(|| ApiError::from(DomainError::BookingNotFound(id)))?;
+
+    // A package credit comes back if the cancellation is free, and is kept
+    // as the fee otherwise
+    let charged =
+        if customer_packages::release_for_cancellation(&tenant, &updated, fee.is_free()).await? {
+            !fee.is_free()
+        } else {
+            // The booking is cancelled either way; a hold that wasn't released
+            // is retried by the hold settlement job
+            match charge_change_fee(&state, &tenant, &updated, &fee, BookingChange::Cancel).await {
+                Ok(charged) => charged,
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to settle payment for booking {}: {}",
+                        updated.id,
+                        e.0
+                    );
+                    false
+                }
+            }
+        };
+
+    Ok(Json(BookingChangeResponse {
+        booking: BookingResponse {
+            id: updated.id.to_string(),
+            customer_id: updated.customer_id.to_string(),
+            walker_id: updated.walker_id.to_string(),
+            service_id: updated.service_id.to_string(),
+            location_id: updated.location_id.to_string(),
+            status: updated.status.to_string(),
+            scheduled_start: updated.scheduled_start.to_rfc3339(),
+            scheduled_end: updated.scheduled_end.to_rfc3339(),
+            price_cents: updated.price_cents,
+            price_display: format!("${:.2}", updated.price_dollars()),
+            price_breakdown: updated.price_breakdown.map(|b| b.0),
+            notes: updated.notes,
+        },
+        fee: change_fee_response(&fee, charged),
+    }))
+}
+
 /// Reschedule a booking to a new time
 pub async fn reschedule_booking(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     tenant: TenantContext,
     auth: AuthUser,
     Path(id): Path<String>,
     Json(req): Json<RescheduleBookingRequest>,
-) -> ApiResult<Json<BookingResponse>> {
+) -> ApiResult<Json<BookingChangeResponse>> {
     let booking_id = id
         .parse()
         .map_err(|_| ApiError::from(AppError::Validation("Invalid booking ID".to_string())))?;
@@ -345,27 +505,118 @@ pub async fn reschedule_booking(
     let duration = booking.scheduled_end - booking.scheduled_start;
     let new_end = new_start + duration;
 
+    // Late fees are based on how close the original start time is
+    let fee = super::cancellation_policies::effective_policy(&tenant, booking.service_id)
+        .await?
+        .evaluate(
+            booking.price_cents,
+            booking.scheduled_start,
+            Utc::now(),
+            BookingChange::Reschedule,
+            ChangeInitiator::Customer,
+        );
+
     // Update the booking
     let updated =
         BookingRepository::reschedule(&tenant.pool, tenant.org_id, booking_id, new_start, new_end)
             .await?
             .ok_or_else(|| ApiError::from(DomainError::BookingNotFound(id)))?;
 
-    Ok(Json(BookingResponse {
-        id: updated.id.to_string(),
-        customer_id: updated.customer_id.to_string(),
-        walker_id: updated.walker_id.to_string(),
-        service_id: updated.service_id.to_string(),
-        location_id: updated.location_id.to_string(),
-        status: updated.status.to_string(),
-        scheduled_start: updated.scheduled_start.to_rfc3339(),
-        scheduled_end: updated.scheduled_end.to_rfc3339(),
-        price_cents: updated.price_cents,
-        price_display: format!("${:.2}", updated.price_dollars()),
-        notes: updated.notes,
+    let charged =
+        match charge_change_fee(&state, &tenant, &updated, &fee, BookingChange::Reschedule).await {
+            Ok(charged) => charged,
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to charge reschedule fee for booking {}: {}",
+                    updated.id,
+                    e.0
+                );
+                false
+            }
+        };
+
+    Ok(Json(BookingChangeResponse {
+        booking: BookingResponse {
+            id: updated.id.to_string(),
+            customer_id: updated.customer_id.to_string(),
+            walker_id: updated.walker_id.to_string(),
+            service_id: updated.service_id.to_string(),
+            location_id: updated.location_id.to_string(),
+            status: updated.status.to_string(),
+            scheduled_start: updated.scheduled_start.to_rfc3339(),
+            scheduled_end: updated.scheduled_end.to_rfc3339(),
+            price_cents: updated.price_cents,
+            price_display: format!("${:.2}", updated.price_dollars()),
+            price_breakdown: updated.price_breakdown.map(|b| b.0),
+            notes: updated.notes,
+        },
+        fee: change_fee_response(&fee, charged),
     }))
 }
 
+/// Collect a late change fee, returning whether it was charged.
+///
+/// A cancellation fee comes out of the booking's held payment when there is
+/// one, and any remaining hold is released. Otherwise the fee is charged to
+/// the customer's default payment method.
+async fn charge_change_fee(
+    state: &AppState,
+    tenant: &TenantContext,
+    booking: &Booking,
+    fee: &CancellationFee,
+    change: BookingChange,
+) -> ApiResult<bool> {
+    if fee.is_free() {
+        if change == BookingChange::Cancel {
+            crate::payments::release_booking_payment(state, tenant, booking).await?;
+        }
+        return Ok(false);
+    }
+
+    let fee_cents = i32::try_from(fee.fee_cents)
+        .map_err(|_| ApiError::from(AppError::Validation("Fee too large".to_string())))?;
+
+    if change == BookingChange::Cancel
+        && crate::payments::capture_cancellation_fee(state, tenant, booking, fee_cents)
+            .await?
+            .is_some()
+    {
+        return Ok(true);
+    }
+
+    let Some(method) = CustomerPaymentMethodRepository::get_default(
+        &tenant.pool,
+        tenant.org_id,
+        booking.customer_id,
+    )
+    .await?
+    else {
+        return Ok(false);
+    };
+
+    let (transaction, _, _) = super::checkout::start_booking_payment(
+        state,
+        tenant,
+        booking,
+        BookingCharge::ChangeFee { fee_cents, change },
+        Some(&method),
+        true,
+        None,
+    )
+    .await?;
+
+    Ok(transaction.status == TransactionStatus::Succeeded)
+}
+
+fn change_fee_response(fee: &CancellationFee, charged: bool) -> ChangeFeeResponse {
+    ChangeFeeResponse {
+        fee_percent: fee.fee_percent,
+        fee_cents: fee.fee_cents,
+        fee_display: format!("${:.2}", fee.fee_cents as f64 / 100.0),
+        charged,
+    }
+}
+
 /// List all bookings (admin/owner only)
 pub async fn list_bookings(
     State(_state): State<AppState>,
@@ -576,7 +827,7 @@ pub async fn list_walker_bookings(
 
 /// Complete a booking (admin/owner only)
 pub async fn complete_booking(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     tenant: TenantContext,
     auth: AuthUser,
     Path(id): Path<String>,
@@ -613,6 +864,16 @@ pub async fn complete_booking(
         .await?
         .ok_or_else(|| ApiError::from(DomainError::BookingNotFound(id)))?;
 
+    // A failed capture is recorded on the transaction and retried by the hold
+    // settlement job
+    if let Err(e) = crate::payments::capture_booking_payment(&state, &tenant, &updated).await {
+        tracing::warn!(
+            "Failed to capture payment for booking {}: {}",
+            updated.id,
+            e.0
+        );
+    }
+
     Ok(Json(BookingResponse {
         id: updated.id.to_string(),
         customer_id: updated.customer_id.to_string(),
@@ -624,6 +885,7 @@ pub async fn complete_booking(
         scheduled_end: updated.scheduled_end.to_rfc3339(),
         price_cents: updated.price_cents,
         price_display: format!("${:.2}", updated.price_dollars()),
+        price_breakdown: updated.price_breakdown.map(|b| b.0),
         notes: updated.notes,
     }))
 }
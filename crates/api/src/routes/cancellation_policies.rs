@@ -0,0 +1,186 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use db::models::{CancellationPolicyTier, UpsertCancellationPolicy};
+use db::{CancellationPolicyRepository, ServiceRepository};
+use domain::{CancellationPolicy, CancellationTier};
+use serde::{Deserialize, Serialize};
+use shared::types::ServiceId;
+use shared::{AppError, DomainError};
+use uuid::Uuid;
+
+use crate::{
+    auth::{AuthUser, TenantContext},
+    error::{ApiError, ApiResult},
+    state::AppState,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PolicyTier {
+    pub within_minutes: i64,
+    pub fee_percent: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CancellationPolicyResponse {
+    pub id: String,
+    /// `None` for the organization default
+    pub service_id: Option<String>,
+    pub tiers: Vec<PolicyTier>,
+    pub walker_cancels_free: bool,
+    pub charge_reschedules: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpsertCancellationPolicyRequest {
+    /// Omit to set the organization default
+    pub service_id: Option<String>,
+    pub tiers: Vec<PolicyTier>,
+    #[serde(default = "default_true")]
+    pub walker_cancels_free: bool,
+    #[serde(default = "default_true")]
+    pub charge_reschedules: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// GET /admin/cancellation-policies - The default and per-service policies
+pub async fn list_cancellation_policies(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+) -> ApiResult<Json<Vec<CancellationPolicyResponse>>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let policies = CancellationPolicyRepository::list(&tenant.pool, tenant.org_id).await?;
+
+    Ok(Json(policies.into_iter().map(policy_response).collect()))
+}
+
+/// PUT /admin/cancellation-policies - Set the default policy, or a service's
+/// policy when `service_id` is given
+pub async fn upsert_cancellation_policy(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Json(req): Json<UpsertCancellationPolicyRequest>,
+) -> ApiResult<Json<CancellationPolicyResponse>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let service_id = match &req.service_id {
+        Some(id) => {
+            let service_id: ServiceId = id.parse().map_err(|_| {
+                ApiError::from(AppError::Validation("Invalid service ID".to_string()))
+            })?;
+            ServiceRepository::find_by_id(&tenant.pool, tenant.org_id, service_id)
+                .await?
+                .ok_or_else(|| ApiError::from(DomainError::ServiceNotFound(id.clone())))?;
+            Some(service_id)
+        }
+        None => None,
+    };
+
+    let tiers: Vec<CancellationPolicyTier> = req
+        .tiers
+        .iter()
+        .map(|tier| CancellationPolicyTier {
+            within_minutes: tier.within_minutes,
+            fee_percent: tier.fee_percent,
+        })
+        .collect();
+
+    let policy = CancellationPolicy {
+        tiers: tiers.iter().map(domain_tier).collect(),
+        walker_cancels_free: req.walker_cancels_free,
+        charge_reschedules: req.charge_reschedules,
+    };
+    policy
+        .validate()
+        .map_err(|e| ApiError::from(AppError::Validation(e)))?;
+
+    let saved = CancellationPolicyRepository::upsert(
+        &tenant.pool,
+        tenant.org_id,
+        UpsertCancellationPolicy {
+            service_id,
+            tiers,
+            walker_cancels_free: req.walker_cancels_free,
+            charge_reschedules: req.charge_reschedules,
+        },
+    )
+    .await?;
+
+    Ok(Json(policy_response(saved)))
+}
+
+/// DELETE /admin/cancellation-policies/:id - Remove a policy; bookings fall
+/// back to the default policy, or to free changes if there is none
+pub async fn delete_cancellation_policy(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> ApiResult<StatusCode> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let policy_id: Uuid = id
+        .parse()
+        .map_err(|_| ApiError::from(AppError::Validation("Invalid policy ID".to_string())))?;
+
+    if !CancellationPolicyRepository::delete(&tenant.pool, tenant.org_id, policy_id).await? {
+        return Err(ApiError::from(AppError::NotFound(
+            "Cancellation policy not found".to_string(),
+        )));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The policy that applies to bookings of a service. Without a configured
+/// policy, cancelling and rescheduling are free.
+pub async fn effective_policy(
+    tenant: &TenantContext,
+    service_id: ServiceId,
+) -> ApiResult<CancellationPolicy> {
+    let policy =
+        CancellationPolicyRepository::get_for_service(&tenant.pool, tenant.org_id, service_id)
+            .await?;
+
+    Ok(match policy {
+        Some(policy) => CancellationPolicy {
+            tiers: policy.tiers.iter().map(domain_tier).collect(),
+            walker_cancels_free: policy.walker_cancels_free,
+            charge_reschedules: policy.charge_reschedules,
+        },
+        None => CancellationPolicy::free(),
+    })
+}
+
+fn domain_tier(tier: &CancellationPolicyTier) -> CancellationTier {
+    CancellationTier {
+        within_minutes: tier.within_minutes,
+        fee_percent: tier.fee_percent,
+    }
+}
+
+fn policy_response(policy: db::models::CancellationPolicy) -> CancellationPolicyResponse {
+    CancellationPolicyResponse {
+        id: policy.id.to_string(),
+        service_id: policy.service_id.map(|id| id.to_string()),
+        tiers: policy
+            .tiers
+            .0
+            .into_iter()
+            .map(|tier| PolicyTier {
+                within_minutes: tier.within_minutes,
+                fee_percent: tier.fee_percent,
+            })
+            .collect(),
+        walker_cancels_free: policy.walker_cancels_free,
+        charge_reschedules: policy.charge_reschedules,
+    }
+}
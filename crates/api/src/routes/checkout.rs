@@ -1,21 +1,40 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    http::HeaderMap,
     Json,
 };
+use chrono::{DateTime, Duration, Utc};
 use db::{
-    models::{CreateTransaction, PaymentProviderType, TransactionFeeBreakdown, TransactionStatus},
-    PaymentProviderRepository, SubscriptionRepository, TransactionRepository,
+    models::{
+        Booking, BookingPriceBreakdown, BookingStatus, CreateTransaction, CustomerPaymentMethod,
+        PaymentProvider, PaymentProviderType, Refund, RefundSource, Transaction,
+        TransactionFeeBreakdown, TransactionStatus, UpdateTransaction,
+    },
+    BookingRepository, CustomerPaymentMethodRepository, LocationRepository,
+    PaymentProviderRepository, RefundRepository, ServiceAreaRepository, ServiceRepository,
+    SubscriptionRepository, TransactionRepository,
 };
+use domain::BookingChange;
+use integrations::gateway::{GatewayPayment, PaymentGateway};
+use integrations::tax::OfflineTaxTable;
 use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use shared::types::{BookingId, UserId};
-use shared::AppError;
+use shared::types::{BookingId, Money};
+use shared::{AppError, DomainError};
 use uuid::Uuid;
 
 use crate::{
     auth::{AuthUser, TenantContext},
+    customer_packages,
     error::{ApiError, ApiResult},
+    payments::{
+        charge_transaction, external_payment_id, fee_breakdown, issue_refund,
+        organization_currency, processing_fee, record_payment, transaction_gateway,
+        RefundInitiator,
+    },
     state::AppState,
+    tax,
 };
 
 /// Checkout session response
@@ -27,6 +46,7 @@ pub struct CheckoutResponse {
     pub customer_fee_cents: i32,
     pub tax_cents: i32,
     pub total_cents: i32,
+    pub currency: String,
     pub provider_type: String,
     /// Client secret for Stripe PaymentIntent (if using Stripe)
     pub client_secret: Option<String>,
@@ -36,37 +56,168 @@ pub struct CheckoutResponse {
 
 #[derive(Debug, Deserialize)]
 pub struct CreateCheckoutRequest {
-    /// The booking ID this payment is for
+    /// The booking ID this payment is for. The amount, walker and tax
+    /// location are all taken from the booking.
     pub booking_id: String,
     /// Payment method ID (from customer_payment_methods)
     pub payment_method_id: Option<String>,
-    /// Subtotal in cents (service price)
-    pub subtotal_cents: i32,
     /// Optional tip in cents
     pub tip_cents: Option<i32>,
-    /// Walker/provider user ID to receive payment
-    pub provider_user_id: String,
-    /// Customer's address state for tax calculation
-    pub customer_state: Option<String>,
-    /// Customer's address zip for tax calculation
-    pub customer_zip: Option<String>,
 }
 
-/// Create a checkout session / payment intent
+/// Create a checkout session / payment intent for a booking
+///
+/// Clients should send an `X-Idempotency-Key` header and reuse it when
+/// retrying, so a retry after a lost response can't charge twice.
 pub async fn create_checkout(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     auth_user: AuthUser,
     tenant: TenantContext,
+    headers: HeaderMap,
     Json(req): Json<CreateCheckoutRequest>,
 ) -> ApiResult<Json<CheckoutResponse>> {
-    let booking_id: Uuid = req
+    let idempotency_key = headers
+        .get("X-Idempotency-Key")
+        .and_then(|v| v.to_str().ok())
+        .filter(|key| !key.is_empty());
+
+    let booking_id: BookingId = req
         .booking_id
         .parse()
         .map_err(|_| ApiError::from(AppError::Validation("Invalid booking ID".to_string())))?;
 
-    let provider_user_id: Uuid = req.provider_user_id.parse().map_err(|_| {
-        ApiError::from(AppError::Validation("Invalid provider user ID".to_string()))
-    })?;
+    let booking = BookingRepository::find_by_id(&tenant.pool, tenant.org_id, booking_id)
+        .await?
+        .ok_or_else(|| ApiError::from(DomainError::BookingNotFound(req.booking_id.clone())))?;
+
+    // Only the booking's customer can pay for it
+    if booking.customer_id != auth_user.user_id {
+        return Err(ApiError::from(DomainError::BookingNotFound(req.booking_id)));
+    }
+
+    if matches!(
+        booking.status,
+        BookingStatus::Cancelled | BookingStatus::NoShow
+    ) {
+        return Err(ApiError::from(AppError::Validation(format!(
+            "Cannot pay for a {} booking",
+            booking.status
+        ))));
+    }
+
+    if customer_packages::is_credit_paid(&tenant, booking_id).await? {
+        return Err(ApiError::from(AppError::Validation(
+            "This walk is paid for with package credit".to_string(),
+        )));
+    }
+
+    // One live payment per booking; failed or refunded payments can be retried
+    if let Some(existing) =
+        TransactionRepository::get_by_booking(&tenant.pool, tenant.org_id, booking_id).await?
+    {
+        if existing.status.is_live() {
+            return Err(ApiError::from(DomainError::PaymentAlreadyExists(
+                existing.status.to_string(),
+            )));
+        }
+    }
+
+    let tip_cents = req.tip_cents.unwrap_or(0);
+    if tip_cents < 0 {
+        return Err(ApiError::from(AppError::Validation(
+            "Tip cannot be negative".to_string(),
+        )));
+    }
+
+    // Get payment method details if provided
+    let payment_method = match &req.payment_method_id {
+        Some(pm_id) => Some(find_payment_method(&tenant, &auth_user, pm_id).await?),
+        None => None,
+    };
+
+    let (transaction, payment, provider) = start_booking_payment(
+        &state,
+        &tenant,
+        &booking,
+        BookingCharge::Walk { tip_cents },
+        payment_method.as_ref(),
+        false,
+        idempotency_key,
+    )
+    .await?;
+
+    Ok(Json(CheckoutResponse {
+        transaction_id: transaction.id.to_string(),
+        status: format!("{:?}", transaction.status).to_lowercase(),
+        subtotal_cents: transaction.subtotal_cents,
+        customer_fee_cents: transaction.customer_fee_cents,
+        tax_cents: transaction.tax_cents,
+        total_cents: transaction.total_cents,
+        currency: transaction.currency.to_string(),
+        provider_type: format!("{:?}", provider.provider_type).to_lowercase(),
+        client_secret: payment.client_secret,
+        payment_id: Some(payment.id),
+    }))
+}
+
+/// `charge` recorded in the metadata of a tip charged after the walk
+pub const TIP_CHARGE: &str = "tip";
+
+/// What a booking payment is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookingCharge {
+    /// The walk itself, plus an optional tip
+    Walk { tip_cents: i32 },
+    /// A late cancellation or reschedule fee
+    ChangeFee {
+        fee_cents: i32,
+        change: BookingChange,
+    },
+    /// A tip after the walk, charged on its own and linked to the walk's
+    /// payment
+    Tip {
+        tip_cents: i32,
+        walk_transaction_id: Option<Uuid>,
+    },
+}
+
+/// Price a booking charge, create its transaction and start the provider
+/// payment.
+///
+/// Payment for an upcoming walk is only held until the walk is completed;
+/// anything else is captured straight away. Used by checkout, when a walker
+/// confirms a booking for a customer with a saved payment method, and to
+/// charge late change fees (`off_session`).
+///
+/// `idempotency_key` is the client's key for the request, if it sent one. It
+/// is scoped to the booking and saved on the transaction, so a retry after a
+/// lost response re-sends the original transaction's payment request and
+/// gets the original payment back rather than charging again.
+pub async fn start_booking_payment(
+    state: &AppState,
+    tenant: &TenantContext,
+    booking: &Booking,
+    charge: BookingCharge,
+    payment_method: Option<&CustomerPaymentMethod>,
+    off_session: bool,
+    idempotency_key: Option<&str>,
+) -> ApiResult<(Transaction, GatewayPayment, PaymentProvider)> {
+    let idempotency_key = idempotency_key.map(|key| format!("booking_{}_{}", booking.id, key));
+    if let Some(key) = &idempotency_key {
+        if let Some(transaction) =
+            TransactionRepository::get_by_idempotency_key(&tenant.pool, tenant.org_id, key).await?
+        {
+            return resend_payment(state, tenant, transaction).await;
+        }
+    }
+
+    let service = ServiceRepository::find_by_id(&tenant.pool, tenant.org_id, booking.service_id)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::NotFound("Service not found".to_string())))?;
+
+    let location = LocationRepository::find_by_id(&tenant.pool, tenant.org_id, booking.location_id)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::NotFound("Location not found".to_string())))?;
 
     // Get the primary payment provider for this tenant
     let provider = PaymentProviderRepository::get_primary(&tenant.pool, tenant.org_id)
@@ -77,108 +228,210 @@ pub async fn create_checkout(
             ))
         })?;
 
-    // Get the platform fee tier for this tenant
-    let fee_tier = SubscriptionRepository::get_org_fee_tier(&tenant.pool, tenant.org_id).await?;
-
-    // Calculate tax (simplified - in production use TaxJar/Avalara)
-    let tax_rate_percent =
-        if let (Some(state), Some(_zip)) = (&req.customer_state, &req.customer_zip) {
-            // Use simple state-based tax rate as fallback
-            match state.to_uppercase().as_str() {
-                "CA" => 7.25,
-                "TX" => 6.25,
-                "FL" => 6.00,
-                "NY" => 8.00,
-                "WA" => 6.50,
-                "OR" | "MT" | "NH" | "DE" => 0.0, // No sales tax
-                _ => 6.0,
-            }
-        } else {
-            0.0 // No tax if address not provided
-        };
-
-    // Calculate fee breakdown
-    let fee_breakdown = TransactionFeeBreakdown::calculate(
-        req.subtotal_cents,
-        fee_tier.customer_fee_percent.to_f64().unwrap_or(0.0),
-        fee_tier.provider_fee_percent.to_f64().unwrap_or(0.0),
-        tax_rate_percent,
-        2.9, // Stripe/Square processing fee percentage
-    );
+    // Fees are kept off the booking so they don't replace its own payment
+    let (subtotal_cents, tip_cents, booking_id, description, metadata) = match charge {
+        BookingCharge::Walk { tip_cents } => {
+            // The booking price already includes any service area adjustment
+            let subtotal_cents = i32::try_from(booking.price_cents)
+                .map_err(|_| ApiError::from(AppError::Validation("Price too large".to_string())))?;
+            (
+                subtotal_cents,
+                tip_cents,
+                Some(booking.id),
+                service.name,
+                None,
+            )
+        }
+        BookingCharge::ChangeFee { fee_cents, change } => {
+            let (label, kind) = match change {
+                BookingChange::Cancel => ("Cancellation fee", "cancellation_fee"),
+                BookingChange::Reschedule => ("Late reschedule fee", "reschedule_fee"),
+            };
+            let metadata = serde_json::json!({
+                "booking_id": booking.id.to_string(),
+                "charge": kind,
+            });
+            (
+                fee_cents,
+                0,
+                None,
+                format!("{}: {}", label, service.name),
+                Some(metadata),
+            )
+        }
+        BookingCharge::Tip {
+            tip_cents,
+            walk_transaction_id,
+        } => {
+            let metadata = serde_json::json!({
+                "booking_id": booking.id.to_string(),
+                "charge": TIP_CHARGE,
+                "walk_transaction_id": walk_transaction_id.map(|id| id.to_string()),
+            });
+            (
+                0,
+                tip_cents,
+                None,
+                format!("Tip: {}", service.name),
+                Some(metadata),
+            )
+        }
+    };
 
-    // Get payment method details if provided
-    let payment_method_id = if let Some(pm_id) = &req.payment_method_id {
-        Some(pm_id.parse::<Uuid>().map_err(|_| {
-            ApiError::from(AppError::Validation(
-                "Invalid payment method ID".to_string(),
-            ))
-        })?)
-    } else {
+    // Services are priced, and so charged, in their own currency
+    let subtotal = Money::new(i64::from(subtotal_cents), service.currency);
+    // Tax is based on where the service is performed; a tip alone is untaxed
+    let tax = if subtotal.is_zero() {
         None
+    } else {
+        Some(tax::quote(state, &location, subtotal).await?)
     };
+    let tax_rate_percent = tax.as_ref().map_or(Decimal::ZERO, |t| t.rate_percent);
+    let processing_fee = processing_fee(
+        tenant,
+        provider.provider_type,
+        payment_method,
+        subtotal.currency(),
+    )
+    .await?;
+    let fee_breakdown = fee_breakdown(tenant, subtotal, tax_rate_percent, processing_fee).await?;
+    // The processor charges on the whole payment, tip included
+    let charged = fee_breakdown
+        .total
+        .checked_add(Money::new(i64::from(tip_cents), subtotal.currency()))?;
+
+    let upcoming = matches!(
+        booking.status,
+        BookingStatus::Pending | BookingStatus::Confirmed | BookingStatus::InProgress
+    );
 
-    // Create the transaction record
+    // Create the transaction record. Tips go to the walker untaxed and fee-free.
     let transaction_input = CreateTransaction {
-        booking_id: Some(BookingId::from_uuid(booking_id)),
-        customer_user_id: auth_user.user_id,
-        provider_user_id: UserId::from_uuid(provider_user_id),
-        payment_method_id,
+        booking_id,
+        customer_user_id: booking.customer_id,
+        provider_user_id: Some(booking.walker_id),
+        payment_method_id: payment_method.map(|pm| pm.id),
         provider_id: provider.id,
-        subtotal_cents: req.subtotal_cents,
-        tip_cents: req.tip_cents.unwrap_or(0),
-        customer_fee_cents: fee_breakdown.customer_fee_cents,
-        provider_fee_cents: fee_breakdown.provider_fee_cents,
-        platform_fee_cents: fee_breakdown.platform_fee_cents,
-        tax_cents: fee_breakdown.tax_cents,
-        processing_fee_cents: fee_breakdown.processing_fee_cents,
-        total_cents: fee_breakdown.total_cents,
-        provider_payout_cents: fee_breakdown.provider_payout_cents,
-        currency: "USD".to_string(),
-        tax_rate_percent: None,
-        tax_jurisdiction: None,
-        description: None,
-        metadata: None,
+        subtotal_cents,
+        tip_cents,
+        customer_fee_cents: cents(fee_breakdown.customer_fee)?,
+        provider_fee_cents: cents(fee_breakdown.provider_fee)?,
+        platform_fee_cents: cents(fee_breakdown.platform_fee)?,
+        tax_cents: cents(fee_breakdown.tax)?,
+        processing_fee_cents: cents(processing_fee.apply(charged)?)?,
+        total_cents: cents(charged)?,
+        provider_payout_cents: cents(fee_breakdown.provider_payout)? + tip_cents,
+        currency: fee_breakdown.total.currency(),
+        tax_rate_percent: tax.as_ref().map(|t| t.rate_percent),
+        tax_jurisdiction: tax.as_ref().map(|t| t.jurisdiction.clone()),
+        tax_calculation_id: tax.and_then(|t| t.calculation_id),
+        description: Some(description),
+        metadata,
+        // Without a client key the transaction ID is used, which only
+        // protects against retries within this request
+        idempotency_key,
+        manual_capture: upcoming && matches!(charge, BookingCharge::Walk { .. }),
+        off_session,
     };
 
-    let transaction =
-        TransactionRepository::create(&tenant.pool, tenant.org_id, transaction_input).await?;
-
-    // Create payment with the provider
-    let (client_secret, payment_id) = match provider.provider_type {
-        PaymentProviderType::Stripe => {
-            let secret =
-                create_stripe_payment_intent(&provider, &transaction, &fee_breakdown).await?;
-            (Some(secret), None)
-        }
-        PaymentProviderType::Square => {
-            let id = create_square_payment(&provider, &transaction, &fee_breakdown).await?;
-            (None, Some(id))
+    // The unique index settles concurrent checkouts for the same booking
+    let transaction = match TransactionRepository::create(
+        &tenant.pool,
+        tenant.org_id,
+        transaction_input,
+    )
+    .await?
+    {
+        Some(transaction) => transaction,
+        None => {
+            let status =
+                TransactionRepository::get_by_booking(&tenant.pool, tenant.org_id, booking.id)
+                    .await?
+                    .map_or_else(|| "pending".to_string(), |t| t.status.to_string());
+            return Err(ApiError::from(DomainError::PaymentAlreadyExists(status)));
         }
-        PaymentProviderType::Platform => {
-            // Platform default - use Stripe
-            let secret =
-                create_stripe_payment_intent(&provider, &transaction, &fee_breakdown).await?;
-            (Some(secret), None)
+    };
+
+    let gateway = state.payment_gateways.for_provider(&provider)?;
+    send_payment(
+        state,
+        tenant,
+        provider,
+        gateway.as_ref(),
+        transaction,
+        payment_method,
+    )
+    .await
+}
+
+/// Send the payment request for a transaction whose idempotency key was
+/// reused, with the payment method it was first sent with
+async fn resend_payment(
+    state: &AppState,
+    tenant: &TenantContext,
+    transaction: Transaction,
+) -> ApiResult<(Transaction, GatewayPayment, PaymentProvider)> {
+    let (provider, gateway) = transaction_gateway(state, tenant, &transaction).await?;
+    let payment_method = match transaction.payment_method_id {
+        Some(id) => Some(
+            CustomerPaymentMethodRepository::find_by_id(&tenant.pool, tenant.org_id, id)
+                .await?
+                .ok_or_else(|| {
+                    ApiError::from(AppError::NotFound("Payment method not found".to_string()))
+                })?,
+        ),
+        None => None,
+    };
+
+    send_payment(
+        state,
+        tenant,
+        provider,
+        gateway.as_ref(),
+        transaction,
+        payment_method.as_ref(),
+    )
+    .await
+}
+
+/// Charge a transaction and record the provider's payment on it. A failed
+/// request is recorded on the transaction.
+async fn send_payment(
+    state: &AppState,
+    tenant: &TenantContext,
+    provider: PaymentProvider,
+    gateway: &dyn PaymentGateway,
+    transaction: Transaction,
+    payment_method: Option<&CustomerPaymentMethod>,
+) -> ApiResult<(Transaction, GatewayPayment, PaymentProvider)> {
+    let payment = match charge_transaction(gateway, &transaction, payment_method).await {
+        Ok(payment) => payment,
+        Err(e) => {
+            TransactionRepository::update(
+                &tenant.pool,
+                transaction.id,
+                UpdateTransaction {
+                    status: Some(TransactionStatus::Failed),
+                    failure_message: Some(e.0.to_string()),
+                    ..Default::default()
+                },
+            )
+            .await?;
+            return Err(e);
         }
     };
 
-    // Update transaction with external payment ID
-    let external_id = client_secret.clone().or(payment_id.clone());
-    if let Some(ext_id) = &external_id {
-        TransactionRepository::update_external_id(&tenant.pool, transaction.id, ext_id).await?;
-    }
+    let transaction = record_payment(
+        state,
+        tenant,
+        &transaction,
+        provider.provider_type,
+        gateway,
+        &payment,
+    )
+    .await?;
 
-    Ok(Json(CheckoutResponse {
-        transaction_id: transaction.id.to_string(),
-        status: format!("{:?}", transaction.status).to_lowercase(),
-        subtotal_cents: transaction.subtotal_cents,
-        customer_fee_cents: transaction.customer_fee_cents,
-        tax_cents: transaction.tax_cents,
-        total_cents: transaction.total_cents,
-        provider_type: format!("{:?}", provider.provider_type).to_lowercase(),
-        client_secret,
-        payment_id,
-    }))
+    Ok((transaction, payment, provider))
 }
 
 /// Get checkout/transaction details
@@ -198,7 +451,7 @@ pub async fn get_checkout(
 
     // Verify user is either customer or provider
     if transaction.customer_user_id != auth_user.user_id
-        && transaction.provider_user_id != auth_user.user_id
+        && transaction.provider_user_id != Some(auth_user.user_id)
     {
         return Err(ApiError::from(AppError::NotFound(
             "Transaction not found".to_string(),
@@ -216,6 +469,7 @@ pub async fn get_checkout(
         customer_fee_cents: transaction.customer_fee_cents,
         tax_cents: transaction.tax_cents,
         total_cents: transaction.total_cents,
+        currency: transaction.currency.to_string(),
         provider_type: provider
             .map(|p| format!("{:?}", p.provider_type).to_lowercase())
             .unwrap_or_else(|| "unknown".to_string()),
@@ -231,6 +485,7 @@ pub struct TransactionListItem {
     pub status: String,
     pub subtotal_cents: i32,
     pub total_cents: i32,
+    pub currency: String,
     pub created_at: String,
     pub is_customer: bool,
 }
@@ -253,6 +508,7 @@ pub async fn list_transactions(
             status: format!("{:?}", t.status).to_lowercase(),
             subtotal_cents: t.subtotal_cents,
             total_cents: t.total_cents,
+            currency: t.currency.to_string(),
             created_at: t.created_at.to_rfc3339(),
             is_customer: t.customer_user_id == auth_user.user_id,
         })
@@ -261,6 +517,56 @@ pub async fn list_transactions(
     Ok(Json(response))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct TransactionSummaryQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TransactionSummaryResponse {
+    pub currency: String,
+    pub transaction_count: i64,
+    pub successful_count: i64,
+    pub failed_count: i64,
+    pub total_volume_cents: i64,
+    pub total_fees_cents: i64,
+    pub net_earnings_cents: i64,
+    pub refunded_cents: i64,
+}
+
+/// Transaction totals for a period, one entry per currency (admin/owner
+/// only). Defaults to the last 30 days.
+pub async fn get_transaction_summary(
+    State(_state): State<AppState>,
+    auth_user: AuthUser,
+    tenant: TenantContext,
+    Query(query): Query<TransactionSummaryQuery>,
+) -> ApiResult<Json<Vec<TransactionSummaryResponse>>> {
+    tenant.require_manager(auth_user.user_id).await?;
+
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - Duration::days(30));
+    let summaries =
+        TransactionRepository::get_summary(&tenant.pool, tenant.org_id, from, to).await?;
+
+    Ok(Json(
+        summaries
+            .into_iter()
+            .map(|s| TransactionSummaryResponse {
+                currency: s.currency.to_string(),
+                transaction_count: s.transaction_count,
+                successful_count: s.successful_count,
+                failed_count: s.failed_count,
+                total_volume_cents: s.total_volume_cents,
+                total_fees_cents: s.total_fees_cents,
+                net_earnings_cents: s.net_earnings_cents,
+                refunded_cents: s.refunded_cents,
+            })
+            .collect(),
+    ))
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ConfirmPaymentRequest {
     /// Payment method ID to use (for new payments)
@@ -268,8 +574,10 @@ pub struct ConfirmPaymentRequest {
 }
 
 /// Confirm a payment (after client-side confirmation)
+///
+/// Syncs the transaction with the payment's current state at the provider.
 pub async fn confirm_payment(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     auth_user: AuthUser,
     tenant: TenantContext,
     Path(id): Path<String>,
@@ -290,56 +598,114 @@ pub async fn confirm_payment(
         )));
     }
 
-    // Update status to processing
-    TransactionRepository::update_status(
-        &tenant.pool,
-        transaction.id,
-        TransactionStatus::Processing,
-    )
-    .await?;
-
     let provider =
         PaymentProviderRepository::get_by_id(&tenant.pool, tenant.org_id, transaction.provider_id)
-            .await?;
+            .await?
+            .ok_or_else(|| {
+                ApiError::from(AppError::Internal("Payment provider not found".to_string()))
+            })?;
+
+    let payment_id = external_payment_id(&transaction)
+        .ok_or_else(|| ApiError::from(AppError::Validation("No payment to confirm".to_string())))?
+        .to_string();
+
+    let gateway = state.payment_gateways.for_provider(&provider)?;
+    let payment = gateway.payment_status(&payment_id).await?;
+
+    // Refunds and disputes are driven by webhooks; only sync unsettled payments
+    let transaction = if matches!(
+        transaction.status,
+        TransactionStatus::Pending | TransactionStatus::Processing | TransactionStatus::Authorized
+    ) {
+        record_payment(
+            &state,
+            &tenant,
+            &transaction,
+            provider.provider_type,
+            gateway.as_ref(),
+            &payment,
+        )
+        .await?
+    } else {
+        transaction
+    };
 
     Ok(Json(CheckoutResponse {
         transaction_id: transaction.id.to_string(),
-        status: "processing".to_string(),
+        status: format!("{:?}", transaction.status).to_lowercase(),
         subtotal_cents: transaction.subtotal_cents,
         customer_fee_cents: transaction.customer_fee_cents,
         tax_cents: transaction.tax_cents,
         total_cents: transaction.total_cents,
-        provider_type: provider
-            .map(|p| format!("{:?}", p.provider_type).to_lowercase())
-            .unwrap_or_else(|| "unknown".to_string()),
+        currency: transaction.currency.to_string(),
+        provider_type: format!("{:?}", provider.provider_type).to_lowercase(),
         client_secret: None,
-        payment_id: transaction.external_payment_id,
+        payment_id: Some(payment_id),
     }))
 }
 
 #[derive(Debug, Deserialize)]
 pub struct RefundRequest {
-    /// Amount to refund in cents (if partial refund)
+    /// Amount to refund in cents (defaults to everything remaining)
     pub amount_cents: Option<i32>,
     /// Reason for refund
     pub reason: Option<String>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct RefundResponse {
+    pub id: String,
+    pub transaction_id: String,
+    pub amount_cents: i32,
+    pub currency: String,
+    pub reason: Option<String>,
+    pub status: String,
+    pub source: String,
+    pub initiated_by: Option<String>,
+    pub provider_refund_id: Option<String>,
+    pub platform_fee_reversed_cents: i32,
+    pub provider_payout_reversed_cents: i32,
+    pub failure_message: Option<String>,
+    pub created_at: String,
+}
+
+impl From<Refund> for RefundResponse {
+    fn from(refund: Refund) -> Self {
+        Self {
+            id: refund.id.to_string(),
+            transaction_id: refund.transaction_id.to_string(),
+            amount_cents: refund.amount_cents,
+            currency: refund.currency.to_string(),
+            reason: refund.reason,
+            status: format!("{:?}", refund.status).to_lowercase(),
+            source: format!("{:?}", refund.source).to_lowercase(),
+            initiated_by: refund.initiated_by.map(|id| id.to_string()),
+            provider_refund_id: refund.provider_refund_id,
+            platform_fee_reversed_cents: refund.platform_fee_reversed_cents,
+            provider_payout_reversed_cents: refund.provider_payout_reversed_cents,
+            failure_message: refund.failure_message,
+            created_at: refund.created_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct IssueRefundResponse {
+    pub refund: RefundResponse,
+    pub transaction_status: String,
+    pub refunded_amount_cents: i32,
+    pub refundable_amount_cents: i32,
+}
+
 /// Request a refund for a transaction
 pub async fn request_refund(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     auth_user: AuthUser,
     tenant: TenantContext,
     Path(id): Path<String>,
     Json(req): Json<RefundRequest>,
-) -> ApiResult<Json<serde_json::Value>> {
-    let transaction_id: Uuid = id
-        .parse()
-        .map_err(|_| ApiError::from(AppError::Validation("Invalid transaction ID".to_string())))?;
-
-    let transaction = TransactionRepository::get_by_id(&tenant.pool, tenant.org_id, transaction_id)
-        .await?
-        .ok_or_else(|| ApiError::from(AppError::NotFound("Transaction not found".to_string())))?;
+) -> ApiResult<Json<IssueRefundResponse>> {
+    let transaction = find_transaction(&tenant, &id).await?;
 
     // Only customers can request refunds
     if transaction.customer_user_id != auth_user.user_id {
@@ -348,211 +714,132 @@ pub async fn request_refund(
         )));
     }
 
-    // Check transaction is refundable
-    if transaction.status != TransactionStatus::Succeeded {
-        return Err(ApiError::from(AppError::Validation(
-            "Transaction cannot be refunded".to_string(),
-        )));
-    }
-
-    let refund_amount = req.amount_cents.unwrap_or(transaction.total_cents);
-
-    // Get the payment provider
-    let provider =
-        PaymentProviderRepository::get_by_id(&tenant.pool, tenant.org_id, transaction.provider_id)
-            .await?
-            .ok_or_else(|| {
-                ApiError::from(AppError::Internal("Payment provider not found".to_string()))
-            })?;
-
-    // Process refund through payment provider
-    let refund_id = match provider.provider_type {
-        PaymentProviderType::Stripe | PaymentProviderType::Platform => {
-            process_stripe_refund(&transaction, refund_amount, req.reason.as_deref()).await?
-        }
-        PaymentProviderType::Square => {
-            process_square_refund(
-                &provider,
-                &transaction,
-                refund_amount,
-                req.reason.as_deref(),
-            )
-            .await?
-        }
+    let initiator = RefundInitiator {
+        user_id: auth_user.user_id,
+        source: RefundSource::Customer,
     };
-
-    let new_status = if refund_amount >= transaction.total_cents {
-        TransactionStatus::Refunded
-    } else {
-        TransactionStatus::PartiallyRefunded
-    };
-
-    TransactionRepository::update_status(&tenant.pool, transaction.id, new_status).await?;
-
-    Ok(Json(serde_json::json!({
-        "success": true,
-        "refund_id": refund_id,
-        "refund_amount_cents": refund_amount,
-        "status": format!("{:?}", new_status).to_lowercase()
-    })))
+    issue(&state, &tenant, &transaction, req, initiator).await
 }
 
-// Helper functions for creating payments with providers
-
-async fn create_stripe_payment_intent(
-    provider: &db::models::PaymentProvider,
-    transaction: &db::models::Transaction,
-    _fee_breakdown: &TransactionFeeBreakdown,
-) -> Result<String, ApiError> {
-    let stripe_secret = std::env::var("STRIPE_SECRET_KEY")
-        .map_err(|_| ApiError::from(AppError::Internal("Stripe not configured".to_string())))?;
-
-    let client = reqwest::Client::new();
-
-    // Build params
-    let mut params = vec![
-        ("amount", transaction.total_cents.to_string()),
-        ("currency", "usd".to_string()),
-        ("automatic_payment_methods[enabled]", "true".to_string()),
-    ];
-
-    // Add application fee for platform revenue
-    if transaction.platform_fee_cents > 0 {
-        params.push((
-            "application_fee_amount",
-            transaction.platform_fee_cents.to_string(),
-        ));
-    }
+/// Refund a transaction on the customer's behalf (admin/owner only)
+pub async fn admin_refund(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    tenant: TenantContext,
+    Path(id): Path<String>,
+    Json(req): Json<RefundRequest>,
+) -> ApiResult<Json<IssueRefundResponse>> {
+    tenant.require_manager(auth_user.user_id).await?;
 
-    // Add transfer data for connected account
-    if let Some(merchant_id) = &provider.merchant_id {
-        params.push(("transfer_data[destination]", merchant_id.clone()));
-    }
+    let transaction = find_transaction(&tenant, &id).await?;
+    let initiator = RefundInitiator {
+        user_id: auth_user.user_id,
+        source: RefundSource::Admin,
+    };
+    issue(&state, &tenant, &transaction, req, initiator).await
+}
 
-    let response = client
-        .post("https://api.stripe.com/v1/payment_intents")
-        .header("Authorization", format!("Bearer {}", stripe_secret))
-        .form(&params)
-        .send()
-        .await
-        .map_err(|e| ApiError::from(AppError::Internal(e.to_string())))?;
-
-    if !response.status().is_success() {
-        let error_body = response.text().await.unwrap_or_default();
-        return Err(ApiError::from(AppError::Internal(format!(
-            "Stripe error: {}",
-            error_body
-        ))));
-    }
+/// List a transaction's refunds (customer or admin/owner)
+pub async fn list_refunds(
+    State(_state): State<AppState>,
+    auth_user: AuthUser,
+    tenant: TenantContext,
+    Path(id): Path<String>,
+) -> ApiResult<Json<Vec<RefundResponse>>> {
+    let transaction = find_transaction(&tenant, &id).await?;
 
-    #[derive(Deserialize)]
-    struct PaymentIntentResponse {
-        client_secret: String,
+    if transaction.customer_user_id != auth_user.user_id
+        && !tenant.is_manager(auth_user.user_id).await?
+    {
+        return Err(ApiError::from(AppError::NotFound(
+            "Transaction not found".to_string(),
+        )));
     }
 
-    let pi: PaymentIntentResponse = response
-        .json()
-        .await
-        .map_err(|e| ApiError::from(AppError::Internal(e.to_string())))?;
+    let refunds =
+        RefundRepository::list_by_transaction(&tenant.pool, tenant.org_id, transaction.id).await?;
 
-    Ok(pi.client_secret)
+    Ok(Json(
+        refunds.into_iter().map(RefundResponse::from).collect(),
+    ))
 }
 
-async fn create_square_payment(
-    provider: &db::models::PaymentProvider,
-    transaction: &db::models::Transaction,
-    _fee_breakdown: &TransactionFeeBreakdown,
-) -> Result<String, ApiError> {
-    let access_token = provider
-        .access_token_encrypted
-        .as_ref()
-        .ok_or_else(|| ApiError::from(AppError::Internal("Square not configured".to_string())))?;
-
-    let sandbox = std::env::var("SQUARE_SANDBOX")
-        .map(|v| v == "true")
-        .unwrap_or(false);
-
-    let base_url = if sandbox {
-        "https://connect.squareupsandbox.com/v2"
-    } else {
-        "https://connect.squareup.com/v2"
-    };
+/// One of the customer's own saved payment methods
+/// A saved payment method belonging to the signed-in user
+pub async fn find_payment_method(
+    tenant: &TenantContext,
+    auth_user: &AuthUser,
+    id: &str,
+) -> ApiResult<CustomerPaymentMethod> {
+    let id = id.parse::<Uuid>().map_err(|_| {
+        ApiError::from(AppError::Validation(
+            "Invalid payment method ID".to_string(),
+        ))
+    })?;
 
-    let client = reqwest::Client::new();
-
-    // First create an order
-    let order_response = client
-        .post(format!("{}/orders", base_url))
-        .header("Authorization", format!("Bearer {}", access_token))
-        .header("Content-Type", "application/json")
-        .header("Square-Version", "2024-01-18")
-        .json(&serde_json::json!({
-            "idempotency_key": transaction.id.to_string(),
-            "order": {
-                "location_id": provider.metadata.as_ref()
-                        .and_then(|m| m.get("location_id"))
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("main"),
-                "line_items": [{
-                    "name": "Service Payment",
-                    "quantity": "1",
-                    "base_price_money": {
-                        "amount": transaction.subtotal_cents as i64,
-                        "currency": "USD"
-                    }
-                }],
-                "service_charges": [{
-                    "name": "Service Fee",
-                    "amount_money": {
-                        "amount": transaction.customer_fee_cents as i64,
-                        "currency": "USD"
-                    },
-                    "calculation_phase": "TOTAL_PHASE"
-                }],
-                "taxes": [{
-                    "name": "Sales Tax",
-                    "percentage": format!("{:.2}", (transaction.tax_cents as f64 / transaction.subtotal_cents as f64) * 100.0),
-                    "scope": "ORDER"
-                }]
-            }
-        }))
-        .send()
-        .await
-        .map_err(|e| ApiError::from(AppError::Internal(e.to_string())))?;
-
-    if !order_response.status().is_success() {
-        let error_body = order_response.text().await.unwrap_or_default();
-        return Err(ApiError::from(AppError::Internal(format!(
-            "Square order error: {}",
-            error_body
-        ))));
-    }
+    CustomerPaymentMethodRepository::find_by_id(&tenant.pool, tenant.org_id, id)
+        .await?
+        .filter(|pm| pm.user_id == auth_user.user_id)
+        .ok_or_else(|| ApiError::from(AppError::NotFound("Payment method not found".to_string())))
+}
 
-    #[derive(Deserialize)]
-    struct SquareOrderResponse {
-        order: SquareOrder,
-    }
+async fn find_transaction(tenant: &TenantContext, id: &str) -> ApiResult<Transaction> {
+    let transaction_id: Uuid = id
+        .parse()
+        .map_err(|_| ApiError::from(AppError::Validation("Invalid transaction ID".to_string())))?;
 
-    #[derive(Deserialize)]
-    struct SquareOrder {
-        id: String,
-    }
+    TransactionRepository::get_by_id(&tenant.pool, tenant.org_id, transaction_id)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::NotFound("Transaction not found".to_string())))
+}
 
-    let order: SquareOrderResponse = order_response
-        .json()
-        .await
-        .map_err(|e| ApiError::from(AppError::Internal(e.to_string())))?;
+async fn issue(
+    state: &AppState,
+    tenant: &TenantContext,
+    transaction: &Transaction,
+    req: RefundRequest,
+    initiator: RefundInitiator,
+) -> ApiResult<Json<IssueRefundResponse>> {
+    let pending = RefundRepository::pending_total(&tenant.pool, transaction.id).await?;
+    let remaining = i64::from(transaction.refundable_amount_cents()) - pending;
+    let amount_cents = req
+        .amount_cents
+        .unwrap_or_else(|| i32::try_from(remaining).unwrap_or(0));
+
+    let (refund, transaction) = issue_refund(
+        state,
+        tenant,
+        transaction,
+        amount_cents,
+        req.reason,
+        initiator,
+    )
+    .await?;
 
-    // Return the order ID - client will use Square Web Payments SDK to complete
-    Ok(order.order.id)
+    Ok(Json(IssueRefundResponse {
+        refund: refund.into(),
+        transaction_status: transaction.status.to_string(),
+        refunded_amount_cents: transaction.refunded_amount_cents,
+        refundable_amount_cents: transaction.refundable_amount_cents(),
+    }))
 }
 
 /// Calculate fees preview without creating a transaction
 #[derive(Debug, Deserialize)]
 pub struct FeePreviewRequest {
-    pub subtotal_cents: i32,
+    /// Subtotal in cents (required unless the service to book is given)
+    pub subtotal_cents: Option<i32>,
+    /// Service to price, with service area adjustments for the walker and location
+    pub service_id: Option<String>,
+    pub location_id: Option<String>,
+    pub walker_id: Option<String>,
     pub tip_cents: Option<i32>,
+    /// State to estimate tax for when no location is given
     pub customer_state: Option<String>,
+    /// Country of `customer_state`; defaults to the US
+    pub customer_country: Option<String>,
+    /// Saved payment method to price processing for; defaults to a card
+    pub payment_method_id: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -562,197 +849,150 @@ pub struct FeePreviewResponse {
     pub customer_fee_cents: i32,
     pub tax_cents: i32,
     pub total_cents: i32,
+    pub currency: String,
+    /// What the payment provider is expected to charge, paid by the platform
+    pub processing_fee_cents: i32,
     pub customer_fee_percent: f64,
     pub tax_rate_percent: f64,
+    pub price_breakdown: Option<BookingPriceBreakdown>,
 }
 
 pub async fn preview_fees(
-    State(_state): State<AppState>,
-    _auth_user: AuthUser,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
     tenant: TenantContext,
     Json(req): Json<FeePreviewRequest>,
 ) -> ApiResult<Json<FeePreviewResponse>> {
+    // Price the booking the same way create_booking will, if a service is given
+    let mut location = None;
+    let mut currency = None;
+    let price_breakdown = if let Some(service_id) = &req.service_id {
+        let service_id = service_id
+            .parse()
+            .map_err(|_| ApiError::from(AppError::Validation("Invalid service ID".to_string())))?;
+        let service = ServiceRepository::find_by_id(&tenant.pool, tenant.org_id, service_id)
+            .await?
+            .ok_or_else(|| ApiError::from(AppError::NotFound("Service not found".to_string())))?;
+
+        location = match &req.location_id {
+            Some(location_id) => {
+                let location_id = location_id.parse().map_err(|_| {
+                    ApiError::from(AppError::Validation("Invalid location ID".to_string()))
+                })?;
+                let location =
+                    LocationRepository::find_by_id(&tenant.pool, tenant.org_id, location_id)
+                        .await?
+                        .filter(|l| l.user_id == auth_user.user_id)
+                        .ok_or_else(|| {
+                            ApiError::from(AppError::NotFound("Location not found".to_string()))
+                        })?;
+                Some(location)
+            }
+            None => None,
+        };
+
+        let matching_areas = match (&location, &req.walker_id) {
+            (Some(location), Some(walker_id)) => {
+                let walker_id = walker_id.parse().map_err(|_| {
+                    ApiError::from(AppError::Validation("Invalid walker ID".to_string()))
+                })?;
+                ServiceAreaRepository::find_walker_areas_for_location(
+                    &tenant.pool,
+                    tenant.org_id,
+                    walker_id,
+                    location.latitude,
+                    location.longitude,
+                )
+                .await?
+            }
+            _ => Vec::new(),
+        };
+
+        currency = Some(service.currency);
+
+        Some(BookingPriceBreakdown::calculate(
+            service.base_price_cents,
+            &matching_areas,
+        ))
+    } else {
+        None
+    };
+
+    let subtotal_cents = match (&price_breakdown, req.subtotal_cents) {
+        (Some(breakdown), _) => i32::try_from(breakdown.total_cents)
+            .map_err(|_| ApiError::from(AppError::Validation("Price too large".to_string())))?,
+        (None, Some(subtotal)) => subtotal,
+        (None, None) => {
+            return Err(ApiError::from(AppError::Validation(
+                "Either subtotal_cents or service_id is required".to_string(),
+            )))
+        }
+    };
+
     // Get the fee tier for this tenant
     let fee_tier = SubscriptionRepository::get_org_fee_tier(&tenant.pool, tenant.org_id).await?;
 
-    // Calculate tax rate
-    let tax_rate_percent = if let Some(state) = &req.customer_state {
-        match state.to_uppercase().as_str() {
-            "CA" => 7.25,
-            "TX" => 6.25,
-            "FL" => 6.00,
-            "NY" => 8.00,
-            "WA" => 6.50,
-            "OR" | "MT" | "NH" | "DE" => 0.0,
-            _ => 6.0,
+    // A bare subtotal is in the organization's currency
+    let currency = match currency {
+        Some(currency) => currency,
+        None => organization_currency(&state, &tenant).await?,
+    };
+    let subtotal = Money::new(i64::from(subtotal_cents), currency);
+
+    // Tax as checkout will charge it for the location, or estimated for the
+    // customer's state
+    let tax_rate_percent = match (&location, &req.customer_state) {
+        (Some(location), _) => tax::quote(&state, location, subtotal).await?.rate_percent,
+        (None, Some(customer_state)) => {
+            let country = req.customer_country.as_deref().unwrap_or("US");
+            OfflineTaxTable::quote(country, customer_state)
+                .map_err(|e| ApiError::from(AppError::Validation(e.to_string())))?
+                .rate_percent
         }
-    } else {
-        0.0
+        (None, None) => Decimal::ZERO,
     };
 
-    let customer_fee_pct = fee_tier.customer_fee_percent.to_f64().unwrap_or(0.0);
-    let provider_fee_pct = fee_tier.provider_fee_percent.to_f64().unwrap_or(0.0);
+    // Price processing for the provider checkout would charge through
+    let payment_method = match &req.payment_method_id {
+        Some(pm_id) => Some(find_payment_method(&tenant, &auth_user, pm_id).await?),
+        None => None,
+    };
+    let provider_type = PaymentProviderRepository::get_primary(&tenant.pool, tenant.org_id)
+        .await?
+        .map_or(PaymentProviderType::Platform, |p| p.provider_type);
+    let processing_fee =
+        processing_fee(&tenant, provider_type, payment_method.as_ref(), currency).await?;
 
     let fee_breakdown = TransactionFeeBreakdown::calculate(
-        req.subtotal_cents,
-        customer_fee_pct,
-        provider_fee_pct,
+        subtotal,
+        fee_tier.customer_fee_percent,
+        fee_tier.provider_fee_percent,
         tax_rate_percent,
-        2.9,
-    );
+        processing_fee,
+    )?;
 
     let tip = req.tip_cents.unwrap_or(0);
-    let total = fee_breakdown.total_cents + tip;
+    let charged = fee_breakdown
+        .total
+        .checked_add(Money::new(i64::from(tip), currency))?;
+    let total = cents(charged)?;
 
     Ok(Json(FeePreviewResponse {
-        subtotal_cents: req.subtotal_cents,
+        subtotal_cents,
         tip_cents: tip,
-        customer_fee_cents: fee_breakdown.customer_fee_cents,
-        tax_cents: fee_breakdown.tax_cents,
+        customer_fee_cents: cents(fee_breakdown.customer_fee)?,
+        tax_cents: cents(fee_breakdown.tax)?,
         total_cents: total,
-        customer_fee_percent: customer_fee_pct * 100.0,
-        tax_rate_percent,
+        currency: currency.to_string(),
+        processing_fee_cents: cents(processing_fee.apply(charged)?)?,
+        customer_fee_percent: fee_tier.customer_fee_display(),
+        tax_rate_percent: tax_rate_percent.to_f64().unwrap_or(0.0),
+        price_breakdown,
     }))
 }
 
-// Refund helper functions
-
-async fn process_stripe_refund(
-    transaction: &db::models::Transaction,
-    amount_cents: i32,
-    reason: Option<&str>,
-) -> Result<String, ApiError> {
-    let stripe_secret = std::env::var("STRIPE_SECRET_KEY")
-        .map_err(|_| ApiError::from(AppError::Internal("Stripe not configured".to_string())))?;
-
-    let payment_intent_id = transaction
-        .external_payment_id
-        .as_ref()
-        .ok_or_else(|| ApiError::from(AppError::Validation("No payment to refund".to_string())))?;
-
-    // Extract the payment intent ID from client secret if needed
-    let pi_id = if payment_intent_id.contains("_secret_") {
-        payment_intent_id
-            .split("_secret_")
-            .next()
-            .unwrap_or(payment_intent_id)
-    } else {
-        payment_intent_id.as_str()
-    };
-
-    let client = reqwest::Client::new();
-
-    let mut params = vec![
-        ("payment_intent", pi_id.to_string()),
-        ("amount", amount_cents.to_string()),
-    ];
-
-    if let Some(r) = reason {
-        let stripe_reason = match r.to_lowercase().as_str() {
-            "duplicate" => "duplicate",
-            "fraudulent" => "fraudulent",
-            _ => "requested_by_customer",
-        };
-        params.push(("reason", stripe_reason.to_string()));
-    }
-
-    let response = client
-        .post("https://api.stripe.com/v1/refunds")
-        .header("Authorization", format!("Bearer {}", stripe_secret))
-        .form(&params)
-        .send()
-        .await
-        .map_err(|e| ApiError::from(AppError::Internal(e.to_string())))?;
-
-    if !response.status().is_success() {
-        let error_body = response.text().await.unwrap_or_default();
-        return Err(ApiError::from(AppError::Internal(format!(
-            "Stripe refund error: {}",
-            error_body
-        ))));
-    }
-
-    #[derive(Deserialize)]
-    struct RefundResponse {
-        id: String,
-    }
-
-    let refund: RefundResponse = response
-        .json()
-        .await
-        .map_err(|e| ApiError::from(AppError::Internal(e.to_string())))?;
-
-    Ok(refund.id)
-}
-
-async fn process_square_refund(
-    provider: &db::models::PaymentProvider,
-    transaction: &db::models::Transaction,
-    amount_cents: i32,
-    reason: Option<&str>,
-) -> Result<String, ApiError> {
-    let access_token = provider
-        .access_token_encrypted
-        .as_ref()
-        .ok_or_else(|| ApiError::from(AppError::Internal("Square not configured".to_string())))?;
-
-    let payment_id = transaction
-        .external_payment_id
-        .as_ref()
-        .ok_or_else(|| ApiError::from(AppError::Validation("No payment to refund".to_string())))?;
-
-    let sandbox = std::env::var("SQUARE_SANDBOX")
-        .map(|v| v == "true")
-        .unwrap_or(false);
-
-    let base_url = if sandbox {
-        "https://connect.squareupsandbox.com/v2"
-    } else {
-        "https://connect.squareup.com/v2"
-    };
-
-    let client = reqwest::Client::new();
-
-    let response = client
-        .post(format!("{}/refunds", base_url))
-        .header("Authorization", format!("Bearer {}", access_token))
-        .header("Content-Type", "application/json")
-        .header("Square-Version", "2024-01-18")
-        .json(&serde_json::json!({
-            "idempotency_key": format!("refund_{}", transaction.id),
-            "payment_id": payment_id,
-            "amount_money": {
-                "amount": amount_cents as i64,
-                "currency": "USD"
-            },
-            "reason": reason.unwrap_or("Requested by customer")
-        }))
-        .send()
-        .await
-        .map_err(|e| ApiError::from(AppError::Internal(e.to_string())))?;
-
-    if !response.status().is_success() {
-        let error_body = response.text().await.unwrap_or_default();
-        return Err(ApiError::from(AppError::Internal(format!(
-            "Square refund error: {}",
-            error_body
-        ))));
-    }
-
-    #[derive(Deserialize)]
-    struct SquareRefundResponse {
-        refund: SquareRefund,
-    }
-
-    #[derive(Deserialize)]
-    struct SquareRefund {
-        id: String,
-    }
-
-    let refund: SquareRefundResponse = response
-        .json()
-        .await
-        .map_err(|e| ApiError::from(AppError::Internal(e.to_string())))?;
-
-    Ok(refund.refund.id)
+/// Amount in cents as stored on transactions
+pub fn cents(amount: Money) -> ApiResult<i32> {
+    i32::try_from(amount.cents())
+        .map_err(|_| ApiError::from(AppError::Validation("Amount too large".to_string())))
 }
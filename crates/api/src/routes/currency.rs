@@ -0,0 +1,86 @@
+use axum::{extract::State, Json};
+use db::models::{OrganizationSettings, UpdateOrganization};
+use db::OrganizationRepository;
+use serde::{Deserialize, Serialize};
+use shared::types::Currency;
+use shared::DomainError;
+
+use crate::{
+    auth::{AuthUser, TenantContext},
+    error::{ApiError, ApiResult},
+    state::AppState,
+};
+
+#[derive(Debug, Serialize)]
+pub struct CurrencySettingsResponse {
+    pub default_currency: String,
+    pub supported_currencies: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateCurrencySettingsRequest {
+    pub default_currency: Currency,
+}
+
+/// GET /admin/currency - The organization's default currency
+pub async fn get_currency_settings(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+) -> ApiResult<Json<CurrencySettingsResponse>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let settings = organization_settings(&state, &tenant).await?;
+
+    Ok(Json(settings_response(settings.default_currency())))
+}
+
+/// PUT /admin/currency - Change the default currency
+///
+/// Existing services keep the currency they were priced in; only services
+/// created afterwards and new payouts pick up the new default.
+pub async fn update_currency_settings(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Json(req): Json<UpdateCurrencySettingsRequest>,
+) -> ApiResult<Json<CurrencySettingsResponse>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let current = organization_settings(&state, &tenant).await?;
+    OrganizationRepository::update(
+        &state.pool,
+        tenant.org_id,
+        UpdateOrganization {
+            name: None,
+            slug: None,
+            custom_domain: None,
+            settings: Some(OrganizationSettings {
+                default_currency: Some(req.default_currency),
+                ..current
+            }),
+        },
+    )
+    .await?
+    .ok_or_else(|| ApiError::from(DomainError::TenantNotFound(tenant.org_id.to_string())))?;
+
+    Ok(Json(settings_response(req.default_currency)))
+}
+
+fn settings_response(currency: Currency) -> CurrencySettingsResponse {
+    CurrencySettingsResponse {
+        default_currency: currency.to_string(),
+        supported_currencies: Currency::ALL.iter().map(|c| c.to_string()).collect(),
+    }
+}
+
+async fn organization_settings(
+    state: &AppState,
+    tenant: &TenantContext,
+) -> ApiResult<OrganizationSettings> {
+    let org = OrganizationRepository::find_by_id(&state.pool, tenant.org_id)
+        .await?
+        .ok_or_else(|| ApiError::from(DomainError::TenantNotFound(tenant.org_id.to_string())))?;
+
+    Ok(org.settings.0)
+}
@@ -0,0 +1,225 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::Utc;
+use db::models::{Dispute, DisputeEvidence, DisputeStatus};
+use db::DisputeRepository;
+use serde::{Deserialize, Serialize};
+use shared::AppError;
+use uuid::Uuid;
+
+use crate::{
+    auth::{AuthUser, TenantContext},
+    disputes,
+    error::{ApiError, ApiResult},
+    state::AppState,
+};
+
+#[derive(Debug, Serialize)]
+pub struct DisputeResponse {
+    pub id: String,
+    pub transaction_id: String,
+    pub amount_cents: i32,
+    pub currency: String,
+    pub reason: String,
+    pub reason_display: String,
+    pub status: String,
+    pub evidence_submitted: bool,
+    pub evidence_submitted_at: Option<String>,
+    pub evidence_due_by: Option<String>,
+    pub days_until_due: Option<i64>,
+    pub needs_attention: bool,
+    /// Evidence reminder waiting to be dismissed: the due date is within
+    /// this many days
+    pub reminder_days: Option<i32>,
+    pub resolved_at: Option<String>,
+    pub outcome: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DisputeDetailResponse {
+    #[serde(flatten)]
+    pub dispute: DisputeResponse,
+    /// The saved evidence, or evidence assembled from the booking if none
+    /// was saved yet
+    pub evidence: DisputeEvidence,
+    pub evidence_saved: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListDisputesQuery {
+    pub status: Option<String>,
+    /// Only disputes with an evidence reminder waiting to be dismissed
+    #[serde(default)]
+    pub reminder_due: bool,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// GET /admin/disputes - Disputes, newest first
+pub async fn list_disputes(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Query(query): Query<ListDisputesQuery>,
+) -> ApiResult<Json<Vec<DisputeResponse>>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let status = match query.status.as_deref().map(str::to_lowercase).as_deref() {
+        None => None,
+        Some("needs_response") => Some(DisputeStatus::NeedsResponse),
+        Some("under_review") => Some(DisputeStatus::UnderReview),
+        Some("won") => Some(DisputeStatus::Won),
+        Some("lost") => Some(DisputeStatus::Lost),
+        Some(_) => {
+            return Err(ApiError::from(AppError::Validation(
+                "Invalid status filter".to_string(),
+            )))
+        }
+    };
+
+    if query.reminder_due {
+        let now = Utc::now();
+        let disputes =
+            DisputeRepository::list_awaiting_evidence(&tenant.pool, tenant.org_id).await?;
+        return Ok(Json(
+            disputes
+                .iter()
+                .filter(|d| d.reminder_due(now).is_some())
+                .map(dispute_response)
+                .collect(),
+        ));
+    }
+
+    let disputes = DisputeRepository::list_for_org(
+        &tenant.pool,
+        tenant.org_id,
+        status,
+        query.limit.unwrap_or(50).min(200),
+        query.offset.unwrap_or(0),
+    )
+    .await?;
+
+    Ok(Json(disputes.iter().map(dispute_response).collect()))
+}
+
+/// GET /admin/disputes/:id - A dispute and its evidence
+pub async fn get_dispute(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path(dispute_id): Path<Uuid>,
+) -> ApiResult<Json<DisputeDetailResponse>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let dispute = load_dispute(&tenant, dispute_id).await?;
+    let (evidence, evidence_saved) = match &dispute.evidence {
+        Some(evidence) => (evidence.0.clone(), true),
+        None => (disputes::assemble_evidence(&tenant, &dispute).await?, false),
+    };
+
+    Ok(Json(DisputeDetailResponse {
+        dispute: dispute_response(&dispute),
+        evidence,
+        evidence_saved,
+    }))
+}
+
+/// PUT /admin/disputes/:id/evidence - Save edited evidence without
+/// submitting it
+pub async fn save_evidence(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path(dispute_id): Path<Uuid>,
+    Json(evidence): Json<DisputeEvidence>,
+) -> ApiResult<Json<DisputeDetailResponse>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let dispute = load_dispute(&tenant, dispute_id).await?;
+    if dispute.evidence_submitted {
+        return Err(ApiError::from(AppError::Validation(
+            "Evidence was already submitted".to_string(),
+        )));
+    }
+
+    let dispute =
+        DisputeRepository::save_evidence(&tenant.pool, tenant.org_id, dispute.id, &evidence)
+            .await?
+            .ok_or_else(|| ApiError::from(AppError::NotFound("Dispute".to_string())))?;
+
+    Ok(Json(DisputeDetailResponse {
+        dispute: dispute_response(&dispute),
+        evidence,
+        evidence_saved: true,
+    }))
+}
+
+/// POST /admin/disputes/:id/evidence/submit - Submit the saved evidence, or
+/// evidence assembled from the booking if none was saved, to the provider
+pub async fn submit_evidence(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path(dispute_id): Path<Uuid>,
+) -> ApiResult<Json<DisputeDetailResponse>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let dispute = load_dispute(&tenant, dispute_id).await?;
+    let evidence = match &dispute.evidence {
+        Some(evidence) => evidence.0.clone(),
+        None => disputes::assemble_evidence(&tenant, &dispute).await?,
+    };
+    let dispute = disputes::submit_evidence(&state, &tenant, &dispute, &evidence).await?;
+
+    Ok(Json(DisputeDetailResponse {
+        dispute: dispute_response(&dispute),
+        evidence,
+        evidence_saved: true,
+    }))
+}
+
+/// POST /admin/disputes/:id/reminder/dismiss - Dismiss the evidence reminder
+/// shown for a dispute until the next one comes due
+pub async fn dismiss_reminder(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path(dispute_id): Path<Uuid>,
+) -> ApiResult<Json<DisputeResponse>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let dispute = load_dispute(&tenant, dispute_id).await?;
+    let dispute = disputes::dismiss_reminder(&tenant, &dispute, Utc::now()).await?;
+
+    Ok(Json(dispute_response(&dispute)))
+}
+
+async fn load_dispute(tenant: &TenantContext, dispute_id: Uuid) -> ApiResult<Dispute> {
+    DisputeRepository::get_by_id(&tenant.pool, tenant.org_id, dispute_id)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::NotFound("Dispute".to_string())))
+}
+
+fn dispute_response(dispute: &Dispute) -> DisputeResponse {
+    DisputeResponse {
+        id: dispute.id.to_string(),
+        transaction_id: dispute.transaction_id.to_string(),
+        amount_cents: dispute.amount_cents,
+        currency: dispute.currency.to_string(),
+        reason: dispute.reason.clone(),
+        reason_display: dispute.reason_display().to_string(),
+        status: dispute.status.to_string(),
+        evidence_submitted: dispute.evidence_submitted,
+        evidence_submitted_at: dispute.evidence_submitted_at.map(|dt| dt.to_rfc3339()),
+        evidence_due_by: dispute.evidence_due_by.map(|dt| dt.to_rfc3339()),
+        days_until_due: dispute.days_until_due(),
+        needs_attention: dispute.needs_attention(),
+        reminder_days: dispute.reminder_due(Utc::now()),
+        resolved_at: dispute.resolved_at.map(|dt| dt.to_rfc3339()),
+        outcome: dispute.outcome.clone(),
+        created_at: dispute.created_at.to_rfc3339(),
+    }
+}
@@ -0,0 +1,186 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use db::models::LedgerAccountBalance;
+use db::LedgerRepository;
+use serde::Serialize;
+use shared::types::UserId;
+use shared::AppError;
+use uuid::Uuid;
+
+use crate::{
+    auth::{AuthUser, TenantContext},
+    error::{ApiError, ApiResult},
+    state::AppState,
+};
+
+#[derive(Debug, Serialize)]
+pub struct LedgerBalanceResponse {
+    pub account_type: String,
+    pub walker_id: Option<String>,
+    pub currency: String,
+    pub debit_cents: i64,
+    pub credit_cents: i64,
+    /// Balance on the account's normal side
+    pub balance_cents: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LedgerCurrencyCheckResponse {
+    pub currency: String,
+    pub balanced: bool,
+    pub total_debits_cents: i64,
+    pub total_credits_cents: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LedgerCheckResponse {
+    pub balanced: bool,
+    /// Totals per currency; each currency has to balance on its own
+    pub currencies: Vec<LedgerCurrencyCheckResponse>,
+    pub unbalanced_entry_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JournalLineResponse {
+    pub account_id: String,
+    pub debit_cents: i64,
+    pub credit_cents: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JournalEntryResponse {
+    pub id: String,
+    pub kind: String,
+    pub source_id: String,
+    pub description: String,
+    pub currency: String,
+    pub occurred_at: String,
+    pub lines: Vec<JournalLineResponse>,
+}
+
+/// GET /admin/ledger/balances - Balance of every ledger account
+pub async fn get_balances(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+) -> ApiResult<Json<Vec<LedgerBalanceResponse>>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let balances = LedgerRepository::balances(&tenant.pool, tenant.org_id).await?;
+
+    Ok(Json(balances.iter().map(balance_response).collect()))
+}
+
+/// GET /walkers/:id/balance - What the organization owes a walker
+pub async fn get_walker_balance(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path(walker_id): Path<String>,
+) -> ApiResult<Json<Vec<LedgerBalanceResponse>>> {
+    let walker_id: UserId = walker_id
+        .parse()
+        .map_err(|_| ApiError::from(AppError::Validation("Invalid walker ID".to_string())))?;
+    if walker_id != auth.user_id && !tenant.is_manager(auth.user_id).await? {
+        return Err(ApiError::from(AppError::Forbidden));
+    }
+
+    let balances = LedgerRepository::walker_balance(&tenant.pool, tenant.org_id, walker_id).await?;
+
+    Ok(Json(balances.iter().map(balance_response).collect()))
+}
+
+/// GET /admin/ledger/check - Verify that debits equal credits in each currency
+pub async fn check_ledger(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+) -> ApiResult<Json<LedgerCheckResponse>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let check = LedgerRepository::check(&tenant.pool, tenant.org_id).await?;
+    for totals in check.currencies.iter().filter(|t| !t.is_balanced()) {
+        tracing::warn!(
+            "Ledger for org {} is unbalanced in {}: {} debits, {} credits",
+            tenant.org_id,
+            totals.currency,
+            totals.total_debits_cents,
+            totals.total_credits_cents
+        );
+    }
+    if !check.unbalanced_entry_ids.is_empty() {
+        tracing::warn!(
+            "Ledger for org {} has {} unbalanced entries",
+            tenant.org_id,
+            check.unbalanced_entry_ids.len()
+        );
+    }
+
+    Ok(Json(LedgerCheckResponse {
+        balanced: check.is_balanced(),
+        currencies: check
+            .currencies
+            .iter()
+            .map(|t| LedgerCurrencyCheckResponse {
+                currency: t.currency.to_string(),
+                balanced: t.is_balanced(),
+                total_debits_cents: t.total_debits_cents,
+                total_credits_cents: t.total_credits_cents,
+            })
+            .collect(),
+        unbalanced_entry_ids: check
+            .unbalanced_entry_ids
+            .iter()
+            .map(Uuid::to_string)
+            .collect(),
+    }))
+}
+
+/// GET /admin/transactions/:id/ledger - Journal entries for a transaction
+pub async fn list_transaction_entries(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path(transaction_id): Path<Uuid>,
+) -> ApiResult<Json<Vec<JournalEntryResponse>>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let entries =
+        LedgerRepository::list_by_transaction(&tenant.pool, tenant.org_id, transaction_id).await?;
+
+    let mut response = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let lines = LedgerRepository::get_lines(&tenant.pool, entry.id).await?;
+        response.push(JournalEntryResponse {
+            id: entry.id.to_string(),
+            kind: entry.kind.to_string(),
+            source_id: entry.source_id.to_string(),
+            description: entry.description,
+            currency: entry.currency.to_string(),
+            occurred_at: entry.occurred_at.to_rfc3339(),
+            lines: lines
+                .into_iter()
+                .map(|l| JournalLineResponse {
+                    account_id: l.account_id.to_string(),
+                    debit_cents: l.debit_cents,
+                    credit_cents: l.credit_cents,
+                })
+                .collect(),
+        });
+    }
+
+    Ok(Json(response))
+}
+
+fn balance_response(balance: &LedgerAccountBalance) -> LedgerBalanceResponse {
+    LedgerBalanceResponse {
+        account_type: balance.account_type.to_string(),
+        walker_id: balance.walker_id.map(|id| id.to_string()),
+        currency: balance.currency.to_string(),
+        debit_cents: balance.debit_cents,
+        credit_cents: balance.credit_cents,
+        balance_cents: balance.balance_cents(),
+    }
+}
@@ -0,0 +1,387 @@
+//! Live walker location streams over Server-Sent Events.
+//!
+//! Walker apps post fixes to `POST /walkers/:id/location`; these endpoints poll
+//! the stored location and push an event whenever it changes, so any API
+//! instance can serve a stream without shared in-memory state.
+
+use std::{collections::HashSet, convert::Infallible, time::Duration};
+
+use axum::{
+    extract::{Path, State},
+    response::sse::{Event, KeepAlive, Sse},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use db::models::{Booking, BookingStatus, WalkerLocation};
+use db::{BookingRepository, LocationRepository, MembershipRepository, WalkerLocationRepository};
+use futures::stream::{self, Stream};
+use serde::Serialize;
+use shared::types::{BookingId, Coordinates, UserId};
+use shared::{AppError, DomainError};
+
+use crate::{
+    auth::{self, AuthUser, StreamAuth, TenantContext},
+    error::{ApiError, ApiResult},
+    state::AppState,
+};
+
+/// How often stored locations are checked for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Locations older than this are flagged stale (matches the REST endpoints)
+const STALE_MINUTES: i64 = 30;
+
+/// How long before a confirmed walk's start the walker can be followed
+const LEAD_MINUTES: i64 = 60;
+
+/// Live location of the walker assigned to a booking
+#[derive(Debug, Serialize)]
+pub struct BookingLocationEvent {
+    pub booking_id: String,
+    pub booking_status: String,
+    pub walker_id: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub accuracy_meters: Option<f64>,
+    pub heading: Option<f64>,
+    pub speed_mps: Option<f64>,
+    pub updated_at: String,
+    pub is_stale: bool,
+    /// Straight-line distance to the pickup location, before the walk starts
+    pub distance_km: Option<f64>,
+    /// Estimated minutes until the walker arrives, before the walk starts
+    pub eta_minutes: Option<i32>,
+}
+
+/// Sent once when a booking stops being active and the stream closes
+#[derive(Debug, Serialize)]
+pub struct BookingEndedEvent {
+    pub booking_id: String,
+    pub booking_status: String,
+}
+
+/// A single on-duty walker in the admin feed
+#[derive(Debug, Serialize)]
+pub struct OnDutyWalker {
+    pub walker_id: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub accuracy_meters: Option<f64>,
+    pub heading: Option<f64>,
+    pub speed_mps: Option<f64>,
+    pub updated_at: String,
+}
+
+/// Snapshot of every on-duty walker in the organization
+#[derive(Debug, Serialize)]
+pub struct OnDutyWalkersEvent {
+    pub walkers: Vec<OnDutyWalker>,
+}
+
+/// Short-lived credential for opening a stream from `EventSource`
+#[derive(Debug, Serialize)]
+pub struct StreamTicketResponse {
+    pub ticket: String,
+    pub expires_in: i64,
+}
+
+/// POST /auth/stream-ticket - Issue a ticket for the live streams
+///
+/// `EventSource` can't send an Authorization header, so clients exchange their
+/// session token for a ticket and pass it as `?ticket=`. Tickets expire after a
+/// minute and are rejected everywhere except the stream endpoints.
+pub async fn create_stream_ticket(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    tenant: TenantContext,
+) -> ApiResult<Json<StreamTicketResponse>> {
+    let ticket = auth::create_stream_ticket(auth.user_id, tenant.org_id, &state.jwt_secret)
+        .map_err(|e| {
+            ApiError::from(AppError::Internal(format!(
+                "Failed to create stream ticket: {}",
+                e
+            )))
+        })?;
+
+    Ok(Json(StreamTicketResponse {
+        ticket,
+        expires_in: auth::STREAM_TICKET_TTL_SECONDS,
+    }))
+}
+
+/// GET /bookings/:id/live - Stream the assigned walker's location (SSE)
+///
+/// Available to the booking's customer and walker while the walk is in
+/// progress, or once it's confirmed from an hour before its scheduled start
+/// until its scheduled end. Emits `location` events as the walker moves and
+/// a final `ended` event once the booking is completed or cancelled, or its
+/// window passes without the walk starting.
+pub async fn stream_booking_location(
+    State(_state): State<AppState>,
+    StreamAuth { auth, tenant }: StreamAuth,
+    Path(id): Path<String>,
+) -> ApiResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let booking_id: BookingId = id
+        .parse()
+        .map_err(|_| ApiError::from(AppError::Validation("Invalid booking ID".to_string())))?;
+
+    let booking = BookingRepository::find_by_id(&tenant.pool, tenant.org_id, booking_id)
+        .await?
+        .ok_or_else(|| ApiError::from(DomainError::BookingNotFound(id)))?;
+
+    if booking.customer_id != auth.user_id && booking.walker_id != auth.user_id {
+        return Err(ApiError::from(AppError::Forbidden));
+    }
+
+    if !matches!(
+        booking.status,
+        BookingStatus::Confirmed | BookingStatus::InProgress
+    ) {
+        return Err(ApiError::from(DomainError::InvalidStateTransition(
+            booking.status.to_string(),
+        )));
+    }
+    if !is_active(&booking, Utc::now()) {
+        return Err(ApiError::from(AppError::Validation(format!(
+            "Live tracking opens {} minutes before the walk",
+            LEAD_MINUTES
+        ))));
+    }
+
+    let destination =
+        LocationRepository::find_by_id(&tenant.pool, tenant.org_id, booking.location_id)
+            .await?
+            .and_then(|location| Coordinates::new(location.latitude, location.longitude).ok());
+
+    let state = BookingStream {
+        tenant,
+        booking_id,
+        walker_id: booking.walker_id,
+        destination,
+        interval: poll_interval(),
+        last_sent: None,
+        finished: false,
+    };
+
+    let events = stream::unfold(state, |mut state| async move {
+        let event = state.next_event().await?;
+        Some((Ok(event), state))
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// GET /admin/walkers/live - Stream all on-duty walkers in the organization (SSE)
+///
+/// Emits a `walkers` snapshot whenever any on-duty walker's location changes.
+pub async fn stream_on_duty_walkers(
+    State(_state): State<AppState>,
+    StreamAuth { auth, tenant }: StreamAuth,
+) -> ApiResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let state = WalkersStream {
+        tenant,
+        interval: poll_interval(),
+        last_snapshot: None,
+    };
+
+    let events = stream::unfold(state, |mut state| async move {
+        let event = state.next_event().await;
+        Some((Ok(event), state))
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+struct BookingStream {
+    tenant: TenantContext,
+    booking_id: BookingId,
+    walker_id: UserId,
+    destination: Option<Coordinates>,
+    interval: tokio::time::Interval,
+    /// Location timestamp and booking status of the last event sent
+    last_sent: Option<(DateTime<Utc>, BookingStatus)>,
+    finished: bool,
+}
+
+impl BookingStream {
+    /// Wait for the next event to send, or `None` once the stream is over
+    async fn next_event(&mut self) -> Option<Event> {
+        if self.finished {
+            return None;
+        }
+
+        loop {
+            self.interval.tick().await;
+
+            let booking = match BookingRepository::find_by_id(
+                &self.tenant.pool,
+                self.tenant.org_id,
+                self.booking_id,
+            )
+            .await
+            {
+                Ok(Some(booking)) => booking,
+                Ok(None) => return None,
+                Err(e) => {
+                    tracing::warn!("Live booking stream failed to load booking: {}", e);
+                    continue;
+                }
+            };
+
+            if !is_active(&booking, Utc::now()) {
+                self.finished = true;
+                return json_event(
+                    "ended",
+                    &BookingEndedEvent {
+                        booking_id: booking.id.to_string(),
+                        booking_status: booking.status.to_string(),
+                    },
+                );
+            }
+
+            let location =
+                match WalkerLocationRepository::get(&self.tenant.pool, self.walker_id).await {
+                    Ok(Some(location)) => location,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        tracing::warn!("Live booking stream failed to load location: {}", e);
+                        continue;
+                    }
+                };
+
+            // Re-send when the walker moves or the walk starts
+            let current = (location.updated_at, booking.status);
+            if self.last_sent == Some(current) {
+                continue;
+            }
+            self.last_sent = Some(current);
+
+            return json_event("location", &self.location_event(&booking, &location));
+        }
+    }
+
+    fn location_event(&self, booking: &Booking, location: &WalkerLocation) -> BookingLocationEvent {
+        // ETA only makes sense while the walker is on the way to the pickup
+        let approach = self
+            .destination
+            .filter(|_| booking.status == BookingStatus::Confirmed);
+        let position = Coordinates::new_unchecked(location.latitude, location.longitude);
+
+        BookingLocationEvent {
+            booking_id: booking.id.to_string(),
+            booking_status: booking.status.to_string(),
+            walker_id: location.walker_id.to_string(),
+            latitude: location.latitude,
+            longitude: location.longitude,
+            accuracy_meters: location.accuracy_meters,
+            heading: location.heading,
+            speed_mps: location.speed_mps,
+            updated_at: location.updated_at.to_rfc3339(),
+            is_stale: location.is_stale(STALE_MINUTES),
+            distance_km: approach
+                .map(|destination| (position.distance_km(&destination) * 100.0).round() / 100.0),
+            eta_minutes: approach.map(|destination| position.estimate_travel_minutes(&destination)),
+        }
+    }
+}
+
+struct WalkersStream {
+    tenant: TenantContext,
+    interval: tokio::time::Interval,
+    last_snapshot: Option<Vec<(UserId, DateTime<Utc>)>>,
+}
+
+impl WalkersStream {
+    /// Wait until the on-duty walkers change and return a snapshot event
+    async fn next_event(&mut self) -> Event {
+        loop {
+            self.interval.tick().await;
+
+            let walkers = match self.on_duty_walkers().await {
+                Ok(walkers) => walkers,
+                Err(e) => {
+                    tracing::warn!("Live walker stream failed to load locations: {}", e);
+                    continue;
+                }
+            };
+
+            let snapshot: Vec<_> = walkers
+                .iter()
+                .map(|location| (location.walker_id, location.updated_at))
+                .collect();
+            if self.last_snapshot.as_ref() == Some(&snapshot) {
+                continue;
+            }
+            self.last_snapshot = Some(snapshot);
+
+            let event = OnDutyWalkersEvent {
+                walkers: walkers
+                    .into_iter()
+                    .map(|location| OnDutyWalker {
+                        walker_id: location.walker_id.to_string(),
+                        latitude: location.latitude,
+                        longitude: location.longitude,
+                        accuracy_meters: location.accuracy_meters,
+                        heading: location.heading,
+                        speed_mps: location.speed_mps,
+                        updated_at: location.updated_at.to_rfc3339(),
+                    })
+                    .collect(),
+            };
+            if let Some(event) = json_event("walkers", &event) {
+                return event;
+            }
+        }
+    }
+
+    /// Fresh on-duty locations, limited to this organization's service providers
+    async fn on_duty_walkers(&self) -> Result<Vec<WalkerLocation>, sqlx::Error> {
+        let members: HashSet<UserId> =
+            MembershipRepository::find_by_organization(&self.tenant.pool, self.tenant.org_id)
+                .await?
+                .into_iter()
+                .filter(|m| m.is_active() && m.role.is_service_provider())
+                .map(|m| m.user_id)
+                .collect();
+
+        let mut locations = WalkerLocationRepository::get_on_duty(&self.tenant.pool, STALE_MINUTES)
+            .await?
+            .into_iter()
+            .filter(|location| members.contains(&location.walker_id))
+            .collect::<Vec<_>>();
+        locations.sort_by_key(|location| location.walker_id.to_string());
+
+        Ok(locations)
+    }
+}
+
+/// Whether the walker's location is shared with the booking at `now`: while
+/// the walk is in progress, or around its scheduled time once confirmed
+fn is_active(booking: &Booking, now: DateTime<Utc>) -> bool {
+    match booking.status {
+        BookingStatus::InProgress => true,
+        BookingStatus::Confirmed => {
+            now >= booking.scheduled_start - chrono::Duration::minutes(LEAD_MINUTES)
+                && now <= booking.scheduled_end
+        }
+        _ => false,
+    }
+}
+
+fn poll_interval() -> tokio::time::Interval {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    interval
+}
+
+fn json_event<T: Serialize>(name: &str, payload: &T) -> Option<Event> {
+    match Event::default().event(name).json_data(payload) {
+        Ok(event) => Some(event),
+        Err(e) => {
+            tracing::error!("Failed to serialize {} event: {}", name, e);
+            None
+        }
+    }
+}
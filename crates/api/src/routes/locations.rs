@@ -13,6 +13,9 @@ use crate::{
     state::AppState,
 };
 
+/// Country of a location created without one
+const DEFAULT_COUNTRY: &str = "US";
+
 #[derive(Debug, Deserialize)]
 pub struct CreateLocationRequest {
     pub name: String,
@@ -20,6 +23,8 @@ pub struct CreateLocationRequest {
     pub city: String,
     pub state: String,
     pub zip_code: String,
+    /// ISO 3166-1 alpha-2 country code; defaults to the US
+    pub country: Option<String>,
     pub latitude: f64,
     pub longitude: f64,
     pub notes: Option<String>,
@@ -33,6 +38,7 @@ pub struct UpdateLocationRequest {
     pub city: Option<String>,
     pub state: Option<String>,
     pub zip_code: Option<String>,
+    pub country: Option<String>,
     pub latitude: Option<f64>,
     pub longitude: Option<f64>,
     pub notes: Option<String>,
@@ -47,6 +53,7 @@ pub struct LocationResponse {
     pub city: String,
     pub state: String,
     pub zip_code: String,
+    pub country: String,
     pub full_address: String,
     pub latitude: f64,
     pub longitude: f64,
@@ -101,6 +108,10 @@ pub async fn create_location(
             city: req.city,
             state: req.state,
             zip_code: req.zip_code,
+            country: match &req.country {
+                Some(country) => country_code(country)?,
+                None => DEFAULT_COUNTRY.to_string(),
+            },
             latitude: req.latitude,
             longitude: req.longitude,
             notes: req.notes,
@@ -117,6 +128,7 @@ pub async fn create_location(
         city: location.city,
         state: location.state,
         zip_code: location.zip_code,
+        country: location.country,
         full_address,
         latitude: location.latitude,
         longitude: location.longitude,
@@ -144,6 +156,7 @@ pub async fn list_locations(
                 city: l.city,
                 state: l.state,
                 zip_code: l.zip_code,
+                country: l.country,
                 full_address,
                 latitude: l.latitude,
                 longitude: l.longitude,
@@ -203,6 +216,7 @@ pub async fn update_location(
         city: req.city,
         state: req.state,
         zip_code: req.zip_code,
+        country: req.country.as_deref().map(country_code).transpose()?,
         latitude: req.latitude,
         longitude: req.longitude,
         notes: req.notes,
@@ -221,6 +235,7 @@ pub async fn update_location(
         city: updated.city,
         state: updated.state,
         zip_code: updated.zip_code,
+        country: updated.country,
         full_address,
         latitude: updated.latitude,
         longitude: updated.longitude,
@@ -286,6 +301,7 @@ pub async fn set_default_location(
         city: updated.city,
         state: updated.state,
         zip_code: updated.zip_code,
+        country: updated.country,
         full_address,
         latitude: updated.latitude,
         longitude: updated.longitude,
@@ -293,3 +309,14 @@ pub async fn set_default_location(
         is_default: updated.is_default,
     }))
 }
+
+/// Normalize an ISO 3166-1 alpha-2 country code
+fn country_code(country: &str) -> ApiResult<String> {
+    let country = country.trim().to_uppercase();
+    if country.len() != 2 || !country.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(ApiError::from(AppError::Validation(
+            "Country must be a two-letter ISO code".to_string(),
+        )));
+    }
+    Ok(country)
+}
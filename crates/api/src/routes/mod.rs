@@ -7,12 +7,17 @@ pub mod blocks;
 pub mod bookings;
 pub mod branding;
 pub mod calendar;
+pub mod cancellation_policies;
 pub mod checkout;
 pub mod contexts;
+pub mod currency;
 pub mod dashboard;
+pub mod disputes;
 pub mod feedback;
 pub mod health;
 pub mod invitations;
+pub mod ledger;
+pub mod live_tracking;
 pub mod locations;
 pub mod oauth;
 pub mod payment_methods;
@@ -21,15 +26,21 @@ pub mod payouts;
 pub mod pets;
 pub mod phone_auth;
 pub mod platform_auth;
+pub mod processing_fees;
 pub mod prometheus;
+pub mod reconciliation;
 pub mod recurring_bookings;
 pub mod service_areas;
 pub mod services;
 pub mod subscriptions;
+pub mod tips;
 pub mod travel_time;
 pub mod user_identities;
 pub mod users;
+pub mod walk_trails;
+pub mod walker_earnings;
 pub mod walker_profiles;
 pub mod wallet_auth;
+pub mod webhook_events;
 pub mod webhooks;
 pub mod working_hours;
@@ -95,6 +95,9 @@ pub struct CreatePaymentMethodRequest {
     pub exp_year: Option<i32>,
     /// Cardholder name
     pub cardholder_name: Option<String>,
+    /// For cards: issuing country (Stripe's `card.country`), used to price
+    /// international cards
+    pub card_country: Option<String>,
     /// For bank accounts: bank name
     pub bank_name: Option<String>,
     /// For bank accounts: last 4 of account number
@@ -169,6 +172,7 @@ pub async fn create_payment_method(
         exp_month: req.exp_month,
         exp_year: req.exp_year,
         cardholder_name: req.cardholder_name,
+        card_country: req.card_country,
         bank_name: req.bank_name,
         account_last_four: req.account_last_four,
         wallet_type: req.wallet_type,
@@ -115,7 +115,7 @@ pub struct StripeOAuthCallback {
 
 /// Handle Stripe Connect OAuth callback
 pub async fn stripe_connect_callback(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     _auth_user: AuthUser,
     tenant: TenantContext,
     Json(req): Json<StripeOAuthCallback>,
@@ -190,6 +190,14 @@ pub async fn stripe_connect_callback(
 
     let provider = PaymentProviderRepository::create(&tenant.pool, tenant.org_id, input).await?;
 
+    // Route the account's Connect webhooks to this organization
+    PaymentProviderRepository::register_stripe_account(
+        &state.pool,
+        tenant.org_id,
+        &oauth_response.stripe_user_id,
+    )
+    .await?;
+
     Ok(Json(provider_to_response(provider)))
 }
 
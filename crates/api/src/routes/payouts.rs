@@ -3,20 +3,20 @@ use axum::{
     Json,
 };
 use db::{
-    models::{
-        CreatePayout, PaymentProviderType, Payout, PayoutStatus, UpdatePayout, UpdatePayoutSettings,
-    },
-    PaymentProviderRepository, PayoutRepository,
+    models::{CreatePayout, PayoutLineItem, PayoutStatus, UpdatePayoutSettings},
+    DisputeRepository, PayoutRepository, RefundRepository, TransactionRepository,
 };
+use integrations::gateway::PayoutSpeed;
 use serde::{Deserialize, Serialize};
-use shared::types::OrganizationId;
+use shared::types::{Currency, Money};
 use shared::AppError;
-use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::{
     auth::{AuthUser, TenantContext},
     error::{ApiError, ApiResult},
+    payments::organization_currency,
+    payouts,
     state::AppState,
 };
 
@@ -41,6 +41,7 @@ pub struct PayoutResponse {
     pub amount_cents: i32,
     pub fee_cents: i32,
     pub net_amount_cents: i32,
+    pub currency: String,
     pub status: String,
     pub initiated_at: Option<String>,
     pub completed_at: Option<String>,
@@ -53,6 +54,7 @@ pub struct PayoutResponse {
 /// Payout summary response
 #[derive(Debug, Serialize)]
 pub struct PayoutSummaryResponse {
+    pub currency: String,
     pub total_payouts: i64,
     pub total_amount_cents: i64,
     pub pending_amount_cents: i64,
@@ -150,15 +152,27 @@ pub async fn update_payout_settings(
     }))
 }
 
-/// Get payout summary for the organization
+#[derive(Debug, Deserialize)]
+pub struct PayoutSummaryQuery {
+    /// Defaults to the organization's currency
+    pub currency: Option<Currency>,
+}
+
+/// Get payout summary for the organization in one currency
 pub async fn get_payout_summary(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     _auth_user: AuthUser,
     tenant: TenantContext,
+    Query(query): Query<PayoutSummaryQuery>,
 ) -> ApiResult<Json<PayoutSummaryResponse>> {
-    let summary = PayoutRepository::get_summary(&tenant.pool, tenant.org_id).await?;
+    let currency = match query.currency {
+        Some(currency) => currency,
+        None => organization_currency(&state, &tenant).await?,
+    };
+    let summary = PayoutRepository::get_summary(&tenant.pool, tenant.org_id, currency).await?;
 
     Ok(Json(PayoutSummaryResponse {
+        currency: currency.to_string(),
         total_payouts: summary.total_payouts,
         total_amount_cents: summary.total_amount_cents,
         pending_amount_cents: summary.pending_amount_cents,
@@ -188,9 +202,10 @@ pub async fn list_payouts(
             "paid" => PayoutStatus::Paid,
             "failed" => PayoutStatus::Failed,
             "canceled" | "cancelled" => PayoutStatus::Canceled,
+            "manual" => PayoutStatus::Manual,
             _ => {
                 return Err(ApiError::from(AppError::Validation(
-                    "Invalid status filter. Must be 'pending', 'in_transit', 'paid', 'failed', or 'canceled'".to_string(),
+                    "Invalid status filter. Must be 'pending', 'in_transit', 'paid', 'failed', 'canceled', or 'manual'".to_string(),
                 )))
             }
         })
@@ -214,6 +229,7 @@ pub async fn list_payouts(
             amount_cents: p.amount_cents,
             fee_cents: p.fee_cents,
             net_amount_cents: p.net_amount_cents,
+            currency: p.currency.to_string(),
             status: p.status.to_string(),
             initiated_at: p.initiated_at.map(|dt| dt.to_rfc3339()),
             completed_at: p.completed_at.map(|dt| dt.to_rfc3339()),
@@ -227,13 +243,70 @@ pub async fn list_payouts(
     Ok(Json(response))
 }
 
-/// Get a specific payout
+/// A payout with the transactions, refunds and fees that make it up
+#[derive(Debug, Serialize)]
+pub struct PayoutDetailResponse {
+    pub id: String,
+    pub amount_cents: i32,
+    /// Fee for the payout itself, such as for instant payouts
+    pub fee_cents: i32,
+    pub net_amount_cents: i32,
+    pub currency: String,
+    pub status: String,
+    pub provider_payout_id: Option<String>,
+    pub failure_code: Option<String>,
+    pub failure_message: Option<String>,
+    pub initiated_at: Option<String>,
+    pub arrival_date: Option<String>,
+    pub completed_at: Option<String>,
+    pub period_start: String,
+    pub period_end: String,
+    pub transaction_count: i32,
+    pub transactions: Vec<PayoutTransactionResponse>,
+    /// Refunds of transactions an earlier payout covered, deducted from this
+    /// one
+    pub late_refunds: Vec<PayoutRefundResponse>,
+    pub created_at: String,
+}
+
+/// A transaction's part in a payout
+#[derive(Debug, Serialize)]
+pub struct PayoutTransactionResponse {
+    pub transaction_id: String,
+    pub booking_id: Option<String>,
+    pub walker_id: Option<String>,
+    pub charged_at: String,
+    pub total_cents: i32,
+    pub tip_cents: i32,
+    pub tax_cents: i32,
+    pub platform_fee_cents: i32,
+    pub processing_fee_cents: i32,
+    /// What the organization earned from the charge
+    pub payout_cents: i64,
+    /// Refunds the payout deducted
+    pub refunds: Vec<PayoutRefundResponse>,
+    /// Taken back by a dispute lost before the payout
+    pub dispute_reversed_cents: i64,
+    /// What the transaction added to the payout
+    pub amount_cents: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PayoutRefundResponse {
+    pub refund_id: String,
+    pub amount_cents: i32,
+    /// Taken back from the payout by the refund
+    pub reversed_cents: i64,
+    pub status: String,
+}
+
+/// Get a specific payout and what makes it up
 pub async fn get_payout(
     State(_state): State<AppState>,
     _auth_user: AuthUser,
     tenant: TenantContext,
     Path(id): Path<String>,
-) -> ApiResult<Json<PayoutResponse>> {
+) -> ApiResult<Json<PayoutDetailResponse>> {
     let payout_id: Uuid = id
         .parse()
         .map_err(|_| ApiError::from(AppError::Validation("Invalid payout ID".to_string())))?;
@@ -242,29 +315,94 @@ pub async fn get_payout(
         .await?
         .ok_or_else(|| ApiError::from(AppError::NotFound("Payout not found".to_string())))?;
 
-    Ok(Json(PayoutResponse {
+    let mut transactions = Vec::with_capacity(payout.transaction_ids.len());
+    for transaction_id in &payout.transaction_ids {
+        let Some(transaction) =
+            TransactionRepository::get_by_id(&tenant.pool, tenant.org_id, *transaction_id).await?
+        else {
+            continue;
+        };
+        let refunds =
+            RefundRepository::list_by_transaction(&tenant.pool, tenant.org_id, transaction.id)
+                .await?;
+        let dispute = DisputeRepository::get_by_transaction(&tenant.pool, transaction.id).await?;
+        let item = PayoutLineItem::for_payout(&payout, &transaction, &refunds, dispute.as_ref());
+
+        transactions.push(PayoutTransactionResponse {
+            transaction_id: transaction.id.to_string(),
+            booking_id: transaction.related_booking_id().map(|id| id.to_string()),
+            walker_id: transaction.provider_user_id.map(|id| id.to_string()),
+            charged_at: transaction.created_at.to_rfc3339(),
+            total_cents: transaction.total_cents,
+            tip_cents: transaction.tip_cents,
+            tax_cents: transaction.tax_cents,
+            platform_fee_cents: transaction.platform_fee_cents,
+            processing_fee_cents: transaction.processing_fee_cents,
+            payout_cents: item.payout_cents,
+            refunds: item
+                .refunds
+                .iter()
+                .filter_map(|(refund_id, reversed_cents)| {
+                    let refund = refunds.iter().find(|r| r.id == *refund_id)?;
+                    Some(PayoutRefundResponse {
+                        refund_id: refund.id.to_string(),
+                        amount_cents: refund.amount_cents,
+                        reversed_cents: *reversed_cents,
+                        status: format!("{:?}", refund.status).to_lowercase(),
+                    })
+                })
+                .collect(),
+            dispute_reversed_cents: item.dispute_reversed_cents,
+            amount_cents: item.amount_cents,
+        });
+    }
+
+    let late_refunds =
+        RefundRepository::list_by_ids(&tenant.pool, tenant.org_id, &payout.refund_ids)
+            .await?
+            .into_iter()
+            .filter(|r| !payout.transaction_ids.contains(&r.transaction_id))
+            .map(|refund| PayoutRefundResponse {
+                refund_id: refund.id.to_string(),
+                amount_cents: refund.amount_cents,
+                reversed_cents: i64::from(refund.provider_payout_reversed_cents),
+                status: format!("{:?}", refund.status).to_lowercase(),
+            })
+            .collect();
+
+    Ok(Json(PayoutDetailResponse {
         id: payout.id.to_string(),
         amount_cents: payout.amount_cents,
         fee_cents: payout.fee_cents,
         net_amount_cents: payout.net_amount_cents,
+        currency: payout.currency.to_string(),
         status: payout.status.to_string(),
+        provider_payout_id: payout.stripe_payout_id.or(payout.square_payout_id),
+        failure_code: payout.failure_code,
+        failure_message: payout.failure_message,
         initiated_at: payout.initiated_at.map(|dt| dt.to_rfc3339()),
+        arrival_date: payout.arrival_date.map(|dt| dt.to_rfc3339()),
         completed_at: payout.completed_at.map(|dt| dt.to_rfc3339()),
         period_start: payout.period_start.to_rfc3339(),
         period_end: payout.period_end.to_rfc3339(),
         transaction_count: payout.transaction_count,
+        transactions,
+        late_refunds,
         created_at: payout.created_at.to_rfc3339(),
     }))
 }
 
 /// Request an instant payout
 pub async fn request_instant_payout(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     _auth_user: AuthUser,
     tenant: TenantContext,
 ) -> ApiResult<Json<PayoutResponse>> {
+    // Payouts are made in the organization's currency
+    let currency = organization_currency(&state, &tenant).await?;
+
     // Get the org's payout summary to check available balance
-    let summary = PayoutRepository::get_summary(&tenant.pool, tenant.org_id).await?;
+    let summary = PayoutRepository::get_summary(&tenant.pool, tenant.org_id, currency).await?;
 
     // For now, use pending_amount_cents as available amount
     // In production, this would be calculated from settled transactions
@@ -282,8 +420,8 @@ pub async fn request_instant_payout(
     if let Some(settings) = &settings {
         if available_amount < settings.minimum_payout_cents {
             return Err(ApiError::from(AppError::Validation(format!(
-                "Available balance is below minimum payout amount of ${:.2}",
-                settings.minimum_payout_cents as f64 / 100.0
+                "Available balance is below minimum payout amount of {}",
+                Money::new(settings.minimum_payout_cents.into(), currency)
             ))));
         }
     }
@@ -299,27 +437,24 @@ pub async fn request_instant_payout(
         amount_cents: available_amount,
         fee_cents,
         net_amount_cents: net_amount,
-        currency: "USD".to_string(),
+        currency,
         period_start: now - chrono::Duration::days(30), // Period start (last 30 days)
         period_end: now,                                // Period end
         transaction_count: 0, // Would be calculated from actual transactions
         transaction_ids: vec![],
+        refund_ids: vec![],
+        walker_earning_ids: vec![],
+        scheduled_for: None,
     };
 
-    let payout = PayoutRepository::create(&tenant.pool, tenant.org_id, input).await?;
+    // Only scheduled payouts can conflict with an existing one
+    let payout = PayoutRepository::create(&tenant.pool, tenant.org_id, input)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::Internal("Payout not created".to_string())))?;
 
     // Initiate payout through payment provider
-    if let Ok((stripe_id, square_id)) =
-        initiate_provider_payout(&tenant.pool, tenant.org_id, &payout).await
-    {
-        // Update payout with external ID
-        let update = UpdatePayout {
-            stripe_payout_id: stripe_id,
-            square_payout_id: square_id,
-            initiated_at: Some(chrono::Utc::now()),
-            ..Default::default()
-        };
-        let _ = PayoutRepository::update(&tenant.pool, payout.id, update).await;
+    if let Err(e) = payouts::send_payout(&state, &tenant, &payout, PayoutSpeed::Instant).await {
+        tracing::warn!("Failed to send instant payout {}: {}", payout.id, e.0);
     }
 
     Ok(Json(PayoutResponse {
@@ -327,6 +462,7 @@ pub async fn request_instant_payout(
         amount_cents: payout.amount_cents,
         fee_cents: payout.fee_cents,
         net_amount_cents: payout.net_amount_cents,
+        currency: payout.currency.to_string(),
         status: payout.status.to_string(),
         initiated_at: payout.initiated_at.map(|dt| dt.to_rfc3339()),
         completed_at: payout.completed_at.map(|dt| dt.to_rfc3339()),
@@ -336,84 +472,3 @@ pub async fn request_instant_payout(
         created_at: payout.created_at.to_rfc3339(),
     }))
 }
-
-// Helper function to initiate payouts through payment providers
-
-async fn initiate_provider_payout(
-    pool: &PgPool,
-    org_id: OrganizationId,
-    payout: &Payout,
-) -> Result<(Option<String>, Option<String>), ApiError> {
-    // Get the primary payment provider
-    let provider = PaymentProviderRepository::get_primary(pool, org_id)
-        .await?
-        .ok_or_else(|| {
-            ApiError::from(AppError::Internal(
-                "No payment provider configured".to_string(),
-            ))
-        })?;
-
-    match provider.provider_type {
-        PaymentProviderType::Stripe | PaymentProviderType::Platform => {
-            let stripe_id = initiate_stripe_payout(&provider, payout).await?;
-            Ok((Some(stripe_id), None))
-        }
-        PaymentProviderType::Square => {
-            // Square doesn't support payouts the same way - funds go directly to merchant
-            // Return a placeholder ID
-            let square_id = format!("square_payout_{}", payout.id);
-            Ok((None, Some(square_id)))
-        }
-    }
-}
-
-async fn initiate_stripe_payout(
-    provider: &db::models::PaymentProvider,
-    payout: &Payout,
-) -> Result<String, ApiError> {
-    let stripe_secret = std::env::var("STRIPE_SECRET_KEY")
-        .map_err(|_| ApiError::from(AppError::Internal("Stripe not configured".to_string())))?;
-
-    let connected_account = provider.stripe_account_id.as_ref().ok_or_else(|| {
-        ApiError::from(AppError::Internal(
-            "Stripe account not connected".to_string(),
-        ))
-    })?;
-
-    let client = reqwest::Client::new();
-
-    let params = vec![
-        ("amount", payout.net_amount_cents.to_string()),
-        ("currency", "usd".to_string()),
-        ("method", "instant".to_string()), // or "standard" for regular speed
-    ];
-
-    let response = client
-        .post("https://api.stripe.com/v1/payouts")
-        .header("Authorization", format!("Bearer {}", stripe_secret))
-        .header("Stripe-Account", connected_account)
-        .form(&params)
-        .send()
-        .await
-        .map_err(|e| ApiError::from(AppError::Internal(e.to_string())))?;
-
-    if !response.status().is_success() {
-        let error_body = response.text().await.unwrap_or_default();
-        return Err(ApiError::from(AppError::Internal(format!(
-            "Stripe payout error: {}",
-            error_body
-        ))));
-    }
-
-    #[derive(serde::Deserialize)]
-    struct PayoutResponse {
-        id: String,
-    }
-
-    let stripe_payout: PayoutResponse = response
-        .json()
-        .await
-        .map_err(|e| ApiError::from(AppError::Internal(e.to_string())))?;
-
-    Ok(stripe_payout.id)
-}
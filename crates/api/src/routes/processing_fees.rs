@@ -0,0 +1,175 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use db::models::{
+    CardRegion, PaymentMethodType, PaymentProviderType, ProcessingFeeSchedule,
+    UpsertProcessingFeeSchedule,
+};
+use db::ProcessingFeeScheduleRepository;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use shared::AppError;
+use uuid::Uuid;
+
+use crate::{
+    auth::{AuthUser, TenantContext},
+    error::{ApiError, ApiResult},
+    state::AppState,
+};
+
+#[derive(Debug, Serialize)]
+pub struct ProcessingFeeScheduleResponse {
+    pub id: String,
+    pub provider_type: String,
+    /// `None` when the schedule applies to every payment method
+    pub method_type: Option<String>,
+    /// `None` when the schedule applies to domestic and international cards
+    pub card_region: Option<String>,
+    pub percent: Decimal,
+    pub fixed_cents: i64,
+    pub cap_cents: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpsertProcessingFeeScheduleRequest {
+    /// "stripe", "square" or "platform"
+    pub provider_type: String,
+    /// "card", "apple_pay", "google_pay", "shop_pay", "link" or
+    /// "bank_account"; omit for every method
+    pub method_type: Option<String>,
+    /// "domestic" or "international"; omit for both
+    pub card_region: Option<String>,
+    /// Percentage of the amount charged (e.g. 2.9)
+    pub percent: Decimal,
+    #[serde(default)]
+    pub fixed_cents: i64,
+    pub cap_cents: Option<i64>,
+}
+
+/// GET /admin/processing-fees - The organization's processor fee schedules
+pub async fn list_processing_fees(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+) -> ApiResult<Json<Vec<ProcessingFeeScheduleResponse>>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let schedules = ProcessingFeeScheduleRepository::list(&tenant.pool, tenant.org_id).await?;
+
+    Ok(Json(schedules.into_iter().map(schedule_response).collect()))
+}
+
+/// PUT /admin/processing-fees - Set what a provider charges for a payment
+/// method and card region
+pub async fn upsert_processing_fee(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Json(req): Json<UpsertProcessingFeeScheduleRequest>,
+) -> ApiResult<Json<ProcessingFeeScheduleResponse>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let provider_type = match req.provider_type.to_lowercase().as_str() {
+        "stripe" => PaymentProviderType::Stripe,
+        "square" => PaymentProviderType::Square,
+        "platform" => PaymentProviderType::Platform,
+        _ => {
+            return Err(ApiError::from(AppError::Validation(
+                "provider_type must be 'stripe', 'square' or 'platform'".to_string(),
+            )))
+        }
+    };
+    let method_type = req
+        .method_type
+        .as_deref()
+        .map(parse_method_type)
+        .transpose()?;
+    let card_region = match req.card_region.as_deref().map(str::to_lowercase).as_deref() {
+        None => None,
+        Some("domestic") => Some(CardRegion::Domestic),
+        Some("international") => Some(CardRegion::International),
+        Some(_) => {
+            return Err(ApiError::from(AppError::Validation(
+                "card_region must be 'domestic' or 'international'".to_string(),
+            )))
+        }
+    };
+
+    if req.percent < Decimal::ZERO || req.percent >= Decimal::ONE_HUNDRED {
+        return Err(ApiError::from(AppError::Validation(
+            "percent must be between 0 and 100".to_string(),
+        )));
+    }
+    if req.fixed_cents < 0 || req.cap_cents.is_some_and(|cap| cap < 0) {
+        return Err(ApiError::from(AppError::Validation(
+            "Fees cannot be negative".to_string(),
+        )));
+    }
+
+    let saved = ProcessingFeeScheduleRepository::upsert(
+        &tenant.pool,
+        tenant.org_id,
+        UpsertProcessingFeeSchedule {
+            provider_type,
+            method_type,
+            card_region,
+            percent: req.percent,
+            fixed_cents: req.fixed_cents,
+            cap_cents: req.cap_cents,
+        },
+    )
+    .await?;
+
+    Ok(Json(schedule_response(saved)))
+}
+
+/// DELETE /admin/processing-fees/:id - Remove a schedule; payments fall back
+/// to a broader schedule or the provider's list price
+pub async fn delete_processing_fee(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> ApiResult<StatusCode> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let schedule_id: Uuid = id
+        .parse()
+        .map_err(|_| ApiError::from(AppError::Validation("Invalid schedule ID".to_string())))?;
+
+    if !ProcessingFeeScheduleRepository::delete(&tenant.pool, tenant.org_id, schedule_id).await? {
+        return Err(ApiError::from(AppError::NotFound(
+            "Processing fee schedule not found".to_string(),
+        )));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn parse_method_type(method_type: &str) -> ApiResult<PaymentMethodType> {
+    match method_type.to_lowercase().as_str() {
+        "card" => Ok(PaymentMethodType::Card),
+        "apple_pay" => Ok(PaymentMethodType::ApplePay),
+        "google_pay" => Ok(PaymentMethodType::GooglePay),
+        "shop_pay" => Ok(PaymentMethodType::ShopPay),
+        "link" => Ok(PaymentMethodType::Link),
+        "bank_account" => Ok(PaymentMethodType::BankAccount),
+        _ => Err(ApiError::from(AppError::Validation(
+            "Invalid method type. Must be 'card', 'apple_pay', 'google_pay', 'shop_pay', 'link', or 'bank_account'".to_string(),
+        ))),
+    }
+}
+
+fn schedule_response(schedule: ProcessingFeeSchedule) -> ProcessingFeeScheduleResponse {
+    ProcessingFeeScheduleResponse {
+        id: schedule.id.to_string(),
+        provider_type: schedule.provider_type.to_string(),
+        method_type: schedule.method_type.map(|m| m.to_string()),
+        card_region: schedule.card_region.map(|r| r.to_string()),
+        percent: schedule.percent,
+        fixed_cents: schedule.fixed_cents,
+        cap_cents: schedule.cap_cents,
+    }
+}
@@ -0,0 +1,224 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use db::models::{ReconciliationMismatch, ReconciliationRun};
+use db::ReconciliationRepository;
+use serde::{Deserialize, Serialize};
+use shared::AppError;
+use uuid::Uuid;
+
+use crate::{
+    auth::{AuthUser, TenantContext},
+    error::{ApiError, ApiResult},
+    reconciliation,
+    state::AppState,
+};
+
+/// Longest period a single request may reconcile
+const MAX_RECONCILIATION_DAYS: i64 = 31;
+
+#[derive(Debug, Serialize)]
+pub struct ReconciliationRunResponse {
+    pub id: String,
+    pub provider_id: String,
+    pub period_start: String,
+    pub period_end: String,
+    pub status: String,
+    pub local_count: i32,
+    pub remote_count: i32,
+    pub matched_count: i32,
+    pub mismatch_count: i32,
+    pub error_message: Option<String>,
+    pub started_at: String,
+    pub completed_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReconciliationMismatchResponse {
+    pub id: String,
+    pub run_id: String,
+    pub kind: String,
+    pub transaction_id: Option<String>,
+    pub external_payment_id: Option<String>,
+    pub local_amount_cents: Option<i64>,
+    pub remote_amount_cents: Option<i64>,
+    pub local_status: Option<String>,
+    pub remote_status: Option<String>,
+    pub resolved: bool,
+    pub resolved_at: Option<String>,
+    pub resolution_note: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReconciliationRunDetailResponse {
+    #[serde(flatten)]
+    pub run: ReconciliationRunResponse,
+    pub mismatches: Vec<ReconciliationMismatchResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StartReconciliationRequest {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolveMismatchRequest {
+    pub note: Option<String>,
+}
+
+/// POST /admin/reconciliation/runs - Reconcile every active provider for a
+/// period
+pub async fn start_reconciliation(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Json(req): Json<StartReconciliationRequest>,
+) -> ApiResult<Json<Vec<ReconciliationRunResponse>>> {
+    tenant.require_manager(auth.user_id).await?;
+    if req.to <= req.from {
+        return Err(ApiError::from(AppError::Validation(
+            "The end of the period must be after its start".to_string(),
+        )));
+    }
+    if req.to - req.from > Duration::days(MAX_RECONCILIATION_DAYS) {
+        return Err(ApiError::from(AppError::Validation(format!(
+            "Reconcile at most {} days at a time",
+            MAX_RECONCILIATION_DAYS
+        ))));
+    }
+
+    let runs = reconciliation::reconcile_all(&state, &tenant, req.from, req.to, Some(auth.user_id))
+        .await?;
+
+    Ok(Json(runs.iter().map(run_response).collect()))
+}
+
+/// GET /admin/reconciliation/runs - Recent runs, newest first
+pub async fn list_runs(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Query(query): Query<ListQuery>,
+) -> ApiResult<Json<Vec<ReconciliationRunResponse>>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let runs = ReconciliationRepository::list_runs(
+        &tenant.pool,
+        tenant.org_id,
+        query.limit.unwrap_or(50).min(200),
+        query.offset.unwrap_or(0),
+    )
+    .await?;
+
+    Ok(Json(runs.iter().map(run_response).collect()))
+}
+
+/// GET /admin/reconciliation/runs/:id - A run and its mismatches
+pub async fn get_run(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path(run_id): Path<Uuid>,
+) -> ApiResult<Json<ReconciliationRunDetailResponse>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let run = ReconciliationRepository::get_run(&tenant.pool, tenant.org_id, run_id)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::NotFound("Reconciliation run".to_string())))?;
+    let mismatches =
+        ReconciliationRepository::list_mismatches(&tenant.pool, tenant.org_id, run.id).await?;
+
+    Ok(Json(ReconciliationRunDetailResponse {
+        run: run_response(&run),
+        mismatches: mismatches.iter().map(mismatch_response).collect(),
+    }))
+}
+
+/// GET /admin/reconciliation/mismatches - Mismatches awaiting review
+pub async fn list_open_mismatches(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Query(query): Query<ListQuery>,
+) -> ApiResult<Json<Vec<ReconciliationMismatchResponse>>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let mismatches = ReconciliationRepository::list_open_mismatches(
+        &tenant.pool,
+        tenant.org_id,
+        query.limit.unwrap_or(50).min(200),
+        query.offset.unwrap_or(0),
+    )
+    .await?;
+
+    Ok(Json(mismatches.iter().map(mismatch_response).collect()))
+}
+
+/// POST /admin/reconciliation/mismatches/:id/resolve - Mark a mismatch
+/// reviewed
+pub async fn resolve_mismatch(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path(mismatch_id): Path<Uuid>,
+    Json(req): Json<ResolveMismatchRequest>,
+) -> ApiResult<Json<ReconciliationMismatchResponse>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let mismatch = ReconciliationRepository::resolve_mismatch(
+        &tenant.pool,
+        tenant.org_id,
+        mismatch_id,
+        auth.user_id,
+        req.note.as_deref(),
+    )
+    .await?
+    .ok_or_else(|| ApiError::from(AppError::NotFound("Open mismatch".to_string())))?;
+
+    Ok(Json(mismatch_response(&mismatch)))
+}
+
+fn run_response(run: &ReconciliationRun) -> ReconciliationRunResponse {
+    ReconciliationRunResponse {
+        id: run.id.to_string(),
+        provider_id: run.provider_id.to_string(),
+        period_start: run.period_start.to_rfc3339(),
+        period_end: run.period_end.to_rfc3339(),
+        status: run.status.to_string(),
+        local_count: run.local_count,
+        remote_count: run.remote_count,
+        matched_count: run.matched_count,
+        mismatch_count: run.mismatch_count,
+        error_message: run.error_message.clone(),
+        started_at: run.started_at.to_rfc3339(),
+        completed_at: run.completed_at.map(|dt| dt.to_rfc3339()),
+    }
+}
+
+fn mismatch_response(mismatch: &ReconciliationMismatch) -> ReconciliationMismatchResponse {
+    ReconciliationMismatchResponse {
+        id: mismatch.id.to_string(),
+        run_id: mismatch.run_id.to_string(),
+        kind: mismatch.kind.to_string(),
+        transaction_id: mismatch.transaction_id.map(|id| id.to_string()),
+        external_payment_id: mismatch.external_payment_id.clone(),
+        local_amount_cents: mismatch.local_amount_cents,
+        remote_amount_cents: mismatch.remote_amount_cents,
+        local_status: mismatch.local_status.clone(),
+        remote_status: mismatch.remote_status.clone(),
+        resolved: mismatch.is_resolved(),
+        resolved_at: mismatch.resolved_at.map(|dt| dt.to_rfc3339()),
+        resolution_note: mismatch.resolution_note.clone(),
+        created_at: mismatch.created_at.to_rfc3339(),
+    }
+}
@@ -5,11 +5,13 @@ use axum::{
 };
 use chrono::{Datelike, Duration, NaiveDate, NaiveTime};
 use db::models::{
-    CreateBooking, CreateRecurringBookingSeries, OccurrenceConflict, RecurrenceFrequency,
+    BookingPriceBreakdown, CreateBooking, CreateRecurringBookingSeries, OccurrenceConflict,
+    RecurrenceFrequency,
 };
 use db::{
     check_conflicts_batch, generate_occurrence_dates, to_utc_datetime, BookingRepository,
-    LocationRepository, RecurringBookingRepository, ServiceRepository, UserRepository,
+    LocationRepository, RecurringBookingRepository, ServiceAreaRepository, ServiceRepository,
+    UserRepository,
 };
 use serde::{Deserialize, Serialize};
 use shared::{AppError, DomainError};
@@ -274,6 +276,18 @@ pub async fn create_recurring_booking(
         return Err(ApiError::from(AppError::Forbidden));
     }
 
+    // Price each occurrence with the walker's service area adjustment
+    let matching_areas = ServiceAreaRepository::find_walker_areas_for_location(
+        &tenant.pool,
+        tenant.org_id,
+        walker_id,
+        location.latitude,
+        location.longitude,
+    )
+    .await?;
+    let price_breakdown =
+        BookingPriceBreakdown::calculate(service.base_price_cents, &matching_areas);
+
     // Get day of week from start date
     let day_of_week = start_date.weekday().num_days_from_sunday() as i32;
 
@@ -369,7 +383,7 @@ pub async fn create_recurring_booking(
             timezone: timezone.clone(),
             end_date,
             total_occurrences,
-            price_cents_per_booking: service.base_price_cents,
+            price_cents_per_booking: price_breakdown.total_cents,
             default_notes: req.notes.clone(),
             idempotency_key,
         },
@@ -410,7 +424,8 @@ pub async fn create_recurring_booking(
                 location_id,
                 scheduled_start: start,
                 scheduled_end: end,
-                price_cents: service.base_price_cents,
+                price_cents: price_breakdown.total_cents,
+                price_breakdown: Some(price_breakdown.clone()),
                 notes: req.notes.clone(),
                 recurring_series_id: Some(series.id),
                 occurrence_number: Some((idx + 1) as i32),
@@ -1,11 +1,21 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    http::header::CONTENT_TYPE,
+    response::{IntoResponse, Response},
     Json,
 };
-use db::models::{CreateServiceArea, PolygonPoint, ServiceAreaResponse, UpdateServiceArea};
+use db::models::{
+    CreateServiceArea, PolygonPoint, ServiceArea, ServiceAreaResponse, UpdateServiceArea, UserRole,
+};
 use db::{ServiceAreaRepository, UserRepository};
 use serde::{Deserialize, Serialize};
+use shared::geometry::{
+    geojson_feature, geojson_feature_collection, kml_document, polygons_from_geojson,
+    polygons_from_kml, KmlPlacemark, Polygon,
+};
+use shared::types::Coordinates;
 use shared::AppError;
+use std::collections::HashMap;
 
 use crate::{
     auth::{AuthUser, TenantContext},
@@ -20,6 +30,7 @@ pub struct CreateServiceAreaRequest {
     pub name: String,
     pub color: Option<String>,
     pub polygon: Vec<PolygonPointRequest>,
+    pub holes: Option<Vec<Vec<PolygonPointRequest>>>,
     pub is_active: Option<bool>,
     pub priority: Option<i32>,
     pub price_adjustment_percent: Option<i32>,
@@ -36,13 +47,106 @@ pub struct PolygonPointRequest {
 pub struct UpdateServiceAreaRequest {
     pub name: Option<String>,
     pub color: Option<String>,
+    /// Replaces the whole geometry; holes are cleared unless also given
     pub polygon: Option<Vec<PolygonPointRequest>>,
+    pub holes: Option<Vec<Vec<PolygonPointRequest>>>,
     pub is_active: Option<bool>,
     pub priority: Option<i32>,
     pub price_adjustment_percent: Option<i32>,
     pub notes: Option<String>,
 }
 
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GeoFormat {
+    #[default]
+    GeoJson,
+    Kml,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportServiceAreasRequest {
+    pub format: GeoFormat,
+    /// A GeoJSON object (or string), or a KML document string
+    pub data: serde_json::Value,
+    /// Maximum vertices per imported area; larger areas are simplified
+    pub max_vertices: Option<usize>,
+    pub color: Option<String>,
+    pub is_active: Option<bool>,
+    pub priority: Option<i32>,
+    pub price_adjustment_percent: Option<i32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ExportServiceAreasQuery {
+    #[serde(default)]
+    pub format: GeoFormat,
+}
+
+/// Default vertex budget for imported areas
+const DEFAULT_IMPORT_MAX_VERTICES: usize = 250;
+
+/// Upper bound on the vertex budget a client can request
+const MAX_IMPORT_VERTICES: usize = 1000;
+
+const KML_CONTENT_TYPE: &str = "application/vnd.google-earth.kml+xml";
+
+// MARK: - Helpers
+
+type AreaGeometry = (Vec<PolygonPoint>, Vec<Vec<PolygonPoint>>);
+type AreaGeometryUpdate = (Option<Vec<PolygonPoint>>, Option<Vec<Vec<PolygonPoint>>>);
+
+fn to_coordinates(points: Vec<PolygonPointRequest>) -> Vec<Coordinates> {
+    points
+        .into_iter()
+        .map(|p| Coordinates::new_unchecked(p.lat, p.lng))
+        .collect()
+}
+
+/// Split a geometry polygon into stored exterior and hole point lists
+fn area_geometry(polygon: &Polygon) -> AreaGeometry {
+    let ring = |ring: &[Coordinates]| ring.iter().copied().map(PolygonPoint::from).collect();
+    (
+        ring(polygon.vertices()),
+        polygon.holes().iter().map(|h| ring(h)).collect(),
+    )
+}
+
+/// Validate a requested polygon and holes, normalizing each to an open ring
+fn validate_polygon(
+    points: Vec<PolygonPointRequest>,
+    holes: Option<Vec<Vec<PolygonPointRequest>>>,
+) -> ApiResult<AreaGeometry> {
+    let polygon = Polygon::with_holes(
+        to_coordinates(points),
+        holes
+            .unwrap_or_default()
+            .into_iter()
+            .map(to_coordinates)
+            .collect(),
+    )
+    .map_err(|e| ApiError::from(AppError::Validation(e.to_string())))?;
+
+    Ok(area_geometry(&polygon))
+}
+
+/// Validate the geometry fields of an update request
+fn validate_polygon_update(
+    req_polygon: Option<Vec<PolygonPointRequest>>,
+    req_holes: Option<Vec<Vec<PolygonPointRequest>>>,
+) -> ApiResult<AreaGeometryUpdate> {
+    match req_polygon {
+        Some(points) => {
+            let (polygon, holes) = validate_polygon(points, req_holes)?;
+            Ok((Some(polygon), Some(holes)))
+        }
+        None if req_holes.is_some() => Err(ApiError::from(AppError::Validation(
+            "Holes can only be updated together with the polygon".to_string(),
+        ))),
+        None => Ok((None, None)),
+    }
+}
+
 // MARK: - Walker Endpoints
 
 /// Get current walker's service areas
@@ -85,21 +189,7 @@ pub async fn create_my_service_area(
         return Err(ApiError::from(AppError::Forbidden));
     }
 
-    // Validate polygon has at least 3 points
-    if req.polygon.len() < 3 {
-        return Err(ApiError::from(AppError::Validation(
-            "Polygon must have at least 3 points".to_string(),
-        )));
-    }
-
-    let polygon: Vec<PolygonPoint> = req
-        .polygon
-        .into_iter()
-        .map(|p| PolygonPoint {
-            lat: p.lat,
-            lng: p.lng,
-        })
-        .collect();
+    let (polygon, holes) = validate_polygon(req.polygon, req.holes)?;
 
     let area = ServiceAreaRepository::create(
         &tenant.pool,
@@ -109,6 +199,7 @@ pub async fn create_my_service_area(
             name: req.name,
             color: req.color,
             polygon,
+            holes,
             is_active: req.is_active.unwrap_or(true),
             priority: req.priority,
             price_adjustment_percent: req.price_adjustment_percent,
@@ -150,23 +241,7 @@ pub async fn update_my_service_area(
         return Err(ApiError::from(AppError::Forbidden));
     }
 
-    // Validate polygon if provided
-    if let Some(ref polygon) = req.polygon {
-        if polygon.len() < 3 {
-            return Err(ApiError::from(AppError::Validation(
-                "Polygon must have at least 3 points".to_string(),
-            )));
-        }
-    }
-
-    let polygon = req.polygon.map(|p| {
-        p.into_iter()
-            .map(|pt| PolygonPoint {
-                lat: pt.lat,
-                lng: pt.lng,
-            })
-            .collect()
-    });
+    let (polygon, holes) = validate_polygon_update(req.polygon, req.holes)?;
 
     let updated = ServiceAreaRepository::update(
         &tenant.pool,
@@ -176,6 +251,7 @@ pub async fn update_my_service_area(
             name: req.name,
             color: req.color,
             polygon,
+            holes,
             is_active: req.is_active,
             priority: req.priority,
             price_adjustment_percent: req.price_adjustment_percent,
@@ -312,21 +388,7 @@ pub async fn create_walker_service_area(
         )));
     }
 
-    // Validate polygon
-    if req.polygon.len() < 3 {
-        return Err(ApiError::from(AppError::Validation(
-            "Polygon must have at least 3 points".to_string(),
-        )));
-    }
-
-    let polygon: Vec<PolygonPoint> = req
-        .polygon
-        .into_iter()
-        .map(|p| PolygonPoint {
-            lat: p.lat,
-            lng: p.lng,
-        })
-        .collect();
+    let (polygon, holes) = validate_polygon(req.polygon, req.holes)?;
 
     let area = ServiceAreaRepository::create(
         &tenant.pool,
@@ -336,6 +398,7 @@ pub async fn create_walker_service_area(
             name: req.name,
             color: req.color,
             polygon,
+            holes,
             is_active: req.is_active.unwrap_or(true),
             priority: req.priority,
             price_adjustment_percent: req.price_adjustment_percent,
@@ -368,23 +431,7 @@ pub async fn update_service_area(
         .parse()
         .map_err(|_| ApiError::from(AppError::Validation("Invalid area ID".to_string())))?;
 
-    // Validate polygon if provided
-    if let Some(ref polygon) = req.polygon {
-        if polygon.len() < 3 {
-            return Err(ApiError::from(AppError::Validation(
-                "Polygon must have at least 3 points".to_string(),
-            )));
-        }
-    }
-
-    let polygon = req.polygon.map(|p| {
-        p.into_iter()
-            .map(|pt| PolygonPoint {
-                lat: pt.lat,
-                lng: pt.lng,
-            })
-            .collect()
-    });
+    let (polygon, holes) = validate_polygon_update(req.polygon, req.holes)?;
 
     let updated = ServiceAreaRepository::update(
         &tenant.pool,
@@ -394,6 +441,7 @@ pub async fn update_service_area(
             name: req.name,
             color: req.color,
             polygon,
+            holes,
             is_active: req.is_active,
             priority: req.priority,
             price_adjustment_percent: req.price_adjustment_percent,
@@ -437,6 +485,248 @@ pub async fn delete_service_area(
     Ok(Json(DeleteResponse { success: true }))
 }
 
+/// Import service areas for a walker from GeoJSON or KML (admin only)
+///
+/// Every polygon is validated (and simplified to the vertex limit) before any
+/// area is created, so a bad polygon rejects the whole import.
+pub async fn import_walker_service_areas(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path(walker_id): Path<String>,
+    Json(req): Json<ImportServiceAreasRequest>,
+) -> ApiResult<Json<Vec<ServiceAreaResponse>>> {
+    // Verify user is admin
+    let user = UserRepository::find_by_id(&tenant.pool, tenant.org_id, auth.user_id)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::Forbidden))?;
+
+    if !user.is_admin() {
+        return Err(ApiError::from(AppError::Forbidden));
+    }
+
+    let walker_user_id = walker_id
+        .parse()
+        .map_err(|_| ApiError::from(AppError::Validation("Invalid walker ID".to_string())))?;
+
+    // Verify walker exists
+    let walker = UserRepository::find_by_id(&tenant.pool, tenant.org_id, walker_user_id)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::NotFound("Walker not found".to_string())))?;
+
+    if !walker.is_walker() {
+        return Err(ApiError::from(AppError::Validation(
+            "User is not a walker".to_string(),
+        )));
+    }
+
+    let max_vertices = req
+        .max_vertices
+        .unwrap_or(DEFAULT_IMPORT_MAX_VERTICES)
+        .min(MAX_IMPORT_VERTICES);
+
+    let imported = match (req.format, &req.data) {
+        (GeoFormat::GeoJson, serde_json::Value::String(text)) => {
+            let value: serde_json::Value = serde_json::from_str(text).map_err(|e| {
+                ApiError::from(AppError::Validation(format!("Invalid GeoJSON: {}", e)))
+            })?;
+            polygons_from_geojson(&value)
+        }
+        (GeoFormat::GeoJson, value) => polygons_from_geojson(value),
+        (GeoFormat::Kml, serde_json::Value::String(text)) => polygons_from_kml(text),
+        (GeoFormat::Kml, _) => {
+            return Err(ApiError::from(AppError::Validation(
+                "KML data must be a string".to_string(),
+            )))
+        }
+    }
+    .map_err(|e| ApiError::from(AppError::Validation(e.to_string())))?;
+
+    // Validate everything before creating anything
+    let mut areas = Vec::with_capacity(imported.len());
+    for (index, named) in imported.into_iter().enumerate() {
+        let name = named
+            .name
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| format!("Imported area {}", index + 1));
+
+        let polygon = named.polygon.simplified(max_vertices);
+        polygon
+            .validate()
+            .map_err(|e| ApiError::from(AppError::Validation(format!("{}: {}", name, e))))?;
+
+        areas.push((name, polygon));
+    }
+
+    let mut responses = Vec::with_capacity(areas.len());
+    for (name, polygon) in areas {
+        let (polygon, holes) = area_geometry(&polygon);
+        let area = ServiceAreaRepository::create(
+            &tenant.pool,
+            CreateServiceArea {
+                organization_id: tenant.org_id,
+                walker_id: walker_user_id,
+                name,
+                color: req.color.clone(),
+                polygon,
+                holes,
+                is_active: req.is_active.unwrap_or(true),
+                priority: req.priority,
+                price_adjustment_percent: req.price_adjustment_percent,
+                notes: None,
+            },
+        )
+        .await?;
+        responses.push(ServiceAreaResponse::from(area));
+    }
+
+    Ok(Json(responses))
+}
+
+/// Export a single service area as a GeoJSON Feature or KML document (admin only)
+pub async fn export_service_area(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path(area_id): Path<String>,
+    Query(query): Query<ExportServiceAreasQuery>,
+) -> ApiResult<Response> {
+    // Verify user is admin
+    let user = UserRepository::find_by_id(&tenant.pool, tenant.org_id, auth.user_id)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::Forbidden))?;
+
+    if !user.is_admin() {
+        return Err(ApiError::from(AppError::Forbidden));
+    }
+
+    let area_uuid = area_id
+        .parse()
+        .map_err(|_| ApiError::from(AppError::Validation("Invalid area ID".to_string())))?;
+
+    let area = ServiceAreaRepository::find_by_id(&tenant.pool, tenant.org_id, area_uuid)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::NotFound("Service area not found".to_string())))?;
+
+    let walker_name = UserRepository::find_by_id(
+        &tenant.pool,
+        tenant.org_id,
+        shared::types::UserId::from_uuid(area.walker_id),
+    )
+    .await?
+    .map(|u| u.full_name());
+
+    let response = match query.format {
+        GeoFormat::GeoJson => Json(area_feature(&area, walker_name.as_deref())).into_response(),
+        GeoFormat::Kml => {
+            let document = areas_kml(&area.name, std::slice::from_ref(&area), &HashMap::new());
+            ([(CONTENT_TYPE, KML_CONTENT_TYPE)], document).into_response()
+        }
+    };
+
+    Ok(response)
+}
+
+/// Export all of the organization's service areas as a GeoJSON
+/// FeatureCollection or KML document, for visualizing coverage (admin only)
+pub async fn export_all_service_areas(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Query(query): Query<ExportServiceAreasQuery>,
+) -> ApiResult<Response> {
+    // Verify user is admin
+    let user = UserRepository::find_by_id(&tenant.pool, tenant.org_id, auth.user_id)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::Forbidden))?;
+
+    if !user.is_admin() {
+        return Err(ApiError::from(AppError::Forbidden));
+    }
+
+    let areas = ServiceAreaRepository::list_all(&tenant.pool, tenant.org_id).await?;
+
+    let walker_names: HashMap<uuid::Uuid, String> =
+        UserRepository::list_by_role(&tenant.pool, tenant.org_id, UserRole::Walker)
+            .await?
+            .into_iter()
+            .map(|u| (*u.id.as_uuid(), u.full_name()))
+            .collect();
+
+    let response = match query.format {
+        GeoFormat::GeoJson => {
+            let features = areas
+                .iter()
+                .map(|area| {
+                    area_feature(area, walker_names.get(&area.walker_id).map(String::as_str))
+                })
+                .collect();
+            Json(geojson_feature_collection(features)).into_response()
+        }
+        GeoFormat::Kml => {
+            let document = areas_kml("Service areas", &areas, &walker_names);
+            ([(CONTENT_TYPE, KML_CONTENT_TYPE)], document).into_response()
+        }
+    };
+
+    Ok(response)
+}
+
+/// Build a GeoJSON Feature for a service area, with simplestyle fill/stroke
+fn area_feature(area: &ServiceArea, walker_name: Option<&str>) -> serde_json::Value {
+    let geometry = area.geometry();
+    let color = area.color.clone().unwrap_or_else(|| "#3B82F6".to_string());
+
+    let mut properties = serde_json::Map::new();
+    properties.insert("id".to_string(), area.id.to_string().into());
+    properties.insert("name".to_string(), area.name.clone().into());
+    properties.insert("walker_id".to_string(), area.walker_id.to_string().into());
+    if let Some(walker_name) = walker_name {
+        properties.insert("walker_name".to_string(), walker_name.into());
+    }
+    properties.insert("is_active".to_string(), area.is_active.into());
+    properties.insert("priority".to_string(), area.priority.unwrap_or(0).into());
+    properties.insert(
+        "price_adjustment_percent".to_string(),
+        area.price_adjustment_percent.unwrap_or(0).into(),
+    );
+    properties.insert("area_km2".to_string(), geometry.area_km2().into());
+    properties.insert("fill".to_string(), color.clone().into());
+    properties.insert("stroke".to_string(), color.into());
+
+    geojson_feature(&geometry, properties)
+}
+
+/// Build a KML document with one Placemark per service area
+fn areas_kml(
+    document_name: &str,
+    areas: &[ServiceArea],
+    walker_names: &HashMap<uuid::Uuid, String>,
+) -> String {
+    let geometries: Vec<Polygon> = areas.iter().map(ServiceArea::geometry).collect();
+    let descriptions: Vec<Option<String>> = areas
+        .iter()
+        .map(|area| {
+            walker_names
+                .get(&area.walker_id)
+                .map(|name| format!("Walker: {}", name))
+        })
+        .collect();
+
+    let placemarks: Vec<KmlPlacemark<'_>> = areas
+        .iter()
+        .zip(&geometries)
+        .zip(&descriptions)
+        .map(|((area, polygon), description)| KmlPlacemark {
+            name: &area.name,
+            description: description.as_deref(),
+            polygon,
+        })
+        .collect();
+
+    kml_document(document_name, &placemarks)
+}
+
 #[derive(Debug, Serialize)]
 pub struct DeleteResponse {
     pub success: bool,
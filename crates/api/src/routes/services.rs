@@ -2,13 +2,15 @@ use axum::{
     extract::{Path, State},
     Json,
 };
-use db::ServiceRepository;
+use db::{models::Service, ServiceRepository};
 use serde::{Deserialize, Serialize};
+use shared::types::{Currency, Money};
 use shared::{AppError, DomainError};
 
 use crate::{
     auth::TenantContext,
     error::{ApiError, ApiResult},
+    payments::organization_currency,
     state::AppState,
 };
 
@@ -19,6 +21,7 @@ pub struct ServiceResponse {
     pub description: Option<String>,
     pub duration_minutes: i32,
     pub price_cents: i64,
+    pub currency: String,
     pub price_display: String,
     pub is_active: bool,
 }
@@ -29,6 +32,8 @@ pub struct CreateServiceRequest {
     pub description: Option<String>,
     pub duration_minutes: i32,
     pub base_price_cents: i64,
+    /// Defaults to the organization's currency
+    pub currency: Option<Currency>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -38,6 +43,7 @@ pub struct UpdateServiceRequest {
     pub duration_minutes: Option<i32>,
     pub base_price_cents: Option<i64>,
     pub is_active: Option<bool>,
+    pub currency: Option<Currency>,
 }
 
 pub async fn list_services(
@@ -46,23 +52,7 @@ pub async fn list_services(
 ) -> ApiResult<Json<Vec<ServiceResponse>>> {
     let services = ServiceRepository::list_all(&tenant.pool, tenant.org_id).await?;
 
-    let response: Vec<ServiceResponse> = services
-        .into_iter()
-        .map(|s| {
-            let price_display = format!("${:.2}", s.price_dollars());
-            ServiceResponse {
-                id: s.id.to_string(),
-                name: s.name,
-                description: s.description,
-                duration_minutes: s.duration_minutes,
-                price_cents: s.base_price_cents,
-                price_display,
-                is_active: s.is_active,
-            }
-        })
-        .collect();
-
-    Ok(Json(response))
+    Ok(Json(services.into_iter().map(service_response).collect()))
 }
 
 pub async fn get_service(
@@ -78,43 +68,31 @@ pub async fn get_service(
         .await?
         .ok_or_else(|| ApiError::from(DomainError::ServiceNotFound(id)))?;
 
-    let price_display = format!("${:.2}", service.price_dollars());
-    Ok(Json(ServiceResponse {
-        id: service.id.to_string(),
-        name: service.name,
-        description: service.description,
-        duration_minutes: service.duration_minutes,
-        price_cents: service.base_price_cents,
-        price_display,
-        is_active: service.is_active,
-    }))
+    Ok(Json(service_response(service)))
 }
 
 pub async fn create_service(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     tenant: TenantContext,
     Json(req): Json<CreateServiceRequest>,
 ) -> ApiResult<Json<ServiceResponse>> {
+    let currency = match req.currency {
+        Some(currency) => currency,
+        None => organization_currency(&state, &tenant).await?,
+    };
+
     let input = db::models::CreateService {
         organization_id: tenant.org_id,
         name: req.name,
         description: req.description,
         duration_minutes: req.duration_minutes,
         base_price_cents: req.base_price_cents,
+        currency,
     };
 
     let service = ServiceRepository::create(&tenant.pool, input).await?;
 
-    let price_display = format!("${:.2}", service.price_dollars());
-    Ok(Json(ServiceResponse {
-        id: service.id.to_string(),
-        name: service.name,
-        description: service.description,
-        duration_minutes: service.duration_minutes,
-        price_cents: service.base_price_cents,
-        price_display,
-        is_active: service.is_active,
-    }))
+    Ok(Json(service_response(service)))
 }
 
 pub async fn update_service(
@@ -133,20 +111,26 @@ pub async fn update_service(
         duration_minutes: req.duration_minutes,
         base_price_cents: req.base_price_cents,
         is_active: req.is_active,
+        currency: req.currency,
     };
 
     let service = ServiceRepository::update(&tenant.pool, tenant.org_id, service_id, input)
         .await?
         .ok_or_else(|| ApiError::from(DomainError::ServiceNotFound(id)))?;
 
-    let price_display = format!("${:.2}", service.price_dollars());
-    Ok(Json(ServiceResponse {
+    Ok(Json(service_response(service)))
+}
+
+fn service_response(service: Service) -> ServiceResponse {
+    let price = Money::new(service.base_price_cents, service.currency);
+    ServiceResponse {
         id: service.id.to_string(),
         name: service.name,
         description: service.description,
         duration_minutes: service.duration_minutes,
         price_cents: service.base_price_cents,
-        price_display,
+        currency: service.currency.to_string(),
+        price_display: price.to_string(),
         is_active: service.is_active,
-    }))
+    }
 }
@@ -2,21 +2,26 @@ use axum::{
     extract::{Path, State},
     Json,
 };
+use chrono::{NaiveTime, Utc};
 use db::{
     models::{
-        CreateCustomerSubscription, CreateTenantSubscription, PlanTier, UpdateTenantSubscription,
+        CreateCustomerSubscription, CreatePackagePlan, CustomerSubscription, PackagePlan,
+        PaymentMethodType, PlanTier, TenantSubscription,
     },
-    SubscriptionRepository,
+    LocationRepository, ServiceRepository, SubscriptionRepository, UserRepository,
 };
-use rust_decimal::prelude::ToPrimitive;
 use serde::{Deserialize, Serialize};
-use shared::AppError;
+use shared::types::{LocationId, ServiceId, UserId};
+use shared::{AppError, DomainError};
 use uuid::Uuid;
 
 use crate::{
     auth::{AuthUser, TenantContext},
+    customer_packages,
     error::{ApiError, ApiResult},
+    routes::checkout::find_payment_method,
     state::AppState,
+    tenant_billing::{self, BillingPeriod},
 };
 
 /// Tenant subscription response
@@ -31,7 +36,14 @@ pub struct TenantSubscriptionResponse {
     pub provider_fee_percent: f64,
     pub current_period_start: Option<String>,
     pub current_period_end: Option<String>,
+    pub trial_end: Option<String>,
     pub cancel_at_period_end: bool,
+    /// When the subscription became past due
+    pub past_due_since: Option<String>,
+    /// Paid features are restricted until the subscription is paid
+    pub restricted: bool,
+    /// The plan whose features the organization has
+    pub effective_plan_tier: String,
     pub created_at: String,
 }
 
@@ -58,27 +70,9 @@ pub async fn get_tenant_subscription(
     let subscription =
         SubscriptionRepository::get_tenant_subscription(&tenant.pool, tenant.org_id).await?;
 
-    let response = if let Some(s) = subscription {
-        // Get the fee tier for the plan to include pricing info
-        let fee_tier = SubscriptionRepository::get_fee_tier(&tenant.pool, s.plan_tier)
-            .await?
-            .ok_or_else(|| ApiError::from(AppError::Internal("Fee tier not found".to_string())))?;
-
-        Some(TenantSubscriptionResponse {
-            id: s.id.to_string(),
-            plan_tier: s.plan_tier.to_string(),
-            status: s.status.to_string(),
-            monthly_price_cents: fee_tier.monthly_price_cents,
-            annual_price_cents: fee_tier.annual_price_cents,
-            customer_fee_percent: fee_tier.customer_fee_percent.to_f64().unwrap_or(0.0) * 100.0,
-            provider_fee_percent: fee_tier.provider_fee_percent.to_f64().unwrap_or(0.0) * 100.0,
-            current_period_start: s.current_period_start.map(|dt| dt.to_rfc3339()),
-            current_period_end: s.current_period_end.map(|dt| dt.to_rfc3339()),
-            cancel_at_period_end: s.cancel_at_period_end,
-            created_at: s.created_at.to_rfc3339(),
-        })
-    } else {
-        None
+    let response = match subscription {
+        Some(s) => Some(tenant_subscription_response(&tenant, s).await?),
+        None => None,
     };
 
     Ok(Json(response))
@@ -98,9 +92,9 @@ pub async fn list_fee_tiers(
             let features = get_tier_features(&t.plan_tier);
             FeeTierResponse {
                 plan_tier: t.plan_tier.to_string(),
+                customer_fee_percent: t.customer_fee_display(),
+                provider_fee_percent: t.provider_fee_display(),
                 display_name: t.display_name,
-                customer_fee_percent: t.customer_fee_percent.to_f64().unwrap_or(0.0) * 100.0,
-                provider_fee_percent: t.provider_fee_percent.to_f64().unwrap_or(0.0) * 100.0,
                 min_customer_fee_cents: t.min_customer_fee_cents,
                 min_provider_fee_cents: t.min_provider_fee_cents,
                 monthly_price_cents: t.monthly_price_cents,
@@ -157,107 +151,84 @@ pub struct CreateSubscriptionRequest {
 
 /// Create/upgrade tenant subscription
 pub async fn create_tenant_subscription(
-    State(_state): State<AppState>,
-    _auth_user: AuthUser,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
     tenant: TenantContext,
     Json(req): Json<CreateSubscriptionRequest>,
 ) -> ApiResult<Json<TenantSubscriptionResponse>> {
-    let plan_tier = match req.plan_tier.to_lowercase().as_str() {
-        "free" => PlanTier::Free,
-        "professional" => PlanTier::Professional,
-        "business" => PlanTier::Business,
-        "enterprise" => PlanTier::Enterprise,
+    tenant.require_manager(auth_user.user_id).await?;
+
+    let plan_tier: PlanTier = req
+        .plan_tier
+        .parse()
+        .map_err(|_| ApiError::from(AppError::Validation("Invalid plan tier".to_string())))?;
+    let billing_period = match req.billing_period.to_lowercase().as_str() {
+        "monthly" => BillingPeriod::Monthly,
+        "annual" => BillingPeriod::Annual,
         _ => {
             return Err(ApiError::from(AppError::Validation(
-                "Invalid plan tier".to_string(),
+                "Invalid billing period. Must be 'monthly' or 'annual'".to_string(),
             )))
         }
     };
 
-    // Get the fee tier info
-    let fee_tier = SubscriptionRepository::get_fee_tier(&tenant.pool, plan_tier)
-        .await?
-        .ok_or_else(|| ApiError::from(AppError::Internal("Fee tier not found".to_string())))?;
-
-    // Calculate period dates
-    let now = chrono::Utc::now();
-    let period_end = if req.billing_period == "annual" {
-        now + chrono::Duration::days(365)
-    } else {
-        now + chrono::Duration::days(30)
-    };
-
-    // Create the subscription
-    let input = CreateTenantSubscription {
+    let user = UserRepository::find_by_id(&tenant.pool, tenant.org_id, auth_user.user_id).await?;
+    let subscription = tenant_billing::subscribe(
+        &state,
+        &tenant,
         plan_tier,
-        stripe_subscription_id: None,
-        stripe_customer_id: None,
-        current_period_start: Some(now),
-        current_period_end: Some(period_end),
-        trial_end: None,
-    };
-
-    let subscription =
-        SubscriptionRepository::create_tenant_subscription(&tenant.pool, tenant.org_id, input)
-            .await?;
-
-    // TODO: Create payment/charge for the subscription via Stripe/Square
+        billing_period,
+        user.as_ref().map(|u| u.email.as_str()),
+        req.payment_method_id.as_deref(),
+    )
+    .await?;
 
-    Ok(Json(TenantSubscriptionResponse {
-        id: subscription.id.to_string(),
-        plan_tier: subscription.plan_tier.to_string(),
-        status: subscription.status.to_string(),
-        monthly_price_cents: fee_tier.monthly_price_cents,
-        annual_price_cents: fee_tier.annual_price_cents,
-        customer_fee_percent: fee_tier.customer_fee_percent.to_f64().unwrap_or(0.0) * 100.0,
-        provider_fee_percent: fee_tier.provider_fee_percent.to_f64().unwrap_or(0.0) * 100.0,
-        current_period_start: subscription.current_period_start.map(|dt| dt.to_rfc3339()),
-        current_period_end: subscription.current_period_end.map(|dt| dt.to_rfc3339()),
-        cancel_at_period_end: subscription.cancel_at_period_end,
-        created_at: subscription.created_at.to_rfc3339(),
-    }))
+    Ok(Json(
+        tenant_subscription_response(&tenant, subscription).await?,
+    ))
 }
 
 /// Cancel tenant subscription (at period end)
 pub async fn cancel_tenant_subscription(
-    State(_state): State<AppState>,
-    _auth_user: AuthUser,
+    State(state): State<AppState>,
+    auth_user: AuthUser,
     tenant: TenantContext,
 ) -> ApiResult<Json<TenantSubscriptionResponse>> {
-    // Update subscription to cancel at period end
-    let input = UpdateTenantSubscription {
-        cancel_at_period_end: Some(true),
-        canceled_at: Some(chrono::Utc::now()),
-        ..Default::default()
-    };
+    tenant.require_manager(auth_user.user_id).await?;
 
-    let subscription =
-        SubscriptionRepository::update_tenant_subscription(&tenant.pool, tenant.org_id, input)
-            .await?
-            .ok_or_else(|| {
-                ApiError::from(AppError::NotFound(
-                    "No active subscription found".to_string(),
-                ))
-            })?;
+    let subscription = tenant_billing::cancel(&state, &tenant).await?;
+
+    Ok(Json(
+        tenant_subscription_response(&tenant, subscription).await?,
+    ))
+}
 
-    // Get fee tier for response
-    let fee_tier = SubscriptionRepository::get_fee_tier(&tenant.pool, subscription.plan_tier)
+async fn tenant_subscription_response(
+    tenant: &TenantContext,
+    s: TenantSubscription,
+) -> ApiResult<TenantSubscriptionResponse> {
+    // Get the fee tier for the plan to include pricing info
+    let fee_tier = SubscriptionRepository::get_fee_tier(&tenant.pool, s.plan_tier)
         .await?
         .ok_or_else(|| ApiError::from(AppError::Internal("Fee tier not found".to_string())))?;
 
-    Ok(Json(TenantSubscriptionResponse {
-        id: subscription.id.to_string(),
-        plan_tier: subscription.plan_tier.to_string(),
-        status: subscription.status.to_string(),
+    Ok(TenantSubscriptionResponse {
+        id: s.id.to_string(),
+        plan_tier: s.plan_tier.to_string(),
+        status: s.status.to_string(),
         monthly_price_cents: fee_tier.monthly_price_cents,
         annual_price_cents: fee_tier.annual_price_cents,
-        customer_fee_percent: fee_tier.customer_fee_percent.to_f64().unwrap_or(0.0) * 100.0,
-        provider_fee_percent: fee_tier.provider_fee_percent.to_f64().unwrap_or(0.0) * 100.0,
-        current_period_start: subscription.current_period_start.map(|dt| dt.to_rfc3339()),
-        current_period_end: subscription.current_period_end.map(|dt| dt.to_rfc3339()),
-        cancel_at_period_end: subscription.cancel_at_period_end,
-        created_at: subscription.created_at.to_rfc3339(),
-    }))
+        customer_fee_percent: fee_tier.customer_fee_display(),
+        provider_fee_percent: fee_tier.provider_fee_display(),
+        current_period_start: s.current_period_start.map(|dt| dt.to_rfc3339()),
+        current_period_end: s.current_period_end.map(|dt| dt.to_rfc3339()),
+        trial_end: s.trial_end.map(|dt| dt.to_rfc3339()),
+        cancel_at_period_end: s.cancel_at_period_end,
+        past_due_since: s.past_due_since.map(|dt| dt.to_rfc3339()),
+        restricted: s.is_restricted(),
+        effective_plan_tier: s.effective_plan_tier().to_string(),
+        created_at: s.created_at.to_rfc3339(),
+    })
 }
 
 // Customer subscriptions (recurring service packages)
@@ -277,6 +248,15 @@ pub struct CustomerSubscriptionResponse {
     pub current_period_end: Option<String>,
     pub cancel_at_period_end: bool,
     pub auto_create_bookings: bool,
+    /// Walks granted each period, for a service package
+    pub walks_per_period: Option<i32>,
+    /// Walks left to book this period
+    pub credits_remaining: i64,
+    pub preferred_walker_id: Option<String>,
+    pub preferred_day_of_week: Option<i32>,
+    /// "HH:MM" in the subscription's timezone
+    pub preferred_time: Option<String>,
+    pub timezone: String,
     pub created_at: String,
 }
 
@@ -293,83 +273,289 @@ pub async fn list_customer_subscriptions(
     )
     .await?;
 
-    let response: Vec<CustomerSubscriptionResponse> = subscriptions
+    let mut response = Vec::with_capacity(subscriptions.len());
+    for s in subscriptions {
+        response.push(customer_subscription_response(&tenant, s).await?);
+    }
+
+    Ok(Json(response))
+}
+
+/// Package plan response
+#[derive(Debug, Serialize)]
+pub struct PackagePlanResponse {
+    pub id: String,
+    pub service_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub price_cents: i32,
+    pub interval: String,
+    pub interval_count: i32,
+    pub walks_per_period: i32,
+    pub is_active: bool,
+    pub created_at: String,
+}
+
+impl From<PackagePlan> for PackagePlanResponse {
+    fn from(p: PackagePlan) -> Self {
+        Self {
+            id: p.id.to_string(),
+            service_id: p.service_id.to_string(),
+            name: p.name,
+            description: p.description,
+            price_cents: p.price_cents,
+            interval: p.interval,
+            interval_count: p.interval_count,
+            walks_per_period: p.walks_per_period,
+            is_active: p.is_active,
+            created_at: p.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// List the package plans customers can subscribe to
+pub async fn list_package_plans(
+    State(_state): State<AppState>,
+    _auth_user: AuthUser,
+    tenant: TenantContext,
+) -> ApiResult<Json<Vec<PackagePlanResponse>>> {
+    let plans = SubscriptionRepository::list_active_package_plans(&tenant.pool, tenant.org_id)
+        .await?
         .into_iter()
-        .map(|s| CustomerSubscriptionResponse {
-            id: s.id.to_string(),
-            service_id: s.service_id.map(|id| id.to_string()),
-            name: s.name,
-            description: s.description,
-            status: s.status.to_string(),
-            price_cents: s.price_cents,
-            interval: s.interval,
-            interval_count: s.interval_count,
-            current_period_start: s.current_period_start.map(|dt| dt.to_rfc3339()),
-            current_period_end: s.current_period_end.map(|dt| dt.to_rfc3339()),
-            cancel_at_period_end: s.cancel_at_period_end,
-            auto_create_bookings: s.auto_create_bookings,
-            created_at: s.created_at.to_rfc3339(),
-        })
+        .map(PackagePlanResponse::from)
         .collect();
 
-    Ok(Json(response))
+    Ok(Json(plans))
 }
 
 #[derive(Debug, Deserialize)]
-pub struct CreateCustomerSubscriptionRequest {
-    /// Service this subscription is for (optional)
-    pub service_id: Option<String>,
-    /// Display name for the subscription
+pub struct CreatePackagePlanRequest {
+    /// Service whose bookings the plan's walks pay for
+    pub service_id: String,
+    /// Display name for the plan
     pub name: String,
-    /// Description
     pub description: Option<String>,
-    /// Price in cents
+    /// Price of each period in cents
     pub price_cents: i32,
     /// Interval: "week", "month", or "year"
     pub interval: String,
     /// How many intervals between charges
     pub interval_count: Option<i32>,
+    /// Walks granted each period
+    pub walks_per_period: i32,
+}
+
+/// Offer a package plan (managers only)
+pub async fn create_package_plan(
+    State(_state): State<AppState>,
+    auth_user: AuthUser,
+    tenant: TenantContext,
+    Json(req): Json<CreatePackagePlanRequest>,
+) -> ApiResult<Json<PackagePlanResponse>> {
+    tenant.require_manager(auth_user.user_id).await?;
+
+    let service_id: ServiceId = req
+        .service_id
+        .parse()
+        .map_err(|_| ApiError::from(AppError::Validation("Invalid service ID".to_string())))?;
+    ServiceRepository::find_by_id(&tenant.pool, tenant.org_id, service_id)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::NotFound("Service not found".to_string())))?;
+
+    // Validate interval
+    if !["week", "month", "year"].contains(&req.interval.as_str()) {
+        return Err(ApiError::from(AppError::Validation(
+            "Invalid interval. Must be 'week', 'month', or 'year'".to_string(),
+        )));
+    }
+    let interval_count = req.interval_count.unwrap_or(1);
+    if interval_count < 1 {
+        return Err(ApiError::from(AppError::Validation(
+            "Interval count must be at least 1".to_string(),
+        )));
+    }
+    if req.price_cents <= 0 {
+        return Err(ApiError::from(AppError::Validation(
+            "Price must be positive".to_string(),
+        )));
+    }
+    if req.walks_per_period <= 0 {
+        return Err(ApiError::from(AppError::Validation(
+            "Walks per period must be positive".to_string(),
+        )));
+    }
+
+    let plan = SubscriptionRepository::create_package_plan(
+        &tenant.pool,
+        tenant.org_id,
+        CreatePackagePlan {
+            service_id: *service_id.as_uuid(),
+            name: req.name,
+            description: req.description,
+            price_cents: req.price_cents,
+            interval: req.interval,
+            interval_count,
+            walks_per_period: req.walks_per_period,
+        },
+    )
+    .await?;
+
+    Ok(Json(plan.into()))
+}
+
+/// Stop offering a package plan (managers only). Existing subscriptions
+/// carry on at the terms they subscribed to.
+pub async fn archive_package_plan(
+    State(_state): State<AppState>,
+    auth_user: AuthUser,
+    tenant: TenantContext,
+    Path(id): Path<String>,
+) -> ApiResult<Json<PackagePlanResponse>> {
+    tenant.require_manager(auth_user.user_id).await?;
+
+    let plan_id: Uuid = id
+        .parse()
+        .map_err(|_| ApiError::from(AppError::Validation("Invalid plan ID".to_string())))?;
+    let plan = SubscriptionRepository::archive_package_plan(&tenant.pool, tenant.org_id, plan_id)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::NotFound("Package plan not found".to_string())))?;
+
+    Ok(Json(plan.into()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateCustomerSubscriptionRequest {
+    /// Package plan to subscribe to, which sets the service, price, interval
+    /// and walks per period
+    pub package_plan_id: String,
+    /// Walker auto-created bookings are made with
+    pub preferred_walker_id: Option<String>,
+    /// Where the walks take place
+    pub location_id: Option<String>,
     /// Auto-create bookings from this subscription
     pub auto_create_bookings: Option<bool>,
     /// Preferred day of week (0-6, Sunday-Saturday)
     pub preferred_day_of_week: Option<i32>,
-    /// Payment method to use
-    pub payment_method_id: Option<String>,
+    /// Preferred start time, "HH:MM"
+    pub preferred_time: Option<String>,
+    /// Timezone of the preferred day and time (default UTC)
+    pub timezone: Option<String>,
+    /// Saved payment method each period is charged to
+    pub payment_method_id: String,
 }
 
-/// Create a customer subscription (service package)
+/// Subscribe the customer to a package plan.
+///
+/// The first period is charged straight away; the subscription isn't
+/// created if that payment fails.
 pub async fn create_customer_subscription(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     auth_user: AuthUser,
     tenant: TenantContext,
     Json(req): Json<CreateCustomerSubscriptionRequest>,
 ) -> ApiResult<Json<CustomerSubscriptionResponse>> {
-    let service_id =
-        if let Some(sid) = &req.service_id {
-            Some(sid.parse::<Uuid>().map_err(|_| {
-                ApiError::from(AppError::Validation("Invalid service ID".to_string()))
-            })?)
-        } else {
-            None
-        };
+    let plan_id: Uuid = req
+        .package_plan_id
+        .parse()
+        .map_err(|_| ApiError::from(AppError::Validation("Invalid plan ID".to_string())))?;
+    let plan = SubscriptionRepository::get_package_plan(&tenant.pool, tenant.org_id, plan_id)
+        .await?
+        .filter(|p| p.is_active)
+        .ok_or_else(|| ApiError::from(AppError::NotFound("Package plan not found".to_string())))?;
 
-    // Validate interval
-    if !["week", "month", "year"].contains(&req.interval.as_str()) {
+    if req
+        .preferred_day_of_week
+        .is_some_and(|day| !(0..=6).contains(&day))
+    {
         return Err(ApiError::from(AppError::Validation(
-            "Invalid interval. Must be 'week', 'month', or 'year'".to_string(),
+            "Preferred day of week must be 0-6".to_string(),
+        )));
+    }
+
+    let preferred_time = match &req.preferred_time {
+        Some(time) => Some(NaiveTime::parse_from_str(time, "%H:%M").map_err(|_| {
+            ApiError::from(AppError::Validation(
+                "Invalid preferred time. Use HH:MM".to_string(),
+            ))
+        })?),
+        None => None,
+    };
+    let timezone = req.timezone.unwrap_or_else(|| "UTC".to_string());
+    let noon = NaiveTime::from_hms_opt(12, 0, 0).unwrap_or_default();
+    if db::to_utc_datetime(Utc::now().date_naive(), noon, &timezone).is_none() {
+        return Err(ApiError::from(AppError::Validation(
+            "Invalid timezone".to_string(),
+        )));
+    }
+
+    let preferred_walker_id = match req.preferred_walker_id {
+        Some(id) => {
+            let walker_id: UserId = id.parse().map_err(|_| {
+                ApiError::from(AppError::Validation("Invalid walker ID".to_string()))
+            })?;
+            let walker = UserRepository::find_by_id(&tenant.pool, tenant.org_id, walker_id)
+                .await?
+                .filter(|w| w.is_walker())
+                .ok_or_else(|| ApiError::from(DomainError::WalkerNotFound(id)))?;
+            Some(walker.id)
+        }
+        None => None,
+    };
+
+    let location_id = match &req.location_id {
+        Some(id) => {
+            let location_id: LocationId = id.parse().map_err(|_| {
+                ApiError::from(AppError::Validation("Invalid location ID".to_string()))
+            })?;
+            let location = LocationRepository::find_by_id(&tenant.pool, tenant.org_id, location_id)
+                .await?
+                .ok_or_else(|| ApiError::from(DomainError::LocationNotFound(id.clone())))?;
+            if location.user_id != auth_user.user_id {
+                return Err(ApiError::from(AppError::Forbidden));
+            }
+            Some(location_id)
+        }
+        None => None,
+    };
+
+    let auto_create_bookings = req.auto_create_bookings.unwrap_or(false);
+    if auto_create_bookings
+        && (req.preferred_day_of_week.is_none()
+            || preferred_time.is_none()
+            || location_id.is_none()
+            || preferred_walker_id.is_none())
+    {
+        return Err(ApiError::from(AppError::Validation(
+            "Auto-created bookings need a preferred walker, day and time, and a location"
+                .to_string(),
+        )));
+    }
+
+    // Periods are charged while the customer is away, so only methods that
+    // settle immediately are accepted
+    let payment_method = find_payment_method(&tenant, &auth_user, &req.payment_method_id).await?;
+    if payment_method.method_type == PaymentMethodType::BankAccount {
+        return Err(ApiError::from(AppError::Validation(
+            "Subscriptions must be paid by card".to_string(),
         )));
     }
 
     let input = CreateCustomerSubscription {
-        service_id,
-        name: req.name,
-        description: req.description,
-        price_cents: req.price_cents,
-        interval: req.interval,
-        interval_count: req.interval_count.unwrap_or(1),
-        auto_create_bookings: req.auto_create_bookings.unwrap_or(false),
+        package_plan_id: Some(plan.id),
+        service_id: Some(plan.service_id),
+        name: plan.name,
+        description: plan.description,
+        price_cents: plan.price_cents,
+        interval: plan.interval,
+        interval_count: plan.interval_count,
+        auto_create_bookings,
         preferred_day_of_week: req.preferred_day_of_week,
-        preferred_time: None,
+        preferred_time,
+        walks_per_period: Some(plan.walks_per_period),
+        preferred_walker_id,
+        location_id,
+        timezone,
+        payment_method_id: Some(payment_method.id),
     };
 
     let subscription = SubscriptionRepository::create_customer_subscription(
@@ -380,70 +566,58 @@ pub async fn create_customer_subscription(
     )
     .await?;
 
-    // TODO: Process initial payment
-
-    Ok(Json(CustomerSubscriptionResponse {
-        id: subscription.id.to_string(),
-        service_id: subscription.service_id.map(|id| id.to_string()),
-        name: subscription.name,
-        description: subscription.description,
-        status: subscription.status.to_string(),
-        price_cents: subscription.price_cents,
-        interval: subscription.interval,
-        interval_count: subscription.interval_count,
-        current_period_start: subscription.current_period_start.map(|dt| dt.to_rfc3339()),
-        current_period_end: subscription.current_period_end.map(|dt| dt.to_rfc3339()),
-        cancel_at_period_end: subscription.cancel_at_period_end,
-        auto_create_bookings: subscription.auto_create_bookings,
-        created_at: subscription.created_at.to_rfc3339(),
-    }))
+    let subscription =
+        customer_packages::start(&state, &tenant, &subscription, &payment_method).await?;
+
+    Ok(Json(
+        customer_subscription_response(&tenant, subscription).await?,
+    ))
 }
 
 /// Cancel a customer subscription
 pub async fn cancel_customer_subscription(
     State(_state): State<AppState>,
-    _auth_user: AuthUser,
+    auth_user: AuthUser,
     tenant: TenantContext,
     Path(id): Path<String>,
 ) -> ApiResult<Json<CustomerSubscriptionResponse>> {
-    let subscription_id: Uuid = id
-        .parse()
-        .map_err(|_| ApiError::from(AppError::Validation("Invalid subscription ID".to_string())))?;
+    let subscription = find_customer_subscription(&tenant, &auth_user, &id).await?;
 
     // Cancel at period end (not immediately)
     let subscription = SubscriptionRepository::cancel_customer_subscription(
         &tenant.pool,
         tenant.org_id,
-        subscription_id,
+        subscription.id,
         false, // cancel at period end
     )
     .await?
     .ok_or_else(|| ApiError::from(AppError::NotFound("Subscription not found".to_string())))?;
 
-    Ok(Json(CustomerSubscriptionResponse {
-        id: subscription.id.to_string(),
-        service_id: subscription.service_id.map(|id| id.to_string()),
-        name: subscription.name,
-        description: subscription.description,
-        status: subscription.status.to_string(),
-        price_cents: subscription.price_cents,
-        interval: subscription.interval,
-        interval_count: subscription.interval_count,
-        current_period_start: subscription.current_period_start.map(|dt| dt.to_rfc3339()),
-        current_period_end: subscription.current_period_end.map(|dt| dt.to_rfc3339()),
-        cancel_at_period_end: subscription.cancel_at_period_end,
-        auto_create_bookings: subscription.auto_create_bookings,
-        created_at: subscription.created_at.to_rfc3339(),
-    }))
+    Ok(Json(
+        customer_subscription_response(&tenant, subscription).await?,
+    ))
 }
 
 /// Get a specific customer subscription
 pub async fn get_customer_subscription(
     State(_state): State<AppState>,
-    _auth_user: AuthUser,
+    auth_user: AuthUser,
     tenant: TenantContext,
     Path(id): Path<String>,
 ) -> ApiResult<Json<CustomerSubscriptionResponse>> {
+    let subscription = find_customer_subscription(&tenant, &auth_user, &id).await?;
+
+    Ok(Json(
+        customer_subscription_response(&tenant, subscription).await?,
+    ))
+}
+
+/// A subscription the user owns, or any in the organization for a manager
+async fn find_customer_subscription(
+    tenant: &TenantContext,
+    auth_user: &AuthUser,
+    id: &str,
+) -> ApiResult<CustomerSubscription> {
     let subscription_id: Uuid = id
         .parse()
         .map_err(|_| ApiError::from(AppError::Validation("Invalid subscription ID".to_string())))?;
@@ -456,19 +630,41 @@ pub async fn get_customer_subscription(
     .await?
     .ok_or_else(|| ApiError::from(AppError::NotFound("Subscription not found".to_string())))?;
 
-    Ok(Json(CustomerSubscriptionResponse {
-        id: subscription.id.to_string(),
-        service_id: subscription.service_id.map(|id| id.to_string()),
-        name: subscription.name,
-        description: subscription.description,
-        status: subscription.status.to_string(),
-        price_cents: subscription.price_cents,
-        interval: subscription.interval,
-        interval_count: subscription.interval_count,
-        current_period_start: subscription.current_period_start.map(|dt| dt.to_rfc3339()),
-        current_period_end: subscription.current_period_end.map(|dt| dt.to_rfc3339()),
-        cancel_at_period_end: subscription.cancel_at_period_end,
-        auto_create_bookings: subscription.auto_create_bookings,
-        created_at: subscription.created_at.to_rfc3339(),
-    }))
+    if subscription.user_id != auth_user.user_id && !tenant.is_manager(auth_user.user_id).await? {
+        return Err(ApiError::from(AppError::NotFound(
+            "Subscription not found".to_string(),
+        )));
+    }
+
+    Ok(subscription)
+}
+
+async fn customer_subscription_response(
+    tenant: &TenantContext,
+    s: CustomerSubscription,
+) -> ApiResult<CustomerSubscriptionResponse> {
+    let credits_remaining =
+        SubscriptionRepository::credit_balance(&tenant.pool, tenant.org_id, s.id).await?;
+
+    Ok(CustomerSubscriptionResponse {
+        id: s.id.to_string(),
+        service_id: s.service_id.map(|id| id.to_string()),
+        name: s.name,
+        description: s.description,
+        status: s.status.to_string(),
+        price_cents: s.price_cents,
+        interval: s.interval,
+        interval_count: s.interval_count,
+        current_period_start: s.current_period_start.map(|dt| dt.to_rfc3339()),
+        current_period_end: s.current_period_end.map(|dt| dt.to_rfc3339()),
+        cancel_at_period_end: s.cancel_at_period_end,
+        auto_create_bookings: s.auto_create_bookings,
+        walks_per_period: s.walks_per_period,
+        credits_remaining,
+        preferred_walker_id: s.preferred_walker_id.map(|id| id.to_string()),
+        preferred_day_of_week: s.preferred_day_of_week,
+        preferred_time: s.preferred_time.map(|t| t.format("%H:%M").to_string()),
+        timezone: s.timezone,
+        created_at: s.created_at.to_rfc3339(),
+    })
 }
@@ -0,0 +1,232 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use db::{
+    models::{BookingStatus, CustomerPaymentMethod},
+    BookingRepository, CustomerPaymentMethodRepository, TransactionRepository,
+};
+use serde::{Deserialize, Serialize};
+use shared::types::{BookingId, UserId};
+use shared::{AppError, DomainError};
+use uuid::Uuid;
+
+use super::checkout::{find_payment_method, start_booking_payment, BookingCharge, TIP_CHARGE};
+use crate::{
+    auth::{AuthUser, TenantContext},
+    error::{ApiError, ApiResult},
+    state::AppState,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct TipRequest {
+    pub tip_cents: i32,
+    /// Saved payment method to charge; defaults to the one that paid for the
+    /// walk, then the customer's default
+    pub payment_method_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TipResponse {
+    pub transaction_id: String,
+    pub booking_id: String,
+    /// The walk's own payment, if it was paid through the platform
+    pub walk_transaction_id: Option<String>,
+    pub status: String,
+    pub tip_cents: i32,
+    pub total_cents: i32,
+    pub currency: String,
+}
+
+/// POST /bookings/:id/tip - Tip the walker after a completed walk.
+///
+/// The tip is charged to a saved payment method as a payment of its own,
+/// linked to the walk's payment, and paid to the walker in full.
+pub async fn tip_booking(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    tenant: TenantContext,
+    Path(id): Path<String>,
+    Json(req): Json<TipRequest>,
+) -> ApiResult<Json<TipResponse>> {
+    let booking_id: BookingId = id
+        .parse()
+        .map_err(|_| ApiError::from(AppError::Validation("Invalid booking ID".to_string())))?;
+
+    let booking = BookingRepository::find_by_id(&tenant.pool, tenant.org_id, booking_id)
+        .await?
+        .filter(|b| b.customer_id == auth_user.user_id)
+        .ok_or_else(|| ApiError::from(DomainError::BookingNotFound(id)))?;
+
+    if booking.status != BookingStatus::Completed {
+        return Err(ApiError::from(AppError::Validation(
+            "Tips can only be added once the walk is completed".to_string(),
+        )));
+    }
+    if req.tip_cents <= 0 {
+        return Err(ApiError::from(AppError::Validation(
+            "Tip must be positive".to_string(),
+        )));
+    }
+
+    // One tip per walk; a failed tip can be retried
+    let tips = TransactionRepository::list_booking_charges(
+        &tenant.pool,
+        tenant.org_id,
+        booking_id,
+        TIP_CHARGE,
+    )
+    .await?;
+    if let Some(existing) = tips.iter().find(|t| t.status.is_live()) {
+        return Err(ApiError::from(DomainError::PaymentAlreadyExists(
+            existing.status.to_string(),
+        )));
+    }
+
+    let walk_transaction =
+        TransactionRepository::get_by_booking(&tenant.pool, tenant.org_id, booking_id).await?;
+    let payment_method = match &req.payment_method_id {
+        Some(pm_id) => find_payment_method(&tenant, &auth_user, pm_id).await?,
+        None => default_payment_method(
+            &tenant,
+            &auth_user,
+            walk_transaction.as_ref().and_then(|t| t.payment_method_id),
+        )
+        .await?
+        .ok_or_else(|| {
+            ApiError::from(AppError::Validation(
+                "A saved payment method is required to tip".to_string(),
+            ))
+        })?,
+    };
+
+    // The customer isn't paying through the browser, so charge off-session
+    let (transaction, _, _) = start_booking_payment(
+        &state,
+        &tenant,
+        &booking,
+        BookingCharge::Tip {
+            tip_cents: req.tip_cents,
+            walk_transaction_id: walk_transaction.as_ref().map(|t| t.id),
+        },
+        Some(&payment_method),
+        true,
+        None,
+    )
+    .await?;
+
+    Ok(Json(TipResponse {
+        transaction_id: transaction.id.to_string(),
+        booking_id: booking.id.to_string(),
+        walk_transaction_id: walk_transaction.map(|t| t.id.to_string()),
+        status: transaction.status.to_string(),
+        tip_cents: transaction.tip_cents,
+        total_cents: transaction.total_cents,
+        currency: transaction.currency.to_string(),
+    }))
+}
+
+/// The payment method that paid for the walk, if it's still saved, or the
+/// customer's default
+async fn default_payment_method(
+    tenant: &TenantContext,
+    auth_user: &AuthUser,
+    walk_payment_method_id: Option<Uuid>,
+) -> ApiResult<Option<CustomerPaymentMethod>> {
+    if let Some(id) = walk_payment_method_id {
+        let method = CustomerPaymentMethodRepository::find_by_id(&tenant.pool, tenant.org_id, id)
+            .await?
+            .filter(|pm| pm.user_id == auth_user.user_id);
+        if method.is_some() {
+            return Ok(method);
+        }
+    }
+
+    Ok(
+        CustomerPaymentMethodRepository::get_default(
+            &tenant.pool,
+            tenant.org_id,
+            auth_user.user_id,
+        )
+        .await?,
+    )
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TipSummaryQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Only this walker (managers only; walkers always see their own)
+    pub walker_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WalkerTipSummaryResponse {
+    pub walker_id: String,
+    pub currency: String,
+    pub walk_count: i64,
+    pub tipped_walk_count: i64,
+    /// Share of walks that were tipped, as a percentage
+    pub tip_rate_percent: f64,
+    pub tip_count: i64,
+    pub tip_cents: i64,
+    pub average_tip_cents: i64,
+    /// Tips added after the walk
+    pub post_walk_tip_count: i64,
+    pub post_walk_tip_cents: i64,
+}
+
+/// GET /tips/summary - Tips per walker over a period, one entry per walker
+/// and currency. Managers see every walker; walkers see their own. Defaults
+/// to the last 30 days.
+pub async fn get_tip_summary(
+    State(_state): State<AppState>,
+    auth: AuthUser,
+    tenant: TenantContext,
+    Query(query): Query<TipSummaryQuery>,
+) -> ApiResult<Json<Vec<WalkerTipSummaryResponse>>> {
+    let walker_id = if tenant.is_manager(auth.user_id).await? {
+        query
+            .walker_id
+            .as_deref()
+            .map(|id| {
+                id.parse::<UserId>().map_err(|_| {
+                    ApiError::from(AppError::Validation("Invalid walker ID".to_string()))
+                })
+            })
+            .transpose()?
+    } else {
+        Some(auth.user_id)
+    };
+
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - Duration::days(30));
+    let summaries = TransactionRepository::get_tip_summary(
+        &tenant.pool,
+        tenant.org_id,
+        from,
+        to,
+        walker_id,
+        TIP_CHARGE,
+    )
+    .await?;
+
+    Ok(Json(
+        summaries
+            .into_iter()
+            .map(|s| WalkerTipSummaryResponse {
+                walker_id: s.walker_id.to_string(),
+                currency: s.currency.to_string(),
+                walk_count: s.walk_count,
+                tipped_walk_count: s.tipped_walk_count,
+                tip_rate_percent: s.tip_rate_percent(),
+                tip_count: s.tip_count,
+                tip_cents: s.tip_cents,
+                average_tip_cents: s.average_tip_cents(),
+                post_walk_tip_count: s.post_walk_tip_count,
+                post_walk_tip_cents: s.post_walk_tip_cents,
+            })
+            .collect(),
+    ))
+}
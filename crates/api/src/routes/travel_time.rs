@@ -2,9 +2,10 @@ use axum::{
     extract::{Path, Query, State},
     Json,
 };
-use chrono::{Datelike, Duration, Utc};
+use chrono::{DateTime, Datelike, Duration, Utc};
 use db::{
-    BookingRepository, LocationRepository, TravelTimeCacheRepository, WalkerLocationRepository,
+    BookingRepository, LocationRepository, TravelTimeCacheRepository, WalkTrailRepository,
+    WalkerLocationRepository,
 };
 use serde::{Deserialize, Serialize};
 use shared::{types::Coordinates, AppError};
@@ -27,6 +28,8 @@ pub struct UpdateLocationRequest {
     pub heading: Option<f64>,
     pub speed_mps: Option<f64>,
     pub is_on_duty: Option<bool>,
+    /// When the fix was taken, for updates buffered while offline
+    pub recorded_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -41,6 +44,9 @@ pub struct LocationResponse {
 }
 
 /// POST /walkers/:id/location - Update walker's live location (from iOS app)
+///
+/// While the walker has a booking in progress, each update is also stored as
+/// a breadcrumb on that booking's trail.
 pub async fn update_walker_location(
     State(_state): State<AppState>,
     tenant: TenantContext,
@@ -74,6 +80,31 @@ pub async fn update_walker_location(
 
     let location = WalkerLocationRepository::upsert(&tenant.pool, walker_id, &update).await?;
 
+    if let Some(booking) =
+        BookingRepository::find_in_progress_for_walker(&tenant.pool, tenant.org_id, walker_id)
+            .await?
+    {
+        // Don't accept fixes from the future or from before the walk started
+        let now = Utc::now();
+        let recorded_at = req.recorded_at.unwrap_or(now).min(now);
+        if booking
+            .actual_start
+            .map_or(true, |start| recorded_at >= start)
+        {
+            let breadcrumb = db::models::CreateWalkBreadcrumb {
+                organization_id: tenant.org_id,
+                booking_id: booking.id,
+                walker_id,
+                latitude: req.latitude,
+                longitude: req.longitude,
+                accuracy_meters: req.accuracy_meters,
+                speed_mps: req.speed_mps,
+                recorded_at,
+            };
+            WalkTrailRepository::record(&tenant.pool, &breadcrumb).await?;
+        }
+    }
+
     Ok(Json(LocationResponse {
         walker_id: location.walker_id.to_string(),
         latitude: location.latitude,
@@ -0,0 +1,186 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use chrono::{Duration, Utc};
+use db::models::{OrganizationSettings, UpdateOrganization};
+use db::{BookingRepository, OrganizationRepository, WalkTrailRepository};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use shared::geometry::{process_track, track_stats, track_to_geojson, TrackFilter, TrackPoint};
+use shared::{AppError, DomainError};
+
+use crate::{
+    auth::{AuthUser, TenantContext},
+    error::{ApiError, ApiResult},
+    state::AppState,
+};
+
+/// Longest retention an organization can configure (about ten years)
+const MAX_RETENTION_DAYS: i32 = 3650;
+
+/// GET /bookings/:id/trail - The walked route as a GeoJSON LineString Feature
+///
+/// The raw breadcrumbs are cleaned, smoothed and downsampled; distance and
+/// duration are computed on the processed trail and returned as properties.
+pub async fn get_booking_trail(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> ApiResult<Json<Value>> {
+    let booking_id = id
+        .parse()
+        .map_err(|_| ApiError::from(AppError::Validation("Invalid booking ID".to_string())))?;
+
+    let booking = BookingRepository::find_by_id(&tenant.pool, tenant.org_id, booking_id)
+        .await?
+        .ok_or_else(|| ApiError::from(DomainError::BookingNotFound(id)))?;
+
+    // Customer, walker or an org manager can view the trail
+    if booking.customer_id != auth.user_id
+        && booking.walker_id != auth.user_id
+        && !tenant.is_manager(auth.user_id).await?
+    {
+        return Err(ApiError::from(AppError::Forbidden));
+    }
+
+    let breadcrumbs =
+        WalkTrailRepository::list_for_booking(&tenant.pool, tenant.org_id, booking_id).await?;
+    let raw: Vec<TrackPoint> = breadcrumbs.iter().map(TrackPoint::from).collect();
+    let trail = process_track(&raw, &TrackFilter::default());
+    let stats = track_stats(&trail);
+
+    let mut properties = Map::new();
+    properties.insert("booking_id".into(), booking.id.to_string().into());
+    properties.insert("status".into(), booking.status.to_string().into());
+    properties.insert(
+        "distance_meters".into(),
+        stats.distance_meters.round().into(),
+    );
+    properties.insert(
+        "distance_km".into(),
+        ((stats.distance_meters / 10.0).round() / 100.0).into(),
+    );
+    properties.insert("duration_seconds".into(), stats.duration_seconds.into());
+    properties.insert(
+        "duration_minutes".into(),
+        ((stats.duration_seconds + 59) / 60).into(),
+    );
+    properties.insert("average_speed_mps".into(), stats.average_speed_mps.into());
+    properties.insert(
+        "started_at".into(),
+        stats.started_at.map(|t| t.to_rfc3339()).into(),
+    );
+    properties.insert(
+        "ended_at".into(),
+        stats.ended_at.map(|t| t.to_rfc3339()).into(),
+    );
+    properties.insert("point_count".into(), trail.len().into());
+    properties.insert("raw_point_count".into(), raw.len().into());
+
+    Ok(Json(track_to_geojson(&trail, properties)))
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrailSettingsResponse {
+    pub retention_days: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateTrailSettingsRequest {
+    pub retention_days: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpdateTrailSettingsResponse {
+    pub retention_days: i32,
+    pub purged_points: u64,
+}
+
+/// GET /admin/walk-trails/settings - Current walk trail retention policy
+pub async fn get_trail_settings(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+) -> ApiResult<Json<TrailSettingsResponse>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let settings = organization_settings(&state, &tenant).await?;
+
+    Ok(Json(TrailSettingsResponse {
+        retention_days: settings.walk_trail_retention_days(),
+    }))
+}
+
+/// PUT /admin/walk-trails/settings - Change the retention policy, purging
+/// trails that fall outside the new period immediately
+pub async fn update_trail_settings(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Json(req): Json<UpdateTrailSettingsRequest>,
+) -> ApiResult<Json<UpdateTrailSettingsResponse>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    if !(1..=MAX_RETENTION_DAYS).contains(&req.retention_days) {
+        return Err(ApiError::from(AppError::Validation(format!(
+            "retention_days must be between 1 and {}",
+            MAX_RETENTION_DAYS
+        ))));
+    }
+
+    let current = organization_settings(&state, &tenant).await?;
+    OrganizationRepository::update(
+        &state.pool,
+        tenant.org_id,
+        UpdateOrganization {
+            name: None,
+            slug: None,
+            custom_domain: None,
+            settings: Some(OrganizationSettings {
+                walk_trail_retention_days: Some(req.retention_days),
+                ..current
+            }),
+        },
+    )
+    .await?
+    .ok_or_else(|| ApiError::from(DomainError::TenantNotFound(tenant.org_id.to_string())))?;
+
+    let purged_points = purge_expired_trails(&state, &tenant).await?;
+
+    Ok(Json(UpdateTrailSettingsResponse {
+        retention_days: req.retention_days,
+        purged_points,
+    }))
+}
+
+/// Delete breadcrumbs older than the organization's retention period,
+/// except those of walks under an open dispute, returning the number of
+/// points removed
+pub async fn purge_expired_trails(state: &AppState, tenant: &TenantContext) -> ApiResult<u64> {
+    let settings = organization_settings(state, tenant).await?;
+    let cutoff = Utc::now() - Duration::days(settings.walk_trail_retention_days().into());
+
+    let purged = WalkTrailRepository::delete_before(&tenant.pool, tenant.org_id, cutoff).await?;
+    if purged > 0 {
+        tracing::info!(
+            "Purged {} walk trail points for organization {}",
+            purged,
+            tenant.org_id
+        );
+    }
+
+    Ok(purged)
+}
+
+async fn organization_settings(
+    state: &AppState,
+    tenant: &TenantContext,
+) -> ApiResult<OrganizationSettings> {
+    let org = OrganizationRepository::find_by_id(&state.pool, tenant.org_id)
+        .await?
+        .ok_or_else(|| ApiError::from(DomainError::TenantNotFound(tenant.org_id.to_string())))?;
+
+    Ok(org.settings.0)
+}
@@ -0,0 +1,511 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use db::{
+    models::{
+        PayoutStatus, RevenueSplitType, UpsertRevenueSplit, WalkerEarning, WalkerPayout,
+        WalkerRevenueSplit,
+    },
+    PaymentProviderRepository, ServiceRepository, UserRepository, WalkerEarningRepository,
+};
+use integrations::stripe::StripeClient;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use shared::types::{ServiceId, UserId};
+use shared::AppError;
+use uuid::Uuid;
+
+use crate::{
+    auth::{AuthUser, TenantContext},
+    error::{ApiError, ApiResult},
+    state::AppState,
+    walker_earnings,
+};
+
+#[derive(Debug, Serialize)]
+pub struct RevenueSplitResponse {
+    pub id: String,
+    pub walker_id: String,
+    /// Service the split applies to; all services when absent
+    pub service_id: Option<String>,
+    pub split_type: RevenueSplitType,
+    pub percent: Option<Decimal>,
+    pub flat_cents: Option<i32>,
+}
+
+impl From<WalkerRevenueSplit> for RevenueSplitResponse {
+    fn from(split: WalkerRevenueSplit) -> Self {
+        Self {
+            id: split.id.to_string(),
+            walker_id: split.walker_id.to_string(),
+            service_id: split.service_id.map(|id| id.to_string()),
+            split_type: split.split_type,
+            percent: split.percent,
+            flat_cents: split.flat_cents,
+        }
+    }
+}
+
+/// GET /walkers/:id/revenue-splits - How a walker's walks are split
+pub async fn list_revenue_splits(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path(walker_id): Path<String>,
+) -> ApiResult<Json<Vec<RevenueSplitResponse>>> {
+    let walker_id = parse_walker_id(&walker_id)?;
+    if walker_id != auth.user_id && !tenant.is_manager(auth.user_id).await? {
+        return Err(ApiError::from(AppError::Forbidden));
+    }
+
+    let splits =
+        WalkerEarningRepository::list_splits(&tenant.pool, tenant.org_id, walker_id).await?;
+
+    Ok(Json(splits.into_iter().map(Into::into).collect()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevenueSplitRequest {
+    /// Only this service; omit to set the walker's default split
+    pub service_id: Option<ServiceId>,
+    pub split_type: RevenueSplitType,
+    /// Walker's percentage of the service price, for percent splits
+    pub percent: Option<Decimal>,
+    /// Walker's amount per walk, for flat splits
+    pub flat_cents: Option<i32>,
+}
+
+/// PUT /walkers/:id/revenue-splits - Set a walker's split for a service, or
+/// their default split (managers only)
+pub async fn set_revenue_split(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path(walker_id): Path<String>,
+    Json(req): Json<RevenueSplitRequest>,
+) -> ApiResult<Json<RevenueSplitResponse>> {
+    tenant.require_manager(auth.user_id).await?;
+    let walker_id = parse_walker_id(&walker_id)?;
+
+    let input = match req.split_type {
+        RevenueSplitType::Percent => {
+            let percent = req
+                .percent
+                .filter(|p| *p >= Decimal::ZERO && *p <= Decimal::ONE_HUNDRED)
+                .ok_or_else(|| {
+                    ApiError::from(AppError::Validation(
+                        "Percent splits need a percent between 0 and 100".to_string(),
+                    ))
+                })?;
+            UpsertRevenueSplit {
+                service_id: req.service_id,
+                split_type: req.split_type,
+                percent: Some(percent.round_dp(2)),
+                flat_cents: None,
+            }
+        }
+        RevenueSplitType::Flat => {
+            let flat_cents = req.flat_cents.filter(|c| *c >= 0).ok_or_else(|| {
+                ApiError::from(AppError::Validation(
+                    "Flat splits need a non-negative flat_cents".to_string(),
+                ))
+            })?;
+            UpsertRevenueSplit {
+                service_id: req.service_id,
+                split_type: req.split_type,
+                percent: None,
+                flat_cents: Some(flat_cents),
+            }
+        }
+    };
+
+    if let Some(service_id) = input.service_id {
+        ServiceRepository::find_by_id(&tenant.pool, tenant.org_id, service_id)
+            .await?
+            .ok_or_else(|| ApiError::from(AppError::NotFound("Service not found".to_string())))?;
+    }
+
+    let split =
+        WalkerEarningRepository::upsert_split(&tenant.pool, tenant.org_id, walker_id, input)
+            .await?;
+
+    Ok(Json(split.into()))
+}
+
+/// DELETE /walkers/:id/revenue-splits/:split_id - Remove a split (managers
+/// only)
+pub async fn delete_revenue_split(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path((walker_id, split_id)): Path<(String, Uuid)>,
+) -> ApiResult<Json<serde_json::Value>> {
+    tenant.require_manager(auth.user_id).await?;
+    let walker_id = parse_walker_id(&walker_id)?;
+
+    let deleted =
+        WalkerEarningRepository::delete_split(&tenant.pool, tenant.org_id, walker_id, split_id)
+            .await?;
+    if !deleted {
+        return Err(ApiError::from(AppError::NotFound(
+            "Revenue split not found".to_string(),
+        )));
+    }
+
+    Ok(Json(serde_json::json!({ "deleted": true })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EarningsQuery {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EarningsTotalsResponse {
+    pub currency: String,
+    pub walk_count: i64,
+    pub walk_cents: i64,
+    pub tip_cents: i64,
+    /// Taken back by refunds and lost disputes (negative)
+    pub reversed_cents: i64,
+    pub earned_cents: i64,
+    /// Earned in the period but not yet paid out
+    pub unpaid_cents: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EarningResponse {
+    pub id: String,
+    pub transaction_id: String,
+    pub kind: String,
+    pub amount_cents: i32,
+    pub currency: String,
+    pub walker_payout_id: Option<String>,
+    pub created_at: String,
+}
+
+impl From<WalkerEarning> for EarningResponse {
+    fn from(earning: WalkerEarning) -> Self {
+        Self {
+            id: earning.id.to_string(),
+            transaction_id: earning.transaction_id.to_string(),
+            kind: earning.kind.to_string(),
+            amount_cents: earning.amount_cents,
+            currency: earning.currency.to_string(),
+            walker_payout_id: earning.walker_payout_id.map(|id| id.to_string()),
+            created_at: earning.created_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct EarningsStatementResponse {
+    pub walker_id: String,
+    pub from: String,
+    pub to: String,
+    pub totals: Vec<EarningsTotalsResponse>,
+    pub earnings: Vec<EarningResponse>,
+}
+
+/// GET /walkers/:id/earnings - A walker's earnings statement for a period.
+/// Walkers see their own; managers see anyone's. Defaults to the last 30
+/// days.
+pub async fn get_earnings_statement(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path(walker_id): Path<String>,
+    Query(query): Query<EarningsQuery>,
+) -> ApiResult<Json<EarningsStatementResponse>> {
+    let walker_id = parse_walker_id(&walker_id)?;
+    if walker_id != auth.user_id && !tenant.is_manager(auth.user_id).await? {
+        return Err(ApiError::from(AppError::Forbidden));
+    }
+
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - Duration::days(30));
+    let totals =
+        WalkerEarningRepository::get_summary(&tenant.pool, tenant.org_id, walker_id, from, to)
+            .await?;
+    let earnings =
+        WalkerEarningRepository::list_for_walker(&tenant.pool, tenant.org_id, walker_id, from, to)
+            .await?;
+
+    Ok(Json(EarningsStatementResponse {
+        walker_id: walker_id.to_string(),
+        from: from.to_rfc3339(),
+        to: to.to_rfc3339(),
+        totals: totals
+            .into_iter()
+            .map(|t| EarningsTotalsResponse {
+                currency: t.currency.to_string(),
+                walk_count: t.walk_count,
+                walk_cents: t.walk_cents,
+                tip_cents: t.tip_cents,
+                reversed_cents: t.reversed_cents,
+                earned_cents: t.earned_cents,
+                unpaid_cents: t.unpaid_cents,
+            })
+            .collect(),
+        earnings: earnings.into_iter().map(Into::into).collect(),
+    }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct WalkerPayoutResponse {
+    pub id: String,
+    pub walker_id: String,
+    pub amount_cents: i32,
+    pub currency: String,
+    pub status: String,
+    pub stripe_transfer_id: Option<String>,
+    pub earning_count: i32,
+    pub period_start: String,
+    pub period_end: String,
+    pub initiated_at: Option<String>,
+    pub completed_at: Option<String>,
+    pub created_at: String,
+}
+
+impl From<WalkerPayout> for WalkerPayoutResponse {
+    fn from(payout: WalkerPayout) -> Self {
+        Self {
+            id: payout.id.to_string(),
+            walker_id: payout.walker_id.to_string(),
+            amount_cents: payout.amount_cents,
+            currency: payout.currency.to_string(),
+            status: payout.status.to_string(),
+            stripe_transfer_id: payout.stripe_transfer_id,
+            earning_count: payout.earning_count,
+            period_start: payout.period_start.to_rfc3339(),
+            period_end: payout.period_end.to_rfc3339(),
+            initiated_at: payout.initiated_at.map(|dt| dt.to_rfc3339()),
+            completed_at: payout.completed_at.map(|dt| dt.to_rfc3339()),
+            created_at: payout.created_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListWalkerPayoutsQuery {
+    /// Only this walker (managers only; walkers always see their own)
+    pub walker_id: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// GET /walker-payouts - Walker payouts, newest first
+pub async fn list_walker_payouts(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Query(query): Query<ListWalkerPayoutsQuery>,
+) -> ApiResult<Json<Vec<WalkerPayoutResponse>>> {
+    let walker_id = if tenant.is_manager(auth.user_id).await? {
+        query
+            .walker_id
+            .as_deref()
+            .map(parse_walker_id)
+            .transpose()?
+    } else {
+        Some(auth.user_id)
+    };
+
+    let payouts = WalkerEarningRepository::list_payouts(
+        &tenant.pool,
+        tenant.org_id,
+        walker_id,
+        query.limit.unwrap_or(50),
+        query.offset.unwrap_or(0),
+    )
+    .await?;
+
+    Ok(Json(payouts.into_iter().map(Into::into).collect()))
+}
+
+/// POST /walkers/:id/payouts - Pay a walker their unpaid earnings now
+/// (managers only)
+pub async fn pay_walker(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path(walker_id): Path<String>,
+) -> ApiResult<Json<Vec<WalkerPayoutResponse>>> {
+    tenant.require_manager(auth.user_id).await?;
+    let walker_id = parse_walker_id(&walker_id)?;
+
+    let payouts = walker_earnings::pay_walker(&state, &tenant, walker_id, Utc::now()).await?;
+    if payouts.is_empty() {
+        return Err(ApiError::from(AppError::Validation(
+            "Walker has no unpaid earnings".to_string(),
+        )));
+    }
+
+    Ok(Json(payouts.into_iter().map(Into::into).collect()))
+}
+
+/// POST /walker-payouts/:id/mark-paid - Record a payout made outside the
+/// platform (managers only)
+pub async fn mark_walker_payout_paid(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path(payout_id): Path<Uuid>,
+) -> ApiResult<Json<WalkerPayoutResponse>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let payout = WalkerEarningRepository::get_payout(&tenant.pool, tenant.org_id, payout_id)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::NotFound("Walker payout not found".to_string())))?;
+    if payout.status != PayoutStatus::Pending || payout.stripe_transfer_id.is_some() {
+        return Err(ApiError::from(AppError::Validation(format!(
+            "Walker payout is already {}",
+            payout.status
+        ))));
+    }
+
+    let payout = WalkerEarningRepository::update_payout_status(
+        &tenant.pool,
+        tenant.org_id,
+        payout.id,
+        PayoutStatus::Paid,
+        None,
+    )
+    .await?
+    .ok_or_else(|| ApiError::from(AppError::NotFound("Walker payout not found".to_string())))?;
+
+    Ok(Json(payout.into()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PayoutAccountRequest {
+    /// Where Stripe sends the walker if the onboarding link expires
+    pub refresh_url: String,
+    /// Where Stripe sends the walker when onboarding is done
+    pub return_url: String,
+    /// Two-letter country code; defaults to "US"
+    pub country: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PayoutAccountResponse {
+    pub stripe_account_id: String,
+    pub payouts_enabled: bool,
+    /// Stripe onboarding link, when onboarding is needed
+    pub onboarding_url: Option<String>,
+}
+
+/// POST /walker/payout-account - Connect a Stripe account to be paid through,
+/// returning the onboarding link
+pub async fn connect_payout_account(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Json(req): Json<PayoutAccountRequest>,
+) -> ApiResult<Json<PayoutAccountResponse>> {
+    let client = stripe_client(&state)?;
+
+    let account =
+        match WalkerEarningRepository::get_account(&tenant.pool, tenant.org_id, auth.user_id)
+            .await?
+        {
+            Some(account) => account,
+            None => {
+                let user = UserRepository::find_by_id(&tenant.pool, tenant.org_id, auth.user_id)
+                    .await?
+                    .ok_or_else(|| {
+                        ApiError::from(AppError::NotFound("User not found".to_string()))
+                    })?;
+                let country = req.country.as_deref().unwrap_or("US").to_uppercase();
+                let stripe_account = client
+                    .create_express_account(&user.email, &country, "individual")
+                    .await
+                    .map_err(|e| ApiError::from(AppError::ExternalApi(e.to_string())))?;
+
+                // Route the account's Connect webhooks to this organization
+                PaymentProviderRepository::register_stripe_account(
+                    &state.pool,
+                    tenant.org_id,
+                    &stripe_account.id,
+                )
+                .await?;
+                WalkerEarningRepository::upsert_account(
+                    &tenant.pool,
+                    tenant.org_id,
+                    auth.user_id,
+                    &stripe_account.id,
+                    stripe_account.payouts_enabled,
+                )
+                .await?
+            }
+        };
+
+    let link = client
+        .create_account_link(
+            &account.stripe_account_id,
+            &req.refresh_url,
+            &req.return_url,
+            "account_onboarding",
+        )
+        .await
+        .map_err(|e| ApiError::from(AppError::ExternalApi(e.to_string())))?;
+
+    Ok(Json(PayoutAccountResponse {
+        stripe_account_id: account.stripe_account_id,
+        payouts_enabled: account.payouts_enabled,
+        onboarding_url: Some(link.url),
+    }))
+}
+
+/// GET /walker/payout-account - The walker's Stripe account, refreshed from
+/// Stripe
+pub async fn get_payout_account(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+) -> ApiResult<Json<Option<PayoutAccountResponse>>> {
+    let Some(account) =
+        WalkerEarningRepository::get_account(&tenant.pool, tenant.org_id, auth.user_id).await?
+    else {
+        return Ok(Json(None));
+    };
+
+    let stripe_account = stripe_client(&state)?
+        .get_account(&account.stripe_account_id)
+        .await
+        .map_err(|e| ApiError::from(AppError::ExternalApi(e.to_string())))?;
+    let account = if stripe_account.payouts_enabled != account.payouts_enabled {
+        WalkerEarningRepository::upsert_account(
+            &tenant.pool,
+            tenant.org_id,
+            auth.user_id,
+            &account.stripe_account_id,
+            stripe_account.payouts_enabled,
+        )
+        .await?
+    } else {
+        account
+    };
+
+    Ok(Json(Some(PayoutAccountResponse {
+        stripe_account_id: account.stripe_account_id,
+        payouts_enabled: account.payouts_enabled,
+        onboarding_url: None,
+    })))
+}
+
+fn stripe_client(state: &AppState) -> ApiResult<StripeClient> {
+    let secret_key =
+        state.config.stripe_secret_key.clone().ok_or_else(|| {
+            ApiError::from(AppError::Internal("Stripe not configured".to_string()))
+        })?;
+    Ok(StripeClient::new(secret_key, None))
+}
+
+fn parse_walker_id(id: &str) -> ApiResult<UserId> {
+    id.parse()
+        .map_err(|_| ApiError::from(AppError::Validation("Invalid walker ID".to_string())))
+}
@@ -0,0 +1,138 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use db::models::{PaymentWebhookEvent, WebhookEventStatus};
+use db::WebhookEventRepository;
+use serde::{Deserialize, Serialize};
+use shared::AppError;
+use uuid::Uuid;
+
+use crate::{
+    auth::{AuthUser, TenantContext},
+    error::{ApiError, ApiResult},
+    state::AppState,
+    webhook_events,
+};
+
+#[derive(Debug, Serialize)]
+pub struct WebhookEventResponse {
+    pub id: String,
+    pub provider: String,
+    pub event_id: String,
+    pub event_type: String,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: Option<String>,
+    pub last_attempt_at: Option<String>,
+    pub processed_at: Option<String>,
+    pub error_message: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookEventDetailResponse {
+    #[serde(flatten)]
+    pub event: WebhookEventResponse,
+    pub payload: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListWebhookEventsQuery {
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// GET /admin/webhook-events - Webhook events, newest first
+pub async fn list_webhook_events(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Query(query): Query<ListWebhookEventsQuery>,
+) -> ApiResult<Json<Vec<WebhookEventResponse>>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let status = match query.status.as_deref().map(str::to_lowercase).as_deref() {
+        None => None,
+        Some("pending") => Some(WebhookEventStatus::Pending),
+        Some("processing") => Some(WebhookEventStatus::Processing),
+        Some("processed") => Some(WebhookEventStatus::Processed),
+        Some("failed") => Some(WebhookEventStatus::Failed),
+        Some("dead") => Some(WebhookEventStatus::Dead),
+        Some(_) => {
+            return Err(ApiError::from(AppError::Validation(
+                "Invalid status filter".to_string(),
+            )))
+        }
+    };
+
+    let events = WebhookEventRepository::list_for_org(
+        &tenant.pool,
+        tenant.org_id,
+        status,
+        query.limit.unwrap_or(50).min(200),
+        query.offset.unwrap_or(0),
+    )
+    .await?;
+
+    Ok(Json(events.iter().map(event_response).collect()))
+}
+
+/// GET /admin/webhook-events/:id - A webhook event and its payload
+pub async fn get_webhook_event(
+    State(_state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path(event_id): Path<Uuid>,
+) -> ApiResult<Json<WebhookEventDetailResponse>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let event = WebhookEventRepository::get_by_id(&tenant.pool, tenant.org_id, event_id)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::NotFound("Webhook event".to_string())))?;
+
+    Ok(Json(detail_response(event)))
+}
+
+/// POST /admin/webhook-events/:id/replay - Process a failed or dead-lettered
+/// event again
+pub async fn replay_webhook_event(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    auth: AuthUser,
+    Path(event_id): Path<Uuid>,
+) -> ApiResult<Json<WebhookEventDetailResponse>> {
+    tenant.require_manager(auth.user_id).await?;
+
+    let event = webhook_events::replay(&state, &tenant, event_id).await?;
+
+    Ok(Json(detail_response(event)))
+}
+
+fn event_response(event: &PaymentWebhookEvent) -> WebhookEventResponse {
+    let awaiting = matches!(
+        event.status,
+        WebhookEventStatus::Pending | WebhookEventStatus::Failed
+    );
+    WebhookEventResponse {
+        id: event.id.to_string(),
+        provider: event.provider.to_string(),
+        event_id: event.event_id.clone(),
+        event_type: event.event_type.clone(),
+        status: event.status.to_string(),
+        attempts: event.attempts,
+        next_attempt_at: awaiting.then(|| event.next_attempt_at.to_rfc3339()),
+        last_attempt_at: event.last_attempt_at.map(|dt| dt.to_rfc3339()),
+        processed_at: event.processed_at.map(|dt| dt.to_rfc3339()),
+        error_message: event.error_message.clone(),
+        created_at: event.created_at.to_rfc3339(),
+    }
+}
+
+fn detail_response(event: PaymentWebhookEvent) -> WebhookEventDetailResponse {
+    WebhookEventDetailResponse {
+        event: event_response(&event),
+        payload: event.payload,
+    }
+}
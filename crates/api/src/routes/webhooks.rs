@@ -4,17 +4,31 @@ use axum::{
     http::{HeaderMap, StatusCode},
 };
 use base64::Engine;
+use chrono::{DateTime, NaiveDate, Utc};
 use db::{
-    models::{CreateDispute, CreateWebhookEvent, DisputeStatus, TransactionStatus, UpdateDispute},
-    DisputeRepository, PaymentProviderRepository, TransactionRepository, WebhookEventRepository,
+    models::{
+        CreateDispute, CreateWebhookEvent, Dispute, DisputeStatus, PaymentProviderType,
+        PaymentWebhookEvent, PayoutStatus, RefundStatus, Transaction, TransactionStatus,
+    },
+    DisputeRepository, PaymentProviderRepository, PayoutRepository, SubscriptionRepository,
+    TransactionRepository, WalkerEarningRepository, WebhookEventRepository,
 };
 use hmac::{Hmac, Mac};
+use integrations::stripe::Account;
 use serde::Deserialize;
 use sha2::Sha256;
 use shared::types::OrganizationId;
+use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::state::AppState;
+use crate::{
+    auth::TenantContext,
+    disputes, ledger,
+    payments::{record_provider_refund, transaction_gateway},
+    payouts,
+    state::AppState,
+    tax, tenant_billing, walker_earnings, webhook_events,
+};
 
 type HmacSha256 = Hmac<Sha256>;
 
@@ -41,7 +55,7 @@ pub async fn stripe_webhook(
     let provider = PaymentProviderRepository::get_by_type(
         &pool,
         OrganizationId::from_uuid(org_id),
-        db::models::PaymentProviderType::Stripe,
+        PaymentProviderType::Stripe,
     )
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
@@ -71,238 +85,440 @@ pub async fn stripe_webhook(
     let event: StripeWebhookEvent = serde_json::from_str(payload_str)
         .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid JSON: {}", e)))?;
 
-    // Check for idempotency
-    let already_processed = WebhookEventRepository::is_processed(&pool, "stripe", &event.id)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    if already_processed {
-        return Ok(StatusCode::OK);
-    }
-
-    // Record the event
-    let webhook_input = CreateWebhookEvent {
-        provider: "stripe".to_string(),
-        event_id: event.id.clone(),
-        event_type: event.event_type.clone(),
-        payload: serde_json::from_str(payload_str).unwrap_or_default(),
-    };
-
-    WebhookEventRepository::create(&pool, webhook_input)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    record_event(
+        &state,
+        pool,
+        CreateWebhookEvent {
+            organization_id: OrganizationId::from_uuid(org_id),
+            provider: PaymentProviderType::Stripe,
+            event_id: event.id,
+            event_type: event.event_type,
+            payload: serde_json::from_str(payload_str).unwrap_or_default(),
+        },
+    )
+    .await
+}
 
-    // Process the event
+/// Apply a Stripe event
+async fn process_stripe_event(
+    state: &AppState,
+    pool: &PgPool,
+    org_id: Uuid,
+    event: StripeWebhookEvent,
+) -> Result<(), (StatusCode, String)> {
     match event.event_type.as_str() {
         "payment_intent.succeeded" => {
             if let Some(payment_intent_id) = event.data.object.get("id").and_then(|v| v.as_str()) {
                 // Find and update the transaction
                 if let Some(transaction) = TransactionRepository::get_by_external_id(
-                    &pool,
+                    pool,
                     OrganizationId::from_uuid(org_id),
                     payment_intent_id,
                 )
                 .await
                 .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
                 {
-                    TransactionRepository::update_status(
-                        &pool,
+                    if let Some(transaction) = TransactionRepository::update_status(
+                        pool,
                         transaction.id,
                         TransactionStatus::Succeeded,
                     )
                     .await
-                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+                    {
+                        post_charge(state, pool, &transaction).await?;
+                    }
                 }
             }
         }
-        "payment_intent.payment_failed" => {
+        "payment_intent.amount_capturable_updated" => {
+            // The payment was authorized for manual capture
             if let Some(payment_intent_id) = event.data.object.get("id").and_then(|v| v.as_str()) {
                 if let Some(transaction) = TransactionRepository::get_by_external_id(
-                    &pool,
+                    pool,
                     OrganizationId::from_uuid(org_id),
                     payment_intent_id,
                 )
                 .await
                 .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
                 {
-                    TransactionRepository::update_status(
-                        &pool,
-                        transaction.id,
-                        TransactionStatus::Failed,
-                    )
-                    .await
-                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+                    if matches!(
+                        transaction.status,
+                        TransactionStatus::Pending | TransactionStatus::Processing
+                    ) {
+                        let tenant = TenantContext {
+                            org_id: transaction.organization_id,
+                            pool: pool.clone(),
+                        };
+                        let (_, gateway) = transaction_gateway(state, &tenant, &transaction)
+                            .await
+                            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.0.to_string()))?;
+                        let now = Utc::now();
+                        TransactionRepository::record_authorization(
+                            pool,
+                            transaction.id,
+                            payment_intent_id,
+                            now,
+                            now + gateway.authorization_validity(),
+                        )
+                        .await
+                        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+                    }
                 }
             }
         }
-        "charge.refunded" => {
-            if let Some(payment_intent_id) = event
-                .data
-                .object
-                .get("payment_intent")
-                .and_then(|v| v.as_str())
-            {
+        "payment_intent.canceled" => {
+            // A hold was released, either by us or because it expired
+            if let Some(payment_intent_id) = event.data.object.get("id").and_then(|v| v.as_str()) {
                 if let Some(transaction) = TransactionRepository::get_by_external_id(
-                    &pool,
+                    pool,
                     OrganizationId::from_uuid(org_id),
                     payment_intent_id,
                 )
                 .await
                 .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
                 {
-                    // Check if full or partial refund
-                    let refunded = event
-                        .data
-                        .object
-                        .get("amount_refunded")
-                        .and_then(|v| v.as_i64())
-                        .unwrap_or(0) as i32;
-
-                    let total = event
-                        .data
-                        .object
-                        .get("amount")
-                        .and_then(|v| v.as_i64())
-                        .unwrap_or(0) as i32;
-
-                    let status = if refunded >= total {
-                        TransactionStatus::Refunded
-                    } else {
-                        TransactionStatus::PartiallyRefunded
-                    };
-
-                    TransactionRepository::update_status(&pool, transaction.id, status)
+                    if transaction.is_authorized() {
+                        TransactionRepository::update_status(
+                            pool,
+                            transaction.id,
+                            TransactionStatus::Canceled,
+                        )
                         .await
                         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+                    }
                 }
             }
         }
-        "charge.dispute.created" => {
-            if let Some(payment_intent_id) = event
-                .data
-                .object
-                .get("payment_intent")
-                .and_then(|v| v.as_str())
-            {
+        "payment_intent.payment_failed" => {
+            if let Some(payment_intent_id) = event.data.object.get("id").and_then(|v| v.as_str()) {
                 if let Some(transaction) = TransactionRepository::get_by_external_id(
-                    &pool,
+                    pool,
                     OrganizationId::from_uuid(org_id),
                     payment_intent_id,
                 )
                 .await
                 .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
                 {
-                    let dispute_id = event
-                        .data
-                        .object
-                        .get("id")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("");
-
-                    let amount = event
-                        .data
-                        .object
-                        .get("amount")
-                        .and_then(|v| v.as_i64())
-                        .unwrap_or(0) as i32;
-
-                    let reason = event
-                        .data
-                        .object
-                        .get("reason")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("general")
-                        .to_string();
-
-                    // Create dispute record
-                    let dispute_input = CreateDispute {
-                        transaction_id: transaction.id,
-                        amount_cents: amount,
-                        currency: "USD".to_string(),
-                        stripe_dispute_id: Some(dispute_id.to_string()),
-                        square_dispute_id: None,
-                        reason,
-                        evidence_due_by: None, // TODO: Parse from Stripe response
-                    };
-
-                    DisputeRepository::create(
-                        &pool,
-                        OrganizationId::from_uuid(org_id),
-                        dispute_input,
-                    )
-                    .await
-                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-                    // Update transaction status
                     TransactionRepository::update_status(
-                        &pool,
+                        pool,
                         transaction.id,
-                        TransactionStatus::Disputed,
+                        TransactionStatus::Failed,
                     )
                     .await
                     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
                 }
             }
         }
-        "charge.dispute.closed" => {
-            let dispute_id = event
+        "charge.refunded" => {
+            let payment_intent_id = event
                 .data
                 .object
-                .get("id")
-                .and_then(|v| v.as_str())
-                .unwrap_or("");
+                .get("payment_intent")
+                .and_then(|v| v.as_str());
+            let refunds = event
+                .data
+                .object
+                .get("refunds")
+                .and_then(|r| r.get("data"))
+                .and_then(|d| d.as_array());
 
-            let status = event
+            if let (Some(payment_intent_id), Some(refunds)) = (payment_intent_id, refunds) {
+                for refund in refunds {
+                    apply_stripe_refund(state, pool, org_id, payment_intent_id, refund).await?;
+                }
+            }
+        }
+        "refund.created" | "refund.updated" | "charge.refund.updated" => {
+            if let Some(payment_intent_id) = event
                 .data
                 .object
-                .get("status")
+                .get("payment_intent")
                 .and_then(|v| v.as_str())
-                .unwrap_or("");
-
-            let dispute_status = match status {
-                "won" => DisputeStatus::Won,
-                _ => DisputeStatus::Lost,
-            };
-
-            if let Some(dispute) = DisputeRepository::get_by_stripe_dispute(&pool, dispute_id)
-                .await
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
             {
-                let update_input = UpdateDispute {
-                    status: Some(dispute_status),
-                    resolved_at: Some(chrono::Utc::now()),
-                    outcome: Some(status.to_string()),
-                    ..Default::default()
-                };
-
-                DisputeRepository::update(&pool, dispute.id, update_input)
-                    .await
-                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+                apply_stripe_refund(state, pool, org_id, payment_intent_id, &event.data.object)
+                    .await?;
+            }
+        }
+        "charge.dispute.created"
+        | "charge.dispute.updated"
+        | "charge.dispute.funds_withdrawn"
+        | "charge.dispute.funds_reinstated"
+        | "charge.dispute.closed" => {
+            apply_stripe_dispute(pool, OrganizationId::from_uuid(org_id), &event.data.object)
+                .await?;
+        }
+        "payout.created" | "payout.updated" | "payout.paid" | "payout.failed"
+        | "payout.canceled" => {
+            let object = &event.data.object;
+            let payout_id = object.get("id").and_then(|v| v.as_str()).unwrap_or("");
+
+            if let Some(payout) = PayoutRepository::get_by_stripe_payout(
+                pool,
+                OrganizationId::from_uuid(org_id),
+                payout_id,
+            )
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            {
+                let status = object.get("status").and_then(|v| v.as_str()).unwrap_or("");
+                let arrival_date = object
+                    .get("arrival_date")
+                    .and_then(|v| v.as_i64())
+                    .and_then(|ts| DateTime::from_timestamp(ts, 0));
+
+                payouts::apply_provider_status(
+                    pool,
+                    &payout,
+                    PayoutStatus::from_provider(status),
+                    object.get("failure_code").and_then(|v| v.as_str()),
+                    object.get("failure_message").and_then(|v| v.as_str()),
+                    arrival_date,
+                )
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.0.to_string()))?;
+            }
+        }
+        "transfer.reversed" => {
+            let object = &event.data.object;
+            let transfer_id = object.get("id").and_then(|v| v.as_str()).unwrap_or("");
+            // Partial reversals leave the payout paid
+            let fully_reversed = object
+                .get("reversed")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+
+            if fully_reversed {
+                let walker_payout = walker_earnings::reverse_transfer(
+                    pool,
+                    OrganizationId::from_uuid(org_id),
+                    transfer_id,
+                )
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-                // If dispute was won, revert transaction to succeeded
-                if dispute_status == DisputeStatus::Won {
-                    TransactionRepository::update_status(
-                        &pool,
-                        dispute.transaction_id,
-                        TransactionStatus::Succeeded,
+                if walker_payout.is_none() {
+                    if let Some(payout) = PayoutRepository::get_by_stripe_transfer(
+                        pool,
+                        OrganizationId::from_uuid(org_id),
+                        transfer_id,
                     )
                     .await
-                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+                    {
+                        payouts::apply_provider_status(
+                            pool,
+                            &payout,
+                            PayoutStatus::Failed,
+                            Some("transfer_reversed"),
+                            Some("Transfer reversed"),
+                            None,
+                        )
+                        .await
+                        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.0.to_string()))?;
+                    }
                 }
+            } else {
+                tracing::warn!("Transfer {} was partially reversed", transfer_id);
             }
         }
+        "account.updated" => {
+            let account: Account = serde_json::from_value(event.data.object.clone())
+                .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid account: {}", e)))?;
+            apply_account_update(pool, OrganizationId::from_uuid(org_id), &account).await?;
+        }
+        "account.application.deauthorized" => {
+            if let Some(account_id) = event.account.as_deref() {
+                if PaymentProviderRepository::deauthorize_stripe_account(
+                    pool,
+                    OrganizationId::from_uuid(org_id),
+                    account_id,
+                )
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+                .is_some()
+                {
+                    tracing::warn!(
+                        "Stripe account {} disconnected from organization {}",
+                        account_id,
+                        org_id
+                    );
+                }
+            }
+        }
+        "customer.subscription.created"
+        | "customer.subscription.updated"
+        | "customer.subscription.deleted"
+        | "customer.subscription.paused"
+        | "customer.subscription.resumed"
+        | "customer.subscription.trial_will_end" => {
+            tenant_billing::apply_subscription_event(
+                state,
+                pool,
+                OrganizationId::from_uuid(org_id),
+                &event.event_type,
+                &event.data.object,
+            )
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.0.to_string()))?;
+        }
+        "invoice.paid" | "invoice.payment_failed" => {
+            tenant_billing::apply_invoice_event(
+                state,
+                pool,
+                OrganizationId::from_uuid(org_id),
+                &event.event_type,
+                &event.data.object,
+            )
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.0.to_string()))?;
+        }
         _ => {
             // Unhandled event type - that's OK
             tracing::debug!("Unhandled Stripe webhook event: {}", event.event_type);
         }
     }
 
-    // Mark event as processed
-    WebhookEventRepository::mark_processed(&pool, "stripe", &event.id)
+    Ok(())
+}
+
+/// Stripe Connect webhook handler, for events on connected accounts
+/// POST /webhooks/stripe-connect
+pub async fn stripe_connect_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let (event, payload) = parse_platform_event(
+        &headers,
+        &body,
+        state.config.stripe_connect_webhook_secret.as_deref(),
+    )?;
+
+    // Events for accounts that aren't connected to an organization are
+    // acknowledged so Stripe doesn't keep resending them
+    let Some(account) = event.account.clone() else {
+        tracing::debug!("Stripe Connect event {} has no account", event.id);
+        return Ok(StatusCode::OK);
+    };
+    let Some(org_id) = PaymentProviderRepository::find_org_by_stripe_account(&state.pool, &account)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    else {
+        tracing::warn!(
+            "Stripe Connect event {} is for unknown account {}",
+            event.id,
+            account
+        );
+        return Ok(StatusCode::OK);
+    };
 
-    Ok(StatusCode::OK)
+    let pool = state
+        .tenant_pool_manager
+        .get_pool(org_id)
+        .await
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid organization".to_string()))?;
+
+    record_event(
+        &state,
+        pool,
+        CreateWebhookEvent {
+            organization_id: org_id,
+            provider: PaymentProviderType::Stripe,
+            event_id: event.id,
+            event_type: event.event_type,
+            payload,
+        },
+    )
+    .await
+}
+
+/// Stripe Billing webhook handler, for the platform's billing of
+/// organizations
+/// POST /webhooks/stripe-billing
+pub async fn stripe_billing_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let (event, payload) = parse_platform_event(
+        &headers,
+        &body,
+        state.config.stripe_billing_webhook_secret.as_deref(),
+    )?;
+
+    // Events for customers that aren't an organization's billing customer
+    // are acknowledged so Stripe doesn't keep resending them
+    let Some(customer) = event
+        .data
+        .object
+        .get("customer")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+    else {
+        tracing::debug!("Stripe billing event {} has no customer", event.id);
+        return Ok(StatusCode::OK);
+    };
+    let Some(org_id) = SubscriptionRepository::find_org_by_billing_customer(&state.pool, &customer)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    else {
+        tracing::warn!(
+            "Stripe billing event {} is for unknown customer {}",
+            event.id,
+            customer
+        );
+        return Ok(StatusCode::OK);
+    };
+
+    let pool = state
+        .tenant_pool_manager
+        .get_pool(org_id)
+        .await
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid organization".to_string()))?;
+
+    record_event(
+        &state,
+        pool,
+        CreateWebhookEvent {
+            organization_id: org_id,
+            provider: PaymentProviderType::Platform,
+            event_id: event.id,
+            event_type: event.event_type,
+            payload,
+        },
+    )
+    .await
+}
+
+/// Verify and parse an event Stripe sent to one of the platform's webhooks,
+/// returning it along with its payload
+fn parse_platform_event(
+    headers: &HeaderMap,
+    body: &[u8],
+    webhook_secret: Option<&str>,
+) -> Result<(StripeWebhookEvent, serde_json::Value), (StatusCode, String)> {
+    let signature = headers
+        .get("stripe-signature")
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Missing signature".to_string()))?;
+
+    let webhook_secret = webhook_secret.ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            "Webhook secret not configured".to_string(),
+        )
+    })?;
+
+    verify_stripe_signature(body, signature, webhook_secret)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid signature: {}", e)))?;
+
+    let payload: serde_json::Value = serde_json::from_slice(body)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid JSON: {}", e)))?;
+    let event: StripeWebhookEvent = serde_json::from_value(payload.clone())
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid JSON: {}", e)))?;
+
+    Ok((event, payload))
 }
 
 /// Square webhook handler
@@ -328,7 +544,7 @@ pub async fn square_webhook(
     let provider = PaymentProviderRepository::get_by_type(
         &pool,
         OrganizationId::from_uuid(org_id),
-        db::models::PaymentProviderType::Square,
+        PaymentProviderType::Square,
     )
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
@@ -361,28 +577,27 @@ pub async fn square_webhook(
     let event: SquareWebhookEvent = serde_json::from_str(payload_str)
         .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid JSON: {}", e)))?;
 
-    // Check for idempotency
-    let already_processed = WebhookEventRepository::is_processed(&pool, "square", &event.event_id)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-    if already_processed {
-        return Ok(StatusCode::OK);
-    }
-
-    // Record the event
-    let webhook_input = CreateWebhookEvent {
-        provider: "square".to_string(),
-        event_id: event.event_id.clone(),
-        event_type: event.event_type.clone(),
-        payload: serde_json::from_str(payload_str).unwrap_or_default(),
-    };
-
-    WebhookEventRepository::create(&pool, webhook_input)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    record_event(
+        &state,
+        pool,
+        CreateWebhookEvent {
+            organization_id: OrganizationId::from_uuid(org_id),
+            provider: PaymentProviderType::Square,
+            event_id: event.event_id,
+            event_type: event.event_type,
+            payload: serde_json::from_str(payload_str).unwrap_or_default(),
+        },
+    )
+    .await
+}
 
-    // Process the event
+/// Apply a Square event
+async fn process_square_event(
+    state: &AppState,
+    pool: &PgPool,
+    org_id: Uuid,
+    event: SquareWebhookEvent,
+) -> Result<(), (StatusCode, String)> {
     match event.event_type.as_str() {
         "payment.completed" => {
             if let Some(payment_id) = event
@@ -393,20 +608,23 @@ pub async fn square_webhook(
                 .and_then(|v| v.as_str())
             {
                 if let Some(transaction) = TransactionRepository::get_by_external_id(
-                    &pool,
+                    pool,
                     OrganizationId::from_uuid(org_id),
                     payment_id,
                 )
                 .await
                 .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
                 {
-                    TransactionRepository::update_status(
-                        &pool,
+                    if let Some(transaction) = TransactionRepository::update_status(
+                        pool,
                         transaction.id,
                         TransactionStatus::Succeeded,
                     )
                     .await
-                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+                    {
+                        post_charge(state, pool, &transaction).await?;
+                    }
                 }
             }
         }
@@ -419,7 +637,7 @@ pub async fn square_webhook(
                 .and_then(|v| v.as_str())
             {
                 if let Some(transaction) = TransactionRepository::get_by_external_id(
-                    &pool,
+                    pool,
                     OrganizationId::from_uuid(org_id),
                     payment_id,
                 )
@@ -427,7 +645,7 @@ pub async fn square_webhook(
                 .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
                 {
                     TransactionRepository::update_status(
-                        &pool,
+                        pool,
                         transaction.id,
                         TransactionStatus::Failed,
                     )
@@ -437,89 +655,78 @@ pub async fn square_webhook(
             }
         }
         "refund.created" | "refund.updated" => {
-            // Handle refunds
             if let Some(refund) = event.data.object.get("refund") {
+                let refund_id = refund.get("id").and_then(|v| v.as_str()).unwrap_or("");
                 let payment_id = refund
                     .get("payment_id")
                     .and_then(|v| v.as_str())
                     .unwrap_or("");
+                let amount = refund
+                    .get("amount_money")
+                    .and_then(|m| m.get("amount"))
+                    .and_then(|v| v.as_i64())
+                    .unwrap_or(0) as i32;
                 let status = refund.get("status").and_then(|v| v.as_str()).unwrap_or("");
 
-                if status == "COMPLETED" {
-                    if let Some(transaction) = TransactionRepository::get_by_external_id(
-                        &pool,
-                        OrganizationId::from_uuid(org_id),
-                        payment_id,
-                    )
-                    .await
-                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-                    {
-                        TransactionRepository::update_status(
-                            &pool,
-                            transaction.id,
-                            TransactionStatus::Refunded,
-                        )
-                        .await
-                        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-                    }
-                }
+                apply_refund(
+                    state,
+                    pool,
+                    org_id,
+                    payment_id,
+                    refund_id,
+                    amount,
+                    RefundStatus::from_provider(status),
+                )
+                .await?;
             }
         }
-        "dispute.created" => {
+        "dispute.created" | "dispute.state.changed" | "dispute.state.updated" => {
             if let Some(dispute) = event.data.object.get("dispute") {
-                let dispute_id = dispute.get("id").and_then(|v| v.as_str()).unwrap_or("");
-                let payment_id = dispute
-                    .get("disputed_payment")
-                    .and_then(|dp| dp.get("payment_id"))
+                apply_square_dispute(pool, OrganizationId::from_uuid(org_id), dispute).await?;
+            }
+        }
+        "payout.sent" | "payout.paid" | "payout.failed" => {
+            let object = event.data.object.get("payout");
+            let payout_id = object
+                .and_then(|p| p.get("id"))
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+
+            // Square pays out on its own schedule; only payouts we recorded
+            // are tracked
+            if let Some(payout) = PayoutRepository::get_by_square_payout(
+                pool,
+                OrganizationId::from_uuid(org_id),
+                payout_id,
+            )
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            {
+                let status = object
+                    .and_then(|p| p.get("status"))
                     .and_then(|v| v.as_str())
                     .unwrap_or("");
-
-                let amount = dispute
-                    .get("amount_money")
-                    .and_then(|m| m.get("amount"))
-                    .and_then(|v| v.as_i64())
-                    .unwrap_or(0) as i32;
-
-                let reason = dispute
-                    .get("reason")
+                let arrival_date = object
+                    .and_then(|p| p.get("arrival_date"))
                     .and_then(|v| v.as_str())
-                    .unwrap_or("general")
-                    .to_string();
-
-                if let Some(transaction) = TransactionRepository::get_by_external_id(
-                    &pool,
-                    OrganizationId::from_uuid(org_id),
-                    payment_id,
+                    .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+                    .and_then(|d| d.and_hms_opt(0, 0, 0))
+                    .map(|d| d.and_utc());
+                let failure_message = (status.eq_ignore_ascii_case("failed"))
+                    .then_some("Square could not complete the payout");
+
+                payouts::apply_provider_status(
+                    pool,
+                    &payout,
+                    PayoutStatus::from_provider(status),
+                    None,
+                    failure_message,
+                    arrival_date,
                 )
                 .await
-                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
-                {
-                    let dispute_input = CreateDispute {
-                        transaction_id: transaction.id,
-                        amount_cents: amount,
-                        currency: "USD".to_string(),
-                        stripe_dispute_id: None,
-                        square_dispute_id: Some(dispute_id.to_string()),
-                        reason,
-                        evidence_due_by: None,
-                    };
-
-                    DisputeRepository::create(
-                        &pool,
-                        OrganizationId::from_uuid(org_id),
-                        dispute_input,
-                    )
-                    .await
-                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-
-                    TransactionRepository::update_status(
-                        &pool,
-                        transaction.id,
-                        TransactionStatus::Disputed,
-                    )
-                    .await
-                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
-                }
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.0.to_string()))?;
+            } else {
+                tracing::debug!("Square payout {} is not one of ours", payout_id);
             }
         }
         _ => {
@@ -527,14 +734,50 @@ pub async fn square_webhook(
         }
     }
 
-    // Mark event as processed
-    WebhookEventRepository::mark_processed(&pool, "square", &event.event_id)
+    Ok(())
+}
+
+/// Store an event and acknowledge it, processing it in the background. An
+/// event the provider already sent is acknowledged without processing it
+/// again.
+async fn record_event(
+    state: &AppState,
+    pool: PgPool,
+    input: CreateWebhookEvent,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let org_id = input.organization_id;
+    if let Some(event) = WebhookEventRepository::create(&pool, input)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    {
+        webhook_events::spawn_processing(state.clone(), TenantContext { org_id, pool }, event.id);
+    }
 
     Ok(StatusCode::OK)
 }
 
+/// Apply a stored webhook event
+pub async fn process_event(
+    state: &AppState,
+    pool: &PgPool,
+    org_id: OrganizationId,
+    event: &PaymentWebhookEvent,
+) -> Result<(), String> {
+    let result = match event.provider {
+        PaymentProviderType::Square => {
+            let parsed: SquareWebhookEvent = serde_json::from_value(event.payload.clone())
+                .map_err(|e| format!("Invalid payload: {}", e))?;
+            process_square_event(state, pool, *org_id.as_uuid(), parsed).await
+        }
+        PaymentProviderType::Stripe | PaymentProviderType::Platform => {
+            let parsed: StripeWebhookEvent = serde_json::from_value(event.payload.clone())
+                .map_err(|e| format!("Invalid payload: {}", e))?;
+            process_stripe_event(state, pool, *org_id.as_uuid(), parsed).await
+        }
+    };
+    result.map_err(|(_, message)| message)
+}
+
 // Webhook event structures
 
 #[derive(Debug, Deserialize)]
@@ -542,6 +785,9 @@ struct StripeWebhookEvent {
     id: String,
     #[serde(rename = "type")]
     event_type: String,
+    /// Connected account the event happened on, for Connect events
+    #[serde(default)]
+    account: Option<String>,
     data: StripeEventData,
 }
 
@@ -565,6 +811,276 @@ struct SquareEventData {
 
 // Signature verification
 
+/// Post a charge the provider reports as paid to the ledger and report it
+/// for sales tax
+async fn post_charge(
+    state: &AppState,
+    pool: &PgPool,
+    transaction: &Transaction,
+) -> Result<(), (StatusCode, String)> {
+    ledger::post_charge(pool, transaction.organization_id, transaction)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    walker_earnings::record_charge(pool, transaction.organization_id, transaction)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.0.to_string()))?;
+
+    let tenant = TenantContext {
+        org_id: transaction.organization_id,
+        pool: pool.clone(),
+    };
+    tax::report_sale(state, &tenant, transaction).await;
+    Ok(())
+}
+
+/// Apply a Stripe refund object to its transaction
+async fn apply_stripe_refund(
+    state: &AppState,
+    pool: &PgPool,
+    org_id: Uuid,
+    payment_intent_id: &str,
+    refund: &serde_json::Value,
+) -> Result<(), (StatusCode, String)> {
+    let refund_id = refund.get("id").and_then(|v| v.as_str()).unwrap_or("");
+    let amount = refund.get("amount").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
+    let status = refund.get("status").and_then(|v| v.as_str()).unwrap_or("");
+
+    apply_refund(
+        state,
+        pool,
+        org_id,
+        payment_intent_id,
+        refund_id,
+        amount,
+        RefundStatus::from_provider(status),
+    )
+    .await
+}
+
+/// Record a provider refund against the transaction for `payment_id`
+async fn apply_refund(
+    state: &AppState,
+    pool: &PgPool,
+    org_id: Uuid,
+    payment_id: &str,
+    refund_id: &str,
+    amount_cents: i32,
+    status: RefundStatus,
+) -> Result<(), (StatusCode, String)> {
+    if refund_id.is_empty() || amount_cents <= 0 {
+        return Ok(());
+    }
+
+    let org_id = OrganizationId::from_uuid(org_id);
+    let Some(transaction) = TransactionRepository::get_by_external_id(pool, org_id, payment_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    else {
+        return Ok(());
+    };
+
+    let tenant = TenantContext {
+        org_id,
+        pool: pool.clone(),
+    };
+    record_provider_refund(
+        state,
+        &tenant,
+        &transaction,
+        refund_id,
+        amount_cents,
+        status,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.0.to_string()))?;
+
+    Ok(())
+}
+
+/// Apply a connected account's onboarding status to the organization's
+/// provider, or to the walker it pays out to
+async fn apply_account_update(
+    pool: &PgPool,
+    org_id: OrganizationId,
+    account: &Account,
+) -> Result<(), (StatusCode, String)> {
+    let provider = PaymentProviderRepository::update_stripe_status(
+        pool,
+        org_id,
+        &account.id,
+        account.charges_enabled,
+        account.payouts_enabled,
+        Some(account.verification_status()),
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if provider.is_none() {
+        WalkerEarningRepository::update_account_status(
+            pool,
+            org_id,
+            &account.id,
+            account.payouts_enabled,
+        )
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Apply a Stripe dispute object, opening the dispute if it's new
+async fn apply_stripe_dispute(
+    pool: &PgPool,
+    org_id: OrganizationId,
+    object: &serde_json::Value,
+) -> Result<(), (StatusCode, String)> {
+    let dispute_id = object.get("id").and_then(|v| v.as_str()).unwrap_or("");
+    let status = object.get("status").and_then(|v| v.as_str()).unwrap_or("");
+    let evidence_due_by = object
+        .get("evidence_details")
+        .and_then(|d| d.get("due_by"))
+        .and_then(|v| v.as_i64())
+        .and_then(|ts| DateTime::from_timestamp(ts, 0));
+
+    let existing = DisputeRepository::get_by_stripe_dispute(pool, dispute_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let dispute = match existing {
+        Some(dispute) => dispute,
+        None => {
+            let payment_intent_id = object
+                .get("payment_intent")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            let input = |transaction: &Transaction| CreateDispute {
+                transaction_id: transaction.id,
+                amount_cents: object.get("amount").and_then(|v| v.as_i64()).unwrap_or(0) as i32,
+                currency: transaction.currency,
+                stripe_dispute_id: Some(dispute_id.to_string()),
+                square_dispute_id: None,
+                reason: object
+                    .get("reason")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("general")
+                    .to_string(),
+                evidence_due_by,
+            };
+            match open_dispute(pool, org_id, payment_intent_id, input).await? {
+                Some(dispute) => dispute,
+                None => return Ok(()),
+            }
+        }
+    };
+
+    disputes::apply_status(
+        pool,
+        &dispute,
+        DisputeStatus::from_provider(status),
+        Some(status),
+        evidence_due_by,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(())
+}
+
+/// Apply a Square dispute object, opening the dispute if it's new
+async fn apply_square_dispute(
+    pool: &PgPool,
+    org_id: OrganizationId,
+    object: &serde_json::Value,
+) -> Result<(), (StatusCode, String)> {
+    let dispute_id = object
+        .get("id")
+        .or_else(|| object.get("dispute_id"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    let state = object.get("state").and_then(|v| v.as_str()).unwrap_or("");
+    let evidence_due_by = object
+        .get("due_at")
+        .and_then(|v| v.as_str())
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    let existing = DisputeRepository::get_by_square_dispute(pool, dispute_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let dispute = match existing {
+        Some(dispute) => dispute,
+        None => {
+            let payment_id = object
+                .get("disputed_payment")
+                .and_then(|dp| dp.get("payment_id"))
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            let input = |transaction: &Transaction| CreateDispute {
+                transaction_id: transaction.id,
+                amount_cents: object
+                    .get("amount_money")
+                    .and_then(|m| m.get("amount"))
+                    .and_then(|v| v.as_i64())
+                    .unwrap_or(0) as i32,
+                currency: transaction.currency,
+                stripe_dispute_id: None,
+                square_dispute_id: Some(dispute_id.to_string()),
+                reason: object
+                    .get("reason")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("general")
+                    .to_string(),
+                evidence_due_by,
+            };
+            match open_dispute(pool, org_id, payment_id, input).await? {
+                Some(dispute) => dispute,
+                None => return Ok(()),
+            }
+        }
+    };
+
+    disputes::apply_status(
+        pool,
+        &dispute,
+        DisputeStatus::from_provider(state),
+        Some(&state.to_lowercase()),
+        evidence_due_by,
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(())
+}
+
+/// Open a dispute against the transaction for `payment_id`, posting the
+/// disputed funds to the ledger. Returns `None` if the payment isn't ours.
+async fn open_dispute(
+    pool: &PgPool,
+    org_id: OrganizationId,
+    payment_id: &str,
+    input: impl FnOnce(&Transaction) -> CreateDispute,
+) -> Result<Option<Dispute>, (StatusCode, String)> {
+    let Some(transaction) = TransactionRepository::get_by_external_id(pool, org_id, payment_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    else {
+        return Ok(None);
+    };
+
+    let dispute = DisputeRepository::create(pool, org_id, input(&transaction))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    TransactionRepository::update_status(pool, transaction.id, TransactionStatus::Disputed)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // A retry finds the dispute already open and posts it in `apply_status`
+    ledger::post_dispute(pool, dispute.organization_id, &dispute)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    walker_earnings::record_dispute(pool, dispute.organization_id, &dispute)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Some(dispute))
+}
+
 fn verify_stripe_signature(payload: &[u8], signature: &str, secret: &str) -> Result<(), String> {
     // Parse the signature header
     let mut timestamp: Option<&str> = None;
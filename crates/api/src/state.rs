@@ -1,9 +1,12 @@
 use db::TenantPoolManager;
+use integrations::tax::{OfflineTaxTable, TaxJarClient, TaxProvider};
 use integrations::GoogleMapsClient;
 use metrics_exporter_prometheus::PrometheusHandle;
 use sqlx::PgPool;
 use std::sync::Arc;
 
+use crate::payments::PaymentGateways;
+
 /// Configuration for external services
 #[derive(Clone, Default)]
 pub struct AppConfig {
@@ -11,6 +14,18 @@ pub struct AppConfig {
     pub github_token: Option<String>,
     /// GitHub repository for feedback issues (format: "owner/repo")
     pub github_feedback_repo: Option<String>,
+    /// Stripe platform secret key
+    pub stripe_secret_key: Option<String>,
+    /// Signing secret for the platform's Stripe Connect webhook
+    pub stripe_connect_webhook_secret: Option<String>,
+    /// Signing secret for the platform's Stripe Billing webhook
+    pub stripe_billing_webhook_secret: Option<String>,
+    /// Use the Square sandbox API
+    pub square_sandbox: bool,
+    /// TaxJar API key; without one tax comes from the built-in state table
+    pub taxjar_api_key: Option<String>,
+    /// Use the TaxJar sandbox API
+    pub taxjar_sandbox: bool,
 }
 
 /// Application state shared across all handlers
@@ -21,6 +36,8 @@ pub struct AppState {
     pub google_maps: Option<Arc<GoogleMapsClient>>,
     pub tenant_pool_manager: Arc<TenantPoolManager>,
     pub metrics_handle: PrometheusHandle,
+    pub payment_gateways: PaymentGateways,
+    pub tax_provider: Arc<dyn TaxProvider>,
     pub config: AppConfig,
 }
 
@@ -49,6 +66,12 @@ impl AppState {
     ) -> Self {
         let google_maps = google_maps_key.map(|key| Arc::new(GoogleMapsClient::new(key)));
         let tenant_pool_manager = Arc::new(TenantPoolManager::new(pool.clone()));
+        let payment_gateways =
+            PaymentGateways::new(config.stripe_secret_key.clone(), config.square_sandbox);
+        let tax_provider: Arc<dyn TaxProvider> = match &config.taxjar_api_key {
+            Some(key) => Arc::new(TaxJarClient::new(key.clone(), config.taxjar_sandbox)),
+            None => Arc::new(OfflineTaxTable),
+        };
 
         Self {
             pool,
@@ -56,6 +79,8 @@ impl AppState {
             google_maps,
             tenant_pool_manager,
             metrics_handle,
+            payment_gateways,
+            tax_provider,
             config,
         }
     }
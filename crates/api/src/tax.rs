@@ -0,0 +1,226 @@
+//! Sales tax through the configured [`TaxProvider`].
+//!
+//! Tax is owed where the service is performed, so quotes use the booking's
+//! location. If the provider can't be reached the statewide table is used
+//! instead; it only covers the US, so elsewhere checkout fails rather than
+//! charge a guessed rate. Sales and refunds are reported once the
+//! money has moved; failures are logged and retried by the tax reporting job.
+
+use db::models::{Location, Refund, RefundStatus, Transaction};
+use db::{BookingRepository, LocationRepository, RefundRepository, TransactionRepository};
+use integrations::tax::{Address, OfflineTaxTable, TaxCategory, TaxQuote, TaxReport, TaxRequest};
+use shared::types::Money;
+use shared::AppError;
+
+use crate::{
+    auth::TenantContext,
+    error::{ApiError, ApiResult},
+    state::AppState,
+};
+
+/// Sales and refunds reported per organization each time the job runs
+const REPORT_BATCH_SIZE: i64 = 100;
+
+/// Tax on `amount` for a walk at `location`
+pub async fn quote(state: &AppState, location: &Location, amount: Money) -> ApiResult<TaxQuote> {
+    let address = location_address(location);
+    let request = TaxRequest {
+        // Walkers travel to the customer, so the sale is made there too
+        from: address.clone(),
+        to: address,
+        amount_cents: amount.cents(),
+        category: TaxCategory::PetServices,
+    };
+
+    match state.tax_provider.calculate(&request).await {
+        Ok(quote) => Ok(quote),
+        Err(e) => {
+            tracing::warn!(
+                "{} tax calculation failed for location {}, using state rate: {}",
+                state.tax_provider.provider_name(),
+                location.id,
+                e
+            );
+            OfflineTaxTable::quote(&location.country, &location.state)
+                .map_err(|e| ApiError::from(AppError::Validation(e.to_string())))
+        }
+    }
+}
+
+/// Report a sale to the tax provider once its transaction has been captured
+pub async fn report_sale(state: &AppState, tenant: &TenantContext, transaction: &Transaction) {
+    if !state.tax_provider.reports_transactions()
+        || transaction.tax_reported_at.is_some()
+        || transaction.tax_jurisdiction.is_none()
+        || (transaction.captured_at.is_none() && !transaction.is_successful())
+    {
+        return;
+    }
+
+    if let Err(e) = try_report_sale(state, tenant, transaction).await {
+        tracing::warn!(
+            "Failed to report transaction {} to {}: {}",
+            transaction.id,
+            state.tax_provider.provider_name(),
+            e.0
+        );
+    }
+}
+
+/// Report a refund to the tax provider once the provider has paid it.
+///
+/// Refunds of sales not yet reported are left for the tax reporting job,
+/// which reports the sale first.
+pub async fn report_refund(
+    state: &AppState,
+    tenant: &TenantContext,
+    transaction: &Transaction,
+    refund: &Refund,
+) {
+    if !state.tax_provider.reports_transactions()
+        || refund.status != RefundStatus::Succeeded
+        || refund.tax_reported_at.is_some()
+        || transaction.tax_reported_at.is_none()
+    {
+        return;
+    }
+
+    if let Err(e) = try_report_refund(state, tenant, transaction, refund).await {
+        tracing::warn!(
+            "Failed to report refund {} to {}: {}",
+            refund.id,
+            state.tax_provider.provider_name(),
+            e.0
+        );
+    }
+}
+
+/// Report sales and refunds that haven't reached the tax provider yet.
+/// Returns how many were reported.
+pub async fn report_unreported(state: &AppState, tenant: &TenantContext) -> ApiResult<usize> {
+    if !state.tax_provider.reports_transactions() {
+        return Ok(0);
+    }
+
+    let mut reported = 0;
+    let transactions =
+        TransactionRepository::list_tax_unreported(&tenant.pool, tenant.org_id, REPORT_BATCH_SIZE)
+            .await?;
+    for transaction in &transactions {
+        match try_report_sale(state, tenant, transaction).await {
+            Ok(()) => reported += 1,
+            Err(e) => tracing::warn!(
+                "Failed to report transaction {} to {}: {}",
+                transaction.id,
+                state.tax_provider.provider_name(),
+                e.0
+            ),
+        }
+    }
+
+    let refunds =
+        RefundRepository::list_tax_unreported(&tenant.pool, tenant.org_id, REPORT_BATCH_SIZE)
+            .await?;
+    for refund in &refunds {
+        let Some(transaction) =
+            TransactionRepository::get_by_id(&tenant.pool, tenant.org_id, refund.transaction_id)
+                .await?
+        else {
+            continue;
+        };
+        match try_report_refund(state, tenant, &transaction, refund).await {
+            Ok(()) => reported += 1,
+            Err(e) => tracing::warn!(
+                "Failed to report refund {} to {}: {}",
+                refund.id,
+                state.tax_provider.provider_name(),
+                e.0
+            ),
+        }
+    }
+
+    Ok(reported)
+}
+
+async fn try_report_sale(
+    state: &AppState,
+    tenant: &TenantContext,
+    transaction: &Transaction,
+) -> ApiResult<()> {
+    let address = sale_address(tenant, transaction).await?;
+    let report = TaxReport {
+        id: transaction.id.to_string(),
+        sale_id: None,
+        date: transaction.captured_at.unwrap_or(transaction.created_at),
+        from: address.clone(),
+        to: address,
+        // Tips aren't taxed
+        amount_cents: i64::from(transaction.subtotal_cents + transaction.customer_fee_cents),
+        tax_cents: i64::from(transaction.tax_cents),
+    };
+
+    state
+        .tax_provider
+        .report_sale(&report)
+        .await
+        .map_err(|e| ApiError::from(AppError::ExternalApi(e.to_string())))?;
+    TransactionRepository::mark_tax_reported(&tenant.pool, transaction.id).await?;
+    Ok(())
+}
+
+async fn try_report_refund(
+    state: &AppState,
+    tenant: &TenantContext,
+    transaction: &Transaction,
+    refund: &Refund,
+) -> ApiResult<()> {
+    let address = sale_address(tenant, transaction).await?;
+    let report = TaxReport {
+        id: refund.id.to_string(),
+        sale_id: Some(transaction.id.to_string()),
+        date: refund.updated_at,
+        from: address.clone(),
+        to: address,
+        amount_cents: i64::from(
+            refund.subtotal_refunded_cents + refund.customer_fee_refunded_cents,
+        ),
+        tax_cents: i64::from(refund.tax_refunded_cents),
+    };
+
+    state
+        .tax_provider
+        .report_refund(&report)
+        .await
+        .map_err(|e| ApiError::from(AppError::ExternalApi(e.to_string())))?;
+    RefundRepository::mark_tax_reported(&tenant.pool, refund.id).await?;
+    Ok(())
+}
+
+/// Address of the booking a transaction paid for
+async fn sale_address(tenant: &TenantContext, transaction: &Transaction) -> ApiResult<Address> {
+    let not_found = || {
+        ApiError::from(AppError::NotFound(
+            "Location for taxed sale not found".to_string(),
+        ))
+    };
+
+    let booking_id = transaction.related_booking_id().ok_or_else(not_found)?;
+    let booking = BookingRepository::find_by_id(&tenant.pool, tenant.org_id, booking_id)
+        .await?
+        .ok_or_else(not_found)?;
+    let location = LocationRepository::find_by_id(&tenant.pool, tenant.org_id, booking.location_id)
+        .await?
+        .ok_or_else(not_found)?;
+
+    Ok(location_address(&location))
+}
+
+fn location_address(location: &Location) -> Address {
+    Address::full(
+        &location.address,
+        &location.city,
+        &location.state,
+        &location.zip_code,
+        &location.country,
+    )
+}
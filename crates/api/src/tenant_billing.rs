@@ -0,0 +1,518 @@
+//! The organization's subscription to the platform.
+//!
+//! Paid plans are Stripe Billing subscriptions on the platform account. A
+//! first paid plan starts with a trial; changing plan later is prorated, and
+//! cancelling takes effect at the end of the paid period. Our record follows
+//! the subscription as Stripe reports it through webhooks. A subscription
+//! whose payment fails is past due and keeps its plan for a grace period,
+//! after which the organization is restricted to the free plan's features
+//! until it pays.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use db::models::{
+    CreateTenantSubscription, PlanTier, SubscriptionStatus, SyncTenantSubscription,
+    TenantSubscription, UpdateTenantSubscription, TENANT_TRIAL_DAYS,
+};
+use db::{OrganizationRepository, SubscriptionRepository};
+use integrations::stripe::{
+    CreatePriceParams, CreateSubscriptionParams, Invoice, Price, StripeClient, Subscription,
+};
+use shared::types::OrganizationId;
+use shared::AppError;
+use sqlx::PgPool;
+
+use crate::{
+    auth::TenantContext,
+    error::{ApiError, ApiResult},
+    state::AppState,
+};
+
+/// How often a paid plan is billed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BillingPeriod {
+    Monthly,
+    Annual,
+}
+
+impl BillingPeriod {
+    fn interval(&self) -> &'static str {
+        match self {
+            BillingPeriod::Monthly => "month",
+            BillingPeriod::Annual => "year",
+        }
+    }
+}
+
+impl std::fmt::Display for BillingPeriod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BillingPeriod::Monthly => write!(f, "monthly"),
+            BillingPeriod::Annual => write!(f, "annual"),
+        }
+    }
+}
+
+/// Put the organization on a plan: start a subscription for its first paid
+/// plan, or move its subscription to another plan, prorating the change
+pub async fn subscribe(
+    state: &AppState,
+    tenant: &TenantContext,
+    plan_tier: PlanTier,
+    period: BillingPeriod,
+    billing_email: Option<&str>,
+    payment_method_id: Option<&str>,
+) -> ApiResult<TenantSubscription> {
+    let existing =
+        SubscriptionRepository::get_tenant_subscription(&tenant.pool, tenant.org_id).await?;
+    let live = existing.as_ref().filter(|s| {
+        s.stripe_subscription_id.is_some()
+            && !matches!(
+                s.status,
+                SubscriptionStatus::Canceled | SubscriptionStatus::Incomplete
+            )
+    });
+
+    if plan_tier == PlanTier::Free {
+        if live.is_some() {
+            return Err(ApiError::from(AppError::Validation(
+                "Cancel the subscription to move to the free plan".to_string(),
+            )));
+        }
+        return subscribe_free(state, tenant, existing.is_some()).await;
+    }
+
+    let fee_tier = SubscriptionRepository::get_fee_tier(&tenant.pool, plan_tier)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::Internal("Fee tier not found".to_string())))?;
+    let price_cents = match period {
+        BillingPeriod::Monthly => fee_tier.monthly_price_cents,
+        BillingPeriod::Annual => fee_tier.annual_price_cents,
+    };
+    if price_cents <= 0 {
+        return Err(ApiError::from(AppError::Validation(format!(
+            "The {} plan can't be subscribed to online",
+            fee_tier.display_name
+        ))));
+    }
+
+    let client = stripe_client(state)?;
+    let price = plan_price(
+        &client,
+        plan_tier,
+        period,
+        price_cents,
+        &fee_tier.display_name,
+    )
+    .await?;
+    let metadata = HashMap::from([
+        ("organization_id".to_string(), tenant.org_id.to_string()),
+        ("plan_tier".to_string(), plan_tier.to_string()),
+        ("billing_period".to_string(), period.to_string()),
+    ]);
+
+    let subscription = match live.and_then(|s| s.stripe_subscription_id.as_deref()) {
+        Some(subscription_id) => {
+            let current = client
+                .get_subscription(subscription_id)
+                .await
+                .map_err(external)?;
+            let item = current.items.data.first().ok_or_else(|| {
+                ApiError::from(AppError::Internal("Subscription has no items".to_string()))
+            })?;
+            client
+                .change_subscription_price(subscription_id, &item.id, &price.id, metadata)
+                .await
+                .map_err(external)?
+        }
+        None => {
+            let customer_id =
+                billing_customer(state, tenant, &client, existing.as_ref(), billing_email).await?;
+            if let Some(payment_method_id) = payment_method_id {
+                client
+                    .attach_payment_method(payment_method_id, &customer_id)
+                    .await
+                    .map_err(external)?;
+                client
+                    .update_customer(&customer_id, None, None, Some(payment_method_id))
+                    .await
+                    .map_err(external)?;
+            }
+
+            // One trial per organization
+            let trialed = existing.as_ref().is_some_and(|s| s.trial_start.is_some());
+            let idempotency_key = format!(
+                "tenant_subscription_{}_{}_{}",
+                tenant.org_id,
+                price.id,
+                existing
+                    .as_ref()
+                    .and_then(|s| s.stripe_subscription_id.as_deref())
+                    .unwrap_or("new")
+            );
+            client
+                .create_subscription(
+                    &CreateSubscriptionParams {
+                        customer: customer_id,
+                        price: price.id.clone(),
+                        trial_period_days: (!trialed).then_some(TENANT_TRIAL_DAYS),
+                        default_payment_method: payment_method_id.map(str::to_string),
+                        metadata,
+                    },
+                    Some(&idempotency_key),
+                )
+                .await
+                .map_err(external)?
+        }
+    };
+
+    apply_subscription(state, &tenant.pool, tenant.org_id, &subscription)
+        .await?
+        .ok_or_else(|| {
+            ApiError::from(AppError::Internal(
+                "Subscription was replaced while it was being changed".to_string(),
+            ))
+        })
+}
+
+/// Cancel the organization's subscription at the end of the paid period
+pub async fn cancel(state: &AppState, tenant: &TenantContext) -> ApiResult<TenantSubscription> {
+    let subscription_id =
+        SubscriptionRepository::get_tenant_subscription(&tenant.pool, tenant.org_id)
+            .await?
+            .filter(|s| s.status != SubscriptionStatus::Canceled)
+            .and_then(|s| s.stripe_subscription_id)
+            .ok_or_else(|| {
+                ApiError::from(AppError::NotFound(
+                    "No active subscription found".to_string(),
+                ))
+            })?;
+
+    let subscription = stripe_client(state)?
+        .set_cancel_at_period_end(&subscription_id, true)
+        .await
+        .map_err(external)?;
+
+    apply_subscription(state, &tenant.pool, tenant.org_id, &subscription)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::NotFound("Subscription".to_string())))
+}
+
+/// Apply a `customer.subscription.*` event. Returns false if the
+/// subscription isn't the organization's platform subscription.
+pub async fn apply_subscription_event(
+    state: &AppState,
+    pool: &PgPool,
+    org_id: OrganizationId,
+    event_type: &str,
+    object: &serde_json::Value,
+) -> ApiResult<bool> {
+    let subscription: Subscription = serde_json::from_value(object.clone()).map_err(|e| {
+        ApiError::from(AppError::Validation(format!("Invalid subscription: {}", e)))
+    })?;
+    if !is_billing_customer(state, org_id, &subscription.customer).await? {
+        return Ok(false);
+    }
+
+    if event_type == "customer.subscription.trial_will_end" {
+        tracing::info!(
+            "Platform trial for organization {} ends at {}",
+            org_id,
+            subscription
+                .trial_end
+                .and_then(timestamp)
+                .map(|dt| dt.to_rfc3339())
+                .unwrap_or_default()
+        );
+    }
+    refresh(state, pool, org_id, &subscription.id).await?;
+    Ok(true)
+}
+
+/// Apply an `invoice.*` event. Returns false if the invoice isn't for the
+/// organization's platform subscription.
+pub async fn apply_invoice_event(
+    state: &AppState,
+    pool: &PgPool,
+    org_id: OrganizationId,
+    event_type: &str,
+    object: &serde_json::Value,
+) -> ApiResult<bool> {
+    let invoice: Invoice = serde_json::from_value(object.clone())
+        .map_err(|e| ApiError::from(AppError::Validation(format!("Invalid invoice: {}", e))))?;
+    let Some(customer) = invoice.customer.as_deref() else {
+        return Ok(false);
+    };
+    if !is_billing_customer(state, org_id, customer).await? {
+        return Ok(false);
+    }
+    let Some(subscription_id) = invoice.subscription_id() else {
+        return Ok(true);
+    };
+
+    if event_type == "invoice.payment_failed" {
+        tracing::warn!(
+            "Platform invoice {} for organization {} failed payment (attempt {}), next attempt {}",
+            invoice.id,
+            org_id,
+            invoice.attempt_count,
+            invoice
+                .next_payment_attempt
+                .and_then(timestamp)
+                .map(|dt| dt.to_rfc3339())
+                .unwrap_or_else(|| "none".to_string())
+        );
+    }
+    refresh(state, pool, org_id, subscription_id).await?;
+    Ok(true)
+}
+
+/// Restrict the organization to the free plan's features if its
+/// subscription has been past due longer than the grace period. Returns
+/// whether it was restricted.
+pub async fn restrict_if_overdue(
+    state: &AppState,
+    tenant: &TenantContext,
+    now: DateTime<Utc>,
+) -> ApiResult<bool> {
+    let Some(subscription) =
+        SubscriptionRepository::get_tenant_subscription(&tenant.pool, tenant.org_id).await?
+    else {
+        return Ok(false);
+    };
+    if !subscription.restriction_due(now) {
+        return Ok(false);
+    }
+    let Some(restricted) =
+        SubscriptionRepository::restrict_tenant_subscription(&tenant.pool, tenant.org_id).await?
+    else {
+        return Ok(false);
+    };
+
+    set_org_plan_tier(state, &tenant.pool, tenant.org_id, &restricted).await?;
+    tracing::warn!(
+        "Organization {} restricted to the free plan; its {} subscription has been past due since {}",
+        tenant.org_id,
+        restricted.plan_tier,
+        restricted
+            .past_due_since
+            .map(|dt| dt.to_rfc3339())
+            .unwrap_or_default()
+    );
+    Ok(true)
+}
+
+/// Fetch a subscription from Stripe and record it, so events applied out of
+/// order still leave the latest state
+async fn refresh(
+    state: &AppState,
+    pool: &PgPool,
+    org_id: OrganizationId,
+    subscription_id: &str,
+) -> ApiResult<()> {
+    let subscription = stripe_client(state)?
+        .get_subscription(subscription_id)
+        .await
+        .map_err(external)?;
+    if apply_subscription(state, pool, org_id, &subscription)
+        .await?
+        .is_none()
+    {
+        tracing::debug!(
+            "Ignoring replaced subscription {} for organization {}",
+            subscription_id,
+            org_id
+        );
+    }
+    Ok(())
+}
+
+/// Record a subscription as Stripe reports it and update the plan the
+/// organization has. Returns `None` if the organization has since replaced
+/// it.
+async fn apply_subscription(
+    state: &AppState,
+    pool: &PgPool,
+    org_id: OrganizationId,
+    subscription: &Subscription,
+) -> ApiResult<Option<TenantSubscription>> {
+    let plan_tier = match subscription
+        .metadata_value("plan_tier")
+        .and_then(|tier| tier.parse().ok())
+    {
+        Some(tier) => tier,
+        None => SubscriptionRepository::get_tenant_subscription(pool, org_id)
+            .await?
+            .map(|s| s.plan_tier)
+            .unwrap_or_default(),
+    };
+    let (period_start, period_end) = subscription.current_period();
+
+    let input = SyncTenantSubscription {
+        plan_tier,
+        stripe_subscription_id: subscription.id.clone(),
+        stripe_customer_id: subscription.customer.clone(),
+        status: SubscriptionStatus::from_provider(&subscription.status),
+        current_period_start: period_start.and_then(timestamp),
+        current_period_end: period_end.and_then(timestamp),
+        trial_start: subscription.trial_start.and_then(timestamp),
+        trial_end: subscription.trial_end.and_then(timestamp),
+        cancel_at_period_end: subscription.cancel_at_period_end,
+        canceled_at: subscription.canceled_at.and_then(timestamp),
+    };
+    let Some(synced) =
+        SubscriptionRepository::sync_tenant_subscription(pool, org_id, &input).await?
+    else {
+        return Ok(None);
+    };
+
+    set_org_plan_tier(state, pool, org_id, &synced).await?;
+    Ok(Some(synced))
+}
+
+/// Put the organization on the free plan without a Stripe subscription
+async fn subscribe_free(
+    state: &AppState,
+    tenant: &TenantContext,
+    exists: bool,
+) -> ApiResult<TenantSubscription> {
+    let subscription = if exists {
+        SubscriptionRepository::update_tenant_subscription(
+            &tenant.pool,
+            tenant.org_id,
+            UpdateTenantSubscription {
+                plan_tier: Some(PlanTier::Free),
+                status: Some(SubscriptionStatus::Active),
+                ..Default::default()
+            },
+        )
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::NotFound("Subscription".to_string())))?
+    } else {
+        SubscriptionRepository::create_tenant_subscription(
+            &tenant.pool,
+            tenant.org_id,
+            CreateTenantSubscription {
+                plan_tier: PlanTier::Free,
+                stripe_subscription_id: None,
+                stripe_customer_id: None,
+                current_period_start: None,
+                current_period_end: None,
+                trial_end: None,
+            },
+        )
+        .await?
+    };
+
+    set_org_plan_tier(state, &tenant.pool, tenant.org_id, &subscription).await?;
+    Ok(subscription)
+}
+
+/// The organization's billing customer, creating it the first time
+async fn billing_customer(
+    state: &AppState,
+    tenant: &TenantContext,
+    client: &StripeClient,
+    existing: Option<&TenantSubscription>,
+    billing_email: Option<&str>,
+) -> ApiResult<String> {
+    if let Some(customer_id) = existing.and_then(|s| s.stripe_customer_id.clone()) {
+        return Ok(customer_id);
+    }
+
+    let organization = OrganizationRepository::find_by_id(&state.pool, tenant.org_id)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::NotFound("Organization".to_string())))?;
+    let customer = client
+        .create_customer(
+            billing_email,
+            Some(&organization.name),
+            Some(HashMap::from([(
+                "organization_id".to_string(),
+                tenant.org_id.to_string(),
+            )])),
+        )
+        .await
+        .map_err(external)?;
+
+    SubscriptionRepository::register_billing_customer(&state.pool, tenant.org_id, &customer.id)
+        .await?;
+    Ok(customer.id)
+}
+
+/// The Stripe price for a plan, creating it the first time it's used. The
+/// lookup key includes the amount, so a changed plan price gets a new Stripe
+/// price and existing subscribers keep theirs until they change plan.
+async fn plan_price(
+    client: &StripeClient,
+    plan_tier: PlanTier,
+    period: BillingPeriod,
+    price_cents: i32,
+    display_name: &str,
+) -> ApiResult<Price> {
+    let lookup_key = format!("platform_{}_{}_{}", plan_tier, period, price_cents);
+    if let Some(price) = client
+        .find_price_by_lookup_key(&lookup_key)
+        .await
+        .map_err(external)?
+    {
+        return Ok(price);
+    }
+
+    client
+        .create_price(&CreatePriceParams {
+            unit_amount: price_cents.into(),
+            currency: "usd".to_string(),
+            interval: period.interval().to_string(),
+            interval_count: 1,
+            product_name: format!("{} plan", display_name),
+            lookup_key: Some(lookup_key),
+            metadata: HashMap::from([("plan_tier".to_string(), plan_tier.to_string())]),
+        })
+        .await
+        .map_err(external)
+}
+
+/// Whether a Stripe customer is the organization's platform billing
+/// customer
+async fn is_billing_customer(
+    state: &AppState,
+    org_id: OrganizationId,
+    customer_id: &str,
+) -> ApiResult<bool> {
+    Ok(
+        SubscriptionRepository::find_org_by_billing_customer(&state.pool, customer_id).await?
+            == Some(org_id),
+    )
+}
+
+/// Keep the organization's plan tier in step with its subscription, in its
+/// own database and the main one
+async fn set_org_plan_tier(
+    state: &AppState,
+    pool: &PgPool,
+    org_id: OrganizationId,
+    subscription: &TenantSubscription,
+) -> ApiResult<()> {
+    let tier = subscription.effective_plan_tier();
+    SubscriptionRepository::update_org_plan_tier(pool, org_id, tier).await?;
+    SubscriptionRepository::update_org_plan_tier(&state.pool, org_id, tier).await?;
+    Ok(())
+}
+
+fn stripe_client(state: &AppState) -> ApiResult<StripeClient> {
+    let secret_key =
+        state.config.stripe_secret_key.clone().ok_or_else(|| {
+            ApiError::from(AppError::Internal("Stripe not configured".to_string()))
+        })?;
+    Ok(StripeClient::new(secret_key, None))
+}
+
+fn external(e: integrations::stripe::StripeError) -> ApiError {
+    ApiError::from(AppError::ExternalApi(e.to_string()))
+}
+
+fn timestamp(secs: i64) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(secs, 0)
+}
@@ -0,0 +1,367 @@
+//! Each walker's share of the payments for their walks.
+//!
+//! Earnings are recorded once a payment is captured, using the walker's
+//! revenue split for the service, and taken back in proportion when the
+//! payment is refunded or a dispute is lost. Package walks are earned by the
+//! walker who does them as each credit is redeemed, and taken back if the
+//! credit is returned. Tips always go to the walker in full. Like ledger
+//! posts, each is keyed by the record it describes, and failures are returned
+//! so the payment flow that triggered them is retried.
+//!
+//! Walker payouts collect a walker's unpaid earnings. Walkers with a
+//! connected Stripe account are paid by a transfer from the platform balance,
+//! which is posted to the ledger and deducted from the organization's next
+//! payout; the rest are paid outside the platform and marked paid by a
+//! manager. A reversed transfer fails the payout, posts the funds back and
+//! returns its earnings to the walker's unpaid balance.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use db::models::{
+    Booking, Dispute, DisputeStatus, NewWalkerEarning, PackageCredit, PayoutStatus, Refund,
+    RefundAllocation, RefundStatus, Transaction, WalkerEarningKind, WalkerPayout,
+};
+use db::{
+    BookingRepository, PaymentProviderRepository, TransactionRepository, WalkerEarningRepository,
+};
+use integrations::gateway::{GatewayError, TransferRequest};
+use shared::types::{OrganizationId, UserId};
+use shared::AppError;
+use sqlx::PgPool;
+
+use crate::{
+    auth::TenantContext,
+    error::{ApiError, ApiResult},
+    ledger,
+    state::AppState,
+};
+
+/// Record what the walker earned from a captured payment
+pub async fn record_charge(
+    pool: &PgPool,
+    org_id: OrganizationId,
+    transaction: &Transaction,
+) -> ApiResult<()> {
+    if transaction.captured_at.is_none() && !transaction.is_successful() {
+        return Ok(());
+    }
+    // Package walks are credited to walkers as they're redeemed
+    let Some(walker_id) = transaction.provider_user_id else {
+        return Ok(());
+    };
+
+    let service_id = match transaction.related_booking_id() {
+        Some(booking_id) => BookingRepository::find_by_id(pool, org_id, booking_id)
+            .await?
+            .map(|b| b.service_id),
+        None => None,
+    };
+    let split = WalkerEarningRepository::get_split(pool, org_id, walker_id, service_id).await?;
+
+    for earning in NewWalkerEarning::for_charge(transaction, split.as_ref())? {
+        WalkerEarningRepository::record(pool, org_id, &earning).await?;
+    }
+    Ok(())
+}
+
+/// Record what the booking's walker earns for a package walk paid for with
+/// `credit`, out of one of the period's `walks`
+pub async fn record_redemption(
+    pool: &PgPool,
+    org_id: OrganizationId,
+    booking: &Booking,
+    credit: &PackageCredit,
+    walks: i32,
+) -> ApiResult<()> {
+    let Some(transaction_id) = credit.transaction_id else {
+        return Ok(());
+    };
+    let Some(transaction) = TransactionRepository::get_by_id(pool, org_id, transaction_id).await?
+    else {
+        return Ok(());
+    };
+    let split = WalkerEarningRepository::get_split(
+        pool,
+        org_id,
+        booking.walker_id,
+        Some(booking.service_id),
+    )
+    .await?;
+
+    if let Some(earning) = NewWalkerEarning::for_redemption(
+        &transaction,
+        walks,
+        booking.walker_id,
+        credit.id,
+        split.as_ref(),
+    )? {
+        WalkerEarningRepository::record(pool, org_id, &earning).await?;
+    }
+    Ok(())
+}
+
+/// Take back what a package walk earned when its credit is returned
+pub async fn record_credit_return(
+    pool: &PgPool,
+    org_id: OrganizationId,
+    redemption: &PackageCredit,
+    returned: &PackageCredit,
+) -> Result<(), sqlx::Error> {
+    let Some(earning) = WalkerEarningRepository::get_by_source(
+        pool,
+        org_id,
+        redemption.id,
+        WalkerEarningKind::Walk,
+    )
+    .await?
+    else {
+        return Ok(());
+    };
+    WalkerEarningRepository::record(
+        pool,
+        org_id,
+        &NewWalkerEarning::for_return(&earning, returned.id),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Take back the walker's share of a refund once the provider has paid it
+pub async fn record_refund(
+    pool: &PgPool,
+    org_id: OrganizationId,
+    transaction: &Transaction,
+    refund: &Refund,
+) -> Result<(), sqlx::Error> {
+    if refund.status != RefundStatus::Succeeded {
+        return Ok(());
+    }
+
+    let allocation = RefundAllocation {
+        subtotal_cents: refund.subtotal_refunded_cents,
+        tip_cents: refund.tip_refunded_cents,
+        customer_fee_cents: refund.customer_fee_refunded_cents,
+        tax_cents: refund.tax_refunded_cents,
+        platform_fee_cents: refund.platform_fee_reversed_cents,
+        provider_payout_cents: refund.provider_payout_reversed_cents,
+    };
+    record_reversal(
+        pool,
+        org_id,
+        transaction,
+        WalkerEarningKind::Refund,
+        refund.id,
+        &allocation,
+    )
+    .await
+}
+
+/// Take back the walker's share of a lost dispute
+pub async fn record_dispute(
+    pool: &PgPool,
+    org_id: OrganizationId,
+    dispute: &Dispute,
+) -> Result<(), sqlx::Error> {
+    if dispute.status != DisputeStatus::Lost {
+        return Ok(());
+    }
+
+    let Some(transaction) =
+        TransactionRepository::get_by_id(pool, org_id, dispute.transaction_id).await?
+    else {
+        return Ok(());
+    };
+    let allocation = RefundAllocation::for_refund(&transaction, dispute.amount_cents);
+    record_reversal(
+        pool,
+        org_id,
+        &transaction,
+        WalkerEarningKind::Dispute,
+        dispute.id,
+        &allocation,
+    )
+    .await
+}
+
+async fn record_reversal(
+    pool: &PgPool,
+    org_id: OrganizationId,
+    transaction: &Transaction,
+    kind: WalkerEarningKind,
+    source_id: uuid::Uuid,
+    allocation: &RefundAllocation,
+) -> Result<(), sqlx::Error> {
+    let walk_earned =
+        WalkerEarningRepository::walk_earned_cents(pool, org_id, transaction.id).await?;
+    let walk_earned = i32::try_from(walk_earned).unwrap_or(i32::MAX);
+
+    if let Some(earning) =
+        NewWalkerEarning::for_reversal(transaction, kind, source_id, walk_earned, allocation)
+    {
+        WalkerEarningRepository::record(pool, org_id, &earning).await?;
+    }
+    Ok(())
+}
+
+/// Pay every walker their earnings recorded before `before`, one payout per
+/// walker and currency, and resend payouts that couldn't be transferred
+/// before. Returns the payouts created.
+pub async fn pay_walkers(
+    state: &AppState,
+    tenant: &TenantContext,
+    before: DateTime<Utc>,
+) -> ApiResult<Vec<WalkerPayout>> {
+    let unsent =
+        WalkerEarningRepository::list_uninitiated_payouts(&tenant.pool, tenant.org_id).await?;
+    for payout in &unsent {
+        send_or_log(state, tenant, payout).await;
+    }
+
+    create_payouts(state, tenant, None, before).await
+}
+
+/// Pay one walker their earnings recorded before `before`
+pub async fn pay_walker(
+    state: &AppState,
+    tenant: &TenantContext,
+    walker_id: UserId,
+    before: DateTime<Utc>,
+) -> ApiResult<Vec<WalkerPayout>> {
+    create_payouts(state, tenant, Some(walker_id), before).await
+}
+
+async fn create_payouts(
+    state: &AppState,
+    tenant: &TenantContext,
+    walker_id: Option<UserId>,
+    before: DateTime<Utc>,
+) -> ApiResult<Vec<WalkerPayout>> {
+    let unpaid =
+        WalkerEarningRepository::list_unpaid(&tenant.pool, tenant.org_id, walker_id, before)
+            .await?;
+
+    let mut payouts = Vec::new();
+    for balance in &unpaid {
+        let Some(payout) = WalkerEarningRepository::create_payout(
+            &tenant.pool,
+            tenant.org_id,
+            balance.walker_id,
+            balance.currency,
+            before,
+        )
+        .await?
+        else {
+            continue;
+        };
+        payouts.push(send_or_log(state, tenant, &payout).await);
+    }
+    Ok(payouts)
+}
+
+/// Send a payout, returning it as it now stands
+async fn send_or_log(
+    state: &AppState,
+    tenant: &TenantContext,
+    payout: &WalkerPayout,
+) -> WalkerPayout {
+    match send_walker_payout(state, tenant, payout).await {
+        Ok(Some(sent)) => sent,
+        Ok(None) => payout.clone(),
+        Err(e) => {
+            tracing::warn!(
+                "Failed to transfer walker payout {} for organization {}, will retry: {}",
+                payout.id,
+                tenant.org_id,
+                e.0
+            );
+            payout.clone()
+        }
+    }
+}
+
+/// Transfer a payout to the walker's connected account. Returns `None` if
+/// the walker is paid outside the platform.
+async fn send_walker_payout(
+    state: &AppState,
+    tenant: &TenantContext,
+    payout: &WalkerPayout,
+) -> ApiResult<Option<WalkerPayout>> {
+    let Some(account) =
+        WalkerEarningRepository::get_account(&tenant.pool, tenant.org_id, payout.walker_id)
+            .await?
+            .filter(|a| a.payouts_enabled)
+    else {
+        return Ok(None);
+    };
+    let provider = PaymentProviderRepository::get_primary(&tenant.pool, tenant.org_id)
+        .await?
+        .ok_or_else(|| {
+            ApiError::from(AppError::Internal(
+                "No payment provider configured".to_string(),
+            ))
+        })?;
+
+    let gateway = state.payment_gateways.for_provider(&provider)?;
+    let request = TransferRequest {
+        amount_cents: payout.amount_cents.into(),
+        currency: payout.currency,
+        destination: account.stripe_account_id,
+        idempotency_key: format!("walker_payout_{}", payout.id),
+        metadata: HashMap::from([
+            ("walker_payout_id".to_string(), payout.id.to_string()),
+            ("walker_id".to_string(), payout.walker_id.to_string()),
+        ]),
+    };
+
+    match gateway.transfer(&request).await {
+        // Only marked transferred once it's posted, so a failed post is sent
+        // again (under the same idempotency key) and posted on the next run
+        Ok(transfer) => {
+            ledger::post_walker_transfer(&tenant.pool, tenant.org_id, payout).await?;
+            Ok(
+                WalkerEarningRepository::mark_transferred(&tenant.pool, payout.id, &transfer.id)
+                    .await?,
+            )
+        }
+        Err(GatewayError::NotSupported(_)) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Fail the walker payout a reversed transfer paid, so its earnings are paid
+/// again. Returns the payout, if the transfer paid one.
+pub async fn reverse_transfer(
+    pool: &PgPool,
+    org_id: OrganizationId,
+    stripe_transfer_id: &str,
+) -> Result<Option<WalkerPayout>, sqlx::Error> {
+    let Some(payout) =
+        WalkerEarningRepository::get_payout_by_transfer(pool, org_id, stripe_transfer_id).await?
+    else {
+        return Ok(None);
+    };
+    ledger::post_walker_transfer_reversed(pool, org_id, &payout).await?;
+    if payout.status == PayoutStatus::Failed {
+        return Ok(Some(payout));
+    }
+
+    let payout = WalkerEarningRepository::update_payout_status(
+        pool,
+        org_id,
+        payout.id,
+        PayoutStatus::Failed,
+        Some("Transfer reversed"),
+    )
+    .await?;
+    if let Some(payout) = &payout {
+        let released = WalkerEarningRepository::release_earnings(pool, org_id, payout.id).await?;
+        tracing::info!(
+            "Walker payout {} was reversed; {} earnings returned to walker {}",
+            payout.id,
+            released,
+            payout.walker_id
+        );
+    }
+    Ok(payout)
+}
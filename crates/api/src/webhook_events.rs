@@ -0,0 +1,143 @@
+//! Processing payment provider webhooks.
+//!
+//! Webhooks are stored as they arrive and acknowledged straight away, so the
+//! provider doesn't resend them while we work, then processed in the
+//! background. An attempt that fails is retried with exponential backoff;
+//! an event that fails every attempt is dead-lettered for a manager to
+//! inspect and replay. The worker picks up retries along with any event the
+//! server stopped processing partway.
+
+use chrono::Utc;
+use db::models::{PaymentWebhookEvent, WebhookEventStatus};
+use db::WebhookEventRepository;
+use shared::AppError;
+use uuid::Uuid;
+
+use crate::{
+    auth::TenantContext,
+    error::{ApiError, ApiResult},
+    routes::webhooks::process_event,
+    state::AppState,
+};
+
+/// Events claimed per organization each time the worker runs
+const WEBHOOK_BATCH_SIZE: i64 = 100;
+
+/// Process a newly recorded event in the background
+pub fn spawn_processing(state: AppState, tenant: TenantContext, event_id: Uuid) {
+    tokio::spawn(async move {
+        match WebhookEventRepository::claim(&tenant.pool, event_id).await {
+            Ok(Some(event)) => {
+                process(&state, &tenant, &event).await;
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!(
+                "Failed to claim webhook event {}, the worker will retry it: {}",
+                event_id,
+                e
+            ),
+        }
+    });
+}
+
+/// Process the organization's events that are due. Returns the number
+/// processed successfully.
+pub async fn process_due(state: &AppState, tenant: &TenantContext) -> ApiResult<usize> {
+    let events =
+        WebhookEventRepository::claim_due(&tenant.pool, tenant.org_id, WEBHOOK_BATCH_SIZE).await?;
+
+    let mut processed = 0;
+    for event in &events {
+        if process(state, tenant, event).await.status == WebhookEventStatus::Processed {
+            processed += 1;
+        }
+    }
+    Ok(processed)
+}
+
+/// Process a failed or dead-lettered event again now, with a fresh set of
+/// attempts
+pub async fn replay(
+    state: &AppState,
+    tenant: &TenantContext,
+    id: Uuid,
+) -> ApiResult<PaymentWebhookEvent> {
+    WebhookEventRepository::replay(&tenant.pool, tenant.org_id, id)
+        .await?
+        .ok_or_else(|| ApiError::from(AppError::NotFound("Failed webhook event".to_string())))?;
+
+    // The worker may claim it first, in which case it's already processing
+    match WebhookEventRepository::claim(&tenant.pool, id).await? {
+        Some(event) => Ok(process(state, tenant, &event).await),
+        None => WebhookEventRepository::get_by_id(&tenant.pool, tenant.org_id, id)
+            .await?
+            .ok_or_else(|| ApiError::from(AppError::NotFound("Webhook event".to_string()))),
+    }
+}
+
+/// Apply a claimed event and record the outcome, returning the event as it
+/// now stands
+async fn process(
+    state: &AppState,
+    tenant: &TenantContext,
+    event: &PaymentWebhookEvent,
+) -> PaymentWebhookEvent {
+    let updated = match process_event(state, &tenant.pool, tenant.org_id, event).await {
+        Ok(()) => {
+            WebhookEventRepository::mark_processed(&tenant.pool, event.id, event.claimed_by).await
+        }
+        Err(message) => {
+            let (status, next_attempt_at) = event.after_failure(Utc::now());
+            if status == WebhookEventStatus::Dead {
+                tracing::error!(
+                    "{} webhook {} ({}) failed {} times and was dead-lettered for organization {}: {}",
+                    event.provider,
+                    event.event_id,
+                    event.event_type,
+                    event.attempts,
+                    tenant.org_id,
+                    message
+                );
+            } else {
+                tracing::warn!(
+                    "{} webhook {} ({}) failed, retrying at {}: {}",
+                    event.provider,
+                    event.event_id,
+                    event.event_type,
+                    next_attempt_at,
+                    message
+                );
+            }
+            WebhookEventRepository::mark_failed(
+                &tenant.pool,
+                event.id,
+                event.claimed_by,
+                status,
+                next_attempt_at,
+                &message,
+            )
+            .await
+        }
+    };
+
+    match updated {
+        Ok(Some(updated)) => updated,
+        Ok(None) => {
+            // Taken over after the processing timeout; the attempt holding
+            // the claim now records the outcome
+            tracing::warn!(
+                "Webhook event {} was claimed again while processing, leaving its outcome to the new attempt",
+                event.id
+            );
+            event.clone()
+        }
+        Err(e) => {
+            tracing::warn!(
+                "Failed to record the outcome of webhook event {}: {}",
+                event.id,
+                e
+            );
+            event.clone()
+        }
+    }
+}
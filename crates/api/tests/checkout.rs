@@ -0,0 +1,397 @@
+mod common;
+
+use api::payments::record_provider_refund;
+use axum::http::{Method, StatusCode};
+use common::{build_request, TestApp};
+use db::models::{
+    BookingStatus, CreateRefund, MembershipRole, RefundAllocation, RefundSource, RefundStatus,
+    TransactionStatus, UpdateLocation, UpdateTransaction,
+};
+use db::{BookingRepository, LocationRepository, RefundRepository, TransactionRepository};
+use integrations::gateway::DECLINED_PAYMENT_METHOD;
+use serde_json::json;
+
+#[tokio::test]
+async fn a_booking_has_one_live_payment() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    app.payment_provider().await;
+    let customer = app.user(MembershipRole::Customer).await;
+    let walker = app.user(MembershipRole::Walker).await;
+    let booking = app.booking(&customer, &walker, 2500).await;
+    let token = app.token(customer.id);
+    let body = json!({ "booking_id": booking.id.to_string() });
+
+    // Concurrent checkouts race past the pre-check; the index lets one through
+    let (first, second) = tokio::join!(
+        app.request(Method::POST, "/checkout", Some(&token), Some(body.clone())),
+        app.request(Method::POST, "/checkout", Some(&token), Some(body.clone())),
+    );
+    let mut statuses = [first.0, second.0];
+    statuses.sort();
+    assert_eq!(statuses, [StatusCode::OK, StatusCode::CONFLICT]);
+
+    let (status, _) = app
+        .request(Method::POST, "/checkout", Some(&token), Some(body))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn a_retried_checkout_returns_the_original_payment() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    app.payment_provider().await;
+    let customer = app.user(MembershipRole::Customer).await;
+    let walker = app.user(MembershipRole::Walker).await;
+    let booking = app.booking(&customer, &walker, 2500).await;
+    let token = app.token(customer.id);
+    let body = json!({ "booking_id": booking.id.to_string() });
+
+    let checkout = |key: &str| {
+        let mut request =
+            build_request(Method::POST, "/checkout", Some(&token), Some(body.clone()));
+        request
+            .headers_mut()
+            .insert("X-Idempotency-Key", key.parse().unwrap());
+        request
+    };
+
+    let (status, first) = app.send(checkout("attempt-1")).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // The response was lost and the attempt recorded as failed
+    let transaction_id = first["transaction_id"].as_str().unwrap().parse().unwrap();
+    TransactionRepository::update(
+        &app.pool,
+        transaction_id,
+        UpdateTransaction {
+            status: Some(TransactionStatus::Failed),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let (status, retry) = app.send(checkout("attempt-1")).await;
+    assert_eq!(status, StatusCode::OK, "{}", retry);
+    assert_eq!(retry["transaction_id"], first["transaction_id"]);
+    assert_eq!(retry["payment_id"], first["payment_id"]);
+
+    // The provider saw the same request twice
+    let requests = app.gateway.authorize_requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0], requests[1]);
+}
+
+#[tokio::test]
+async fn walks_outside_the_tax_table_are_not_taxed_at_a_guess() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    app.payment_provider().await;
+    let customer = app.user(MembershipRole::Customer).await;
+    let walker = app.user(MembershipRole::Walker).await;
+    let booking = app.booking(&customer, &walker, 2500).await;
+    LocationRepository::update(
+        &app.pool,
+        app.org_id,
+        booking.location_id,
+        UpdateLocation {
+            country: Some("CA".to_string()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let (status, body) = app
+        .request(
+            Method::POST,
+            "/checkout",
+            Some(&app.token(customer.id)),
+            Some(json!({ "booking_id": booking.id.to_string() })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{}", body);
+    assert!(app.gateway.authorize_requests().is_empty());
+}
+
+#[tokio::test]
+async fn checkout_with_a_saved_card_charges_and_refunds() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    app.payment_provider().await;
+    let customer = app.user(MembershipRole::Customer).await;
+    let walker = app.user(MembershipRole::Walker).await;
+    let booking = app.booking(&customer, &walker, 2500).await;
+    BookingRepository::update_status(&app.pool, app.org_id, booking.id, BookingStatus::Completed)
+        .await
+        .unwrap();
+    let card = app.saved_card(&customer, "pm_card_visa").await;
+    let token = app.token(customer.id);
+
+    let (status, checkout) = app
+        .request(
+            Method::POST,
+            "/checkout",
+            Some(&token),
+            Some(json!({
+                "booking_id": booking.id.to_string(),
+                "payment_method_id": card.id.to_string(),
+                "tip_cents": 500,
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    // A finished walk is captured straight away
+    assert_eq!(checkout["status"], "succeeded");
+    assert_eq!(checkout["subtotal_cents"], 2500);
+    let total = checkout["total_cents"].as_i64().unwrap();
+    assert!(total >= 3000, "total {} excludes the tip", total);
+
+    let payment = app
+        .gateway
+        .payment(checkout["payment_id"].as_str().unwrap())
+        .unwrap();
+    assert_eq!(payment.amount_cents, total);
+
+    let transaction_id = checkout["transaction_id"].as_str().unwrap();
+    let (status, refund) = app
+        .request(
+            Method::POST,
+            &format!("/checkout/{}/refund", transaction_id),
+            Some(&token),
+            Some(json!({ "amount_cents": 1000, "reason": "Short walk" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(refund["refund"]["amount_cents"], 1000);
+    assert_eq!(refund["refunded_amount_cents"], 1000);
+    assert_eq!(refund["refundable_amount_cents"], total - 1000);
+    assert_eq!(refund["transaction_status"], "partially_refunded");
+
+    let refunds = app.gateway.refunds();
+    assert_eq!(refunds.len(), 1);
+    assert_eq!(refunds[0].amount_cents, 1000);
+
+    let admin = app.user(MembershipRole::Admin).await;
+    let (status, check) = app
+        .request(
+            Method::GET,
+            "/admin/ledger/check",
+            Some(&app.token(admin.id)),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(check["balanced"], true, "{}", check);
+    assert_eq!(check["currencies"][0]["currency"], "USD");
+    assert!(
+        check["currencies"][0]["total_debits_cents"]
+            .as_i64()
+            .unwrap()
+            > 0
+    );
+}
+
+#[tokio::test]
+async fn a_refund_reported_before_it_is_matched_counts_once() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    app.payment_provider().await;
+    let customer = app.user(MembershipRole::Customer).await;
+    let walker = app.user(MembershipRole::Walker).await;
+    let booking = app.booking(&customer, &walker, 2500).await;
+    BookingRepository::update_status(&app.pool, app.org_id, booking.id, BookingStatus::Completed)
+        .await
+        .unwrap();
+    let card = app.saved_card(&customer, "pm_card_visa").await;
+    let (status, checkout) = app
+        .request(
+            Method::POST,
+            "/checkout",
+            Some(&app.token(customer.id)),
+            Some(json!({
+                "booking_id": booking.id.to_string(),
+                "payment_method_id": card.id.to_string(),
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", checkout);
+    let transaction_id = checkout["transaction_id"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let transaction = TransactionRepository::get_by_id(&app.pool, app.org_id, transaction_id)
+        .await
+        .unwrap()
+        .unwrap();
+
+    // A platform refund sent to the provider, not yet matched to its refund
+    let pending = RefundRepository::create(
+        &app.pool,
+        app.org_id,
+        CreateRefund {
+            transaction_id,
+            amount_cents: 1000,
+            currency: transaction.currency,
+            reason: Some("Short walk".to_string()),
+            status: RefundStatus::Pending,
+            source: RefundSource::Customer,
+            initiated_by: Some(customer.id),
+            provider_refund_id: None,
+            allocation: RefundAllocation::for_refund(&transaction, 1000),
+        },
+    )
+    .await
+    .unwrap();
+
+    // The provider reports it twice at once before it's matched
+    let tenant = app.tenant();
+    let report = || {
+        record_provider_refund(
+            &app.state,
+            &tenant,
+            &transaction,
+            "re_race",
+            1000,
+            RefundStatus::Succeeded,
+        )
+    };
+    let (first, second) = tokio::join!(report(), report());
+    assert_eq!(first.unwrap().id, second.unwrap().id);
+
+    let (refund, changed) = RefundRepository::attach_provider_refund(
+        &app.pool,
+        app.org_id,
+        pending.id,
+        "re_race",
+        RefundStatus::Succeeded,
+    )
+    .await
+    .unwrap();
+    assert!(!changed);
+    assert_eq!(refund.source, RefundSource::Customer);
+    assert_eq!(refund.initiated_by, Some(customer.id));
+
+    let refunds = RefundRepository::list_by_transaction(&app.pool, app.org_id, transaction_id)
+        .await
+        .unwrap();
+    assert_eq!(refunds.len(), 1);
+    assert_eq!(refunds[0].id, refund.id);
+    let refunded = TransactionRepository::get_by_id(&app.pool, app.org_id, transaction_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(refunded.refunded_amount_cents, 1000);
+}
+
+#[tokio::test]
+async fn a_declined_checkout_can_be_retried() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    app.payment_provider().await;
+    let customer = app.user(MembershipRole::Customer).await;
+    let walker = app.user(MembershipRole::Walker).await;
+    let booking = app.booking(&customer, &walker, 2500).await;
+    let declined = app.saved_card(&customer, DECLINED_PAYMENT_METHOD).await;
+    let token = app.token(customer.id);
+
+    let checkout = |payment_method_id: String| {
+        app.request(
+            Method::POST,
+            "/checkout",
+            Some(&token),
+            Some(json!({
+                "booking_id": booking.id.to_string(),
+                "payment_method_id": payment_method_id,
+            })),
+        )
+    };
+
+    let (status, body) = checkout(declined.id.to_string()).await;
+    assert_eq!(status, StatusCode::PAYMENT_REQUIRED);
+    assert_eq!(body["error"]["code"], "PAYMENT_DECLINED");
+
+    let failed = TransactionRepository::get_by_booking(&app.pool, app.org_id, booking.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(failed.status, TransactionStatus::Failed);
+    assert!(failed.failure_message.is_some());
+
+    // The failed attempt doesn't block paying with another card
+    let card = app.saved_card(&customer, "pm_card_visa").await;
+    let (status, body) = checkout(card.id.to_string()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "authorized");
+}
+
+#[tokio::test]
+async fn a_declined_hold_stops_the_walker_confirming() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    app.payment_provider().await;
+    let customer = app.user(MembershipRole::Customer).await;
+    let walker = app.user(MembershipRole::Walker).await;
+    let booking = app.booking(&customer, &walker, 2500).await;
+    app.saved_card(&customer, DECLINED_PAYMENT_METHOD).await;
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            &format!("/bookings/{}/confirm", booking.id),
+            Some(&app.token(walker.id)),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::PAYMENT_REQUIRED);
+
+    let booking = BookingRepository::find_by_id(&app.pool, app.org_id, booking.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(booking.status, BookingStatus::Pending);
+}
+
+#[tokio::test]
+async fn confirming_a_booking_holds_the_saved_card() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    app.payment_provider().await;
+    let customer = app.user(MembershipRole::Customer).await;
+    let walker = app.user(MembershipRole::Walker).await;
+    let booking = app.booking(&customer, &walker, 2500).await;
+    app.saved_card(&customer, "pm_card_visa").await;
+
+    let (status, body) = app
+        .request(
+            Method::POST,
+            &format!("/bookings/{}/confirm", booking.id),
+            Some(&app.token(walker.id)),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "confirmed");
+
+    let transaction = TransactionRepository::get_by_booking(&app.pool, app.org_id, booking.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(transaction.status, TransactionStatus::Authorized);
+    let payment = app
+        .gateway
+        .payment(transaction.external_payment_id.as_deref().unwrap())
+        .unwrap();
+    assert_eq!(payment.amount_cents, i64::from(transaction.total_cents));
+}
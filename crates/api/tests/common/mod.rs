@@ -0,0 +1,318 @@
+//! Shared setup for API tests that run against a real database.
+//!
+//! Each test gets a freshly migrated database created next to the one in
+//! `DATABASE_URL`, holding a single organization whose tenant database is the
+//! same database. Tests are skipped when `DATABASE_URL` isn't set.
+
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use api::{
+    auth::{create_token, TenantContext},
+    payments::PaymentGateways,
+    AppState,
+};
+use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode},
+    Router,
+};
+use chrono::{DateTime, Duration, Utc};
+use db::models::*;
+use db::{
+    BookingRepository, CustomerPaymentMethodRepository, LocationRepository, MembershipRepository,
+    OrganizationRepository, PaymentProviderRepository, ServiceRepository, TenantDatabaseRepository,
+    UserRepository,
+};
+use integrations::gateway::FakeGateway;
+use metrics_exporter_prometheus::PrometheusBuilder;
+use serde_json::Value;
+use shared::types::{Currency, OrganizationId, UserId};
+use sqlx::{Connection, PgConnection, PgPool};
+use tower::ServiceExt;
+
+pub const JWT_SECRET: &str = "test-secret";
+
+pub struct TestApp {
+    pub state: AppState,
+    pub pool: PgPool,
+    pub org_id: OrganizationId,
+    pub gateway: Arc<FakeGateway>,
+}
+
+impl TestApp {
+    /// Set up a fresh database and app, or `None` when no database is configured
+    pub async fn new() -> Option<Self> {
+        let Ok(base_url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL not set, skipping database test");
+            return None;
+        };
+
+        let name = format!("api_test_{}", uuid::Uuid::new_v4().simple());
+        let mut admin = PgConnection::connect(&base_url)
+            .await
+            .expect("connect to DATABASE_URL");
+        sqlx::query(&format!("CREATE DATABASE {}", name))
+            .execute(&mut admin)
+            .await
+            .expect("create test database");
+        admin.close().await.ok();
+
+        let url = match base_url.rsplit_once('/') {
+            Some((server, _)) => format!("{}/{}", server, name),
+            None => panic!("DATABASE_URL has no database name"),
+        };
+        let pool = db::create_pool(&url)
+            .await
+            .expect("connect to test database");
+        sqlx::migrate!("../../migrations")
+            .run(&pool)
+            .await
+            .expect("run migrations");
+
+        let org = OrganizationRepository::create(
+            &pool,
+            CreateOrganization {
+                name: "Test Walkers".to_string(),
+                slug: name.replace('_', "-"),
+                subdomain: None,
+                custom_domain: None,
+                settings: None,
+            },
+        )
+        .await
+        .expect("create organization");
+        TenantDatabaseRepository::create(
+            &pool,
+            CreateTenantDatabase {
+                organization_id: org.id,
+                connection_string: url,
+                status: Some(TenantDbStatus::Active),
+            },
+        )
+        .await
+        .expect("create tenant database");
+
+        let gateway = Arc::new(FakeGateway::new());
+        let metrics_handle = PrometheusBuilder::new().build_recorder().handle();
+        let mut state = AppState::new(pool.clone(), JWT_SECRET.to_string(), None, metrics_handle);
+        state.payment_gateways = PaymentGateways::with_gateway(gateway.clone());
+
+        Some(Self {
+            state,
+            pool,
+            org_id: org.id,
+            gateway,
+        })
+    }
+
+    pub fn app(&self) -> Router {
+        api::create_app(self.state.clone())
+    }
+
+    pub fn token(&self, user_id: UserId) -> String {
+        create_token(user_id, Some(self.org_id), JWT_SECRET).expect("create token")
+    }
+
+    /// The test organization, for calling handlers' helpers directly
+    pub fn tenant(&self) -> TenantContext {
+        TenantContext {
+            org_id: self.org_id,
+            pool: self.pool.clone(),
+        }
+    }
+
+    /// Create a user with an active membership in the test organization
+    pub async fn user(&self, role: MembershipRole) -> User {
+        let user_role = match role {
+            MembershipRole::Owner | MembershipRole::Admin => UserRole::Admin,
+            MembershipRole::Walker => UserRole::Walker,
+            MembershipRole::Customer => UserRole::Customer,
+        };
+        let user = UserRepository::create(
+            &self.pool,
+            CreateUser {
+                organization_id: self.org_id,
+                email: format!("{}@example.com", uuid::Uuid::new_v4().simple()),
+                password_hash: "not-a-real-hash".to_string(),
+                role: user_role,
+                first_name: "Test".to_string(),
+                last_name: role.to_string(),
+                phone: None,
+                timezone: None,
+            },
+        )
+        .await
+        .expect("create user");
+        MembershipRepository::create(
+            &self.pool,
+            CreateMembership {
+                user_id: user.id,
+                organization_id: self.org_id,
+                role,
+                status: Some(MembershipStatus::Active),
+                title: None,
+            },
+        )
+        .await
+        .expect("create membership");
+        user
+    }
+
+    pub async fn service(&self, price_cents: i64) -> Service {
+        ServiceRepository::create(
+            &self.pool,
+            CreateService {
+                organization_id: self.org_id,
+                name: "30 minute walk".to_string(),
+                description: None,
+                duration_minutes: 30,
+                base_price_cents: price_cents,
+                currency: Currency::USD,
+            },
+        )
+        .await
+        .expect("create service")
+    }
+
+    pub async fn location(&self, customer: &User) -> Location {
+        LocationRepository::create(
+            &self.pool,
+            CreateLocation {
+                organization_id: self.org_id,
+                user_id: customer.id,
+                name: "Home".to_string(),
+                address: "1 Main St".to_string(),
+                city: "Springfield".to_string(),
+                state: "IL".to_string(),
+                zip_code: "62701".to_string(),
+                country: "US".to_string(),
+                latitude: 39.7817,
+                longitude: -89.6501,
+                notes: None,
+                is_default: true,
+            },
+        )
+        .await
+        .expect("create location")
+    }
+
+    /// A pending booking for tomorrow
+    pub async fn booking(&self, customer: &User, walker: &User, price_cents: i64) -> Booking {
+        let service = self.service(price_cents).await;
+        let location = self.location(customer).await;
+        let start: DateTime<Utc> = Utc::now() + Duration::days(1);
+        BookingRepository::create(
+            &self.pool,
+            CreateBooking {
+                organization_id: self.org_id,
+                customer_id: customer.id,
+                walker_id: walker.id,
+                service_id: service.id,
+                location_id: location.id,
+                scheduled_start: start,
+                scheduled_end: start + Duration::minutes(30),
+                price_cents,
+                price_breakdown: None,
+                notes: None,
+                recurring_series_id: None,
+                occurrence_number: None,
+            },
+        )
+        .await
+        .expect("create booking")
+    }
+
+    /// Connect the organization to Stripe so payments go through the gateway
+    pub async fn payment_provider(&self) -> PaymentProvider {
+        PaymentProviderRepository::create(
+            &self.pool,
+            self.org_id,
+            CreatePaymentProvider {
+                provider_type: PaymentProviderType::Stripe,
+                stripe_account_id: Some("acct_test".to_string()),
+                stripe_account_type: None,
+                square_merchant_id: None,
+                access_token_encrypted: None,
+                refresh_token_encrypted: None,
+                token_expires_at: None,
+            },
+        )
+        .await
+        .expect("create payment provider")
+    }
+
+    /// Save a default card for `customer`; use
+    /// [`DECLINED_PAYMENT_METHOD`](integrations::gateway::DECLINED_PAYMENT_METHOD)
+    /// for one the gateway declines
+    pub async fn saved_card(&self, customer: &User, payment_method: &str) -> CustomerPaymentMethod {
+        CustomerPaymentMethodRepository::create(
+            &self.pool,
+            self.org_id,
+            customer.id,
+            CreateCustomerPaymentMethod {
+                provider_type: PaymentProviderType::Stripe,
+                method_type: PaymentMethodType::Card,
+                stripe_payment_method_id: Some(payment_method.to_string()),
+                stripe_customer_id: Some("cus_test".to_string()),
+                square_card_id: None,
+                square_customer_id: None,
+                last_four: Some("4242".to_string()),
+                brand: Some("visa".to_string()),
+                exp_month: Some(12),
+                exp_year: Some(2040),
+                cardholder_name: None,
+                card_country: Some("US".to_string()),
+                bank_name: None,
+                account_last_four: None,
+                wallet_type: None,
+                is_default: true,
+                billing_address: None,
+            },
+        )
+        .await
+        .expect("create payment method")
+    }
+
+    /// Send a request and return the status and JSON body (`Null` if empty)
+    pub async fn request(
+        &self,
+        method: Method,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        self.send(build_request(method, uri, token, body)).await
+    }
+
+    /// Send a prepared request and return the status and JSON body
+    pub async fn send(&self, request: Request<Body>) -> (StatusCode, Value) {
+        let response = self.app().oneshot(request).await.expect("send request");
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("read body");
+        let json = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+        (status, json)
+    }
+}
+
+pub fn build_request(
+    method: Method,
+    uri: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> Request<Body> {
+    let mut builder = Request::builder().method(method).uri(uri);
+    if let Some(token) = token {
+        builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    match body {
+        Some(body) => builder
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string())),
+        None => builder.body(Body::empty()),
+    }
+    .expect("build request")
+}
@@ -0,0 +1,242 @@
+mod common;
+
+use api::disputes;
+use axum::http::{Method, StatusCode};
+use chrono::{Duration, Utc};
+use common::TestApp;
+use db::models::{
+    BookingStatus, CreateDispute, CreateWalkBreadcrumb, Dispute, DisputeStatus, JournalEntryKind,
+    MembershipRole, UpdateDispute,
+};
+use db::{
+    BookingRepository, DisputeRepository, LedgerRepository, TransactionRepository,
+    WalkTrailRepository,
+};
+use serde_json::{json, Value};
+use shared::types::Currency;
+
+/// A dispute against a paid walk, with evidence due in `due_in`
+async fn dispute(app: &TestApp, due_in: Duration) -> Dispute {
+    app.payment_provider().await;
+    let customer = app.user(MembershipRole::Customer).await;
+    let walker = app.user(MembershipRole::Walker).await;
+    let card = app.saved_card(&customer, "pm_card_visa").await;
+    let booking = app.booking(&customer, &walker, 2500).await;
+    BookingRepository::update_status(&app.pool, app.org_id, booking.id, BookingStatus::Completed)
+        .await
+        .unwrap();
+    let (status, checkout) = app
+        .request(
+            Method::POST,
+            "/checkout",
+            Some(&app.token(customer.id)),
+            Some(json!({
+                "booking_id": booking.id.to_string(),
+                "payment_method_id": card.id.to_string(),
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", checkout);
+
+    DisputeRepository::create(
+        &app.pool,
+        app.org_id,
+        CreateDispute {
+            transaction_id: checkout["transaction_id"]
+                .as_str()
+                .unwrap()
+                .parse()
+                .unwrap(),
+            amount_cents: 2500,
+            currency: Currency::USD,
+            stripe_dispute_id: Some("dp_test".to_string()),
+            square_dispute_id: None,
+            reason: "product_not_received".to_string(),
+            evidence_due_by: Some(Utc::now() + due_in),
+        },
+    )
+    .await
+    .unwrap()
+}
+
+async fn reminders(app: &TestApp, token: &str) -> Vec<Value> {
+    let (status, body) = app
+        .request(
+            Method::GET,
+            "/admin/disputes?reminder_due=true",
+            Some(token),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    body.as_array().unwrap().clone()
+}
+
+/// The kinds of the ledger entries posted for a dispute
+async fn dispute_entries(app: &TestApp, dispute: &Dispute) -> Vec<JournalEntryKind> {
+    LedgerRepository::list_by_transaction(&app.pool, app.org_id, dispute.transaction_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|entry| entry.source_id == dispute.id)
+        .map(|entry| entry.kind)
+        .collect()
+}
+
+#[tokio::test]
+async fn reminders_stay_listed_until_a_manager_dismisses_them() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    let dispute = dispute(&app, Duration::days(2)).await;
+    let admin = app.user(MembershipRole::Admin).await;
+    let token = app.token(admin.id);
+
+    // Listing doesn't count as the reminder being seen
+    for _ in 0..2 {
+        let due = reminders(&app, &token).await;
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0]["id"], dispute.id.to_string());
+        assert_eq!(due[0]["reminder_days"], 3);
+    }
+
+    let (status, dismissed) = app
+        .request(
+            Method::POST,
+            &format!("/admin/disputes/{}/reminder/dismiss", dispute.id),
+            Some(&token),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(dismissed["reminder_days"], Value::Null);
+    assert!(reminders(&app, &token).await.is_empty());
+
+    // The last reminder comes due as the deadline nears
+    DisputeRepository::update(
+        &app.pool,
+        dispute.id,
+        UpdateDispute {
+            evidence_due_by: Some(Utc::now() + Duration::hours(12)),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    let due = reminders(&app, &token).await;
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0]["reminder_days"], 1);
+}
+
+#[tokio::test]
+async fn only_managers_dismiss_reminders() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    let dispute = dispute(&app, Duration::days(2)).await;
+    let walker = app.user(MembershipRole::Walker).await;
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            &format!("/admin/disputes/{}/reminder/dismiss", dispute.id),
+            Some(&app.token(walker.id)),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let dispute = DisputeRepository::get_by_id(&app.pool, app.org_id, dispute.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(dispute.last_reminder_days, None);
+}
+
+#[tokio::test]
+async fn a_repeated_report_posts_what_a_failed_one_missed() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    let dispute = dispute(&app, Duration::days(2)).await;
+
+    let lost = disputes::apply_status(&app.pool, &dispute, DisputeStatus::Lost, None, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        dispute_entries(&app, &dispute).await,
+        vec![
+            JournalEntryKind::DisputeOpened,
+            JournalEntryKind::DisputeLost
+        ]
+    );
+
+    // As if posting the outcome had failed after the status was saved
+    sqlx::query("DELETE FROM journal_entries WHERE source_id = $1 AND kind = 'dispute_lost'")
+        .bind(dispute.id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    disputes::apply_status(&app.pool, &lost, DisputeStatus::Lost, None, None)
+        .await
+        .unwrap();
+    disputes::apply_status(&app.pool, &lost, DisputeStatus::Lost, None, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        dispute_entries(&app, &dispute).await,
+        vec![
+            JournalEntryKind::DisputeOpened,
+            JournalEntryKind::DisputeLost
+        ]
+    );
+}
+
+#[tokio::test]
+async fn trails_of_disputed_walks_outlive_the_retention_period() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    let dispute = dispute(&app, Duration::days(2)).await;
+    let charge = TransactionRepository::get_by_id(&app.pool, app.org_id, dispute.transaction_id)
+        .await
+        .unwrap()
+        .unwrap();
+    let booking_id = charge.booking_id.unwrap();
+    WalkTrailRepository::record(
+        &app.pool,
+        &CreateWalkBreadcrumb {
+            organization_id: app.org_id,
+            booking_id,
+            walker_id: charge.provider_user_id.unwrap(),
+            latitude: 40.7128,
+            longitude: -74.006,
+            accuracy_meters: None,
+            speed_mps: None,
+            recorded_at: Utc::now() - Duration::days(400),
+        },
+    )
+    .await
+    .unwrap();
+
+    let purged = WalkTrailRepository::delete_before(&app.pool, app.org_id, Utc::now())
+        .await
+        .unwrap();
+    assert_eq!(purged, 0);
+
+    // Once the dispute is resolved the trail is purged like any other
+    disputes::apply_status(&app.pool, &dispute, DisputeStatus::Won, Some("won"), None)
+        .await
+        .unwrap();
+    let purged = WalkTrailRepository::delete_before(&app.pool, app.org_id, Utc::now())
+        .await
+        .unwrap();
+    assert_eq!(purged, 1);
+    assert!(
+        WalkTrailRepository::list_for_booking(&app.pool, app.org_id, booking_id)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
@@ -0,0 +1,234 @@
+mod common;
+
+use std::time::Duration;
+
+use api::auth::{create_stream_ticket, create_token};
+use axum::http::{header, Method, StatusCode};
+use chrono::Utc;
+use common::{build_request, TestApp, JWT_SECRET};
+use db::models::{Booking, MembershipRole, User, WalkerLocationUpdate};
+use db::{BookingRepository, WalkerLocationRepository};
+use futures::StreamExt;
+use serde_json::Value;
+use shared::types::OrganizationId;
+use tower::ServiceExt;
+
+/// A confirmed booking starting in ten minutes whose walker has reported a
+/// location
+async fn active_booking(app: &TestApp) -> (User, User, Booking) {
+    let customer = app.user(MembershipRole::Customer).await;
+    let walker = app.user(MembershipRole::Walker).await;
+    let booking = app.booking(&customer, &walker, 2500).await;
+    let start = Utc::now() + chrono::Duration::minutes(10);
+    BookingRepository::reschedule(
+        &app.pool,
+        app.org_id,
+        booking.id,
+        start,
+        start + chrono::Duration::minutes(30),
+    )
+    .await
+    .unwrap();
+    let booking = BookingRepository::confirm(&app.pool, app.org_id, booking.id)
+        .await
+        .unwrap()
+        .unwrap();
+    WalkerLocationRepository::upsert(
+        &app.pool,
+        walker.id,
+        &WalkerLocationUpdate {
+            latitude: 39.78,
+            longitude: -89.65,
+            accuracy_meters: Some(5.0),
+            heading: None,
+            speed_mps: None,
+            is_on_duty: true,
+        },
+    )
+    .await
+    .unwrap();
+    (customer, walker, booking)
+}
+
+/// Open a stream and return its status, content type and first frame
+async fn first_frame(
+    app: &TestApp,
+    uri: &str,
+    token: Option<&str>,
+) -> (StatusCode, String, String) {
+    let response = app
+        .app()
+        .oneshot(build_request(Method::GET, uri, token, None))
+        .await
+        .unwrap();
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    if !status.is_success() {
+        return (status, content_type, String::new());
+    }
+
+    let mut body = response.into_body().into_data_stream();
+    let chunk = tokio::time::timeout(Duration::from_secs(5), body.next())
+        .await
+        .expect("stream sent no event")
+        .expect("stream ended")
+        .unwrap();
+    (
+        status,
+        content_type,
+        String::from_utf8(chunk.to_vec()).unwrap(),
+    )
+}
+
+fn frame_data(frame: &str) -> Value {
+    let data = frame
+        .lines()
+        .find_map(|line| line.strip_prefix("data: "))
+        .expect("frame has data");
+    serde_json::from_str(data).unwrap()
+}
+
+#[tokio::test]
+async fn booking_stream_requires_authentication() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    let (_, _, booking) = active_booking(&app).await;
+    let uri = format!("/bookings/{}/live", booking.id);
+
+    let (status, _, _) = first_frame(&app, &uri, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _, _) = first_frame(&app, &format!("{}?ticket=garbage", uri), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn session_tokens_are_not_accepted_in_the_query() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    let (customer, _, booking) = active_booking(&app).await;
+    let token = app.token(customer.id);
+
+    for param in ["access_token", "ticket"] {
+        let uri = format!("/bookings/{}/live?{}={}", booking.id, param, token);
+        let (status, _, _) = first_frame(&app, &uri, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{} accepted", param);
+    }
+}
+
+#[tokio::test]
+async fn stream_tickets_are_not_session_tokens() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    let customer = app.user(MembershipRole::Customer).await;
+    let ticket = create_stream_ticket(customer.id, app.org_id, JWT_SECRET).unwrap();
+
+    let (status, _) = app
+        .request(Method::GET, "/bookings", Some(&ticket), None)
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn booking_stream_is_limited_to_participants() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    let (_, _, booking) = active_booking(&app).await;
+    let uri = format!("/bookings/{}/live", booking.id);
+
+    let stranger = app.user(MembershipRole::Customer).await;
+    let (status, _, _) = first_frame(&app, &uri, Some(&app.token(stranger.id))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // A token for another organization can't reach this tenant's bookings
+    let foreign = create_token(stranger.id, Some(OrganizationId::new()), JWT_SECRET).unwrap();
+    let (status, _, _) = first_frame(&app, &uri, Some(&foreign)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn booking_stream_sends_location_events_with_a_ticket() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    let (customer, walker, booking) = active_booking(&app).await;
+
+    let (status, body) = app
+        .request(
+            Method::POST,
+            "/auth/stream-ticket",
+            Some(&app.token(customer.id)),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let ticket = body["ticket"].as_str().unwrap();
+    // Tickets are plain JWTs, so they survive percent-encoding intact
+    let encoded = ticket.replace('.', "%2E");
+
+    let uri = format!("/bookings/{}/live?ticket={}", booking.id, encoded);
+    let (status, content_type, frame) = first_frame(&app, &uri, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(content_type.starts_with("text/event-stream"));
+    assert!(frame.starts_with("event: location\n"), "{:?}", frame);
+
+    let data = frame_data(&frame);
+    assert_eq!(data["booking_id"], booking.id.to_string());
+    assert_eq!(data["walker_id"], walker.id.to_string());
+    assert_eq!(data["booking_status"], "confirmed");
+    assert_eq!(data["latitude"], 39.78);
+}
+
+#[tokio::test]
+async fn booking_stream_opens_shortly_before_the_walk() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    let (customer, _, booking) = active_booking(&app).await;
+    let start = Utc::now() + chrono::Duration::days(2);
+    BookingRepository::reschedule(
+        &app.pool,
+        app.org_id,
+        booking.id,
+        start,
+        start + chrono::Duration::minutes(30),
+    )
+    .await
+    .unwrap();
+
+    let uri = format!("/bookings/{}/live", booking.id);
+    let (status, _, _) = first_frame(&app, &uri, Some(&app.token(customer.id))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn on_duty_stream_is_limited_to_managers() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    let (customer, walker, _) = active_booking(&app).await;
+    let admin = app.user(MembershipRole::Admin).await;
+
+    for user in [&customer, &walker] {
+        let (status, _, _) =
+            first_frame(&app, "/admin/walkers/live", Some(&app.token(user.id))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    let (status, _, frame) =
+        first_frame(&app, "/admin/walkers/live", Some(&app.token(admin.id))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(frame.starts_with("event: walkers\n"), "{:?}", frame);
+    let walkers = frame_data(&frame)["walkers"].as_array().unwrap().clone();
+    assert_eq!(walkers.len(), 1);
+    assert_eq!(walkers[0]["walker_id"], walker.id.to_string());
+}
@@ -0,0 +1,332 @@
+mod common;
+
+use api::customer_packages;
+use axum::http::{Method, StatusCode};
+use chrono::{DateTime, Duration, Utc};
+use common::TestApp;
+use db::models::{CustomerSubscription, Location, MembershipRole, PackageCredit, User};
+use db::{SubscriptionRepository, WalkerEarningRepository};
+use serde_json::{json, Value};
+use shared::types::{BookingId, UserId};
+
+/// A monthly plan of four walks for 100.00, offered by a manager
+async fn plan(app: &TestApp) -> Value {
+    let admin = app.user(MembershipRole::Admin).await;
+    let service = app.service(2500).await;
+    let (status, plan) = app
+        .request(
+            Method::POST,
+            "/subscriptions/packages",
+            Some(&app.token(admin.id)),
+            Some(json!({
+                "service_id": service.id.to_string(),
+                "name": "Weekday walks",
+                "price_cents": 10000,
+                "interval": "month",
+                "walks_per_period": 4,
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", plan);
+    plan
+}
+
+/// Subscribe `customer` to `plan` with a saved card, sending `extra` fields
+/// along with the request
+async fn subscribe(
+    app: &TestApp,
+    plan: &Value,
+    customer: &User,
+    extra: Value,
+) -> (StatusCode, Value) {
+    let walker = app.user(MembershipRole::Walker).await;
+    let card = app.saved_card(customer, "pm_card_visa").await;
+
+    let mut body = json!({
+        "package_plan_id": plan["id"],
+        "preferred_walker_id": walker.id.to_string(),
+        "payment_method_id": card.id.to_string(),
+    });
+    if let (Some(body), Some(extra)) = (body.as_object_mut(), extra.as_object()) {
+        body.extend(extra.clone());
+    }
+    app.request(
+        Method::POST,
+        "/subscriptions/customer",
+        Some(&app.token(customer.id)),
+        Some(body),
+    )
+    .await
+}
+
+/// A package paid for with a saved card, started and with its first period
+/// over
+async fn lapsed_package(app: &TestApp) -> CustomerSubscription {
+    app.payment_provider().await;
+    let plan = plan(app).await;
+    let customer = app.user(MembershipRole::Customer).await;
+    let (status, started) = subscribe(app, &plan, &customer, json!({})).await;
+    assert_eq!(status, StatusCode::OK, "{}", started);
+
+    let started = SubscriptionRepository::get_customer_subscription(
+        &app.pool,
+        app.org_id,
+        started["id"].as_str().unwrap().parse().unwrap(),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    let period_end = Utc::now() - Duration::hours(1);
+    set_period(app, &started, period_end - Duration::days(30), period_end).await
+}
+
+/// Book a walk `days` from now through the API, returning the booking's ID
+async fn book(
+    app: &TestApp,
+    customer: &User,
+    walker: &User,
+    location: &Location,
+    service_id: String,
+    days: i64,
+) -> BookingId {
+    let (status, booking) = app
+        .request(
+            Method::POST,
+            "/bookings",
+            Some(&app.token(customer.id)),
+            Some(json!({
+                "service_id": service_id,
+                "location_id": location.id.to_string(),
+                "walker_id": walker.id.to_string(),
+                "start_time": (Utc::now() + Duration::days(days)).to_rfc3339(),
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", booking);
+    booking["id"].as_str().unwrap().parse().unwrap()
+}
+
+async fn redemption(app: &TestApp, booking_id: &BookingId) -> Option<PackageCredit> {
+    SubscriptionRepository::get_booking_redemption(&app.pool, app.org_id, *booking_id)
+        .await
+        .unwrap()
+}
+
+async fn earned_cents(app: &TestApp, walker: &User) -> i32 {
+    let now = Utc::now();
+    WalkerEarningRepository::list_for_walker(
+        &app.pool,
+        app.org_id,
+        walker.id,
+        now - Duration::days(1),
+        now + Duration::days(1),
+    )
+    .await
+    .unwrap()
+    .iter()
+    .map(|e| e.amount_cents)
+    .sum()
+}
+
+async fn set_period(
+    app: &TestApp,
+    subscription: &CustomerSubscription,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> CustomerSubscription {
+    sqlx::query_as(
+        r#"
+        UPDATE customer_subscriptions
+        SET current_period_start = $2, current_period_end = $3
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(subscription.id)
+    .bind(start)
+    .bind(end)
+    .fetch_one(&app.pool)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn servers_renewing_together_charge_a_period_once() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    let subscription = lapsed_package(&app).await;
+    let charged = app.gateway.authorize_requests().len();
+
+    let tenant = app.tenant();
+    let now = Utc::now();
+    let (first, second) = tokio::join!(
+        customer_packages::renew_due(&app.state, &tenant, now),
+        customer_packages::renew_due(&app.state, &tenant, now),
+    );
+    assert_eq!(first.unwrap() + second.unwrap(), 1);
+    assert_eq!(app.gateway.authorize_requests().len(), charged + 1);
+
+    let renewed =
+        SubscriptionRepository::get_customer_subscription(&app.pool, app.org_id, subscription.id)
+            .await
+            .unwrap()
+            .unwrap();
+    assert_eq!(
+        renewed.current_period_start,
+        subscription.current_period_end
+    );
+    assert_eq!(renewed.next_payment_attempt_at, None);
+}
+
+#[tokio::test]
+async fn a_period_charged_before_renewal_failed_isnt_charged_again() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    let subscription = lapsed_package(&app).await;
+    let tenant = app.tenant();
+
+    let renewed = customer_packages::renew_due(&app.state, &tenant, Utc::now())
+        .await
+        .unwrap();
+    assert_eq!(renewed, 1);
+    let charged = app.gateway.authorize_requests().len();
+
+    // As if starting the period had failed after the charge went through
+    set_period(
+        &app,
+        &subscription,
+        subscription.current_period_start.unwrap(),
+        subscription.current_period_end.unwrap(),
+    )
+    .await;
+
+    let renewed = customer_packages::renew_due(&app.state, &tenant, Utc::now())
+        .await
+        .unwrap();
+    assert_eq!(renewed, 1);
+    assert_eq!(app.gateway.authorize_requests().len(), charged);
+}
+
+#[tokio::test]
+async fn customers_subscribe_on_the_plans_terms() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    app.payment_provider().await;
+    let plan = plan(&app).await;
+
+    // Terms sent by the customer are ignored
+    let customer = app.user(MembershipRole::Customer).await;
+    let (status, subscription) = subscribe(
+        &app,
+        &plan,
+        &customer,
+        json!({ "price_cents": 1, "walks_per_period": 100, "interval": "year" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", subscription);
+    assert_eq!(subscription["price_cents"], 10000);
+    assert_eq!(subscription["walks_per_period"], 4);
+    assert_eq!(subscription["credits_remaining"], 4);
+    assert_eq!(subscription["interval"], "month");
+    assert_eq!(subscription["service_id"], plan["service_id"]);
+    let charged = app.gateway.authorize_requests().pop().unwrap();
+    assert!(charged.amount_cents >= 10000);
+
+    // Only managers offer plans
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/subscriptions/packages",
+            Some(&app.token(customer.id)),
+            Some(json!({
+                "service_id": plan["service_id"],
+                "name": "Cheap walks",
+                "price_cents": 1,
+                "interval": "month",
+                "walks_per_period": 100,
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Archived plans take no new subscribers
+    let admin = app.user(MembershipRole::Admin).await;
+    let (status, _) = app
+        .request(
+            Method::DELETE,
+            &format!("/subscriptions/packages/{}", plan["id"].as_str().unwrap()),
+            Some(&app.token(admin.id)),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = subscribe(&app, &plan, &customer, json!({})).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn credits_only_pay_for_the_packages_service() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    app.payment_provider().await;
+    let plan = plan(&app).await;
+    let customer = app.user(MembershipRole::Customer).await;
+    let walker = app.user(MembershipRole::Walker).await;
+    let (status, subscription) = subscribe(&app, &plan, &customer, json!({})).await;
+    assert_eq!(status, StatusCode::OK, "{}", subscription);
+
+    let location = app.location(&customer).await;
+    let other = app.service(9000).await;
+
+    let booked = book(&app, &customer, &walker, &location, other.id.to_string(), 1).await;
+    assert!(redemption(&app, &booked).await.is_none());
+    let service_id = plan["service_id"].as_str().unwrap().to_string();
+    let booked = book(&app, &customer, &walker, &location, service_id, 2).await;
+    assert!(redemption(&app, &booked).await.is_some());
+}
+
+#[tokio::test]
+async fn the_walker_who_does_a_package_walk_earns_it() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    app.payment_provider().await;
+    let plan = plan(&app).await;
+    let customer = app.user(MembershipRole::Customer).await;
+    let (status, subscription) = subscribe(&app, &plan, &customer, json!({})).await;
+    assert_eq!(status, StatusCode::OK, "{}", subscription);
+
+    let preferred: UserId = subscription["preferred_walker_id"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let walker = app.user(MembershipRole::Walker).await;
+    assert_ne!(walker.id, preferred);
+
+    // Nobody earns anything until a credit is spent
+    assert_eq!(earned_cents(&app, &walker).await, 0);
+
+    let location = app.location(&customer).await;
+    let service_id = plan["service_id"].as_str().unwrap().to_string();
+    let booked = book(&app, &customer, &walker, &location, service_id, 2).await;
+    assert!(redemption(&app, &booked).await.is_some());
+    let earned = earned_cents(&app, &walker).await;
+    assert!(earned > 0 && earned <= 2500, "{}", earned);
+
+    // A credit returned by a free cancellation takes the walk back
+    let (status, cancelled) = app
+        .request(
+            Method::POST,
+            &format!("/bookings/{}/cancel", booked),
+            Some(&app.token(customer.id)),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", cancelled);
+    assert_eq!(earned_cents(&app, &walker).await, 0);
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use api::payments::settle_finished_bookings;
+use axum::http::{Method, StatusCode};
+use common::TestApp;
+use db::models::{Booking, BookingStatus, MembershipRole, Transaction, TransactionStatus};
+use db::{BookingRepository, TransactionRepository};
+use integrations::gateway::PaymentState;
+
+/// A booking the walker confirmed, holding the customer's saved card
+async fn held_booking(app: &TestApp) -> (Booking, Transaction) {
+    let customer = app.user(MembershipRole::Customer).await;
+    let walker = app.user(MembershipRole::Walker).await;
+    let booking = app.booking(&customer, &walker, 2500).await;
+    app.saved_card(&customer, "pm_card_visa").await;
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            &format!("/bookings/{}/confirm", booking.id),
+            Some(&app.token(walker.id)),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let transaction = TransactionRepository::get_by_booking(&app.pool, app.org_id, booking.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(transaction.status, TransactionStatus::Authorized);
+    (booking, transaction)
+}
+
+#[tokio::test]
+async fn completing_a_booking_captures_the_hold() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    app.payment_provider().await;
+    let (booking, transaction) = held_booking(&app).await;
+    let admin = app.user(MembershipRole::Admin).await;
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            &format!("/bookings/{}/complete", booking.id),
+            Some(&app.token(admin.id)),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let payment_id = transaction.external_payment_id.as_deref().unwrap();
+    assert_eq!(
+        app.gateway.payment(payment_id).unwrap().state,
+        PaymentState::Succeeded
+    );
+}
+
+#[tokio::test]
+async fn holds_left_on_finished_bookings_are_settled() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    app.payment_provider().await;
+    let (completed, completed_hold) = held_booking(&app).await;
+    let (cancelled, cancelled_hold) = held_booking(&app).await;
+
+    // The bookings finished but their capture and release never happened
+    for (booking, status) in [
+        (&completed, BookingStatus::Completed),
+        (&cancelled, BookingStatus::Cancelled),
+    ] {
+        BookingRepository::update_status(&app.pool, app.org_id, booking.id, status)
+            .await
+            .unwrap();
+    }
+
+    let settled = settle_finished_bookings(&app.state, &app.tenant())
+        .await
+        .unwrap();
+    assert_eq!(settled, 2);
+
+    let captured = TransactionRepository::get_by_id(&app.pool, app.org_id, completed_hold.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(captured.status, TransactionStatus::Succeeded);
+    let released = TransactionRepository::get_by_id(&app.pool, app.org_id, cancelled_hold.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(released.status, TransactionStatus::Canceled);
+    assert_eq!(
+        app.gateway
+            .payment(cancelled_hold.external_payment_id.as_deref().unwrap())
+            .unwrap()
+            .state,
+        PaymentState::Canceled
+    );
+
+    // Nothing is left to settle
+    let settled = settle_finished_bookings(&app.state, &app.tenant())
+        .await
+        .unwrap();
+    assert_eq!(settled, 0);
+}
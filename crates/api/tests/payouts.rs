@@ -0,0 +1,247 @@
+mod common;
+
+use api::payouts::run_scheduled_payouts;
+use axum::http::{Method, StatusCode};
+use chrono::{Days, NaiveDate, Utc};
+use common::TestApp;
+use db::models::{
+    BookingStatus, CreatePayoutSettings, CustomerPaymentMethod, MembershipRole, RevenueSplitType,
+    Transaction, UpdatePayoutSettings, UpsertRevenueSplit, User,
+};
+use db::{
+    BookingRepository, LedgerRepository, PayoutRepository, TransactionRepository,
+    WalkerEarningRepository,
+};
+use rust_decimal::Decimal;
+use serde_json::json;
+
+/// Pay out every day to a verified bank account
+async fn daily_payouts(app: &TestApp) {
+    PayoutRepository::create_settings(
+        &app.pool,
+        app.org_id,
+        CreatePayoutSettings {
+            payout_method: "bank".to_string(),
+            payout_schedule: "daily".to_string(),
+            payout_day_of_week: None,
+            payout_day_of_month: None,
+            minimum_payout_cents: Some(100),
+        },
+    )
+    .await
+    .unwrap();
+    PayoutRepository::update_settings(
+        &app.pool,
+        app.org_id,
+        UpdatePayoutSettings {
+            stripe_bank_account_id: Some("ba_test".to_string()),
+            is_verified: Some(true),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+}
+
+/// A completed walk paid for with `card`
+async fn paid_walk(
+    app: &TestApp,
+    customer: &User,
+    card: &CustomerPaymentMethod,
+    walker: &User,
+) -> Transaction {
+    let booking = app.booking(customer, walker, 2500).await;
+    BookingRepository::update_status(&app.pool, app.org_id, booking.id, BookingStatus::Completed)
+        .await
+        .unwrap();
+    let (status, checkout) = app
+        .request(
+            Method::POST,
+            "/checkout",
+            Some(&app.token(customer.id)),
+            Some(json!({
+                "booking_id": booking.id.to_string(),
+                "payment_method_id": card.id.to_string(),
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", checkout);
+
+    let id = checkout["transaction_id"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    TransactionRepository::get_by_id(&app.pool, app.org_id, id)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+fn days_from_now(days: u64) -> NaiveDate {
+    Utc::now().date_naive() + Days::new(days)
+}
+
+#[tokio::test]
+async fn servers_running_the_job_together_pay_out_once() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    app.payment_provider().await;
+    daily_payouts(&app).await;
+    let customer = app.user(MembershipRole::Customer).await;
+    let walker = app.user(MembershipRole::Walker).await;
+    let card = app.saved_card(&customer, "pm_card_visa").await;
+    let transaction = paid_walk(&app, &customer, &card, &walker).await;
+
+    let tenant = app.tenant();
+    let date = days_from_now(1);
+    let (first, second) = tokio::join!(
+        run_scheduled_payouts(&app.state, &tenant, date),
+        run_scheduled_payouts(&app.state, &tenant, date),
+    );
+    let created: Vec<_> = first.unwrap().into_iter().chain(second.unwrap()).collect();
+    assert_eq!(created.len(), 1);
+
+    let payout = &created[0];
+    assert_eq!(payout.scheduled_for, Some(date));
+    assert_eq!(payout.transaction_ids, vec![transaction.id]);
+    assert_eq!(payout.amount_cents, transaction.provider_payout_cents);
+
+    let sent = app.gateway.payouts();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(i64::from(payout.net_amount_cents), sent[0].amount_cents);
+
+    // Running again the same day pays nothing more
+    let again = run_scheduled_payouts(&app.state, &tenant, date)
+        .await
+        .unwrap();
+    assert!(again.is_empty());
+    assert_eq!(app.gateway.payouts().len(), 1);
+}
+
+#[tokio::test]
+async fn a_refund_after_payout_comes_off_the_next_one() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    app.payment_provider().await;
+    daily_payouts(&app).await;
+    let customer = app.user(MembershipRole::Customer).await;
+    let walker = app.user(MembershipRole::Walker).await;
+    let card = app.saved_card(&customer, "pm_card_visa").await;
+    let paid_out = paid_walk(&app, &customer, &card, &walker).await;
+
+    let tenant = app.tenant();
+    let first = run_scheduled_payouts(&app.state, &tenant, days_from_now(1))
+        .await
+        .unwrap();
+    assert_eq!(first.len(), 1);
+
+    let (status, refund) = app
+        .request(
+            Method::POST,
+            &format!("/checkout/{}/refund", paid_out.id),
+            Some(&app.token(customer.id)),
+            Some(json!({ "amount_cents": 1000 })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", refund);
+    let refund_id = refund["refund"]["id"].as_str().unwrap().to_string();
+    let reversed = refund["refund"]["provider_payout_reversed_cents"]
+        .as_i64()
+        .unwrap();
+    assert!(reversed > 0);
+
+    let next = paid_walk(&app, &customer, &card, &walker).await;
+    let second = run_scheduled_payouts(&app.state, &tenant, days_from_now(2))
+        .await
+        .unwrap();
+    assert_eq!(second.len(), 1);
+    let payout = &second[0];
+    assert_eq!(payout.transaction_ids, vec![next.id]);
+    assert_eq!(
+        i64::from(payout.amount_cents),
+        i64::from(next.provider_payout_cents) - reversed
+    );
+    assert_eq!(
+        payout.refund_ids,
+        vec![refund_id.parse::<uuid::Uuid>().unwrap()]
+    );
+
+    // The detail shows where the difference went
+    let admin = app.user(MembershipRole::Admin).await;
+    let (status, detail) = app
+        .request(
+            Method::GET,
+            &format!("/payouts/{}", payout.id),
+            Some(&app.token(admin.id)),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(detail["late_refunds"][0]["refund_id"], refund_id);
+    assert_eq!(detail["late_refunds"][0]["reversed_cents"], reversed);
+
+    // The refund is only deducted once
+    paid_walk(&app, &customer, &card, &walker).await;
+    let third = run_scheduled_payouts(&app.state, &tenant, days_from_now(3))
+        .await
+        .unwrap();
+    assert!(third[0].refund_ids.is_empty());
+}
+
+#[tokio::test]
+async fn walker_transfers_come_off_the_organizations_payout() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    app.payment_provider().await;
+    daily_payouts(&app).await;
+    let customer = app.user(MembershipRole::Customer).await;
+    let walker = app.user(MembershipRole::Walker).await;
+    let card = app.saved_card(&customer, "pm_card_visa").await;
+
+    // The walker keeps 60% and is paid to their connected account
+    WalkerEarningRepository::upsert_split(
+        &app.pool,
+        app.org_id,
+        walker.id,
+        UpsertRevenueSplit {
+            service_id: None,
+            split_type: RevenueSplitType::Percent,
+            percent: Some(Decimal::from(60)),
+            flat_cents: None,
+        },
+    )
+    .await
+    .unwrap();
+    WalkerEarningRepository::upsert_account(&app.pool, app.org_id, walker.id, "acct_walker", true)
+        .await
+        .unwrap();
+    let transaction = paid_walk(&app, &customer, &card, &walker).await;
+
+    let payouts = run_scheduled_payouts(&app.state, &app.tenant(), days_from_now(1))
+        .await
+        .unwrap();
+    let transfers = app.gateway.transfers();
+    assert_eq!(transfers.len(), 1);
+    assert_eq!(transfers[0].amount_cents, 1500);
+
+    assert_eq!(payouts.len(), 1);
+    assert_eq!(
+        i64::from(payouts[0].amount_cents),
+        i64::from(transaction.provider_payout_cents) - 1500
+    );
+    assert_eq!(payouts[0].walker_earning_ids.len(), 1);
+
+    // The transfer is posted against what the walker is owed
+    let owed = LedgerRepository::walker_balance(&app.pool, app.org_id, walker.id)
+        .await
+        .unwrap();
+    assert_eq!(owed[0].debit_cents, 1500);
+    assert!(LedgerRepository::check(&app.pool, app.org_id)
+        .await
+        .unwrap()
+        .is_balanced());
+}
@@ -0,0 +1,206 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::TestApp;
+use db::models::{Booking, BookingStatus, CustomerPaymentMethod, MembershipRole, User};
+use db::{BookingRepository, TransactionRepository};
+use serde_json::{json, Value};
+
+/// A completed walk the customer paid for with `card`, plus `tip_cents`
+/// added at checkout
+async fn paid_walk(
+    app: &TestApp,
+    customer: &User,
+    card: &CustomerPaymentMethod,
+    walker: &User,
+    tip_cents: i32,
+) -> Booking {
+    let booking = app.booking(customer, walker, 2500).await;
+    BookingRepository::update_status(&app.pool, app.org_id, booking.id, BookingStatus::Completed)
+        .await
+        .unwrap();
+
+    let (status, checkout) = app
+        .request(
+            Method::POST,
+            "/checkout",
+            Some(&app.token(customer.id)),
+            Some(json!({
+                "booking_id": booking.id.to_string(),
+                "payment_method_id": card.id.to_string(),
+                "tip_cents": tip_cents,
+            })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", checkout);
+    assert_eq!(checkout["status"], "succeeded");
+    booking
+}
+
+async fn tip(
+    app: &TestApp,
+    customer: &User,
+    booking: &Booking,
+    tip_cents: i32,
+) -> (StatusCode, Value) {
+    app.request(
+        Method::POST,
+        &format!("/bookings/{}/tip", booking.id),
+        Some(&app.token(customer.id)),
+        Some(json!({ "tip_cents": tip_cents })),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn a_tip_is_charged_off_session_to_the_walks_card() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    app.payment_provider().await;
+    let customer = app.user(MembershipRole::Customer).await;
+    let walker = app.user(MembershipRole::Walker).await;
+    let card = app.saved_card(&customer, "pm_card_visa").await;
+    let booking = paid_walk(&app, &customer, &card, &walker, 0).await;
+    let walk = TransactionRepository::get_by_booking(&app.pool, app.org_id, booking.id)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let (status, body) = tip(&app, &customer, &booking, 500).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["status"], "succeeded");
+    assert_eq!(body["tip_cents"], 500);
+    // Tips are untaxed and fee-free
+    assert_eq!(body["total_cents"], 500);
+    assert_eq!(body["walk_transaction_id"], walk.id.to_string());
+
+    let request = app.gateway.authorize_requests().pop().unwrap();
+    assert!(request.off_session);
+    assert_eq!(request.payment_method.as_deref(), Some("pm_card_visa"));
+    assert_eq!(request.amount_cents, 500);
+
+    // One tip per walk
+    let (status, _) = tip(&app, &customer, &booking, 500).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn tips_go_to_the_walker_in_full() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    app.payment_provider().await;
+    let customer = app.user(MembershipRole::Customer).await;
+    let walker = app.user(MembershipRole::Walker).await;
+    let admin = app.user(MembershipRole::Admin).await;
+    let card = app.saved_card(&customer, "pm_card_visa").await;
+
+    let (status, _) = app
+        .request(
+            Method::PUT,
+            &format!("/walkers/{}/revenue-splits", walker.id),
+            Some(&app.token(admin.id)),
+            Some(json!({ "split_type": "percent", "percent": "60" })),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let booking = paid_walk(&app, &customer, &card, &walker, 0).await;
+    let (status, body) = tip(&app, &customer, &booking, 500).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let tip_id = body["transaction_id"].as_str().unwrap().parse().unwrap();
+    let tip = TransactionRepository::get_by_id(&app.pool, app.org_id, tip_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(tip.provider_payout_cents, 500);
+    assert_eq!(tip.platform_fee_cents, 0);
+    assert_eq!(
+        app.gateway
+            .authorize_requests()
+            .pop()
+            .unwrap()
+            .application_fee_cents,
+        Some(0)
+    );
+
+    // The split applies to the walk, not the tip
+    let (status, statement) = app
+        .request(
+            Method::GET,
+            &format!("/walkers/{}/earnings", walker.id),
+            Some(&app.token(walker.id)),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let totals = &statement["totals"][0];
+    assert_eq!(totals["walk_cents"], 1500);
+    assert_eq!(totals["tip_cents"], 500);
+    assert_eq!(totals["earned_cents"], 2000);
+}
+
+#[tokio::test]
+async fn tip_summary_totals_each_walkers_tips() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    app.payment_provider().await;
+    let customer = app.user(MembershipRole::Customer).await;
+    let tipped = app.user(MembershipRole::Walker).await;
+    let untipped = app.user(MembershipRole::Walker).await;
+    let admin = app.user(MembershipRole::Admin).await;
+    let card = app.saved_card(&customer, "pm_card_visa").await;
+
+    // One walk tipped at checkout, one tipped afterwards, one not at all
+    paid_walk(&app, &customer, &card, &tipped, 300).await;
+    let later = paid_walk(&app, &customer, &card, &tipped, 0).await;
+    let (status, _) = tip(&app, &customer, &later, 500).await;
+    assert_eq!(status, StatusCode::OK);
+    paid_walk(&app, &customer, &card, &untipped, 0).await;
+
+    let (status, summary) = app
+        .request(
+            Method::GET,
+            "/tips/summary",
+            Some(&app.token(admin.id)),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let summary = summary.as_array().unwrap();
+    assert_eq!(summary.len(), 2);
+
+    let top = &summary[0];
+    assert_eq!(top["walker_id"], tipped.id.to_string());
+    assert_eq!(top["currency"], "USD");
+    assert_eq!(top["walk_count"], 2);
+    assert_eq!(top["tipped_walk_count"], 2);
+    assert_eq!(top["tip_rate_percent"], 100.0);
+    assert_eq!(top["tip_count"], 2);
+    assert_eq!(top["tip_cents"], 800);
+    assert_eq!(top["average_tip_cents"], 400);
+    assert_eq!(top["post_walk_tip_count"], 1);
+    assert_eq!(top["post_walk_tip_cents"], 500);
+
+    let bottom = &summary[1];
+    assert_eq!(bottom["walker_id"], untipped.id.to_string());
+    assert_eq!(bottom["walk_count"], 1);
+    assert_eq!(bottom["tip_count"], 0);
+    assert_eq!(bottom["tip_cents"], 0);
+
+    // Walkers only see their own tips
+    let (status, own) = app
+        .request(
+            Method::GET,
+            &format!("/tips/summary?walker_id={}", tipped.id),
+            Some(&app.token(untipped.id)),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(own.as_array().unwrap().len(), 1);
+    assert_eq!(own[0]["walker_id"], untipped.id.to_string());
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use common::TestApp;
+use db::models::{
+    CreateWebhookEvent, PaymentProviderType, WebhookEventStatus, WEBHOOK_PROCESSING_TIMEOUT_MINUTES,
+};
+use db::WebhookEventRepository;
+use serde_json::json;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn only_the_attempt_holding_the_claim_records_the_outcome() {
+    let Some(app) = TestApp::new().await else {
+        return;
+    };
+    let event = WebhookEventRepository::create(
+        &app.pool,
+        CreateWebhookEvent {
+            organization_id: app.org_id,
+            provider: PaymentProviderType::Stripe,
+            event_id: format!("evt_{}", Uuid::new_v4().simple()),
+            event_type: "payment_intent.succeeded".to_string(),
+            payload: json!({}),
+        },
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    let stalled = WebhookEventRepository::claim(&app.pool, event.id)
+        .await
+        .unwrap()
+        .unwrap();
+
+    // The first attempt outlives the processing timeout and is taken over
+    sqlx::query(
+        "UPDATE payment_webhook_events \
+         SET last_attempt_at = NOW() - make_interval(mins => $2) \
+         WHERE id = $1",
+    )
+    .bind(event.id)
+    .bind(WEBHOOK_PROCESSING_TIMEOUT_MINUTES as i32 + 1)
+    .execute(&app.pool)
+    .await
+    .unwrap();
+    let current = WebhookEventRepository::claim_due(&app.pool, app.org_id, 100)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|claimed| claimed.id == event.id)
+        .unwrap();
+    assert_ne!(current.claimed_by, stalled.claimed_by);
+
+    let failed = WebhookEventRepository::mark_failed(
+        &app.pool,
+        event.id,
+        stalled.claimed_by,
+        WebhookEventStatus::Dead,
+        stalled.next_attempt_at,
+        "timed out",
+    )
+    .await
+    .unwrap();
+    assert!(failed.is_none());
+
+    let processed = WebhookEventRepository::mark_processed(&app.pool, event.id, current.claimed_by)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(processed.status, WebhookEventStatus::Processed);
+    assert!(
+        WebhookEventRepository::mark_processed(&app.pool, event.id, stalled.claimed_by)
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
@@ -4,6 +4,9 @@ use shared::types::{
     BookingId, LocationId, OrganizationId, RecurringBookingSeriesId, ServiceId, UserId,
 };
 use sqlx::FromRow;
+use uuid::Uuid;
+
+use super::ServiceArea;
 
 /// Booking status enum
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
@@ -45,6 +48,7 @@ pub struct Booking {
     pub actual_start: Option<DateTime<Utc>>,
     pub actual_end: Option<DateTime<Utc>>,
     pub price_cents: i64,
+    pub price_breakdown: Option<sqlx::types::Json<BookingPriceBreakdown>>,
     pub notes: Option<String>,
     pub recurring_series_id: Option<RecurringBookingSeriesId>,
     pub occurrence_number: Option<i32>,
@@ -79,6 +83,10 @@ impl Booking {
         self.status == BookingStatus::Pending
     }
 
+    pub fn can_start(&self) -> bool {
+        self.status == BookingStatus::Confirmed
+    }
+
     pub fn is_recurring(&self) -> bool {
         self.recurring_series_id.is_some()
     }
@@ -95,7 +103,113 @@ pub struct CreateBooking {
     pub scheduled_start: DateTime<Utc>,
     pub scheduled_end: DateTime<Utc>,
     pub price_cents: i64,
+    pub price_breakdown: Option<BookingPriceBreakdown>,
     pub notes: Option<String>,
     pub recurring_series_id: Option<RecurringBookingSeriesId>,
     pub occurrence_number: Option<i32>,
 }
+
+/// How a booking's price was derived, captured at booking time so later
+/// changes to service prices or area adjustments don't alter it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BookingPriceBreakdown {
+    pub base_price_cents: i64,
+    pub service_area_id: Option<Uuid>,
+    pub service_area_name: Option<String>,
+    pub price_adjustment_percent: i32,
+    pub price_adjustment_cents: i64,
+    pub total_cents: i64,
+}
+
+impl BookingPriceBreakdown {
+    /// Calculate the booking price from the service base price and the
+    /// walker's service areas covering the booking location.
+    ///
+    /// When several areas match, the highest priority one (lowest `priority`
+    /// value) wins; ties keep the first area in the given order.
+    pub fn calculate(base_price_cents: i64, matching_areas: &[ServiceArea]) -> Self {
+        let area = matching_areas
+            .iter()
+            .min_by_key(|area| area.priority.unwrap_or(0));
+
+        let price_adjustment_percent = area.and_then(|a| a.price_adjustment_percent).unwrap_or(0);
+
+        // Round half away from zero to the nearest cent
+        let scaled = base_price_cents * price_adjustment_percent as i64;
+        let price_adjustment_cents = if scaled >= 0 {
+            (scaled + 50) / 100
+        } else {
+            (scaled - 50) / 100
+        };
+
+        Self {
+            base_price_cents,
+            service_area_id: area.map(|a| a.id),
+            service_area_name: area.map(|a| a.name.clone()),
+            price_adjustment_percent,
+            price_adjustment_cents,
+            total_cents: (base_price_cents + price_adjustment_cents).max(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PolygonPoint;
+
+    fn area(priority: Option<i32>, adjustment: Option<i32>) -> ServiceArea {
+        ServiceArea {
+            id: Uuid::new_v4(),
+            organization_id: Uuid::new_v4(),
+            walker_id: Uuid::new_v4(),
+            name: format!("Area {:?}", priority),
+            color: None,
+            polygon: sqlx::types::Json(vec![
+                PolygonPoint { lat: 0.0, lng: 0.0 },
+                PolygonPoint { lat: 0.0, lng: 1.0 },
+                PolygonPoint { lat: 1.0, lng: 1.0 },
+            ]),
+            holes: sqlx::types::Json(Vec::new()),
+            min_latitude: None,
+            max_latitude: None,
+            min_longitude: None,
+            max_longitude: None,
+            is_active: true,
+            priority,
+            price_adjustment_percent: adjustment,
+            notes: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_no_matching_area_uses_base_price() {
+        let breakdown = BookingPriceBreakdown::calculate(2500, &[]);
+        assert_eq!(breakdown.total_cents, 2500);
+        assert_eq!(breakdown.price_adjustment_cents, 0);
+        assert!(breakdown.service_area_id.is_none());
+    }
+
+    #[test]
+    fn test_highest_priority_area_wins() {
+        let areas = vec![area(Some(5), Some(50)), area(Some(1), Some(10))];
+        let breakdown = BookingPriceBreakdown::calculate(2500, &areas);
+        assert_eq!(breakdown.service_area_id, Some(areas[1].id));
+        assert_eq!(breakdown.price_adjustment_percent, 10);
+        assert_eq!(breakdown.price_adjustment_cents, 250);
+        assert_eq!(breakdown.total_cents, 2750);
+    }
+
+    #[test]
+    fn test_negative_adjustment_rounds_and_clamps() {
+        let breakdown = BookingPriceBreakdown::calculate(1999, &[area(None, Some(-15))]);
+        // -299.85 rounds to -300
+        assert_eq!(breakdown.price_adjustment_cents, -300);
+        assert_eq!(breakdown.total_cents, 1699);
+
+        let breakdown = BookingPriceBreakdown::calculate(1000, &[area(None, Some(-150))]);
+        assert_eq!(breakdown.total_cents, 0);
+    }
+}
@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use shared::types::{OrganizationId, ServiceId};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Fee charged for changes made within `within_minutes` of the booking start
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CancellationPolicyTier {
+    pub within_minutes: i64,
+    /// Percentage of the booking price, 0-100
+    pub fee_percent: u32,
+}
+
+/// Cancellation and late-reschedule fee policy for an organization, or for
+/// a single service when `service_id` is set
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct CancellationPolicy {
+    pub id: Uuid,
+    pub organization_id: OrganizationId,
+    pub service_id: Option<ServiceId>,
+    pub tiers: sqlx::types::Json<Vec<CancellationPolicyTier>>,
+    pub walker_cancels_free: bool,
+    pub charge_reschedules: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Input for creating or replacing a policy
+#[derive(Debug, Clone)]
+pub struct UpsertCancellationPolicy {
+    pub service_id: Option<ServiceId>,
+    pub tiers: Vec<CancellationPolicyTier>,
+    pub walker_cancels_free: bool,
+    pub charge_reschedules: bool,
+}
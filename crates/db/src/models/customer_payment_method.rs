@@ -1,10 +1,10 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use shared::types::{OrganizationId, UserId};
+use shared::types::{Currency, OrganizationId, UserId};
 use sqlx::FromRow;
 use uuid::Uuid;
 
-use super::PaymentProviderType;
+use super::{CardRegion, PaymentProviderType};
 
 /// Payment method type
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
@@ -50,6 +50,8 @@ pub struct CustomerPaymentMethod {
     pub exp_month: Option<i32>,
     pub exp_year: Option<i32>,
     pub cardholder_name: Option<String>,
+    /// Country the card was issued in (ISO 3166-1 alpha-2)
+    pub card_country: Option<String>,
     // Bank account
     pub bank_name: Option<String>,
     pub account_last_four: Option<String>,
@@ -125,6 +127,16 @@ impl CustomerPaymentMethod {
         false
     }
 
+    /// Region of the card behind this payment method when charging in
+    /// `currency`. Cards of unknown origin are assumed to be domestic.
+    pub fn card_region(&self, currency: Currency) -> CardRegion {
+        self.card_country
+            .as_deref()
+            .map_or(CardRegion::Domestic, |country| {
+                CardRegion::of_card(country, currency)
+            })
+    }
+
     /// Get expiry date display
     pub fn expiry_display(&self) -> Option<String> {
         if let (Some(month), Some(year)) = (self.exp_month, self.exp_year) {
@@ -169,6 +181,7 @@ pub struct CreateCustomerPaymentMethod {
     pub exp_month: Option<i32>,
     pub exp_year: Option<i32>,
     pub cardholder_name: Option<String>,
+    pub card_country: Option<String>,
     pub bank_name: Option<String>,
     pub account_last_four: Option<String>,
     pub wallet_type: Option<String>,
@@ -1,9 +1,15 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
-use shared::types::OrganizationId;
+use shared::geometry::{process_track, track_stats, TrackFilter, TrackPoint};
+use shared::types::{Currency, Money, OrganizationId};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use super::{Booking, CancellationPolicy, Service, Transaction, User, WalkBreadcrumb};
+
+/// Days before the evidence due date that reminders are shown
+pub const DISPUTE_REMINDER_DAYS: [i32; 3] = [7, 3, 1];
+
 /// Dispute status enum
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "dispute_status", rename_all = "snake_case")]
@@ -25,6 +31,21 @@ impl std::fmt::Display for DisputeStatus {
     }
 }
 
+impl DisputeStatus {
+    /// Map a Stripe or Square dispute status. Inquiries closed without a
+    /// chargeback count as won.
+    pub fn from_provider(status: &str) -> Self {
+        match status.to_lowercase().as_str() {
+            "under_review" | "warning_under_review" | "processing" | "inquiry_processing" => {
+                DisputeStatus::UnderReview
+            }
+            "won" | "warning_closed" | "inquiry_closed" => DisputeStatus::Won,
+            "lost" | "accepted" => DisputeStatus::Lost,
+            _ => DisputeStatus::NeedsResponse,
+        }
+    }
+}
+
 /// Dispute database model
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct Dispute {
@@ -32,13 +53,18 @@ pub struct Dispute {
     pub organization_id: OrganizationId,
     pub transaction_id: Uuid,
     pub amount_cents: i32,
-    pub currency: String,
+    pub currency: Currency,
     pub stripe_dispute_id: Option<String>,
     pub square_dispute_id: Option<String>,
     pub reason: String,
     pub status: DisputeStatus,
     pub evidence_submitted: bool,
     pub evidence_due_by: Option<DateTime<Utc>>,
+    /// Evidence assembled or edited for the dispute
+    pub evidence: Option<sqlx::types::Json<DisputeEvidence>>,
+    pub evidence_submitted_at: Option<DateTime<Utc>>,
+    /// Days before the due date of the last reminder managers dismissed
+    pub last_reminder_days: Option<i32>,
     pub resolved_at: Option<DateTime<Utc>>,
     pub outcome: Option<String>,
     pub metadata: Option<serde_json::Value>,
@@ -80,6 +106,25 @@ impl Dispute {
         matches!(self.status, DisputeStatus::Won | DisputeStatus::Lost)
     }
 
+    /// The evidence reminder due at `now`, if any: the closest of
+    /// [`DISPUTE_REMINDER_DAYS`] the due date is within, unless that
+    /// reminder or a later one was already dismissed
+    pub fn reminder_due(&self, now: DateTime<Utc>) -> Option<i32> {
+        if self.status != DisputeStatus::NeedsResponse || self.evidence_submitted {
+            return None;
+        }
+        let due_by = self.evidence_due_by.filter(|due_by| *due_by > now)?;
+
+        let days = DISPUTE_REMINDER_DAYS
+            .into_iter()
+            .filter(|days| due_by - now <= Duration::days(i64::from(*days)))
+            .min()?;
+        match self.last_reminder_days {
+            Some(last) if last <= days => None,
+            _ => Some(days),
+        }
+    }
+
     /// Get display-friendly reason
     pub fn reason_display(&self) -> &str {
         match self.reason.as_str() {
@@ -101,7 +146,7 @@ impl Dispute {
 pub struct CreateDispute {
     pub transaction_id: Uuid,
     pub amount_cents: i32,
-    pub currency: String,
+    pub currency: Currency,
     pub stripe_dispute_id: Option<String>,
     pub square_dispute_id: Option<String>,
     pub reason: String,
@@ -119,37 +164,466 @@ pub struct UpdateDispute {
     pub metadata: Option<serde_json::Value>,
 }
 
-/// Dispute evidence submission
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Evidence for a dispute, assembled from the booking it paid for and
+/// editable before it's submitted to the provider
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct DisputeEvidence {
-    pub customer_communication: Option<String>,
+    pub customer_name: Option<String>,
+    pub customer_email: Option<String>,
+    /// The service that was booked
+    pub product_description: Option<String>,
+    pub service_date: Option<String>,
+    /// Booking and walk times and the GPS trail
     pub service_documentation: Option<String>,
+    pub customer_communication: Option<String>,
     pub receipt: Option<String>,
     pub cancellation_policy: Option<String>,
-    pub customer_signature: Option<String>,
-    pub service_date: Option<String>,
+    /// Anything else, such as links to walk photos
     pub additional_documentation: Option<Vec<String>>,
 }
 
-/// Payment webhook event for audit logging
-#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
-pub struct PaymentWebhookEvent {
-    pub id: Uuid,
-    pub provider: String,
-    pub event_id: String,
-    pub event_type: String,
-    pub payload: serde_json::Value,
-    pub processed: bool,
-    pub processed_at: Option<DateTime<Utc>>,
-    pub error_message: Option<String>,
-    pub created_at: DateTime<Utc>,
+/// The records a dispute's evidence is assembled from
+#[derive(Debug, Clone, Copy)]
+pub struct EvidenceSources<'a> {
+    pub transaction: &'a Transaction,
+    pub booking: Option<&'a Booking>,
+    pub service: Option<&'a Service>,
+    pub customer: Option<&'a User>,
+    pub trail: &'a [WalkBreadcrumb],
+    pub policy: Option<&'a CancellationPolicy>,
 }
 
-/// Input for creating a webhook event record
-#[derive(Debug, Clone, Deserialize)]
-pub struct CreateWebhookEvent {
-    pub provider: String,
-    pub event_id: String,
-    pub event_type: String,
-    pub payload: serde_json::Value,
+impl DisputeEvidence {
+    /// Assemble evidence from what we know about the disputed charge
+    pub fn assemble(sources: EvidenceSources<'_>) -> Self {
+        let transaction = sources.transaction;
+        let money = |cents: i32| Money::new(cents.into(), transaction.currency);
+
+        let mut receipt = format!(
+            "Charged {} on {}: service {}",
+            money(transaction.total_cents),
+            format_time(transaction.created_at),
+            money(transaction.subtotal_cents)
+        );
+        for (label, cents) in [
+            ("tip", transaction.tip_cents),
+            ("fees", transaction.customer_fee_cents),
+            ("tax", transaction.tax_cents),
+        ] {
+            if cents > 0 {
+                receipt.push_str(&format!(", {} {}", label, money(cents)));
+            }
+        }
+        receipt.push('.');
+
+        Self {
+            customer_name: sources
+                .customer
+                .map(|c| format!("{} {}", c.first_name, c.last_name)),
+            customer_email: sources.customer.map(|c| c.email.clone()),
+            product_description: sources.service.map(|service| {
+                let mut description =
+                    format!("{} ({} minutes)", service.name, service.duration_minutes);
+                if let Some(details) = &service.description {
+                    description.push_str(&format!(": {}", details));
+                }
+                description
+            }),
+            service_date: sources.booking.map(|b| {
+                b.actual_start
+                    .unwrap_or(b.scheduled_start)
+                    .format("%Y-%m-%d")
+                    .to_string()
+            }),
+            service_documentation: sources
+                .booking
+                .map(|b| service_documentation(b, sources.trail)),
+            customer_communication: sources
+                .booking
+                .and_then(|b| b.notes.as_deref())
+                .filter(|notes| !notes.trim().is_empty())
+                .map(|notes| format!("Customer's notes on the booking: {}", notes.trim())),
+            receipt: Some(receipt),
+            cancellation_policy: sources.policy.map(describe_policy),
+            additional_documentation: None,
+        }
+    }
+}
+
+fn service_documentation(booking: &Booking, trail: &[WalkBreadcrumb]) -> String {
+    let mut lines = vec![
+        format!(
+            "Booked {} for {} to {}.",
+            format_time(booking.created_at),
+            format_time(booking.scheduled_start),
+            format_time(booking.scheduled_end)
+        ),
+        format!("Booking status: {}.", booking.status),
+    ];
+    match (booking.actual_start, booking.actual_end) {
+        (Some(start), Some(end)) => lines.push(format!(
+            "The walker started the walk at {} and finished at {}.",
+            format_time(start),
+            format_time(end)
+        )),
+        (Some(start), None) => lines.push(format!(
+            "The walker started the walk at {}.",
+            format_time(start)
+        )),
+        _ => {}
+    }
+
+    let raw: Vec<TrackPoint> = trail.iter().map(TrackPoint::from).collect();
+    let stats = track_stats(&process_track(&raw, &TrackFilter::default()));
+    match (stats.started_at, stats.ended_at) {
+        (Some(start), Some(end)) => lines.push(format!(
+            "GPS trail: {} locations recorded from {} to {}, covering {:.2} km.",
+            raw.len(),
+            format_time(start),
+            format_time(end),
+            stats.distance_meters / 1000.0
+        )),
+        _ => lines.push("No GPS trail was recorded for the walk.".to_string()),
+    }
+
+    lines.join("\n")
+}
+
+fn describe_policy(policy: &CancellationPolicy) -> String {
+    let mut tiers = policy.tiers.0.clone();
+    tiers.sort_by_key(|t| std::cmp::Reverse(t.within_minutes));
+
+    let mut lines: Vec<String> = tiers
+        .iter()
+        .map(|tier| {
+            format!(
+                "Cancellations within {} of the start are charged {}% of the price.",
+                describe_minutes(tier.within_minutes),
+                tier.fee_percent
+            )
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push("Bookings can be cancelled at any time without charge.".to_string());
+    }
+    if policy.charge_reschedules {
+        lines.push("Late reschedules are charged the same as cancellations.".to_string());
+    }
+    if policy.walker_cancels_free {
+        lines.push("Cancellations by the walker are never charged.".to_string());
+    }
+    lines.join("\n")
+}
+
+fn describe_minutes(minutes: i64) -> String {
+    match minutes {
+        m if m % (24 * 60) == 0 => plural(m / (24 * 60), "day"),
+        m if m % 60 == 0 => plural(m / 60, "hour"),
+        m => plural(m, "minute"),
+    }
+}
+
+fn plural(count: i64, unit: &str) -> String {
+    if count == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", count, unit)
+    }
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M UTC").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{BookingStatus, CancellationPolicyTier, TransactionStatus, UserRole};
+    use chrono::TimeZone;
+    use shared::types::{BookingId, LocationId, ServiceId, UserId};
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 4, hour, minute, 0).unwrap()
+    }
+
+    fn dispute(due_in: Option<Duration>, last_reminder_days: Option<i32>) -> Dispute {
+        Dispute {
+            id: Uuid::new_v4(),
+            organization_id: OrganizationId::new(),
+            transaction_id: Uuid::new_v4(),
+            amount_cents: 4000,
+            currency: Currency::USD,
+            stripe_dispute_id: Some("dp_123".to_string()),
+            square_dispute_id: None,
+            reason: "product_not_received".to_string(),
+            status: DisputeStatus::NeedsResponse,
+            evidence_submitted: false,
+            evidence_due_by: due_in.map(|d| at(12, 0) + d),
+            evidence: None,
+            evidence_submitted_at: None,
+            last_reminder_days,
+            resolved_at: None,
+            outcome: None,
+            metadata: None,
+            created_at: at(9, 0),
+            updated_at: at(9, 0),
+        }
+    }
+
+    fn transaction() -> Transaction {
+        Transaction {
+            id: Uuid::new_v4(),
+            organization_id: OrganizationId::new(),
+            booking_id: None,
+            customer_user_id: UserId::new(),
+            provider_user_id: Some(UserId::new()),
+            payment_method_id: None,
+            provider_id: Uuid::new_v4(),
+            subtotal_cents: 3500,
+            tip_cents: 500,
+            customer_fee_cents: 0,
+            provider_fee_cents: 0,
+            platform_fee_cents: 0,
+            tax_cents: 0,
+            processing_fee_cents: 0,
+            total_cents: 4000,
+            provider_payout_cents: 4000,
+            processing_fee_reported: false,
+            currency: Currency::USD,
+            status: TransactionStatus::Disputed,
+            external_payment_id: None,
+            stripe_payment_intent_id: None,
+            stripe_charge_id: None,
+            stripe_transfer_id: None,
+            square_payment_id: None,
+            square_order_id: None,
+            tax_rate_percent: None,
+            tax_jurisdiction: None,
+            tax_calculation_id: None,
+            tax_reported_at: None,
+            refunded_amount_cents: 0,
+            authorized_at: None,
+            authorization_expires_at: None,
+            captured_at: None,
+            idempotency_key: None,
+            manual_capture: false,
+            off_session: false,
+            failure_code: None,
+            failure_message: None,
+            description: None,
+            metadata: None,
+            created_at: at(8, 0),
+            updated_at: at(8, 0),
+        }
+    }
+
+    fn booking() -> Booking {
+        Booking {
+            id: BookingId::new(),
+            organization_id: None,
+            customer_id: UserId::new(),
+            walker_id: UserId::new(),
+            service_id: ServiceId::new(),
+            location_id: LocationId::new(),
+            status: BookingStatus::Completed,
+            scheduled_start: at(10, 0),
+            scheduled_end: at(10, 30),
+            actual_start: Some(at(10, 2)),
+            actual_end: Some(at(10, 31)),
+            price_cents: 3500,
+            price_breakdown: None,
+            notes: Some("Please use the side gate".to_string()),
+            recurring_series_id: None,
+            occurrence_number: None,
+            created_at: at(8, 0),
+            updated_at: at(10, 31),
+        }
+    }
+
+    fn breadcrumb(booking: &Booking, minute: u32, latitude: f64) -> WalkBreadcrumb {
+        WalkBreadcrumb {
+            id: Uuid::new_v4(),
+            organization_id: OrganizationId::new(),
+            booking_id: booking.id,
+            walker_id: booking.walker_id,
+            latitude,
+            longitude: -122.0,
+            accuracy_meters: Some(5.0),
+            speed_mps: None,
+            recorded_at: at(10, minute),
+            created_at: at(10, minute),
+        }
+    }
+
+    #[test]
+    fn test_status_from_provider() {
+        assert_eq!(
+            DisputeStatus::from_provider("warning_needs_response"),
+            DisputeStatus::NeedsResponse
+        );
+        assert_eq!(
+            DisputeStatus::from_provider("EVIDENCE_REQUIRED"),
+            DisputeStatus::NeedsResponse
+        );
+        assert_eq!(
+            DisputeStatus::from_provider("PROCESSING"),
+            DisputeStatus::UnderReview
+        );
+        assert_eq!(
+            DisputeStatus::from_provider("warning_closed"),
+            DisputeStatus::Won
+        );
+        assert_eq!(
+            DisputeStatus::from_provider("ACCEPTED"),
+            DisputeStatus::Lost
+        );
+        assert_eq!(DisputeStatus::from_provider("lost"), DisputeStatus::Lost);
+    }
+
+    #[test]
+    fn test_reminders_step_down_as_the_due_date_nears() {
+        let now = at(12, 0);
+        assert_eq!(
+            dispute(Some(Duration::days(10)), None).reminder_due(now),
+            None
+        );
+        assert_eq!(
+            dispute(Some(Duration::days(6)), None).reminder_due(now),
+            Some(7)
+        );
+        assert_eq!(
+            dispute(Some(Duration::days(6)), Some(7)).reminder_due(now),
+            None
+        );
+        assert_eq!(
+            dispute(Some(Duration::days(2)), Some(7)).reminder_due(now),
+            Some(3)
+        );
+        assert_eq!(
+            dispute(Some(Duration::hours(5)), None).reminder_due(now),
+            Some(1)
+        );
+        assert_eq!(
+            dispute(Some(Duration::hours(5)), Some(1)).reminder_due(now),
+            None
+        );
+        assert_eq!(
+            dispute(Some(Duration::hours(-1)), None).reminder_due(now),
+            None
+        );
+        assert_eq!(dispute(None, None).reminder_due(now), None);
+
+        let mut submitted = dispute(Some(Duration::days(2)), None);
+        submitted.evidence_submitted = true;
+        assert_eq!(submitted.reminder_due(now), None);
+    }
+
+    #[test]
+    fn test_assembles_evidence_from_the_booking() {
+        let txn = transaction();
+        let booking = booking();
+        let customer = User {
+            id: booking.customer_id,
+            organization_id: OrganizationId::new(),
+            email: "pat@example.com".to_string(),
+            password_hash: String::new(),
+            role: UserRole::Customer,
+            first_name: "Pat".to_string(),
+            last_name: "Lee".to_string(),
+            phone: None,
+            timezone: "UTC".to_string(),
+            created_at: at(8, 0),
+            updated_at: at(8, 0),
+        };
+        let service = Service {
+            id: booking.service_id,
+            organization_id: OrganizationId::new(),
+            name: "Solo walk".to_string(),
+            description: Some("A walk for one dog".to_string()),
+            duration_minutes: 30,
+            base_price_cents: 3500,
+            currency: Currency::USD,
+            is_active: true,
+            created_at: at(8, 0),
+            updated_at: at(8, 0),
+        };
+        let policy = CancellationPolicy {
+            id: Uuid::new_v4(),
+            organization_id: OrganizationId::new(),
+            service_id: None,
+            tiers: sqlx::types::Json(vec![
+                CancellationPolicyTier {
+                    within_minutes: 120,
+                    fee_percent: 50,
+                },
+                CancellationPolicyTier {
+                    within_minutes: 24 * 60,
+                    fee_percent: 25,
+                },
+            ]),
+            walker_cancels_free: true,
+            charge_reschedules: false,
+            created_at: at(8, 0),
+            updated_at: at(8, 0),
+        };
+        let trail: Vec<WalkBreadcrumb> = (0..10)
+            .map(|i| breadcrumb(&booking, 2 + i * 3, 37.0 + f64::from(i) * 0.001))
+            .collect();
+
+        let evidence = DisputeEvidence::assemble(EvidenceSources {
+            transaction: &txn,
+            booking: Some(&booking),
+            service: Some(&service),
+            customer: Some(&customer),
+            trail: &trail,
+            policy: Some(&policy),
+        });
+
+        assert_eq!(evidence.customer_name.as_deref(), Some("Pat Lee"));
+        assert_eq!(evidence.customer_email.as_deref(), Some("pat@example.com"));
+        assert_eq!(
+            evidence.product_description.as_deref(),
+            Some("Solo walk (30 minutes): A walk for one dog")
+        );
+        assert_eq!(evidence.service_date.as_deref(), Some("2024-03-04"));
+
+        let documentation = evidence.service_documentation.unwrap();
+        assert!(documentation.contains("for 2024-03-04 10:00 UTC to 2024-03-04 10:30 UTC"));
+        assert!(documentation.contains("started the walk at 2024-03-04 10:02 UTC"));
+        assert!(documentation.contains("GPS trail: 10 locations recorded from"));
+
+        assert_eq!(
+            evidence.customer_communication.as_deref(),
+            Some("Customer's notes on the booking: Please use the side gate")
+        );
+        assert!(evidence.receipt.unwrap().contains("tip $5.00"));
+        assert_eq!(
+            evidence.cancellation_policy.as_deref(),
+            Some(
+                "Cancellations within 1 day of the start are charged 25% of the price.\n\
+                 Cancellations within 2 hours of the start are charged 50% of the price.\n\
+                 Cancellations by the walker are never charged."
+            )
+        );
+    }
+
+    #[test]
+    fn test_evidence_without_a_booking() {
+        let txn = transaction();
+        let evidence = DisputeEvidence::assemble(EvidenceSources {
+            transaction: &txn,
+            booking: None,
+            service: None,
+            customer: None,
+            trail: &[],
+            policy: None,
+        });
+
+        assert_eq!(evidence.service_documentation, None);
+        assert_eq!(evidence.customer_communication, None);
+        assert!(evidence
+            .receipt
+            .unwrap()
+            .starts_with("Charged $40.00 on 2024-03-04"));
+    }
 }
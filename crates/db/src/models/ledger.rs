@@ -0,0 +1,770 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use shared::types::{Currency, OrganizationId, UserId};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use super::{Dispute, Payout, Refund, RefundAllocation, Transaction, WalkerPayout};
+
+/// Ledger account type enum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "ledger_account_type", rename_all = "snake_case")]
+pub enum LedgerAccountType {
+    /// Owed to the platform by customers until the processor settles it
+    CustomerReceivable,
+    /// Owed to walkers (or the organization) until paid out
+    WalkerPayable,
+    /// Fees the platform has earned
+    PlatformRevenue,
+    /// Tax collected on behalf of a jurisdiction
+    TaxPayable,
+    /// Processing fees paid to Stripe or Square
+    ProcessorFees,
+    /// Funds held by the payment processor
+    ProcessorBalance,
+}
+
+impl std::fmt::Display for LedgerAccountType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LedgerAccountType::CustomerReceivable => write!(f, "customer_receivable"),
+            LedgerAccountType::WalkerPayable => write!(f, "walker_payable"),
+            LedgerAccountType::PlatformRevenue => write!(f, "platform_revenue"),
+            LedgerAccountType::TaxPayable => write!(f, "tax_payable"),
+            LedgerAccountType::ProcessorFees => write!(f, "processor_fees"),
+            LedgerAccountType::ProcessorBalance => write!(f, "processor_balance"),
+        }
+    }
+}
+
+impl LedgerAccountType {
+    /// Whether the account's balance grows with debits (assets and expenses)
+    /// rather than credits (liabilities and revenue)
+    pub fn is_debit_normal(&self) -> bool {
+        matches!(
+            self,
+            LedgerAccountType::CustomerReceivable
+                | LedgerAccountType::ProcessorFees
+                | LedgerAccountType::ProcessorBalance
+        )
+    }
+}
+
+/// What a journal entry records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "journal_entry_kind", rename_all = "snake_case")]
+pub enum JournalEntryKind {
+    Charge,
+    Refund,
+    DisputeOpened,
+    DisputeWon,
+    DisputeLost,
+    Payout,
+    PayoutFailed,
+    WalkerTransfer,
+    WalkerTransferReversed,
+}
+
+impl std::fmt::Display for JournalEntryKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JournalEntryKind::Charge => write!(f, "charge"),
+            JournalEntryKind::Refund => write!(f, "refund"),
+            JournalEntryKind::DisputeOpened => write!(f, "dispute_opened"),
+            JournalEntryKind::DisputeWon => write!(f, "dispute_won"),
+            JournalEntryKind::DisputeLost => write!(f, "dispute_lost"),
+            JournalEntryKind::Payout => write!(f, "payout"),
+            JournalEntryKind::PayoutFailed => write!(f, "payout_failed"),
+            JournalEntryKind::WalkerTransfer => write!(f, "walker_transfer"),
+            JournalEntryKind::WalkerTransferReversed => write!(f, "walker_transfer_reversed"),
+        }
+    }
+}
+
+/// Ledger account database model
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct LedgerAccount {
+    pub id: Uuid,
+    pub organization_id: OrganizationId,
+    pub account_type: LedgerAccountType,
+    pub walker_id: Option<UserId>,
+    pub currency: Currency,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Journal entry database model
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: Uuid,
+    pub organization_id: OrganizationId,
+    pub kind: JournalEntryKind,
+    /// The transaction, refund, dispute or payout being recorded
+    pub source_id: Uuid,
+    pub transaction_id: Option<Uuid>,
+    pub description: String,
+    pub currency: Currency,
+    pub occurred_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Journal line database model
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct JournalLine {
+    pub id: Uuid,
+    pub journal_entry_id: Uuid,
+    pub account_id: Uuid,
+    pub debit_cents: i64,
+    pub credit_cents: i64,
+}
+
+/// Identifies a ledger account within an organization
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LedgerAccountKey {
+    pub account_type: LedgerAccountType,
+    /// Only set for walker payable accounts
+    pub walker_id: Option<UserId>,
+}
+
+impl LedgerAccountKey {
+    pub fn new(account_type: LedgerAccountType) -> Self {
+        Self {
+            account_type,
+            walker_id: None,
+        }
+    }
+
+    pub fn walker(walker_id: UserId) -> Self {
+        Self::payable_to(Some(walker_id))
+    }
+
+    /// Owed to a walker, or to the organization when there isn't one
+    pub fn payable_to(walker_id: Option<UserId>) -> Self {
+        Self {
+            account_type: LedgerAccountType::WalkerPayable,
+            walker_id,
+        }
+    }
+}
+
+/// A line of a journal entry that has not been posted yet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NewJournalLine {
+    pub account: LedgerAccountKey,
+    pub debit_cents: i64,
+    pub credit_cents: i64,
+}
+
+/// A journal entry ready to post.
+///
+/// Entries can only be built from the payment records they describe, and
+/// every constructor produces lines whose debits equal their credits.
+#[derive(Debug, Clone)]
+pub struct NewJournalEntry {
+    pub kind: JournalEntryKind,
+    pub source_id: Uuid,
+    pub transaction_id: Option<Uuid>,
+    pub description: String,
+    pub currency: Currency,
+    pub occurred_at: DateTime<Utc>,
+    lines: Vec<NewJournalLine>,
+}
+
+impl NewJournalEntry {
+    fn new(
+        kind: JournalEntryKind,
+        source_id: Uuid,
+        transaction_id: Option<Uuid>,
+        description: String,
+        currency: Currency,
+        occurred_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            kind,
+            source_id,
+            transaction_id,
+            description,
+            currency,
+            occurred_at,
+            lines: Vec::new(),
+        }
+    }
+
+    /// A captured charge.
+    ///
+    /// The customer owes the full total, which is split between the walker,
+    /// the platform and the tax authority, and is then settled by the
+    /// processor less its processing fee. The platform keeps whatever is left
+    /// after the walker's payout and tax, which is its platform fee.
+    pub fn charge(transaction: &Transaction) -> Self {
+        let total = i64::from(transaction.total_cents);
+        let payout = i64::from(transaction.provider_payout_cents);
+        let tax = i64::from(transaction.tax_cents);
+        let processing = i64::from(transaction.processing_fee_cents);
+        let receivable = LedgerAccountKey::new(LedgerAccountType::CustomerReceivable);
+        let processor = LedgerAccountKey::new(LedgerAccountType::ProcessorBalance);
+
+        Self::new(
+            JournalEntryKind::Charge,
+            transaction.id,
+            Some(transaction.id),
+            format!("Charge {}", transaction.id),
+            transaction.currency,
+            transaction.captured_at.unwrap_or(transaction.updated_at),
+        )
+        .debit(receivable, total)
+        .credit(
+            LedgerAccountKey::payable_to(transaction.provider_user_id),
+            payout,
+        )
+        .credit(
+            LedgerAccountKey::new(LedgerAccountType::PlatformRevenue),
+            total - payout - tax,
+        )
+        .credit(LedgerAccountKey::new(LedgerAccountType::TaxPayable), tax)
+        .debit(processor, total)
+        .credit(receivable, total)
+        .debit(
+            LedgerAccountKey::new(LedgerAccountType::ProcessorFees),
+            processing,
+        )
+        .credit(processor, processing)
+    }
+
+    /// A refund paid back to the customer out of the processor balance,
+    /// reversing the walker's payout, platform fee and tax as allocated when
+    /// the refund was created
+    pub fn refund(transaction: &Transaction, refund: &Refund) -> Self {
+        let allocation = RefundAllocation {
+            subtotal_cents: refund.subtotal_refunded_cents,
+            tip_cents: refund.tip_refunded_cents,
+            customer_fee_cents: refund.customer_fee_refunded_cents,
+            tax_cents: refund.tax_refunded_cents,
+            platform_fee_cents: refund.platform_fee_reversed_cents,
+            provider_payout_cents: refund.provider_payout_reversed_cents,
+        };
+
+        Self::new(
+            JournalEntryKind::Refund,
+            refund.id,
+            Some(transaction.id),
+            format!("Refund {} of charge {}", refund.id, transaction.id),
+            refund.currency,
+            refund.updated_at,
+        )
+        .reverse_allocation(transaction, &allocation, i64::from(refund.amount_cents))
+        .credit(
+            LedgerAccountKey::new(LedgerAccountType::ProcessorBalance),
+            i64::from(refund.amount_cents),
+        )
+    }
+
+    /// Disputed funds withdrawn by the processor. The customer owes them
+    /// again until the dispute is decided.
+    pub fn dispute_opened(dispute: &Dispute) -> Self {
+        let amount = i64::from(dispute.amount_cents);
+        Self::new(
+            JournalEntryKind::DisputeOpened,
+            dispute.id,
+            Some(dispute.transaction_id),
+            format!("Dispute {} opened", dispute.id),
+            dispute.currency,
+            dispute.created_at,
+        )
+        .debit(
+            LedgerAccountKey::new(LedgerAccountType::CustomerReceivable),
+            amount,
+        )
+        .credit(
+            LedgerAccountKey::new(LedgerAccountType::ProcessorBalance),
+            amount,
+        )
+    }
+
+    /// A won dispute returns the withdrawn funds
+    pub fn dispute_won(dispute: &Dispute) -> Self {
+        let amount = i64::from(dispute.amount_cents);
+        Self::new(
+            JournalEntryKind::DisputeWon,
+            dispute.id,
+            Some(dispute.transaction_id),
+            format!("Dispute {} won", dispute.id),
+            dispute.currency,
+            dispute.resolved_at.unwrap_or(dispute.updated_at),
+        )
+        .debit(
+            LedgerAccountKey::new(LedgerAccountType::ProcessorBalance),
+            amount,
+        )
+        .credit(
+            LedgerAccountKey::new(LedgerAccountType::CustomerReceivable),
+            amount,
+        )
+    }
+
+    /// A lost dispute writes off the receivable against the charge, split
+    /// the same way a refund of that amount would be
+    pub fn dispute_lost(transaction: &Transaction, dispute: &Dispute) -> Self {
+        let amount = i64::from(dispute.amount_cents);
+        let allocation = RefundAllocation::for_refund(transaction, dispute.amount_cents);
+
+        Self::new(
+            JournalEntryKind::DisputeLost,
+            dispute.id,
+            Some(transaction.id),
+            format!("Dispute {} lost", dispute.id),
+            dispute.currency,
+            dispute.resolved_at.unwrap_or(dispute.updated_at),
+        )
+        .reverse_allocation(transaction, &allocation, amount)
+        .credit(
+            LedgerAccountKey::new(LedgerAccountType::CustomerReceivable),
+            amount,
+        )
+    }
+
+    /// Funds paid out to the organization's bank account
+    pub fn payout(payout: &Payout) -> Self {
+        let amount = i64::from(payout.amount_cents);
+        Self::new(
+            JournalEntryKind::Payout,
+            payout.id,
+            None,
+            format!("Payout {}", payout.id),
+            payout.currency,
+            payout.created_at,
+        )
+        .debit(
+            LedgerAccountKey::new(LedgerAccountType::WalkerPayable),
+            amount,
+        )
+        .credit(
+            LedgerAccountKey::new(LedgerAccountType::ProcessorBalance),
+            amount,
+        )
+    }
+
+    /// A failed payout returns the funds to the processor balance
+    pub fn payout_failed(payout: &Payout) -> Self {
+        let amount = i64::from(payout.amount_cents);
+        Self::new(
+            JournalEntryKind::PayoutFailed,
+            payout.id,
+            None,
+            format!("Payout {} failed", payout.id),
+            payout.currency,
+            payout.updated_at,
+        )
+        .debit(
+            LedgerAccountKey::new(LedgerAccountType::ProcessorBalance),
+            amount,
+        )
+        .credit(
+            LedgerAccountKey::new(LedgerAccountType::WalkerPayable),
+            amount,
+        )
+    }
+
+    /// Funds transferred to a walker's connected account
+    pub fn walker_transfer(payout: &WalkerPayout) -> Self {
+        let amount = i64::from(payout.amount_cents);
+        Self::new(
+            JournalEntryKind::WalkerTransfer,
+            payout.id,
+            None,
+            format!("Transfer of walker payout {}", payout.id),
+            payout.currency,
+            payout.initiated_at.unwrap_or(payout.updated_at),
+        )
+        .debit(LedgerAccountKey::walker(payout.walker_id), amount)
+        .credit(
+            LedgerAccountKey::new(LedgerAccountType::ProcessorBalance),
+            amount,
+        )
+    }
+
+    /// A reversed transfer returns the funds to the processor balance, owed
+    /// to the walker again
+    pub fn walker_transfer_reversed(payout: &WalkerPayout) -> Self {
+        let amount = i64::from(payout.amount_cents);
+        Self::new(
+            JournalEntryKind::WalkerTransferReversed,
+            payout.id,
+            None,
+            format!("Transfer of walker payout {} reversed", payout.id),
+            payout.currency,
+            payout.updated_at,
+        )
+        .debit(
+            LedgerAccountKey::new(LedgerAccountType::ProcessorBalance),
+            amount,
+        )
+        .credit(LedgerAccountKey::walker(payout.walker_id), amount)
+    }
+
+    /// Debit the walker, platform and tax accounts for an allocated refund.
+    /// The platform absorbs any rounding difference so the debits always
+    /// total `amount`.
+    fn reverse_allocation(
+        self,
+        transaction: &Transaction,
+        allocation: &RefundAllocation,
+        amount: i64,
+    ) -> Self {
+        let payout = i64::from(allocation.provider_payout_cents);
+        let tax = i64::from(allocation.tax_cents);
+
+        self.debit(
+            LedgerAccountKey::payable_to(transaction.provider_user_id),
+            payout,
+        )
+        .debit(
+            LedgerAccountKey::new(LedgerAccountType::PlatformRevenue),
+            amount - payout - tax,
+        )
+        .debit(LedgerAccountKey::new(LedgerAccountType::TaxPayable), tax)
+    }
+
+    fn debit(self, account: LedgerAccountKey, cents: i64) -> Self {
+        self.line(account, cents, 0)
+    }
+
+    fn credit(self, account: LedgerAccountKey, cents: i64) -> Self {
+        self.line(account, 0, cents)
+    }
+
+    /// Add a line, flipping negative amounts to the other side and skipping
+    /// zero amounts
+    fn line(mut self, account: LedgerAccountKey, debit_cents: i64, credit_cents: i64) -> Self {
+        let net = debit_cents - credit_cents;
+        if net != 0 {
+            self.lines.push(NewJournalLine {
+                account,
+                debit_cents: net.max(0),
+                credit_cents: (-net).max(0),
+            });
+        }
+        self
+    }
+
+    pub fn lines(&self) -> &[NewJournalLine] {
+        &self.lines
+    }
+
+    pub fn total_debits_cents(&self) -> i64 {
+        self.lines.iter().map(|l| l.debit_cents).sum()
+    }
+
+    pub fn total_credits_cents(&self) -> i64 {
+        self.lines.iter().map(|l| l.credit_cents).sum()
+    }
+
+    pub fn is_balanced(&self) -> bool {
+        self.total_debits_cents() == self.total_credits_cents()
+    }
+}
+
+/// Posted totals for one ledger account
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct LedgerAccountBalance {
+    pub account_type: LedgerAccountType,
+    pub walker_id: Option<UserId>,
+    pub currency: Currency,
+    pub debit_cents: i64,
+    pub credit_cents: i64,
+}
+
+impl LedgerAccountBalance {
+    /// Balance on the account's normal side, so a positive balance is money
+    /// held for asset and expense accounts and money owed for the rest
+    pub fn balance_cents(&self) -> i64 {
+        if self.account_type.is_debit_normal() {
+            self.debit_cents - self.credit_cents
+        } else {
+            self.credit_cents - self.debit_cents
+        }
+    }
+}
+
+/// Total debits and credits posted in one currency
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct LedgerCurrencyTotals {
+    pub currency: Currency,
+    pub total_debits_cents: i64,
+    pub total_credits_cents: i64,
+}
+
+impl LedgerCurrencyTotals {
+    pub fn is_balanced(&self) -> bool {
+        self.total_debits_cents == self.total_credits_cents
+    }
+}
+
+/// Result of checking that an organization's ledger balances.
+///
+/// Amounts in different currencies never offset each other, so the ledger
+/// has to balance in each currency on its own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerCheck {
+    /// One row per currency with postings
+    pub currencies: Vec<LedgerCurrencyTotals>,
+    /// Entries whose own debits and credits differ, or that post to an
+    /// account in another currency
+    pub unbalanced_entry_ids: Vec<Uuid>,
+}
+
+impl LedgerCheck {
+    pub fn is_balanced(&self) -> bool {
+        self.currencies
+            .iter()
+            .all(LedgerCurrencyTotals::is_balanced)
+            && self.unbalanced_entry_ids.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{DisputeStatus, RefundSource, RefundStatus, TransactionStatus};
+
+    fn transaction() -> Transaction {
+        Transaction {
+            id: Uuid::new_v4(),
+            organization_id: OrganizationId::new(),
+            booking_id: None,
+            customer_user_id: UserId::new(),
+            provider_user_id: Some(UserId::new()),
+            payment_method_id: None,
+            provider_id: Uuid::new_v4(),
+            subtotal_cents: 4000,
+            tip_cents: 500,
+            customer_fee_cents: 120,
+            provider_fee_cents: 200,
+            platform_fee_cents: 320,
+            tax_cents: 333,
+            processing_fee_cents: 171,
+            total_cents: 4953,
+            provider_payout_cents: 4300,
+            processing_fee_reported: false,
+            currency: Currency::USD,
+            status: TransactionStatus::Succeeded,
+            external_payment_id: None,
+            stripe_payment_intent_id: None,
+            stripe_charge_id: None,
+            stripe_transfer_id: None,
+            square_payment_id: None,
+            square_order_id: None,
+            tax_rate_percent: None,
+            tax_jurisdiction: None,
+            tax_calculation_id: None,
+            tax_reported_at: None,
+            refunded_amount_cents: 0,
+            authorized_at: None,
+            authorization_expires_at: None,
+            captured_at: Some(Utc::now()),
+            idempotency_key: None,
+            manual_capture: false,
+            off_session: false,
+            failure_code: None,
+            failure_message: None,
+            description: None,
+            metadata: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn dispute(txn: &Transaction, amount_cents: i32) -> Dispute {
+        Dispute {
+            id: Uuid::new_v4(),
+            organization_id: txn.organization_id,
+            transaction_id: txn.id,
+            amount_cents,
+            currency: txn.currency,
+            stripe_dispute_id: None,
+            square_dispute_id: None,
+            reason: "fraudulent".to_string(),
+            status: DisputeStatus::Lost,
+            evidence_submitted: false,
+            evidence_due_by: None,
+            evidence: None,
+            evidence_submitted_at: None,
+            last_reminder_days: None,
+            resolved_at: None,
+            outcome: None,
+            metadata: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Net balance of one account across several entries
+    fn balance(entries: &[&NewJournalEntry], account: LedgerAccountKey) -> i64 {
+        entries
+            .iter()
+            .flat_map(|e| e.lines())
+            .filter(|l| l.account == account)
+            .map(|l| l.credit_cents - l.debit_cents)
+            .sum()
+    }
+
+    #[test]
+    fn test_charge_is_balanced() {
+        let txn = transaction();
+        let entry = NewJournalEntry::charge(&txn);
+
+        assert!(entry.is_balanced());
+        assert_eq!(
+            balance(
+                &[&entry],
+                LedgerAccountKey::payable_to(txn.provider_user_id)
+            ),
+            4300
+        );
+        assert_eq!(
+            balance(
+                &[&entry],
+                LedgerAccountKey::new(LedgerAccountType::PlatformRevenue)
+            ),
+            320
+        );
+        assert_eq!(
+            balance(
+                &[&entry],
+                LedgerAccountKey::new(LedgerAccountType::CustomerReceivable)
+            ),
+            0
+        );
+    }
+
+    #[test]
+    fn test_full_refund_clears_charge() {
+        let txn = transaction();
+        let allocation = RefundAllocation::for_refund(&txn, txn.total_cents);
+        let refund = Refund {
+            id: Uuid::new_v4(),
+            organization_id: txn.organization_id,
+            transaction_id: txn.id,
+            amount_cents: txn.total_cents,
+            currency: txn.currency,
+            reason: None,
+            status: RefundStatus::Succeeded,
+            source: RefundSource::Admin,
+            initiated_by: None,
+            provider_refund_id: None,
+            subtotal_refunded_cents: allocation.subtotal_cents,
+            tip_refunded_cents: allocation.tip_cents,
+            customer_fee_refunded_cents: allocation.customer_fee_cents,
+            tax_refunded_cents: allocation.tax_cents,
+            platform_fee_reversed_cents: allocation.platform_fee_cents,
+            provider_payout_reversed_cents: allocation.provider_payout_cents,
+            failure_message: None,
+            tax_reported_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let charge = NewJournalEntry::charge(&txn);
+        let refund = NewJournalEntry::refund(&txn, &refund);
+        let entries = [&charge, &refund];
+
+        assert!(refund.is_balanced());
+        for account_type in [
+            LedgerAccountType::WalkerPayable,
+            LedgerAccountType::PlatformRevenue,
+            LedgerAccountType::TaxPayable,
+        ] {
+            let account = match account_type {
+                LedgerAccountType::WalkerPayable => {
+                    LedgerAccountKey::payable_to(txn.provider_user_id)
+                }
+                other => LedgerAccountKey::new(other),
+            };
+            assert_eq!(balance(&entries, account), 0);
+        }
+        // Only the processing fee is lost
+        assert_eq!(
+            balance(
+                &entries,
+                LedgerAccountKey::new(LedgerAccountType::ProcessorBalance)
+            ),
+            171
+        );
+    }
+
+    #[test]
+    fn test_disputes_and_payouts_are_balanced() {
+        let txn = transaction();
+        let dispute = dispute(&txn, 2000);
+
+        let opened = NewJournalEntry::dispute_opened(&dispute);
+        let lost = NewJournalEntry::dispute_lost(&txn, &dispute);
+        assert!(opened.is_balanced());
+        assert!(lost.is_balanced());
+        assert!(NewJournalEntry::dispute_won(&dispute).is_balanced());
+        assert_eq!(
+            balance(
+                &[&opened, &lost],
+                LedgerAccountKey::new(LedgerAccountType::CustomerReceivable)
+            ),
+            0
+        );
+
+        let payout = Payout {
+            id: Uuid::new_v4(),
+            organization_id: txn.organization_id,
+            amount_cents: 4300,
+            fee_cents: 0,
+            net_amount_cents: 4300,
+            currency: Currency::USD,
+            period_start: Utc::now(),
+            period_end: Utc::now(),
+            stripe_payout_id: None,
+            stripe_transfer_id: None,
+            square_payout_id: None,
+            status: crate::models::PayoutStatus::Pending,
+            initiated_at: None,
+            arrival_date: None,
+            completed_at: None,
+            failure_code: None,
+            failure_message: None,
+            transaction_count: 1,
+            transaction_ids: vec![txn.id],
+            refund_ids: Vec::new(),
+            walker_earning_ids: Vec::new(),
+            scheduled_for: None,
+            metadata: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        assert!(NewJournalEntry::payout(&payout).is_balanced());
+        assert!(NewJournalEntry::payout_failed(&payout).is_balanced());
+    }
+
+    #[test]
+    fn test_currencies_must_balance_separately() {
+        let totals = |currency, debits, credits| LedgerCurrencyTotals {
+            currency,
+            total_debits_cents: debits,
+            total_credits_cents: credits,
+        };
+
+        // The grand totals match, but each currency is off
+        let check = LedgerCheck {
+            currencies: vec![
+                totals(Currency::CAD, 1000, 900),
+                totals(Currency::USD, 900, 1000),
+            ],
+            unbalanced_entry_ids: vec![],
+        };
+        assert!(!check.is_balanced());
+
+        let check = LedgerCheck {
+            currencies: vec![
+                totals(Currency::CAD, 1000, 1000),
+                totals(Currency::USD, 900, 900),
+            ],
+            unbalanced_entry_ids: vec![],
+        };
+        assert!(check.is_balanced());
+    }
+}
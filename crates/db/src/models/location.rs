@@ -14,6 +14,8 @@ pub struct Location {
     pub city: String,
     pub state: String,
     pub zip_code: String,
+    /// ISO 3166-1 alpha-2 country code
+    pub country: String,
     pub latitude: f64,
     pub longitude: f64,
     pub notes: Option<String>,
@@ -47,6 +49,7 @@ pub struct CreateLocation {
     pub city: String,
     pub state: String,
     pub zip_code: String,
+    pub country: String,
     pub latitude: f64,
     pub longitude: f64,
     pub notes: Option<String>,
@@ -61,6 +64,7 @@ pub struct UpdateLocation {
     pub city: Option<String>,
     pub state: Option<String>,
     pub zip_code: Option<String>,
+    pub country: Option<String>,
     pub latitude: Option<f64>,
     pub longitude: Option<f64>,
     pub notes: Option<String>,
@@ -1,9 +1,11 @@
 mod block;
 mod booking;
 mod calendar;
+mod cancellation_policy;
 mod customer_payment_method;
 mod dispute;
 mod invitation;
+mod ledger;
 mod location;
 mod membership;
 mod organization;
@@ -12,7 +14,10 @@ mod payment_provider;
 mod payout;
 mod pet;
 mod platform_admin;
+mod processing_fee_schedule;
+mod reconciliation;
 mod recurring_booking;
+mod refund;
 mod service;
 mod service_area;
 mod subscription;
@@ -21,15 +26,20 @@ mod transaction;
 mod travel_time;
 mod user;
 mod user_identity;
+mod walk_trail;
+mod walker_earning;
 mod walker_profile;
+mod webhook_event;
 mod working_hours;
 
 pub use block::*;
 pub use booking::*;
 pub use calendar::*;
+pub use cancellation_policy::*;
 pub use customer_payment_method::*;
 pub use dispute::*;
 pub use invitation::*;
+pub use ledger::*;
 pub use location::*;
 pub use membership::*;
 pub use organization::*;
@@ -38,7 +48,10 @@ pub use payment_provider::*;
 pub use payout::*;
 pub use pet::*;
 pub use platform_admin::*;
+pub use processing_fee_schedule::*;
+pub use reconciliation::*;
 pub use recurring_booking::*;
+pub use refund::*;
 pub use service::*;
 pub use service_area::*;
 pub use subscription::*;
@@ -47,5 +60,8 @@ pub use transaction::*;
 pub use travel_time::*;
 pub use user::*;
 pub use user_identity::*;
+pub use walk_trail::*;
+pub use walker_earning::*;
 pub use walker_profile::*;
+pub use webhook_event::*;
 pub use working_hours::*;
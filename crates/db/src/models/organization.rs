@@ -1,6 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use shared::types::OrganizationId;
+use shared::types::{Currency, OrganizationId};
 use sqlx::FromRow;
 
 /// Organization settings including branding configuration
@@ -16,6 +16,27 @@ pub struct OrganizationSettings {
     pub favicon_url: Option<String>,
     /// Custom font family for the organization
     pub font_family: Option<String>,
+    /// Days to keep GPS trails of completed walks (defaults to
+    /// [`DEFAULT_WALK_TRAIL_RETENTION_DAYS`](crate::models::DEFAULT_WALK_TRAIL_RETENTION_DAYS))
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub walk_trail_retention_days: Option<i32>,
+    /// Currency new services are priced in and payouts are made in
+    /// (defaults to USD)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_currency: Option<Currency>,
+}
+
+impl OrganizationSettings {
+    /// Effective walk trail retention period in days
+    pub fn walk_trail_retention_days(&self) -> i32 {
+        self.walk_trail_retention_days
+            .unwrap_or(crate::models::DEFAULT_WALK_TRAIL_RETENTION_DAYS)
+    }
+
+    /// Effective default currency
+    pub fn default_currency(&self) -> Currency {
+        self.default_currency.unwrap_or_default()
+    }
 }
 
 /// Organization database model
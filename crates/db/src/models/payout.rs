@@ -1,9 +1,11 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, Months, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
-use shared::types::OrganizationId;
+use shared::types::{Currency, OrganizationId};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use super::{Dispute, DisputeStatus, Refund, RefundAllocation, Transaction, WalkerEarning};
+
 /// Payout status enum
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "payout_status", rename_all = "snake_case")]
@@ -13,6 +15,9 @@ pub enum PayoutStatus {
     Paid,
     Failed,
     Canceled,
+    /// The provider pays the merchant on its own schedule, so there is no
+    /// provider payout to follow
+    Manual,
 }
 
 impl std::fmt::Display for PayoutStatus {
@@ -23,8 +28,33 @@ impl std::fmt::Display for PayoutStatus {
             PayoutStatus::Paid => write!(f, "paid"),
             PayoutStatus::Failed => write!(f, "failed"),
             PayoutStatus::Canceled => write!(f, "canceled"),
+            PayoutStatus::Manual => write!(f, "manual"),
+        }
+    }
+}
+
+impl PayoutStatus {
+    /// Map a Stripe or Square payout status
+    pub fn from_provider(status: &str) -> Self {
+        match status.to_lowercase().as_str() {
+            "paid" => PayoutStatus::Paid,
+            "in_transit" | "sent" => PayoutStatus::InTransit,
+            "failed" => PayoutStatus::Failed,
+            "canceled" | "cancelled" => PayoutStatus::Canceled,
+            _ => PayoutStatus::Pending,
         }
     }
+
+    /// Whether the payout has reached the bank or been given up on
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            PayoutStatus::Paid
+                | PayoutStatus::Failed
+                | PayoutStatus::Canceled
+                | PayoutStatus::Manual
+        )
+    }
 }
 
 /// Payout settings for tenants using platform default
@@ -85,6 +115,26 @@ impl PayoutSettings {
         self.is_verified
             && (self.stripe_bank_account_id.is_some() || self.square_bank_account_id.is_some())
     }
+
+    /// Whether a scheduled payout is due on `date`. Monthly payouts set past
+    /// the end of a short month are made on its last day.
+    pub fn is_due_on(&self, date: NaiveDate) -> bool {
+        match self.payout_schedule.as_str() {
+            "daily" => true,
+            "weekly" => {
+                date.weekday().num_days_from_sunday() as i32 == self.payout_day_of_week.unwrap_or(1)
+            }
+            "monthly" => {
+                let last_day = date
+                    .with_day(1)
+                    .and_then(|first| first.checked_add_months(Months::new(1)))
+                    .and_then(|next| next.pred_opt())
+                    .map_or(31, |last| last.day() as i32);
+                date.day() as i32 == self.payout_day_of_month.unwrap_or(1).clamp(1, last_day)
+            }
+            _ => false,
+        }
+    }
 }
 
 /// Input for creating payout settings
@@ -122,7 +172,7 @@ pub struct Payout {
     pub amount_cents: i32,
     pub fee_cents: i32,
     pub net_amount_cents: i32,
-    pub currency: String,
+    pub currency: Currency,
     pub period_start: DateTime<Utc>,
     pub period_end: DateTime<Utc>,
     pub stripe_payout_id: Option<String>,
@@ -136,6 +186,12 @@ pub struct Payout {
     pub failure_message: Option<String>,
     pub transaction_count: i32,
     pub transaction_ids: Vec<Uuid>,
+    /// Refunds the payout deducted
+    pub refund_ids: Vec<Uuid>,
+    /// Walker earnings transferred to walkers that the payout deducted
+    pub walker_earning_ids: Vec<Uuid>,
+    /// Day a scheduled payout was made for; `None` for instant payouts
+    pub scheduled_for: Option<NaiveDate>,
     pub metadata: Option<serde_json::Value>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -156,6 +212,16 @@ impl Payout {
     pub fn can_retry(&self) -> bool {
         self.status == PayoutStatus::Failed
     }
+
+    /// Key the provider deduplicates sends of this payout by. A scheduled
+    /// payout is identified by its organization, day and currency, whichever
+    /// server sends it.
+    pub fn idempotency_key(&self) -> String {
+        match self.scheduled_for {
+            Some(date) => format!("payout_{}_{}_{}", self.organization_id, date, self.currency),
+            None => format!("payout_{}", self.id),
+        }
+    }
 }
 
 /// Input for creating a payout
@@ -164,11 +230,14 @@ pub struct CreatePayout {
     pub amount_cents: i32,
     pub fee_cents: i32,
     pub net_amount_cents: i32,
-    pub currency: String,
+    pub currency: Currency,
     pub period_start: DateTime<Utc>,
     pub period_end: DateTime<Utc>,
     pub transaction_count: i32,
     pub transaction_ids: Vec<Uuid>,
+    pub refund_ids: Vec<Uuid>,
+    pub walker_earning_ids: Vec<Uuid>,
+    pub scheduled_for: Option<NaiveDate>,
 }
 
 /// Input for updating a payout
@@ -186,6 +255,163 @@ pub struct UpdatePayout {
     pub metadata: Option<serde_json::Value>,
 }
 
+/// Transactions paid out together, in one currency, by a scheduled payout
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayoutBatch {
+    pub currency: Currency,
+    pub amount_cents: i64,
+    pub transaction_ids: Vec<Uuid>,
+    /// Refunds the batch deducts
+    pub refund_ids: Vec<Uuid>,
+    /// Walker earnings the batch deducts
+    pub walker_earning_ids: Vec<Uuid>,
+    /// When the earliest transaction in the batch was made
+    pub period_start: DateTime<Utc>,
+}
+
+impl PayoutBatch {
+    /// What a transaction adds to a payout: the provider's share, less what
+    /// refunds and a lost dispute took back. Walker earnings paid out of it
+    /// by transfer are deducted separately, see [`PayoutBatch::collect`]. `None` while a dispute is open,
+    /// since the funds may still be withdrawn.
+    pub fn transaction_amount(
+        transaction: &Transaction,
+        refunds: &[Refund],
+        dispute: Option<&Dispute>,
+    ) -> Option<i64> {
+        let dispute_reversed = match dispute {
+            Some(d) if !d.is_resolved() => return None,
+            Some(d) if d.status == DisputeStatus::Lost => {
+                RefundAllocation::for_refund(transaction, d.amount_cents).provider_payout_cents
+            }
+            _ => 0,
+        };
+        let refund_reversed: i64 = refunds
+            .iter()
+            .filter(|r| r.status.is_outstanding())
+            .map(|r| i64::from(r.provider_payout_reversed_cents))
+            .sum();
+
+        Some(
+            (i64::from(transaction.provider_payout_cents)
+                - refund_reversed
+                - i64::from(dispute_reversed))
+            .max(0),
+        )
+    }
+
+    /// Group transactions and the amounts they add into one batch per
+    /// currency, with the refunds and transferred walker earnings deducted.
+    /// Refunds of a transaction in the batch are already counted in its
+    /// amount; the rest were made after their transaction was paid out and
+    /// come off the batch now. Walker earnings already left the balance in a
+    /// transfer to the walker, so they come off too (and negative earnings,
+    /// taken back from the walker, are added back).
+    pub fn collect<'a>(
+        amounts: impl IntoIterator<Item = (&'a Transaction, i64)>,
+        refunds: impl IntoIterator<Item = &'a Refund>,
+        walker_earnings: impl IntoIterator<Item = &'a WalkerEarning>,
+    ) -> Vec<Self> {
+        let mut batches: Vec<Self> = Vec::new();
+        for (transaction, amount_cents) in amounts {
+            let batch =
+                Self::for_currency(&mut batches, transaction.currency, transaction.created_at);
+            batch.amount_cents += amount_cents;
+            batch.transaction_ids.push(transaction.id);
+            batch.period_start = batch.period_start.min(transaction.created_at);
+        }
+        for refund in refunds {
+            let batch = Self::for_currency(&mut batches, refund.currency, refund.created_at);
+            if !batch.transaction_ids.contains(&refund.transaction_id) {
+                batch.amount_cents -= i64::from(refund.provider_payout_reversed_cents);
+            }
+            batch.refund_ids.push(refund.id);
+        }
+        for earning in walker_earnings {
+            let batch = Self::for_currency(&mut batches, earning.currency, earning.created_at);
+            batch.amount_cents -= i64::from(earning.amount_cents);
+            batch.walker_earning_ids.push(earning.id);
+        }
+        batches
+    }
+
+    fn for_currency(
+        batches: &mut Vec<Self>,
+        currency: Currency,
+        period_start: DateTime<Utc>,
+    ) -> &mut Self {
+        let index = match batches.iter().position(|b| b.currency == currency) {
+            Some(index) => index,
+            None => {
+                batches.push(Self {
+                    currency,
+                    amount_cents: 0,
+                    transaction_ids: Vec::new(),
+                    refund_ids: Vec::new(),
+                    walker_earning_ids: Vec::new(),
+                    period_start,
+                });
+                batches.len() - 1
+            }
+        };
+        &mut batches[index]
+    }
+}
+
+/// One transaction's part in a payout: the walker's share of the charge,
+/// less the refunds the payout deducted and what a lost dispute had taken
+/// back when the payout was made
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayoutLineItem {
+    pub transaction_id: Uuid,
+    pub payout_cents: i64,
+    /// Refunds made before the payout and the amount each took back
+    pub refunds: Vec<(Uuid, i64)>,
+    pub dispute_reversed_cents: i64,
+    /// What the transaction added to the payout
+    pub amount_cents: i64,
+}
+
+impl PayoutLineItem {
+    pub fn for_payout(
+        payout: &Payout,
+        transaction: &Transaction,
+        refunds: &[Refund],
+        dispute: Option<&Dispute>,
+    ) -> Self {
+        let refunds: Vec<Refund> = refunds
+            .iter()
+            .filter(|r| payout.refund_ids.contains(&r.id))
+            .cloned()
+            .collect();
+        // A dispute still open at the time would have kept the transaction
+        // out of the payout, so only one already lost counts
+        let dispute = dispute.filter(|d| {
+            d.status == DisputeStatus::Lost
+                && d.resolved_at.is_some_and(|at| at <= payout.created_at)
+        });
+
+        Self {
+            transaction_id: transaction.id,
+            payout_cents: i64::from(transaction.provider_payout_cents),
+            refunds: refunds
+                .iter()
+                .map(|r| (r.id, i64::from(r.provider_payout_reversed_cents)))
+                .collect(),
+            dispute_reversed_cents: dispute
+                .map(|d| {
+                    i64::from(
+                        RefundAllocation::for_refund(transaction, d.amount_cents)
+                            .provider_payout_cents,
+                    )
+                })
+                .unwrap_or(0),
+            amount_cents: PayoutBatch::transaction_amount(transaction, &refunds, dispute)
+                .unwrap_or(0),
+        }
+    }
+}
+
 /// Payout summary for dashboard
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct PayoutSummary {
@@ -195,3 +421,369 @@ pub struct PayoutSummary {
     pub last_payout_date: Option<DateTime<Utc>>,
     pub next_payout_date: Option<DateTime<Utc>>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{RefundSource, RefundStatus, TransactionStatus, WalkerEarningKind};
+    use shared::types::UserId;
+
+    fn settings(
+        schedule: &str,
+        day_of_week: Option<i32>,
+        day_of_month: Option<i32>,
+    ) -> PayoutSettings {
+        PayoutSettings {
+            id: Uuid::new_v4(),
+            organization_id: OrganizationId::new(),
+            payout_method: "bank".to_string(),
+            stripe_bank_account_id: Some("ba_123".to_string()),
+            square_bank_account_id: None,
+            bank_name: None,
+            bank_account_last_four: None,
+            bank_routing_last_four: None,
+            payout_schedule: schedule.to_string(),
+            payout_day_of_week: day_of_week,
+            payout_day_of_month: day_of_month,
+            minimum_payout_cents: 100,
+            is_verified: true,
+            verification_status: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn transaction(currency: Currency, provider_payout_cents: i32) -> Transaction {
+        Transaction {
+            id: Uuid::new_v4(),
+            organization_id: OrganizationId::new(),
+            booking_id: None,
+            customer_user_id: UserId::new(),
+            provider_user_id: Some(UserId::new()),
+            payment_method_id: None,
+            provider_id: Uuid::new_v4(),
+            subtotal_cents: 4000,
+            tip_cents: 0,
+            customer_fee_cents: 0,
+            provider_fee_cents: 0,
+            platform_fee_cents: 0,
+            tax_cents: 0,
+            processing_fee_cents: 0,
+            total_cents: 4000,
+            provider_payout_cents,
+            processing_fee_reported: false,
+            currency,
+            status: TransactionStatus::Succeeded,
+            external_payment_id: None,
+            stripe_payment_intent_id: None,
+            stripe_charge_id: None,
+            stripe_transfer_id: None,
+            square_payment_id: None,
+            square_order_id: None,
+            tax_rate_percent: None,
+            tax_jurisdiction: None,
+            tax_calculation_id: None,
+            tax_reported_at: None,
+            refunded_amount_cents: 0,
+            authorized_at: None,
+            authorization_expires_at: None,
+            captured_at: None,
+            idempotency_key: None,
+            manual_capture: false,
+            off_session: false,
+            failure_code: None,
+            failure_message: None,
+            description: None,
+            metadata: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn refund(transaction: &Transaction, status: RefundStatus, reversed_cents: i32) -> Refund {
+        Refund {
+            id: Uuid::new_v4(),
+            organization_id: transaction.organization_id,
+            transaction_id: transaction.id,
+            amount_cents: reversed_cents,
+            currency: transaction.currency,
+            reason: None,
+            status,
+            source: RefundSource::Customer,
+            initiated_by: None,
+            provider_refund_id: None,
+            subtotal_refunded_cents: reversed_cents,
+            tip_refunded_cents: 0,
+            customer_fee_refunded_cents: 0,
+            tax_refunded_cents: 0,
+            platform_fee_reversed_cents: 0,
+            provider_payout_reversed_cents: reversed_cents,
+            failure_message: None,
+            tax_reported_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn dispute(transaction: &Transaction, status: DisputeStatus, amount_cents: i32) -> Dispute {
+        Dispute {
+            id: Uuid::new_v4(),
+            organization_id: transaction.organization_id,
+            transaction_id: transaction.id,
+            amount_cents,
+            currency: transaction.currency,
+            stripe_dispute_id: None,
+            square_dispute_id: None,
+            reason: "fraudulent".to_string(),
+            status,
+            evidence_submitted: false,
+            evidence_due_by: None,
+            evidence: None,
+            evidence_submitted_at: None,
+            last_reminder_days: None,
+            resolved_at: None,
+            outcome: None,
+            metadata: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_schedule_due_dates() {
+        assert!(settings("daily", None, None).is_due_on(date(2024, 3, 5)));
+
+        // 2024-03-04 is a Monday
+        let weekly = settings("weekly", None, None);
+        assert!(weekly.is_due_on(date(2024, 3, 4)));
+        assert!(!weekly.is_due_on(date(2024, 3, 5)));
+        assert!(settings("weekly", Some(0), None).is_due_on(date(2024, 3, 3)));
+
+        let monthly = settings("monthly", None, Some(15));
+        assert!(monthly.is_due_on(date(2024, 3, 15)));
+        assert!(!monthly.is_due_on(date(2024, 3, 16)));
+
+        assert!(!settings("yearly", None, None).is_due_on(date(2024, 3, 4)));
+    }
+
+    #[test]
+    fn test_monthly_payout_falls_back_to_last_day() {
+        let monthly = settings("monthly", None, Some(31));
+        assert!(monthly.is_due_on(date(2024, 2, 29)));
+        assert!(!monthly.is_due_on(date(2024, 2, 28)));
+        assert!(monthly.is_due_on(date(2023, 2, 28)));
+        assert!(monthly.is_due_on(date(2024, 4, 30)));
+        assert!(monthly.is_due_on(date(2024, 12, 31)));
+    }
+
+    #[test]
+    fn test_transaction_amount_less_refunds_and_disputes() {
+        let txn = transaction(Currency::USD, 3600);
+        assert_eq!(PayoutBatch::transaction_amount(&txn, &[], None), Some(3600));
+
+        let refunds = [
+            refund(&txn, RefundStatus::Succeeded, 1000),
+            refund(&txn, RefundStatus::Pending, 500),
+            refund(&txn, RefundStatus::Failed, 700),
+        ];
+        assert_eq!(
+            PayoutBatch::transaction_amount(&txn, &refunds, None),
+            Some(2100)
+        );
+
+        let open = dispute(&txn, DisputeStatus::NeedsResponse, 4000);
+        assert_eq!(
+            PayoutBatch::transaction_amount(&txn, &[], Some(&open)),
+            None
+        );
+
+        let won = dispute(&txn, DisputeStatus::Won, 4000);
+        assert_eq!(
+            PayoutBatch::transaction_amount(&txn, &[], Some(&won)),
+            Some(3600)
+        );
+
+        let lost = dispute(&txn, DisputeStatus::Lost, 4000);
+        assert_eq!(
+            PayoutBatch::transaction_amount(&txn, &refunds, Some(&lost)),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn test_payout_status_from_provider() {
+        assert_eq!(PayoutStatus::from_provider("paid"), PayoutStatus::Paid);
+        assert_eq!(
+            PayoutStatus::from_provider("in_transit"),
+            PayoutStatus::InTransit
+        );
+        assert_eq!(PayoutStatus::from_provider("SENT"), PayoutStatus::InTransit);
+        assert_eq!(PayoutStatus::from_provider("FAILED"), PayoutStatus::Failed);
+        assert_eq!(
+            PayoutStatus::from_provider("canceled"),
+            PayoutStatus::Canceled
+        );
+        assert_eq!(
+            PayoutStatus::from_provider("pending"),
+            PayoutStatus::Pending
+        );
+    }
+
+    #[test]
+    fn test_line_item_counts_what_the_payout_deducted() {
+        let txn = transaction(Currency::USD, 3600);
+        let mut payout = Payout {
+            id: Uuid::new_v4(),
+            organization_id: txn.organization_id,
+            amount_cents: 0,
+            fee_cents: 0,
+            net_amount_cents: 0,
+            currency: Currency::USD,
+            period_start: txn.created_at,
+            period_end: Utc::now(),
+            stripe_payout_id: None,
+            stripe_transfer_id: None,
+            square_payout_id: None,
+            status: PayoutStatus::Pending,
+            initiated_at: None,
+            arrival_date: None,
+            completed_at: None,
+            failure_code: None,
+            failure_message: None,
+            transaction_count: 1,
+            transaction_ids: vec![txn.id],
+            refund_ids: Vec::new(),
+            walker_earning_ids: Vec::new(),
+            scheduled_for: None,
+            metadata: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let before = refund(&txn, RefundStatus::Succeeded, 1000);
+        let failed = refund(&txn, RefundStatus::Failed, 700);
+        let mut lost = dispute(&txn, DisputeStatus::Lost, 1000);
+        lost.resolved_at = Some(Utc::now());
+        payout.created_at = Utc::now();
+        payout.refund_ids = vec![before.id];
+        let after = refund(&txn, RefundStatus::Succeeded, 500);
+
+        let refunds = [before.clone(), failed, after];
+        let item = PayoutLineItem::for_payout(&payout, &txn, &refunds, Some(&lost));
+        assert_eq!(item.payout_cents, 3600);
+        assert_eq!(item.refunds, vec![(before.id, 1000)]);
+        assert_eq!(item.dispute_reversed_cents, 1000);
+        assert_eq!(item.amount_cents, 1600);
+
+        lost.resolved_at = Some(payout.created_at + chrono::Duration::seconds(1));
+        let item = PayoutLineItem::for_payout(&payout, &txn, &refunds, Some(&lost));
+        assert_eq!(item.dispute_reversed_cents, 0);
+        assert_eq!(item.amount_cents, 2600);
+    }
+
+    #[test]
+    fn test_batches_by_currency() {
+        let mut first = transaction(Currency::USD, 3000);
+        first.created_at -= chrono::Duration::days(3);
+        let second = transaction(Currency::USD, 2000);
+        let cad = transaction(Currency::CAD, 1500);
+
+        let batches = PayoutBatch::collect([(&second, 2000), (&cad, 1500), (&first, 2500)], [], []);
+        assert_eq!(batches.len(), 2);
+
+        let usd = &batches[0];
+        assert_eq!(usd.currency, Currency::USD);
+        assert_eq!(usd.amount_cents, 4500);
+        assert_eq!(usd.transaction_ids, vec![second.id, first.id]);
+        assert_eq!(usd.period_start, first.created_at);
+
+        assert_eq!(batches[1].currency, Currency::CAD);
+        assert_eq!(batches[1].amount_cents, 1500);
+    }
+
+    #[test]
+    fn test_batch_deducts_refunds_made_after_an_earlier_payout() {
+        let paid_out = transaction(Currency::USD, 3000);
+        let txn = transaction(Currency::USD, 2000);
+        let counted = refund(&txn, RefundStatus::Succeeded, 500);
+        let late = refund(&paid_out, RefundStatus::Succeeded, 800);
+
+        let batches = PayoutBatch::collect([(&txn, 1500)], [&counted, &late], []);
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].amount_cents, 700);
+        assert_eq!(batches[0].transaction_ids, vec![txn.id]);
+        assert_eq!(batches[0].refund_ids, vec![counted.id, late.id]);
+
+        // A refund alone leaves nothing to pay out
+        let batches = PayoutBatch::collect([], [&late], []);
+        assert_eq!(batches[0].amount_cents, -800);
+        assert!(batches[0].transaction_ids.is_empty());
+    }
+
+    #[test]
+    fn test_batch_deducts_walker_transfers() {
+        let txn = transaction(Currency::USD, 3000);
+        let earning = |amount_cents| WalkerEarning {
+            id: Uuid::new_v4(),
+            organization_id: txn.organization_id,
+            walker_id: UserId::new(),
+            transaction_id: txn.id,
+            source_id: Uuid::new_v4(),
+            kind: WalkerEarningKind::Walk,
+            amount_cents,
+            currency: Currency::USD,
+            walker_payout_id: Some(Uuid::new_v4()),
+            created_at: Utc::now(),
+        };
+        let walk = earning(2000);
+        let taken_back = earning(-500);
+
+        let batches = PayoutBatch::collect([(&txn, 3000)], [], [&walk, &taken_back]);
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].amount_cents, 1500);
+        assert_eq!(batches[0].walker_earning_ids, vec![walk.id, taken_back.id]);
+    }
+
+    #[test]
+    fn test_scheduled_payouts_share_an_idempotency_key() {
+        let txn = transaction(Currency::USD, 3000);
+        let payout = |scheduled_for| Payout {
+            id: Uuid::new_v4(),
+            organization_id: txn.organization_id,
+            amount_cents: 3000,
+            fee_cents: 0,
+            net_amount_cents: 3000,
+            currency: Currency::USD,
+            period_start: txn.created_at,
+            period_end: Utc::now(),
+            stripe_payout_id: None,
+            stripe_transfer_id: None,
+            square_payout_id: None,
+            status: PayoutStatus::Pending,
+            initiated_at: None,
+            arrival_date: None,
+            completed_at: None,
+            failure_code: None,
+            failure_message: None,
+            transaction_count: 1,
+            transaction_ids: vec![txn.id],
+            refund_ids: Vec::new(),
+            walker_earning_ids: Vec::new(),
+            scheduled_for,
+            metadata: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let day = Some(date(2024, 3, 4));
+        assert_eq!(payout(day).idempotency_key(), payout(day).idempotency_key());
+        assert_ne!(
+            payout(None).idempotency_key(),
+            payout(None).idempotency_key()
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use shared::types::{Currency, OrganizationId};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use super::{PaymentMethodType, PaymentProviderType, ProcessingFee};
+
+/// Eurozone countries, where EUR cards are domestic
+const EUROZONE: [&str; 20] = [
+    "AT", "BE", "CY", "DE", "EE", "ES", "FI", "FR", "GR", "HR", "IE", "IT", "LT", "LU", "LV", "MT",
+    "NL", "PT", "SI", "SK",
+];
+
+/// Where a card was issued, relative to the currency it is charged in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "card_region", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum CardRegion {
+    Domestic,
+    International,
+}
+
+impl std::fmt::Display for CardRegion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CardRegion::Domestic => write!(f, "domestic"),
+            CardRegion::International => write!(f, "international"),
+        }
+    }
+}
+
+impl CardRegion {
+    /// Region of a card issued in `country` (ISO 3166-1 alpha-2) and charged
+    /// in `currency`. Cards from where the currency is local are domestic.
+    pub fn of_card(country: &str, currency: Currency) -> Self {
+        let country = country.trim().to_uppercase();
+        let domestic = match currency {
+            Currency::USD => country == "US",
+            Currency::CAD => country == "CA",
+            Currency::GBP => country == "GB",
+            Currency::EUR => EUROZONE.contains(&country.as_str()),
+        };
+
+        if domestic {
+            CardRegion::Domestic
+        } else {
+            CardRegion::International
+        }
+    }
+}
+
+/// What a payment provider charges an organization, for one payment method
+/// and card region or for all of them when those are unset
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ProcessingFeeSchedule {
+    pub id: Uuid,
+    pub organization_id: OrganizationId,
+    pub provider_type: PaymentProviderType,
+    pub method_type: Option<PaymentMethodType>,
+    pub card_region: Option<CardRegion>,
+    /// Percentage of the amount charged (e.g. 2.9 for 2.9%)
+    pub percent: Decimal,
+    pub fixed_cents: i64,
+    pub cap_cents: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ProcessingFeeSchedule {
+    pub fn fee(&self) -> ProcessingFee {
+        ProcessingFee {
+            percent: self.percent,
+            fixed_cents: self.fixed_cents,
+            cap_cents: self.cap_cents,
+        }
+    }
+
+    fn matches(
+        &self,
+        provider: PaymentProviderType,
+        method: PaymentMethodType,
+        region: CardRegion,
+    ) -> bool {
+        self.provider_type == provider
+            && self.method_type.map_or(true, |m| m == method)
+            && self.card_region.map_or(true, |r| r == region)
+    }
+
+    /// A schedule for a payment method beats one for a card region, which
+    /// beats a catch-all
+    fn specificity(&self) -> u8 {
+        u8::from(self.method_type.is_some()) * 2 + u8::from(self.card_region.is_some())
+    }
+
+    /// Fee for a payment: the most specific matching schedule, or the
+    /// provider's list price when none match
+    pub fn resolve(
+        schedules: &[ProcessingFeeSchedule],
+        provider: PaymentProviderType,
+        method: PaymentMethodType,
+        region: CardRegion,
+        currency: Currency,
+    ) -> ProcessingFee {
+        schedules
+            .iter()
+            .filter(|s| s.matches(provider, method, region))
+            .max_by_key(|s| s.specificity())
+            .map(ProcessingFeeSchedule::fee)
+            .unwrap_or_else(|| ProcessingFee::list_price(provider, method, region, currency))
+    }
+}
+
+/// Input for creating or replacing a fee schedule
+#[derive(Debug, Clone)]
+pub struct UpsertProcessingFeeSchedule {
+    pub provider_type: PaymentProviderType,
+    pub method_type: Option<PaymentMethodType>,
+    pub card_region: Option<CardRegion>,
+    pub percent: Decimal,
+    pub fixed_cents: i64,
+    pub cap_cents: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(
+        method_type: Option<PaymentMethodType>,
+        card_region: Option<CardRegion>,
+        percent: i64,
+    ) -> ProcessingFeeSchedule {
+        ProcessingFeeSchedule {
+            id: Uuid::new_v4(),
+            organization_id: OrganizationId::new(),
+            provider_type: PaymentProviderType::Stripe,
+            method_type,
+            card_region,
+            percent: Decimal::new(percent, 1),
+            fixed_cents: 30,
+            cap_cents: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_card_region() {
+        assert_eq!(
+            CardRegion::of_card("us", Currency::USD),
+            CardRegion::Domestic
+        );
+        assert_eq!(
+            CardRegion::of_card("GB", Currency::USD),
+            CardRegion::International
+        );
+        assert_eq!(
+            CardRegion::of_card("IE", Currency::EUR),
+            CardRegion::Domestic
+        );
+        assert_eq!(
+            CardRegion::of_card("GB", Currency::EUR),
+            CardRegion::International
+        );
+    }
+
+    #[test]
+    fn test_most_specific_schedule_wins() {
+        let schedules = vec![
+            schedule(None, None, 25),
+            schedule(None, Some(CardRegion::International), 39),
+            schedule(Some(PaymentMethodType::Card), None, 27),
+            schedule(
+                Some(PaymentMethodType::Card),
+                Some(CardRegion::International),
+                42,
+            ),
+        ];
+        let resolve = |method, region| {
+            ProcessingFeeSchedule::resolve(
+                &schedules,
+                PaymentProviderType::Stripe,
+                method,
+                region,
+                Currency::USD,
+            )
+            .percent
+        };
+
+        assert_eq!(
+            resolve(PaymentMethodType::Card, CardRegion::International),
+            Decimal::new(42, 1)
+        );
+        assert_eq!(
+            resolve(PaymentMethodType::Card, CardRegion::Domestic),
+            Decimal::new(27, 1)
+        );
+        assert_eq!(
+            resolve(PaymentMethodType::Link, CardRegion::International),
+            Decimal::new(39, 1)
+        );
+        assert_eq!(
+            resolve(PaymentMethodType::Link, CardRegion::Domestic),
+            Decimal::new(25, 1)
+        );
+    }
+
+    #[test]
+    fn test_falls_back_to_list_price() {
+        let schedules = vec![schedule(None, None, 25)];
+
+        assert_eq!(
+            ProcessingFeeSchedule::resolve(
+                &schedules,
+                PaymentProviderType::Square,
+                PaymentMethodType::Card,
+                CardRegion::Domestic,
+                Currency::USD,
+            ),
+            ProcessingFee::standard()
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use shared::types::{OrganizationId, UserId};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Reconciliation run status enum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "reconciliation_status", rename_all = "snake_case")]
+pub enum ReconciliationStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+impl std::fmt::Display for ReconciliationStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReconciliationStatus::Running => write!(f, "running"),
+            ReconciliationStatus::Completed => write!(f, "completed"),
+            ReconciliationStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// How a local transaction and the provider's record disagree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "reconciliation_mismatch_kind", rename_all = "snake_case")]
+pub enum ReconciliationMismatchKind {
+    /// The provider has a payment with no local transaction
+    MissingLocally,
+    /// A local transaction the provider has no payment for
+    MissingRemotely,
+    AmountMismatch,
+    StatusMismatch,
+    RefundMismatch,
+}
+
+impl std::fmt::Display for ReconciliationMismatchKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReconciliationMismatchKind::MissingLocally => write!(f, "missing_locally"),
+            ReconciliationMismatchKind::MissingRemotely => write!(f, "missing_remotely"),
+            ReconciliationMismatchKind::AmountMismatch => write!(f, "amount_mismatch"),
+            ReconciliationMismatchKind::StatusMismatch => write!(f, "status_mismatch"),
+            ReconciliationMismatchKind::RefundMismatch => write!(f, "refund_mismatch"),
+        }
+    }
+}
+
+/// Reconciliation run database model
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ReconciliationRun {
+    pub id: Uuid,
+    pub organization_id: OrganizationId,
+    pub provider_id: Uuid,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub status: ReconciliationStatus,
+    pub local_count: i32,
+    pub remote_count: i32,
+    pub matched_count: i32,
+    pub mismatch_count: i32,
+    pub error_message: Option<String>,
+    pub requested_by: Option<UserId>,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// Reconciliation mismatch database model
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct ReconciliationMismatch {
+    pub id: Uuid,
+    pub run_id: Uuid,
+    pub organization_id: OrganizationId,
+    pub kind: ReconciliationMismatchKind,
+    pub transaction_id: Option<Uuid>,
+    pub external_payment_id: Option<String>,
+    pub local_amount_cents: Option<i64>,
+    pub remote_amount_cents: Option<i64>,
+    pub local_status: Option<String>,
+    pub remote_status: Option<String>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub resolved_by: Option<UserId>,
+    pub resolution_note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ReconciliationMismatch {
+    pub fn is_resolved(&self) -> bool {
+        self.resolved_at.is_some()
+    }
+}
+
+/// Input for recording a mismatch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreateReconciliationMismatch {
+    pub kind: ReconciliationMismatchKind,
+    pub transaction_id: Option<Uuid>,
+    pub external_payment_id: Option<String>,
+    pub local_amount_cents: Option<i64>,
+    pub remote_amount_cents: Option<i64>,
+    pub local_status: Option<String>,
+    pub remote_status: Option<String>,
+}
+
+/// Totals recorded when a run completes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReconciliationCounts {
+    pub local_count: i32,
+    pub remote_count: i32,
+    pub matched_count: i32,
+}
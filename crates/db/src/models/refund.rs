@@ -0,0 +1,300 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use shared::types::{Currency, OrganizationId, UserId};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use super::Transaction;
+
+/// Refund status enum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "refund_status", rename_all = "snake_case")]
+pub enum RefundStatus {
+    Pending,
+    Succeeded,
+    Failed,
+    Canceled,
+}
+
+impl RefundStatus {
+    /// Map a Stripe or Square refund status
+    pub fn from_provider(status: &str) -> Self {
+        match status.to_lowercase().as_str() {
+            "succeeded" | "completed" => RefundStatus::Succeeded,
+            "failed" | "rejected" => RefundStatus::Failed,
+            "canceled" => RefundStatus::Canceled,
+            _ => RefundStatus::Pending,
+        }
+    }
+
+    /// Whether the refunded amount counts against the transaction
+    pub fn is_outstanding(&self) -> bool {
+        matches!(self, RefundStatus::Pending | RefundStatus::Succeeded)
+    }
+}
+
+/// Who issued a refund
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "refund_source", rename_all = "snake_case")]
+pub enum RefundSource {
+    Customer,
+    /// An organization admin on the customer's behalf
+    Admin,
+    /// Issued outside the platform, e.g. from the provider's dashboard
+    Provider,
+}
+
+/// Refund database model
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct Refund {
+    pub id: Uuid,
+    pub organization_id: OrganizationId,
+    pub transaction_id: Uuid,
+    pub amount_cents: i32,
+    pub currency: Currency,
+    pub reason: Option<String>,
+    pub status: RefundStatus,
+    pub source: RefundSource,
+    pub initiated_by: Option<UserId>,
+    pub provider_refund_id: Option<String>,
+    pub subtotal_refunded_cents: i32,
+    pub tip_refunded_cents: i32,
+    pub customer_fee_refunded_cents: i32,
+    pub tax_refunded_cents: i32,
+    pub platform_fee_reversed_cents: i32,
+    pub provider_payout_reversed_cents: i32,
+    pub failure_message: Option<String>,
+    /// When the refund was reported to the tax provider
+    pub tax_reported_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Input for creating a refund
+#[derive(Debug, Clone)]
+pub struct CreateRefund {
+    pub transaction_id: Uuid,
+    pub amount_cents: i32,
+    pub currency: Currency,
+    pub reason: Option<String>,
+    pub status: RefundStatus,
+    pub source: RefundSource,
+    pub initiated_by: Option<UserId>,
+    pub provider_refund_id: Option<String>,
+    pub allocation: RefundAllocation,
+}
+
+/// How a refund is split across the parts of the original charge.
+///
+/// Each part of the charge (subtotal, tip, customer fee and tax) is refunded
+/// in proportion to its share of the total. The platform gives back the
+/// customer fee it refunds plus the same share of the provider fee, which is
+/// what the application fee covers; the walker gives back the rest. Processing
+/// fees are kept by the provider and never reversed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RefundAllocation {
+    pub subtotal_cents: i32,
+    pub tip_cents: i32,
+    pub customer_fee_cents: i32,
+    pub tax_cents: i32,
+    pub platform_fee_cents: i32,
+    pub provider_payout_cents: i32,
+}
+
+impl RefundAllocation {
+    /// Split a refund of `amount_cents` on top of what the transaction has
+    /// already refunded.
+    ///
+    /// Allocations are computed on the running total, so a series of partial
+    /// refunds that adds up to the full charge reverses exactly the amounts
+    /// that were charged.
+    pub fn for_refund(transaction: &Transaction, amount_cents: i32) -> Self {
+        let before = Self::cumulative(transaction, transaction.refunded_amount_cents);
+        let after = Self::cumulative(
+            transaction,
+            transaction.refunded_amount_cents + amount_cents,
+        );
+
+        Self {
+            subtotal_cents: after.subtotal_cents - before.subtotal_cents,
+            tip_cents: after.tip_cents - before.tip_cents,
+            customer_fee_cents: after.customer_fee_cents - before.customer_fee_cents,
+            tax_cents: after.tax_cents - before.tax_cents,
+            platform_fee_cents: after.platform_fee_cents - before.platform_fee_cents,
+            provider_payout_cents: after.provider_payout_cents - before.provider_payout_cents,
+        }
+    }
+
+    /// Allocation of the first `refunded_cents` refunded from a transaction
+    fn cumulative(transaction: &Transaction, refunded_cents: i32) -> Self {
+        let refunded_cents = refunded_cents.clamp(0, transaction.total_cents);
+        let [subtotal_cents, tip_cents, customer_fee_cents, tax_cents] = apportion(
+            refunded_cents,
+            [
+                transaction.subtotal_cents,
+                transaction.tip_cents,
+                transaction.customer_fee_cents,
+                transaction.tax_cents,
+            ],
+        );
+
+        let provider_fee_cents = scale(
+            transaction.provider_fee_cents,
+            subtotal_cents,
+            transaction.subtotal_cents,
+        );
+
+        Self {
+            subtotal_cents,
+            tip_cents,
+            customer_fee_cents,
+            tax_cents,
+            platform_fee_cents: customer_fee_cents + provider_fee_cents,
+            provider_payout_cents: subtotal_cents - provider_fee_cents + tip_cents,
+        }
+    }
+}
+
+/// Split `amount` in proportion to `weights` using the largest remainder
+/// method, so the parts always add up to `amount`
+fn apportion<const N: usize>(amount: i32, weights: [i32; N]) -> [i32; N] {
+    let total: i64 = weights.iter().map(|w| i64::from(*w)).sum();
+    let mut parts = [0; N];
+    if total <= 0 {
+        if N > 0 {
+            parts[0] = amount;
+        }
+        return parts;
+    }
+
+    let mut remainders = [(0i64, 0usize); N];
+    let mut allocated = 0;
+    for (i, weight) in weights.iter().enumerate() {
+        let exact = i64::from(amount) * i64::from(*weight);
+        parts[i] = (exact / total) as i32;
+        remainders[i] = (exact % total, i);
+        allocated += parts[i];
+    }
+
+    remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    for (_, i) in remainders.iter().take((amount - allocated) as usize) {
+        parts[*i] += 1;
+    }
+    parts
+}
+
+/// `value * numerator / denominator`, rounded half up
+fn scale(value: i32, numerator: i32, denominator: i32) -> i32 {
+    if denominator == 0 {
+        return 0;
+    }
+    let scaled = (i64::from(value) * i64::from(numerator) * 2 + i64::from(denominator))
+        / (2 * i64::from(denominator));
+    scaled as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TransactionStatus;
+
+    fn transaction() -> Transaction {
+        Transaction {
+            id: Uuid::new_v4(),
+            organization_id: OrganizationId::new(),
+            booking_id: None,
+            customer_user_id: UserId::new(),
+            provider_user_id: Some(UserId::new()),
+            payment_method_id: None,
+            provider_id: Uuid::new_v4(),
+            subtotal_cents: 4000,
+            tip_cents: 500,
+            customer_fee_cents: 120,
+            provider_fee_cents: 200,
+            platform_fee_cents: 320,
+            tax_cents: 333,
+            processing_fee_cents: 171,
+            total_cents: 4953,
+            provider_payout_cents: 4300,
+            processing_fee_reported: false,
+            currency: Currency::USD,
+            status: TransactionStatus::Succeeded,
+            external_payment_id: None,
+            stripe_payment_intent_id: None,
+            stripe_charge_id: None,
+            stripe_transfer_id: None,
+            square_payment_id: None,
+            square_order_id: None,
+            tax_rate_percent: None,
+            tax_jurisdiction: None,
+            tax_calculation_id: None,
+            tax_reported_at: None,
+            refunded_amount_cents: 0,
+            authorized_at: None,
+            authorization_expires_at: None,
+            captured_at: None,
+            idempotency_key: None,
+            manual_capture: false,
+            off_session: false,
+            failure_code: None,
+            failure_message: None,
+            description: None,
+            metadata: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_full_refund_reverses_everything() {
+        let txn = transaction();
+        let allocation = RefundAllocation::for_refund(&txn, txn.total_cents);
+
+        assert_eq!(allocation.subtotal_cents, 4000);
+        assert_eq!(allocation.tip_cents, 500);
+        assert_eq!(allocation.tax_cents, 333);
+        assert_eq!(allocation.platform_fee_cents, txn.platform_fee_cents);
+        assert_eq!(allocation.provider_payout_cents, txn.provider_payout_cents);
+    }
+
+    #[test]
+    fn test_partial_refunds_add_up_to_full_refund() {
+        let mut txn = transaction();
+        let mut total = RefundAllocation::default();
+
+        for amount in [1001, 7, 2500, 1445] {
+            let allocation = RefundAllocation::for_refund(&txn, amount);
+            assert_eq!(
+                allocation.subtotal_cents
+                    + allocation.tip_cents
+                    + allocation.customer_fee_cents
+                    + allocation.tax_cents,
+                amount
+            );
+
+            total.subtotal_cents += allocation.subtotal_cents;
+            total.tip_cents += allocation.tip_cents;
+            total.customer_fee_cents += allocation.customer_fee_cents;
+            total.tax_cents += allocation.tax_cents;
+            total.platform_fee_cents += allocation.platform_fee_cents;
+            total.provider_payout_cents += allocation.provider_payout_cents;
+            txn.refunded_amount_cents += amount;
+        }
+
+        assert_eq!(txn.refunded_amount_cents, txn.total_cents);
+        assert_eq!(total, RefundAllocation::for_refund(&transaction(), 4953));
+    }
+
+    #[test]
+    fn test_provider_status_mapping() {
+        assert_eq!(
+            RefundStatus::from_provider("COMPLETED"),
+            RefundStatus::Succeeded
+        );
+        assert_eq!(
+            RefundStatus::from_provider("PENDING"),
+            RefundStatus::Pending
+        );
+        assert!(!RefundStatus::from_provider("REJECTED").is_outstanding());
+    }
+}
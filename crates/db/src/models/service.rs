@@ -1,6 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use shared::types::{OrganizationId, ServiceId};
+use shared::types::{Currency, OrganizationId, ServiceId};
 use sqlx::FromRow;
 
 /// Service database model
@@ -12,6 +12,8 @@ pub struct Service {
     pub description: Option<String>,
     pub duration_minutes: i32,
     pub base_price_cents: i64,
+    /// Currency the price is in
+    pub currency: Currency,
     pub is_active: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -31,6 +33,7 @@ pub struct CreateService {
     pub description: Option<String>,
     pub duration_minutes: i32,
     pub base_price_cents: i64,
+    pub currency: Currency,
 }
 
 /// Input for updating a service
@@ -41,4 +44,5 @@ pub struct UpdateService {
     pub duration_minutes: Option<i32>,
     pub base_price_cents: Option<i64>,
     pub is_active: Option<bool>,
+    pub currency: Option<Currency>,
 }
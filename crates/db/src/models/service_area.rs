@@ -1,6 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use shared::types::{OrganizationId, UserId};
+use shared::geometry::Polygon;
+use shared::types::{Coordinates, OrganizationId, UserId};
 use sqlx::FromRow;
 use uuid::Uuid;
 
@@ -12,6 +13,21 @@ pub struct PolygonPoint {
     pub lng: f64,
 }
 
+impl From<&PolygonPoint> for Coordinates {
+    fn from(point: &PolygonPoint) -> Self {
+        Coordinates::new_unchecked(point.lat, point.lng)
+    }
+}
+
+impl From<Coordinates> for PolygonPoint {
+    fn from(coords: Coordinates) -> Self {
+        Self {
+            lat: coords.latitude,
+            lng: coords.longitude,
+        }
+    }
+}
+
 // MARK: - Service Area
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
@@ -22,6 +38,7 @@ pub struct ServiceArea {
     pub name: String,
     pub color: Option<String>,
     pub polygon: sqlx::types::Json<Vec<PolygonPoint>>,
+    pub holes: sqlx::types::Json<Vec<Vec<PolygonPoint>>>,
     pub min_latitude: Option<f64>,
     pub max_latitude: Option<f64>,
     pub min_longitude: Option<f64>,
@@ -34,6 +51,26 @@ pub struct ServiceArea {
     pub updated_at: DateTime<Utc>,
 }
 
+impl ServiceArea {
+    /// Get the stored polygon and holes as a geometry polygon
+    pub fn geometry(&self) -> Polygon {
+        Polygon::with_holes_unchecked(
+            self.polygon.0.iter().map(Coordinates::from).collect(),
+            self.holes
+                .0
+                .iter()
+                .map(|hole| hole.iter().map(Coordinates::from).collect())
+                .collect(),
+        )
+    }
+
+    /// Check whether a point lies inside this area's polygon (boundary inclusive)
+    pub fn contains(&self, latitude: f64, longitude: f64) -> bool {
+        self.geometry()
+            .contains(&Coordinates::new_unchecked(latitude, longitude))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateServiceArea {
     pub organization_id: OrganizationId,
@@ -41,6 +78,7 @@ pub struct CreateServiceArea {
     pub name: String,
     pub color: Option<String>,
     pub polygon: Vec<PolygonPoint>,
+    pub holes: Vec<Vec<PolygonPoint>>,
     pub is_active: bool,
     pub priority: Option<i32>,
     pub price_adjustment_percent: Option<i32>,
@@ -52,6 +90,7 @@ pub struct UpdateServiceArea {
     pub name: Option<String>,
     pub color: Option<String>,
     pub polygon: Option<Vec<PolygonPoint>>,
+    pub holes: Option<Vec<Vec<PolygonPoint>>>,
     pub is_active: Option<bool>,
     pub priority: Option<i32>,
     pub price_adjustment_percent: Option<i32>,
@@ -67,6 +106,8 @@ pub struct ServiceAreaResponse {
     pub name: String,
     pub color: String,
     pub polygon: Vec<PolygonPoint>,
+    pub holes: Vec<Vec<PolygonPoint>>,
+    pub area_km2: f64,
     pub is_active: bool,
     pub priority: i32,
     pub price_adjustment_percent: i32,
@@ -77,12 +118,15 @@ pub struct ServiceAreaResponse {
 
 impl From<ServiceArea> for ServiceAreaResponse {
     fn from(area: ServiceArea) -> Self {
+        let area_km2 = area.geometry().area_km2();
         Self {
             id: area.id.to_string(),
             walker_id: area.walker_id.to_string(),
             name: area.name,
             color: area.color.unwrap_or_else(|| "#3B82F6".to_string()),
             polygon: area.polygon.0,
+            holes: area.holes.0,
+            area_km2,
             is_active: area.is_active,
             priority: area.priority.unwrap_or(0),
             price_adjustment_percent: area.price_adjustment_percent.unwrap_or(0),
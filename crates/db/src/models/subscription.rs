@@ -1,9 +1,22 @@
-use chrono::{DateTime, NaiveTime, Utc};
+use chrono::{DateTime, Datelike, Duration, Months, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use rust_decimal::prelude::ToPrimitive;
 use serde::{Deserialize, Serialize};
-use shared::types::{OrganizationId, UserId};
+use shared::types::{BookingId, LocationId, OrganizationId, UserId};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+/// Days a past-due tenant subscription keeps its paid features before
+/// they're restricted
+pub const DUNNING_GRACE_DAYS: i64 = 7;
+
+/// Trial length for an organization's first paid plan
+pub const TENANT_TRIAL_DAYS: u32 = 14;
+
+/// Days after a failed package payment before each retry. The package is
+/// cancelled when the last retry fails.
+pub const PACKAGE_PAYMENT_RETRY_DAYS: [i64; 3] = [1, 3, 5];
+
 /// Subscription status enum
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "subscription_status", rename_all = "snake_case")]
@@ -29,6 +42,20 @@ impl std::fmt::Display for SubscriptionStatus {
     }
 }
 
+impl SubscriptionStatus {
+    /// Map a Stripe subscription status
+    pub fn from_provider(status: &str) -> Self {
+        match status.to_lowercase().as_str() {
+            "active" => SubscriptionStatus::Active,
+            "trialing" => SubscriptionStatus::Trialing,
+            "past_due" | "unpaid" => SubscriptionStatus::PastDue,
+            "paused" => SubscriptionStatus::Paused,
+            "canceled" | "incomplete_expired" => SubscriptionStatus::Canceled,
+            _ => SubscriptionStatus::Incomplete,
+        }
+    }
+}
+
 /// Plan tier enum
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "plan_tier", rename_all = "snake_case")]
@@ -51,6 +78,20 @@ impl std::fmt::Display for PlanTier {
     }
 }
 
+impl std::str::FromStr for PlanTier {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "free" => Ok(PlanTier::Free),
+            "professional" => Ok(PlanTier::Professional),
+            "business" => Ok(PlanTier::Business),
+            "enterprise" => Ok(PlanTier::Enterprise),
+            _ => Err(format!("Unknown plan tier: {}", s)),
+        }
+    }
+}
+
 /// Platform fee tier configuration
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct PlatformFeeTier {
@@ -72,20 +113,16 @@ pub struct PlatformFeeTier {
 impl PlatformFeeTier {
     /// Get customer fee as percentage (e.g., 3.0 for 3%)
     pub fn customer_fee_display(&self) -> f64 {
-        self.customer_fee_percent
-            .to_string()
-            .parse::<f64>()
+        (self.customer_fee_percent * rust_decimal::Decimal::ONE_HUNDRED)
+            .to_f64()
             .unwrap_or(0.0)
-            * 100.0
     }
 
     /// Get provider fee as percentage (e.g., 20.0 for 20%)
     pub fn provider_fee_display(&self) -> f64 {
-        self.provider_fee_percent
-            .to_string()
-            .parse::<f64>()
+        (self.provider_fee_percent * rust_decimal::Decimal::ONE_HUNDRED)
+            .to_f64()
             .unwrap_or(0.0)
-            * 100.0
     }
 
     /// Get monthly price in dollars
@@ -112,6 +149,8 @@ pub struct TenantSubscription {
     pub metadata: Option<serde_json::Value>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub past_due_since: Option<DateTime<Utc>>,
+    pub restricted_at: Option<DateTime<Utc>>,
 }
 
 impl TenantSubscription {
@@ -142,6 +181,32 @@ impl TenantSubscription {
             (end - now).num_days().max(0)
         })
     }
+
+    /// Whether paid features were restricted for non-payment
+    pub fn is_restricted(&self) -> bool {
+        self.restricted_at.is_some()
+    }
+
+    /// Whether the subscription has been past due longer than the grace
+    /// period and its features aren't restricted yet
+    pub fn restriction_due(&self, now: DateTime<Utc>) -> bool {
+        self.status == SubscriptionStatus::PastDue
+            && self.restricted_at.is_none()
+            && self
+                .past_due_since
+                .is_some_and(|since| now - since >= chrono::Duration::days(DUNNING_GRACE_DAYS))
+    }
+
+    /// The plan whose features the organization has: its plan while the
+    /// subscription is in good standing or within the dunning grace period,
+    /// and the free plan otherwise
+    pub fn effective_plan_tier(&self) -> PlanTier {
+        match self.status {
+            SubscriptionStatus::Active | SubscriptionStatus::Trialing => self.plan_tier,
+            SubscriptionStatus::PastDue if !self.is_restricted() => self.plan_tier,
+            _ => PlanTier::Free,
+        }
+    }
 }
 
 /// Input for creating a tenant subscription
@@ -155,6 +220,21 @@ pub struct CreateTenantSubscription {
     pub trial_end: Option<DateTime<Utc>>,
 }
 
+/// A tenant subscription as Stripe reports it
+#[derive(Debug, Clone)]
+pub struct SyncTenantSubscription {
+    pub plan_tier: PlanTier,
+    pub stripe_subscription_id: String,
+    pub stripe_customer_id: String,
+    pub status: SubscriptionStatus,
+    pub current_period_start: Option<DateTime<Utc>>,
+    pub current_period_end: Option<DateTime<Utc>>,
+    pub trial_start: Option<DateTime<Utc>>,
+    pub trial_end: Option<DateTime<Utc>>,
+    pub cancel_at_period_end: bool,
+    pub canceled_at: Option<DateTime<Utc>>,
+}
+
 /// Input for updating a tenant subscription
 #[derive(Debug, Clone, Deserialize, Default)]
 pub struct UpdateTenantSubscription {
@@ -194,6 +274,17 @@ pub struct CustomerSubscription {
     pub metadata: Option<serde_json::Value>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Walk credits granted each period; `None` for a plain recurring charge
+    pub walks_per_period: Option<i32>,
+    pub preferred_walker_id: Option<UserId>,
+    pub location_id: Option<LocationId>,
+    /// Timezone the preferred day and time are in
+    pub timezone: String,
+    pub payment_method_id: Option<Uuid>,
+    pub failed_payment_attempts: i32,
+    pub next_payment_attempt_at: Option<DateTime<Utc>>,
+    /// The plan subscribed to; its terms are copied onto the subscription
+    pub package_plan_id: Option<Uuid>,
 }
 
 impl CustomerSubscription {
@@ -219,11 +310,164 @@ impl CustomerSubscription {
             _ => format!("Every {} {}", self.interval_count, self.interval),
         }
     }
+
+    /// Whether the subscription grants walk credits
+    pub fn is_package(&self) -> bool {
+        self.walks_per_period.is_some()
+    }
+
+    /// End of a billing period starting at `start`
+    pub fn period_after(&self, start: DateTime<Utc>) -> DateTime<Utc> {
+        let count = self.interval_count.max(1) as u32;
+        match self.interval.as_str() {
+            "week" => start + Duration::weeks(i64::from(count)),
+            "year" => start
+                .checked_add_months(Months::new(12 * count))
+                .unwrap_or(start),
+            _ => start
+                .checked_add_months(Months::new(count))
+                .unwrap_or(start),
+        }
+    }
+
+    /// Whether the next period is due to be charged: the current one has
+    /// ended and any retry delay has passed
+    pub fn renewal_due(&self, now: DateTime<Utc>) -> bool {
+        matches!(
+            self.status,
+            SubscriptionStatus::Active | SubscriptionStatus::PastDue
+        ) && self.current_period_end.is_some_and(|end| end <= now)
+            && self.next_payment_attempt_at.map_or(true, |at| at <= now)
+    }
+
+    /// Status and next attempt after a renewal payment fails: past due until
+    /// the retries run out, then cancelled
+    pub fn after_failed_payment(
+        &self,
+        now: DateTime<Utc>,
+    ) -> (SubscriptionStatus, Option<DateTime<Utc>>) {
+        match PACKAGE_PAYMENT_RETRY_DAYS.get(self.failed_payment_attempts.max(0) as usize) {
+            Some(days) => (
+                SubscriptionStatus::PastDue,
+                Some(now + Duration::days(*days)),
+            ),
+            None => (SubscriptionStatus::Canceled, None),
+        }
+    }
+
+    /// Start times of the walks to schedule in a period: each preferred day
+    /// of the week at the preferred time, in the subscription's timezone, up
+    /// to the walks the period grants
+    pub fn walk_starts(
+        &self,
+        period_start: DateTime<Utc>,
+        period_end: DateTime<Utc>,
+    ) -> Vec<DateTime<Utc>> {
+        let (Some(walks), Some(day), Some(time)) = (
+            self.walks_per_period,
+            self.preferred_day_of_week,
+            self.preferred_time,
+        ) else {
+            return Vec::new();
+        };
+        let Ok(tz) = self.timezone.parse::<Tz>() else {
+            return Vec::new();
+        };
+
+        let mut starts = Vec::new();
+        let mut date = period_start.with_timezone(&tz).date_naive();
+        let last = period_end.with_timezone(&tz).date_naive();
+        while date <= last && starts.len() < walks.max(0) as usize {
+            if date.weekday().num_days_from_sunday() as i32 == day {
+                let start = tz
+                    .from_local_datetime(&date.and_time(time))
+                    .earliest()
+                    .map(|start| start.with_timezone(&Utc));
+                if let Some(start) = start.filter(|s| *s >= period_start && *s < period_end) {
+                    starts.push(start);
+                }
+            }
+            date = match date.succ_opt() {
+                Some(next) => next,
+                None => break,
+            };
+        }
+        starts
+    }
+}
+
+/// What a package credit entry records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "package_credit_kind", rename_all = "snake_case")]
+pub enum PackageCreditKind {
+    /// Credits for a paid period
+    Grant,
+    /// A credit used for a booking
+    Redeem,
+    /// A credit returned when its booking was cancelled in time
+    Restore,
+    /// Credits left unused when their period ended
+    Expire,
+}
+
+impl std::fmt::Display for PackageCreditKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PackageCreditKind::Grant => write!(f, "grant"),
+            PackageCreditKind::Redeem => write!(f, "redeem"),
+            PackageCreditKind::Restore => write!(f, "restore"),
+            PackageCreditKind::Expire => write!(f, "expire"),
+        }
+    }
+}
+
+/// A change to a package subscription's walk credit balance
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct PackageCredit {
+    pub id: Uuid,
+    pub organization_id: OrganizationId,
+    pub subscription_id: Uuid,
+    pub kind: PackageCreditKind,
+    pub credits: i32,
+    pub transaction_id: Option<Uuid>,
+    pub booking_id: Option<BookingId>,
+    pub period_end: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A service package a manager offers customers
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct PackagePlan {
+    pub id: Uuid,
+    pub organization_id: OrganizationId,
+    pub service_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub price_cents: i32,
+    pub interval: String,
+    pub interval_count: i32,
+    pub walks_per_period: i32,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Input for creating a package plan
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreatePackagePlan {
+    pub service_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub price_cents: i32,
+    pub interval: String,
+    pub interval_count: i32,
+    pub walks_per_period: i32,
 }
 
 /// Input for creating a customer subscription
 #[derive(Debug, Clone, Deserialize)]
 pub struct CreateCustomerSubscription {
+    pub package_plan_id: Option<Uuid>,
     pub service_id: Option<Uuid>,
     pub name: String,
     pub description: Option<String>,
@@ -233,6 +477,11 @@ pub struct CreateCustomerSubscription {
     pub auto_create_bookings: bool,
     pub preferred_day_of_week: Option<i32>,
     pub preferred_time: Option<NaiveTime>,
+    pub walks_per_period: Option<i32>,
+    pub preferred_walker_id: Option<UserId>,
+    pub location_id: Option<LocationId>,
+    pub timezone: String,
+    pub payment_method_id: Option<Uuid>,
 }
 
 /// Input for updating a customer subscription
@@ -254,3 +503,195 @@ pub struct UpdateCustomerSubscription {
     pub preferred_time: Option<NaiveTime>,
     pub metadata: Option<serde_json::Value>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscription(status: SubscriptionStatus) -> TenantSubscription {
+        let now = Utc::now();
+        TenantSubscription {
+            id: Uuid::new_v4(),
+            organization_id: OrganizationId::new(),
+            plan_tier: PlanTier::Business,
+            stripe_subscription_id: Some("sub_1".to_string()),
+            stripe_customer_id: Some("cus_1".to_string()),
+            status,
+            current_period_start: Some(now),
+            current_period_end: Some(now + chrono::Duration::days(30)),
+            trial_start: None,
+            trial_end: None,
+            cancel_at_period_end: false,
+            canceled_at: None,
+            metadata: None,
+            created_at: now,
+            updated_at: now,
+            past_due_since: None,
+            restricted_at: None,
+        }
+    }
+
+    #[test]
+    fn test_status_from_provider() {
+        assert_eq!(
+            SubscriptionStatus::from_provider("trialing"),
+            SubscriptionStatus::Trialing
+        );
+        assert_eq!(
+            SubscriptionStatus::from_provider("unpaid"),
+            SubscriptionStatus::PastDue
+        );
+        assert_eq!(
+            SubscriptionStatus::from_provider("incomplete_expired"),
+            SubscriptionStatus::Canceled
+        );
+        assert_eq!(
+            SubscriptionStatus::from_provider("incomplete"),
+            SubscriptionStatus::Incomplete
+        );
+    }
+
+    #[test]
+    fn test_restriction_due_after_grace_period() {
+        let now = Utc::now();
+        let mut sub = subscription(SubscriptionStatus::PastDue);
+        assert!(!sub.restriction_due(now));
+
+        sub.past_due_since = Some(now - chrono::Duration::days(DUNNING_GRACE_DAYS - 1));
+        assert!(!sub.restriction_due(now));
+        assert_eq!(sub.effective_plan_tier(), PlanTier::Business);
+
+        sub.past_due_since = Some(now - chrono::Duration::days(DUNNING_GRACE_DAYS));
+        assert!(sub.restriction_due(now));
+
+        sub.restricted_at = Some(now);
+        assert!(!sub.restriction_due(now));
+        assert_eq!(sub.effective_plan_tier(), PlanTier::Free);
+    }
+
+    #[test]
+    fn test_effective_plan_tier() {
+        assert_eq!(
+            subscription(SubscriptionStatus::Trialing).effective_plan_tier(),
+            PlanTier::Business
+        );
+        assert_eq!(
+            subscription(SubscriptionStatus::Canceled).effective_plan_tier(),
+            PlanTier::Free
+        );
+        assert_eq!(
+            subscription(SubscriptionStatus::Incomplete).effective_plan_tier(),
+            PlanTier::Free
+        );
+    }
+
+    fn package(interval: &str) -> CustomerSubscription {
+        let now = Utc::now();
+        CustomerSubscription {
+            id: Uuid::new_v4(),
+            organization_id: OrganizationId::new(),
+            user_id: UserId::new(),
+            service_id: Some(Uuid::new_v4()),
+            name: "10 walks a month".to_string(),
+            description: None,
+            price_cents: 20000,
+            interval: interval.to_string(),
+            interval_count: 1,
+            stripe_subscription_id: None,
+            stripe_price_id: None,
+            square_subscription_id: None,
+            status: SubscriptionStatus::Active,
+            current_period_start: Some(now - chrono::Duration::days(30)),
+            current_period_end: Some(now),
+            cancel_at_period_end: false,
+            canceled_at: None,
+            auto_create_bookings: true,
+            preferred_day_of_week: Some(0),
+            preferred_time: NaiveTime::from_hms_opt(9, 0, 0),
+            metadata: None,
+            created_at: now,
+            updated_at: now,
+            walks_per_period: Some(10),
+            preferred_walker_id: Some(UserId::new()),
+            location_id: None,
+            timezone: "America/Denver".to_string(),
+            payment_method_id: Some(Uuid::new_v4()),
+            failed_payment_attempts: 0,
+            next_payment_attempt_at: None,
+            package_plan_id: None,
+        }
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_package_period_after() {
+        let start = utc("2026-01-31T12:00:00Z");
+        assert_eq!(
+            package("month").period_after(start),
+            utc("2026-02-28T12:00:00Z")
+        );
+        assert_eq!(
+            package("week").period_after(start),
+            utc("2026-02-07T12:00:00Z")
+        );
+        assert_eq!(
+            package("year").period_after(start),
+            utc("2027-01-31T12:00:00Z")
+        );
+    }
+
+    #[test]
+    fn test_package_renewal_and_retries() {
+        let now = Utc::now();
+        let mut sub = package("month");
+        sub.current_period_end = Some(now);
+        assert!(sub.renewal_due(now));
+        assert!(!sub.renewal_due(now - chrono::Duration::hours(1)));
+
+        let (status, retry_at) = sub.after_failed_payment(now);
+        assert_eq!(status, SubscriptionStatus::PastDue);
+        assert_eq!(retry_at, Some(now + chrono::Duration::days(1)));
+
+        sub.status = SubscriptionStatus::PastDue;
+        sub.failed_payment_attempts = 1;
+        sub.next_payment_attempt_at = retry_at;
+        assert!(!sub.renewal_due(now));
+        assert!(sub.renewal_due(now + chrono::Duration::days(1)));
+
+        sub.failed_payment_attempts = PACKAGE_PAYMENT_RETRY_DAYS.len() as i32;
+        assert_eq!(
+            sub.after_failed_payment(now),
+            (SubscriptionStatus::Canceled, None)
+        );
+
+        sub.status = SubscriptionStatus::Canceled;
+        assert!(!sub.renewal_due(now + chrono::Duration::days(30)));
+    }
+
+    #[test]
+    fn test_package_walk_starts() {
+        let mut sub = package("month");
+        let start = utc("2026-01-01T00:00:00Z");
+        let end = utc("2026-02-01T00:00:00Z");
+
+        // Sundays in January at 9am Denver time (UTC-7)
+        assert_eq!(
+            sub.walk_starts(start, end),
+            vec![
+                utc("2026-01-04T16:00:00Z"),
+                utc("2026-01-11T16:00:00Z"),
+                utc("2026-01-18T16:00:00Z"),
+                utc("2026-01-25T16:00:00Z"),
+            ]
+        );
+
+        sub.walks_per_period = Some(2);
+        assert_eq!(sub.walk_starts(start, end).len(), 2);
+
+        sub.preferred_time = None;
+        assert!(sub.walk_starts(start, end).is_empty());
+    }
+}
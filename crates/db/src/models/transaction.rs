@@ -1,20 +1,27 @@
 use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use shared::types::{BookingId, OrganizationId, UserId};
+use shared::types::{BookingId, Currency, Money, MoneyError, OrganizationId, RoundingMode, UserId};
 use sqlx::FromRow;
 use uuid::Uuid;
 
+use super::{CardRegion, PaymentMethodType, PaymentProviderType};
+
 /// Transaction status enum
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "transaction_status", rename_all = "snake_case")]
 pub enum TransactionStatus {
     Pending,
     Processing,
+    /// Funds are held at the provider and waiting to be captured
+    Authorized,
     Succeeded,
     Failed,
     Refunded,
     PartiallyRefunded,
     Disputed,
+    /// The authorization was released without capturing
+    Canceled,
 }
 
 impl std::fmt::Display for TransactionStatus {
@@ -22,15 +29,30 @@ impl std::fmt::Display for TransactionStatus {
         match self {
             TransactionStatus::Pending => write!(f, "pending"),
             TransactionStatus::Processing => write!(f, "processing"),
+            TransactionStatus::Authorized => write!(f, "authorized"),
             TransactionStatus::Succeeded => write!(f, "succeeded"),
             TransactionStatus::Failed => write!(f, "failed"),
             TransactionStatus::Refunded => write!(f, "refunded"),
             TransactionStatus::PartiallyRefunded => write!(f, "partially_refunded"),
             TransactionStatus::Disputed => write!(f, "disputed"),
+            TransactionStatus::Canceled => write!(f, "canceled"),
         }
     }
 }
 
+impl TransactionStatus {
+    /// Whether a transaction in this status still represents a payment for its
+    /// booking. A booking may only have one live transaction at a time (enforced
+    /// by a partial unique index); failed, released or fully refunded payments
+    /// can be retried with a new checkout.
+    pub fn is_live(&self) -> bool {
+        !matches!(
+            self,
+            TransactionStatus::Failed | TransactionStatus::Refunded | TransactionStatus::Canceled
+        )
+    }
+}
+
 /// Transaction database model - comprehensive payment transaction records
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct Transaction {
@@ -38,7 +60,9 @@ pub struct Transaction {
     pub organization_id: OrganizationId,
     pub booking_id: Option<BookingId>,
     pub customer_user_id: UserId,
-    pub provider_user_id: UserId,
+    /// The walker paid for the transaction; `None` for package charges,
+    /// whose walks are credited to walkers as they're redeemed
+    pub provider_user_id: Option<UserId>,
     pub payment_method_id: Option<Uuid>,
     pub provider_id: Uuid,
 
@@ -52,8 +76,11 @@ pub struct Transaction {
     pub processing_fee_cents: i32,
     pub total_cents: i32,
     pub provider_payout_cents: i32,
+    /// `processing_fee_cents` is the fee the provider reported, rather than
+    /// our estimate from the fee schedule
+    pub processing_fee_reported: bool,
 
-    pub currency: String,
+    pub currency: Currency,
     pub status: TransactionStatus,
 
     // External payment reference (provider-agnostic)
@@ -70,10 +97,26 @@ pub struct Transaction {
     pub tax_rate_percent: Option<rust_decimal::Decimal>,
     pub tax_jurisdiction: Option<String>,
     pub tax_calculation_id: Option<String>,
+    /// When the sale was reported to the tax provider
+    pub tax_reported_at: Option<DateTime<Utc>>,
 
     // Refund tracking
     pub refunded_amount_cents: i32,
 
+    // Manual capture
+    pub authorized_at: Option<DateTime<Utc>>,
+    pub authorization_expires_at: Option<DateTime<Utc>>,
+    pub captured_at: Option<DateTime<Utc>>,
+
+    // Provider payment request
+    /// Idempotency key the payment is requested with; `None` uses the
+    /// transaction's ID
+    pub idempotency_key: Option<String>,
+    /// The payment is only authorized, to be captured later
+    pub manual_capture: bool,
+    /// The payment is made while the customer isn't present
+    pub off_session: bool,
+
     // Failure info
     pub failure_code: Option<String>,
     pub failure_message: Option<String>,
@@ -100,6 +143,19 @@ impl Transaction {
         self.provider_payout_cents as f64 / 100.0
     }
 
+    /// Check if funds are held and waiting to be captured
+    pub fn is_authorized(&self) -> bool {
+        self.status == TransactionStatus::Authorized
+    }
+
+    /// Check if the hold will be released by the provider before `at`
+    pub fn authorization_expires_before(&self, at: DateTime<Utc>) -> bool {
+        self.is_authorized()
+            && self
+                .authorization_expires_at
+                .is_some_and(|expires_at| expires_at <= at)
+    }
+
     /// Check if transaction is successful
     pub fn is_successful(&self) -> bool {
         self.status == TransactionStatus::Succeeded
@@ -121,6 +177,19 @@ impl Transaction {
             0
         }
     }
+
+    /// Booking the transaction paid for. Tips and fees charged for a change
+    /// to a booking reference it in their metadata.
+    pub fn related_booking_id(&self) -> Option<BookingId> {
+        self.booking_id.or_else(|| {
+            self.metadata
+                .as_ref()
+                .and_then(|m| m.get("booking_id"))
+                .and_then(|v| v.as_str())
+                .and_then(|id| id.parse::<Uuid>().ok())
+                .map(BookingId::from_uuid)
+        })
+    }
 }
 
 /// Input for creating a new transaction
@@ -128,7 +197,7 @@ impl Transaction {
 pub struct CreateTransaction {
     pub booking_id: Option<BookingId>,
     pub customer_user_id: UserId,
-    pub provider_user_id: UserId,
+    pub provider_user_id: Option<UserId>,
     pub payment_method_id: Option<Uuid>,
     pub provider_id: Uuid,
     pub subtotal_cents: i32,
@@ -140,11 +209,15 @@ pub struct CreateTransaction {
     pub processing_fee_cents: i32,
     pub total_cents: i32,
     pub provider_payout_cents: i32,
-    pub currency: String,
+    pub currency: Currency,
     pub tax_rate_percent: Option<rust_decimal::Decimal>,
     pub tax_jurisdiction: Option<String>,
+    pub tax_calculation_id: Option<String>,
     pub description: Option<String>,
     pub metadata: Option<serde_json::Value>,
+    pub idempotency_key: Option<String>,
+    pub manual_capture: bool,
+    pub off_session: bool,
 }
 
 /// Input for updating a transaction
@@ -163,71 +236,326 @@ pub struct UpdateTransaction {
     pub metadata: Option<serde_json::Value>,
 }
 
+/// What a payment processor charges to process a payment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessingFee {
+    /// Percentage of the amount charged (e.g. 2.9 for 2.9%)
+    pub percent: Decimal,
+    /// Fixed charge per payment, in the currency's minor unit
+    pub fixed_cents: i64,
+    /// Most charged for a single payment, if capped (e.g. ACH)
+    pub cap_cents: Option<i64>,
+}
+
+impl ProcessingFee {
+    /// Typical Stripe/Square US card pricing: 2.9% + 30 cents
+    pub fn standard() -> Self {
+        Self::new(Decimal::new(29, 1), 30)
+    }
+
+    pub fn new(percent: Decimal, fixed_cents: i64) -> Self {
+        Self {
+            percent,
+            fixed_cents,
+            cap_cents: None,
+        }
+    }
+
+    /// Limit the fee for a single payment to `cap_cents`
+    pub fn capped_at(self, cap_cents: i64) -> Self {
+        Self {
+            cap_cents: Some(cap_cents),
+            ..self
+        }
+    }
+
+    /// Approximate published pricing, used when an organization hasn't
+    /// configured its own fee schedule
+    pub fn list_price(
+        provider: PaymentProviderType,
+        method: PaymentMethodType,
+        region: CardRegion,
+        currency: Currency,
+    ) -> Self {
+        let international = region == CardRegion::International;
+        match (provider, method) {
+            // ACH and other bank debits
+            (PaymentProviderType::Square, PaymentMethodType::BankAccount) => {
+                Self::new(Decimal::ONE, 0)
+            }
+            (_, PaymentMethodType::BankAccount) => Self::new(Decimal::new(8, 1), 0).capped_at(500),
+            // Cards and wallets, which are charged as cards
+            (PaymentProviderType::Square, _) => match (currency, international) {
+                (Currency::USD | Currency::CAD, _) => Self::standard(),
+                (Currency::GBP | Currency::EUR, false) => Self::new(Decimal::new(14, 1), 25),
+                (Currency::GBP | Currency::EUR, true) => Self::new(Decimal::new(25, 1), 25),
+            },
+            (PaymentProviderType::Stripe | PaymentProviderType::Platform, _) => {
+                let domestic = match currency {
+                    Currency::USD | Currency::CAD => Self::standard(),
+                    Currency::GBP => Self::new(Decimal::new(15, 1), 20),
+                    Currency::EUR => Self::new(Decimal::new(15, 1), 25),
+                };
+                if international {
+                    // Cross-border cards cost an extra 1.5%
+                    Self::new(domestic.percent + Decimal::new(15, 1), domestic.fixed_cents)
+                } else {
+                    domestic
+                }
+            }
+        }
+    }
+
+    /// Processing fee for charging `amount`. Nothing is charged, nothing is
+    /// owed.
+    pub fn apply(&self, amount: Money) -> Result<Money, MoneyError> {
+        if amount.is_zero() {
+            return Ok(Money::zero_in(amount.currency()));
+        }
+        let fee = amount
+            .percent(self.percent, RoundingMode::HalfUp)?
+            .checked_add(Money::new(self.fixed_cents, amount.currency()))?;
+        Ok(match self.cap_cents {
+            Some(cap) if fee.cents() > cap => Money::new(cap, amount.currency()),
+            _ => fee,
+        })
+    }
+}
+
 /// Transaction fee breakdown for display
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransactionFeeBreakdown {
-    pub subtotal_cents: i32,
-    pub customer_fee_cents: i32,
-    pub customer_fee_percent: f64,
-    pub provider_fee_cents: i32,
-    pub provider_fee_percent: f64,
-    pub platform_fee_cents: i32,
-    pub tax_cents: i32,
-    pub tax_rate_percent: f64,
-    pub processing_fee_cents: i32,
-    pub total_cents: i32,
-    pub provider_payout_cents: i32,
+    pub subtotal: Money,
+    pub customer_fee: Money,
+    /// Fraction of the subtotal charged to the customer (e.g. 0.03)
+    pub customer_fee_rate: Decimal,
+    pub provider_fee: Money,
+    /// Fraction of the subtotal taken from the provider (e.g. 0.20)
+    pub provider_fee_rate: Decimal,
+    pub platform_fee: Money,
+    pub tax: Money,
+    pub tax_rate_percent: Decimal,
+    pub processing_fee: Money,
+    pub total: Money,
+    pub provider_payout: Money,
 }
 
 impl TransactionFeeBreakdown {
+    /// Platform fees round half-to-even so rounding doesn't favour either
+    /// side across many transactions
+    pub const FEE_ROUNDING: RoundingMode = RoundingMode::Bankers;
+
+    /// Sales tax rounds half up, as most jurisdictions require
+    pub const TAX_ROUNDING: RoundingMode = RoundingMode::HalfUp;
+
     /// Calculate fee breakdown from amounts
     ///
     /// Fee model:
     /// - Customer pays: subtotal + customer_fee + tax = total
-    /// - Provider receives: subtotal - provider_fee - processing_fee = provider_payout
-    /// - Platform earns: customer_fee + provider_fee (platform_fee)
+    /// - Provider receives: subtotal - provider_fee = provider_payout
+    /// - Platform earns: customer_fee + provider_fee (platform_fee), and
+    ///   bears the processing fee on the total
+    ///
+    /// Fails if an amount does not fit in `i64` minor units.
     pub fn calculate(
-        subtotal_cents: i32,
-        customer_fee_percent: f64,
-        provider_fee_percent: f64,
-        tax_rate_percent: f64,
-        processing_fee_percent: f64,
-    ) -> Self {
+        subtotal: Money,
+        customer_fee_rate: Decimal,
+        provider_fee_rate: Decimal,
+        tax_rate_percent: Decimal,
+        processing_fee: ProcessingFee,
+    ) -> Result<Self, MoneyError> {
         // Customer service fee (paid by customer)
-        let customer_fee_cents = ((subtotal_cents as f64) * customer_fee_percent).round() as i32;
+        let customer_fee = subtotal.multiply(customer_fee_rate, Self::FEE_ROUNDING)?;
 
         // Tax on subtotal + customer fee
-        let taxable_amount = subtotal_cents + customer_fee_cents;
-        let tax_cents = ((taxable_amount as f64) * (tax_rate_percent / 100.0)).round() as i32;
+        let taxable = subtotal.checked_add(customer_fee)?;
+        let tax = taxable.percent(tax_rate_percent, Self::TAX_ROUNDING)?;
 
         // Total paid by customer
-        let total_cents = subtotal_cents + customer_fee_cents + tax_cents;
+        let total = taxable.checked_add(tax)?;
 
         // Provider fee (taken from provider's share)
-        let provider_fee_cents = ((subtotal_cents as f64) * provider_fee_percent).round() as i32;
-
-        // Processing fee (Stripe/Square - typically 2.9% + $0.30)
-        let processing_fee_cents =
-            ((total_cents as f64) * (processing_fee_percent / 100.0)).round() as i32 + 30;
+        let provider_fee = subtotal.multiply(provider_fee_rate, Self::FEE_ROUNDING)?;
 
         // Platform total revenue (customer fee + provider fee)
-        let platform_fee_cents = customer_fee_cents + provider_fee_cents;
-
-        // What provider receives
-        let provider_payout_cents = subtotal_cents - provider_fee_cents;
+        let platform_fee = customer_fee.checked_add(provider_fee)?;
 
-        Self {
-            subtotal_cents,
-            customer_fee_cents,
-            customer_fee_percent,
-            provider_fee_cents,
-            provider_fee_percent,
-            platform_fee_cents,
-            tax_cents,
+        Ok(Self {
+            subtotal,
+            customer_fee,
+            customer_fee_rate,
+            provider_fee,
+            provider_fee_rate,
+            platform_fee,
+            tax,
             tax_rate_percent,
-            processing_fee_cents,
-            total_cents,
-            provider_payout_cents,
+            processing_fee: processing_fee.apply(total)?,
+            total,
+            // What provider receives
+            provider_payout: subtotal.checked_sub(provider_fee)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_fee_breakdown() {
+        let breakdown = TransactionFeeBreakdown::calculate(
+            Money::from_cents(10_000),
+            Decimal::new(3, 2),
+            Decimal::new(20, 2),
+            Decimal::new(725, 2),
+            ProcessingFee::standard(),
+        )
+        .unwrap();
+
+        assert_eq!(breakdown.customer_fee.cents(), 300);
+        assert_eq!(breakdown.tax.cents(), 747); // 7.25% of 103.00 = 7.4675
+        assert_eq!(breakdown.total.cents(), 11_047);
+        assert_eq!(breakdown.provider_fee.cents(), 2_000);
+        assert_eq!(breakdown.platform_fee.cents(), 2_300);
+        assert_eq!(breakdown.provider_payout.cents(), 8_000);
+        assert_eq!(breakdown.processing_fee.cents(), 350); // 2.9% of 110.47 + 0.30
+    }
+
+    #[test]
+    fn test_fee_breakdown_rounds_fees_to_even() {
+        // 3% of $0.50 is 1.5 cents
+        let breakdown = TransactionFeeBreakdown::calculate(
+            Money::from_cents(50),
+            Decimal::new(3, 2),
+            Decimal::ZERO,
+            Decimal::ZERO,
+            ProcessingFee::standard(),
+        )
+        .unwrap();
+        assert_eq!(breakdown.customer_fee.cents(), 2);
+    }
+
+    #[test]
+    fn test_no_processing_fee_on_nothing() {
+        let breakdown = TransactionFeeBreakdown::calculate(
+            Money::zero(),
+            Decimal::new(3, 2),
+            Decimal::new(20, 2),
+            Decimal::new(6, 0),
+            ProcessingFee::standard(),
+        )
+        .unwrap();
+        assert!(breakdown.total.is_zero());
+        assert!(breakdown.processing_fee.is_zero());
+    }
+
+    #[test]
+    fn test_fee_breakdown_overflow_is_an_error() {
+        let breakdown = TransactionFeeBreakdown::calculate(
+            Money::from_cents(i64::MAX - 100),
+            Decimal::new(3, 2),
+            Decimal::new(20, 2),
+            Decimal::new(725, 2),
+            ProcessingFee::standard(),
+        );
+        assert_eq!(breakdown.unwrap_err(), MoneyError::Overflow);
+    }
+
+    #[test]
+    fn test_processing_fee_cap() {
+        let ach = ProcessingFee::list_price(
+            PaymentProviderType::Stripe,
+            PaymentMethodType::BankAccount,
+            CardRegion::Domestic,
+            Currency::USD,
+        );
+        // 0.8% of $100.00, then capped at $5.00 on $1,000.00
+        assert_eq!(ach.apply(Money::from_cents(10_000)).unwrap().cents(), 80);
+        assert_eq!(ach.apply(Money::from_cents(100_000)).unwrap().cents(), 500);
+    }
+
+    #[test]
+    fn test_list_price_by_region_and_currency() {
+        let card = |region, currency| {
+            ProcessingFee::list_price(
+                PaymentProviderType::Stripe,
+                PaymentMethodType::Card,
+                region,
+                currency,
+            )
+        };
+
+        assert_eq!(
+            card(CardRegion::Domestic, Currency::USD),
+            ProcessingFee::standard()
+        );
+        assert_eq!(
+            card(CardRegion::International, Currency::USD),
+            ProcessingFee::new(Decimal::new(44, 1), 30)
+        );
+        assert_eq!(
+            card(CardRegion::Domestic, Currency::GBP),
+            ProcessingFee::new(Decimal::new(15, 1), 20)
+        );
+        // Wallets are priced as the card behind them
+        assert_eq!(
+            ProcessingFee::list_price(
+                PaymentProviderType::Stripe,
+                PaymentMethodType::ApplePay,
+                CardRegion::Domestic,
+                Currency::EUR,
+            ),
+            card(CardRegion::Domestic, Currency::EUR)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_components_sum_to_total(
+            subtotal_cents in 0i64..100_000_000,
+            customer_bps in 0i64..2_000,
+            provider_bps in 0i64..5_000,
+            tax_hundredths in 0i64..1_500,
+            currency in prop::sample::select(Currency::ALL.to_vec()),
+        ) {
+            let breakdown = TransactionFeeBreakdown::calculate(
+                Money::new(subtotal_cents, currency),
+                Decimal::new(customer_bps, 4),
+                Decimal::new(provider_bps, 4),
+                Decimal::new(tax_hundredths, 2),
+                ProcessingFee::standard(),
+            )
+            .unwrap();
+            let sum = |amounts: &[Money]| {
+                amounts
+                    .iter()
+                    .try_fold(Money::zero_in(currency), |sum, a| sum.checked_add(*a))
+                    .unwrap()
+            };
+
+            prop_assert_eq!(
+                sum(&[breakdown.subtotal, breakdown.customer_fee, breakdown.tax]),
+                breakdown.total
+            );
+            prop_assert_eq!(
+                sum(&[breakdown.customer_fee, breakdown.provider_fee]),
+                breakdown.platform_fee
+            );
+            prop_assert_eq!(
+                sum(&[breakdown.provider_payout, breakdown.provider_fee]),
+                breakdown.subtotal
+            );
+            // Where the customer's money goes: the provider, the platform and
+            // the tax authority
+            prop_assert_eq!(
+                sum(&[breakdown.provider_payout, breakdown.platform_fee, breakdown.tax]),
+                breakdown.total
+            );
+            prop_assert_eq!(breakdown.processing_fee.currency(), currency);
+            prop_assert!(!breakdown.customer_fee.cents().is_negative());
+            prop_assert!(!breakdown.tax.cents().is_negative());
+            prop_assert!(!breakdown.provider_payout.cents().is_negative());
         }
     }
 }
@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use shared::geometry::TrackPoint;
+use shared::types::{BookingId, Coordinates, OrganizationId, UserId};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+/// Default number of days walk trails are kept when an organization hasn't
+/// configured a retention period
+pub const DEFAULT_WALK_TRAIL_RETENTION_DAYS: i32 = 90;
+
+/// A GPS fix recorded during an in-progress walk
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct WalkBreadcrumb {
+    pub id: Uuid,
+    pub organization_id: OrganizationId,
+    pub booking_id: BookingId,
+    pub walker_id: UserId,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub accuracy_meters: Option<f64>,
+    pub speed_mps: Option<f64>,
+    pub recorded_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<&WalkBreadcrumb> for TrackPoint {
+    fn from(crumb: &WalkBreadcrumb) -> Self {
+        TrackPoint {
+            coordinates: Coordinates::new_unchecked(crumb.latitude, crumb.longitude),
+            recorded_at: crumb.recorded_at,
+            accuracy_meters: crumb.accuracy_meters,
+        }
+    }
+}
+
+/// Input for recording a breadcrumb
+#[derive(Debug, Clone)]
+pub struct CreateWalkBreadcrumb {
+    pub organization_id: OrganizationId,
+    pub booking_id: BookingId,
+    pub walker_id: UserId,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub accuracy_meters: Option<f64>,
+    pub speed_mps: Option<f64>,
+    pub recorded_at: DateTime<Utc>,
+}
@@ -0,0 +1,468 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use shared::types::{Currency, Money, MoneyError, OrganizationId, RoundingMode, ServiceId, UserId};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use super::{PayoutStatus, RefundAllocation, Transaction};
+
+/// How a walker's share of a walk is worked out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "revenue_split_type", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum RevenueSplitType {
+    /// A percentage of the service price
+    Percent,
+    /// A fixed amount per walk
+    Flat,
+}
+
+/// A walker's share of their walks, for one service or (without a service)
+/// all of them
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct WalkerRevenueSplit {
+    pub id: Uuid,
+    pub organization_id: OrganizationId,
+    pub walker_id: UserId,
+    pub service_id: Option<ServiceId>,
+    pub split_type: RevenueSplitType,
+    pub percent: Option<Decimal>,
+    pub flat_cents: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl WalkerRevenueSplit {
+    /// Walker's share of a walk priced at `price_cents`, out of the
+    /// `available_cents` the organization kept for it after platform fees
+    pub fn walker_share_cents(
+        &self,
+        price_cents: i32,
+        available_cents: i32,
+        currency: Currency,
+    ) -> Result<i32, MoneyError> {
+        let share = match self.split_type {
+            RevenueSplitType::Percent => {
+                let percent = self.percent.unwrap_or(Decimal::ONE_HUNDRED);
+                let share = Money::new(price_cents.into(), currency)
+                    .percent(percent, RoundingMode::HalfUp)?
+                    .cents();
+                i32::try_from(share).map_err(|_| MoneyError::Overflow)?
+            }
+            RevenueSplitType::Flat => self.flat_cents.unwrap_or(0),
+        };
+        Ok(share.clamp(0, available_cents.max(0)))
+    }
+}
+
+/// Input for setting a walker's revenue split
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpsertRevenueSplit {
+    pub service_id: Option<ServiceId>,
+    pub split_type: RevenueSplitType,
+    pub percent: Option<Decimal>,
+    pub flat_cents: Option<i32>,
+}
+
+/// Stripe Connect account a walker is paid through
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct WalkerPayoutAccount {
+    pub organization_id: OrganizationId,
+    pub walker_id: UserId,
+    pub stripe_account_id: String,
+    pub payouts_enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// What a walker earning records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "walker_earning_kind", rename_all = "snake_case")]
+pub enum WalkerEarningKind {
+    /// The walker's share of a walk
+    Walk,
+    Tip,
+    /// Earnings taken back by a refund
+    Refund,
+    /// Earnings taken back by a lost dispute
+    Dispute,
+}
+
+impl std::fmt::Display for WalkerEarningKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WalkerEarningKind::Walk => write!(f, "walk"),
+            WalkerEarningKind::Tip => write!(f, "tip"),
+            WalkerEarningKind::Refund => write!(f, "refund"),
+            WalkerEarningKind::Dispute => write!(f, "dispute"),
+        }
+    }
+}
+
+/// An amount a walker earned, or had taken back, on a transaction
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct WalkerEarning {
+    pub id: Uuid,
+    pub organization_id: OrganizationId,
+    pub walker_id: UserId,
+    pub transaction_id: Uuid,
+    /// The transaction, refund or dispute recorded
+    pub source_id: Uuid,
+    pub kind: WalkerEarningKind,
+    /// Negative for refunds and lost disputes
+    pub amount_cents: i32,
+    pub currency: Currency,
+    /// Payout that paid this earning to the walker
+    pub walker_payout_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An earning to record
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewWalkerEarning {
+    pub walker_id: UserId,
+    pub transaction_id: Uuid,
+    pub source_id: Uuid,
+    pub kind: WalkerEarningKind,
+    pub amount_cents: i32,
+    pub currency: Currency,
+}
+
+impl NewWalkerEarning {
+    /// What the walker earns from a captured transaction: their split of the
+    /// service share and all of the tip. Without a split the walker earns the
+    /// whole service share.
+    pub fn for_charge(
+        transaction: &Transaction,
+        split: Option<&WalkerRevenueSplit>,
+    ) -> Result<Vec<Self>, MoneyError> {
+        let Some(walker_id) = transaction.provider_user_id else {
+            return Ok(Vec::new());
+        };
+        let service_share = service_share_cents(transaction);
+        let walk_cents = match split {
+            Some(split) => split.walker_share_cents(
+                transaction.subtotal_cents,
+                service_share,
+                transaction.currency,
+            )?,
+            None => service_share,
+        };
+
+        Ok([
+            (WalkerEarningKind::Walk, walk_cents),
+            (WalkerEarningKind::Tip, transaction.tip_cents),
+        ]
+        .into_iter()
+        .filter(|(_, amount_cents)| *amount_cents > 0)
+        .map(|(kind, amount_cents)| Self {
+            walker_id,
+            transaction_id: transaction.id,
+            source_id: transaction.id,
+            kind,
+            amount_cents,
+            currency: transaction.currency,
+        })
+        .collect())
+    }
+
+    /// What a walker earns for a package walk paid for with a credit: their
+    /// split of one of the period's `walks` out of the package charge
+    pub fn for_redemption(
+        transaction: &Transaction,
+        walks: i32,
+        walker_id: UserId,
+        credit_id: Uuid,
+        split: Option<&WalkerRevenueSplit>,
+    ) -> Result<Option<Self>, MoneyError> {
+        if walks <= 0 {
+            return Ok(None);
+        }
+        let service_share = service_share_cents(transaction) / walks;
+        let amount_cents = match split {
+            Some(split) => split.walker_share_cents(
+                transaction.subtotal_cents / walks,
+                service_share,
+                transaction.currency,
+            )?,
+            None => service_share,
+        };
+
+        Ok((amount_cents > 0).then_some(Self {
+            walker_id,
+            transaction_id: transaction.id,
+            source_id: credit_id,
+            kind: WalkerEarningKind::Walk,
+            amount_cents,
+            currency: transaction.currency,
+        }))
+    }
+
+    /// Takes back an earning in full, e.g. when a package credit is returned
+    /// for a walk that didn't happen
+    pub fn for_return(earning: &WalkerEarning, source_id: Uuid) -> Self {
+        Self {
+            walker_id: earning.walker_id,
+            transaction_id: earning.transaction_id,
+            source_id,
+            kind: WalkerEarningKind::Refund,
+            amount_cents: -earning.amount_cents,
+            currency: earning.currency,
+        }
+    }
+
+    /// Earnings a refund or lost dispute takes back, as allocated against
+    /// the transaction: all of the refunded tip, and the walker's proportion
+    /// of the service share reversed
+    pub fn for_reversal(
+        transaction: &Transaction,
+        kind: WalkerEarningKind,
+        source_id: Uuid,
+        walk_earned_cents: i32,
+        allocation: &RefundAllocation,
+    ) -> Option<Self> {
+        let walker_id = transaction.provider_user_id?;
+        let service_share = i64::from(service_share_cents(transaction));
+        let service_reversed =
+            i64::from((allocation.provider_payout_cents - allocation.tip_cents).max(0));
+        let walk_reversed = if service_share > 0 {
+            // Round half up
+            (service_reversed * i64::from(walk_earned_cents) * 2 + service_share)
+                / (2 * service_share)
+        } else {
+            0
+        };
+
+        let amount_cents = i32::try_from(walk_reversed).unwrap_or(i32::MAX) + allocation.tip_cents;
+        (amount_cents > 0).then(|| Self {
+            walker_id,
+            transaction_id: transaction.id,
+            source_id,
+            kind,
+            amount_cents: -amount_cents,
+            currency: transaction.currency,
+        })
+    }
+}
+
+/// What the organization kept for the service itself, after platform fees
+fn service_share_cents(transaction: &Transaction) -> i32 {
+    (transaction.provider_payout_cents - transaction.tip_cents).max(0)
+}
+
+/// A walker's earnings over a period, in one currency
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct WalkerEarningsSummary {
+    pub currency: Currency,
+    pub walk_cents: i64,
+    pub tip_cents: i64,
+    /// Taken back by refunds and lost disputes (negative)
+    pub reversed_cents: i64,
+    pub earned_cents: i64,
+    /// Earned but not yet in a payout
+    pub unpaid_cents: i64,
+    pub walk_count: i64,
+}
+
+/// A walker's earnings not yet paid out, in one currency
+#[derive(Debug, Clone, FromRow)]
+pub struct UnpaidWalkerEarnings {
+    pub walker_id: UserId,
+    pub currency: Currency,
+    pub amount_cents: i64,
+    pub earning_count: i64,
+    pub period_start: DateTime<Utc>,
+}
+
+/// A payout of a walker's earnings
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct WalkerPayout {
+    pub id: Uuid,
+    pub organization_id: OrganizationId,
+    pub walker_id: UserId,
+    pub amount_cents: i32,
+    pub currency: Currency,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub status: PayoutStatus,
+    pub stripe_transfer_id: Option<String>,
+    pub failure_message: Option<String>,
+    pub earning_count: i32,
+    pub initiated_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TransactionStatus;
+
+    fn transaction(subtotal_cents: i32, tip_cents: i32, provider_payout_cents: i32) -> Transaction {
+        Transaction {
+            id: Uuid::new_v4(),
+            organization_id: OrganizationId::new(),
+            booking_id: None,
+            customer_user_id: UserId::new(),
+            provider_user_id: Some(UserId::new()),
+            payment_method_id: None,
+            provider_id: Uuid::new_v4(),
+            subtotal_cents,
+            tip_cents,
+            customer_fee_cents: 0,
+            provider_fee_cents: subtotal_cents + tip_cents - provider_payout_cents,
+            platform_fee_cents: 0,
+            tax_cents: 0,
+            processing_fee_cents: 0,
+            total_cents: subtotal_cents + tip_cents,
+            provider_payout_cents,
+            processing_fee_reported: false,
+            currency: Currency::USD,
+            status: TransactionStatus::Succeeded,
+            external_payment_id: None,
+            stripe_payment_intent_id: None,
+            stripe_charge_id: None,
+            stripe_transfer_id: None,
+            square_payment_id: None,
+            square_order_id: None,
+            tax_rate_percent: None,
+            tax_jurisdiction: None,
+            tax_calculation_id: None,
+            tax_reported_at: None,
+            refunded_amount_cents: 0,
+            authorized_at: None,
+            authorization_expires_at: None,
+            captured_at: None,
+            idempotency_key: None,
+            manual_capture: false,
+            off_session: false,
+            failure_code: None,
+            failure_message: None,
+            description: None,
+            metadata: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn split(
+        split_type: RevenueSplitType,
+        percent: Option<Decimal>,
+        flat_cents: Option<i32>,
+    ) -> WalkerRevenueSplit {
+        WalkerRevenueSplit {
+            id: Uuid::new_v4(),
+            organization_id: OrganizationId::new(),
+            walker_id: UserId::new(),
+            service_id: None,
+            split_type,
+            percent,
+            flat_cents,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn amounts(earnings: &[NewWalkerEarning]) -> Vec<(WalkerEarningKind, i32)> {
+        earnings.iter().map(|e| (e.kind, e.amount_cents)).collect()
+    }
+
+    #[test]
+    fn test_percent_split_with_full_tip() {
+        // $40 walk with a $5 tip; the platform kept $2
+        let txn = transaction(4000, 500, 4300);
+        let percent = split(RevenueSplitType::Percent, Some(Decimal::new(6250, 2)), None);
+
+        let earnings = NewWalkerEarning::for_charge(&txn, Some(&percent)).unwrap();
+        assert_eq!(
+            amounts(&earnings),
+            vec![
+                (WalkerEarningKind::Walk, 2500),
+                (WalkerEarningKind::Tip, 500)
+            ]
+        );
+        assert!(earnings
+            .iter()
+            .all(|e| Some(e.walker_id) == txn.provider_user_id && e.source_id == txn.id));
+    }
+
+    #[test]
+    fn test_flat_split_is_capped_at_service_share() {
+        let txn = transaction(4000, 0, 3800);
+        let flat = split(RevenueSplitType::Flat, None, Some(2000));
+        assert_eq!(
+            amounts(&NewWalkerEarning::for_charge(&txn, Some(&flat)).unwrap()),
+            vec![(WalkerEarningKind::Walk, 2000)]
+        );
+
+        let too_much = split(RevenueSplitType::Flat, None, Some(5000));
+        assert_eq!(
+            amounts(&NewWalkerEarning::for_charge(&txn, Some(&too_much)).unwrap()),
+            vec![(WalkerEarningKind::Walk, 3800)]
+        );
+    }
+
+    #[test]
+    fn test_no_split_earns_service_share_and_tips_only_charge() {
+        let txn = transaction(4000, 0, 3800);
+        assert_eq!(
+            amounts(&NewWalkerEarning::for_charge(&txn, None).unwrap()),
+            vec![(WalkerEarningKind::Walk, 3800)]
+        );
+
+        let tip = transaction(0, 1000, 1000);
+        let percent = split(RevenueSplitType::Percent, Some(Decimal::new(50, 0)), None);
+        assert_eq!(
+            amounts(&NewWalkerEarning::for_charge(&tip, Some(&percent)).unwrap()),
+            vec![(WalkerEarningKind::Tip, 1000)]
+        );
+    }
+
+    #[test]
+    fn test_reversal_takes_back_walker_proportion() {
+        let txn = transaction(4000, 500, 4300);
+        let refund_id = Uuid::new_v4();
+
+        // Half the walk refunded, no tip: the walker earned 2500 of the
+        // 3800 service share
+        let allocation = RefundAllocation {
+            subtotal_cents: 2000,
+            provider_payout_cents: 1900,
+            ..Default::default()
+        };
+        let reversal = NewWalkerEarning::for_reversal(
+            &txn,
+            WalkerEarningKind::Refund,
+            refund_id,
+            2500,
+            &allocation,
+        )
+        .unwrap();
+        assert_eq!(reversal.amount_cents, -1250);
+        assert_eq!(reversal.source_id, refund_id);
+
+        let full = RefundAllocation::for_refund(&txn, txn.total_cents);
+        let reversal = NewWalkerEarning::for_reversal(
+            &txn,
+            WalkerEarningKind::Dispute,
+            Uuid::new_v4(),
+            2500,
+            &full,
+        )
+        .unwrap();
+        assert_eq!(reversal.amount_cents, -3000);
+
+        assert_eq!(
+            NewWalkerEarning::for_reversal(
+                &txn,
+                WalkerEarningKind::Refund,
+                refund_id,
+                2500,
+                &RefundAllocation::default(),
+            ),
+            None
+        );
+    }
+}
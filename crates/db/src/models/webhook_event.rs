@@ -0,0 +1,156 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use shared::types::OrganizationId;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use super::PaymentProviderType;
+
+/// Attempts made to process a webhook event before it's dead-lettered
+pub const MAX_WEBHOOK_ATTEMPTS: i32 = 8;
+
+/// Delay before the first retry; each later retry waits twice as long
+const WEBHOOK_RETRY_BASE_SECONDS: i64 = 30;
+
+/// Longest delay between retries
+const WEBHOOK_RETRY_MAX_SECONDS: i64 = 6 * 60 * 60;
+
+/// How long an event may be processing before it's assumed abandoned and
+/// picked up again
+pub const WEBHOOK_PROCESSING_TIMEOUT_MINUTES: i64 = 10;
+
+/// Webhook event processing status enum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "webhook_event_status", rename_all = "snake_case")]
+pub enum WebhookEventStatus {
+    /// Received and waiting to be processed
+    Pending,
+    Processing,
+    Processed,
+    /// Failed and waiting to be retried
+    Failed,
+    /// Failed every attempt; only processed again when replayed
+    Dead,
+}
+
+impl std::fmt::Display for WebhookEventStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WebhookEventStatus::Pending => write!(f, "pending"),
+            WebhookEventStatus::Processing => write!(f, "processing"),
+            WebhookEventStatus::Processed => write!(f, "processed"),
+            WebhookEventStatus::Failed => write!(f, "failed"),
+            WebhookEventStatus::Dead => write!(f, "dead"),
+        }
+    }
+}
+
+/// A payment provider webhook event, stored when it arrives and processed
+/// by the webhook worker
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct PaymentWebhookEvent {
+    pub id: Uuid,
+    pub organization_id: Option<OrganizationId>,
+    pub provider: PaymentProviderType,
+    pub event_id: String,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    pub status: WebhookEventStatus,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    /// Token of the attempt that last claimed the event; only that attempt
+    /// records its outcome
+    pub claimed_by: Option<Uuid>,
+    pub processed: bool,
+    pub processed_at: Option<DateTime<Utc>>,
+    /// Error from the last failed attempt
+    pub error_message: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PaymentWebhookEvent {
+    /// Where an event goes after an attempt that failed: retried after a
+    /// backoff, or dead-lettered once it has used all its attempts
+    pub fn after_failure(&self, now: DateTime<Utc>) -> (WebhookEventStatus, DateTime<Utc>) {
+        if self.attempts >= MAX_WEBHOOK_ATTEMPTS {
+            (WebhookEventStatus::Dead, now)
+        } else {
+            (WebhookEventStatus::Failed, now + retry_delay(self.attempts))
+        }
+    }
+}
+
+/// Delay before retrying an event that has failed `attempts` times
+pub fn retry_delay(attempts: i32) -> Duration {
+    let exponent = u32::try_from(attempts.saturating_sub(1))
+        .unwrap_or(0)
+        .min(20);
+    let seconds = WEBHOOK_RETRY_BASE_SECONDS.saturating_mul(1 << exponent);
+    Duration::seconds(seconds.min(WEBHOOK_RETRY_MAX_SECONDS))
+}
+
+/// Input for recording a webhook event
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateWebhookEvent {
+    pub organization_id: OrganizationId,
+    pub provider: PaymentProviderType,
+    pub event_id: String,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(attempts: i32) -> PaymentWebhookEvent {
+        let now = Utc::now();
+        PaymentWebhookEvent {
+            id: Uuid::new_v4(),
+            organization_id: Some(OrganizationId::new()),
+            provider: PaymentProviderType::Stripe,
+            event_id: "evt_1".to_string(),
+            event_type: "payment_intent.succeeded".to_string(),
+            payload: serde_json::json!({}),
+            status: WebhookEventStatus::Processing,
+            attempts,
+            next_attempt_at: now,
+            last_attempt_at: Some(now),
+            claimed_by: Some(Uuid::new_v4()),
+            processed: false,
+            processed_at: None,
+            error_message: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off_exponentially() {
+        assert_eq!(retry_delay(1), Duration::seconds(30));
+        assert_eq!(retry_delay(2), Duration::seconds(60));
+        assert_eq!(retry_delay(4), Duration::seconds(240));
+        assert_eq!(retry_delay(12), Duration::hours(6));
+        assert_eq!(retry_delay(i32::MAX), Duration::hours(6));
+    }
+
+    #[test]
+    fn test_failed_event_dead_lettered_after_last_attempt() {
+        let now = Utc::now();
+
+        assert_eq!(
+            event(1).after_failure(now),
+            (WebhookEventStatus::Failed, now + Duration::seconds(30))
+        );
+        assert_eq!(
+            event(MAX_WEBHOOK_ATTEMPTS - 1).after_failure(now).0,
+            WebhookEventStatus::Failed
+        );
+        assert_eq!(
+            event(MAX_WEBHOOK_ATTEMPTS).after_failure(now),
+            (WebhookEventStatus::Dead, now)
+        );
+    }
+}
@@ -47,9 +47,9 @@ impl BookingRepository {
         // Insert the booking
         let booking = sqlx::query_as::<_, Booking>(
             r#"
-            INSERT INTO bookings (id, organization_id, customer_id, walker_id, service_id, location_id, scheduled_start, scheduled_end, price_cents, notes, recurring_series_id, occurrence_number)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
-            RETURNING id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, notes, recurring_series_id, occurrence_number, created_at, updated_at
+            INSERT INTO bookings (id, organization_id, customer_id, walker_id, service_id, location_id, scheduled_start, scheduled_end, price_cents, price_breakdown, notes, recurring_series_id, occurrence_number)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            RETURNING id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, price_breakdown, notes, recurring_series_id, occurrence_number, created_at, updated_at
             "#,
         )
         .bind(id.as_uuid())
@@ -61,6 +61,7 @@ impl BookingRepository {
         .bind(input.scheduled_start)
         .bind(input.scheduled_end)
         .bind(input.price_cents)
+        .bind(input.price_breakdown.as_ref().map(sqlx::types::Json))
         .bind(&input.notes)
         .bind(input.recurring_series_id.map(|id| *id.as_uuid()))
         .bind(input.occurrence_number)
@@ -81,9 +82,9 @@ impl BookingRepository {
 
         sqlx::query_as::<_, Booking>(
             r#"
-            INSERT INTO bookings (id, organization_id, customer_id, walker_id, service_id, location_id, scheduled_start, scheduled_end, price_cents, notes, recurring_series_id, occurrence_number)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
-            RETURNING id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, notes, recurring_series_id, occurrence_number, created_at, updated_at
+            INSERT INTO bookings (id, organization_id, customer_id, walker_id, service_id, location_id, scheduled_start, scheduled_end, price_cents, price_breakdown, notes, recurring_series_id, occurrence_number)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            RETURNING id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, price_breakdown, notes, recurring_series_id, occurrence_number, created_at, updated_at
             "#,
         )
         .bind(id.as_uuid())
@@ -95,6 +96,7 @@ impl BookingRepository {
         .bind(input.scheduled_start)
         .bind(input.scheduled_end)
         .bind(input.price_cents)
+        .bind(input.price_breakdown.as_ref().map(sqlx::types::Json))
         .bind(&input.notes)
         .bind(input.recurring_series_id.map(|id| *id.as_uuid()))
         .bind(input.occurrence_number)
@@ -109,7 +111,7 @@ impl BookingRepository {
     ) -> Result<Option<Booking>, sqlx::Error> {
         sqlx::query_as::<_, Booking>(
             r#"
-            SELECT id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, notes, recurring_series_id, occurrence_number, created_at, updated_at
+            SELECT id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, price_breakdown, notes, recurring_series_id, occurrence_number, created_at, updated_at
             FROM bookings
             WHERE id = $1 AND organization_id = $2
             "#,
@@ -129,7 +131,7 @@ impl BookingRepository {
     ) -> Result<Vec<Booking>, sqlx::Error> {
         sqlx::query_as::<_, Booking>(
             r#"
-            SELECT id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, notes, recurring_series_id, occurrence_number, created_at, updated_at
+            SELECT id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, price_breakdown, notes, recurring_series_id, occurrence_number, created_at, updated_at
             FROM bookings
             WHERE walker_id = $1
               AND organization_id = $2
@@ -154,7 +156,7 @@ impl BookingRepository {
     ) -> Result<Vec<Booking>, sqlx::Error> {
         sqlx::query_as::<_, Booking>(
             r#"
-            SELECT id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, notes, recurring_series_id, occurrence_number, created_at, updated_at
+            SELECT id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, price_breakdown, notes, recurring_series_id, occurrence_number, created_at, updated_at
             FROM bookings
             WHERE customer_id = $1 AND organization_id = $2
             ORDER BY scheduled_start DESC
@@ -174,7 +176,7 @@ impl BookingRepository {
     ) -> Result<Vec<Booking>, sqlx::Error> {
         sqlx::query_as::<_, Booking>(
             r#"
-            SELECT id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, notes, recurring_series_id, occurrence_number, created_at, updated_at
+            SELECT id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, price_breakdown, notes, recurring_series_id, occurrence_number, created_at, updated_at
             FROM bookings
             WHERE walker_id = $1 AND organization_id = $2
             ORDER BY scheduled_start DESC
@@ -197,7 +199,7 @@ impl BookingRepository {
             UPDATE bookings
             SET status = $3, updated_at = NOW()
             WHERE id = $1 AND organization_id = $2
-            RETURNING id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, notes, recurring_series_id, occurrence_number, created_at, updated_at
+            RETURNING id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, price_breakdown, notes, recurring_series_id, occurrence_number, created_at, updated_at
             "#,
         )
         .bind(id.as_uuid())
@@ -295,7 +297,7 @@ impl BookingRepository {
             Some(status) => {
                 sqlx::query_as::<_, Booking>(
                     r#"
-                    SELECT id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, notes, recurring_series_id, occurrence_number, created_at, updated_at
+                    SELECT id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, price_breakdown, notes, recurring_series_id, occurrence_number, created_at, updated_at
                     FROM bookings
                     WHERE organization_id = $1 AND status = $2
                     ORDER BY scheduled_start DESC
@@ -309,7 +311,7 @@ impl BookingRepository {
             None => {
                 sqlx::query_as::<_, Booking>(
                     r#"
-                    SELECT id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, notes, recurring_series_id, occurrence_number, created_at, updated_at
+                    SELECT id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, price_breakdown, notes, recurring_series_id, occurrence_number, created_at, updated_at
                     FROM bookings
                     WHERE organization_id = $1
                     ORDER BY scheduled_start DESC
@@ -322,13 +324,69 @@ impl BookingRepository {
         }
     }
 
-    /// Complete a booking
+    /// Start a booking, recording when the walk actually began
+    pub async fn start(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        id: BookingId,
+    ) -> Result<Option<Booking>, sqlx::Error> {
+        sqlx::query_as::<_, Booking>(
+            r#"
+            UPDATE bookings
+            SET status = 'in_progress',
+                actual_start = COALESCE(actual_start, NOW()),
+                updated_at = NOW()
+            WHERE id = $1 AND organization_id = $2
+            RETURNING id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, price_breakdown, notes, recurring_series_id, occurrence_number, created_at, updated_at
+            "#,
+        )
+        .bind(id.as_uuid())
+        .bind(org_id.as_uuid())
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Complete a booking, recording when the walk actually ended
     pub async fn complete(
         pool: &PgPool,
         org_id: OrganizationId,
         id: BookingId,
     ) -> Result<Option<Booking>, sqlx::Error> {
-        Self::update_status(pool, org_id, id, BookingStatus::Completed).await
+        sqlx::query_as::<_, Booking>(
+            r#"
+            UPDATE bookings
+            SET status = 'completed',
+                actual_end = COALESCE(actual_end, NOW()),
+                updated_at = NOW()
+            WHERE id = $1 AND organization_id = $2
+            RETURNING id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, price_breakdown, notes, recurring_series_id, occurrence_number, created_at, updated_at
+            "#,
+        )
+        .bind(id.as_uuid())
+        .bind(org_id.as_uuid())
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Find the walker's in-progress booking, if they are currently on a walk
+    pub async fn find_in_progress_for_walker(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        walker_id: UserId,
+    ) -> Result<Option<Booking>, sqlx::Error> {
+        sqlx::query_as::<_, Booking>(
+            r#"
+            SELECT id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, price_breakdown, notes, recurring_series_id, occurrence_number, created_at, updated_at
+            FROM bookings
+            WHERE walker_id = $1 AND organization_id = $2 AND status = 'in_progress'
+            ORDER BY actual_start DESC NULLS LAST
+            LIMIT 1
+            "#,
+        )
+        .bind(walker_id.as_uuid())
+        .bind(org_id.as_uuid())
+        .fetch_optional(pool)
+        .await
     }
 
     /// Find all bookings in a recurring series
@@ -339,7 +397,7 @@ impl BookingRepository {
     ) -> Result<Vec<Booking>, sqlx::Error> {
         sqlx::query_as::<_, Booking>(
             r#"
-            SELECT id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, notes, recurring_series_id, occurrence_number, created_at, updated_at
+            SELECT id, organization_id, customer_id, walker_id, service_id, location_id, status, scheduled_start, scheduled_end, actual_start, actual_end, price_cents, price_breakdown, notes, recurring_series_id, occurrence_number, created_at, updated_at
             FROM bookings
             WHERE recurring_series_id = $1 AND organization_id = $2
             ORDER BY scheduled_start ASC
@@ -0,0 +1,91 @@
+use shared::types::{OrganizationId, ServiceId};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::{CancellationPolicy, UpsertCancellationPolicy};
+
+pub struct CancellationPolicyRepository;
+
+impl CancellationPolicyRepository {
+    /// List the organization's default and service policies
+    pub async fn list(
+        pool: &PgPool,
+        org_id: OrganizationId,
+    ) -> Result<Vec<CancellationPolicy>, sqlx::Error> {
+        sqlx::query_as::<_, CancellationPolicy>(
+            r#"
+            SELECT * FROM cancellation_policies
+            WHERE organization_id = $1
+            ORDER BY service_id NULLS FIRST
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Get the policy that applies to a service: its own policy if it has
+    /// one, otherwise the organization default
+    pub async fn get_for_service(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        service_id: ServiceId,
+    ) -> Result<Option<CancellationPolicy>, sqlx::Error> {
+        sqlx::query_as::<_, CancellationPolicy>(
+            r#"
+            SELECT * FROM cancellation_policies
+            WHERE organization_id = $1 AND (service_id = $2 OR service_id IS NULL)
+            ORDER BY service_id NULLS LAST
+            LIMIT 1
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(service_id.as_uuid())
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Create or replace the default or service policy
+    pub async fn upsert(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        input: UpsertCancellationPolicy,
+    ) -> Result<CancellationPolicy, sqlx::Error> {
+        sqlx::query_as::<_, CancellationPolicy>(
+            r#"
+            INSERT INTO cancellation_policies (organization_id, service_id, tiers,
+                walker_cancels_free, charge_reschedules)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (organization_id, COALESCE(service_id, '00000000-0000-0000-0000-000000000000'::uuid))
+            DO UPDATE SET
+                tiers = EXCLUDED.tiers,
+                walker_cancels_free = EXCLUDED.walker_cancels_free,
+                charge_reschedules = EXCLUDED.charge_reschedules,
+                updated_at = NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(input.service_id.map(|id| *id.as_uuid()))
+        .bind(sqlx::types::Json(&input.tiers))
+        .bind(input.walker_cancels_free)
+        .bind(input.charge_reschedules)
+        .fetch_one(pool)
+        .await
+    }
+
+    pub async fn delete(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        id: Uuid,
+    ) -> Result<bool, sqlx::Error> {
+        let result =
+            sqlx::query("DELETE FROM cancellation_policies WHERE id = $1 AND organization_id = $2")
+                .bind(id)
+                .bind(org_id.as_uuid())
+                .execute(pool)
+                .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -29,11 +29,11 @@ impl CustomerPaymentMethodRepository {
                 id, organization_id, user_id, provider_type, method_type,
                 stripe_payment_method_id, stripe_customer_id,
                 square_card_id, square_customer_id,
-                last_four, brand, exp_month, exp_year, cardholder_name,
+                last_four, brand, exp_month, exp_year, cardholder_name, card_country,
                 bank_name, account_last_four, wallet_type,
                 is_default, billing_address
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20)
             RETURNING *
             "#,
         )
@@ -51,6 +51,7 @@ impl CustomerPaymentMethodRepository {
         .bind(input.exp_month)
         .bind(input.exp_year)
         .bind(input.cardholder_name)
+        .bind(input.card_country.map(|c| c.to_uppercase()))
         .bind(input.bank_name)
         .bind(input.account_last_four)
         .bind(input.wallet_type)
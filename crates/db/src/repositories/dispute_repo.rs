@@ -2,9 +2,7 @@ use shared::types::OrganizationId;
 use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::models::{
-    CreateDispute, CreateWebhookEvent, Dispute, DisputeStatus, PaymentWebhookEvent, UpdateDispute,
-};
+use crate::models::{CreateDispute, Dispute, DisputeEvidence, DisputeStatus, UpdateDispute};
 
 pub struct DisputeRepository;
 
@@ -31,7 +29,7 @@ impl DisputeRepository {
         .bind(org_id.as_uuid())
         .bind(input.transaction_id)
         .bind(input.amount_cents)
-        .bind(&input.currency)
+        .bind(input.currency)
         .bind(input.stripe_dispute_id)
         .bind(input.square_dispute_id)
         .bind(&input.reason)
@@ -74,6 +72,22 @@ impl DisputeRepository {
         .await
     }
 
+    /// Get dispute by Square dispute ID
+    pub async fn get_by_square_dispute(
+        pool: &PgPool,
+        square_dispute_id: &str,
+    ) -> Result<Option<Dispute>, sqlx::Error> {
+        sqlx::query_as::<_, Dispute>(
+            r#"
+            SELECT * FROM disputes
+            WHERE square_dispute_id = $1
+            "#,
+        )
+        .bind(square_dispute_id)
+        .fetch_optional(pool)
+        .await
+    }
+
     /// Get dispute by transaction ID
     pub async fn get_by_transaction(
         pool: &PgPool,
@@ -165,16 +179,43 @@ impl DisputeRepository {
         .await
     }
 
-    /// Mark evidence as submitted
+    /// Save evidence for a dispute without submitting it
+    pub async fn save_evidence(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        id: Uuid,
+        evidence: &DisputeEvidence,
+    ) -> Result<Option<Dispute>, sqlx::Error> {
+        sqlx::query_as::<_, Dispute>(
+            r#"
+            UPDATE disputes
+            SET
+                evidence = $3,
+                updated_at = NOW()
+            WHERE id = $1 AND organization_id = $2
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(org_id.as_uuid())
+        .bind(sqlx::types::Json(evidence))
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Mark evidence as submitted, recording what was sent
     pub async fn mark_evidence_submitted(
         pool: &PgPool,
         id: Uuid,
+        evidence: &DisputeEvidence,
     ) -> Result<Option<Dispute>, sqlx::Error> {
         sqlx::query_as::<_, Dispute>(
             r#"
             UPDATE disputes
             SET
                 evidence_submitted = true,
+                evidence = $2,
+                evidence_submitted_at = NOW(),
                 status = 'under_review',
                 updated_at = NOW()
             WHERE id = $1
@@ -182,6 +223,52 @@ impl DisputeRepository {
             "#,
         )
         .bind(id)
+        .bind(sqlx::types::Json(evidence))
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Disputes still waiting on evidence that have a due date
+    pub async fn list_awaiting_evidence(
+        pool: &PgPool,
+        org_id: OrganizationId,
+    ) -> Result<Vec<Dispute>, sqlx::Error> {
+        sqlx::query_as::<_, Dispute>(
+            r#"
+            SELECT * FROM disputes
+            WHERE organization_id = $1
+                AND status = 'needs_response'
+                AND evidence_submitted = false
+                AND evidence_due_by IS NOT NULL
+            ORDER BY evidence_due_by
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Record that managers have seen the evidence reminder for `days`
+    /// before the due date. Returns `None` if that reminder or a later one
+    /// was already recorded.
+    pub async fn mark_reminded(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        id: Uuid,
+        days: i32,
+    ) -> Result<Option<Dispute>, sqlx::Error> {
+        sqlx::query_as::<_, Dispute>(
+            r#"
+            UPDATE disputes
+            SET last_reminder_days = $3, updated_at = NOW()
+            WHERE id = $1 AND organization_id = $2
+                AND (last_reminder_days IS NULL OR last_reminder_days > $3)
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(org_id.as_uuid())
+        .bind(days)
         .fetch_optional(pool)
         .await
     }
@@ -231,117 +318,3 @@ impl DisputeRepository {
         .await
     }
 }
-
-pub struct WebhookEventRepository;
-
-impl WebhookEventRepository {
-    /// Record a webhook event
-    pub async fn create(
-        pool: &PgPool,
-        input: CreateWebhookEvent,
-    ) -> Result<PaymentWebhookEvent, sqlx::Error> {
-        let id = Uuid::new_v4();
-
-        sqlx::query_as::<_, PaymentWebhookEvent>(
-            r#"
-            INSERT INTO payment_webhook_events (id, provider, event_id, event_type, payload)
-            VALUES ($1, $2::payment_provider_type, $3, $4, $5)
-            ON CONFLICT (provider, event_id) DO NOTHING
-            RETURNING *
-            "#,
-        )
-        .bind(id)
-        .bind(&input.provider)
-        .bind(&input.event_id)
-        .bind(&input.event_type)
-        .bind(&input.payload)
-        .fetch_one(pool)
-        .await
-    }
-
-    /// Check if event was already processed (idempotency)
-    pub async fn is_processed(
-        pool: &PgPool,
-        provider: &str,
-        event_id: &str,
-    ) -> Result<bool, sqlx::Error> {
-        let result: Option<bool> = sqlx::query_scalar(
-            r#"
-            SELECT processed FROM payment_webhook_events
-            WHERE provider = $1::payment_provider_type AND event_id = $2
-            "#,
-        )
-        .bind(provider)
-        .bind(event_id)
-        .fetch_optional(pool)
-        .await?;
-
-        Ok(result.unwrap_or(false))
-    }
-
-    /// Mark event as processed
-    pub async fn mark_processed(
-        pool: &PgPool,
-        provider: &str,
-        event_id: &str,
-    ) -> Result<(), sqlx::Error> {
-        sqlx::query(
-            r#"
-            UPDATE payment_webhook_events
-            SET processed = true, processed_at = NOW()
-            WHERE provider = $1::payment_provider_type AND event_id = $2
-            "#,
-        )
-        .bind(provider)
-        .bind(event_id)
-        .execute(pool)
-        .await?;
-
-        Ok(())
-    }
-
-    /// Mark event as failed
-    pub async fn mark_failed(
-        pool: &PgPool,
-        provider: &str,
-        event_id: &str,
-        error_message: &str,
-    ) -> Result<(), sqlx::Error> {
-        sqlx::query(
-            r#"
-            UPDATE payment_webhook_events
-            SET error_message = $3
-            WHERE provider = $1::payment_provider_type AND event_id = $2
-            "#,
-        )
-        .bind(provider)
-        .bind(event_id)
-        .bind(error_message)
-        .execute(pool)
-        .await?;
-
-        Ok(())
-    }
-
-    /// Get unprocessed events for retry
-    pub async fn get_unprocessed(
-        pool: &PgPool,
-        provider: &str,
-        limit: i64,
-    ) -> Result<Vec<PaymentWebhookEvent>, sqlx::Error> {
-        sqlx::query_as::<_, PaymentWebhookEvent>(
-            r#"
-            SELECT * FROM payment_webhook_events
-            WHERE provider = $1::payment_provider_type
-                AND processed = false
-                AND error_message IS NULL
-            ORDER BY created_at ASC
-            LIMIT $2
-            "#,
-        )
-        .bind(provider)
-        .bind(limit)
-        .fetch_all(pool)
-        .await
-    }
-}
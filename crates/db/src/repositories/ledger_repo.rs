@@ -0,0 +1,216 @@
+use shared::types::{OrganizationId, UserId};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::{
+    JournalEntry, JournalLine, LedgerAccountBalance, LedgerCheck, LedgerCurrencyTotals,
+    NewJournalEntry,
+};
+
+pub struct LedgerRepository;
+
+impl LedgerRepository {
+    /// Post a journal entry and its lines atomically.
+    ///
+    /// Returns `None` if the entry's source has already been posted, so
+    /// events delivered more than once are only recorded once.
+    pub async fn post(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        entry: &NewJournalEntry,
+    ) -> Result<Option<JournalEntry>, sqlx::Error> {
+        if !entry.is_balanced() {
+            return Err(sqlx::Error::Protocol(format!(
+                "Unbalanced journal entry: {} debits, {} credits",
+                entry.total_debits_cents(),
+                entry.total_credits_cents()
+            )));
+        }
+
+        let mut tx = pool.begin().await?;
+
+        let posted = sqlx::query_as::<_, JournalEntry>(
+            r#"
+            INSERT INTO journal_entries (
+                organization_id, kind, source_id, transaction_id, description, currency,
+                occurred_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (organization_id, kind, source_id) DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(entry.kind)
+        .bind(entry.source_id)
+        .bind(entry.transaction_id)
+        .bind(&entry.description)
+        .bind(entry.currency)
+        .bind(entry.occurred_at)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(posted) = posted else {
+            tx.rollback().await?;
+            return Ok(None);
+        };
+
+        for line in entry.lines() {
+            let account_id: Uuid = sqlx::query_scalar(
+                r#"
+                INSERT INTO ledger_accounts (organization_id, account_type, walker_id, currency)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (
+                    organization_id,
+                    account_type,
+                    COALESCE(walker_id, '00000000-0000-0000-0000-000000000000'::uuid),
+                    currency
+                )
+                DO UPDATE SET currency = EXCLUDED.currency
+                RETURNING id
+                "#,
+            )
+            .bind(org_id.as_uuid())
+            .bind(line.account.account_type)
+            .bind(line.account.walker_id.map(|id| *id.as_uuid()))
+            .bind(entry.currency)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            sqlx::query(
+                r#"
+                INSERT INTO journal_lines (journal_entry_id, account_id, debit_cents, credit_cents)
+                VALUES ($1, $2, $3, $4)
+                "#,
+            )
+            .bind(posted.id)
+            .bind(account_id)
+            .bind(line.debit_cents)
+            .bind(line.credit_cents)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(Some(posted))
+    }
+
+    /// List the entries recorded against a transaction, oldest first
+    pub async fn list_by_transaction(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        transaction_id: Uuid,
+    ) -> Result<Vec<JournalEntry>, sqlx::Error> {
+        sqlx::query_as::<_, JournalEntry>(
+            r#"
+            SELECT * FROM journal_entries
+            WHERE transaction_id = $1 AND organization_id = $2
+            ORDER BY occurred_at, created_at
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(org_id.as_uuid())
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Get the lines of an entry
+    pub async fn get_lines(pool: &PgPool, entry_id: Uuid) -> Result<Vec<JournalLine>, sqlx::Error> {
+        sqlx::query_as::<_, JournalLine>(
+            "SELECT * FROM journal_lines WHERE journal_entry_id = $1 ORDER BY id",
+        )
+        .bind(entry_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Posted totals for every account in an organization
+    pub async fn balances(
+        pool: &PgPool,
+        org_id: OrganizationId,
+    ) -> Result<Vec<LedgerAccountBalance>, sqlx::Error> {
+        sqlx::query_as::<_, LedgerAccountBalance>(
+            r#"
+            SELECT a.account_type, a.walker_id, a.currency,
+                   COALESCE(SUM(l.debit_cents), 0)::BIGINT as debit_cents,
+                   COALESCE(SUM(l.credit_cents), 0)::BIGINT as credit_cents
+            FROM ledger_accounts a
+            LEFT JOIN journal_lines l ON l.account_id = a.id
+            WHERE a.organization_id = $1
+            GROUP BY a.id
+            ORDER BY a.account_type, a.currency, a.walker_id NULLS FIRST
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Posted totals for a walker's payable account
+    pub async fn walker_balance(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        walker_id: UserId,
+    ) -> Result<Vec<LedgerAccountBalance>, sqlx::Error> {
+        sqlx::query_as::<_, LedgerAccountBalance>(
+            r#"
+            SELECT a.account_type, a.walker_id, a.currency,
+                   COALESCE(SUM(l.debit_cents), 0)::BIGINT as debit_cents,
+                   COALESCE(SUM(l.credit_cents), 0)::BIGINT as credit_cents
+            FROM ledger_accounts a
+            LEFT JOIN journal_lines l ON l.account_id = a.id
+            WHERE a.organization_id = $1
+              AND a.account_type = 'walker_payable'
+              AND a.walker_id = $2
+            GROUP BY a.id
+            ORDER BY a.currency
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(walker_id.as_uuid())
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Check that debits equal credits in each currency for an organization
+    /// and find any entries that do not balance on their own
+    pub async fn check(pool: &PgPool, org_id: OrganizationId) -> Result<LedgerCheck, sqlx::Error> {
+        let currencies = sqlx::query_as::<_, LedgerCurrencyTotals>(
+            r#"
+            SELECT a.currency,
+                   COALESCE(SUM(l.debit_cents), 0)::BIGINT as total_debits_cents,
+                   COALESCE(SUM(l.credit_cents), 0)::BIGINT as total_credits_cents
+            FROM journal_lines l
+            JOIN ledger_accounts a ON a.id = l.account_id
+            WHERE a.organization_id = $1
+            GROUP BY a.currency
+            ORDER BY a.currency
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .fetch_all(pool)
+        .await?;
+
+        let unbalanced_entry_ids: Vec<Uuid> = sqlx::query_scalar(
+            r#"
+            SELECT e.id
+            FROM journal_entries e
+            LEFT JOIN journal_lines l ON l.journal_entry_id = e.id
+            LEFT JOIN ledger_accounts a ON a.id = l.account_id
+            WHERE e.organization_id = $1
+            GROUP BY e.id
+            HAVING COALESCE(SUM(l.debit_cents), 0) <> COALESCE(SUM(l.credit_cents), 0)
+                OR COALESCE(BOOL_OR(a.currency <> e.currency), false)
+            ORDER BY e.id
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .fetch_all(pool)
+        .await?;
+
+        Ok(LedgerCheck {
+            currencies,
+            unbalanced_entry_ids,
+        })
+    }
+}
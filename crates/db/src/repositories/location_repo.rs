@@ -11,9 +11,9 @@ impl LocationRepository {
 
         sqlx::query_as::<_, Location>(
             r#"
-            INSERT INTO locations (id, organization_id, user_id, name, address, city, state, zip_code, latitude, longitude, notes, is_default)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
-            RETURNING id, organization_id, user_id, name, address, city, state, zip_code, latitude, longitude, notes, is_default, created_at, updated_at
+            INSERT INTO locations (id, organization_id, user_id, name, address, city, state, zip_code, country, latitude, longitude, notes, is_default)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            RETURNING id, organization_id, user_id, name, address, city, state, zip_code, country, latitude, longitude, notes, is_default, created_at, updated_at
             "#,
         )
         .bind(id.as_uuid())
@@ -24,6 +24,7 @@ impl LocationRepository {
         .bind(&input.city)
         .bind(&input.state)
         .bind(&input.zip_code)
+        .bind(&input.country)
         .bind(input.latitude)
         .bind(input.longitude)
         .bind(&input.notes)
@@ -39,7 +40,7 @@ impl LocationRepository {
     ) -> Result<Option<Location>, sqlx::Error> {
         sqlx::query_as::<_, Location>(
             r#"
-            SELECT id, organization_id, user_id, name, address, city, state, zip_code, latitude, longitude, notes, is_default, created_at, updated_at
+            SELECT id, organization_id, user_id, name, address, city, state, zip_code, country, latitude, longitude, notes, is_default, created_at, updated_at
             FROM locations
             WHERE id = $1 AND organization_id = $2
             "#,
@@ -57,7 +58,7 @@ impl LocationRepository {
     ) -> Result<Vec<Location>, sqlx::Error> {
         sqlx::query_as::<_, Location>(
             r#"
-            SELECT id, organization_id, user_id, name, address, city, state, zip_code, latitude, longitude, notes, is_default, created_at, updated_at
+            SELECT id, organization_id, user_id, name, address, city, state, zip_code, country, latitude, longitude, notes, is_default, created_at, updated_at
             FROM locations
             WHERE user_id = $1 AND organization_id = $2
             ORDER BY is_default DESC, name
@@ -88,9 +89,10 @@ impl LocationRepository {
                 longitude = COALESCE($9, longitude),
                 notes = COALESCE($10, notes),
                 is_default = COALESCE($11, is_default),
+                country = COALESCE($12, country),
                 updated_at = NOW()
             WHERE id = $1 AND organization_id = $2
-            RETURNING id, organization_id, user_id, name, address, city, state, zip_code, latitude, longitude, notes, is_default, created_at, updated_at
+            RETURNING id, organization_id, user_id, name, address, city, state, zip_code, country, latitude, longitude, notes, is_default, created_at, updated_at
             "#,
         )
         .bind(id.as_uuid())
@@ -104,6 +106,7 @@ impl LocationRepository {
         .bind(input.longitude)
         .bind(&input.notes)
         .bind(input.is_default)
+        .bind(&input.country)
         .fetch_optional(pool)
         .await
     }
@@ -142,7 +145,7 @@ impl LocationRepository {
             UPDATE locations
             SET is_default = true, updated_at = NOW()
             WHERE id = $1
-            RETURNING id, organization_id, user_id, name, address, city, state, zip_code, latitude, longitude, notes, is_default, created_at, updated_at
+            RETURNING id, organization_id, user_id, name, address, city, state, zip_code, country, latitude, longitude, notes, is_default, created_at, updated_at
             "#,
         )
         .bind(id.as_uuid())
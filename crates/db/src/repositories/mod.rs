@@ -1,9 +1,11 @@
 mod block_repo;
 mod booking_repo;
 mod calendar_repo;
+mod cancellation_policy_repo;
 mod customer_payment_method_repo;
 mod dispute_repo;
 mod invitation_repo;
+mod ledger_repo;
 mod location_repo;
 mod membership_repo;
 mod organization_repo;
@@ -12,7 +14,10 @@ mod payment_repo;
 mod payout_repo;
 mod pet;
 mod platform_admin_repo;
+mod processing_fee_schedule_repo;
+mod reconciliation_repo;
 mod recurring_booking_repo;
+mod refund_repo;
 mod service_area_repo;
 mod service_repo;
 mod subscription_repo;
@@ -21,15 +26,20 @@ mod transaction_repo;
 mod travel_time_repo;
 mod user_identity_repo;
 mod user_repo;
+mod walk_trail_repo;
+mod walker_earning_repo;
 mod walker_profile_repo;
+mod webhook_event_repo;
 mod working_hours_repo;
 
 pub use block_repo::BlockRepository;
 pub use booking_repo::BookingRepository;
 pub use calendar_repo::CalendarRepository;
+pub use cancellation_policy_repo::CancellationPolicyRepository;
 pub use customer_payment_method_repo::CustomerPaymentMethodRepository;
-pub use dispute_repo::{DisputeRepository, WebhookEventRepository};
+pub use dispute_repo::DisputeRepository;
 pub use invitation_repo::InvitationRepository;
+pub use ledger_repo::LedgerRepository;
 pub use location_repo::LocationRepository;
 pub use membership_repo::MembershipRepository;
 pub use organization_repo::OrganizationRepository;
@@ -38,19 +48,25 @@ pub use payment_repo::PaymentRepository;
 pub use payout_repo::PayoutRepository;
 pub use pet::PetRepository;
 pub use platform_admin_repo::PlatformAdminRepository;
+pub use processing_fee_schedule_repo::ProcessingFeeScheduleRepository;
+pub use reconciliation_repo::ReconciliationRepository;
 pub use recurring_booking_repo::{
     check_conflicts, check_conflicts_batch, generate_occurrence_dates, to_utc_datetime,
     RecurringBookingRepository,
 };
+pub use refund_repo::RefundRepository;
 pub use service_area_repo::ServiceAreaRepository;
 pub use service_repo::ServiceRepository;
 pub use subscription_repo::SubscriptionRepository;
 pub use tenant_database_repo::TenantDatabaseRepository;
-pub use transaction_repo::{TransactionRepository, TransactionSummary};
+pub use transaction_repo::{TransactionRepository, TransactionSummary, WalkerTipSummary};
 pub use travel_time_repo::{TravelTimeCacheRepository, WalkerLocationRepository};
 pub use user_identity_repo::{
     PhoneVerificationRepository, UserIdentityRepository, WalletChallengeRepository,
 };
 pub use user_repo::UserRepository;
+pub use walk_trail_repo::WalkTrailRepository;
+pub use walker_earning_repo::WalkerEarningRepository;
 pub use walker_profile_repo::WalkerProfileRepository;
+pub use webhook_event_repo::WebhookEventRepository;
 pub use working_hours_repo::WorkingHoursRepository;
@@ -244,6 +244,7 @@ impl PaymentProviderRepository {
     /// Update Stripe Connect account status after webhook
     pub async fn update_stripe_status(
         pool: &PgPool,
+        org_id: OrganizationId,
         stripe_account_id: &str,
         charges_enabled: bool,
         payouts_enabled: bool,
@@ -258,7 +259,7 @@ impl PaymentProviderRepository {
                 is_verified = $2,
                 verification_status = $4,
                 updated_at = NOW()
-            WHERE stripe_account_id = $1
+            WHERE stripe_account_id = $1 AND organization_id = $5
             RETURNING *
             "#,
         )
@@ -266,7 +267,94 @@ impl PaymentProviderRepository {
         .bind(charges_enabled)
         .bind(payouts_enabled)
         .bind(verification_status)
+        .bind(org_id.as_uuid())
         .fetch_optional(pool)
         .await
     }
+
+    /// Get the provider for a Stripe Connect account
+    pub async fn get_by_stripe_account(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        stripe_account_id: &str,
+    ) -> Result<Option<PaymentProvider>, sqlx::Error> {
+        sqlx::query_as::<_, PaymentProvider>(
+            r#"
+            SELECT * FROM payment_providers
+            WHERE stripe_account_id = $1 AND organization_id = $2
+            "#,
+        )
+        .bind(stripe_account_id)
+        .bind(org_id.as_uuid())
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Disable a Stripe Connect account that disconnected from the platform
+    pub async fn deauthorize_stripe_account(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        stripe_account_id: &str,
+    ) -> Result<Option<PaymentProvider>, sqlx::Error> {
+        sqlx::query_as::<_, PaymentProvider>(
+            r#"
+            UPDATE payment_providers
+            SET
+                is_active = false,
+                is_verified = false,
+                charges_enabled = false,
+                payouts_enabled = false,
+                verification_status = 'deauthorized',
+                updated_at = NOW()
+            WHERE stripe_account_id = $1 AND organization_id = $2
+            RETURNING *
+            "#,
+        )
+        .bind(stripe_account_id)
+        .bind(org_id.as_uuid())
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Record which organization a Stripe Connect account belongs to. Uses
+    /// the main database pool.
+    pub async fn register_stripe_account(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        stripe_account_id: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO stripe_connected_accounts (stripe_account_id, organization_id)
+            VALUES ($1, $2)
+            ON CONFLICT (stripe_account_id)
+            DO UPDATE SET organization_id = EXCLUDED.organization_id
+            "#,
+        )
+        .bind(stripe_account_id)
+        .bind(org_id.as_uuid())
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The organization a Stripe Connect account belongs to. Uses the main
+    /// database pool.
+    pub async fn find_org_by_stripe_account(
+        pool: &PgPool,
+        stripe_account_id: &str,
+    ) -> Result<Option<OrganizationId>, sqlx::Error> {
+        let org_id: Option<Uuid> = sqlx::query_scalar(
+            r#"
+            SELECT organization_id FROM stripe_connected_accounts
+            WHERE stripe_account_id = $1
+            "#,
+        )
+        .bind(stripe_account_id)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(org_id.map(OrganizationId::from_uuid))
+    }
 }
@@ -1,5 +1,5 @@
-use chrono::Utc;
-use shared::types::OrganizationId;
+use chrono::{NaiveDate, Utc};
+use shared::types::{Currency, OrganizationId};
 use sqlx::PgPool;
 use uuid::Uuid;
 
@@ -104,21 +104,26 @@ impl PayoutRepository {
 
     // ========== Payouts ==========
 
-    /// Create a payout
+    /// Create a payout. Returns `None` if a payout for the same scheduled
+    /// day and currency already exists.
     pub async fn create(
         pool: &PgPool,
         org_id: OrganizationId,
         input: CreatePayout,
-    ) -> Result<Payout, sqlx::Error> {
+    ) -> Result<Option<Payout>, sqlx::Error> {
         let id = Uuid::new_v4();
 
         sqlx::query_as::<_, Payout>(
             r#"
             INSERT INTO payouts (
                 id, organization_id, amount_cents, fee_cents, net_amount_cents,
-                currency, period_start, period_end, transaction_count, transaction_ids
+                currency, period_start, period_end, transaction_count, transaction_ids,
+                refund_ids, walker_earning_ids, scheduled_for
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            ON CONFLICT (organization_id, scheduled_for, currency)
+                WHERE scheduled_for IS NOT NULL
+                DO NOTHING
             RETURNING *
             "#,
         )
@@ -127,12 +132,15 @@ impl PayoutRepository {
         .bind(input.amount_cents)
         .bind(input.fee_cents)
         .bind(input.net_amount_cents)
-        .bind(&input.currency)
+        .bind(input.currency)
         .bind(input.period_start)
         .bind(input.period_end)
         .bind(input.transaction_count)
         .bind(&input.transaction_ids)
-        .fetch_one(pool)
+        .bind(&input.refund_ids)
+        .bind(&input.walker_earning_ids)
+        .bind(input.scheduled_for)
+        .fetch_optional(pool)
         .await
     }
 
@@ -157,15 +165,53 @@ impl PayoutRepository {
     /// Get payout by Stripe payout ID
     pub async fn get_by_stripe_payout(
         pool: &PgPool,
+        org_id: OrganizationId,
         stripe_payout_id: &str,
     ) -> Result<Option<Payout>, sqlx::Error> {
         sqlx::query_as::<_, Payout>(
             r#"
             SELECT * FROM payouts
-            WHERE stripe_payout_id = $1
+            WHERE stripe_payout_id = $1 AND organization_id = $2
             "#,
         )
         .bind(stripe_payout_id)
+        .bind(org_id.as_uuid())
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Get payout by Square payout ID
+    pub async fn get_by_square_payout(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        square_payout_id: &str,
+    ) -> Result<Option<Payout>, sqlx::Error> {
+        sqlx::query_as::<_, Payout>(
+            r#"
+            SELECT * FROM payouts
+            WHERE square_payout_id = $1 AND organization_id = $2
+            "#,
+        )
+        .bind(square_payout_id)
+        .bind(org_id.as_uuid())
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Get payout by Stripe transfer ID
+    pub async fn get_by_stripe_transfer(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        stripe_transfer_id: &str,
+    ) -> Result<Option<Payout>, sqlx::Error> {
+        sqlx::query_as::<_, Payout>(
+            r#"
+            SELECT * FROM payouts
+            WHERE stripe_transfer_id = $1 AND organization_id = $2
+            "#,
+        )
+        .bind(stripe_transfer_id)
+        .bind(org_id.as_uuid())
         .fetch_optional(pool)
         .await
     }
@@ -235,11 +281,13 @@ impl PayoutRepository {
         .await
     }
 
-    /// Update payout status
+    /// Update payout status, recording why the provider failed it
     pub async fn update_status(
         pool: &PgPool,
         id: Uuid,
         status: PayoutStatus,
+        failure_code: Option<&str>,
+        failure_message: Option<&str>,
     ) -> Result<Option<Payout>, sqlx::Error> {
         let completed_at = if status == PayoutStatus::Paid {
             Some(Utc::now())
@@ -253,6 +301,8 @@ impl PayoutRepository {
             SET
                 status = $2,
                 completed_at = COALESCE($3, completed_at),
+                failure_code = COALESCE($4, failure_code),
+                failure_message = COALESCE($5, failure_message),
                 updated_at = NOW()
             WHERE id = $1
             RETURNING *
@@ -261,14 +311,17 @@ impl PayoutRepository {
         .bind(id)
         .bind(status)
         .bind(completed_at)
+        .bind(failure_code)
+        .bind(failure_message)
         .fetch_optional(pool)
         .await
     }
 
-    /// Get payout summary for an organization
+    /// Get payout summary for an organization in one currency
     pub async fn get_summary(
         pool: &PgPool,
         org_id: OrganizationId,
+        currency: Currency,
     ) -> Result<PayoutSummary, sqlx::Error> {
         sqlx::query_as::<_, PayoutSummary>(
             r#"
@@ -279,37 +332,53 @@ impl PayoutRepository {
                 MAX(CASE WHEN status = 'paid' THEN completed_at END) as last_payout_date,
                 MIN(CASE WHEN status = 'pending' THEN arrival_date END) as next_payout_date
             FROM payouts
-            WHERE organization_id = $1
+            WHERE organization_id = $1 AND currency = $2
             "#,
         )
         .bind(org_id.as_uuid())
+        .bind(currency)
         .fetch_one(pool)
         .await
     }
 
-    /// Get organizations due for payout
+    /// Payouts created but never sent to the provider
+    pub async fn list_uninitiated(
+        pool: &PgPool,
+        org_id: OrganizationId,
+    ) -> Result<Vec<Payout>, sqlx::Error> {
+        sqlx::query_as::<_, Payout>(
+            r#"
+            SELECT * FROM payouts
+            WHERE organization_id = $1
+                AND status = 'pending'
+                AND initiated_at IS NULL
+            ORDER BY created_at
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Get organizations with verified payout settings whose scheduled
+    /// payout is due on `date`
     pub async fn get_orgs_due_for_payout(
         pool: &PgPool,
-        payout_schedule: &str,
-        day_of_week: Option<i32>,
-        day_of_month: Option<i32>,
+        date: NaiveDate,
     ) -> Result<Vec<OrganizationId>, sqlx::Error> {
-        let orgs = sqlx::query_scalar::<_, Uuid>(
+        let settings = sqlx::query_as::<_, PayoutSettings>(
             r#"
-            SELECT DISTINCT ps.organization_id
-            FROM payout_settings ps
-            WHERE ps.is_verified = true
-                AND ps.payout_schedule = $1
-                AND ($2::integer IS NULL OR ps.payout_day_of_week = $2)
-                AND ($3::integer IS NULL OR ps.payout_day_of_month = $3)
+            SELECT * FROM payout_settings
+            WHERE is_verified = true
             "#,
         )
-        .bind(payout_schedule)
-        .bind(day_of_week)
-        .bind(day_of_month)
         .fetch_all(pool)
         .await?;
 
-        Ok(orgs.into_iter().map(OrganizationId::from_uuid).collect())
+        Ok(settings
+            .into_iter()
+            .filter(|s| s.is_due_on(date))
+            .map(|s| s.organization_id)
+            .collect())
     }
 }
@@ -0,0 +1,91 @@
+use shared::types::OrganizationId;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::{PaymentProviderType, ProcessingFeeSchedule, UpsertProcessingFeeSchedule};
+
+pub struct ProcessingFeeScheduleRepository;
+
+impl ProcessingFeeScheduleRepository {
+    /// List the organization's fee schedules
+    pub async fn list(
+        pool: &PgPool,
+        org_id: OrganizationId,
+    ) -> Result<Vec<ProcessingFeeSchedule>, sqlx::Error> {
+        sqlx::query_as::<_, ProcessingFeeSchedule>(
+            r#"
+            SELECT * FROM processing_fee_schedules
+            WHERE organization_id = $1
+            ORDER BY provider_type, method_type NULLS FIRST, card_region NULLS FIRST
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Fee schedules for one payment provider
+    pub async fn list_for_provider(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        provider_type: PaymentProviderType,
+    ) -> Result<Vec<ProcessingFeeSchedule>, sqlx::Error> {
+        sqlx::query_as::<_, ProcessingFeeSchedule>(
+            r#"
+            SELECT * FROM processing_fee_schedules
+            WHERE organization_id = $1 AND provider_type = $2
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(provider_type)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Create or replace the schedule for a provider, method and region
+    pub async fn upsert(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        input: UpsertProcessingFeeSchedule,
+    ) -> Result<ProcessingFeeSchedule, sqlx::Error> {
+        sqlx::query_as::<_, ProcessingFeeSchedule>(
+            r#"
+            INSERT INTO processing_fee_schedules (organization_id, provider_type, method_type,
+                card_region, percent, fixed_cents, cap_cents)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (organization_id, provider_type, method_type, card_region)
+            DO UPDATE SET
+                percent = EXCLUDED.percent,
+                fixed_cents = EXCLUDED.fixed_cents,
+                cap_cents = EXCLUDED.cap_cents,
+                updated_at = NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(input.provider_type)
+        .bind(input.method_type)
+        .bind(input.card_region)
+        .bind(input.percent)
+        .bind(input.fixed_cents)
+        .bind(input.cap_cents)
+        .fetch_one(pool)
+        .await
+    }
+
+    pub async fn delete(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        id: Uuid,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "DELETE FROM processing_fee_schedules WHERE id = $1 AND organization_id = $2",
+        )
+        .bind(id)
+        .bind(org_id.as_uuid())
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
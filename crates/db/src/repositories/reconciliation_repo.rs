@@ -0,0 +1,246 @@
+use chrono::{DateTime, Utc};
+use shared::types::{OrganizationId, UserId};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::{
+    CreateReconciliationMismatch, ReconciliationCounts, ReconciliationMismatch, ReconciliationRun,
+};
+
+pub struct ReconciliationRepository;
+
+impl ReconciliationRepository {
+    /// Start a run for a provider and period
+    pub async fn create_run(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        provider_id: Uuid,
+        period_start: DateTime<Utc>,
+        period_end: DateTime<Utc>,
+        requested_by: Option<UserId>,
+    ) -> Result<ReconciliationRun, sqlx::Error> {
+        sqlx::query_as::<_, ReconciliationRun>(
+            r#"
+            INSERT INTO reconciliation_runs (
+                organization_id, provider_id, period_start, period_end, requested_by
+            )
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(provider_id)
+        .bind(period_start)
+        .bind(period_end)
+        .bind(requested_by.map(|id| *id.as_uuid()))
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Record a run's mismatches and mark it completed
+    pub async fn complete_run(
+        pool: &PgPool,
+        run: &ReconciliationRun,
+        counts: ReconciliationCounts,
+        mismatches: &[CreateReconciliationMismatch],
+    ) -> Result<ReconciliationRun, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+
+        for mismatch in mismatches {
+            sqlx::query(
+                r#"
+                INSERT INTO reconciliation_mismatches (
+                    run_id, organization_id, kind, transaction_id, external_payment_id,
+                    local_amount_cents, remote_amount_cents, local_status, remote_status
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                "#,
+            )
+            .bind(run.id)
+            .bind(run.organization_id.as_uuid())
+            .bind(mismatch.kind)
+            .bind(mismatch.transaction_id)
+            .bind(&mismatch.external_payment_id)
+            .bind(mismatch.local_amount_cents)
+            .bind(mismatch.remote_amount_cents)
+            .bind(&mismatch.local_status)
+            .bind(&mismatch.remote_status)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let run = sqlx::query_as::<_, ReconciliationRun>(
+            r#"
+            UPDATE reconciliation_runs
+            SET status = 'completed',
+                local_count = $2,
+                remote_count = $3,
+                matched_count = $4,
+                mismatch_count = $5,
+                completed_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(run.id)
+        .bind(counts.local_count)
+        .bind(counts.remote_count)
+        .bind(counts.matched_count)
+        .bind(mismatches.len() as i32)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(run)
+    }
+
+    /// Mark a run failed, e.g. when the provider could not be reached
+    pub async fn fail_run(
+        pool: &PgPool,
+        id: Uuid,
+        error_message: &str,
+    ) -> Result<Option<ReconciliationRun>, sqlx::Error> {
+        sqlx::query_as::<_, ReconciliationRun>(
+            r#"
+            UPDATE reconciliation_runs
+            SET status = 'failed', error_message = $2, completed_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(error_message)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Whether a provider's period has already been reconciled
+    pub async fn has_completed_run(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        provider_id: Uuid,
+        period_start: DateTime<Utc>,
+        period_end: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM reconciliation_runs
+                WHERE organization_id = $1
+                  AND provider_id = $2
+                  AND period_start = $3
+                  AND period_end = $4
+                  AND status = 'completed'
+            )
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(provider_id)
+        .bind(period_start)
+        .bind(period_end)
+        .fetch_one(pool)
+        .await
+    }
+
+    /// List runs, newest first
+    pub async fn list_runs(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ReconciliationRun>, sqlx::Error> {
+        sqlx::query_as::<_, ReconciliationRun>(
+            r#"
+            SELECT * FROM reconciliation_runs
+            WHERE organization_id = $1
+            ORDER BY started_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Get a run by ID
+    pub async fn get_run(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        id: Uuid,
+    ) -> Result<Option<ReconciliationRun>, sqlx::Error> {
+        sqlx::query_as::<_, ReconciliationRun>(
+            "SELECT * FROM reconciliation_runs WHERE id = $1 AND organization_id = $2",
+        )
+        .bind(id)
+        .bind(org_id.as_uuid())
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// List a run's mismatches
+    pub async fn list_mismatches(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        run_id: Uuid,
+    ) -> Result<Vec<ReconciliationMismatch>, sqlx::Error> {
+        sqlx::query_as::<_, ReconciliationMismatch>(
+            r#"
+            SELECT * FROM reconciliation_mismatches
+            WHERE run_id = $1 AND organization_id = $2
+            ORDER BY kind, created_at
+            "#,
+        )
+        .bind(run_id)
+        .bind(org_id.as_uuid())
+        .fetch_all(pool)
+        .await
+    }
+
+    /// List mismatches no one has resolved yet, newest first
+    pub async fn list_open_mismatches(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ReconciliationMismatch>, sqlx::Error> {
+        sqlx::query_as::<_, ReconciliationMismatch>(
+            r#"
+            SELECT * FROM reconciliation_mismatches
+            WHERE organization_id = $1 AND resolved_at IS NULL
+            ORDER BY created_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Mark a mismatch as reviewed
+    pub async fn resolve_mismatch(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        id: Uuid,
+        resolved_by: UserId,
+        note: Option<&str>,
+    ) -> Result<Option<ReconciliationMismatch>, sqlx::Error> {
+        sqlx::query_as::<_, ReconciliationMismatch>(
+            r#"
+            UPDATE reconciliation_mismatches
+            SET resolved_at = NOW(), resolved_by = $3, resolution_note = $4
+            WHERE id = $1 AND organization_id = $2 AND resolved_at IS NULL
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(org_id.as_uuid())
+        .bind(resolved_by.as_uuid())
+        .bind(note)
+        .fetch_optional(pool)
+        .await
+    }
+}
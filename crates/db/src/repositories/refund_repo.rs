@@ -0,0 +1,409 @@
+use chrono::{DateTime, Utc};
+use shared::types::OrganizationId;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::models::{CreateRefund, Refund, RefundStatus};
+
+pub struct RefundRepository;
+
+impl RefundRepository {
+    /// Record a refund
+    pub async fn create(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        input: CreateRefund,
+    ) -> Result<Refund, sqlx::Error> {
+        sqlx::query_as::<_, Refund>(
+            r#"
+            INSERT INTO refunds (
+                organization_id, transaction_id, amount_cents, currency, reason, status,
+                source, initiated_by, provider_refund_id, subtotal_refunded_cents,
+                tip_refunded_cents, customer_fee_refunded_cents, tax_refunded_cents,
+                platform_fee_reversed_cents, provider_payout_reversed_cents
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            RETURNING *
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(input.transaction_id)
+        .bind(input.amount_cents)
+        .bind(input.currency)
+        .bind(&input.reason)
+        .bind(input.status)
+        .bind(input.source)
+        .bind(input.initiated_by.map(|id| *id.as_uuid()))
+        .bind(&input.provider_refund_id)
+        .bind(input.allocation.subtotal_cents)
+        .bind(input.allocation.tip_cents)
+        .bind(input.allocation.customer_fee_cents)
+        .bind(input.allocation.tax_cents)
+        .bind(input.allocation.platform_fee_cents)
+        .bind(input.allocation.provider_payout_cents)
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Record a refund the provider reported. Returns `None` if a refund
+    /// with its provider refund ID was already recorded.
+    pub async fn create_for_provider(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        input: CreateRefund,
+    ) -> Result<Option<Refund>, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+
+        // Serializes with a platform refund being matched to the same
+        // provider refund
+        if let Some(provider_refund_id) = &input.provider_refund_id {
+            lock_provider_refund(&mut tx, org_id, provider_refund_id).await?;
+        }
+
+        let refund = sqlx::query_as::<_, Refund>(
+            r#"
+            INSERT INTO refunds (
+                organization_id, transaction_id, amount_cents, currency, reason, status,
+                source, initiated_by, provider_refund_id, subtotal_refunded_cents,
+                tip_refunded_cents, customer_fee_refunded_cents, tax_refunded_cents,
+                platform_fee_reversed_cents, provider_payout_reversed_cents
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            ON CONFLICT (organization_id, provider_refund_id)
+                WHERE provider_refund_id IS NOT NULL
+                DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(input.transaction_id)
+        .bind(input.amount_cents)
+        .bind(input.currency)
+        .bind(&input.reason)
+        .bind(input.status)
+        .bind(input.source)
+        .bind(input.initiated_by.map(|id| *id.as_uuid()))
+        .bind(&input.provider_refund_id)
+        .bind(input.allocation.subtotal_cents)
+        .bind(input.allocation.tip_cents)
+        .bind(input.allocation.customer_fee_cents)
+        .bind(input.allocation.tax_cents)
+        .bind(input.allocation.platform_fee_cents)
+        .bind(input.allocation.provider_payout_cents)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(refund)
+    }
+
+    /// Match a pending platform refund to the provider refund it became and
+    /// set its status. If the provider's report of the refund was recorded
+    /// first, that record is kept, taking over who issued the refund and
+    /// why, and the pending one is removed.
+    ///
+    /// Returns the refund and whether its status changed, so a refund is
+    /// only counted against its transaction once.
+    pub async fn attach_provider_refund(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        id: Uuid,
+        provider_refund_id: &str,
+        status: RefundStatus,
+    ) -> Result<(Refund, bool), sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        lock_provider_refund(&mut tx, org_id, provider_refund_id).await?;
+
+        let reported = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            SELECT id FROM refunds
+            WHERE organization_id = $1 AND provider_refund_id = $2 AND id <> $3
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(provider_refund_id)
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let target = match reported {
+            Some(reported) => {
+                sqlx::query(
+                    r#"
+                    UPDATE refunds r
+                    SET source = p.source,
+                        initiated_by = p.initiated_by,
+                        reason = COALESCE(r.reason, p.reason),
+                        updated_at = NOW()
+                    FROM refunds p
+                    WHERE r.id = $1 AND p.id = $2 AND p.organization_id = $3
+                    "#,
+                )
+                .bind(reported)
+                .bind(id)
+                .bind(org_id.as_uuid())
+                .execute(&mut *tx)
+                .await?;
+                sqlx::query("DELETE FROM refunds WHERE id = $1 AND organization_id = $2")
+                    .bind(id)
+                    .bind(org_id.as_uuid())
+                    .execute(&mut *tx)
+                    .await?;
+                reported
+            }
+            None => id,
+        };
+
+        let previous = sqlx::query_scalar::<_, RefundStatus>(
+            "SELECT status FROM refunds WHERE id = $1 FOR UPDATE",
+        )
+        .bind(target)
+        .fetch_one(&mut *tx)
+        .await?;
+        let refund = sqlx::query_as::<_, Refund>(
+            r#"
+            UPDATE refunds
+            SET status = $2, provider_refund_id = $3, updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(target)
+        .bind(status)
+        .bind(provider_refund_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok((refund, previous != status))
+    }
+
+    /// List a transaction's refunds, oldest first
+    pub async fn list_by_transaction(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        transaction_id: Uuid,
+    ) -> Result<Vec<Refund>, sqlx::Error> {
+        sqlx::query_as::<_, Refund>(
+            r#"
+            SELECT * FROM refunds
+            WHERE transaction_id = $1 AND organization_id = $2
+            ORDER BY created_at
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(org_id.as_uuid())
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Get refunds by ID
+    pub async fn list_by_ids(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        ids: &[Uuid],
+    ) -> Result<Vec<Refund>, sqlx::Error> {
+        sqlx::query_as::<_, Refund>(
+            r#"
+            SELECT * FROM refunds
+            WHERE id = ANY($1) AND organization_id = $2
+            ORDER BY created_at
+            "#,
+        )
+        .bind(ids)
+        .bind(org_id.as_uuid())
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Refunds of a transaction that no live payout has deducted yet
+    pub async fn list_undeducted_by_transaction(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        transaction_id: Uuid,
+    ) -> Result<Vec<Refund>, sqlx::Error> {
+        sqlx::query_as::<_, Refund>(
+            r#"
+            SELECT * FROM refunds
+            WHERE transaction_id = $1 AND organization_id = $2
+                AND id NOT IN (
+                    SELECT UNNEST(refund_ids) FROM payouts
+                    WHERE organization_id = $2
+                        AND status NOT IN ('failed', 'canceled')
+                )
+            ORDER BY created_at
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(org_id.as_uuid())
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Refunds made before `before_date` whose transaction a live payout has
+    /// already paid out, and that no live payout has deducted yet
+    pub async fn list_undeducted_after_payout(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        before_date: DateTime<Utc>,
+    ) -> Result<Vec<Refund>, sqlx::Error> {
+        sqlx::query_as::<_, Refund>(
+            r#"
+            SELECT * FROM refunds
+            WHERE organization_id = $1
+                AND status IN ('pending', 'succeeded')
+                AND created_at < $2
+                AND transaction_id IN (
+                    SELECT UNNEST(transaction_ids) FROM payouts
+                    WHERE organization_id = $1
+                        AND status NOT IN ('failed', 'canceled')
+                )
+                AND id NOT IN (
+                    SELECT UNNEST(refund_ids) FROM payouts
+                    WHERE organization_id = $1
+                        AND status NOT IN ('failed', 'canceled')
+                )
+            ORDER BY created_at
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(before_date)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Find a refund by the provider's refund ID
+    pub async fn get_by_provider_refund_id(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        provider_refund_id: &str,
+    ) -> Result<Option<Refund>, sqlx::Error> {
+        sqlx::query_as::<_, Refund>(
+            r#"
+            SELECT * FROM refunds
+            WHERE provider_refund_id = $1 AND organization_id = $2
+            "#,
+        )
+        .bind(provider_refund_id)
+        .bind(org_id.as_uuid())
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Total of a transaction's refunds still waiting on the provider
+    pub async fn pending_total(pool: &PgPool, transaction_id: Uuid) -> Result<i64, sqlx::Error> {
+        let total: Option<i64> = sqlx::query_scalar(
+            r#"
+            SELECT SUM(amount_cents)::BIGINT FROM refunds
+            WHERE transaction_id = $1 AND status = 'pending'
+            "#,
+        )
+        .bind(transaction_id)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(total.unwrap_or(0))
+    }
+
+    /// Record that the refund was reported to the tax provider
+    pub async fn mark_tax_reported(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE refunds
+            SET tax_reported_at = NOW(), updated_at = NOW()
+            WHERE id = $1 AND tax_reported_at IS NULL
+            "#,
+        )
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Succeeded refunds of reported sales that haven't been reported to the
+    /// tax provider themselves, oldest first
+    pub async fn list_tax_unreported(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        limit: i64,
+    ) -> Result<Vec<Refund>, sqlx::Error> {
+        sqlx::query_as::<_, Refund>(
+            r#"
+            SELECT r.* FROM refunds r
+            JOIN transactions t ON t.id = r.transaction_id
+            WHERE r.organization_id = $1
+                AND r.status = 'succeeded'
+                AND r.tax_reported_at IS NULL
+                AND t.tax_reported_at IS NOT NULL
+            ORDER BY r.created_at
+            LIMIT $2
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Move a refund to `status`. Returns `None` if it was already there,
+    /// so concurrent reports only count a refund once.
+    pub async fn transition_status(
+        pool: &PgPool,
+        id: Uuid,
+        status: RefundStatus,
+    ) -> Result<Option<Refund>, sqlx::Error> {
+        sqlx::query_as::<_, Refund>(
+            r#"
+            UPDATE refunds
+            SET status = $2, updated_at = NOW()
+            WHERE id = $1 AND status <> $2
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(status)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Update a refund after hearing back from the provider
+    pub async fn update_status(
+        pool: &PgPool,
+        id: Uuid,
+        status: RefundStatus,
+        provider_refund_id: Option<&str>,
+        failure_message: Option<&str>,
+    ) -> Result<Option<Refund>, sqlx::Error> {
+        sqlx::query_as::<_, Refund>(
+            r#"
+            UPDATE refunds
+            SET status = $2,
+                provider_refund_id = COALESCE($3, provider_refund_id),
+                failure_message = COALESCE($4, failure_message),
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(status)
+        .bind(provider_refund_id)
+        .bind(failure_message)
+        .fetch_optional(pool)
+        .await
+    }
+}
+
+/// Hold a transaction-scoped lock on a provider refund ID
+async fn lock_provider_refund(
+    tx: &mut Transaction<'_, Postgres>,
+    org_id: OrganizationId,
+    provider_refund_id: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+        .bind(format!("refund_{}_{}", org_id, provider_refund_id))
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
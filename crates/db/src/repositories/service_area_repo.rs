@@ -14,7 +14,7 @@ impl ServiceAreaRepository {
     ) -> Result<Option<ServiceArea>, sqlx::Error> {
         sqlx::query_as::<_, ServiceArea>(
             r#"
-            SELECT id, organization_id, walker_id, name, color, polygon,
+            SELECT id, organization_id, walker_id, name, color, polygon, holes,
                    min_latitude, max_latitude, min_longitude, max_longitude,
                    is_active, priority, price_adjustment_percent, notes,
                    created_at, updated_at
@@ -35,7 +35,7 @@ impl ServiceAreaRepository {
     ) -> Result<Vec<ServiceArea>, sqlx::Error> {
         sqlx::query_as::<_, ServiceArea>(
             r#"
-            SELECT id, organization_id, walker_id, name, color, polygon,
+            SELECT id, organization_id, walker_id, name, color, polygon, holes,
                    min_latitude, max_latitude, min_longitude, max_longitude,
                    is_active, priority, price_adjustment_percent, notes,
                    created_at, updated_at
@@ -56,7 +56,7 @@ impl ServiceAreaRepository {
     ) -> Result<Vec<ServiceArea>, sqlx::Error> {
         sqlx::query_as::<_, ServiceArea>(
             r#"
-            SELECT id, organization_id, walker_id, name, color, polygon,
+            SELECT id, organization_id, walker_id, name, color, polygon, holes,
                    min_latitude, max_latitude, min_longitude, max_longitude,
                    is_active, priority, price_adjustment_percent, notes,
                    created_at, updated_at
@@ -75,13 +75,14 @@ impl ServiceAreaRepository {
         area: CreateServiceArea,
     ) -> Result<ServiceArea, sqlx::Error> {
         let polygon_json = sqlx::types::Json(&area.polygon);
+        let holes_json = sqlx::types::Json(&area.holes);
 
         sqlx::query_as::<_, ServiceArea>(
             r#"
-            INSERT INTO service_areas (organization_id, walker_id, name, color, polygon,
+            INSERT INTO service_areas (organization_id, walker_id, name, color, polygon, holes,
                 is_active, priority, price_adjustment_percent, notes)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
-            RETURNING id, organization_id, walker_id, name, color, polygon,
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING id, organization_id, walker_id, name, color, polygon, holes,
                 min_latitude, max_latitude, min_longitude, max_longitude,
                 is_active, priority, price_adjustment_percent, notes,
                 created_at, updated_at
@@ -92,6 +93,7 @@ impl ServiceAreaRepository {
         .bind(&area.name)
         .bind(&area.color)
         .bind(polygon_json)
+        .bind(holes_json)
         .bind(area.is_active)
         .bind(area.priority)
         .bind(area.price_adjustment_percent)
@@ -110,6 +112,8 @@ impl ServiceAreaRepository {
             .polygon
             .as_ref()
             .map(|p| sqlx::types::Json(p.clone()));
+        let holes_json: Option<sqlx::types::Json<Vec<Vec<crate::models::PolygonPoint>>>> =
+            update.holes.as_ref().map(|h| sqlx::types::Json(h.clone()));
 
         sqlx::query_as::<_, ServiceArea>(
             r#"
@@ -117,13 +121,14 @@ impl ServiceAreaRepository {
             SET name = COALESCE($3, name),
                 color = COALESCE($4, color),
                 polygon = COALESCE($5, polygon),
-                is_active = COALESCE($6, is_active),
-                priority = COALESCE($7, priority),
-                price_adjustment_percent = COALESCE($8, price_adjustment_percent),
-                notes = COALESCE($9, notes),
+                holes = COALESCE($6, holes),
+                is_active = COALESCE($7, is_active),
+                priority = COALESCE($8, priority),
+                price_adjustment_percent = COALESCE($9, price_adjustment_percent),
+                notes = COALESCE($10, notes),
                 updated_at = NOW()
             WHERE id = $1 AND organization_id = $2
-            RETURNING id, organization_id, walker_id, name, color, polygon,
+            RETURNING id, organization_id, walker_id, name, color, polygon, holes,
                 min_latitude, max_latitude, min_longitude, max_longitude,
                 is_active, priority, price_adjustment_percent, notes,
                 created_at, updated_at
@@ -134,6 +139,7 @@ impl ServiceAreaRepository {
         .bind(&update.name)
         .bind(&update.color)
         .bind(polygon_json)
+        .bind(holes_json)
         .bind(update.is_active)
         .bind(update.priority)
         .bind(update.price_adjustment_percent)
@@ -168,11 +174,29 @@ impl ServiceAreaRepository {
         latitude: f64,
         longitude: f64,
     ) -> Result<Vec<(Uuid, Uuid, String)>, sqlx::Error> {
-        // Uses bounding box for quick filtering
-        // Note: For precise polygon containment, you'd need PostGIS or application-level check
-        sqlx::query_as::<_, (Uuid, Uuid, String)>(
+        let areas = Self::find_areas_for_location(pool, org_id, latitude, longitude).await?;
+
+        Ok(areas
+            .into_iter()
+            .map(|area| (area.walker_id, area.id, area.name))
+            .collect())
+    }
+
+    /// Find active service areas whose polygon contains a given location,
+    /// ordered by priority (lower = higher priority)
+    pub async fn find_areas_for_location(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        latitude: f64,
+        longitude: f64,
+    ) -> Result<Vec<ServiceArea>, sqlx::Error> {
+        // Bounding box prefilter in SQL, exact polygon containment in application
+        let candidates = sqlx::query_as::<_, ServiceArea>(
             r#"
-            SELECT walker_id, id, name
+            SELECT id, organization_id, walker_id, name, color, polygon, holes,
+                   min_latitude, max_latitude, min_longitude, max_longitude,
+                   is_active, priority, price_adjustment_percent, notes,
+                   created_at, updated_at
             FROM service_areas
             WHERE organization_id = $1
               AND is_active = TRUE
@@ -187,6 +211,28 @@ impl ServiceAreaRepository {
         .bind(latitude)
         .bind(longitude)
         .fetch_all(pool)
-        .await
+        .await?;
+
+        Ok(candidates
+            .into_iter()
+            .filter(|area| area.contains(latitude, longitude))
+            .collect())
+    }
+
+    /// Find a walker's active service areas containing a given location,
+    /// ordered by priority (lower = higher priority)
+    pub async fn find_walker_areas_for_location(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        walker_id: UserId,
+        latitude: f64,
+        longitude: f64,
+    ) -> Result<Vec<ServiceArea>, sqlx::Error> {
+        let areas = Self::find_areas_for_location(pool, org_id, latitude, longitude).await?;
+
+        Ok(areas
+            .into_iter()
+            .filter(|area| area.walker_id == *walker_id.as_uuid())
+            .collect())
     }
 }
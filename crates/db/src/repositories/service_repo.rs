@@ -11,9 +11,9 @@ impl ServiceRepository {
 
         sqlx::query_as::<_, Service>(
             r#"
-            INSERT INTO services (id, organization_id, name, description, duration_minutes, base_price_cents)
-            VALUES ($1, $2, $3, $4, $5, $6)
-            RETURNING id, organization_id, name, description, duration_minutes, base_price_cents, is_active, created_at, updated_at
+            INSERT INTO services (id, organization_id, name, description, duration_minutes, base_price_cents, currency)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id, organization_id, name, description, duration_minutes, base_price_cents, currency, is_active, created_at, updated_at
             "#,
         )
         .bind(id.as_uuid())
@@ -22,6 +22,7 @@ impl ServiceRepository {
         .bind(&input.description)
         .bind(input.duration_minutes)
         .bind(input.base_price_cents)
+        .bind(input.currency)
         .fetch_one(pool)
         .await
     }
@@ -33,7 +34,7 @@ impl ServiceRepository {
     ) -> Result<Option<Service>, sqlx::Error> {
         sqlx::query_as::<_, Service>(
             r#"
-            SELECT id, organization_id, name, description, duration_minutes, base_price_cents, is_active, created_at, updated_at
+            SELECT id, organization_id, name, description, duration_minutes, base_price_cents, currency, is_active, created_at, updated_at
             FROM services
             WHERE id = $1 AND organization_id = $2
             "#,
@@ -50,7 +51,7 @@ impl ServiceRepository {
     ) -> Result<Vec<Service>, sqlx::Error> {
         sqlx::query_as::<_, Service>(
             r#"
-            SELECT id, organization_id, name, description, duration_minutes, base_price_cents, is_active, created_at, updated_at
+            SELECT id, organization_id, name, description, duration_minutes, base_price_cents, currency, is_active, created_at, updated_at
             FROM services
             WHERE organization_id = $1 AND is_active = true
             ORDER BY name
@@ -67,7 +68,7 @@ impl ServiceRepository {
     ) -> Result<Vec<Service>, sqlx::Error> {
         sqlx::query_as::<_, Service>(
             r#"
-            SELECT id, organization_id, name, description, duration_minutes, base_price_cents, is_active, created_at, updated_at
+            SELECT id, organization_id, name, description, duration_minutes, base_price_cents, currency, is_active, created_at, updated_at
             FROM services
             WHERE organization_id = $1
             ORDER BY name
@@ -93,9 +94,10 @@ impl ServiceRepository {
                 duration_minutes = COALESCE($5, duration_minutes),
                 base_price_cents = COALESCE($6, base_price_cents),
                 is_active = COALESCE($7, is_active),
+                currency = COALESCE($8, currency),
                 updated_at = NOW()
             WHERE id = $1 AND organization_id = $2
-            RETURNING id, organization_id, name, description, duration_minutes, base_price_cents, is_active, created_at, updated_at
+            RETURNING id, organization_id, name, description, duration_minutes, base_price_cents, currency, is_active, created_at, updated_at
             "#,
         )
         .bind(id.as_uuid())
@@ -105,6 +107,7 @@ impl ServiceRepository {
         .bind(input.duration_minutes)
         .bind(input.base_price_cents)
         .bind(input.is_active)
+        .bind(input.currency)
         .fetch_optional(pool)
         .await
     }
@@ -1,10 +1,13 @@
-use shared::types::{OrganizationId, UserId};
+use chrono::{DateTime, Utc};
+use shared::types::{BookingId, OrganizationId, UserId};
 use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::models::{
-    CreateCustomerSubscription, CreateTenantSubscription, CustomerSubscription, PlanTier,
-    PlatformFeeTier, TenantSubscription, UpdateCustomerSubscription, UpdateTenantSubscription,
+    CreateCustomerSubscription, CreatePackagePlan, CreateTenantSubscription, CustomerSubscription,
+    PackageCredit, PackagePlan, PlanTier, PlatformFeeTier, SubscriptionStatus,
+    SyncTenantSubscription, TenantSubscription, UpdateCustomerSubscription,
+    UpdateTenantSubscription,
 };
 
 pub struct SubscriptionRepository;
@@ -42,7 +45,8 @@ impl SubscriptionRepository {
     }
 
     /// Get fee tier for an organization based on their subscription
-    /// Returns the 'free' tier if no subscription exists
+    /// Returns the 'free' tier if no subscription exists, or if it lapsed or
+    /// was restricted for non-payment
     pub async fn get_org_fee_tier(
         pool: &PgPool,
         org_id: OrganizationId,
@@ -53,6 +57,8 @@ impl SubscriptionRepository {
             SELECT pft.*
             FROM platform_fee_tiers pft
             LEFT JOIN tenant_subscriptions ts ON ts.plan_tier = pft.plan_tier
+                AND ts.status IN ('active', 'trialing', 'past_due')
+                AND ts.restricted_at IS NULL
             WHERE (ts.organization_id = $1 OR pft.plan_tier = 'free')
             ORDER BY
                 CASE WHEN ts.organization_id = $1 THEN 0 ELSE 1 END,
@@ -168,6 +174,132 @@ impl SubscriptionRepository {
         .await
     }
 
+    /// Record a tenant subscription as Stripe reports it. The subscription
+    /// becomes past due from the first time it's reported so, and its
+    /// restriction is lifted once it isn't. Events for a subscription the
+    /// organization has since replaced are ignored.
+    pub async fn sync_tenant_subscription(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        input: &SyncTenantSubscription,
+    ) -> Result<Option<TenantSubscription>, sqlx::Error> {
+        sqlx::query_as::<_, TenantSubscription>(
+            r#"
+            INSERT INTO tenant_subscriptions (
+                id, organization_id, plan_tier,
+                stripe_subscription_id, stripe_customer_id, status,
+                current_period_start, current_period_end, trial_start, trial_end,
+                cancel_at_period_end, canceled_at, past_due_since
+            )
+            VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12,
+                CASE WHEN $6 = 'past_due'::subscription_status THEN NOW() END
+            )
+            ON CONFLICT (organization_id) DO UPDATE
+            SET
+                plan_tier = EXCLUDED.plan_tier,
+                stripe_subscription_id = EXCLUDED.stripe_subscription_id,
+                stripe_customer_id = EXCLUDED.stripe_customer_id,
+                status = EXCLUDED.status,
+                current_period_start = EXCLUDED.current_period_start,
+                current_period_end = EXCLUDED.current_period_end,
+                trial_start = EXCLUDED.trial_start,
+                trial_end = EXCLUDED.trial_end,
+                cancel_at_period_end = EXCLUDED.cancel_at_period_end,
+                canceled_at = EXCLUDED.canceled_at,
+                past_due_since = CASE
+                    WHEN EXCLUDED.status = 'past_due'
+                    THEN COALESCE(tenant_subscriptions.past_due_since, NOW())
+                END,
+                restricted_at = CASE
+                    WHEN EXCLUDED.status = 'past_due' THEN tenant_subscriptions.restricted_at
+                END,
+                updated_at = NOW()
+            WHERE tenant_subscriptions.stripe_subscription_id IS NULL
+                OR tenant_subscriptions.stripe_subscription_id = EXCLUDED.stripe_subscription_id
+                OR tenant_subscriptions.status IN ('canceled', 'incomplete')
+            RETURNING *
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(org_id.as_uuid())
+        .bind(input.plan_tier)
+        .bind(&input.stripe_subscription_id)
+        .bind(&input.stripe_customer_id)
+        .bind(input.status)
+        .bind(input.current_period_start)
+        .bind(input.current_period_end)
+        .bind(input.trial_start)
+        .bind(input.trial_end)
+        .bind(input.cancel_at_period_end)
+        .bind(input.canceled_at)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Restrict a past-due tenant subscription's paid features. Returns
+    /// `None` if it isn't past due or is already restricted.
+    pub async fn restrict_tenant_subscription(
+        pool: &PgPool,
+        org_id: OrganizationId,
+    ) -> Result<Option<TenantSubscription>, sqlx::Error> {
+        sqlx::query_as::<_, TenantSubscription>(
+            r#"
+            UPDATE tenant_subscriptions
+            SET restricted_at = NOW(), updated_at = NOW()
+            WHERE organization_id = $1
+                AND status = 'past_due'
+                AND restricted_at IS NULL
+            RETURNING *
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Record the organization a platform billing customer belongs to. Uses
+    /// the main database pool.
+    pub async fn register_billing_customer(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        stripe_customer_id: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO stripe_billing_customers (stripe_customer_id, organization_id)
+            VALUES ($1, $2)
+            ON CONFLICT (stripe_customer_id)
+            DO UPDATE SET organization_id = EXCLUDED.organization_id
+            "#,
+        )
+        .bind(stripe_customer_id)
+        .bind(org_id.as_uuid())
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The organization a platform billing customer belongs to. Uses the
+    /// main database pool.
+    pub async fn find_org_by_billing_customer(
+        pool: &PgPool,
+        stripe_customer_id: &str,
+    ) -> Result<Option<OrganizationId>, sqlx::Error> {
+        let org_id: Option<Uuid> = sqlx::query_scalar(
+            r#"
+            SELECT organization_id FROM stripe_billing_customers
+            WHERE stripe_customer_id = $1
+            "#,
+        )
+        .bind(stripe_customer_id)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(org_id.map(OrganizationId::from_uuid))
+    }
+
     /// Update organization plan tier (also updates organizations table)
     pub async fn update_org_plan_tier(
         pool: &PgPool,
@@ -205,9 +337,14 @@ impl SubscriptionRepository {
             INSERT INTO customer_subscriptions (
                 id, organization_id, user_id, service_id,
                 name, description, price_cents, interval, interval_count,
-                auto_create_bookings, preferred_day_of_week, preferred_time
+                auto_create_bookings, preferred_day_of_week, preferred_time,
+                walks_per_period, preferred_walker_id, location_id, timezone,
+                payment_method_id, package_plan_id, status
+            )
+            VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12,
+                $13, $14, $15, $16, $17, $18, 'incomplete'
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
             RETURNING *
             "#,
         )
@@ -223,6 +360,12 @@ impl SubscriptionRepository {
         .bind(input.auto_create_bookings)
         .bind(input.preferred_day_of_week)
         .bind(input.preferred_time)
+        .bind(input.walks_per_period)
+        .bind(input.preferred_walker_id.map(|id| *id.as_uuid()))
+        .bind(input.location_id.map(|id| *id.as_uuid()))
+        .bind(&input.timezone)
+        .bind(input.payment_method_id)
+        .bind(input.package_plan_id)
         .fetch_one(pool)
         .await
     }
@@ -390,21 +533,395 @@ impl SubscriptionRepository {
         }
     }
 
-    /// Get subscriptions needing booking creation
-    pub async fn get_subscriptions_for_booking_creation(
+    /// Claim the subscriptions whose next period is due to be charged,
+    /// including past-due ones whose retry delay has passed. Claimed
+    /// subscriptions aren't due again until `claimed_until`, so concurrent
+    /// runs each charge a subscription at most once.
+    pub async fn claim_due_customer_subscriptions(
         pool: &PgPool,
+        org_id: OrganizationId,
+        now: DateTime<Utc>,
+        claimed_until: DateTime<Utc>,
     ) -> Result<Vec<CustomerSubscription>, sqlx::Error> {
         sqlx::query_as::<_, CustomerSubscription>(
             r#"
-            SELECT * FROM customer_subscriptions
-            WHERE status = 'active'
-                AND auto_create_bookings = true
-                AND current_period_end IS NOT NULL
-                AND current_period_end > NOW()
-            ORDER BY current_period_end ASC
+            UPDATE customer_subscriptions
+            SET next_payment_attempt_at = $3, updated_at = NOW()
+            WHERE id IN (
+                SELECT id FROM customer_subscriptions
+                WHERE organization_id = $1
+                    AND status IN ('active', 'past_due')
+                    AND current_period_end <= $2
+                    AND (next_payment_attempt_at IS NULL OR next_payment_attempt_at <= $2)
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
             "#,
         )
+        .bind(org_id.as_uuid())
+        .bind(now)
+        .bind(claimed_until)
         .fetch_all(pool)
         .await
     }
+
+    /// Start a paid period: make the subscription active and clear any
+    /// failed payment attempts
+    pub async fn renew_customer_subscription(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        id: Uuid,
+        period_start: DateTime<Utc>,
+        period_end: DateTime<Utc>,
+    ) -> Result<Option<CustomerSubscription>, sqlx::Error> {
+        sqlx::query_as::<_, CustomerSubscription>(
+            r#"
+            UPDATE customer_subscriptions
+            SET
+                status = 'active',
+                current_period_start = $3,
+                current_period_end = $4,
+                failed_payment_attempts = 0,
+                next_payment_attempt_at = NULL,
+                updated_at = NOW()
+            WHERE id = $1 AND organization_id = $2
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(org_id.as_uuid())
+        .bind(period_start)
+        .bind(period_end)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Record a failed attempt to charge the next period
+    pub async fn record_package_payment_failure(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        id: Uuid,
+        status: SubscriptionStatus,
+        next_payment_attempt_at: Option<DateTime<Utc>>,
+    ) -> Result<Option<CustomerSubscription>, sqlx::Error> {
+        sqlx::query_as::<_, CustomerSubscription>(
+            r#"
+            UPDATE customer_subscriptions
+            SET
+                status = $3,
+                failed_payment_attempts = failed_payment_attempts + 1,
+                next_payment_attempt_at = $4,
+                canceled_at = CASE WHEN $3 = 'canceled' THEN NOW() ELSE canceled_at END,
+                updated_at = NOW()
+            WHERE id = $1 AND organization_id = $2
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(org_id.as_uuid())
+        .bind(status)
+        .bind(next_payment_attempt_at)
+        .fetch_optional(pool)
+        .await
+    }
+
+    // ========== Package Plans ==========
+
+    /// Offer a new package plan
+    pub async fn create_package_plan(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        input: CreatePackagePlan,
+    ) -> Result<PackagePlan, sqlx::Error> {
+        sqlx::query_as::<_, PackagePlan>(
+            r#"
+            INSERT INTO package_plans (
+                organization_id, service_id, name, description, price_cents,
+                interval, interval_count, walks_per_period
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING *
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(input.service_id)
+        .bind(&input.name)
+        .bind(input.description)
+        .bind(input.price_cents)
+        .bind(&input.interval)
+        .bind(input.interval_count)
+        .bind(input.walks_per_period)
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Get a package plan by ID
+    pub async fn get_package_plan(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        id: Uuid,
+    ) -> Result<Option<PackagePlan>, sqlx::Error> {
+        sqlx::query_as::<_, PackagePlan>(
+            r#"
+            SELECT * FROM package_plans
+            WHERE id = $1 AND organization_id = $2
+            "#,
+        )
+        .bind(id)
+        .bind(org_id.as_uuid())
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// The package plans customers can subscribe to, newest first
+    pub async fn list_active_package_plans(
+        pool: &PgPool,
+        org_id: OrganizationId,
+    ) -> Result<Vec<PackagePlan>, sqlx::Error> {
+        sqlx::query_as::<_, PackagePlan>(
+            r#"
+            SELECT * FROM package_plans
+            WHERE organization_id = $1 AND is_active = true
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Stop offering a package plan. Existing subscriptions carry on.
+    pub async fn archive_package_plan(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        id: Uuid,
+    ) -> Result<Option<PackagePlan>, sqlx::Error> {
+        sqlx::query_as::<_, PackagePlan>(
+            r#"
+            UPDATE package_plans
+            SET is_active = false, updated_at = NOW()
+            WHERE id = $1 AND organization_id = $2
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(org_id.as_uuid())
+        .fetch_optional(pool)
+        .await
+    }
+
+    // ========== Package Credits ==========
+
+    /// Walk credits a subscription has left
+    pub async fn credit_balance(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        subscription_id: Uuid,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COALESCE(SUM(credits), 0)::BIGINT FROM package_credits
+            WHERE subscription_id = $1 AND organization_id = $2
+            "#,
+        )
+        .bind(subscription_id)
+        .bind(org_id.as_uuid())
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Grant a paid period's credits. Returns `None` if the period was
+    /// already granted.
+    pub async fn grant_credits(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        subscription_id: Uuid,
+        credits: i32,
+        transaction_id: Option<Uuid>,
+        period_end: DateTime<Utc>,
+    ) -> Result<Option<PackageCredit>, sqlx::Error> {
+        sqlx::query_as::<_, PackageCredit>(
+            r#"
+            INSERT INTO package_credits (
+                organization_id, subscription_id, kind, credits, transaction_id, period_end
+            )
+            VALUES ($1, $2, 'grant', $3, $4, $5)
+            ON CONFLICT (subscription_id, kind, period_end) WHERE kind IN ('grant', 'expire')
+            DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(subscription_id)
+        .bind(credits)
+        .bind(transaction_id)
+        .bind(period_end)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Expire the credits left when a period ends. Does nothing if none are
+    /// left or they were already expired.
+    pub async fn expire_credits(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        subscription_id: Uuid,
+        period_end: DateTime<Utc>,
+    ) -> Result<Option<PackageCredit>, sqlx::Error> {
+        sqlx::query_as::<_, PackageCredit>(
+            r#"
+            INSERT INTO package_credits (
+                organization_id, subscription_id, kind, credits, period_end
+            )
+            SELECT $1, $2, 'expire', -SUM(credits)::INTEGER, $3
+            FROM package_credits
+            WHERE subscription_id = $2 AND organization_id = $1
+            HAVING SUM(credits) > 0
+            ON CONFLICT (subscription_id, kind, period_end) WHERE kind IN ('grant', 'expire')
+            DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(subscription_id)
+        .bind(period_end)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Use one of a subscription's credits for a booking, paid for by the
+    /// latest period's charge. Returns `None` if none are left or the booking
+    /// already used one.
+    pub async fn redeem_credit(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        subscription_id: Uuid,
+        booking_id: BookingId,
+    ) -> Result<Option<PackageCredit>, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+
+        // Lock the subscription so concurrent bookings can't spend the same
+        // credit
+        sqlx::query("SELECT id FROM customer_subscriptions WHERE id = $1 FOR UPDATE")
+            .bind(subscription_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let balance = sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COALESCE(SUM(credits), 0)::BIGINT FROM package_credits
+            WHERE subscription_id = $1 AND organization_id = $2
+            "#,
+        )
+        .bind(subscription_id)
+        .bind(org_id.as_uuid())
+        .fetch_one(&mut *tx)
+        .await?;
+        if balance <= 0 {
+            return Ok(None);
+        }
+
+        let credit = sqlx::query_as::<_, PackageCredit>(
+            r#"
+            INSERT INTO package_credits (
+                organization_id, subscription_id, kind, credits, booking_id, transaction_id
+            )
+            VALUES (
+                $1, $2, 'redeem', -1, $3,
+                (
+                    SELECT transaction_id FROM package_credits
+                    WHERE subscription_id = $2 AND kind = 'grant'
+                    ORDER BY period_end DESC
+                    LIMIT 1
+                )
+            )
+            ON CONFLICT (booking_id, kind) WHERE booking_id IS NOT NULL
+            DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(subscription_id)
+        .bind(booking_id.as_uuid())
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(credit)
+    }
+
+    /// The credit a booking was paid with, if any
+    pub async fn get_booking_redemption(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        booking_id: BookingId,
+    ) -> Result<Option<PackageCredit>, sqlx::Error> {
+        sqlx::query_as::<_, PackageCredit>(
+            r#"
+            SELECT * FROM package_credits
+            WHERE booking_id = $1 AND organization_id = $2 AND kind = 'redeem'
+            "#,
+        )
+        .bind(booking_id.as_uuid())
+        .bind(org_id.as_uuid())
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Return a cancelled booking's credit. Returns `None` if it was already
+    /// returned.
+    pub async fn restore_credit(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        subscription_id: Uuid,
+        booking_id: BookingId,
+    ) -> Result<Option<PackageCredit>, sqlx::Error> {
+        sqlx::query_as::<_, PackageCredit>(
+            r#"
+            INSERT INTO package_credits (
+                organization_id, subscription_id, kind, credits, booking_id
+            )
+            VALUES ($1, $2, 'restore', 1, $3)
+            ON CONFLICT (booking_id, kind) WHERE booking_id IS NOT NULL
+            DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(subscription_id)
+        .bind(booking_id.as_uuid())
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// The customer's active package with credits left for a service.
+    /// Packages only cover bookings of their own service.
+    pub async fn find_package_for_booking(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        user_id: UserId,
+        service_id: Uuid,
+    ) -> Result<Option<CustomerSubscription>, sqlx::Error> {
+        sqlx::query_as::<_, CustomerSubscription>(
+            r#"
+            SELECT s.* FROM customer_subscriptions s
+            WHERE s.organization_id = $1
+                AND s.user_id = $2
+                AND s.status = 'active'
+                AND s.walks_per_period IS NOT NULL
+                AND s.service_id = $3
+                AND (
+                    SELECT COALESCE(SUM(c.credits), 0) FROM package_credits c
+                    WHERE c.subscription_id = s.id
+                ) > 0
+            ORDER BY s.created_at ASC
+            LIMIT 1
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(user_id.as_uuid())
+        .bind(service_id)
+        .fetch_optional(pool)
+        .await
+    }
 }
@@ -1,19 +1,25 @@
 use chrono::{DateTime, Utc};
-use shared::types::{BookingId, OrganizationId, UserId};
+use shared::types::{BookingId, Currency, OrganizationId, UserId};
 use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::models::{CreateTransaction, Transaction, TransactionStatus, UpdateTransaction};
+use crate::models::{
+    CreateTransaction, Transaction, TransactionFeeBreakdown, TransactionStatus, UpdateTransaction,
+};
 
 pub struct TransactionRepository;
 
 impl TransactionRepository {
     /// Create a new transaction
+    ///
+    /// Returns `None` if the booking already has a live transaction (see
+    /// [`TransactionStatus::is_live`]), or another transaction has the same
+    /// idempotency key.
     pub async fn create(
         pool: &PgPool,
         org_id: OrganizationId,
         input: CreateTransaction,
-    ) -> Result<Transaction, sqlx::Error> {
+    ) -> Result<Option<Transaction>, sqlx::Error> {
         let id = Uuid::new_v4();
 
         sqlx::query_as::<_, Transaction>(
@@ -24,9 +30,11 @@ impl TransactionRepository {
                 subtotal_cents, tip_cents, customer_fee_cents, provider_fee_cents,
                 platform_fee_cents, tax_cents, processing_fee_cents, total_cents,
                 provider_payout_cents, currency, tax_rate_percent, tax_jurisdiction,
-                description, metadata
+                tax_calculation_id, description, metadata, idempotency_key,
+                manual_capture, off_session
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25)
+            ON CONFLICT DO NOTHING
             RETURNING *
             "#,
         )
@@ -34,7 +42,7 @@ impl TransactionRepository {
         .bind(org_id.as_uuid())
         .bind(input.booking_id.map(|b| b.into_uuid()))
         .bind(input.customer_user_id.as_uuid())
-        .bind(input.provider_user_id.as_uuid())
+        .bind(input.provider_user_id.map(|id| *id.as_uuid()))
         .bind(input.payment_method_id)
         .bind(input.provider_id)
         .bind(input.subtotal_cents)
@@ -46,12 +54,16 @@ impl TransactionRepository {
         .bind(input.processing_fee_cents)
         .bind(input.total_cents)
         .bind(input.provider_payout_cents)
-        .bind(&input.currency)
+        .bind(input.currency)
         .bind(input.tax_rate_percent)
         .bind(input.tax_jurisdiction)
+        .bind(input.tax_calculation_id)
         .bind(input.description)
         .bind(input.metadata)
-        .fetch_one(pool)
+        .bind(input.idempotency_key)
+        .bind(input.manual_capture)
+        .bind(input.off_session)
+        .fetch_optional(pool)
         .await
     }
 
@@ -167,6 +179,47 @@ impl TransactionRepository {
         .await
     }
 
+    /// Get the transaction whose payment is requested with `idempotency_key`
+    pub async fn get_by_idempotency_key(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        idempotency_key: &str,
+    ) -> Result<Option<Transaction>, sqlx::Error> {
+        sqlx::query_as::<_, Transaction>(
+            r#"
+            SELECT * FROM transactions
+            WHERE organization_id = $1 AND idempotency_key = $2
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(idempotency_key)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// The latest transaction that hasn't failed or been canceled whose
+    /// metadata contains `metadata`
+    pub async fn find_live_by_metadata(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        metadata: &serde_json::Value,
+    ) -> Result<Option<Transaction>, sqlx::Error> {
+        sqlx::query_as::<_, Transaction>(
+            r#"
+            SELECT * FROM transactions
+            WHERE organization_id = $1
+                AND metadata @> $2
+                AND status NOT IN ('failed', 'canceled')
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(metadata)
+        .fetch_optional(pool)
+        .await
+    }
+
     /// Update external payment ID
     pub async fn update_external_id(
         pool: &PgPool,
@@ -306,20 +359,237 @@ impl TransactionRepository {
         .await
     }
 
-    /// Get transaction summary for an organization
+    /// Record a hold placed at the provider, replacing any earlier one
+    pub async fn record_authorization(
+        pool: &PgPool,
+        id: Uuid,
+        external_id: &str,
+        authorized_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<Transaction>, sqlx::Error> {
+        sqlx::query_as::<_, Transaction>(
+            r#"
+            UPDATE transactions
+            SET
+                status = 'authorized',
+                external_payment_id = $2,
+                authorized_at = $3,
+                authorization_expires_at = $4,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(external_id)
+        .bind(authorized_at)
+        .bind(expires_at)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Record that held funds were captured
+    pub async fn record_capture(
+        pool: &PgPool,
+        id: Uuid,
+    ) -> Result<Option<Transaction>, sqlx::Error> {
+        sqlx::query_as::<_, Transaction>(
+            r#"
+            UPDATE transactions
+            SET status = 'succeeded', captured_at = NOW(), updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Record that only part of a hold was captured, replacing the
+    /// transaction's amounts with what was actually charged (e.g. a late
+    /// cancellation fee)
+    pub async fn record_partial_capture(
+        pool: &PgPool,
+        id: Uuid,
+        breakdown: &TransactionFeeBreakdown,
+        description: &str,
+    ) -> Result<Option<Transaction>, sqlx::Error> {
+        sqlx::query_as::<_, Transaction>(
+            r#"
+            UPDATE transactions
+            SET status = 'succeeded', captured_at = NOW(),
+                subtotal_cents = $2, tip_cents = 0, customer_fee_cents = $3,
+                provider_fee_cents = $4, platform_fee_cents = $5, tax_cents = $6,
+                processing_fee_cents = $7, processing_fee_reported = FALSE, total_cents = $8,
+                provider_payout_cents = $9,
+                description = $10, updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(breakdown.subtotal.cents())
+        .bind(breakdown.customer_fee.cents())
+        .bind(breakdown.provider_fee.cents())
+        .bind(breakdown.platform_fee.cents())
+        .bind(breakdown.tax.cents())
+        .bind(breakdown.processing_fee.cents())
+        .bind(breakdown.total.cents())
+        .bind(breakdown.provider_payout.cents())
+        .bind(description)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Replace the estimated processing fee with the fee the provider
+    /// reported
+    pub async fn record_processing_fee(
+        pool: &PgPool,
+        id: Uuid,
+        processing_fee_cents: i32,
+    ) -> Result<Option<Transaction>, sqlx::Error> {
+        sqlx::query_as::<_, Transaction>(
+            r#"
+            UPDATE transactions
+            SET processing_fee_cents = $2, processing_fee_reported = TRUE, updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(processing_fee_cents)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Record that the sale was reported to the tax provider
+    pub async fn mark_tax_reported(pool: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE transactions
+            SET tax_reported_at = NOW(), updated_at = NOW()
+            WHERE id = $1 AND tax_reported_at IS NULL
+            "#,
+        )
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Captured sales with calculated tax that haven't been reported to the
+    /// tax provider, oldest first
+    pub async fn list_tax_unreported(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        limit: i64,
+    ) -> Result<Vec<Transaction>, sqlx::Error> {
+        sqlx::query_as::<_, Transaction>(
+            r#"
+            SELECT * FROM transactions
+            WHERE organization_id = $1
+                AND tax_reported_at IS NULL
+                AND tax_jurisdiction IS NOT NULL
+                AND (
+                    captured_at IS NOT NULL
+                    OR status IN ('succeeded', 'partially_refunded', 'refunded', 'disputed')
+                )
+            ORDER BY created_at
+            LIMIT $2
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Transactions charged through a provider that were created in
+    /// `[from, to)`, for reconciling against the provider's records
+    pub async fn list_for_provider_between(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        provider_id: Uuid,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Transaction>, sqlx::Error> {
+        sqlx::query_as::<_, Transaction>(
+            r#"
+            SELECT * FROM transactions
+            WHERE organization_id = $1
+                AND provider_id = $2
+                AND created_at >= $3
+                AND created_at < $4
+            ORDER BY created_at
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(provider_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Authorized transactions whose hold is released before `before`
+    pub async fn list_expiring_authorizations(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<Transaction>, sqlx::Error> {
+        sqlx::query_as::<_, Transaction>(
+            r#"
+            SELECT * FROM transactions
+            WHERE organization_id = $1
+                AND status = 'authorized'
+                AND authorization_expires_at <= $2
+            ORDER BY authorization_expires_at
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(before)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Get held transactions whose booking is already completed, cancelled or
+    /// a no-show, i.e. ones whose capture or release still has to happen
+    pub async fn list_held_for_finished_bookings(
+        pool: &PgPool,
+        org_id: OrganizationId,
+    ) -> Result<Vec<Transaction>, sqlx::Error> {
+        sqlx::query_as::<_, Transaction>(
+            r#"
+            SELECT t.* FROM transactions t
+            JOIN bookings b ON b.id = t.booking_id
+            WHERE t.organization_id = $1
+                AND t.status = 'authorized'
+                AND b.status IN ('completed', 'cancelled', 'no_show')
+            ORDER BY t.updated_at
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Get transaction summaries for an organization, one per currency
     pub async fn get_summary(
         pool: &PgPool,
         org_id: OrganizationId,
         start_date: DateTime<Utc>,
         end_date: DateTime<Utc>,
-    ) -> Result<TransactionSummary, sqlx::Error> {
+    ) -> Result<Vec<TransactionSummary>, sqlx::Error> {
         sqlx::query_as::<_, TransactionSummary>(
             r#"
             SELECT
+                currency,
                 COUNT(*) as transaction_count,
                 COALESCE(SUM(total_cents), 0) as total_volume_cents,
                 COALESCE(SUM(provider_fee_cents), 0) as total_fees_cents,
-                COALESCE(SUM(net_amount_cents), 0) as net_earnings_cents,
+                COALESCE(SUM(provider_payout_cents), 0) as net_earnings_cents,
                 COALESCE(SUM(CASE WHEN status = 'succeeded' THEN 1 ELSE 0 END), 0) as successful_count,
                 COALESCE(SUM(CASE WHEN status = 'failed' THEN 1 ELSE 0 END), 0) as failed_count,
                 COALESCE(SUM(refunded_amount_cents), 0) as refunded_cents
@@ -327,16 +597,88 @@ impl TransactionRepository {
             WHERE organization_id = $1
                 AND created_at >= $2
                 AND created_at <= $3
+            GROUP BY currency
+            ORDER BY currency
             "#,
         )
         .bind(org_id.as_uuid())
         .bind(start_date)
         .bind(end_date)
-        .fetch_one(pool)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Charges for a booking made apart from its own payment (e.g. fees and
+    /// tips), which reference the booking in their metadata
+    pub async fn list_booking_charges(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        booking_id: BookingId,
+        charge: &str,
+    ) -> Result<Vec<Transaction>, sqlx::Error> {
+        sqlx::query_as::<_, Transaction>(
+            r#"
+            SELECT * FROM transactions
+            WHERE organization_id = $1
+                AND metadata->>'booking_id' = $2
+                AND metadata->>'charge' = $3
+            ORDER BY created_at
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(booking_id.to_string())
+        .bind(charge)
+        .fetch_all(pool)
         .await
     }
 
-    /// Get pending transactions for payout calculation
+    /// Tips paid to each walker in `[start_date, end_date)`, one entry per
+    /// walker and currency. `post_walk_charge` is the metadata `charge` of
+    /// tips paid after the walk.
+    pub async fn get_tip_summary(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+        walker_id: Option<UserId>,
+        post_walk_charge: &str,
+    ) -> Result<Vec<WalkerTipSummary>, sqlx::Error> {
+        sqlx::query_as::<_, WalkerTipSummary>(
+            r#"
+            SELECT
+                provider_user_id AS walker_id,
+                currency,
+                COUNT(*) FILTER (WHERE booking_id IS NOT NULL) AS walk_count,
+                COUNT(DISTINCT COALESCE(booking_id::text, metadata->>'booking_id'))
+                    FILTER (WHERE tip_cents > 0) AS tipped_walk_count,
+                COUNT(*) FILTER (WHERE tip_cents > 0) AS tip_count,
+                COALESCE(SUM(tip_cents), 0)::BIGINT AS tip_cents,
+                COUNT(*) FILTER (WHERE metadata->>'charge' = $5) AS post_walk_tip_count,
+                COALESCE(SUM(tip_cents) FILTER (WHERE metadata->>'charge' = $5), 0)::BIGINT
+                    AS post_walk_tip_cents
+            FROM transactions
+            WHERE organization_id = $1
+                AND status IN ('succeeded', 'partially_refunded')
+                AND created_at >= $2
+                AND created_at < $3
+                AND provider_user_id IS NOT NULL
+                AND ($4::UUID IS NULL OR provider_user_id = $4)
+            GROUP BY provider_user_id, currency
+            ORDER BY tip_cents DESC
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(start_date)
+        .bind(end_date)
+        .bind(walker_id.map(|id| *id.as_uuid()))
+        .bind(post_walk_charge)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Get captured transactions made before `before_date` that no payout
+    /// has covered yet. Transactions in failed or canceled payouts are paid
+    /// out again.
     pub async fn get_pending_for_payout(
         pool: &PgPool,
         org_id: OrganizationId,
@@ -346,10 +688,12 @@ impl TransactionRepository {
             r#"
             SELECT * FROM transactions
             WHERE organization_id = $1
-                AND status = 'succeeded'
+                AND status IN ('succeeded', 'partially_refunded', 'refunded', 'disputed')
                 AND created_at < $2
                 AND id NOT IN (
-                    SELECT UNNEST(transaction_ids) FROM payouts WHERE organization_id = $1
+                    SELECT UNNEST(transaction_ids) FROM payouts
+                    WHERE organization_id = $1
+                        AND status NOT IN ('failed', 'canceled')
                 )
             ORDER BY created_at ASC
             "#,
@@ -361,9 +705,10 @@ impl TransactionRepository {
     }
 }
 
-/// Transaction summary for dashboard
+/// Transaction summary for dashboard, in a single currency
 #[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
 pub struct TransactionSummary {
+    pub currency: Currency,
     pub transaction_count: i64,
     pub total_volume_cents: i64,
     pub total_fees_cents: i64,
@@ -389,3 +734,36 @@ impl TransactionSummary {
         (self.successful_count as f64 / self.transaction_count as f64) * 100.0
     }
 }
+
+/// Tips paid to a walker over a period, in a single currency
+#[derive(Debug, Clone, sqlx::FromRow, serde::Serialize)]
+pub struct WalkerTipSummary {
+    pub walker_id: UserId,
+    pub currency: Currency,
+    /// Walks paid for
+    pub walk_count: i64,
+    /// Walks tipped at checkout or afterwards
+    pub tipped_walk_count: i64,
+    pub tip_count: i64,
+    pub tip_cents: i64,
+    /// Tips paid after the walk, on their own
+    pub post_walk_tip_count: i64,
+    pub post_walk_tip_cents: i64,
+}
+
+impl WalkerTipSummary {
+    pub fn average_tip_cents(&self) -> i64 {
+        if self.tip_count == 0 {
+            return 0;
+        }
+        self.tip_cents / self.tip_count
+    }
+
+    /// Share of walks that were tipped, as a percentage
+    pub fn tip_rate_percent(&self) -> f64 {
+        if self.walk_count == 0 {
+            return 0.0;
+        }
+        (self.tipped_walk_count as f64 / self.walk_count as f64) * 100.0
+    }
+}
@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+use shared::types::{BookingId, OrganizationId};
+use sqlx::PgPool;
+
+use crate::models::{CreateWalkBreadcrumb, WalkBreadcrumb};
+
+pub struct WalkTrailRepository;
+
+impl WalkTrailRepository {
+    /// Record a breadcrumb for an in-progress booking
+    pub async fn record(
+        pool: &PgPool,
+        input: &CreateWalkBreadcrumb,
+    ) -> Result<WalkBreadcrumb, sqlx::Error> {
+        sqlx::query_as::<_, WalkBreadcrumb>(
+            r#"
+            INSERT INTO walk_breadcrumbs
+                (organization_id, booking_id, walker_id, latitude, longitude,
+                 accuracy_meters, speed_mps, recorded_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id, organization_id, booking_id, walker_id, latitude, longitude,
+                      accuracy_meters, speed_mps, recorded_at, created_at
+            "#,
+        )
+        .bind(input.organization_id.as_uuid())
+        .bind(input.booking_id.as_uuid())
+        .bind(input.walker_id.as_uuid())
+        .bind(input.latitude)
+        .bind(input.longitude)
+        .bind(input.accuracy_meters)
+        .bind(input.speed_mps)
+        .bind(input.recorded_at)
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Get a booking's breadcrumbs in time order
+    pub async fn list_for_booking(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        booking_id: BookingId,
+    ) -> Result<Vec<WalkBreadcrumb>, sqlx::Error> {
+        sqlx::query_as::<_, WalkBreadcrumb>(
+            r#"
+            SELECT id, organization_id, booking_id, walker_id, latitude, longitude,
+                   accuracy_meters, speed_mps, recorded_at, created_at
+            FROM walk_breadcrumbs
+            WHERE booking_id = $1 AND organization_id = $2
+            ORDER BY recorded_at
+            "#,
+        )
+        .bind(booking_id.as_uuid())
+        .bind(org_id.as_uuid())
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Delete an organization's breadcrumbs recorded before the cutoff,
+    /// returning the number removed. Trails of bookings with an open dispute
+    /// are kept as evidence until the dispute is resolved.
+    pub async fn delete_before(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        cutoff: DateTime<Utc>,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM walk_breadcrumbs
+            WHERE organization_id = $1
+                AND recorded_at < $2
+                AND NOT EXISTS (
+                    SELECT 1 FROM disputes d
+                    JOIN transactions t ON t.id = d.transaction_id
+                    WHERE t.booking_id = walk_breadcrumbs.booking_id
+                        AND d.organization_id = $1
+                        AND d.status IN ('needs_response', 'under_review')
+                )
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(cutoff)
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,615 @@
+use chrono::{DateTime, Utc};
+use shared::types::{Currency, OrganizationId, ServiceId, UserId};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::{
+    NewWalkerEarning, PayoutStatus, UnpaidWalkerEarnings, UpsertRevenueSplit, WalkerEarning,
+    WalkerEarningKind, WalkerEarningsSummary, WalkerPayout, WalkerPayoutAccount,
+    WalkerRevenueSplit,
+};
+
+pub struct WalkerEarningRepository;
+
+impl WalkerEarningRepository {
+    // ========== Revenue Splits ==========
+
+    /// List a walker's revenue splits, the default split first
+    pub async fn list_splits(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        walker_id: UserId,
+    ) -> Result<Vec<WalkerRevenueSplit>, sqlx::Error> {
+        sqlx::query_as::<_, WalkerRevenueSplit>(
+            r#"
+            SELECT * FROM walker_revenue_splits
+            WHERE organization_id = $1 AND walker_id = $2
+            ORDER BY service_id NULLS FIRST, created_at
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(walker_id.as_uuid())
+        .fetch_all(pool)
+        .await
+    }
+
+    /// The split for a walker's walks of a service, falling back to their
+    /// default split
+    pub async fn get_split(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        walker_id: UserId,
+        service_id: Option<ServiceId>,
+    ) -> Result<Option<WalkerRevenueSplit>, sqlx::Error> {
+        sqlx::query_as::<_, WalkerRevenueSplit>(
+            r#"
+            SELECT * FROM walker_revenue_splits
+            WHERE organization_id = $1
+                AND walker_id = $2
+                AND (service_id IS NULL OR service_id = $3)
+            ORDER BY service_id NULLS LAST
+            LIMIT 1
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(walker_id.as_uuid())
+        .bind(service_id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Set a walker's split for a service, or their default split
+    pub async fn upsert_split(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        walker_id: UserId,
+        input: UpsertRevenueSplit,
+    ) -> Result<WalkerRevenueSplit, sqlx::Error> {
+        sqlx::query_as::<_, WalkerRevenueSplit>(
+            r#"
+            INSERT INTO walker_revenue_splits (
+                organization_id, walker_id, service_id, split_type, percent, flat_cents
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (
+                organization_id,
+                walker_id,
+                COALESCE(service_id, '00000000-0000-0000-0000-000000000000'::uuid)
+            )
+            DO UPDATE SET
+                split_type = EXCLUDED.split_type,
+                percent = EXCLUDED.percent,
+                flat_cents = EXCLUDED.flat_cents,
+                updated_at = NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(walker_id.as_uuid())
+        .bind(input.service_id)
+        .bind(input.split_type)
+        .bind(input.percent)
+        .bind(input.flat_cents)
+        .fetch_one(pool)
+        .await
+    }
+
+    /// Delete one of a walker's splits
+    pub async fn delete_split(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        walker_id: UserId,
+        id: Uuid,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM walker_revenue_splits
+            WHERE id = $1 AND organization_id = $2 AND walker_id = $3
+            "#,
+        )
+        .bind(id)
+        .bind(org_id.as_uuid())
+        .bind(walker_id.as_uuid())
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // ========== Payout Accounts ==========
+
+    /// Get the Connect account a walker is paid through
+    pub async fn get_account(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        walker_id: UserId,
+    ) -> Result<Option<WalkerPayoutAccount>, sqlx::Error> {
+        sqlx::query_as::<_, WalkerPayoutAccount>(
+            r#"
+            SELECT * FROM walker_payout_accounts
+            WHERE organization_id = $1 AND walker_id = $2
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(walker_id.as_uuid())
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Update whether a walker's Connect account can be paid out to
+    pub async fn update_account_status(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        stripe_account_id: &str,
+        payouts_enabled: bool,
+    ) -> Result<Option<WalkerPayoutAccount>, sqlx::Error> {
+        sqlx::query_as::<_, WalkerPayoutAccount>(
+            r#"
+            UPDATE walker_payout_accounts
+            SET payouts_enabled = $2, updated_at = NOW()
+            WHERE stripe_account_id = $1 AND organization_id = $3
+            RETURNING *
+            "#,
+        )
+        .bind(stripe_account_id)
+        .bind(payouts_enabled)
+        .bind(org_id.as_uuid())
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Record the Connect account a walker is paid through
+    pub async fn upsert_account(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        walker_id: UserId,
+        stripe_account_id: &str,
+        payouts_enabled: bool,
+    ) -> Result<WalkerPayoutAccount, sqlx::Error> {
+        sqlx::query_as::<_, WalkerPayoutAccount>(
+            r#"
+            INSERT INTO walker_payout_accounts (
+                organization_id, walker_id, stripe_account_id, payouts_enabled
+            )
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (organization_id, walker_id)
+            DO UPDATE SET
+                stripe_account_id = EXCLUDED.stripe_account_id,
+                payouts_enabled = EXCLUDED.payouts_enabled,
+                updated_at = NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(walker_id.as_uuid())
+        .bind(stripe_account_id)
+        .bind(payouts_enabled)
+        .fetch_one(pool)
+        .await
+    }
+
+    // ========== Earnings ==========
+
+    /// Record an earning. Returns `None` if its source was already recorded.
+    pub async fn record(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        earning: &NewWalkerEarning,
+    ) -> Result<Option<WalkerEarning>, sqlx::Error> {
+        sqlx::query_as::<_, WalkerEarning>(
+            r#"
+            INSERT INTO walker_earnings (
+                organization_id, walker_id, transaction_id, source_id, kind, amount_cents,
+                currency
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (source_id, kind) DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(earning.walker_id.as_uuid())
+        .bind(earning.transaction_id)
+        .bind(earning.source_id)
+        .bind(earning.kind)
+        .bind(earning.amount_cents)
+        .bind(earning.currency)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// The earning of a kind recorded for a transaction, refund, dispute or
+    /// package credit
+    pub async fn get_by_source(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        source_id: Uuid,
+        kind: WalkerEarningKind,
+    ) -> Result<Option<WalkerEarning>, sqlx::Error> {
+        sqlx::query_as::<_, WalkerEarning>(
+            r#"
+            SELECT * FROM walker_earnings
+            WHERE organization_id = $1 AND source_id = $2 AND kind = $3
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(source_id)
+        .bind(kind)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// What the walker earned for the walk a transaction paid for, before
+    /// anything was taken back
+    pub async fn walk_earned_cents(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        transaction_id: Uuid,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT COALESCE(SUM(amount_cents), 0)::BIGINT
+            FROM walker_earnings
+            WHERE organization_id = $1 AND transaction_id = $2 AND kind = 'walk'
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(transaction_id)
+        .fetch_one(pool)
+        .await
+    }
+
+    /// A walker's earnings recorded in `[from, to)`, oldest first
+    pub async fn list_for_walker(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        walker_id: UserId,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<WalkerEarning>, sqlx::Error> {
+        sqlx::query_as::<_, WalkerEarning>(
+            r#"
+            SELECT * FROM walker_earnings
+            WHERE organization_id = $1
+                AND walker_id = $2
+                AND created_at >= $3
+                AND created_at < $4
+            ORDER BY created_at
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(walker_id.as_uuid())
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// A walker's earnings recorded in `[from, to)`, one summary per currency
+    pub async fn get_summary(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        walker_id: UserId,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<WalkerEarningsSummary>, sqlx::Error> {
+        sqlx::query_as::<_, WalkerEarningsSummary>(
+            r#"
+            SELECT
+                currency,
+                COALESCE(SUM(amount_cents) FILTER (WHERE kind = 'walk'), 0)::BIGINT as walk_cents,
+                COALESCE(SUM(amount_cents) FILTER (WHERE kind = 'tip'), 0)::BIGINT as tip_cents,
+                COALESCE(SUM(amount_cents) FILTER (WHERE kind IN ('refund', 'dispute')), 0)::BIGINT as reversed_cents,
+                COALESCE(SUM(amount_cents), 0)::BIGINT as earned_cents,
+                COALESCE(SUM(amount_cents) FILTER (WHERE walker_payout_id IS NULL), 0)::BIGINT as unpaid_cents,
+                COUNT(*) FILTER (WHERE kind = 'walk') as walk_count
+            FROM walker_earnings
+            WHERE organization_id = $1
+                AND walker_id = $2
+                AND created_at >= $3
+                AND created_at < $4
+            GROUP BY currency
+            ORDER BY currency
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(walker_id.as_uuid())
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Earnings recorded before `before` that no payout has covered, per
+    /// walker and currency. Only positive balances are returned; what a
+    /// refund took back after a payout is deducted from the next one.
+    pub async fn list_unpaid(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        walker_id: Option<UserId>,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<UnpaidWalkerEarnings>, sqlx::Error> {
+        sqlx::query_as::<_, UnpaidWalkerEarnings>(
+            r#"
+            SELECT
+                walker_id,
+                currency,
+                SUM(amount_cents)::BIGINT as amount_cents,
+                COUNT(*) as earning_count,
+                MIN(created_at) as period_start
+            FROM walker_earnings
+            WHERE organization_id = $1
+                AND ($2::uuid IS NULL OR walker_id = $2)
+                AND walker_payout_id IS NULL
+                AND created_at < $3
+            GROUP BY walker_id, currency
+            HAVING SUM(amount_cents) > 0
+            ORDER BY walker_id, currency
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(walker_id.map(|id| *id.as_uuid()))
+        .bind(before)
+        .fetch_all(pool)
+        .await
+    }
+
+    // ========== Walker Payouts ==========
+
+    /// Create a payout covering a walker's unpaid earnings in `currency`
+    /// recorded before `before`. Returns `None` if they come to nothing.
+    pub async fn create_payout(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        walker_id: UserId,
+        currency: Currency,
+        before: DateTime<Utc>,
+    ) -> Result<Option<WalkerPayout>, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+
+        let payout_id: Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO walker_payouts (
+                organization_id, walker_id, currency, period_start, period_end
+            )
+            VALUES ($1, $2, $3, $4, $4)
+            RETURNING id
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(walker_id.as_uuid())
+        .bind(currency)
+        .bind(before)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let claimed: Vec<(i32, DateTime<Utc>)> = sqlx::query_as(
+            r#"
+            UPDATE walker_earnings
+            SET walker_payout_id = $1
+            WHERE organization_id = $2
+                AND walker_id = $3
+                AND currency = $4
+                AND walker_payout_id IS NULL
+                AND created_at < $5
+            RETURNING amount_cents, created_at
+            "#,
+        )
+        .bind(payout_id)
+        .bind(org_id.as_uuid())
+        .bind(walker_id.as_uuid())
+        .bind(currency)
+        .bind(before)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let amount_cents: i64 = claimed.iter().map(|(amount, _)| i64::from(*amount)).sum();
+        let Some(period_start) = claimed.iter().map(|(_, created_at)| *created_at).min() else {
+            tx.rollback().await?;
+            return Ok(None);
+        };
+        if amount_cents <= 0 {
+            tx.rollback().await?;
+            return Ok(None);
+        }
+
+        let payout = sqlx::query_as::<_, WalkerPayout>(
+            r#"
+            UPDATE walker_payouts
+            SET amount_cents = $2, earning_count = $3, period_start = $4, updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(payout_id)
+        .bind(i32::try_from(amount_cents).unwrap_or(i32::MAX))
+        .bind(claimed.len() as i32)
+        .bind(period_start)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(Some(payout))
+    }
+
+    /// Get a walker payout by ID
+    pub async fn get_payout(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        id: Uuid,
+    ) -> Result<Option<WalkerPayout>, sqlx::Error> {
+        sqlx::query_as::<_, WalkerPayout>(
+            r#"
+            SELECT * FROM walker_payouts
+            WHERE id = $1 AND organization_id = $2
+            "#,
+        )
+        .bind(id)
+        .bind(org_id.as_uuid())
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Get a walker payout by the transfer that paid it
+    pub async fn get_payout_by_transfer(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        stripe_transfer_id: &str,
+    ) -> Result<Option<WalkerPayout>, sqlx::Error> {
+        sqlx::query_as::<_, WalkerPayout>(
+            r#"
+            SELECT * FROM walker_payouts
+            WHERE stripe_transfer_id = $1 AND organization_id = $2
+            "#,
+        )
+        .bind(stripe_transfer_id)
+        .bind(org_id.as_uuid())
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Earnings transferred to walkers that no live organization payout has
+    /// deducted yet
+    pub async fn list_transferred_undeducted(
+        pool: &PgPool,
+        org_id: OrganizationId,
+    ) -> Result<Vec<WalkerEarning>, sqlx::Error> {
+        sqlx::query_as::<_, WalkerEarning>(
+            r#"
+            SELECT e.* FROM walker_earnings e
+            JOIN walker_payouts p ON p.id = e.walker_payout_id
+            WHERE e.organization_id = $1
+                AND p.stripe_transfer_id IS NOT NULL
+                AND e.id NOT IN (
+                    SELECT UNNEST(walker_earning_ids) FROM payouts
+                    WHERE organization_id = $1
+                        AND status NOT IN ('failed', 'canceled')
+                )
+            ORDER BY e.created_at
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .fetch_all(pool)
+        .await
+    }
+
+    /// List walker payouts, newest first, for one walker or all of them
+    pub async fn list_payouts(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        walker_id: Option<UserId>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<WalkerPayout>, sqlx::Error> {
+        sqlx::query_as::<_, WalkerPayout>(
+            r#"
+            SELECT * FROM walker_payouts
+            WHERE organization_id = $1
+                AND ($2::uuid IS NULL OR walker_id = $2)
+            ORDER BY created_at DESC
+            LIMIT $3 OFFSET $4
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(walker_id.map(|id| *id.as_uuid()))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Walker payouts never sent to the walker
+    pub async fn list_uninitiated_payouts(
+        pool: &PgPool,
+        org_id: OrganizationId,
+    ) -> Result<Vec<WalkerPayout>, sqlx::Error> {
+        sqlx::query_as::<_, WalkerPayout>(
+            r#"
+            SELECT * FROM walker_payouts
+            WHERE organization_id = $1
+                AND status = 'pending'
+                AND initiated_at IS NULL
+            ORDER BY created_at
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Record the transfer that paid a walker payout
+    pub async fn mark_transferred(
+        pool: &PgPool,
+        id: Uuid,
+        stripe_transfer_id: &str,
+    ) -> Result<Option<WalkerPayout>, sqlx::Error> {
+        sqlx::query_as::<_, WalkerPayout>(
+            r#"
+            UPDATE walker_payouts
+            SET
+                stripe_transfer_id = $2,
+                status = 'paid',
+                initiated_at = NOW(),
+                completed_at = NOW(),
+                failure_message = NULL,
+                updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(stripe_transfer_id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Update a walker payout's status
+    pub async fn update_payout_status(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        id: Uuid,
+        status: PayoutStatus,
+        failure_message: Option<&str>,
+    ) -> Result<Option<WalkerPayout>, sqlx::Error> {
+        sqlx::query_as::<_, WalkerPayout>(
+            r#"
+            UPDATE walker_payouts
+            SET
+                status = $3,
+                failure_message = COALESCE($4, failure_message),
+                completed_at = CASE WHEN $3 = 'paid'::payout_status THEN NOW() ELSE completed_at END,
+                updated_at = NOW()
+            WHERE id = $1 AND organization_id = $2
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(org_id.as_uuid())
+        .bind(status)
+        .bind(failure_message)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Return a payout's earnings to the walker's unpaid balance, so the
+    /// next payout includes them again. Returns how many were released.
+    pub async fn release_earnings(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        payout_id: Uuid,
+    ) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE walker_earnings
+            SET walker_payout_id = NULL
+            WHERE walker_payout_id = $1 AND organization_id = $2
+            "#,
+        )
+        .bind(payout_id)
+        .bind(org_id.as_uuid())
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,231 @@
+use chrono::{DateTime, Utc};
+use shared::types::OrganizationId;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::{
+    CreateWebhookEvent, PaymentWebhookEvent, WebhookEventStatus, WEBHOOK_PROCESSING_TIMEOUT_MINUTES,
+};
+
+pub struct WebhookEventRepository;
+
+impl WebhookEventRepository {
+    /// Record a webhook event. Returns `None` if the provider already sent it.
+    pub async fn create(
+        pool: &PgPool,
+        input: CreateWebhookEvent,
+    ) -> Result<Option<PaymentWebhookEvent>, sqlx::Error> {
+        let id = Uuid::new_v4();
+
+        sqlx::query_as::<_, PaymentWebhookEvent>(
+            r#"
+            INSERT INTO payment_webhook_events (
+                id, organization_id, provider, event_id, event_type, payload
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (provider, event_id) DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(input.organization_id.as_uuid())
+        .bind(input.provider)
+        .bind(&input.event_id)
+        .bind(&input.event_type)
+        .bind(&input.payload)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Get a webhook event by ID
+    pub async fn get_by_id(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        id: Uuid,
+    ) -> Result<Option<PaymentWebhookEvent>, sqlx::Error> {
+        sqlx::query_as::<_, PaymentWebhookEvent>(
+            r#"
+            SELECT * FROM payment_webhook_events
+            WHERE id = $1 AND organization_id = $2
+            "#,
+        )
+        .bind(id)
+        .bind(org_id.as_uuid())
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// List webhook events for an organization, newest first
+    pub async fn list_for_org(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        status: Option<WebhookEventStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<PaymentWebhookEvent>, sqlx::Error> {
+        sqlx::query_as::<_, PaymentWebhookEvent>(
+            r#"
+            SELECT * FROM payment_webhook_events
+            WHERE organization_id = $1
+                AND ($2::webhook_event_status IS NULL OR status = $2)
+            ORDER BY created_at DESC
+            LIMIT $3 OFFSET $4
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(status)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Claim events due to be processed, counting an attempt for each and
+    /// giving each a new claim token. Events left processing longer than the
+    /// processing timeout are claimed again.
+    pub async fn claim_due(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        limit: i64,
+    ) -> Result<Vec<PaymentWebhookEvent>, sqlx::Error> {
+        sqlx::query_as::<_, PaymentWebhookEvent>(
+            r#"
+            UPDATE payment_webhook_events
+            SET
+                status = 'processing',
+                attempts = attempts + 1,
+                last_attempt_at = NOW(),
+                claimed_by = gen_random_uuid(),
+                updated_at = NOW()
+            WHERE id IN (
+                SELECT id FROM payment_webhook_events
+                WHERE organization_id = $1
+                    AND (
+                        (status IN ('pending', 'failed') AND next_attempt_at <= NOW())
+                        OR (
+                            status = 'processing'
+                            AND last_attempt_at < NOW() - make_interval(mins => $3)
+                        )
+                    )
+                ORDER BY next_attempt_at
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(org_id.as_uuid())
+        .bind(limit)
+        .bind(WEBHOOK_PROCESSING_TIMEOUT_MINUTES as i32)
+        .fetch_all(pool)
+        .await
+    }
+
+    /// Claim one pending event, counting an attempt. Returns `None` if it
+    /// isn't pending, such as when the worker already claimed it.
+    pub async fn claim(
+        pool: &PgPool,
+        id: Uuid,
+    ) -> Result<Option<PaymentWebhookEvent>, sqlx::Error> {
+        sqlx::query_as::<_, PaymentWebhookEvent>(
+            r#"
+            UPDATE payment_webhook_events
+            SET
+                status = 'processing',
+                attempts = attempts + 1,
+                last_attempt_at = NOW(),
+                claimed_by = gen_random_uuid(),
+                updated_at = NOW()
+            WHERE id = $1 AND status = 'pending'
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Mark an event processed by the attempt holding the `claimed_by` token.
+    /// Returns `None` if the event was claimed again since, leaving the
+    /// outcome to the attempt that holds it now.
+    pub async fn mark_processed(
+        pool: &PgPool,
+        id: Uuid,
+        claimed_by: Option<Uuid>,
+    ) -> Result<Option<PaymentWebhookEvent>, sqlx::Error> {
+        sqlx::query_as::<_, PaymentWebhookEvent>(
+            r#"
+            UPDATE payment_webhook_events
+            SET
+                status = 'processed',
+                processed = true,
+                processed_at = NOW(),
+                error_message = NULL,
+                updated_at = NOW()
+            WHERE id = $1 AND claimed_by = $2
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(claimed_by)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Record a failed attempt by the holder of the `claimed_by` token, to be
+    /// retried at `next_attempt_at` or dead-lettered. Returns `None` if the
+    /// event was claimed again since.
+    pub async fn mark_failed(
+        pool: &PgPool,
+        id: Uuid,
+        claimed_by: Option<Uuid>,
+        status: WebhookEventStatus,
+        next_attempt_at: DateTime<Utc>,
+        error_message: &str,
+    ) -> Result<Option<PaymentWebhookEvent>, sqlx::Error> {
+        sqlx::query_as::<_, PaymentWebhookEvent>(
+            r#"
+            UPDATE payment_webhook_events
+            SET
+                status = $3,
+                next_attempt_at = $4,
+                error_message = $5,
+                updated_at = NOW()
+            WHERE id = $1 AND claimed_by = $2
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(claimed_by)
+        .bind(status)
+        .bind(next_attempt_at)
+        .bind(error_message)
+        .fetch_optional(pool)
+        .await
+    }
+
+    /// Queue a failed or dead-lettered event to be processed again with a
+    /// fresh set of attempts
+    pub async fn replay(
+        pool: &PgPool,
+        org_id: OrganizationId,
+        id: Uuid,
+    ) -> Result<Option<PaymentWebhookEvent>, sqlx::Error> {
+        sqlx::query_as::<_, PaymentWebhookEvent>(
+            r#"
+            UPDATE payment_webhook_events
+            SET
+                status = 'pending',
+                attempts = 0,
+                next_attempt_at = NOW(),
+                updated_at = NOW()
+            WHERE id = $1 AND organization_id = $2 AND status IN ('failed', 'dead')
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(org_id.as_uuid())
+        .fetch_optional(pool)
+        .await
+    }
+}
@@ -0,0 +1,5 @@
+mod policy;
+
+pub use policy::{
+    BookingChange, CancellationFee, CancellationPolicy, CancellationTier, ChangeInitiator,
+};
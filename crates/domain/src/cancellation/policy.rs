@@ -0,0 +1,217 @@
+use chrono::{DateTime, Duration, Utc};
+
+/// What is being done to the booking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookingChange {
+    Cancel,
+    Reschedule,
+}
+
+/// Who is cancelling or rescheduling the booking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeInitiator {
+    Customer,
+    Walker,
+}
+
+/// Fee charged when a booking changes within `within_minutes` of its start
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CancellationTier {
+    pub within_minutes: i64,
+    /// Percentage of the booking price, 0-100
+    pub fee_percent: u32,
+}
+
+impl CancellationTier {
+    pub fn within_hours(hours: i64, fee_percent: u32) -> Self {
+        Self {
+            within_minutes: hours * 60,
+            fee_percent,
+        }
+    }
+}
+
+/// Late cancellation and reschedule fees for an organization or service.
+///
+/// The tier with the shortest window that the change falls into applies;
+/// changes made before every window are free. Changes after the booking has
+/// started fall into every window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CancellationPolicy {
+    pub tiers: Vec<CancellationTier>,
+    /// Cancellations by the walker never cost the customer anything
+    pub walker_cancels_free: bool,
+    /// Whether late reschedules are charged like cancellations
+    pub charge_reschedules: bool,
+}
+
+impl Default for CancellationPolicy {
+    fn default() -> Self {
+        Self::free()
+    }
+}
+
+impl CancellationPolicy {
+    /// No fees at any time
+    pub fn free() -> Self {
+        Self {
+            tiers: Vec::new(),
+            walker_cancels_free: true,
+            charge_reschedules: false,
+        }
+    }
+
+    /// Free more than 24 hours ahead, 50% within 24 hours, 100% within 2 hours
+    pub fn standard() -> Self {
+        Self {
+            tiers: vec![
+                CancellationTier::within_hours(24, 50),
+                CancellationTier::within_hours(2, 100),
+            ],
+            walker_cancels_free: true,
+            charge_reschedules: true,
+        }
+    }
+
+    /// Check the tiers are usable, returning a message describing the problem
+    pub fn validate(&self) -> Result<(), String> {
+        for tier in &self.tiers {
+            if tier.within_minutes <= 0 {
+                return Err("Tier windows must be positive".to_string());
+            }
+            if tier.fee_percent > 100 {
+                return Err("Tier fees cannot exceed 100%".to_string());
+            }
+        }
+
+        let mut windows: Vec<i64> = self.tiers.iter().map(|t| t.within_minutes).collect();
+        windows.sort_unstable();
+        windows.dedup();
+        if windows.len() != self.tiers.len() {
+            return Err("Tier windows must be unique".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Fee for changing a booking priced at `price_cents` that starts at
+    /// `scheduled_start`, when the change is made at `changed_at`
+    pub fn evaluate(
+        &self,
+        price_cents: i64,
+        scheduled_start: DateTime<Utc>,
+        changed_at: DateTime<Utc>,
+        change: BookingChange,
+        initiator: ChangeInitiator,
+    ) -> CancellationFee {
+        let exempt = (initiator == ChangeInitiator::Walker && self.walker_cancels_free)
+            || (change == BookingChange::Reschedule && !self.charge_reschedules);
+        if exempt {
+            return CancellationFee::none();
+        }
+
+        let notice = scheduled_start - changed_at;
+        let tier = self
+            .tiers
+            .iter()
+            .filter(|tier| notice <= Duration::minutes(tier.within_minutes))
+            .min_by_key(|tier| tier.within_minutes);
+
+        match tier {
+            Some(tier) => CancellationFee {
+                fee_percent: tier.fee_percent,
+                fee_cents: (price_cents * i64::from(tier.fee_percent) + 50) / 100,
+            },
+            None => CancellationFee::none(),
+        }
+    }
+}
+
+/// Fee owed for a cancellation or reschedule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CancellationFee {
+    pub fee_percent: u32,
+    pub fee_cents: i64,
+}
+
+impl CancellationFee {
+    pub fn none() -> Self {
+        Self {
+            fee_percent: 0,
+            fee_cents: 0,
+        }
+    }
+
+    pub fn is_free(&self) -> bool {
+        self.fee_cents == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn start() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 1, 10, 0, 0).unwrap()
+    }
+
+    fn customer_cancel(policy: &CancellationPolicy, hours_before: i64) -> CancellationFee {
+        policy.evaluate(
+            4000,
+            start(),
+            start() - Duration::hours(hours_before),
+            BookingChange::Cancel,
+            ChangeInitiator::Customer,
+        )
+    }
+
+    #[test]
+    fn test_standard_policy_tiers() {
+        let policy = CancellationPolicy::standard();
+
+        assert!(customer_cancel(&policy, 48).is_free());
+        assert_eq!(customer_cancel(&policy, 24).fee_cents, 2000);
+        assert_eq!(customer_cancel(&policy, 12).fee_cents, 2000);
+        assert_eq!(customer_cancel(&policy, 2).fee_cents, 4000);
+        // After the walk should have started
+        assert_eq!(customer_cancel(&policy, -1).fee_percent, 100);
+    }
+
+    #[test]
+    fn test_walker_and_reschedule_exemptions() {
+        let mut policy = CancellationPolicy::standard();
+        let changed_at = start() - Duration::hours(1);
+
+        let walker = policy.evaluate(
+            4000,
+            start(),
+            changed_at,
+            BookingChange::Cancel,
+            ChangeInitiator::Walker,
+        );
+        assert!(walker.is_free());
+
+        policy.charge_reschedules = false;
+        let reschedule = policy.evaluate(
+            4000,
+            start(),
+            changed_at,
+            BookingChange::Reschedule,
+            ChangeInitiator::Customer,
+        );
+        assert!(reschedule.is_free());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(CancellationPolicy::standard().validate().is_ok());
+
+        let mut policy = CancellationPolicy::standard();
+        policy.tiers.push(CancellationTier::within_hours(24, 75));
+        assert!(policy.validate().is_err());
+
+        policy.tiers = vec![CancellationTier::within_hours(1, 150)];
+        assert!(policy.validate().is_err());
+    }
+}
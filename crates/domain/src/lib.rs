@@ -1,3 +1,5 @@
 pub mod availability;
+pub mod cancellation;
 
 pub use availability::*;
+pub use cancellation::*;
@@ -0,0 +1,556 @@
+//! In-memory [`PaymentGateway`] for tests.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use super::{
+    AuthorizeRequest, CaptureMethod, DisputeEvidenceRequest, GatewayError, GatewayPayment,
+    GatewayPaymentRecord, GatewayPayout, GatewayRefund, GatewayResult, GatewayTransfer,
+    PaymentGateway, PaymentState, PayoutRequest, RefundRequest, TransferRequest,
+};
+
+/// Payment method that is always declined
+pub const DECLINED_PAYMENT_METHOD: &str = "pm_card_declined";
+
+/// Payment gateway that keeps payments in memory.
+///
+/// Follows the same rules as the real providers: only authorized payments
+/// can be captured or cancelled, refunds cannot exceed the captured amount,
+/// repeating a request with the same idempotency key returns the original
+/// result, and reusing a key with different parameters is rejected. Payments without a payment method wait in
+/// `RequiresAction` until [`FakeGateway::confirm_client_side`] is called.
+#[derive(Default)]
+pub struct FakeGateway {
+    state: Mutex<FakeState>,
+}
+
+#[derive(Default)]
+struct FakeState {
+    payments: HashMap<String, GatewayPayment>,
+    authorize_requests: Vec<AuthorizeRequest>,
+    refunded: HashMap<String, i64>,
+    refunds: Vec<GatewayRefund>,
+    payouts: Vec<GatewayPayout>,
+    transfers: Vec<GatewayTransfer>,
+    dispute_evidence: Vec<DisputeEvidenceRequest>,
+    idempotent_payments: HashMap<String, (AuthorizeRequest, String)>,
+    idempotent_refunds: HashMap<String, usize>,
+    idempotent_payouts: HashMap<String, usize>,
+    idempotent_transfers: HashMap<String, usize>,
+    capture_methods: HashMap<String, CaptureMethod>,
+    processing_fees: HashMap<String, i64>,
+    created: HashMap<String, DateTime<Utc>>,
+    next_id: u64,
+}
+
+impl FakeState {
+    fn next_id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{}_fake_{}", prefix, self.next_id)
+    }
+
+    fn payment_mut(&mut self, payment_id: &str) -> GatewayResult<&mut GatewayPayment> {
+        self.payments
+            .get_mut(payment_id)
+            .ok_or_else(|| GatewayError::NotFound(payment_id.to_string()))
+    }
+}
+
+impl FakeGateway {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Look up a payment by ID
+    pub fn payment(&self, payment_id: &str) -> Option<GatewayPayment> {
+        self.state().payments.get(payment_id).cloned()
+    }
+
+    /// Every authorization requested, in order, including retries and
+    /// declines
+    pub fn authorize_requests(&self) -> Vec<AuthorizeRequest> {
+        self.state().authorize_requests.clone()
+    }
+
+    /// All refunds issued, in order
+    pub fn refunds(&self) -> Vec<GatewayRefund> {
+        self.state().refunds.clone()
+    }
+
+    /// All payouts created, in order
+    pub fn payouts(&self) -> Vec<GatewayPayout> {
+        self.state().payouts.clone()
+    }
+
+    /// All transfers made, in order
+    pub fn transfers(&self) -> Vec<GatewayTransfer> {
+        self.state().transfers.clone()
+    }
+
+    /// All dispute evidence submitted, in order
+    pub fn dispute_evidence(&self) -> Vec<DisputeEvidenceRequest> {
+        self.state().dispute_evidence.clone()
+    }
+
+    /// Simulate the provider settling a payment and reporting its fee
+    pub fn set_processing_fee(&self, payment_id: &str, fee_cents: i64) {
+        self.state()
+            .processing_fees
+            .insert(payment_id.to_string(), fee_cents);
+    }
+
+    /// Simulate the customer confirming a payment in the browser
+    pub fn confirm_client_side(&self, payment_id: &str) -> GatewayResult<GatewayPayment> {
+        let mut state = self.state();
+        let capture_method = state
+            .capture_methods
+            .get(payment_id)
+            .copied()
+            .unwrap_or_default();
+        let payment = state.payment_mut(payment_id)?;
+        if payment.state != PaymentState::RequiresAction {
+            return Err(invalid_state(payment));
+        }
+        settle(payment, capture_method);
+        Ok(payment.clone())
+    }
+
+    fn state(&self) -> MutexGuard<'_, FakeState> {
+        // A panicking test thread shouldn't poison other tests' assertions
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl PaymentGateway for FakeGateway {
+    fn provider_name(&self) -> &'static str {
+        "fake"
+    }
+
+    async fn authorize(&self, request: &AuthorizeRequest) -> GatewayResult<GatewayPayment> {
+        self.state().authorize_requests.push(request.clone());
+        if request.amount_cents <= 0 {
+            return Err(GatewayError::InvalidRequest(
+                "Amount must be positive".to_string(),
+            ));
+        }
+        if request.payment_method.as_deref() == Some(DECLINED_PAYMENT_METHOD) {
+            return Err(GatewayError::Declined("card_declined".to_string()));
+        }
+
+        let mut state = self.state();
+        if let Some((original, existing)) = state.idempotent_payments.get(&request.idempotency_key)
+        {
+            if original != request {
+                return Err(GatewayError::InvalidRequest(format!(
+                    "Idempotency key {} was used with different parameters",
+                    request.idempotency_key
+                )));
+            }
+            return Ok(state.payments[existing].clone());
+        }
+
+        let id = state.next_id("pay");
+        let mut payment = GatewayPayment {
+            id: id.clone(),
+            state: PaymentState::RequiresAction,
+            amount_cents: request.amount_cents,
+            captured_cents: 0,
+            currency: request.currency.to_string(),
+            client_secret: Some(format!("{}_secret_fake", id)),
+        };
+        if request.payment_method.is_some() {
+            settle(&mut payment, request.capture_method);
+        }
+
+        state.idempotent_payments.insert(
+            request.idempotency_key.clone(),
+            (request.clone(), id.clone()),
+        );
+        state
+            .capture_methods
+            .insert(id.clone(), request.capture_method);
+        state.created.insert(id.clone(), Utc::now());
+        state.payments.insert(id, payment.clone());
+        Ok(payment)
+    }
+
+    async fn capture(
+        &self,
+        payment_id: &str,
+        amount_cents: Option<i64>,
+    ) -> GatewayResult<GatewayPayment> {
+        let mut state = self.state();
+        let payment = state.payment_mut(payment_id)?;
+        if payment.state != PaymentState::Authorized {
+            return Err(invalid_state(payment));
+        }
+
+        let amount = amount_cents.unwrap_or(payment.amount_cents);
+        if amount <= 0 || amount > payment.amount_cents {
+            return Err(GatewayError::InvalidRequest(format!(
+                "Cannot capture {} of a {} authorization",
+                amount, payment.amount_cents
+            )));
+        }
+
+        payment.state = PaymentState::Succeeded;
+        payment.captured_cents = amount;
+        Ok(payment.clone())
+    }
+
+    async fn cancel(&self, payment_id: &str) -> GatewayResult<GatewayPayment> {
+        let mut state = self.state();
+        let payment = state.payment_mut(payment_id)?;
+        if !matches!(
+            payment.state,
+            PaymentState::Authorized | PaymentState::RequiresAction
+        ) {
+            return Err(invalid_state(payment));
+        }
+
+        payment.state = PaymentState::Canceled;
+        Ok(payment.clone())
+    }
+
+    async fn refund(&self, request: &RefundRequest) -> GatewayResult<GatewayRefund> {
+        let mut state = self.state();
+        if let Some(index) = state.idempotent_refunds.get(&request.idempotency_key) {
+            return Ok(state.refunds[*index].clone());
+        }
+
+        let payment = state.payment_mut(&request.payment_id)?;
+        if payment.state != PaymentState::Succeeded {
+            return Err(invalid_state(payment));
+        }
+        let captured = payment.captured_cents;
+
+        let refunded = state
+            .refunded
+            .get(&request.payment_id)
+            .copied()
+            .unwrap_or(0);
+        if request.amount_cents <= 0 || refunded + request.amount_cents > captured {
+            return Err(GatewayError::InvalidRequest(format!(
+                "Cannot refund {} of {} remaining",
+                request.amount_cents,
+                captured - refunded
+            )));
+        }
+
+        let refund = GatewayRefund {
+            id: state.next_id("re"),
+            payment_id: request.payment_id.clone(),
+            amount_cents: request.amount_cents,
+            status: "succeeded".to_string(),
+        };
+        state
+            .refunded
+            .insert(request.payment_id.clone(), refunded + request.amount_cents);
+        let index = state.refunds.len();
+        state.refunds.push(refund.clone());
+        state
+            .idempotent_refunds
+            .insert(request.idempotency_key.clone(), index);
+        Ok(refund)
+    }
+
+    async fn payment_status(&self, payment_id: &str) -> GatewayResult<GatewayPayment> {
+        let mut state = self.state();
+        Ok(state.payment_mut(payment_id)?.clone())
+    }
+
+    async fn processing_fee(&self, payment_id: &str) -> GatewayResult<Option<i64>> {
+        let mut state = self.state();
+        state.payment_mut(payment_id)?;
+        Ok(state.processing_fees.get(payment_id).copied())
+    }
+
+    async fn list_payments(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> GatewayResult<Vec<GatewayPaymentRecord>> {
+        let state = self.state();
+        let mut records: Vec<GatewayPaymentRecord> = state
+            .payments
+            .values()
+            .filter_map(|payment| {
+                let created_at = state.created.get(&payment.id).copied()?;
+                (from <= created_at && created_at < to).then(|| GatewayPaymentRecord {
+                    id: payment.id.clone(),
+                    state: payment.state,
+                    amount_cents: payment.amount_cents,
+                    captured_cents: payment.captured_cents,
+                    refunded_cents: state.refunded.get(&payment.id).copied().unwrap_or(0),
+                    currency: payment.currency.clone(),
+                    created_at,
+                })
+            })
+            .collect();
+        records.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+        Ok(records)
+    }
+
+    async fn payout(&self, request: &PayoutRequest) -> GatewayResult<GatewayPayout> {
+        if request.amount_cents <= 0 {
+            return Err(GatewayError::InvalidRequest(
+                "Amount must be positive".to_string(),
+            ));
+        }
+
+        let mut state = self.state();
+        if let Some(index) = state.idempotent_payouts.get(&request.idempotency_key) {
+            return Ok(state.payouts[*index].clone());
+        }
+
+        let payout = GatewayPayout {
+            id: state.next_id("po"),
+            amount_cents: request.amount_cents,
+            status: "pending".to_string(),
+        };
+        let index = state.payouts.len();
+        state.payouts.push(payout.clone());
+        state
+            .idempotent_payouts
+            .insert(request.idempotency_key.clone(), index);
+        Ok(payout)
+    }
+
+    async fn transfer(&self, request: &TransferRequest) -> GatewayResult<GatewayTransfer> {
+        if request.amount_cents <= 0 {
+            return Err(GatewayError::InvalidRequest(
+                "Amount must be positive".to_string(),
+            ));
+        }
+
+        let mut state = self.state();
+        if let Some(index) = state.idempotent_transfers.get(&request.idempotency_key) {
+            return Ok(state.transfers[*index].clone());
+        }
+
+        let transfer = GatewayTransfer {
+            id: state.next_id("tr"),
+            amount_cents: request.amount_cents,
+            destination: request.destination.clone(),
+        };
+        let index = state.transfers.len();
+        state.transfers.push(transfer.clone());
+        state
+            .idempotent_transfers
+            .insert(request.idempotency_key.clone(), index);
+        Ok(transfer)
+    }
+
+    async fn submit_dispute_evidence(&self, request: &DisputeEvidenceRequest) -> GatewayResult<()> {
+        let mut state = self.state();
+        if state
+            .dispute_evidence
+            .iter()
+            .any(|e| e.dispute_id == request.dispute_id)
+        {
+            return Err(GatewayError::InvalidRequest(format!(
+                "Evidence for dispute {} was already submitted",
+                request.dispute_id
+            )));
+        }
+
+        state.dispute_evidence.push(request.clone());
+        Ok(())
+    }
+}
+
+/// Move a confirmed payment to its post-confirmation state
+fn settle(payment: &mut GatewayPayment, capture_method: CaptureMethod) {
+    match capture_method {
+        CaptureMethod::Automatic => {
+            payment.state = PaymentState::Succeeded;
+            payment.captured_cents = payment.amount_cents;
+        }
+        CaptureMethod::Manual => payment.state = PaymentState::Authorized,
+    }
+}
+
+fn invalid_state(payment: &GatewayPayment) -> GatewayError {
+    GatewayError::InvalidRequest(format!("Payment {} is {:?}", payment.id, payment.state))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway::RefundReason;
+    use shared::types::Currency;
+
+    fn request(key: &str, capture_method: CaptureMethod) -> AuthorizeRequest {
+        AuthorizeRequest {
+            amount_cents: 5000,
+            currency: Currency::USD,
+            capture_method,
+            payment_method: Some("pm_card_visa".to_string()),
+            idempotency_key: key.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn refund(payment_id: &str, amount_cents: i64, key: &str) -> RefundRequest {
+        RefundRequest {
+            payment_id: payment_id.to_string(),
+            amount_cents,
+            currency: Currency::USD,
+            application_fee_cents: 0,
+            reason: RefundReason::RequestedByCustomer,
+            idempotency_key: key.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_authorize_capture_refund() {
+        let gateway = FakeGateway::new();
+        let payment = gateway
+            .authorize(&request("k1", CaptureMethod::Manual))
+            .await
+            .unwrap();
+        assert_eq!(payment.state, PaymentState::Authorized);
+
+        let captured = gateway.capture(&payment.id, Some(4000)).await.unwrap();
+        assert_eq!(captured.state, PaymentState::Succeeded);
+        assert_eq!(captured.captured_cents, 4000);
+
+        gateway
+            .refund(&refund(&payment.id, 3000, "r1"))
+            .await
+            .unwrap();
+        // Only 1000 of the captured 4000 remains
+        assert!(gateway
+            .refund(&refund(&payment.id, 1500, "r2"))
+            .await
+            .is_err());
+        assert_eq!(gateway.refunds().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_authorize() {
+        let gateway = FakeGateway::new();
+        let first = gateway
+            .authorize(&request("same", CaptureMethod::Automatic))
+            .await
+            .unwrap();
+        let second = gateway
+            .authorize(&request("same", CaptureMethod::Automatic))
+            .await
+            .unwrap();
+        assert_eq!(first.id, second.id);
+
+        // The same key can't be reused for a different payment
+        let mut changed = request("same", CaptureMethod::Automatic);
+        changed.amount_cents = 6000;
+        assert!(matches!(
+            gateway.authorize(&changed).await,
+            Err(GatewayError::InvalidRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_declined_and_invalid_transitions() {
+        let gateway = FakeGateway::new();
+        let mut declined = request("k1", CaptureMethod::Automatic);
+        declined.payment_method = Some(DECLINED_PAYMENT_METHOD.to_string());
+        assert!(matches!(
+            gateway.authorize(&declined).await,
+            Err(GatewayError::Declined(_))
+        ));
+
+        let payment = gateway
+            .authorize(&request("k2", CaptureMethod::Automatic))
+            .await
+            .unwrap();
+        assert!(gateway.capture(&payment.id, None).await.is_err());
+        assert!(gateway.cancel(&payment.id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_client_side_confirmation() {
+        let gateway = FakeGateway::new();
+        let mut pending = request("k1", CaptureMethod::Automatic);
+        pending.payment_method = None;
+
+        let payment = gateway.authorize(&pending).await.unwrap();
+        assert_eq!(payment.state, PaymentState::RequiresAction);
+        assert!(payment.client_secret.is_some());
+
+        gateway.confirm_client_side(&payment.id).unwrap();
+        let status = gateway.payment_status(&payment.id).await.unwrap();
+        assert_eq!(status.state, PaymentState::Succeeded);
+    }
+
+    #[tokio::test]
+    async fn test_list_payments_in_range() {
+        let gateway = FakeGateway::new();
+        let payment = gateway
+            .authorize(&request("k1", CaptureMethod::Automatic))
+            .await
+            .unwrap();
+        gateway
+            .refund(&refund(&payment.id, 1200, "r1"))
+            .await
+            .unwrap();
+
+        let now = Utc::now();
+        let listed = gateway
+            .list_payments(
+                now - chrono::Duration::hours(1),
+                now + chrono::Duration::hours(1),
+            )
+            .await
+            .unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].refunded_cents, 1200);
+
+        let earlier = gateway
+            .list_payments(
+                now - chrono::Duration::days(2),
+                now - chrono::Duration::days(1),
+            )
+            .await
+            .unwrap();
+        assert!(earlier.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_transfer() {
+        let gateway = FakeGateway::new();
+        let request = TransferRequest {
+            amount_cents: 2500,
+            currency: Currency::USD,
+            destination: "acct_walker".to_string(),
+            idempotency_key: "walker_payout_1".to_string(),
+            metadata: HashMap::new(),
+        };
+
+        let first = gateway.transfer(&request).await.unwrap();
+        let second = gateway.transfer(&request).await.unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first.destination, "acct_walker");
+        assert_eq!(gateway.transfers().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_dispute_evidence_submitted_once() {
+        let gateway = FakeGateway::new();
+        let request = DisputeEvidenceRequest {
+            dispute_id: "dp_1".to_string(),
+            service_date: Some("2024-03-04".to_string()),
+            ..Default::default()
+        };
+
+        gateway.submit_dispute_evidence(&request).await.unwrap();
+        assert!(matches!(
+            gateway.submit_dispute_evidence(&request).await,
+            Err(GatewayError::InvalidRequest(_))
+        ));
+        assert_eq!(gateway.dispute_evidence(), vec![request]);
+    }
+}
@@ -0,0 +1,315 @@
+//! Provider-agnostic payment operations.
+//!
+//! [`PaymentGateway`] covers the payment lifecycle the platform needs:
+//! authorizing a charge (optionally for later capture), capturing or
+//! cancelling it, refunding, checking its status and processing fee, listing
+//! payments for reconciliation, paying out a connected account,
+//! transferring to another one, and answering disputes with evidence. Stripe and Square implementations wrap the
+//! typed clients in this crate; [`FakeGateway`] keeps everything in memory for tests.
+
+mod fake;
+mod square;
+mod stripe;
+
+pub use fake::{FakeGateway, DECLINED_PAYMENT_METHOD};
+pub use square::SquareGateway;
+pub use stripe::StripeGateway;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use shared::types::Currency;
+use std::collections::HashMap;
+use thiserror::Error;
+
+pub type GatewayResult<T> = Result<T, GatewayError>;
+
+/// Error from a payment gateway
+#[derive(Debug, Error)]
+pub enum GatewayError {
+    #[error("Payment declined: {0}")]
+    Declined(String),
+
+    #[error("Invalid payment request: {0}")]
+    InvalidRequest(String),
+
+    #[error("Payment not found: {0}")]
+    NotFound(String),
+
+    #[error("{0} is not supported by this payment provider")]
+    NotSupported(&'static str),
+
+    #[error("Payment provider error: {message}")]
+    Provider { message: String, retryable: bool },
+}
+
+impl GatewayError {
+    /// Check if the operation can be retried with the same idempotency key
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            GatewayError::Provider {
+                retryable: true,
+                ..
+            }
+        )
+    }
+}
+
+/// When an authorized payment is captured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaptureMethod {
+    /// Funds are captured as soon as the payment is authorized
+    #[default]
+    Automatic,
+    /// Funds are held until [`PaymentGateway::capture`] is called
+    Manual,
+}
+
+/// Request to authorize (and possibly capture) a payment
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuthorizeRequest {
+    /// Amount to charge in the currency's minor unit
+    pub amount_cents: i64,
+    pub currency: Currency,
+    pub capture_method: CaptureMethod,
+    /// Saved payment method or card token. Without one, Stripe returns a
+    /// client secret for the customer to confirm the payment in the browser.
+    pub payment_method: Option<String>,
+    /// Provider customer the payment method belongs to
+    pub customer: Option<String>,
+    /// The customer isn't present to complete authentication (e.g. a
+    /// re-authorization or a charge after the walk)
+    pub off_session: bool,
+    /// Platform's cut of the payment, when paying a connected account
+    pub application_fee_cents: Option<i64>,
+    /// Reused on retries so the provider never creates a second charge
+    pub idempotency_key: String,
+    /// Our reference for the payment (e.g. the transaction ID)
+    pub reference: Option<String>,
+    pub description: Option<String>,
+    pub metadata: HashMap<String, String>,
+}
+
+/// Lifecycle state of a payment at the provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentState {
+    /// Waiting for the customer to supply or confirm a payment method
+    RequiresAction,
+    /// Being processed by the provider
+    Processing,
+    /// Funds are held and waiting to be captured
+    Authorized,
+    /// Funds have been captured
+    Succeeded,
+    /// The authorization was cancelled or expired before capture
+    Canceled,
+    Failed,
+}
+
+impl PaymentState {
+    /// Whether the payment can no longer change state (refunds aside)
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            PaymentState::Succeeded | PaymentState::Canceled | PaymentState::Failed
+        )
+    }
+}
+
+/// A payment as reported by the provider
+#[derive(Debug, Clone, PartialEq)]
+pub struct GatewayPayment {
+    /// Provider payment ID (Stripe PaymentIntent or Square Payment)
+    pub id: String,
+    pub state: PaymentState,
+    pub amount_cents: i64,
+    pub captured_cents: i64,
+    pub currency: String,
+    /// Secret the browser uses to confirm a Stripe payment
+    pub client_secret: Option<String>,
+}
+
+/// A payment as listed by the provider for reconciliation
+#[derive(Debug, Clone, PartialEq)]
+pub struct GatewayPaymentRecord {
+    /// Provider payment ID, matching [`GatewayPayment::id`]
+    pub id: String,
+    pub state: PaymentState,
+    pub amount_cents: i64,
+    pub captured_cents: i64,
+    pub refunded_cents: i64,
+    pub currency: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Why a payment is being refunded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefundReason {
+    Duplicate,
+    Fraudulent,
+    RequestedByCustomer,
+}
+
+impl RefundReason {
+    /// Map a free-form reason to the closest provider reason
+    pub fn from_text(reason: &str) -> Self {
+        match reason.to_lowercase().as_str() {
+            "duplicate" => RefundReason::Duplicate,
+            "fraudulent" => RefundReason::Fraudulent,
+            _ => RefundReason::RequestedByCustomer,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RefundReason::Duplicate => "duplicate",
+            RefundReason::Fraudulent => "fraudulent",
+            RefundReason::RequestedByCustomer => "requested_by_customer",
+        }
+    }
+}
+
+/// Request to refund some or all of a captured payment
+#[derive(Debug, Clone)]
+pub struct RefundRequest {
+    pub payment_id: String,
+    pub amount_cents: i64,
+    pub currency: Currency,
+    /// Part of the platform's application fee given back with the refund
+    pub application_fee_cents: i64,
+    pub reason: RefundReason,
+    pub idempotency_key: String,
+}
+
+/// A refund as reported by the provider
+#[derive(Debug, Clone, PartialEq)]
+pub struct GatewayRefund {
+    pub id: String,
+    pub payment_id: String,
+    pub amount_cents: i64,
+    /// Provider status, e.g. "succeeded" or "PENDING"
+    pub status: String,
+}
+
+/// How fast a payout reaches the bank
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PayoutSpeed {
+    #[default]
+    Standard,
+    Instant,
+}
+
+/// Request to pay out a connected account's balance
+#[derive(Debug, Clone)]
+pub struct PayoutRequest {
+    pub amount_cents: i64,
+    pub currency: Currency,
+    pub speed: PayoutSpeed,
+    pub idempotency_key: String,
+}
+
+/// A payout as reported by the provider
+#[derive(Debug, Clone, PartialEq)]
+pub struct GatewayPayout {
+    pub id: String,
+    pub amount_cents: i64,
+    /// Provider status, e.g. "pending" or "in_transit"
+    pub status: String,
+}
+
+/// Request to move funds from the platform balance to a connected account,
+/// e.g. a walker's
+#[derive(Debug, Clone)]
+pub struct TransferRequest {
+    pub amount_cents: i64,
+    pub currency: Currency,
+    /// Connected account receiving the funds
+    pub destination: String,
+    pub idempotency_key: String,
+    pub metadata: HashMap<String, String>,
+}
+
+/// A transfer as reported by the provider
+#[derive(Debug, Clone, PartialEq)]
+pub struct GatewayTransfer {
+    pub id: String,
+    pub amount_cents: i64,
+    pub destination: String,
+}
+
+/// Evidence answering a dispute. Each field is plain text; the gateway maps
+/// them onto the provider's evidence fields.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DisputeEvidenceRequest {
+    /// Provider's dispute ID
+    pub dispute_id: String,
+    pub customer_name: Option<String>,
+    pub customer_email: Option<String>,
+    pub product_description: Option<String>,
+    pub service_date: Option<String>,
+    pub service_documentation: Option<String>,
+    pub customer_communication: Option<String>,
+    pub receipt: Option<String>,
+    pub cancellation_policy: Option<String>,
+    pub additional_documentation: Vec<String>,
+}
+
+/// Payment operations for one merchant account
+#[async_trait]
+pub trait PaymentGateway: Send + Sync {
+    /// Short provider name, e.g. "stripe"
+    fn provider_name(&self) -> &'static str;
+
+    /// How long an uncaptured authorization is held before the provider
+    /// releases it. Both Stripe and Square hold online card payments for 7 days.
+    fn authorization_validity(&self) -> Duration {
+        Duration::days(7)
+    }
+
+    /// Create a payment, capturing it immediately or holding the funds
+    /// depending on [`AuthorizeRequest::capture_method`]
+    async fn authorize(&self, request: &AuthorizeRequest) -> GatewayResult<GatewayPayment>;
+
+    /// Capture a held payment, optionally for less than the authorized amount
+    async fn capture(
+        &self,
+        payment_id: &str,
+        amount_cents: Option<i64>,
+    ) -> GatewayResult<GatewayPayment>;
+
+    /// Release a held payment without capturing it
+    async fn cancel(&self, payment_id: &str) -> GatewayResult<GatewayPayment>;
+
+    /// Refund part or all of a captured payment
+    async fn refund(&self, request: &RefundRequest) -> GatewayResult<GatewayRefund>;
+
+    /// Fetch the current state of a payment
+    async fn payment_status(&self, payment_id: &str) -> GatewayResult<GatewayPayment>;
+
+    /// Fee the provider charged to process a captured payment, in the
+    /// currency's minor unit, or `None` until the provider has settled it
+    async fn processing_fee(&self, payment_id: &str) -> GatewayResult<Option<i64>>;
+
+    /// List the merchant's payments created in `[from, to)`
+    async fn list_payments(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> GatewayResult<Vec<GatewayPaymentRecord>>;
+
+    /// Pay out the merchant's available balance to their bank
+    async fn payout(&self, request: &PayoutRequest) -> GatewayResult<GatewayPayout>;
+
+    /// Transfer part of the platform balance to a connected account
+    async fn transfer(&self, _request: &TransferRequest) -> GatewayResult<GatewayTransfer> {
+        Err(GatewayError::NotSupported("Transfers"))
+    }
+
+    /// Submit evidence for a dispute. Providers only accept one submission.
+    async fn submit_dispute_evidence(
+        &self,
+        _request: &DisputeEvidenceRequest,
+    ) -> GatewayResult<()> {
+        Err(GatewayError::NotSupported("Dispute evidence"))
+    }
+}
@@ -0,0 +1,371 @@
+//! Square implementation of [`PaymentGateway`] using the Payments API.
+
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use super::{
+    AuthorizeRequest, CaptureMethod, DisputeEvidenceRequest, GatewayError, GatewayPayment,
+    GatewayPaymentRecord, GatewayPayout, GatewayRefund, GatewayResult, PaymentGateway,
+    PaymentState, PayoutRequest, RefundRequest,
+};
+use crate::square::{CreatePaymentRequest, Money, Payment, SquareClient, SquareError};
+
+/// Square gateway for a merchant connected via OAuth
+pub struct SquareGateway {
+    client: SquareClient,
+    location_id: Option<String>,
+}
+
+impl SquareGateway {
+    pub fn new(client: SquareClient, location_id: Option<String>) -> Self {
+        Self {
+            client,
+            location_id,
+        }
+    }
+}
+
+#[async_trait]
+impl PaymentGateway for SquareGateway {
+    fn provider_name(&self) -> &'static str {
+        "square"
+    }
+
+    async fn authorize(&self, request: &AuthorizeRequest) -> GatewayResult<GatewayPayment> {
+        // Square has no client-side confirmation step; a card nonce or saved
+        // card ID is always required
+        let source_id = request.payment_method.clone().ok_or_else(|| {
+            GatewayError::InvalidRequest("Payment method required for Square".to_string())
+        })?;
+
+        let mut payment = CreatePaymentRequest::new(source_id, request.amount_cents)
+            .with_idempotency_key(request.idempotency_key.clone());
+
+        if let Some(fee) = request.application_fee_cents.filter(|fee| *fee > 0) {
+            payment = payment.with_app_fee(fee);
+        }
+        payment = payment.with_currency(request.currency.code());
+        if let Some(customer) = &request.customer {
+            payment = payment.with_customer(customer.clone());
+        }
+        if let Some(location) = &self.location_id {
+            payment = payment.with_location(location.clone());
+        }
+        if let Some(reference) = &request.reference {
+            payment = payment.with_reference(reference.clone());
+        }
+        if let Some(description) = &request.description {
+            payment = payment.with_note(description.clone());
+        }
+        if request.capture_method == CaptureMethod::Manual {
+            payment = payment.delayed_capture();
+        }
+
+        let payment = self.client.create_payment(payment).await?;
+        Ok(payment.into())
+    }
+
+    async fn capture(
+        &self,
+        payment_id: &str,
+        amount_cents: Option<i64>,
+    ) -> GatewayResult<GatewayPayment> {
+        // Square can only complete a payment for the authorized amount
+        if amount_cents.is_some() {
+            let payment = self.client.get_payment(payment_id).await?;
+            if amount_cents != Some(payment.amount_money.amount) {
+                return Err(GatewayError::NotSupported("Partial capture"));
+            }
+        }
+
+        let payment = self.client.complete_payment(payment_id).await?;
+        Ok(payment.into())
+    }
+
+    async fn cancel(&self, payment_id: &str) -> GatewayResult<GatewayPayment> {
+        let payment = self.client.cancel_payment(payment_id).await?;
+        Ok(payment.into())
+    }
+
+    async fn refund(&self, request: &RefundRequest) -> GatewayResult<GatewayRefund> {
+        let refund = self
+            .client
+            .refund_payment(
+                &request.payment_id,
+                Money::new(request.amount_cents, request.currency.code()),
+                (request.application_fee_cents > 0)
+                    .then(|| Money::new(request.application_fee_cents, request.currency.code())),
+                Some(request.reason.as_str()),
+                Some(&request.idempotency_key),
+            )
+            .await?;
+
+        Ok(GatewayRefund {
+            id: refund.id,
+            payment_id: refund.payment_id,
+            amount_cents: refund.amount_money.amount,
+            status: refund.status,
+        })
+    }
+
+    async fn payment_status(&self, payment_id: &str) -> GatewayResult<GatewayPayment> {
+        let payment = self.client.get_payment(payment_id).await?;
+        Ok(payment.into())
+    }
+
+    async fn processing_fee(&self, payment_id: &str) -> GatewayResult<Option<i64>> {
+        let payment = self.client.get_payment(payment_id).await?;
+        Ok(total_processing_fee(&payment))
+    }
+
+    async fn list_payments(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> GatewayResult<Vec<GatewayPaymentRecord>> {
+        let begin_time = from.to_rfc3339_opts(SecondsFormat::Secs, true);
+        let end_time = to.to_rfc3339_opts(SecondsFormat::Secs, true);
+
+        let mut records = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let (payments, next) = self
+                .client
+                .list_payments(
+                    &begin_time,
+                    &end_time,
+                    self.location_id.as_deref(),
+                    cursor.as_deref(),
+                )
+                .await?;
+            records.extend(payments.into_iter().map(GatewayPaymentRecord::from));
+
+            match next {
+                Some(next) => cursor = Some(next),
+                None => return Ok(records),
+            }
+        }
+    }
+
+    async fn payout(&self, _request: &PayoutRequest) -> GatewayResult<GatewayPayout> {
+        // Square settles to the seller's bank account on its own schedule
+        Err(GatewayError::NotSupported("On-demand payout"))
+    }
+
+    async fn submit_dispute_evidence(&self, request: &DisputeEvidenceRequest) -> GatewayResult<()> {
+        // Each piece is keyed by dispute and type, so a submission that
+        // failed partway can be retried without duplicating evidence
+        for (evidence_type, text) in square_evidence(request) {
+            self.client
+                .create_dispute_evidence_text(
+                    &request.dispute_id,
+                    evidence_type,
+                    &text,
+                    &format!("{}_{}", request.dispute_id, evidence_type),
+                )
+                .await?;
+        }
+        self.client
+            .submit_dispute_evidence(&request.dispute_id)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Map evidence onto Square's evidence types
+fn square_evidence(request: &DisputeEvidenceRequest) -> Vec<(&'static str, String)> {
+    let cardholder = [&request.customer_name, &request.customer_email]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect::<Vec<_>>();
+    let service = [&request.service_date, &request.service_documentation]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    [
+        ("CARDHOLDER_INFORMATION", Some(cardholder.join("\n"))),
+        (
+            "PRODUCT_OR_SERVICE_DESCRIPTION",
+            request.product_description.clone(),
+        ),
+        ("SERVICE_RECEIVED_DOCUMENTATION", Some(service.join("\n"))),
+        (
+            "CARDHOLDER_COMMUNICATION_DOCUMENTATION",
+            request.customer_communication.clone(),
+        ),
+        ("RECEIPT", request.receipt.clone()),
+        (
+            "CANCELLATION_OR_REFUND_DOCUMENTATION",
+            request.cancellation_policy.clone(),
+        ),
+        (
+            "GENERIC_EVIDENCE",
+            Some(request.additional_documentation.join("\n")),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(evidence_type, text)| text.filter(|t| !t.is_empty()).map(|t| (evidence_type, t)))
+    .collect()
+}
+
+/// Square's net fee for a payment. Adjustments (e.g. on refunds) are
+/// reported as further, negative fees.
+fn total_processing_fee(payment: &Payment) -> Option<i64> {
+    if payment.processing_fee.is_empty() {
+        return None;
+    }
+    Some(
+        payment
+            .processing_fee
+            .iter()
+            .map(|fee| fee.amount_money.amount)
+            .sum(),
+    )
+}
+
+impl From<Payment> for GatewayPayment {
+    fn from(payment: Payment) -> Self {
+        let state = match payment.status.as_str() {
+            "APPROVED" => PaymentState::Authorized,
+            "PENDING" => PaymentState::Processing,
+            "COMPLETED" => PaymentState::Succeeded,
+            "CANCELED" => PaymentState::Canceled,
+            _ => PaymentState::Failed,
+        };
+        let amount = payment.total_money.unwrap_or(payment.amount_money);
+
+        GatewayPayment {
+            id: payment.id,
+            state,
+            amount_cents: amount.amount,
+            captured_cents: if state == PaymentState::Succeeded {
+                amount.amount
+            } else {
+                0
+            },
+            currency: amount.currency,
+            client_secret: None,
+        }
+    }
+}
+
+impl From<Payment> for GatewayPaymentRecord {
+    fn from(payment: Payment) -> Self {
+        let refunded_cents = payment
+            .refunded_money
+            .as_ref()
+            .map(|money| money.amount)
+            .unwrap_or(0);
+        let created_at = DateTime::parse_from_rfc3339(&payment.created_at)
+            .map(|at| at.with_timezone(&Utc))
+            .unwrap_or_default();
+        let payment = GatewayPayment::from(payment);
+
+        GatewayPaymentRecord {
+            id: payment.id,
+            state: payment.state,
+            amount_cents: payment.amount_cents,
+            captured_cents: payment.captured_cents,
+            refunded_cents,
+            currency: payment.currency,
+            created_at,
+        }
+    }
+}
+
+impl From<SquareError> for GatewayError {
+    fn from(error: SquareError) -> Self {
+        match error {
+            SquareError::CardDeclined(code) => GatewayError::Declined(code),
+            SquareError::ApiError { category, code, .. } if category == "PAYMENT_METHOD_ERROR" => {
+                GatewayError::Declined(code)
+            }
+            SquareError::ApiError { code, .. } if code == "NOT_FOUND" => {
+                GatewayError::NotFound(code)
+            }
+            SquareError::InvalidRequest(message) | SquareError::MissingField(message) => {
+                GatewayError::InvalidRequest(message)
+            }
+            error => GatewayError::Provider {
+                retryable: error.is_retryable(),
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payment(status: &str) -> Payment {
+        serde_json::from_value(serde_json::json!({
+            "id": "sq_123",
+            "status": status,
+            "amount_money": { "amount": 4000, "currency": "USD" },
+            "total_money": { "amount": 4500, "currency": "USD" },
+            "created_at": "2024-06-01T09:00:00Z"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_payment_states() {
+        let approved: GatewayPayment = payment("APPROVED").into();
+        assert_eq!(approved.state, PaymentState::Authorized);
+        assert_eq!(approved.captured_cents, 0);
+
+        let completed: GatewayPayment = payment("COMPLETED").into();
+        assert_eq!(completed.state, PaymentState::Succeeded);
+        assert_eq!(completed.captured_cents, 4500);
+
+        let record = GatewayPaymentRecord::from(payment("COMPLETED"));
+        assert_eq!(record.refunded_cents, 0);
+        assert_eq!(record.created_at.to_rfc3339(), "2024-06-01T09:00:00+00:00");
+    }
+
+    #[test]
+    fn test_processing_fee_includes_adjustments() {
+        assert_eq!(total_processing_fee(&payment("APPROVED")), None);
+
+        let completed: Payment = serde_json::from_value(serde_json::json!({
+            "id": "sq_123",
+            "status": "COMPLETED",
+            "amount_money": { "amount": 4000, "currency": "USD" },
+            "processing_fee": [
+                { "type": "INITIAL", "amount_money": { "amount": 146, "currency": "USD" } },
+                { "type": "ADJUSTMENT", "amount_money": { "amount": -29, "currency": "USD" } }
+            ],
+            "created_at": "2024-06-01T09:00:00Z"
+        }))
+        .unwrap();
+        assert_eq!(total_processing_fee(&completed), Some(117));
+    }
+
+    #[test]
+    fn test_evidence_types() {
+        let request = DisputeEvidenceRequest {
+            dispute_id: "dp_1".to_string(),
+            customer_name: Some("Pat Lee".to_string()),
+            customer_email: Some("pat@example.com".to_string()),
+            service_date: Some("2024-03-04".to_string()),
+            receipt: Some("Charged $40.00".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            square_evidence(&request),
+            vec![
+                (
+                    "CARDHOLDER_INFORMATION",
+                    "Pat Lee\npat@example.com".to_string()
+                ),
+                ("SERVICE_RECEIVED_DOCUMENTATION", "2024-03-04".to_string()),
+                ("RECEIPT", "Charged $40.00".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,451 @@
+//! Stripe implementation of [`PaymentGateway`] using PaymentIntents.
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use super::{
+    AuthorizeRequest, CaptureMethod, DisputeEvidenceRequest, GatewayError, GatewayPayment,
+    GatewayPaymentRecord, GatewayPayout, GatewayRefund, GatewayResult, GatewayTransfer,
+    PaymentGateway, PaymentState, PayoutRequest, PayoutSpeed, RefundRequest, TransferRequest,
+};
+use crate::stripe::{
+    Charge, CreatePaymentIntentParams, PaymentIntent, PaymentIntentStatus, StripeClient,
+    StripeError,
+};
+
+/// Stripe gateway, optionally routing funds to a Connect account
+pub struct StripeGateway {
+    client: StripeClient,
+    connected_account: Option<String>,
+}
+
+impl StripeGateway {
+    /// Charge on the platform account, sending funds to `connected_account`
+    /// (destination charges) when one is given
+    pub fn new(client: StripeClient, connected_account: Option<String>) -> Self {
+        Self {
+            client,
+            connected_account,
+        }
+    }
+}
+
+#[async_trait]
+impl PaymentGateway for StripeGateway {
+    fn provider_name(&self) -> &'static str {
+        "stripe"
+    }
+
+    async fn authorize(&self, request: &AuthorizeRequest) -> GatewayResult<GatewayPayment> {
+        let mut metadata = request.metadata.clone();
+        if let Some(reference) = &request.reference {
+            metadata.insert("reference".to_string(), reference.clone());
+        }
+
+        let has_payment_method = request.payment_method.is_some();
+        let params = CreatePaymentIntentParams {
+            amount: request.amount_cents,
+            currency: request.currency.code().to_lowercase(),
+            customer: request.customer.clone(),
+            payment_method: request.payment_method.clone(),
+            // A saved payment method is confirmed server-side; otherwise the
+            // browser confirms with the client secret
+            confirm: has_payment_method,
+            automatic_payment_methods: !has_payment_method,
+            manual_capture: request.capture_method == CaptureMethod::Manual,
+            off_session: has_payment_method && request.off_session,
+            idempotency_key: Some(request.idempotency_key.clone()),
+            metadata: Some(metadata),
+            application_fee_amount: self
+                .connected_account
+                .as_ref()
+                .and(request.application_fee_cents)
+                .filter(|fee| *fee > 0),
+            transfer_data_destination: self.connected_account.clone(),
+            ..Default::default()
+        };
+
+        let intent = self.client.create_payment_intent(params).await?;
+        Ok(intent.into())
+    }
+
+    async fn capture(
+        &self,
+        payment_id: &str,
+        amount_cents: Option<i64>,
+    ) -> GatewayResult<GatewayPayment> {
+        let intent = self
+            .client
+            .capture_payment_intent(payment_id, amount_cents)
+            .await?;
+        Ok(intent.into())
+    }
+
+    async fn cancel(&self, payment_id: &str) -> GatewayResult<GatewayPayment> {
+        let intent = self.client.cancel_payment_intent(payment_id).await?;
+        Ok(intent.into())
+    }
+
+    async fn refund(&self, request: &RefundRequest) -> GatewayResult<GatewayRefund> {
+        let refund = self
+            .client
+            .create_refund(
+                &request.payment_id,
+                Some(request.amount_cents),
+                Some(request.reason.as_str()),
+                self.connected_account.is_some(),
+                Some(&request.idempotency_key),
+            )
+            .await?;
+
+        Ok(GatewayRefund {
+            id: refund.id,
+            payment_id: request.payment_id.clone(),
+            amount_cents: refund.amount,
+            status: refund.status,
+        })
+    }
+
+    async fn payment_status(&self, payment_id: &str) -> GatewayResult<GatewayPayment> {
+        let intent = self.client.get_payment_intent(payment_id).await?;
+        Ok(intent.into())
+    }
+
+    async fn processing_fee(&self, payment_id: &str) -> GatewayResult<Option<i64>> {
+        let intent = self.client.get_payment_intent(payment_id).await?;
+        let Some(charge_id) = intent.latest_charge else {
+            return Ok(None);
+        };
+        let charge = self.client.get_charge(&charge_id).await?;
+        let Some(balance_transaction) = charge.balance_transaction else {
+            return Ok(None);
+        };
+
+        let balance_transaction = self
+            .client
+            .get_balance_transaction(&balance_transaction)
+            .await?;
+        Ok(Some(balance_transaction.fee))
+    }
+
+    async fn list_payments(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> GatewayResult<Vec<GatewayPaymentRecord>> {
+        let mut records = Vec::new();
+        let mut starting_after: Option<String> = None;
+        loop {
+            let page = self
+                .client
+                .list_charges(from.timestamp(), to.timestamp(), starting_after.as_deref())
+                .await?;
+
+            starting_after = page.data.last().map(|charge| charge.id.clone());
+            // Destination charges live on the platform account; keep only
+            // the ones paid to this gateway's connected account
+            records.extend(
+                page.data
+                    .into_iter()
+                    .filter(|charge| {
+                        charge
+                            .transfer_data
+                            .as_ref()
+                            .map(|t| t.destination.as_str())
+                            == self.connected_account.as_deref()
+                    })
+                    .map(GatewayPaymentRecord::from),
+            );
+
+            if !page.has_more || starting_after.is_none() {
+                return Ok(records);
+            }
+        }
+    }
+
+    async fn payout(&self, request: &PayoutRequest) -> GatewayResult<GatewayPayout> {
+        let account = self.connected_account.as_deref().ok_or_else(|| {
+            GatewayError::InvalidRequest("Stripe account not connected".to_string())
+        })?;
+
+        let method = match request.speed {
+            PayoutSpeed::Standard => "standard",
+            PayoutSpeed::Instant => "instant",
+        };
+
+        let payout = self
+            .client
+            .create_payout(
+                request.amount_cents,
+                &request.currency.code().to_lowercase(),
+                method,
+                account,
+                Some(&request.idempotency_key),
+            )
+            .await?;
+
+        Ok(GatewayPayout {
+            id: payout.id,
+            amount_cents: payout.amount,
+            status: payout.status,
+        })
+    }
+
+    async fn transfer(&self, request: &TransferRequest) -> GatewayResult<GatewayTransfer> {
+        // Destination charges leave the merchant's share in their own
+        // account, which can't transfer to other connected accounts
+        if self.connected_account.is_some() {
+            return Err(GatewayError::NotSupported(
+                "Transfers from a connected account",
+            ));
+        }
+
+        let transfer = self
+            .client
+            .create_transfer(
+                request.amount_cents,
+                &request.currency.code().to_lowercase(),
+                &request.destination,
+                None,
+                Some(request.metadata.clone()),
+                Some(&request.idempotency_key),
+            )
+            .await?;
+
+        Ok(GatewayTransfer {
+            id: transfer.id,
+            amount_cents: transfer.amount,
+            destination: transfer.destination,
+        })
+    }
+
+    async fn submit_dispute_evidence(&self, request: &DisputeEvidenceRequest) -> GatewayResult<()> {
+        // Destination charges are disputed on the platform account
+        self.client
+            .update_dispute_evidence(&request.dispute_id, &stripe_evidence(request), true, None)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Map evidence onto Stripe's text fields. Stripe's document fields take
+/// uploaded files, so the longer documentation goes in `uncategorized_text`.
+fn stripe_evidence(request: &DisputeEvidenceRequest) -> HashMap<String, String> {
+    let mut evidence = HashMap::new();
+    for (field, value) in [
+        ("customer_name", &request.customer_name),
+        ("customer_email_address", &request.customer_email),
+        ("product_description", &request.product_description),
+        ("service_date", &request.service_date),
+        (
+            "cancellation_policy_disclosure",
+            &request.cancellation_policy,
+        ),
+    ] {
+        if let Some(value) = value {
+            evidence.insert(field.to_string(), value.clone());
+        }
+    }
+
+    let additional = (!request.additional_documentation.is_empty())
+        .then(|| request.additional_documentation.join("\n"));
+    let sections: Vec<String> = [
+        ("Service documentation", &request.service_documentation),
+        ("Customer communication", &request.customer_communication),
+        ("Receipt", &request.receipt),
+        ("Additional documentation", &additional),
+    ]
+    .into_iter()
+    .filter_map(|(heading, text)| text.as_ref().map(|text| format!("{}:\n{}", heading, text)))
+    .collect();
+    if !sections.is_empty() {
+        evidence.insert("uncategorized_text".to_string(), sections.join("\n\n"));
+    }
+
+    evidence
+}
+
+impl From<PaymentIntent> for GatewayPayment {
+    fn from(intent: PaymentIntent) -> Self {
+        let state = match intent.status {
+            PaymentIntentStatus::RequiresPaymentMethod
+            | PaymentIntentStatus::RequiresConfirmation
+            | PaymentIntentStatus::RequiresAction => PaymentState::RequiresAction,
+            PaymentIntentStatus::Processing => PaymentState::Processing,
+            PaymentIntentStatus::RequiresCapture => PaymentState::Authorized,
+            PaymentIntentStatus::Succeeded => PaymentState::Succeeded,
+            PaymentIntentStatus::Canceled => PaymentState::Canceled,
+        };
+
+        GatewayPayment {
+            id: intent.id,
+            state,
+            amount_cents: intent.amount,
+            captured_cents: intent.amount_received.unwrap_or(0),
+            currency: intent.currency.to_uppercase(),
+            client_secret: intent.client_secret,
+        }
+    }
+}
+
+impl From<Charge> for GatewayPaymentRecord {
+    fn from(charge: Charge) -> Self {
+        let state = match charge.status.as_str() {
+            "succeeded" if charge.captured => PaymentState::Succeeded,
+            // Releasing an uncaptured charge refunds it in full
+            "succeeded" if charge.refunded => PaymentState::Canceled,
+            "succeeded" => PaymentState::Authorized,
+            "pending" => PaymentState::Processing,
+            _ => PaymentState::Failed,
+        };
+
+        GatewayPaymentRecord {
+            id: charge.payment_intent.unwrap_or(charge.id),
+            state,
+            amount_cents: charge.amount,
+            captured_cents: charge.amount_captured,
+            refunded_cents: if state == PaymentState::Succeeded {
+                charge.amount_refunded
+            } else {
+                0
+            },
+            currency: charge.currency.to_uppercase(),
+            created_at: DateTime::from_timestamp(charge.created, 0).unwrap_or_default(),
+        }
+    }
+}
+
+impl From<StripeError> for GatewayError {
+    fn from(error: StripeError) -> Self {
+        match error {
+            StripeError::CardDeclined(code) => GatewayError::Declined(code),
+            StripeError::ApiError {
+                decline_code: Some(code),
+                ..
+            } => GatewayError::Declined(code),
+            StripeError::ApiError {
+                code: Some(code), ..
+            } if code == "resource_missing" => GatewayError::NotFound(code),
+            StripeError::InvalidRequest(message) | StripeError::MissingField(message) => {
+                GatewayError::InvalidRequest(message)
+            }
+            error => GatewayError::Provider {
+                retryable: error.is_retryable(),
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn intent(status: PaymentIntentStatus) -> PaymentIntent {
+        serde_json::from_value(serde_json::json!({
+            "id": "pi_123",
+            "object": "payment_intent",
+            "amount": 4200,
+            "amount_received": 0,
+            "currency": "usd",
+            "status": status,
+            "client_secret": "pi_123_secret_abc",
+            "customer": null,
+            "payment_method": null,
+            "transfer_data": null,
+            "application_fee_amount": null,
+            "latest_charge": null,
+            "metadata": {},
+            "created": 0
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_payment_intent_states() {
+        let payment: GatewayPayment = intent(PaymentIntentStatus::RequiresCapture).into();
+        assert_eq!(payment.state, PaymentState::Authorized);
+        assert_eq!(payment.currency, "USD");
+
+        let payment: GatewayPayment = intent(PaymentIntentStatus::RequiresAction).into();
+        assert_eq!(payment.state, PaymentState::RequiresAction);
+        assert_eq!(payment.client_secret.as_deref(), Some("pi_123_secret_abc"));
+    }
+
+    #[test]
+    fn test_charge_records() {
+        let charge: Charge = serde_json::from_value(serde_json::json!({
+            "id": "ch_123",
+            "object": "charge",
+            "amount": 4200,
+            "amount_captured": 4200,
+            "amount_refunded": 1000,
+            "currency": "usd",
+            "paid": true,
+            "captured": true,
+            "refunded": false,
+            "status": "succeeded",
+            "payment_intent": "pi_123",
+            "transfer_data": { "destination": "acct_1", "amount": null },
+            "payment_method_details": null,
+            "receipt_url": null,
+            "created": 1717232400
+        }))
+        .unwrap();
+
+        let record = GatewayPaymentRecord::from(charge.clone());
+        assert_eq!(record.id, "pi_123");
+        assert_eq!(record.state, PaymentState::Succeeded);
+        assert_eq!(record.refunded_cents, 1000);
+
+        let released = Charge {
+            captured: false,
+            refunded: true,
+            ..charge
+        };
+        assert_eq!(
+            GatewayPaymentRecord::from(released).state,
+            PaymentState::Canceled
+        );
+    }
+
+    #[test]
+    fn test_decline_errors_map_to_declined() {
+        let error = StripeError::ApiError {
+            code: Some("card_declined".to_string()),
+            message: "Your card was declined.".to_string(),
+            param: None,
+            decline_code: Some("insufficient_funds".to_string()),
+        };
+        assert!(matches!(
+            GatewayError::from(error),
+            GatewayError::Declined(code) if code == "insufficient_funds"
+        ));
+    }
+
+    #[test]
+    fn test_evidence_fields() {
+        let request = DisputeEvidenceRequest {
+            dispute_id: "dp_1".to_string(),
+            customer_name: Some("Pat Lee".to_string()),
+            service_date: Some("2024-03-04".to_string()),
+            service_documentation: Some("Walked 2.1 km".to_string()),
+            cancellation_policy: Some("No charge".to_string()),
+            additional_documentation: vec!["https://example.com/photo.jpg".to_string()],
+            ..Default::default()
+        };
+
+        let evidence = stripe_evidence(&request);
+        assert_eq!(evidence["customer_name"], "Pat Lee");
+        assert_eq!(evidence["service_date"], "2024-03-04");
+        assert_eq!(evidence["cancellation_policy_disclosure"], "No charge");
+        assert_eq!(
+            evidence["uncategorized_text"],
+            "Service documentation:\nWalked 2.1 km\n\n\
+             Additional documentation:\nhttps://example.com/photo.jpg"
+        );
+        assert!(!evidence.contains_key("customer_email_address"));
+    }
+}
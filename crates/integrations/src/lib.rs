@@ -1,8 +1,10 @@
+pub mod gateway;
 pub mod google_maps;
 pub mod square;
 pub mod stripe;
 pub mod tax;
 
+pub use gateway::PaymentGateway;
 pub use google_maps::GoogleMapsClient;
 pub use square::SquareClient;
 pub use stripe::StripeClient;
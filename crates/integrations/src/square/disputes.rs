@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+use super::{client::SquareClient, error::SquareResult, payments::Money};
+
+/// Dispute object
+#[derive(Debug, Clone, Deserialize)]
+pub struct Dispute {
+    pub id: String,
+    /// e.g. "EVIDENCE_REQUIRED", "PROCESSING", "WON" or "LOST"
+    pub state: String,
+    pub reason: Option<String>,
+    pub amount_money: Option<Money>,
+    /// When evidence is due (RFC 3339)
+    pub due_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DisputeResponse {
+    dispute: Dispute,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateEvidenceTextRequest<'a> {
+    idempotency_key: &'a str,
+    evidence_type: &'a str,
+    evidence_text: &'a str,
+}
+
+impl SquareClient {
+    /// Add a piece of text evidence to a dispute. `evidence_type` is one of
+    /// Square's evidence types, e.g. "SERVICE_RECEIVED_DOCUMENTATION".
+    pub async fn create_dispute_evidence_text(
+        &self,
+        dispute_id: &str,
+        evidence_type: &str,
+        evidence_text: &str,
+        idempotency_key: &str,
+    ) -> SquareResult<()> {
+        let request = CreateEvidenceTextRequest {
+            idempotency_key,
+            evidence_type,
+            evidence_text,
+        };
+        let _: serde_json::Value = self
+            .post(&format!("/disputes/{}/evidence-text", dispute_id), &request)
+            .await?;
+        Ok(())
+    }
+
+    /// Submit a dispute's evidence to the card issuer
+    pub async fn submit_dispute_evidence(&self, dispute_id: &str) -> SquareResult<Dispute> {
+        #[derive(Serialize)]
+        struct EmptyBody {}
+        let response: DisputeResponse = self
+            .post(
+                &format!("/disputes/{}/submit-evidence", dispute_id),
+                &EmptyBody {},
+            )
+            .await?;
+        Ok(response.dispute)
+    }
+}
@@ -1,9 +1,11 @@
 mod client;
+mod disputes;
 mod error;
 mod oauth;
 mod payments;
 
 pub use client::SquareClient;
+pub use disputes::Dispute;
 pub use error::{SquareError, SquareResult};
 pub use oauth::{OAuthTokenResponse, RevokeTokenResponse};
-pub use payments::{Card, CreatePaymentRequest, Money, Payment, Refund};
+pub use payments::{Card, CreatePaymentRequest, Money, Payment, ProcessingFee, Refund};
@@ -148,6 +148,8 @@ impl SquareClient {
 }
 
 // URL encoding helper
+pub(super) use urlencoding::encode;
+
 mod urlencoding {
     pub fn encode(s: &str) -> String {
         let mut result = String::new();
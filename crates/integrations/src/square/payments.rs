@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use super::{client::SquareClient, error::SquareResult};
+use super::{client::SquareClient, error::SquareResult, oauth::encode};
 
 /// Money amount
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,12 +11,16 @@ pub struct Money {
 }
 
 impl Money {
-    pub fn usd(cents: i64) -> Self {
+    pub fn new(amount: i64, currency: &str) -> Self {
         Self {
-            amount: cents,
-            currency: "USD".to_string(),
+            amount,
+            currency: currency.to_uppercase(),
         }
     }
+
+    pub fn usd(cents: i64) -> Self {
+        Self::new(cents, "USD")
+    }
 }
 
 /// Payment object
@@ -28,6 +32,10 @@ pub struct Payment {
     pub total_money: Option<Money>,
     pub tip_money: Option<Money>,
     pub app_fee_money: Option<Money>,
+    pub refunded_money: Option<Money>,
+    /// Square's fees, added once the payment is completed
+    #[serde(default)]
+    pub processing_fee: Vec<ProcessingFee>,
     pub source_type: Option<String>,
     pub card_details: Option<CardDetails>,
     pub receipt_number: Option<String>,
@@ -38,6 +46,15 @@ pub struct Payment {
     pub updated_at: Option<String>,
 }
 
+/// Fee Square charged to process a payment
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProcessingFee {
+    pub effective_at: Option<String>,
+    #[serde(rename = "type")]
+    pub fee_type: Option<String>,
+    pub amount_money: Money,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct CardDetails {
     pub card: Option<Card>,
@@ -130,6 +147,25 @@ impl CreatePaymentRequest {
         self.note = Some(note);
         self
     }
+
+    pub fn with_currency(mut self, currency: &str) -> Self {
+        self.amount_money = Money::new(self.amount_money.amount, currency);
+        if let Some(fee) = &mut self.app_fee_money {
+            *fee = Money::new(fee.amount, currency);
+        }
+        self
+    }
+
+    pub fn with_idempotency_key(mut self, key: String) -> Self {
+        self.idempotency_key = key;
+        self
+    }
+
+    /// Authorize only; the payment must be completed separately
+    pub fn delayed_capture(mut self) -> Self {
+        self.autocomplete = false;
+        self
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -137,6 +173,13 @@ struct PaymentResponse {
     payment: Payment,
 }
 
+#[derive(Debug, Deserialize)]
+struct ListPaymentsResponse {
+    #[serde(default)]
+    payments: Vec<Payment>,
+    cursor: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct RefundResponse {
     refund: Refund,
@@ -148,6 +191,8 @@ struct RefundPaymentRequest {
     payment_id: String,
     amount_money: Money,
     #[serde(skip_serializing_if = "Option::is_none")]
+    app_fee_money: Option<Money>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     reason: Option<String>,
 }
 
@@ -164,6 +209,31 @@ impl SquareClient {
         Ok(response.payment)
     }
 
+    /// List payments created in `[begin_time, end_time)` (RFC 3339), one page
+    /// at a time. Returns the payments and the cursor for the next page.
+    pub async fn list_payments(
+        &self,
+        begin_time: &str,
+        end_time: &str,
+        location_id: Option<&str>,
+        cursor: Option<&str>,
+    ) -> SquareResult<(Vec<Payment>, Option<String>)> {
+        let mut path = format!(
+            "/payments?begin_time={}&end_time={}&sort_order=ASC",
+            encode(begin_time),
+            encode(end_time)
+        );
+        if let Some(location_id) = location_id {
+            path.push_str(&format!("&location_id={}", encode(location_id)));
+        }
+        if let Some(cursor) = cursor {
+            path.push_str(&format!("&cursor={}", encode(cursor)));
+        }
+
+        let response: ListPaymentsResponse = self.get(&path).await?;
+        Ok((response.payments, response.cursor))
+    }
+
     /// Complete a payment (if autocomplete was false)
     pub async fn complete_payment(&self, payment_id: &str) -> SquareResult<Payment> {
         #[derive(Serialize)]
@@ -184,17 +254,22 @@ impl SquareClient {
         Ok(response.payment)
     }
 
-    /// Refund a payment
+    /// Refund a payment. A random idempotency key is used if none is given.
     pub async fn refund_payment(
         &self,
         payment_id: &str,
-        amount_cents: i64,
+        amount_money: Money,
+        app_fee_money: Option<Money>,
         reason: Option<&str>,
+        idempotency_key: Option<&str>,
     ) -> SquareResult<Refund> {
         let request = RefundPaymentRequest {
-            idempotency_key: Uuid::new_v4().to_string(),
+            idempotency_key: idempotency_key
+                .map(str::to_string)
+                .unwrap_or_else(|| Uuid::new_v4().to_string()),
             payment_id: payment_id.to_string(),
-            amount_money: Money::usd(amount_cents),
+            amount_money,
+            app_fee_money,
             reason: reason.map(|s| s.to_string()),
         };
 
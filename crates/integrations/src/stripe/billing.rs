@@ -0,0 +1,376 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use super::{
+    client::{StripeClient, StripeList},
+    error::StripeResult,
+};
+
+/// Price object
+#[derive(Debug, Clone, Deserialize)]
+pub struct Price {
+    pub id: String,
+    pub object: String,
+    pub active: bool,
+    pub currency: String,
+    pub unit_amount: Option<i64>,
+    pub lookup_key: Option<String>,
+    pub recurring: Option<Recurring>,
+    pub metadata: Option<HashMap<String, String>>,
+    pub created: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Recurring {
+    pub interval: String,
+    pub interval_count: i64,
+}
+
+/// Subscription object
+#[derive(Debug, Clone, Deserialize)]
+pub struct Subscription {
+    pub id: String,
+    pub object: String,
+    pub customer: String,
+    /// incomplete, incomplete_expired, trialing, active, past_due, canceled,
+    /// unpaid or paused
+    pub status: String,
+    pub items: StripeList<SubscriptionItem>,
+    /// Absent from newer API versions, which report the period per item
+    pub current_period_start: Option<i64>,
+    pub current_period_end: Option<i64>,
+    pub trial_start: Option<i64>,
+    pub trial_end: Option<i64>,
+    #[serde(default)]
+    pub cancel_at_period_end: bool,
+    pub canceled_at: Option<i64>,
+    pub ended_at: Option<i64>,
+    pub metadata: Option<HashMap<String, String>>,
+    pub created: i64,
+}
+
+impl Subscription {
+    /// The current billing period, from the subscription or else its first
+    /// item
+    pub fn current_period(&self) -> (Option<i64>, Option<i64>) {
+        let item = self.items.data.first();
+        (
+            self.current_period_start
+                .or_else(|| item.and_then(|i| i.current_period_start)),
+            self.current_period_end
+                .or_else(|| item.and_then(|i| i.current_period_end)),
+        )
+    }
+
+    /// A metadata value set when the subscription was created or changed
+    pub fn metadata_value(&self, key: &str) -> Option<&str> {
+        self.metadata
+            .as_ref()
+            .and_then(|m| m.get(key))
+            .map(String::as_str)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubscriptionItem {
+    pub id: String,
+    pub price: Price,
+    pub quantity: Option<i64>,
+    pub current_period_start: Option<i64>,
+    pub current_period_end: Option<i64>,
+}
+
+/// Invoice object
+#[derive(Debug, Clone, Deserialize)]
+pub struct Invoice {
+    pub id: String,
+    pub object: String,
+    pub customer: Option<String>,
+    /// Absent from newer API versions, which report it under `parent`
+    pub subscription: Option<String>,
+    pub parent: Option<serde_json::Value>,
+    pub status: Option<String>,
+    pub billing_reason: Option<String>,
+    pub amount_due: i64,
+    pub amount_paid: i64,
+    pub currency: String,
+    #[serde(default)]
+    pub attempt_count: i64,
+    pub next_payment_attempt: Option<i64>,
+    pub created: i64,
+}
+
+impl Invoice {
+    /// The subscription the invoice bills, if any
+    pub fn subscription_id(&self) -> Option<&str> {
+        self.subscription.as_deref().or_else(|| {
+            self.parent
+                .as_ref()
+                .and_then(|p| p.get("subscription_details"))
+                .and_then(|d| d.get("subscription"))
+                .and_then(|v| v.as_str())
+        })
+    }
+}
+
+/// Parameters for creating a recurring price
+#[derive(Debug, Clone)]
+pub struct CreatePriceParams {
+    pub unit_amount: i64,
+    pub currency: String,
+    /// day, week, month or year
+    pub interval: String,
+    pub interval_count: i64,
+    pub product_name: String,
+    pub lookup_key: Option<String>,
+    pub metadata: HashMap<String, String>,
+}
+
+/// Parameters for creating a subscription
+#[derive(Debug, Clone, Default)]
+pub struct CreateSubscriptionParams {
+    pub customer: String,
+    pub price: String,
+    pub trial_period_days: Option<u32>,
+    pub default_payment_method: Option<String>,
+    pub metadata: HashMap<String, String>,
+}
+
+impl CreatePriceParams {
+    fn to_params(&self) -> HashMap<String, String> {
+        let mut params = HashMap::new();
+        params.insert("unit_amount".to_string(), self.unit_amount.to_string());
+        params.insert("currency".to_string(), self.currency.to_lowercase());
+        params.insert("recurring[interval]".to_string(), self.interval.clone());
+        params.insert(
+            "recurring[interval_count]".to_string(),
+            self.interval_count.to_string(),
+        );
+        params.insert("product_data[name]".to_string(), self.product_name.clone());
+
+        if let Some(key) = &self.lookup_key {
+            params.insert("lookup_key".to_string(), key.clone());
+        }
+        for (key, value) in &self.metadata {
+            params.insert(format!("metadata[{}]", key), value.clone());
+        }
+        params
+    }
+}
+
+impl CreateSubscriptionParams {
+    fn to_params(&self) -> HashMap<String, String> {
+        let mut params = HashMap::new();
+        params.insert("customer".to_string(), self.customer.clone());
+        params.insert("items[0][price]".to_string(), self.price.clone());
+        // A failed first payment leaves the subscription incomplete rather
+        // than failing the request
+        params.insert(
+            "payment_behavior".to_string(),
+            "allow_incomplete".to_string(),
+        );
+
+        if let Some(days) = self.trial_period_days {
+            params.insert("trial_period_days".to_string(), days.to_string());
+            // A trial started without a card ends the subscription rather
+            // than leaving an unpaid invoice
+            params.insert(
+                "trial_settings[end_behavior][missing_payment_method]".to_string(),
+                "cancel".to_string(),
+            );
+        }
+        if let Some(pm) = &self.default_payment_method {
+            params.insert("default_payment_method".to_string(), pm.clone());
+        }
+        for (key, value) in &self.metadata {
+            params.insert(format!("metadata[{}]", key), value.clone());
+        }
+        params
+    }
+}
+
+impl StripeClient {
+    // ============ Prices ============
+
+    /// Find the active price with a lookup key
+    pub async fn find_price_by_lookup_key(&self, lookup_key: &str) -> StripeResult<Option<Price>> {
+        let prices: StripeList<Price> = self
+            .get(&format!(
+                "/prices?active=true&lookup_keys%5B%5D={}",
+                lookup_key
+            ))
+            .await?;
+        Ok(prices.data.into_iter().next())
+    }
+
+    /// Create a recurring price, and the product it's for
+    pub async fn create_price(&self, params: &CreatePriceParams) -> StripeResult<Price> {
+        self.post("/prices", &params.to_params()).await
+    }
+
+    // ============ Subscriptions ============
+
+    /// Create a subscription to a price
+    pub async fn create_subscription(
+        &self,
+        params: &CreateSubscriptionParams,
+        idempotency_key: Option<&str>,
+    ) -> StripeResult<Subscription> {
+        self.post_with_options("/subscriptions", &params.to_params(), None, idempotency_key)
+            .await
+    }
+
+    /// Retrieve a subscription
+    pub async fn get_subscription(&self, id: &str) -> StripeResult<Subscription> {
+        self.get(&format!("/subscriptions/{}", id)).await
+    }
+
+    /// Move a subscription item to another price, prorating the change, and
+    /// withdraw any pending cancellation
+    pub async fn change_subscription_price(
+        &self,
+        id: &str,
+        item_id: &str,
+        price: &str,
+        metadata: HashMap<String, String>,
+    ) -> StripeResult<Subscription> {
+        let mut params = HashMap::new();
+        params.insert("items[0][id]".to_string(), item_id.to_string());
+        params.insert("items[0][price]".to_string(), price.to_string());
+        params.insert(
+            "proration_behavior".to_string(),
+            "create_prorations".to_string(),
+        );
+        params.insert("cancel_at_period_end".to_string(), "false".to_string());
+        for (key, value) in metadata {
+            params.insert(format!("metadata[{}]", key), value);
+        }
+
+        self.post(&format!("/subscriptions/{}", id), &params).await
+    }
+
+    /// Set whether a subscription ends when its current period does
+    pub async fn set_cancel_at_period_end(
+        &self,
+        id: &str,
+        cancel_at_period_end: bool,
+    ) -> StripeResult<Subscription> {
+        let mut params = HashMap::new();
+        params.insert(
+            "cancel_at_period_end".to_string(),
+            cancel_at_period_end.to_string(),
+        );
+
+        self.post(&format!("/subscriptions/{}", id), &params).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscription(json: serde_json::Value) -> Subscription {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn item(period: Option<(i64, i64)>) -> serde_json::Value {
+        serde_json::json!({
+            "id": "si_1",
+            "price": {
+                "id": "price_1",
+                "object": "price",
+                "active": true,
+                "currency": "usd",
+                "unit_amount": 2900,
+                "created": 1,
+            },
+            "current_period_start": period.map(|p| p.0),
+            "current_period_end": period.map(|p| p.1),
+        })
+    }
+
+    fn subscription_json(
+        period: Option<(i64, i64)>,
+        item_period: Option<(i64, i64)>,
+    ) -> Subscription {
+        subscription(serde_json::json!({
+            "id": "sub_1",
+            "object": "subscription",
+            "customer": "cus_1",
+            "status": "active",
+            "items": {
+                "object": "list",
+                "data": [item(item_period)],
+                "has_more": false,
+                "url": "/v1/subscription_items",
+            },
+            "current_period_start": period.map(|p| p.0),
+            "current_period_end": period.map(|p| p.1),
+            "metadata": { "plan_tier": "business" },
+            "created": 1,
+        }))
+    }
+
+    #[test]
+    fn test_current_period() {
+        let sub = subscription_json(Some((10, 20)), Some((30, 40)));
+        assert_eq!(sub.current_period(), (Some(10), Some(20)));
+
+        let sub = subscription_json(None, Some((30, 40)));
+        assert_eq!(sub.current_period(), (Some(30), Some(40)));
+        assert_eq!(sub.metadata_value("plan_tier"), Some("business"));
+        assert_eq!(sub.metadata_value("missing"), None);
+    }
+
+    #[test]
+    fn test_invoice_subscription_id() {
+        let invoice = |extra: serde_json::Value| -> Invoice {
+            let mut json = serde_json::json!({
+                "id": "in_1",
+                "object": "invoice",
+                "amount_due": 2900,
+                "amount_paid": 0,
+                "currency": "usd",
+                "created": 1,
+            });
+            json.as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            serde_json::from_value(json).unwrap()
+        };
+
+        assert_eq!(
+            invoice(serde_json::json!({ "subscription": "sub_1" })).subscription_id(),
+            Some("sub_1")
+        );
+        assert_eq!(
+            invoice(serde_json::json!({
+                "parent": { "subscription_details": { "subscription": "sub_2" } }
+            }))
+            .subscription_id(),
+            Some("sub_2")
+        );
+        assert_eq!(invoice(serde_json::json!({})).subscription_id(), None);
+    }
+
+    #[test]
+    fn test_subscription_params() {
+        let params = CreateSubscriptionParams {
+            customer: "cus_1".to_string(),
+            price: "price_1".to_string(),
+            trial_period_days: Some(14),
+            default_payment_method: None,
+            metadata: HashMap::from([("organization_id".to_string(), "org".to_string())]),
+        }
+        .to_params();
+
+        assert_eq!(params["items[0][price]"], "price_1");
+        assert_eq!(params["trial_period_days"], "14");
+        assert_eq!(
+            params["trial_settings[end_behavior][missing_payment_method]"],
+            "cancel"
+        );
+        assert_eq!(params["metadata[organization_id]"], "org");
+        assert!(!params.contains_key("default_payment_method"));
+    }
+}
@@ -44,17 +44,7 @@ impl StripeClient {
         path: &str,
         params: &HashMap<String, String>,
     ) -> StripeResult<T> {
-        let url = format!("{}{}", STRIPE_API_BASE, path);
-
-        let response = self
-            .client
-            .post(&url)
-            .basic_auth(&self.secret_key, None::<&str>)
-            .form(params)
-            .send()
-            .await?;
-
-        self.handle_response(response).await
+        self.post_with_options(path, params, None, None).await
     }
 
     /// Make a POST request on behalf of a connected account
@@ -63,17 +53,38 @@ impl StripeClient {
         path: &str,
         params: &HashMap<String, String>,
         stripe_account: &str,
+    ) -> StripeResult<T> {
+        self.post_with_options(path, params, Some(stripe_account), None)
+            .await
+    }
+
+    /// Make a POST request with an optional connected account and
+    /// idempotency key. Stripe returns the original response when a request
+    /// is retried with the same key, so retries never double-charge.
+    pub async fn post_with_options<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        params: &HashMap<String, String>,
+        stripe_account: Option<&str>,
+        idempotency_key: Option<&str>,
     ) -> StripeResult<T> {
         let url = format!("{}{}", STRIPE_API_BASE, path);
 
-        let response = self
+        let mut request = self
             .client
             .post(&url)
             .basic_auth(&self.secret_key, None::<&str>)
-            .header("Stripe-Account", stripe_account)
-            .form(params)
-            .send()
-            .await?;
+            .form(params);
+
+        if let Some(account) = stripe_account {
+            request = request.header("Stripe-Account", account);
+        }
+
+        if let Some(key) = idempotency_key {
+            request = request.header("Idempotency-Key", key);
+        }
+
+        let response = request.send().await?;
 
         self.handle_response(response).await
     }
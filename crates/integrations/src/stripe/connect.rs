@@ -50,6 +50,28 @@ pub struct AccountRequirements {
     pub disabled_reason: Option<String>,
 }
 
+impl Account {
+    /// Where the account is in onboarding: "restricted" once Stripe has
+    /// disabled it, "verified" when it can take charges and receive payouts,
+    /// "pending_verification" while Stripe reviews submitted details, and
+    /// "onboarding" until then
+    pub fn verification_status(&self) -> &'static str {
+        let disabled = self
+            .requirements
+            .as_ref()
+            .is_some_and(|r| r.disabled_reason.is_some());
+        if disabled && !self.charges_enabled {
+            "restricted"
+        } else if self.charges_enabled && self.payouts_enabled {
+            "verified"
+        } else if self.details_submitted {
+            "pending_verification"
+        } else {
+            "onboarding"
+        }
+    }
+}
+
 /// OAuth token response
 #[derive(Debug, Clone, Deserialize)]
 pub struct OAuthTokenResponse {
@@ -219,3 +241,44 @@ mod urlencoding {
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(charges_enabled: bool, payouts_enabled: bool, details_submitted: bool) -> Account {
+        Account {
+            id: "acct_1".to_string(),
+            object: "account".to_string(),
+            account_type: Some("standard".to_string()),
+            business_profile: None,
+            charges_enabled,
+            payouts_enabled,
+            details_submitted,
+            email: None,
+            requirements: None,
+        }
+    }
+
+    #[test]
+    fn test_verification_status() {
+        assert_eq!(
+            account(false, false, false).verification_status(),
+            "onboarding"
+        );
+        assert_eq!(
+            account(false, false, true).verification_status(),
+            "pending_verification"
+        );
+        assert_eq!(account(true, true, true).verification_status(), "verified");
+
+        let mut restricted = account(false, false, true);
+        restricted.requirements = Some(AccountRequirements {
+            currently_due: vec!["external_account".to_string()],
+            eventually_due: Vec::new(),
+            past_due: vec!["external_account".to_string()],
+            disabled_reason: Some("requirements.past_due".to_string()),
+        });
+        assert_eq!(restricted.verification_status(), "restricted");
+    }
+}
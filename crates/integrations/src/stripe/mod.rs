@@ -1,3 +1,4 @@
+mod billing;
 mod client;
 mod connect;
 mod customers;
@@ -5,6 +6,10 @@ mod error;
 mod payments;
 mod webhooks;
 
+pub use billing::{
+    CreatePriceParams, CreateSubscriptionParams, Invoice, Price, Recurring, Subscription,
+    SubscriptionItem,
+};
 pub use client::{Address, StripeClient, StripeList};
 pub use connect::{Account, AccountLink, AccountType, LoginLink, OAuthTokenResponse};
 pub use customers::{
@@ -12,7 +17,8 @@ pub use customers::{
 };
 pub use error::{StripeError, StripeResult};
 pub use payments::{
-    Charge, CreatePaymentIntentParams, PaymentIntent, PaymentIntentStatus, Refund, Transfer,
+    BalanceTransaction, Charge, CreatePaymentIntentParams, PaymentIntent, PaymentIntentStatus,
+    Refund, Transfer,
 };
 pub use webhooks::{
     construct_event, event_types, parse_event, verify_signature, Dispute, Payout, WebhookEvent,
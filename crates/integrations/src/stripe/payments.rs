@@ -1,7 +1,11 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-use super::{client::StripeClient, error::StripeResult};
+use super::{
+    client::{StripeClient, StripeList},
+    error::StripeResult,
+    webhooks::{Dispute, Payout},
+};
 
 /// Payment Intent status
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -47,17 +51,38 @@ pub struct Charge {
     pub id: String,
     pub object: String,
     pub amount: i64,
+    #[serde(default)]
+    pub amount_captured: i64,
     pub amount_refunded: i64,
     pub currency: String,
     pub paid: bool,
+    #[serde(default)]
+    pub captured: bool,
     pub refunded: bool,
     pub status: String,
     pub payment_intent: Option<String>,
+    pub transfer_data: Option<TransferData>,
     pub payment_method_details: Option<PaymentMethodDetails>,
+    /// Balance transaction recording the funds and Stripe's fee, once the
+    /// charge is captured
+    #[serde(default)]
+    pub balance_transaction: Option<String>,
     pub receipt_url: Option<String>,
     pub created: i64,
 }
 
+/// Balance transaction: funds moving through a Stripe balance, net of fees
+#[derive(Debug, Clone, Deserialize)]
+pub struct BalanceTransaction {
+    pub id: String,
+    pub amount: i64,
+    /// Stripe's fees, in the balance currency's minor unit
+    pub fee: i64,
+    pub net: i64,
+    pub currency: String,
+    pub status: String,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct PaymentMethodDetails {
     pub card: Option<CardDetails>,
@@ -100,6 +125,11 @@ pub struct CreatePaymentIntentParams {
     pub automatic_payment_methods: bool,
     pub return_url: Option<String>,
     pub metadata: Option<HashMap<String, String>>,
+    /// Authorize only; the payment must be captured separately
+    pub manual_capture: bool,
+    /// Charge a saved payment method without the customer present
+    pub off_session: bool,
+    pub idempotency_key: Option<String>,
     // Connect-specific
     pub application_fee_amount: Option<i64>,
     pub transfer_data_destination: Option<String>,
@@ -141,6 +171,14 @@ impl StripeClient {
             form.insert("return_url".to_string(), return_url);
         }
 
+        if params.manual_capture {
+            form.insert("capture_method".to_string(), "manual".to_string());
+        }
+
+        if params.off_session {
+            form.insert("off_session".to_string(), "true".to_string());
+        }
+
         // Connect parameters
         if let Some(fee) = params.application_fee_amount {
             form.insert("application_fee_amount".to_string(), fee.to_string());
@@ -160,7 +198,13 @@ impl StripeClient {
             }
         }
 
-        self.post("/payment_intents", &form).await
+        self.post_with_options(
+            "/payment_intents",
+            &form,
+            None,
+            params.idempotency_key.as_deref(),
+        )
+        .await
     }
 
     /// Retrieve a payment intent
@@ -219,6 +263,29 @@ impl StripeClient {
         self.get(&format!("/charges/{}", id)).await
     }
 
+    /// Retrieve a balance transaction
+    pub async fn get_balance_transaction(&self, id: &str) -> StripeResult<BalanceTransaction> {
+        self.get(&format!("/balance_transactions/{}", id)).await
+    }
+
+    /// List charges created in `[created_gte, created_lt)` (Unix seconds),
+    /// newest first, one page at a time
+    pub async fn list_charges(
+        &self,
+        created_gte: i64,
+        created_lt: i64,
+        starting_after: Option<&str>,
+    ) -> StripeResult<StripeList<Charge>> {
+        let mut path = format!(
+            "/charges?limit=100&created%5Bgte%5D={}&created%5Blt%5D={}",
+            created_gte, created_lt
+        );
+        if let Some(id) = starting_after {
+            path.push_str(&format!("&starting_after={}", id));
+        }
+        self.get(&path).await
+    }
+
     // ============ Refunds ============
 
     /// Create a refund
@@ -227,6 +294,8 @@ impl StripeClient {
         payment_intent: &str,
         amount: Option<i64>,
         reason: Option<&str>,
+        reverse_transfer: bool,
+        idempotency_key: Option<&str>,
     ) -> StripeResult<Refund> {
         let mut params = HashMap::new();
         params.insert("payment_intent".to_string(), payment_intent.to_string());
@@ -239,7 +308,15 @@ impl StripeClient {
             params.insert("reason".to_string(), r.to_string());
         }
 
-        self.post("/refunds", &params).await
+        // For destination charges: pull the refunded share back from the
+        // connected account and return the same share of the application fee
+        if reverse_transfer {
+            params.insert("reverse_transfer".to_string(), "true".to_string());
+            params.insert("refund_application_fee".to_string(), "true".to_string());
+        }
+
+        self.post_with_options("/refunds", &params, None, idempotency_key)
+            .await
     }
 
     /// Retrieve a refund
@@ -257,6 +334,7 @@ impl StripeClient {
         destination: &str,
         source_transaction: Option<&str>,
         metadata: Option<HashMap<String, String>>,
+        idempotency_key: Option<&str>,
     ) -> StripeResult<Transfer> {
         let mut params = HashMap::new();
         params.insert("amount".to_string(), amount.to_string());
@@ -273,7 +351,57 @@ impl StripeClient {
             }
         }
 
-        self.post("/transfers", &params).await
+        self.post_with_options("/transfers", &params, None, idempotency_key)
+            .await
+    }
+
+    // ============ Payouts (for Connect) ============
+
+    /// Pay out a connected account's balance to its bank account or card.
+    ///
+    /// `method` is "standard" or "instant".
+    pub async fn create_payout(
+        &self,
+        amount: i64,
+        currency: &str,
+        method: &str,
+        stripe_account: &str,
+        idempotency_key: Option<&str>,
+    ) -> StripeResult<Payout> {
+        let mut params = HashMap::new();
+        params.insert("amount".to_string(), amount.to_string());
+        params.insert("currency".to_string(), currency.to_string());
+        params.insert("method".to_string(), method.to_string());
+
+        self.post_with_options("/payouts", &params, Some(stripe_account), idempotency_key)
+            .await
+    }
+
+    // ============ Disputes ============
+
+    /// Update a dispute's evidence, keyed by Stripe evidence field (e.g.
+    /// "customer_name" or "uncategorized_text"). Submitting sends the
+    /// evidence to the card issuer; it can't be changed afterwards.
+    pub async fn update_dispute_evidence(
+        &self,
+        dispute_id: &str,
+        evidence: &HashMap<String, String>,
+        submit: bool,
+        stripe_account: Option<&str>,
+    ) -> StripeResult<Dispute> {
+        let mut params: HashMap<String, String> = evidence
+            .iter()
+            .map(|(field, value)| (format!("evidence[{}]", field), value.clone()))
+            .collect();
+        params.insert("submit".to_string(), submit.to_string());
+
+        self.post_with_options(
+            &format!("/disputes/{}", dispute_id),
+            &params,
+            stripe_account,
+            None,
+        )
+        .await
     }
 }
 
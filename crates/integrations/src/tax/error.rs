@@ -10,6 +10,9 @@ pub enum TaxError {
     #[error("Invalid address: {0}")]
     InvalidAddress(String),
 
+    #[error("No tax rates for country: {0}")]
+    UnsupportedCountry(String),
+
     #[error("Tax calculation failed: {0}")]
     CalculationError(String),
 
@@ -1,5 +1,15 @@
+//! Sales tax calculation and reporting.
+//!
+//! [`TaxProvider`] quotes the tax on a sale and records completed sales and
+//! refunds for filing. [`TaxJarClient`] uses the TaxJar API;
+//! [`OfflineTaxTable`] falls back to statewide base rates.
+
 mod error;
+mod offline;
+mod provider;
 mod taxjar;
 
 pub use error::{TaxError, TaxResult};
+pub use offline::OfflineTaxTable;
+pub use provider::{TaxProvider, TaxQuote, TaxReport, TaxRequest};
 pub use taxjar::{Address, TaxCalculation, TaxCategory, TaxJarClient, TaxLineItem, TaxRate};
@@ -0,0 +1,85 @@
+//! Tax from a built-in table of state rates, for when no tax service is
+//! configured or it can't be reached.
+
+use async_trait::async_trait;
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::Decimal;
+
+use super::error::{TaxError, TaxResult};
+use super::provider::{TaxProvider, TaxQuote, TaxRequest};
+use super::taxjar::SimpleTaxCalculator;
+
+/// Statewide base rates from [`SimpleTaxCalculator`]. Local taxes aren't
+/// included and nothing is reported. Only US sales are covered; anything else
+/// is [`TaxError::UnsupportedCountry`] rather than a guess.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OfflineTaxTable;
+
+impl OfflineTaxTable {
+    /// Quote for a sale in `state` of `country`
+    pub fn quote(country: &str, state: &str) -> TaxResult<TaxQuote> {
+        let country = country.trim().to_uppercase();
+        if country != "US" {
+            return Err(TaxError::UnsupportedCountry(country));
+        }
+        let state = state.trim().to_uppercase();
+        let rate = SimpleTaxCalculator::default_state_rate(&state);
+
+        Ok(TaxQuote {
+            rate_percent: Decimal::from_f64(rate).unwrap_or_default().round_dp(4),
+            jurisdiction: state,
+            calculation_id: None,
+        })
+    }
+}
+
+#[async_trait]
+impl TaxProvider for OfflineTaxTable {
+    fn provider_name(&self) -> &'static str {
+        "offline"
+    }
+
+    async fn calculate(&self, request: &TaxRequest) -> TaxResult<TaxQuote> {
+        Self::quote(&request.to.country, &request.to.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tax::{Address, TaxCategory};
+
+    #[test]
+    fn test_quote_by_state() {
+        let quote = OfflineTaxTable::quote("us", " ca ").unwrap();
+        assert_eq!(quote.rate_percent, Decimal::new(725, 2));
+        assert_eq!(quote.jurisdiction, "CA");
+        assert_eq!(quote.calculation_id, None);
+
+        let rate = |state| OfflineTaxTable::quote("US", state).unwrap().rate_percent;
+        assert_eq!(rate("OR"), Decimal::ZERO);
+        assert_eq!(rate("ZZ"), Decimal::new(6, 0));
+    }
+
+    #[test]
+    fn test_other_countries_are_not_guessed() {
+        assert!(matches!(
+            OfflineTaxTable::quote("CA", "ON"),
+            Err(TaxError::UnsupportedCountry(country)) if country == "CA"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_calculate_uses_destination() {
+        let request = TaxRequest {
+            from: Address::us("OR", "97201"),
+            to: Address::us("TX", "73301"),
+            amount_cents: 2500,
+            category: TaxCategory::PetServices,
+        };
+
+        let quote = OfflineTaxTable.calculate(&request).await.unwrap();
+        assert_eq!(quote.rate_percent, Decimal::new(625, 2));
+        assert!(!OfflineTaxTable.reports_transactions());
+    }
+}
@@ -0,0 +1,71 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+
+use super::error::TaxResult;
+use super::taxjar::{Address, TaxCategory};
+
+/// Sale to calculate tax for
+#[derive(Debug, Clone)]
+pub struct TaxRequest {
+    /// Where the service is sold from
+    pub from: Address,
+    /// Where the service is performed
+    pub to: Address,
+    /// Taxable amount in cents
+    pub amount_cents: i64,
+    pub category: TaxCategory,
+}
+
+/// Tax a provider calculated for a sale
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaxQuote {
+    /// Combined rate as a percentage (e.g. 8.25 for 8.25%), applied to the
+    /// taxable amount
+    pub rate_percent: Decimal,
+    /// Where the tax is owed, most general first (e.g. "CA, LOS ANGELES")
+    pub jurisdiction: String,
+    /// Provider's reference for the calculation, if it keeps one
+    pub calculation_id: Option<String>,
+}
+
+/// A completed sale or refund, reported to the provider for filing
+#[derive(Debug, Clone)]
+pub struct TaxReport {
+    /// Our ID for the sale or refund; reporting the same ID twice is a no-op
+    pub id: String,
+    /// For refunds, the ID the original sale was reported under
+    pub sale_id: Option<String>,
+    pub date: DateTime<Utc>,
+    pub from: Address,
+    pub to: Address,
+    /// Amount charged (or refunded) in cents, excluding tax
+    pub amount_cents: i64,
+    /// Tax collected (or refunded) in cents
+    pub tax_cents: i64,
+}
+
+/// Calculates sales tax and records what was collected
+#[async_trait]
+pub trait TaxProvider: Send + Sync {
+    /// Short provider name for logs (e.g. "taxjar")
+    fn provider_name(&self) -> &'static str;
+
+    /// Whether completed sales should be reported to this provider
+    fn reports_transactions(&self) -> bool {
+        false
+    }
+
+    /// Calculate the tax owed on a sale
+    async fn calculate(&self, request: &TaxRequest) -> TaxResult<TaxQuote>;
+
+    /// Record a completed sale
+    async fn report_sale(&self, _report: &TaxReport) -> TaxResult<()> {
+        Ok(())
+    }
+
+    /// Record a refund against a reported sale
+    async fn report_refund(&self, _report: &TaxReport) -> TaxResult<()> {
+        Ok(())
+    }
+}
@@ -1,7 +1,12 @@
+use async_trait::async_trait;
+use chrono::SecondsFormat;
 use reqwest::Client;
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
 use super::error::{TaxError, TaxResult};
+use super::provider::{TaxProvider, TaxQuote, TaxReport, TaxRequest};
 
 const TAXJAR_API_BASE: &str = "https://api.taxjar.com/v2";
 const TAXJAR_SANDBOX_API_BASE: &str = "https://api.sandbox.taxjar.com/v2";
@@ -157,6 +162,35 @@ impl TaxCalculation {
     pub fn effective_rate_percent(&self) -> f64 {
         self.rate * 100.0
     }
+
+    /// Rate on the whole order as a percentage. Only part of an order may be
+    /// taxable, and nothing is where there's no nexus.
+    pub fn order_rate_percent(&self) -> Decimal {
+        if !self.has_nexus || self.taxable_amount <= 0.0 || self.order_total_amount <= 0.0 {
+            return Decimal::ZERO;
+        }
+        let taxable_share = (self.taxable_amount / self.order_total_amount).min(1.0);
+        Decimal::from_f64(self.rate * taxable_share * 100.0)
+            .unwrap_or_default()
+            .round_dp(4)
+    }
+
+    /// Jurisdictions the tax is owed to, most general first
+    pub fn jurisdiction(&self) -> Option<String> {
+        let jurisdictions = self.jurisdictions.as_ref()?;
+        let parts: Vec<String> = [
+            &jurisdictions.state,
+            &jurisdictions.county,
+            &jurisdictions.city,
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|part| !part.is_empty())
+        .map(|part| part.to_uppercase())
+        .collect();
+
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -204,6 +238,50 @@ struct TaxCalculationRequest {
     line_items: Option<Vec<TaxLineItem>>,
 }
 
+/// Order or refund recorded for filing
+#[derive(Debug, Serialize)]
+struct TransactionRequest {
+    transaction_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transaction_reference_id: Option<String>,
+    transaction_date: String,
+    from_country: String,
+    from_zip: String,
+    from_state: String,
+    to_country: String,
+    to_zip: String,
+    to_state: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    to_city: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    to_street: Option<String>,
+    amount: f64,
+    shipping: f64,
+    sales_tax: f64,
+}
+
+impl TransactionRequest {
+    /// Refunds are recorded with negative amounts
+    fn new(report: &TaxReport, sign: f64) -> Self {
+        Self {
+            transaction_id: report.id.clone(),
+            transaction_reference_id: report.sale_id.clone(),
+            transaction_date: report.date.to_rfc3339_opts(SecondsFormat::Secs, true),
+            from_country: report.from.country.clone(),
+            from_zip: report.from.zip.clone(),
+            from_state: report.from.state.clone(),
+            to_country: report.to.country.clone(),
+            to_zip: report.to.zip.clone(),
+            to_state: report.to.state.clone(),
+            to_city: report.to.city.clone(),
+            to_street: report.to.street.clone(),
+            amount: sign * report.amount_cents as f64 / 100.0,
+            shipping: 0.0,
+            sales_tax: sign * report.tax_cents as f64 / 100.0,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct TaxRateResponse {
     rate: TaxRate,
@@ -334,6 +412,46 @@ impl TaxJarClient {
             .await
     }
 
+    /// Record a completed order for filing
+    pub async fn create_order_transaction(&self, report: &TaxReport) -> TaxResult<()> {
+        self.create_transaction("orders", TransactionRequest::new(report, 1.0))
+            .await
+    }
+
+    /// Record a refund of a reported order for filing
+    pub async fn create_refund_transaction(&self, report: &TaxReport) -> TaxResult<()> {
+        let request = TransactionRequest::new(report, -1.0);
+        if request.transaction_reference_id.is_none() {
+            return Err(TaxError::MissingField(
+                "transaction_reference_id".to_string(),
+            ));
+        }
+        self.create_transaction("refunds", request).await
+    }
+
+    async fn create_transaction(&self, kind: &str, request: TransactionRequest) -> TaxResult<()> {
+        let url = format!("{}/transactions/{}", self.base_url(), kind);
+
+        let response = self
+            .client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .send()
+            .await?;
+
+        match self.handle_response::<serde_json::Value>(response).await {
+            Ok(_) => Ok(()),
+            // Already recorded under this ID
+            Err(TaxError::ApiError {
+                status: 422,
+                message,
+            }) if message.contains("already exists") => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
     async fn handle_response<T: serde::de::DeserializeOwned>(
         &self,
         response: reqwest::Response,
@@ -360,8 +478,49 @@ impl TaxJarClient {
     }
 }
 
+#[async_trait]
+impl TaxProvider for TaxJarClient {
+    fn provider_name(&self) -> &'static str {
+        "taxjar"
+    }
+
+    fn reports_transactions(&self) -> bool {
+        true
+    }
+
+    async fn calculate(&self, request: &TaxRequest) -> TaxResult<TaxQuote> {
+        let line_item =
+            TaxLineItem::new("service", 1, request.amount_cents).with_tax_code(request.category);
+        let calculation = self
+            .calculate_tax(
+                &request.from,
+                &request.to,
+                request.amount_cents,
+                0,
+                Some(vec![line_item]),
+            )
+            .await?;
+
+        Ok(TaxQuote {
+            rate_percent: calculation.order_rate_percent(),
+            jurisdiction: calculation
+                .jurisdiction()
+                .unwrap_or_else(|| request.to.state.to_uppercase()),
+            // TaxJar doesn't keep calculations; sales are reported by our ID
+            calculation_id: None,
+        })
+    }
+
+    async fn report_sale(&self, report: &TaxReport) -> TaxResult<()> {
+        self.create_order_transaction(report).await
+    }
+
+    async fn report_refund(&self, report: &TaxReport) -> TaxResult<()> {
+        self.create_refund_transaction(report).await
+    }
+}
+
 /// Simplified tax calculation without external API (for fallback/testing)
-#[allow(dead_code)]
 pub struct SimpleTaxCalculator;
 
 #[allow(dead_code)]
@@ -413,6 +572,69 @@ mod tests {
         assert_eq!(tax, 725);
     }
 
+    fn calculation(rate: f64, taxable_amount: f64, has_nexus: bool) -> TaxCalculation {
+        TaxCalculation {
+            order_total_amount: 40.0,
+            shipping: 0.0,
+            taxable_amount,
+            amount_to_collect: taxable_amount * rate,
+            rate,
+            has_nexus,
+            freight_taxable: false,
+            tax_source: Some("destination".to_string()),
+            jurisdictions: Some(TaxJurisdictions {
+                country: "US".to_string(),
+                state: Some("CA".to_string()),
+                county: Some("Los Angeles".to_string()),
+                city: None,
+            }),
+            breakdown: None,
+        }
+    }
+
+    #[test]
+    fn test_order_rate_percent() {
+        assert_eq!(
+            calculation(0.095, 40.0, true).order_rate_percent(),
+            Decimal::new(95, 1)
+        );
+        // Half the order is taxable
+        assert_eq!(
+            calculation(0.095, 20.0, true).order_rate_percent(),
+            Decimal::new(475, 2)
+        );
+        assert_eq!(
+            calculation(0.095, 40.0, false).order_rate_percent(),
+            Decimal::ZERO
+        );
+    }
+
+    #[test]
+    fn test_jurisdiction() {
+        assert_eq!(
+            calculation(0.095, 40.0, true).jurisdiction().as_deref(),
+            Some("CA, LOS ANGELES")
+        );
+    }
+
+    #[test]
+    fn test_refund_amounts_are_negative() {
+        let report = TaxReport {
+            id: "refund-1".to_string(),
+            sale_id: Some("sale-1".to_string()),
+            date: chrono::Utc::now(),
+            from: Address::us("CA", "90210"),
+            to: Address::us("CA", "90210"),
+            amount_cents: 2500,
+            tax_cents: 181,
+        };
+
+        let request = TransactionRequest::new(&report, -1.0);
+        assert_eq!(request.amount, -25.0);
+        assert_eq!(request.sales_tax, -1.81);
+        assert_eq!(request.transaction_reference_id.as_deref(), Some("sale-1"));
+    }
+
     #[test]
     fn test_address() {
         let addr = Address::us("CA", "90210");
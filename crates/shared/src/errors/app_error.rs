@@ -53,7 +53,9 @@ impl AppError {
                 DomainError::InvalidCredentials | DomainError::InvalidToken => 401, // Unauthorized
                 DomainError::TokenExpired => 401,
                 DomainError::EmailAlreadyExists | DomainError::SlugAlreadyExists(_) => 409, // Conflict
-                _ => 400, // Bad Request
+                DomainError::PaymentAlreadyExists(_) => 409,
+                DomainError::PaymentDeclined(_) => 402, // Payment Required
+                _ => 400,                               // Bad Request
             },
             AppError::Database(_) | AppError::Internal(_) => 500, // Internal Server Error
             AppError::ExternalApi(_) => 503,                      // Service Unavailable
@@ -85,6 +87,8 @@ impl AppError {
                 DomainError::TokenExpired => "TOKEN_EXPIRED",
                 DomainError::EmailAlreadyExists => "EMAIL_EXISTS",
                 DomainError::SlugAlreadyExists(_) => "SLUG_EXISTS",
+                DomainError::PaymentAlreadyExists(_) => "PAYMENT_EXISTS",
+                DomainError::PaymentDeclined(_) => "PAYMENT_DECLINED",
                 _ => "DOMAIN_ERROR",
             },
             AppError::Database(_) => "DATABASE_ERROR",
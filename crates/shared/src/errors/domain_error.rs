@@ -95,4 +95,10 @@ pub enum DomainError {
 
     #[error("User is already a member of this organization")]
     AlreadyMember,
+
+    #[error("Booking already has a {0} payment")]
+    PaymentAlreadyExists(String),
+
+    #[error("Payment declined: {0}")]
+    PaymentDeclined(String),
 }
@@ -0,0 +1,273 @@
+//! GeoJSON (RFC 7946) conversion for polygons.
+//!
+//! Positions are `[longitude, latitude]`; rings are closed, with the exterior
+//! first followed by any holes.
+
+use serde_json::{json, Map, Value};
+
+use super::{close_ring, NamedPolygon, Polygon};
+use crate::types::Coordinates;
+
+/// Error reading polygons from GeoJSON or KML
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum GeoFormatError {
+    #[error("Invalid GeoJSON: {0}")]
+    InvalidGeoJson(String),
+    #[error("Invalid KML: {0}")]
+    InvalidKml(String),
+    #[error("No polygons found")]
+    NoPolygons,
+}
+
+/// Read all polygons from a GeoJSON value.
+///
+/// Accepts a FeatureCollection, a Feature, or a bare geometry. Polygon and
+/// MultiPolygon geometries are read (each MultiPolygon member becomes its own
+/// polygon); other geometry types are skipped. Feature names are taken from
+/// the `name` property. Polygons are returned unvalidated.
+pub fn polygons_from_geojson(value: &Value) -> Result<Vec<NamedPolygon>, GeoFormatError> {
+    let mut polygons = Vec::new();
+    collect_geojson(value, None, &mut polygons)?;
+
+    if polygons.is_empty() {
+        return Err(GeoFormatError::NoPolygons);
+    }
+    Ok(polygons)
+}
+
+/// Convert a polygon to a GeoJSON Polygon geometry
+pub fn polygon_to_geojson(polygon: &Polygon) -> Value {
+    let rings: Vec<Value> = std::iter::once(polygon.vertices())
+        .chain(polygon.holes().iter().map(Vec::as_slice))
+        .map(|ring| {
+            Value::Array(
+                close_ring(ring)
+                    .iter()
+                    .map(|p| json!([p.longitude, p.latitude]))
+                    .collect(),
+            )
+        })
+        .collect();
+
+    json!({
+        "type": "Polygon",
+        "coordinates": rings,
+    })
+}
+
+/// Wrap a polygon in a GeoJSON Feature with the given properties
+pub fn geojson_feature(polygon: &Polygon, properties: Map<String, Value>) -> Value {
+    json!({
+        "type": "Feature",
+        "geometry": polygon_to_geojson(polygon),
+        "properties": properties,
+    })
+}
+
+/// Build a GeoJSON FeatureCollection from features
+pub fn geojson_feature_collection(features: Vec<Value>) -> Value {
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
+
+fn collect_geojson(
+    value: &Value,
+    name: Option<&str>,
+    out: &mut Vec<NamedPolygon>,
+) -> Result<(), GeoFormatError> {
+    let kind = value
+        .get("type")
+        .and_then(Value::as_str)
+        .ok_or_else(|| GeoFormatError::InvalidGeoJson("missing \"type\"".to_string()))?;
+
+    match kind {
+        "FeatureCollection" => {
+            let features = value
+                .get("features")
+                .and_then(Value::as_array)
+                .ok_or_else(|| {
+                    GeoFormatError::InvalidGeoJson("missing \"features\" array".to_string())
+                })?;
+            for feature in features {
+                collect_geojson(feature, None, out)?;
+            }
+        }
+        "Feature" => {
+            let name = value
+                .get("properties")
+                .and_then(|p| p.get("name"))
+                .and_then(Value::as_str);
+            match value.get("geometry") {
+                Some(Value::Null) | None => {}
+                Some(geometry) => collect_geojson(geometry, name, out)?,
+            }
+        }
+        "GeometryCollection" => {
+            let geometries = value
+                .get("geometries")
+                .and_then(Value::as_array)
+                .ok_or_else(|| {
+                    GeoFormatError::InvalidGeoJson("missing \"geometries\" array".to_string())
+                })?;
+            for geometry in geometries {
+                collect_geojson(geometry, name, out)?;
+            }
+        }
+        "Polygon" => {
+            let polygon = parse_polygon_coordinates(coordinates(value)?)?;
+            out.push(NamedPolygon {
+                name: name.map(str::to_string),
+                polygon,
+            });
+        }
+        "MultiPolygon" => {
+            let members = coordinates(value)?
+                .as_array()
+                .ok_or_else(|| invalid("MultiPolygon coordinates must be an array"))?;
+            for (i, member) in members.iter().enumerate() {
+                let polygon = parse_polygon_coordinates(member)?;
+                out.push(NamedPolygon {
+                    name: name.map(|n| {
+                        if members.len() > 1 {
+                            format!("{} ({})", n, i + 1)
+                        } else {
+                            n.to_string()
+                        }
+                    }),
+                    polygon,
+                });
+            }
+        }
+        // Points, lines and other geometries can't describe an area
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn coordinates(geometry: &Value) -> Result<&Value, GeoFormatError> {
+    geometry
+        .get("coordinates")
+        .ok_or_else(|| invalid("geometry is missing \"coordinates\""))
+}
+
+fn parse_polygon_coordinates(value: &Value) -> Result<Polygon, GeoFormatError> {
+    let rings = value
+        .as_array()
+        .ok_or_else(|| invalid("Polygon coordinates must be an array of rings"))?;
+
+    let mut rings = rings.iter().map(parse_ring);
+    let exterior = rings
+        .next()
+        .ok_or_else(|| invalid("Polygon has no rings"))??;
+    let holes = rings.collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Polygon::with_holes_unchecked(exterior, holes))
+}
+
+fn parse_ring(value: &Value) -> Result<Vec<Coordinates>, GeoFormatError> {
+    value
+        .as_array()
+        .ok_or_else(|| invalid("ring must be an array of positions"))?
+        .iter()
+        .map(|position| {
+            let lng = position.get(0).and_then(Value::as_f64);
+            let lat = position.get(1).and_then(Value::as_f64);
+            match (lat, lng) {
+                (Some(lat), Some(lng)) => Ok(Coordinates::new_unchecked(lat, lng)),
+                _ => Err(invalid("position must be [longitude, latitude]")),
+            }
+        })
+        .collect()
+}
+
+fn invalid(message: &str) -> GeoFormatError {
+    GeoFormatError::InvalidGeoJson(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn donut() -> Value {
+        json!({
+            "type": "Feature",
+            "properties": { "name": "Downtown" },
+            "geometry": {
+                "type": "Polygon",
+                "coordinates": [
+                    [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0], [0.0, 0.0]],
+                    [[0.25, 0.25], [0.75, 0.25], [0.75, 0.75], [0.25, 0.75], [0.25, 0.25]]
+                ]
+            }
+        })
+    }
+
+    #[test]
+    fn test_feature_with_hole() {
+        let polygons = polygons_from_geojson(&donut()).unwrap();
+        assert_eq!(polygons.len(), 1);
+        assert_eq!(polygons[0].name.as_deref(), Some("Downtown"));
+
+        let polygon = &polygons[0].polygon;
+        assert_eq!(polygon.vertices().len(), 4);
+        assert_eq!(polygon.holes().len(), 1);
+        assert!(polygon.validate().is_ok());
+        assert!(!polygon.contains(&Coordinates::new_unchecked(0.5, 0.5)));
+    }
+
+    #[test]
+    fn test_multipolygon_members_become_separate_polygons() {
+        let value = json!({
+            "type": "FeatureCollection",
+            "features": [{
+                "type": "Feature",
+                "properties": { "name": "Suburbs" },
+                "geometry": {
+                    "type": "MultiPolygon",
+                    "coordinates": [
+                        [[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 0.0]]],
+                        [[[5.0, 5.0], [6.0, 5.0], [6.0, 6.0], [5.0, 5.0]]]
+                    ]
+                }
+            }, {
+                "type": "Feature",
+                "properties": {},
+                "geometry": { "type": "Point", "coordinates": [0.0, 0.0] }
+            }]
+        });
+
+        let polygons = polygons_from_geojson(&value).unwrap();
+        assert_eq!(polygons.len(), 2);
+        assert_eq!(polygons[0].name.as_deref(), Some("Suburbs (1)"));
+        assert_eq!(polygons[1].name.as_deref(), Some("Suburbs (2)"));
+    }
+
+    #[test]
+    fn test_no_polygons() {
+        let value = json!({ "type": "Point", "coordinates": [0.0, 0.0] });
+        assert_eq!(
+            polygons_from_geojson(&value),
+            Err(GeoFormatError::NoPolygons)
+        );
+    }
+
+    #[test]
+    fn test_invalid_position() {
+        let value = json!({ "type": "Polygon", "coordinates": [[[0.0], [1.0, 1.0]]] });
+        assert!(matches!(
+            polygons_from_geojson(&value),
+            Err(GeoFormatError::InvalidGeoJson(_))
+        ));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let original = polygons_from_geojson(&donut()).unwrap().remove(0).polygon;
+        let exported = geojson_feature(&original, Map::new());
+        let reimported = polygons_from_geojson(&exported).unwrap().remove(0).polygon;
+        assert_eq!(original, reimported);
+    }
+}
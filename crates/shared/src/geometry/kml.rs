@@ -0,0 +1,275 @@
+//! KML conversion for polygons.
+//!
+//! Only Placemarks containing Polygons (directly or inside a MultiGeometry)
+//! are read. KML coordinates are `longitude,latitude[,altitude]` tuples.
+
+use quick_xml::escape::escape;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+use super::{close_ring, GeoFormatError, NamedPolygon, Polygon};
+use crate::types::Coordinates;
+
+/// A polygon to write as a KML Placemark
+#[derive(Debug, Clone, Copy)]
+pub struct KmlPlacemark<'a> {
+    pub name: &'a str,
+    pub description: Option<&'a str>,
+    pub polygon: &'a Polygon,
+}
+
+/// Read all Placemark polygons from a KML document, including holes.
+///
+/// A Placemark with several polygons yields one polygon per member, named
+/// with a numeric suffix. Polygons are returned unvalidated.
+pub fn polygons_from_kml(document: &str) -> Result<Vec<NamedPolygon>, GeoFormatError> {
+    let mut reader = Reader::from_str(document);
+    reader.config_mut().trim_text(true);
+
+    let mut path: Vec<String> = Vec::new();
+    let mut polygons = Vec::new();
+
+    let mut placemark_name: Option<String> = None;
+    let mut placemark_polygons: Vec<Polygon> = Vec::new();
+    let mut exterior: Option<Vec<Coordinates>> = None;
+    let mut holes: Vec<Vec<Coordinates>> = Vec::new();
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| GeoFormatError::InvalidKml(e.to_string()))?;
+
+        match event {
+            Event::Start(e) => {
+                let name = String::from_utf8_lossy(e.local_name().as_ref()).into_owned();
+                match name.as_str() {
+                    "Placemark" => {
+                        placemark_name = None;
+                        placemark_polygons.clear();
+                    }
+                    "Polygon" => {
+                        exterior = None;
+                        holes.clear();
+                    }
+                    _ => {}
+                }
+                path.push(name);
+            }
+            Event::End(_) => match path.pop().as_deref() {
+                Some("Polygon") if in_element(&path, "Placemark") => {
+                    let outer = exterior.take().ok_or_else(|| {
+                        GeoFormatError::InvalidKml("Polygon has no outer boundary".to_string())
+                    })?;
+                    placemark_polygons.push(Polygon::with_holes_unchecked(
+                        outer,
+                        std::mem::take(&mut holes),
+                    ));
+                }
+                Some("Placemark") => {
+                    let count = placemark_polygons.len();
+                    for (i, polygon) in placemark_polygons.drain(..).enumerate() {
+                        polygons.push(NamedPolygon {
+                            name: placemark_name.as_ref().map(|n| {
+                                if count > 1 {
+                                    format!("{} ({})", n, i + 1)
+                                } else {
+                                    n.clone()
+                                }
+                            }),
+                            polygon,
+                        });
+                    }
+                }
+                _ => {}
+            },
+            Event::Text(text) => {
+                let text = text
+                    .unescape()
+                    .map_err(|e| GeoFormatError::InvalidKml(e.to_string()))?;
+                handle_text(&path, &text, &mut placemark_name, &mut exterior, &mut holes)?;
+            }
+            Event::CData(data) => {
+                let text = String::from_utf8_lossy(&data.into_inner()).into_owned();
+                handle_text(&path, &text, &mut placemark_name, &mut exterior, &mut holes)?;
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if polygons.is_empty() {
+        return Err(GeoFormatError::NoPolygons);
+    }
+    Ok(polygons)
+}
+
+/// Write polygons as a KML document with one Placemark each
+pub fn kml_document(name: &str, placemarks: &[KmlPlacemark<'_>]) -> String {
+    let mut kml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <kml xmlns=\"http://www.opengis.net/kml/2.2\">\n<Document>\n",
+    );
+    kml.push_str(&format!("<name>{}</name>\n", escape(name)));
+
+    for placemark in placemarks {
+        kml.push_str("<Placemark>\n");
+        kml.push_str(&format!("<name>{}</name>\n", escape(placemark.name)));
+        if let Some(description) = placemark.description {
+            kml.push_str(&format!(
+                "<description>{}</description>\n",
+                escape(description)
+            ));
+        }
+        kml.push_str("<Polygon>\n");
+        kml.push_str(&format!(
+            "<outerBoundaryIs><LinearRing><coordinates>{}</coordinates></LinearRing></outerBoundaryIs>\n",
+            format_coordinates(placemark.polygon.vertices())
+        ));
+        for hole in placemark.polygon.holes() {
+            kml.push_str(&format!(
+                "<innerBoundaryIs><LinearRing><coordinates>{}</coordinates></LinearRing></innerBoundaryIs>\n",
+                format_coordinates(hole)
+            ));
+        }
+        kml.push_str("</Polygon>\n</Placemark>\n");
+    }
+
+    kml.push_str("</Document>\n</kml>\n");
+    kml
+}
+
+fn handle_text(
+    path: &[String],
+    text: &str,
+    placemark_name: &mut Option<String>,
+    exterior: &mut Option<Vec<Coordinates>>,
+    holes: &mut Vec<Vec<Coordinates>>,
+) -> Result<(), GeoFormatError> {
+    match path.last().map(String::as_str) {
+        // Only the Placemark's own name, not names of nested elements
+        Some("name") if path.len() >= 2 && path[path.len() - 2] == "Placemark" => {
+            *placemark_name = Some(text.trim().to_string());
+        }
+        Some("coordinates") if in_element(path, "Polygon") => {
+            let ring = parse_coordinates(text)?;
+            if in_element(path, "innerBoundaryIs") {
+                holes.push(ring);
+            } else if in_element(path, "outerBoundaryIs") {
+                *exterior = Some(ring);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn in_element(path: &[String], name: &str) -> bool {
+    path.iter().any(|element| element == name)
+}
+
+fn parse_coordinates(text: &str) -> Result<Vec<Coordinates>, GeoFormatError> {
+    text.split_whitespace()
+        .map(|tuple| {
+            let mut parts = tuple.split(',').map(|v| v.trim().parse::<f64>());
+            match (parts.next(), parts.next()) {
+                (Some(Ok(lng)), Some(Ok(lat))) => Ok(Coordinates::new_unchecked(lat, lng)),
+                _ => Err(GeoFormatError::InvalidKml(format!(
+                    "invalid coordinate tuple \"{}\"",
+                    tuple
+                ))),
+            }
+        })
+        .collect()
+}
+
+fn format_coordinates(ring: &[Coordinates]) -> String {
+    close_ring(ring)
+        .iter()
+        .map(|p| format!("{},{}", p.longitude, p.latitude))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DONUT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<kml xmlns="http://www.opengis.net/kml/2.2">
+  <Document>
+    <name>Coverage</name>
+    <Placemark>
+      <name><![CDATA[Downtown & Riverside]]></name>
+      <Polygon>
+        <outerBoundaryIs><LinearRing><coordinates>
+          0,0,0 1,0,0 1,1,0 0,1,0 0,0,0
+        </coordinates></LinearRing></outerBoundaryIs>
+        <innerBoundaryIs><LinearRing><coordinates>
+          0.25,0.25 0.75,0.25 0.75,0.75 0.25,0.75 0.25,0.25
+        </coordinates></LinearRing></innerBoundaryIs>
+      </Polygon>
+    </Placemark>
+    <Placemark>
+      <name>Landmark</name>
+      <Point><coordinates>0.5,0.5</coordinates></Point>
+    </Placemark>
+  </Document>
+</kml>"#;
+
+    #[test]
+    fn test_placemark_with_hole() {
+        let polygons = polygons_from_kml(DONUT).unwrap();
+        assert_eq!(polygons.len(), 1);
+        assert_eq!(polygons[0].name.as_deref(), Some("Downtown & Riverside"));
+
+        let polygon = &polygons[0].polygon;
+        assert_eq!(polygon.vertices().len(), 4);
+        assert_eq!(polygon.holes().len(), 1);
+        assert!(polygon.validate().is_ok());
+    }
+
+    #[test]
+    fn test_multigeometry_placemark() {
+        let kml = r#"<kml><Placemark><name>Suburbs</name><MultiGeometry>
+            <Polygon><outerBoundaryIs><LinearRing><coordinates>0,0 1,0 1,1 0,0</coordinates></LinearRing></outerBoundaryIs></Polygon>
+            <Polygon><outerBoundaryIs><LinearRing><coordinates>5,5 6,5 6,6 5,5</coordinates></LinearRing></outerBoundaryIs></Polygon>
+        </MultiGeometry></Placemark></kml>"#;
+
+        let polygons = polygons_from_kml(kml).unwrap();
+        assert_eq!(polygons.len(), 2);
+        assert_eq!(polygons[1].name.as_deref(), Some("Suburbs (2)"));
+    }
+
+    #[test]
+    fn test_no_polygons() {
+        let kml = "<kml><Placemark><Point><coordinates>0,0</coordinates></Point></Placemark></kml>";
+        assert_eq!(polygons_from_kml(kml), Err(GeoFormatError::NoPolygons));
+    }
+
+    #[test]
+    fn test_invalid_coordinates() {
+        let kml = "<kml><Placemark><Polygon><outerBoundaryIs><LinearRing><coordinates>abc</coordinates></LinearRing></outerBoundaryIs></Polygon></Placemark></kml>";
+        assert!(matches!(
+            polygons_from_kml(kml),
+            Err(GeoFormatError::InvalidKml(_))
+        ));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let original = polygons_from_kml(DONUT).unwrap().remove(0).polygon;
+        let document = kml_document(
+            "Export <test>",
+            &[KmlPlacemark {
+                name: "Downtown & Riverside",
+                description: None,
+                polygon: &original,
+            }],
+        );
+        assert!(document.contains("Export &lt;test&gt;"));
+
+        let reimported = polygons_from_kml(&document).unwrap().remove(0);
+        assert_eq!(reimported.name.as_deref(), Some("Downtown & Riverside"));
+        assert_eq!(reimported.polygon, original);
+    }
+}
@@ -0,0 +1,551 @@
+//! Planar geometry helpers for service area polygons.
+//!
+//! Polygons are small (a neighbourhood or a town), so coordinates are treated
+//! as planar for containment and intersection tests. Area is computed on a
+//! local equirectangular projection centred on the polygon. Submodules handle
+//! GeoJSON/KML import and export, vertex-budget simplification, and GPS track
+//! cleanup for walk breadcrumbs.
+
+use crate::types::Coordinates;
+use serde::{Deserialize, Serialize};
+
+/// Tolerance used for coordinate comparisons (roughly 1cm at the equator)
+const EPSILON: f64 = 1e-9;
+
+/// Minimum number of distinct vertices for a valid polygon
+pub const MIN_POLYGON_VERTICES: usize = 3;
+
+mod geojson;
+mod kml;
+mod simplify;
+mod track;
+
+pub use geojson::*;
+pub use kml::*;
+pub use simplify::*;
+pub use track::*;
+
+/// A simple polygon with optional holes (rings are open, with no repeated
+/// closing vertex)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Polygon {
+    vertices: Vec<Coordinates>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    holes: Vec<Vec<Coordinates>>,
+}
+
+impl Polygon {
+    /// Create a polygon, validating and normalizing the ring.
+    ///
+    /// Accepts both open and closed rings; a trailing vertex equal to the first
+    /// is dropped, as are consecutive duplicate vertices.
+    pub fn new(points: Vec<Coordinates>) -> Result<Self, PolygonError> {
+        Self::with_holes(points, Vec::new())
+    }
+
+    /// Create a polygon with holes, validating and normalizing every ring.
+    ///
+    /// Each hole must be a valid ring lying inside the exterior, and no two
+    /// rings may cross or touch.
+    pub fn with_holes(
+        exterior: Vec<Coordinates>,
+        holes: Vec<Vec<Coordinates>>,
+    ) -> Result<Self, PolygonError> {
+        let polygon = Self::with_holes_unchecked(exterior, holes);
+        polygon.validate()?;
+        Ok(polygon)
+    }
+
+    /// Create a polygon without validation (use when data is trusted)
+    pub fn new_unchecked(points: Vec<Coordinates>) -> Self {
+        Self::with_holes_unchecked(points, Vec::new())
+    }
+
+    /// Create a polygon with holes without validation (use when data is trusted)
+    pub fn with_holes_unchecked(exterior: Vec<Coordinates>, holes: Vec<Vec<Coordinates>>) -> Self {
+        Self {
+            vertices: normalize_ring(exterior),
+            holes: holes.into_iter().map(normalize_ring).collect(),
+        }
+    }
+
+    /// Validate the exterior ring, every hole, and how they relate
+    pub fn validate(&self) -> Result<(), PolygonError> {
+        validate_ring(&self.vertices)?;
+
+        for (index, hole) in self.holes.iter().enumerate() {
+            validate_ring(hole).map_err(|e| PolygonError::InvalidHole {
+                index,
+                message: e.to_string(),
+            })?;
+
+            if !hole.iter().all(|p| point_in_polygon(p, &self.vertices)) {
+                return Err(PolygonError::HoleOutsideExterior(index));
+            }
+        }
+
+        let rings: Vec<&[Coordinates]> = std::iter::once(self.vertices.as_slice())
+            .chain(self.holes.iter().map(Vec::as_slice))
+            .collect();
+        for a in 0..rings.len() {
+            for b in (a + 1)..rings.len() {
+                if rings_intersect(rings[a], rings[b]) {
+                    return Err(PolygonError::RingsIntersect(a, b));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The vertices of the exterior ring as an open ring
+    pub fn vertices(&self) -> &[Coordinates] {
+        &self.vertices
+    }
+
+    /// The holes of the polygon, each as an open ring
+    pub fn holes(&self) -> &[Vec<Coordinates>] {
+        &self.holes
+    }
+
+    /// Total number of vertices across the exterior and all holes
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len() + self.holes.iter().map(Vec::len).sum::<usize>()
+    }
+
+    /// The vertices of the exterior as a closed ring (first vertex repeated)
+    pub fn closed_ring(&self) -> Vec<Coordinates> {
+        close_ring(&self.vertices)
+    }
+
+    /// Check whether a point lies inside the polygon or on its boundary.
+    ///
+    /// Points strictly inside a hole are outside; points on a hole's edge are
+    /// on the polygon's boundary and so count as inside.
+    pub fn contains(&self, point: &Coordinates) -> bool {
+        point_in_polygon(point, &self.vertices)
+            && !self
+                .holes
+                .iter()
+                .any(|hole| point_in_polygon(point, hole) && !point_on_ring(point, hole))
+    }
+
+    /// Approximate area in square kilometers, excluding holes
+    pub fn area_km2(&self) -> f64 {
+        let holes: f64 = self.holes.iter().map(|h| polygon_area_km2(h)).sum();
+        (polygon_area_km2(&self.vertices) - holes).max(0.0)
+    }
+
+    /// Bounding box of the polygon
+    pub fn bounding_box(&self) -> BoundingBox {
+        BoundingBox::from_points(&self.vertices)
+    }
+}
+
+/// A polygon with an optional name, as read from an import file
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedPolygon {
+    pub name: Option<String>,
+    pub polygon: Polygon,
+}
+
+/// Axis-aligned bounding box in degrees
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub min_latitude: f64,
+    pub max_latitude: f64,
+    pub min_longitude: f64,
+    pub max_longitude: f64,
+}
+
+impl BoundingBox {
+    /// Compute the bounding box of a set of points
+    pub fn from_points(points: &[Coordinates]) -> Self {
+        points.iter().fold(
+            Self {
+                min_latitude: 90.0,
+                max_latitude: -90.0,
+                min_longitude: 180.0,
+                max_longitude: -180.0,
+            },
+            |bbox, p| Self {
+                min_latitude: bbox.min_latitude.min(p.latitude),
+                max_latitude: bbox.max_latitude.max(p.latitude),
+                min_longitude: bbox.min_longitude.min(p.longitude),
+                max_longitude: bbox.max_longitude.max(p.longitude),
+            },
+        )
+    }
+
+    /// Check whether a point lies within the box (inclusive)
+    pub fn contains(&self, point: &Coordinates) -> bool {
+        point.latitude >= self.min_latitude
+            && point.latitude <= self.max_latitude
+            && point.longitude >= self.min_longitude
+            && point.longitude <= self.max_longitude
+    }
+}
+
+/// Error for invalid polygons
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum PolygonError {
+    #[error("Polygon must have at least {MIN_POLYGON_VERTICES} distinct points, got {0}")]
+    TooFewVertices(usize),
+    #[error("Invalid coordinates at point {index}: {message}")]
+    InvalidCoordinates { index: usize, message: String },
+    #[error("Polygon edges {0} and {1} intersect")]
+    SelfIntersecting(usize, usize),
+    #[error("Polygon has zero area")]
+    ZeroArea,
+    #[error("Hole {index} is invalid: {message}")]
+    InvalidHole { index: usize, message: String },
+    #[error("Hole {0} is not inside the polygon")]
+    HoleOutsideExterior(usize),
+    #[error("Polygon rings {0} and {1} intersect")]
+    RingsIntersect(usize, usize),
+}
+
+/// Check whether a point lies inside a polygon ring or on its boundary.
+///
+/// Uses the even-odd ray casting rule. The ring may be open or closed.
+pub fn point_in_polygon(point: &Coordinates, ring: &[Coordinates]) -> bool {
+    let n = ring.len();
+    if n < MIN_POLYGON_VERTICES {
+        return false;
+    }
+
+    let (px, py) = (point.longitude, point.latitude);
+    let mut inside = false;
+    let mut j = n - 1;
+
+    for i in 0..n {
+        let (xi, yi) = (ring[i].longitude, ring[i].latitude);
+        let (xj, yj) = (ring[j].longitude, ring[j].latitude);
+
+        if point_on_segment(point, &ring[j], &ring[i]) {
+            return true;
+        }
+
+        if (yi > py) != (yj > py) {
+            let x_intersect = xj + (py - yj) * (xi - xj) / (yi - yj);
+            if px < x_intersect {
+                inside = !inside;
+            }
+        }
+
+        j = i;
+    }
+
+    inside
+}
+
+/// Validate a polygon ring: enough vertices, valid coordinates, no
+/// self-intersections and non-zero area. The ring should already be normalized.
+pub fn validate_ring(ring: &[Coordinates]) -> Result<(), PolygonError> {
+    if ring.len() < MIN_POLYGON_VERTICES {
+        return Err(PolygonError::TooFewVertices(ring.len()));
+    }
+
+    for (index, p) in ring.iter().enumerate() {
+        Coordinates::new(p.latitude, p.longitude).map_err(|e| {
+            PolygonError::InvalidCoordinates {
+                index,
+                message: e.to_string(),
+            }
+        })?;
+    }
+
+    if let Some((a, b)) = find_self_intersection(ring) {
+        return Err(PolygonError::SelfIntersecting(a, b));
+    }
+
+    if signed_planar_area(ring).abs() < EPSILON * EPSILON {
+        return Err(PolygonError::ZeroArea);
+    }
+
+    Ok(())
+}
+
+/// Approximate area of a polygon ring in square kilometers
+pub fn polygon_area_km2(ring: &[Coordinates]) -> f64 {
+    const EARTH_RADIUS_KM: f64 = 6371.0;
+
+    if ring.len() < MIN_POLYGON_VERTICES {
+        return 0.0;
+    }
+
+    // Project onto a local equirectangular plane centred on the mean latitude
+    let mean_lat = ring.iter().map(|p| p.latitude).sum::<f64>() / ring.len() as f64;
+    let lng_scale = mean_lat.to_radians().cos();
+
+    let projected: Vec<(f64, f64)> = ring
+        .iter()
+        .map(|p| {
+            (
+                p.longitude.to_radians() * lng_scale * EARTH_RADIUS_KM,
+                p.latitude.to_radians() * EARTH_RADIUS_KM,
+            )
+        })
+        .collect();
+
+    shoelace(&projected).abs()
+}
+
+/// Repeat the first vertex of an open ring at the end
+pub fn close_ring(ring: &[Coordinates]) -> Vec<Coordinates> {
+    let mut closed = ring.to_vec();
+    if let Some(first) = ring.first() {
+        closed.push(*first);
+    }
+    closed
+}
+
+/// Drop a repeated closing vertex and consecutive duplicate vertices
+fn normalize_ring(points: Vec<Coordinates>) -> Vec<Coordinates> {
+    let mut ring: Vec<Coordinates> = Vec::with_capacity(points.len());
+    for p in points {
+        if !ring.last().is_some_and(|last| same_point(last, &p)) {
+            ring.push(p);
+        }
+    }
+    while ring.len() > 1 && same_point(&ring[0], &ring[ring.len() - 1]) {
+        ring.pop();
+    }
+    ring
+}
+
+/// Find the first pair of non-adjacent edges that intersect
+fn find_self_intersection(ring: &[Coordinates]) -> Option<(usize, usize)> {
+    let n = ring.len();
+    for i in 0..n {
+        let (a1, a2) = (&ring[i], &ring[(i + 1) % n]);
+        for j in (i + 1)..n {
+            // Adjacent edges share a vertex by construction
+            if j == i + 1 || (i == 0 && j == n - 1) {
+                continue;
+            }
+            let (b1, b2) = (&ring[j], &ring[(j + 1) % n]);
+            if segments_intersect(a1, a2, b1, b2) {
+                return Some((i, j));
+            }
+        }
+    }
+    None
+}
+
+/// Check whether any edge of one ring crosses or touches any edge of another
+fn rings_intersect(a: &[Coordinates], b: &[Coordinates]) -> bool {
+    let (n, m) = (a.len(), b.len());
+    (0..n)
+        .any(|i| (0..m).any(|j| segments_intersect(&a[i], &a[(i + 1) % n], &b[j], &b[(j + 1) % m])))
+}
+
+fn point_on_ring(p: &Coordinates, ring: &[Coordinates]) -> bool {
+    let n = ring.len();
+    (0..n).any(|i| point_on_segment(p, &ring[i], &ring[(i + 1) % n]))
+}
+
+fn segments_intersect(
+    p1: &Coordinates,
+    p2: &Coordinates,
+    q1: &Coordinates,
+    q2: &Coordinates,
+) -> bool {
+    let d1 = orientation(q1, q2, p1);
+    let d2 = orientation(q1, q2, p2);
+    let d3 = orientation(p1, p2, q1);
+    let d4 = orientation(p1, p2, q2);
+
+    if ((d1 > EPSILON && d2 < -EPSILON) || (d1 < -EPSILON && d2 > EPSILON))
+        && ((d3 > EPSILON && d4 < -EPSILON) || (d3 < -EPSILON && d4 > EPSILON))
+    {
+        return true;
+    }
+
+    // Collinear or touching cases
+    point_on_segment(p1, q1, q2)
+        || point_on_segment(p2, q1, q2)
+        || point_on_segment(q1, p1, p2)
+        || point_on_segment(q2, p1, p2)
+}
+
+/// Cross product of (b - a) and (c - a) in lng/lat space
+fn orientation(a: &Coordinates, b: &Coordinates, c: &Coordinates) -> f64 {
+    (b.longitude - a.longitude) * (c.latitude - a.latitude)
+        - (b.latitude - a.latitude) * (c.longitude - a.longitude)
+}
+
+fn point_on_segment(p: &Coordinates, a: &Coordinates, b: &Coordinates) -> bool {
+    if orientation(a, b, p).abs() > EPSILON {
+        return false;
+    }
+    p.longitude >= a.longitude.min(b.longitude) - EPSILON
+        && p.longitude <= a.longitude.max(b.longitude) + EPSILON
+        && p.latitude >= a.latitude.min(b.latitude) - EPSILON
+        && p.latitude <= a.latitude.max(b.latitude) + EPSILON
+}
+
+fn same_point(a: &Coordinates, b: &Coordinates) -> bool {
+    (a.latitude - b.latitude).abs() < EPSILON && (a.longitude - b.longitude).abs() < EPSILON
+}
+
+fn signed_planar_area(ring: &[Coordinates]) -> f64 {
+    let points: Vec<(f64, f64)> = ring.iter().map(|p| (p.longitude, p.latitude)).collect();
+    shoelace(&points)
+}
+
+fn shoelace(points: &[(f64, f64)]) -> f64 {
+    let n = points.len();
+    let mut sum = 0.0;
+    for i in 0..n {
+        let (x1, y1) = points[i];
+        let (x2, y2) = points[(i + 1) % n];
+        sum += x1 * y2 - x2 * y1;
+    }
+    sum / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pt(lat: f64, lng: f64) -> Coordinates {
+        Coordinates::new_unchecked(lat, lng)
+    }
+
+    fn square() -> Vec<Coordinates> {
+        vec![pt(0.0, 0.0), pt(0.0, 1.0), pt(1.0, 1.0), pt(1.0, 0.0)]
+    }
+
+    /// A "C" shape opening to the east; its notch is inside the bounding box
+    fn concave() -> Vec<Coordinates> {
+        vec![
+            pt(0.0, 0.0),
+            pt(0.0, 3.0),
+            pt(1.0, 3.0),
+            pt(1.0, 1.0),
+            pt(2.0, 1.0),
+            pt(2.0, 3.0),
+            pt(3.0, 3.0),
+            pt(3.0, 0.0),
+        ]
+    }
+
+    #[test]
+    fn test_point_inside_square() {
+        assert!(point_in_polygon(&pt(0.5, 0.5), &square()));
+        assert!(!point_in_polygon(&pt(1.5, 0.5), &square()));
+    }
+
+    #[test]
+    fn test_point_on_boundary_is_inside() {
+        assert!(point_in_polygon(&pt(0.0, 0.5), &square()));
+        assert!(point_in_polygon(&pt(1.0, 1.0), &square()));
+    }
+
+    #[test]
+    fn test_concave_notch_is_outside() {
+        let polygon = Polygon::new(concave()).unwrap();
+        // Inside the bounding box but within the notch
+        assert!(polygon.bounding_box().contains(&pt(1.5, 2.0)));
+        assert!(!polygon.contains(&pt(1.5, 2.0)));
+        // Inside the arms
+        assert!(polygon.contains(&pt(0.5, 2.0)));
+        assert!(polygon.contains(&pt(2.5, 2.0)));
+    }
+
+    #[test]
+    fn test_closed_ring_is_normalized() {
+        let mut ring = square();
+        ring.push(pt(0.0, 0.0));
+        let polygon = Polygon::new(ring).unwrap();
+        assert_eq!(polygon.vertices().len(), 4);
+        assert_eq!(polygon.closed_ring().len(), 5);
+    }
+
+    #[test]
+    fn test_too_few_vertices() {
+        let ring = vec![pt(0.0, 0.0), pt(0.0, 1.0), pt(0.0, 0.0)];
+        assert_eq!(Polygon::new(ring), Err(PolygonError::TooFewVertices(2)));
+    }
+
+    #[test]
+    fn test_self_intersecting_bowtie() {
+        let bowtie = vec![pt(0.0, 0.0), pt(1.0, 1.0), pt(1.0, 0.0), pt(0.0, 1.0)];
+        assert!(matches!(
+            Polygon::new(bowtie),
+            Err(PolygonError::SelfIntersecting(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_collinear_points_have_zero_area() {
+        let line = vec![pt(0.0, 0.0), pt(1.0, 1.0), pt(2.0, 2.0)];
+        assert_eq!(Polygon::new(line), Err(PolygonError::ZeroArea));
+    }
+
+    #[test]
+    fn test_invalid_coordinates() {
+        let ring = vec![pt(0.0, 0.0), pt(95.0, 1.0), pt(1.0, 0.0)];
+        assert!(matches!(
+            Polygon::new(ring),
+            Err(PolygonError::InvalidCoordinates { index: 1, .. })
+        ));
+    }
+
+    fn hole() -> Vec<Coordinates> {
+        vec![
+            pt(0.25, 0.25),
+            pt(0.25, 0.75),
+            pt(0.75, 0.75),
+            pt(0.75, 0.25),
+        ]
+    }
+
+    #[test]
+    fn test_point_in_hole_is_outside() {
+        let polygon = Polygon::with_holes(square(), vec![hole()]).unwrap();
+        assert!(!polygon.contains(&pt(0.5, 0.5)));
+        assert!(polygon.contains(&pt(0.1, 0.1)));
+        // The hole's edge is part of the polygon boundary
+        assert!(polygon.contains(&pt(0.25, 0.5)));
+    }
+
+    #[test]
+    fn test_hole_reduces_area() {
+        let polygon = Polygon::with_holes(square(), vec![hole()]).unwrap();
+        let expected = polygon_area_km2(&square()) * 0.75;
+        assert!((polygon.area_km2() - expected).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_hole_outside_exterior_is_rejected() {
+        let outside = vec![pt(2.0, 2.0), pt(2.0, 3.0), pt(3.0, 3.0)];
+        assert_eq!(
+            Polygon::with_holes(square(), vec![outside]),
+            Err(PolygonError::HoleOutsideExterior(0))
+        );
+    }
+
+    #[test]
+    fn test_hole_crossing_exterior_is_rejected() {
+        let crossing = vec![pt(0.5, 0.5), pt(0.5, 1.5), pt(0.8, 1.5), pt(0.8, 0.5)];
+        assert!(Polygon::with_holes(square(), vec![crossing]).is_err());
+    }
+
+    #[test]
+    fn test_area_of_one_degree_square_at_equator() {
+        // One degree is ~111.19 km at the equator
+        let area = polygon_area_km2(&square());
+        assert!(area > 12_300.0 && area < 12_400.0, "area was {}", area);
+    }
+
+    #[test]
+    fn test_area_is_orientation_independent() {
+        let mut reversed = square();
+        reversed.reverse();
+        let a = polygon_area_km2(&square());
+        let b = polygon_area_km2(&reversed);
+        assert!((a - b).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,114 @@
+//! Polygon simplification to a vertex budget.
+
+use super::{Polygon, MIN_POLYGON_VERTICES};
+use crate::types::Coordinates;
+
+impl Polygon {
+    /// Simplify the polygon so it has at most `max_vertices` vertices in
+    /// total, distributing the budget across rings by their size.
+    ///
+    /// Every ring keeps at least three vertices, so a polygon with many holes
+    /// may still exceed a very small budget. The result is not re-validated;
+    /// removing vertices can occasionally introduce self-intersections.
+    pub fn simplified(&self, max_vertices: usize) -> Polygon {
+        let total = self.vertex_count();
+        if total <= max_vertices {
+            return self.clone();
+        }
+
+        let budget =
+            |ring: &[Coordinates]| (ring.len() * max_vertices / total).max(MIN_POLYGON_VERTICES);
+
+        Polygon::with_holes_unchecked(
+            simplify_ring(self.vertices(), budget(self.vertices())),
+            self.holes()
+                .iter()
+                .map(|hole| simplify_ring(hole, budget(hole)))
+                .collect(),
+        )
+    }
+}
+
+/// Simplify an open ring to at most `max_vertices` vertices using the
+/// Visvalingam-Whyatt algorithm: repeatedly drop the vertex whose triangle
+/// with its neighbours has the smallest area.
+pub fn simplify_ring(ring: &[Coordinates], max_vertices: usize) -> Vec<Coordinates> {
+    let target = max_vertices.max(MIN_POLYGON_VERTICES);
+    let mut points = ring.to_vec();
+
+    while points.len() > target {
+        let n = points.len();
+        let (index, _) = (0..n)
+            .map(|i| {
+                let prev = &points[(i + n - 1) % n];
+                let next = &points[(i + 1) % n];
+                (i, triangle_area(prev, &points[i], next))
+            })
+            .fold((0, f64::INFINITY), |best, candidate| {
+                if candidate.1 < best.1 {
+                    candidate
+                } else {
+                    best
+                }
+            });
+        points.remove(index);
+    }
+
+    points
+}
+
+fn triangle_area(a: &Coordinates, b: &Coordinates, c: &Coordinates) -> f64 {
+    ((b.longitude - a.longitude) * (c.latitude - a.latitude)
+        - (c.longitude - a.longitude) * (b.latitude - a.latitude))
+        .abs()
+        / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn circle(n: usize) -> Vec<Coordinates> {
+        (0..n)
+            .map(|i| {
+                let theta = i as f64 * std::f64::consts::TAU / n as f64;
+                Coordinates::new_unchecked(theta.sin(), theta.cos())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_simplify_ring_respects_limit() {
+        let simplified = simplify_ring(&circle(100), 12);
+        assert_eq!(simplified.len(), 12);
+    }
+
+    #[test]
+    fn test_simplify_drops_collinear_points_first() {
+        let ring = vec![
+            Coordinates::new_unchecked(0.0, 0.0),
+            Coordinates::new_unchecked(0.0, 0.5),
+            Coordinates::new_unchecked(0.0, 1.0),
+            Coordinates::new_unchecked(1.0, 1.0),
+            Coordinates::new_unchecked(1.0, 0.0),
+        ];
+        let simplified = simplify_ring(&ring, 4);
+        assert!(!simplified.contains(&Coordinates::new_unchecked(0.0, 0.5)));
+    }
+
+    #[test]
+    fn test_simplified_polygon_stays_valid() {
+        let polygon = Polygon::new(circle(500)).unwrap();
+        let simplified = polygon.simplified(50);
+        assert!(simplified.vertex_count() <= 50);
+        assert!(simplified.validate().is_ok());
+        // A 50-gon keeps nearly all of the circle's area
+        assert!(simplified.area_km2() > polygon.area_km2() * 0.99);
+    }
+
+    #[test]
+    fn test_small_polygon_is_unchanged() {
+        let polygon = Polygon::new(circle(8)).unwrap();
+        assert_eq!(polygon.simplified(100), polygon);
+    }
+}
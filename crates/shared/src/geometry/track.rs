@@ -0,0 +1,364 @@
+//! GPS track cleanup and summary statistics for walk breadcrumbs.
+//!
+//! Raw phone fixes are noisy: the first few readings are often inaccurate,
+//! and the occasional fix jumps hundreds of metres. Tracks are cleaned by
+//! dropping inaccurate and physically impossible fixes, smoothed with a small
+//! moving average, and downsampled with Douglas-Peucker before display.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::types::Coordinates;
+
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// A single timestamped GPS fix
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TrackPoint {
+    pub coordinates: Coordinates,
+    pub recorded_at: DateTime<Utc>,
+    pub accuracy_meters: Option<f64>,
+}
+
+/// Tuning for [`process_track`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackFilter {
+    /// Fixes reporting a worse accuracy than this are dropped
+    pub max_accuracy_meters: f64,
+    /// Fixes implying a faster speed than this from the previous kept fix are
+    /// dropped as GPS jumps
+    pub max_speed_mps: f64,
+    /// Number of fixes on each side averaged when smoothing (0 disables)
+    pub smoothing_radius: usize,
+    /// Douglas-Peucker tolerance; vertices closer than this to the simplified
+    /// line are removed
+    pub simplify_tolerance_meters: f64,
+}
+
+impl Default for TrackFilter {
+    fn default() -> Self {
+        Self {
+            max_accuracy_meters: 50.0,
+            // Generous for a walk, but tolerates a dog breaking into a run
+            max_speed_mps: 8.0,
+            smoothing_radius: 2,
+            simplify_tolerance_meters: 5.0,
+        }
+    }
+}
+
+/// Distance and duration of a track
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TrackStats {
+    pub distance_meters: f64,
+    pub duration_seconds: i64,
+    pub started_at: Option<DateTime<Utc>>,
+    pub ended_at: Option<DateTime<Utc>>,
+    /// Average speed over the whole track, if it has a non-zero duration
+    pub average_speed_mps: Option<f64>,
+}
+
+/// Clean, smooth and downsample a raw track.
+///
+/// Points are sorted by time first, so fixes uploaded out of order are
+/// handled. Statistics should be computed on the result, since the raw track
+/// overstates distance with GPS jitter.
+pub fn process_track(points: &[TrackPoint], filter: &TrackFilter) -> Vec<TrackPoint> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by_key(|p| p.recorded_at);
+
+    let cleaned = reject_outliers(&sorted, filter);
+    let smoothed = smooth_track(&cleaned, filter.smoothing_radius);
+    downsample_track(&smoothed, filter.simplify_tolerance_meters)
+}
+
+/// Drop inaccurate fixes and fixes that imply an impossible speed from the
+/// previously kept fix. Expects points sorted by time.
+pub fn reject_outliers(points: &[TrackPoint], filter: &TrackFilter) -> Vec<TrackPoint> {
+    let mut kept: Vec<TrackPoint> = Vec::with_capacity(points.len());
+
+    for point in points {
+        if point
+            .accuracy_meters
+            .is_some_and(|accuracy| accuracy > filter.max_accuracy_meters)
+        {
+            continue;
+        }
+
+        if let Some(last) = kept.last() {
+            let meters = last.coordinates.distance_km(&point.coordinates) * 1000.0;
+            let seconds = (point.recorded_at - last.recorded_at).num_milliseconds() as f64 / 1000.0;
+            // Duplicate timestamps can't be judged by speed; keep only if stationary
+            let too_fast = if seconds <= 0.0 {
+                meters > filter.max_accuracy_meters
+            } else {
+                meters / seconds > filter.max_speed_mps
+            };
+            if too_fast {
+                continue;
+            }
+        }
+
+        kept.push(*point);
+    }
+
+    kept
+}
+
+/// Smooth a track with a centred moving average of `radius` points on each
+/// side. The window shrinks at the ends so the first and last fixes stay put.
+pub fn smooth_track(points: &[TrackPoint], radius: usize) -> Vec<TrackPoint> {
+    if radius == 0 || points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let last = points.len() - 1;
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, point)| {
+            let r = radius.min(i).min(last - i);
+            let window = &points[i - r..=i + r];
+            let n = window.len() as f64;
+            let (lat, lng) = window.iter().fold((0.0, 0.0), |(lat, lng), p| {
+                (lat + p.coordinates.latitude, lng + p.coordinates.longitude)
+            });
+            TrackPoint {
+                coordinates: Coordinates::new_unchecked(lat / n, lng / n),
+                ..*point
+            }
+        })
+        .collect()
+}
+
+/// Downsample a track with the Douglas-Peucker algorithm, keeping the
+/// endpoints and every vertex further than `tolerance_meters` from the
+/// simplified line.
+pub fn downsample_track(points: &[TrackPoint], tolerance_meters: f64) -> Vec<TrackPoint> {
+    if points.len() < 3 || tolerance_meters <= 0.0 {
+        return points.to_vec();
+    }
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((start, end)) = stack.pop() {
+        if end <= start + 1 {
+            continue;
+        }
+
+        let (index, distance) = (start + 1..end)
+            .map(|i| {
+                let d = segment_distance_meters(
+                    &points[i].coordinates,
+                    &points[start].coordinates,
+                    &points[end].coordinates,
+                );
+                (i, d)
+            })
+            .fold((start, 0.0), |best, candidate| {
+                if candidate.1 > best.1 {
+                    candidate
+                } else {
+                    best
+                }
+            });
+
+        if distance > tolerance_meters {
+            keep[index] = true;
+            stack.push((start, index));
+            stack.push((index, end));
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(point, keep)| keep.then_some(*point))
+        .collect()
+}
+
+/// Total distance and elapsed time of a track. Expects points sorted by time.
+pub fn track_stats(points: &[TrackPoint]) -> TrackStats {
+    let distance_meters: f64 = points
+        .windows(2)
+        .map(|pair| pair[0].coordinates.distance_km(&pair[1].coordinates) * 1000.0)
+        .sum();
+
+    let started_at = points.first().map(|p| p.recorded_at);
+    let ended_at = points.last().map(|p| p.recorded_at);
+    let duration_seconds = match (started_at, ended_at) {
+        (Some(start), Some(end)) => (end - start).num_seconds().max(0),
+        _ => 0,
+    };
+
+    TrackStats {
+        distance_meters,
+        duration_seconds,
+        started_at,
+        ended_at,
+        average_speed_mps: (duration_seconds > 0)
+            .then(|| distance_meters / duration_seconds as f64),
+    }
+}
+
+/// Convert a track to a GeoJSON LineString Feature with the given properties.
+///
+/// A track with fewer than two points has no line, so the geometry is null.
+pub fn track_to_geojson(points: &[TrackPoint], properties: Map<String, Value>) -> Value {
+    let geometry = if points.len() < 2 {
+        Value::Null
+    } else {
+        json!({
+            "type": "LineString",
+            "coordinates": points
+                .iter()
+                .map(|p| json!([p.coordinates.longitude, p.coordinates.latitude]))
+                .collect::<Vec<_>>(),
+        })
+    };
+
+    json!({
+        "type": "Feature",
+        "geometry": geometry,
+        "properties": properties,
+    })
+}
+
+/// Distance in metres from `p` to the segment `a`-`b`, on a local
+/// equirectangular projection (accurate over walk-sized distances)
+fn segment_distance_meters(p: &Coordinates, a: &Coordinates, b: &Coordinates) -> f64 {
+    let scale = a.latitude.to_radians().cos();
+    let project = |c: &Coordinates| {
+        (
+            (c.longitude - a.longitude) * scale * METERS_PER_DEGREE,
+            (c.latitude - a.latitude) * METERS_PER_DEGREE,
+        )
+    };
+
+    let (px, py) = project(p);
+    let (bx, by) = project(b);
+    let length_squared = bx * bx + by * by;
+    if length_squared == 0.0 {
+        return (px * px + py * py).sqrt();
+    }
+
+    let t = ((px * bx + py * by) / length_squared).clamp(0.0, 1.0);
+    let (dx, dy) = (px - t * bx, py - t * by);
+    (dx * dx + dy * dy).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn start() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 1, 9, 0, 0).unwrap()
+    }
+
+    /// A point `seconds` into the walk, `north_m` metres north of the origin
+    fn point(seconds: i64, north_m: f64, east_m: f64) -> TrackPoint {
+        TrackPoint {
+            coordinates: Coordinates::new_unchecked(
+                40.0 + north_m / METERS_PER_DEGREE,
+                -74.0 + east_m / (METERS_PER_DEGREE * 40.0_f64.to_radians().cos()),
+            ),
+            recorded_at: start() + Duration::seconds(seconds),
+            accuracy_meters: Some(5.0),
+        }
+    }
+
+    #[test]
+    fn test_rejects_inaccurate_and_jumping_fixes() {
+        let mut inaccurate = point(10, 15.0, 0.0);
+        inaccurate.accuracy_meters = Some(200.0);
+        let points = vec![
+            point(0, 0.0, 0.0),
+            inaccurate,
+            point(20, 30.0, 0.0),
+            // 1km in 10 seconds
+            point(30, 1030.0, 0.0),
+            point(40, 60.0, 0.0),
+        ];
+
+        let kept = reject_outliers(&points, &TrackFilter::default());
+        assert_eq!(kept.len(), 3);
+        assert_eq!(kept[2].recorded_at, start() + Duration::seconds(40));
+    }
+
+    #[test]
+    fn test_smoothing_keeps_endpoints() {
+        let points = vec![
+            point(0, 0.0, 0.0),
+            point(10, 10.0, 4.0),
+            point(20, 20.0, -4.0),
+            point(30, 30.0, 4.0),
+            point(40, 40.0, 0.0),
+        ];
+
+        let smoothed = smooth_track(&points, 1);
+        assert_eq!(smoothed.len(), points.len());
+        assert_eq!(smoothed[0], points[0]);
+        assert_eq!(smoothed[4], points[4]);
+        // The zig-zag is pulled towards the centre line
+        let offset = |p: &TrackPoint| (p.coordinates.longitude + 74.0).abs();
+        assert!(offset(&smoothed[2]) < offset(&points[2]));
+    }
+
+    #[test]
+    fn test_downsample_drops_straight_line_points() {
+        let mut points: Vec<_> = (0..=10)
+            .map(|i| point(i * 10, i as f64 * 10.0, 0.0))
+            .collect();
+        // A corner that must survive
+        points.push(point(110, 100.0, 50.0));
+
+        let simplified = downsample_track(&points, 1.0);
+        assert_eq!(simplified.len(), 3);
+        assert_eq!(simplified[0], points[0]);
+        assert_eq!(simplified[1], points[10]);
+        assert_eq!(simplified[2], points[11]);
+    }
+
+    #[test]
+    fn test_track_stats() {
+        let points = vec![
+            point(0, 0.0, 0.0),
+            point(60, 100.0, 0.0),
+            point(120, 100.0, 100.0),
+        ];
+
+        let stats = track_stats(&points);
+        assert!((stats.distance_meters - 200.0).abs() < 1.0);
+        assert_eq!(stats.duration_seconds, 120);
+        assert!((stats.average_speed_mps.unwrap() - 200.0 / 120.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_empty_track() {
+        let processed = process_track(&[], &TrackFilter::default());
+        let stats = track_stats(&processed);
+        assert_eq!(stats.distance_meters, 0.0);
+        assert_eq!(stats.started_at, None);
+        assert_eq!(stats.average_speed_mps, None);
+        assert!(track_to_geojson(&processed, Map::new())["geometry"].is_null());
+    }
+
+    #[test]
+    fn test_process_sorts_out_of_order_fixes() {
+        let points = vec![
+            point(20, 20.0, 0.0),
+            point(0, 0.0, 0.0),
+            point(10, 10.0, 0.0),
+        ];
+
+        let processed = process_track(&points, &TrackFilter::default());
+        assert_eq!(processed.first().unwrap().recorded_at, start());
+        let geojson = track_to_geojson(&processed, Map::new());
+        assert_eq!(geojson["geometry"]["type"], "LineString");
+    }
+}
@@ -1,4 +1,5 @@
 pub mod errors;
+pub mod geometry;
 pub mod types;
 
 pub use errors::{AppError, DomainError};
@@ -1,19 +1,129 @@
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
 use serde::{Deserialize, Serialize};
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::postgres::{PgArgumentBuffer, PgTypeInfo, PgValueRef};
+use sqlx::{Decode, Encode, Postgres, Type};
 use std::fmt;
-use std::ops::{Add, Sub};
+use std::str::FromStr;
+use thiserror::Error;
 
-/// Currency enum (USD only for MVP)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+/// ISO 4217 currencies an organization can charge in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum Currency {
     #[default]
     USD,
+    CAD,
+    GBP,
+    EUR,
+}
+
+impl Currency {
+    /// Every supported currency
+    pub const ALL: [Currency; 4] = [Currency::USD, Currency::CAD, Currency::GBP, Currency::EUR];
+
+    /// ISO 4217 code, e.g. "USD"
+    pub fn code(&self) -> &'static str {
+        match self {
+            Currency::USD => "USD",
+            Currency::CAD => "CAD",
+            Currency::GBP => "GBP",
+            Currency::EUR => "EUR",
+        }
+    }
+
+    /// Number of decimal places in the currency's minor unit (2 for cents)
+    pub fn minor_unit_exponent(&self) -> u32 {
+        match self {
+            Currency::USD | Currency::CAD | Currency::GBP | Currency::EUR => 2,
+        }
+    }
+
+    /// Symbol used when displaying amounts
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Currency::USD => "$",
+            Currency::CAD => "CA$",
+            Currency::GBP => "£",
+            Currency::EUR => "€",
+        }
+    }
 }
 
 impl fmt::Display for Currency {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// A currency code we don't support
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Unsupported currency: {0}")]
+pub struct UnknownCurrency(pub String);
+
+impl FromStr for Currency {
+    type Err = UnknownCurrency;
+
+    /// Parse an ISO 4217 code in either case, as Stripe sends lowercase codes
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Currency::ALL
+            .into_iter()
+            .find(|c| c.code().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| UnknownCurrency(s.to_string()))
+    }
+}
+
+// Stored as the ISO code in VARCHAR(3) columns
+impl Type<Postgres> for Currency {
+    fn type_info() -> PgTypeInfo {
+        <&str as Type<Postgres>>::type_info()
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        <&str as Type<Postgres>>::compatible(ty)
+    }
+}
+
+impl Encode<'_, Postgres> for Currency {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> IsNull {
+        <&str as Encode<Postgres>>::encode(self.code(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for Currency {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        let code = <&str as Decode<Postgres>>::decode(value)?;
+        Ok(code.parse()?)
+    }
+}
+
+/// Why arithmetic on two amounts failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum MoneyError {
+    #[error("Cannot combine {0} and {1} amounts")]
+    CurrencyMismatch(Currency, Currency),
+    #[error("Amount out of range")]
+    Overflow,
+}
+
+/// How to round a fractional amount to the currency's minor unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    /// Round halves to the nearest even digit, so rounding errors cancel out
+    /// across many amounts
+    Bankers,
+    /// Round halves away from zero
+    HalfUp,
+}
+
+impl RoundingMode {
+    fn strategy(self) -> RoundingStrategy {
         match self {
-            Currency::USD => write!(f, "USD"),
+            RoundingMode::Bankers => RoundingStrategy::MidpointNearestEven,
+            RoundingMode::HalfUp => RoundingStrategy::MidpointAwayFromZero,
         }
     }
 }
@@ -26,12 +136,14 @@ pub struct Money {
 }
 
 impl Money {
+    /// Create money from an amount in the currency's minor unit
+    pub fn new(cents: i64, currency: Currency) -> Self {
+        Self { cents, currency }
+    }
+
     /// Create money from cents
     pub fn from_cents(cents: i64) -> Self {
-        Self {
-            cents,
-            currency: Currency::USD,
-        }
+        Self::new(cents, Currency::USD)
     }
 
     /// Create money from dollars (converts to cents)
@@ -42,6 +154,22 @@ impl Money {
         }
     }
 
+    /// Create money from an amount in major units (e.g. dollars), rounding to
+    /// the currency's minor unit. Fails if the rounded amount does not fit in
+    /// `i64` minor units.
+    pub fn from_decimal(
+        amount: Decimal,
+        currency: Currency,
+        rounding: RoundingMode,
+    ) -> Result<Self, MoneyError> {
+        let scale = Decimal::from(10_i64.pow(currency.minor_unit_exponent()));
+        let cents = amount
+            .checked_mul(scale)
+            .and_then(|minor| round_to_minor_units(minor, rounding))
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Self::new(cents, currency))
+    }
+
     /// Get the amount in cents
     pub fn cents(&self) -> i64 {
         self.cents
@@ -52,6 +180,11 @@ impl Money {
         self.cents as f64 / 100.0
     }
 
+    /// Get the amount in major units as an exact decimal
+    pub fn to_decimal(&self) -> Decimal {
+        Decimal::new(self.cents, self.currency.minor_unit_exponent())
+    }
+
     /// Get the currency
     pub fn currency(&self) -> Currency {
         self.currency
@@ -71,53 +204,88 @@ impl Money {
     pub fn zero() -> Self {
         Self::from_cents(0)
     }
-}
 
-impl Default for Money {
-    fn default() -> Self {
-        Self::zero()
+    /// Zero in the given currency
+    pub fn zero_in(currency: Currency) -> Self {
+        Self::new(0, currency)
     }
-}
 
-impl Add for Money {
-    type Output = Self;
+    /// Multiply by a rate (e.g. `0.15` for 15%), rounding to the minor unit.
+    /// Fails if the result does not fit in `i64` minor units.
+    pub fn multiply(&self, rate: Decimal, rounding: RoundingMode) -> Result<Self, MoneyError> {
+        let cents = Decimal::from(self.cents)
+            .checked_mul(rate)
+            .and_then(|product| round_to_minor_units(product, rounding))
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Self::new(cents, self.currency))
+    }
 
-    fn add(self, other: Self) -> Self {
-        assert_eq!(
-            self.currency, other.currency,
-            "Cannot add different currencies"
-        );
-        Self {
-            cents: self.cents + other.cents,
-            currency: self.currency,
+    /// Take a percentage (e.g. `7.25` for 7.25%), rounding to the minor unit.
+    /// Fails if the result does not fit in `i64` minor units.
+    pub fn percent(&self, percent: Decimal, rounding: RoundingMode) -> Result<Self, MoneyError> {
+        self.multiply(percent / Decimal::ONE_HUNDRED, rounding)
+    }
+
+    /// Add an amount in the same currency.
+    ///
+    /// Amounts in different currencies can't be combined, so there is no
+    /// `+` for `Money`; callers have to handle the mismatch.
+    pub fn checked_add(self, other: Self) -> Result<Self, MoneyError> {
+        self.same_currency(other)?;
+        let cents = self
+            .cents
+            .checked_add(other.cents)
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Self::new(cents, self.currency))
+    }
+
+    /// Subtract an amount in the same currency
+    pub fn checked_sub(self, other: Self) -> Result<Self, MoneyError> {
+        self.same_currency(other)?;
+        let cents = self
+            .cents
+            .checked_sub(other.cents)
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Self::new(cents, self.currency))
+    }
+
+    fn same_currency(&self, other: Self) -> Result<(), MoneyError> {
+        if self.currency == other.currency {
+            Ok(())
+        } else {
+            Err(MoneyError::CurrencyMismatch(self.currency, other.currency))
         }
     }
 }
 
-impl Sub for Money {
-    type Output = Self;
+fn round_to_minor_units(amount: Decimal, rounding: RoundingMode) -> Option<i64> {
+    amount
+        .round_dp_with_strategy(0, rounding.strategy())
+        .to_i64()
+}
 
-    fn sub(self, other: Self) -> Self {
-        assert_eq!(
-            self.currency, other.currency,
-            "Cannot subtract different currencies"
-        );
-        Self {
-            cents: self.cents - other.cents,
-            currency: self.currency,
-        }
+impl Default for Money {
+    fn default() -> Self {
+        Self::zero()
     }
 }
 
 impl fmt::Display for Money {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "${:.2}", self.dollars())
+        write!(
+            f,
+            "{}{:.*}",
+            self.currency.symbol(),
+            self.currency.minor_unit_exponent() as usize,
+            self.to_decimal()
+        )
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_money_from_cents() {
@@ -137,7 +305,7 @@ mod tests {
     fn test_money_add() {
         let a = Money::from_cents(1000);
         let b = Money::from_cents(500);
-        let sum = a + b;
+        let sum = a.checked_add(b).unwrap();
         assert_eq!(sum.cents(), 1500);
     }
 
@@ -145,7 +313,7 @@ mod tests {
     fn test_money_sub() {
         let a = Money::from_cents(1000);
         let b = Money::from_cents(400);
-        let diff = a - b;
+        let diff = a.checked_sub(b).unwrap();
         assert_eq!(diff.cents(), 600);
     }
 
@@ -160,4 +328,117 @@ mod tests {
         let money = Money::from_dollars(10.999);
         assert_eq!(money.cents(), 1100); // Rounds to nearest cent
     }
+
+    #[test]
+    fn test_money_decimal_round_trip() {
+        let money =
+            Money::from_decimal(Decimal::new(12345, 3), Currency::USD, RoundingMode::HalfUp)
+                .unwrap();
+        assert_eq!(money.cents(), 1235);
+        assert_eq!(money.to_decimal(), Decimal::new(1235, 2));
+    }
+
+    #[test]
+    fn test_money_rounding_modes() {
+        // 50 * 0.05 = 2.5 cents
+        let rate = Decimal::new(5, 2);
+        let cents = |cents, rounding| {
+            Money::from_cents(cents)
+                .multiply(rate, rounding)
+                .unwrap()
+                .cents()
+        };
+        assert_eq!(cents(50, RoundingMode::HalfUp), 3);
+        assert_eq!(cents(50, RoundingMode::Bankers), 2);
+
+        // 70 * 0.05 = 3.5 cents rounds to the even 4 either way
+        assert_eq!(cents(70, RoundingMode::HalfUp), 4);
+        assert_eq!(cents(70, RoundingMode::Bankers), 4);
+    }
+
+    #[test]
+    fn test_money_percent() {
+        let money = Money::from_cents(10_000);
+        let tax = money.percent(Decimal::new(725, 2), RoundingMode::HalfUp);
+        assert_eq!(tax, Ok(Money::from_cents(725)));
+    }
+
+    #[test]
+    fn test_money_checked_arithmetic() {
+        let a = Money::from_cents(i64::MAX);
+        assert_eq!(
+            a.checked_add(Money::from_cents(1)),
+            Err(MoneyError::Overflow)
+        );
+        assert_eq!(
+            Money::from_cents(10).checked_sub(Money::from_cents(4)),
+            Ok(Money::from_cents(6))
+        );
+    }
+
+    #[test]
+    fn test_money_out_of_range() {
+        assert_eq!(
+            Money::from_cents(i64::MAX).percent(Decimal::new(200, 0), RoundingMode::HalfUp),
+            Err(MoneyError::Overflow)
+        );
+        assert_eq!(
+            Money::from_cents(1).multiply(Decimal::MAX, RoundingMode::HalfUp),
+            Err(MoneyError::Overflow)
+        );
+        assert_eq!(
+            Money::from_decimal(Decimal::MAX, Currency::USD, RoundingMode::Bankers),
+            Err(MoneyError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_money_rejects_mixed_currencies() {
+        let usd = Money::from_cents(1000);
+        let gbp = Money::new(1000, Currency::GBP);
+        assert_eq!(
+            usd.checked_add(gbp),
+            Err(MoneyError::CurrencyMismatch(Currency::USD, Currency::GBP))
+        );
+        assert!(usd.checked_sub(gbp).is_err());
+    }
+
+    #[test]
+    fn test_currency_codes() {
+        assert_eq!("cad".parse::<Currency>(), Ok(Currency::CAD));
+        assert_eq!("EUR".parse::<Currency>(), Ok(Currency::EUR));
+        assert!("JPY".parse::<Currency>().is_err());
+        for currency in Currency::ALL {
+            assert_eq!(currency.code().parse::<Currency>(), Ok(currency));
+        }
+    }
+
+    #[test]
+    fn test_money_display_in_currency() {
+        assert_eq!(Money::new(1250, Currency::GBP).to_string(), "£12.50");
+        assert_eq!(Money::new(-500, Currency::EUR).to_string(), "€-5.00");
+    }
+
+    proptest! {
+        #[test]
+        fn prop_rounding_is_within_half_a_cent(
+            cents in 0i64..10_000_000,
+            basis_points in 0i64..10_000,
+        ) {
+            let rate = Decimal::new(basis_points, 4);
+            let exact = Decimal::from(cents) * rate;
+            for rounding in [RoundingMode::Bankers, RoundingMode::HalfUp] {
+                let rounded = Money::from_cents(cents).multiply(rate, rounding).unwrap();
+                let error = (Decimal::from(rounded.cents()) - exact).abs();
+                prop_assert!(error <= Decimal::new(5, 1));
+            }
+        }
+
+        #[test]
+        fn prop_decimal_round_trip(cents in -1_000_000_000i64..1_000_000_000) {
+            let money = Money::from_cents(cents);
+            let back = Money::from_decimal(money.to_decimal(), money.currency(), RoundingMode::Bankers);
+            prop_assert_eq!(back, Ok(money));
+        }
+    }
 }
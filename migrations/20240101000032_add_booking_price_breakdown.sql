@@ -0,0 +1,5 @@
+-- Booking price breakdown
+-- Captures how a booking's price was derived (base service price plus any
+-- service area adjustment) so later price changes don't alter past bookings
+
+ALTER TABLE bookings ADD COLUMN IF NOT EXISTS price_breakdown JSONB;
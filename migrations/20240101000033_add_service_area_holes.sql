@@ -0,0 +1,6 @@
+-- Service area holes
+-- Interior rings excluded from a service area (e.g. a park or lake inside a
+-- neighbourhood). Same point format as polygon, one array per hole:
+-- [[{"lat": 39.74, "lng": -104.99}, ...], ...]
+
+ALTER TABLE service_areas ADD COLUMN IF NOT EXISTS holes JSONB NOT NULL DEFAULT '[]';
@@ -0,0 +1,22 @@
+-- GPS breadcrumbs recorded while a booking is in progress, so the walked
+-- route can be shown to the customer afterwards
+CREATE TABLE walk_breadcrumbs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    booking_id UUID NOT NULL REFERENCES bookings(id) ON DELETE CASCADE,
+    walker_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    latitude DOUBLE PRECISION NOT NULL,
+    longitude DOUBLE PRECISION NOT NULL,
+    accuracy_meters DOUBLE PRECISION,
+    speed_mps DOUBLE PRECISION,
+    recorded_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Trails are always read per booking in time order
+CREATE INDEX idx_walk_breadcrumbs_booking
+ON walk_breadcrumbs(booking_id, recorded_at);
+
+-- Retention purges delete by organization and age
+CREATE INDEX idx_walk_breadcrumbs_retention
+ON walk_breadcrumbs(organization_id, recorded_at);
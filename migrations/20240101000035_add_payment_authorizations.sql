@@ -0,0 +1,15 @@
+-- Manual-capture payments
+-- Funds are held when a booking is confirmed and captured after the walk, so
+-- transactions track the hold and when the provider will release it
+
+ALTER TYPE transaction_status ADD VALUE IF NOT EXISTS 'authorized';
+ALTER TYPE transaction_status ADD VALUE IF NOT EXISTS 'canceled';
+
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS authorized_at TIMESTAMPTZ;
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS authorization_expires_at TIMESTAMPTZ;
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS captured_at TIMESTAMPTZ;
+
+-- The re-authorization job looks for holds nearing expiry
+CREATE INDEX IF NOT EXISTS idx_transactions_authorization_expiry
+ON transactions(organization_id, authorization_expires_at)
+WHERE authorization_expires_at IS NOT NULL;
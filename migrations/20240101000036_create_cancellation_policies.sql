@@ -0,0 +1,20 @@
+-- Late cancellation and reschedule fee policies. A row without a service is
+-- the organization's default; a service-specific row overrides it.
+-- tiers: [{"within_minutes": 1440, "fee_percent": 50}, ...]
+CREATE TABLE cancellation_policies (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    service_id UUID REFERENCES services(id) ON DELETE CASCADE,
+    tiers JSONB NOT NULL DEFAULT '[]',
+    walker_cancels_free BOOLEAN NOT NULL DEFAULT TRUE,
+    charge_reschedules BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- One default policy per organization and one policy per service
+CREATE UNIQUE INDEX idx_cancellation_policies_scope
+ON cancellation_policies(
+    organization_id,
+    COALESCE(service_id, '00000000-0000-0000-0000-000000000000'::uuid)
+);
@@ -0,0 +1,47 @@
+-- One row per refund of a transaction, with how the refunded amount was
+-- split across the original charge so fees can be reversed accurately
+
+DO $$ BEGIN
+    CREATE TYPE refund_status AS ENUM ('pending', 'succeeded', 'failed', 'canceled');
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+DO $$ BEGIN
+    CREATE TYPE refund_source AS ENUM ('customer', 'admin', 'provider');
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+CREATE TABLE refunds (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    amount_cents INTEGER NOT NULL CHECK (amount_cents > 0),
+    currency VARCHAR(3) NOT NULL DEFAULT 'USD',
+    reason TEXT,
+    status refund_status NOT NULL DEFAULT 'pending',
+    source refund_source NOT NULL,
+    -- NULL for refunds issued from the provider's dashboard
+    initiated_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    provider_refund_id VARCHAR(255),
+
+    -- Portion of the refund taken from each part of the charge
+    subtotal_refunded_cents INTEGER NOT NULL DEFAULT 0,
+    tip_refunded_cents INTEGER NOT NULL DEFAULT 0,
+    customer_fee_refunded_cents INTEGER NOT NULL DEFAULT 0,
+    tax_refunded_cents INTEGER NOT NULL DEFAULT 0,
+    -- Fees given back by the platform (including the application fee) and
+    -- the amount clawed back from the walker
+    platform_fee_reversed_cents INTEGER NOT NULL DEFAULT 0,
+    provider_payout_reversed_cents INTEGER NOT NULL DEFAULT 0,
+
+    failure_message TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_refunds_transaction ON refunds(transaction_id, created_at);
+CREATE UNIQUE INDEX idx_refunds_provider_refund_id
+ON refunds(organization_id, provider_refund_id)
+WHERE provider_refund_id IS NOT NULL;
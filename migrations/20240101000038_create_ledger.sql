@@ -0,0 +1,67 @@
+-- Double-entry ledger. Every money movement is a journal entry whose lines
+-- debit and credit ledger accounts by the same total.
+
+DO $$ BEGIN
+    CREATE TYPE ledger_account_type AS ENUM (
+        'customer_receivable', 'walker_payable', 'platform_revenue', 'tax_payable',
+        'processor_fees', 'processor_balance'
+    );
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+DO $$ BEGIN
+    CREATE TYPE journal_entry_kind AS ENUM (
+        'charge', 'refund', 'dispute_opened', 'dispute_won', 'dispute_lost', 'payout',
+        'payout_failed'
+    );
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+-- Walker payable accounts are kept per walker; payouts to the organization
+-- as a whole use the account without a walker
+CREATE TABLE ledger_accounts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    account_type ledger_account_type NOT NULL,
+    walker_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    currency VARCHAR(3) NOT NULL DEFAULT 'USD',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_ledger_accounts_key
+ON ledger_accounts(
+    organization_id,
+    account_type,
+    COALESCE(walker_id, '00000000-0000-0000-0000-000000000000'::uuid),
+    currency
+);
+
+-- source_id is the transaction, refund, dispute or payout the entry records;
+-- an event is only ever posted once
+CREATE TABLE journal_entries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    kind journal_entry_kind NOT NULL,
+    source_id UUID NOT NULL,
+    transaction_id UUID REFERENCES transactions(id) ON DELETE SET NULL,
+    description TEXT NOT NULL,
+    currency VARCHAR(3) NOT NULL DEFAULT 'USD',
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (organization_id, kind, source_id)
+);
+
+CREATE TABLE journal_lines (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    journal_entry_id UUID NOT NULL REFERENCES journal_entries(id) ON DELETE CASCADE,
+    account_id UUID NOT NULL REFERENCES ledger_accounts(id) ON DELETE RESTRICT,
+    debit_cents BIGINT NOT NULL DEFAULT 0 CHECK (debit_cents >= 0),
+    credit_cents BIGINT NOT NULL DEFAULT 0 CHECK (credit_cents >= 0),
+    CHECK ((debit_cents = 0) <> (credit_cents = 0))
+);
+
+CREATE INDEX idx_journal_lines_entry ON journal_lines(journal_entry_id);
+CREATE INDEX idx_journal_lines_account ON journal_lines(account_id);
+CREATE INDEX idx_journal_entries_transaction ON journal_entries(transaction_id);
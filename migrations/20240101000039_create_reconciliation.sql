@@ -0,0 +1,63 @@
+-- Reconciliation of local transactions against the payment provider's records.
+-- Each run covers one provider and date range; mismatches are kept for admins
+-- to review and resolve.
+
+DO $$ BEGIN
+    CREATE TYPE reconciliation_status AS ENUM ('running', 'completed', 'failed');
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+DO $$ BEGIN
+    CREATE TYPE reconciliation_mismatch_kind AS ENUM (
+        'missing_locally', 'missing_remotely', 'amount_mismatch', 'status_mismatch',
+        'refund_mismatch'
+    );
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+CREATE TABLE reconciliation_runs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    provider_id UUID NOT NULL REFERENCES payment_providers(id) ON DELETE CASCADE,
+    period_start TIMESTAMPTZ NOT NULL,
+    period_end TIMESTAMPTZ NOT NULL,
+    status reconciliation_status NOT NULL DEFAULT 'running',
+    local_count INTEGER NOT NULL DEFAULT 0,
+    remote_count INTEGER NOT NULL DEFAULT 0,
+    matched_count INTEGER NOT NULL DEFAULT 0,
+    mismatch_count INTEGER NOT NULL DEFAULT 0,
+    error_message TEXT,
+    -- NULL for scheduled runs
+    requested_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ,
+    CHECK (period_end > period_start)
+);
+
+CREATE INDEX idx_reconciliation_runs_org ON reconciliation_runs(organization_id, started_at DESC);
+
+CREATE TABLE reconciliation_mismatches (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    run_id UUID NOT NULL REFERENCES reconciliation_runs(id) ON DELETE CASCADE,
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    kind reconciliation_mismatch_kind NOT NULL,
+    -- NULL when the provider has a payment we have no record of
+    transaction_id UUID REFERENCES transactions(id) ON DELETE SET NULL,
+    -- NULL for local transactions that never reached the provider
+    external_payment_id VARCHAR(255),
+    local_amount_cents BIGINT,
+    remote_amount_cents BIGINT,
+    local_status VARCHAR(50),
+    remote_status VARCHAR(50),
+    resolved_at TIMESTAMPTZ,
+    resolved_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    resolution_note TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_reconciliation_mismatches_run ON reconciliation_mismatches(run_id);
+CREATE INDEX idx_reconciliation_mismatches_open
+ON reconciliation_mismatches(organization_id, created_at DESC)
+WHERE resolved_at IS NULL;
@@ -0,0 +1,27 @@
+-- Multi-currency support: services are priced in a currency, and every
+-- currency column is limited to the currencies we can charge in
+
+ALTER TABLE services ADD COLUMN IF NOT EXISTS currency VARCHAR(3) NOT NULL DEFAULT 'USD';
+
+DO $$ BEGIN
+    ALTER TABLE services ADD CONSTRAINT services_currency_supported
+        CHECK (currency IN ('USD', 'CAD', 'GBP', 'EUR'));
+    ALTER TABLE transactions ADD CONSTRAINT transactions_currency_supported
+        CHECK (currency IN ('USD', 'CAD', 'GBP', 'EUR'));
+    ALTER TABLE refunds ADD CONSTRAINT refunds_currency_supported
+        CHECK (currency IN ('USD', 'CAD', 'GBP', 'EUR'));
+    ALTER TABLE disputes ADD CONSTRAINT disputes_currency_supported
+        CHECK (currency IN ('USD', 'CAD', 'GBP', 'EUR'));
+    ALTER TABLE payouts ADD CONSTRAINT payouts_currency_supported
+        CHECK (currency IN ('USD', 'CAD', 'GBP', 'EUR'));
+    ALTER TABLE ledger_accounts ADD CONSTRAINT ledger_accounts_currency_supported
+        CHECK (currency IN ('USD', 'CAD', 'GBP', 'EUR'));
+    ALTER TABLE journal_entries ADD CONSTRAINT journal_entries_currency_supported
+        CHECK (currency IN ('USD', 'CAD', 'GBP', 'EUR'));
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+-- Payout summaries and reports are broken down by currency
+CREATE INDEX IF NOT EXISTS idx_transactions_org_currency ON transactions(organization_id, currency);
+CREATE INDEX IF NOT EXISTS idx_payouts_org_currency ON payouts(organization_id, currency);
@@ -0,0 +1,36 @@
+-- Processor fee schedules: what each payment provider charges per payment
+-- method and card region. A row without a method or region applies to any
+-- method or region; the most specific matching row wins, and published list
+-- prices apply when nothing matches.
+
+DO $$ BEGIN
+    CREATE TYPE card_region AS ENUM ('domestic', 'international');
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+CREATE TABLE processing_fee_schedules (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    provider_type payment_provider_type NOT NULL,
+    method_type payment_method_type,
+    card_region card_region,
+    percent NUMERIC(6, 3) NOT NULL CHECK (percent >= 0 AND percent < 100),
+    fixed_cents BIGINT NOT NULL DEFAULT 0 CHECK (fixed_cents >= 0),
+    -- Most the processor charges for a single payment (e.g. ACH)
+    cap_cents BIGINT CHECK (cap_cents IS NULL OR cap_cents >= 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- One schedule per provider, method and region, counting "any" as a value
+CREATE UNIQUE INDEX idx_processing_fee_schedules_scope
+ON processing_fee_schedules(organization_id, provider_type, method_type, card_region)
+NULLS NOT DISTINCT;
+
+-- Country the card was issued in, to price international cards
+ALTER TABLE customer_payment_methods ADD COLUMN IF NOT EXISTS card_country VARCHAR(2);
+
+-- Whether processing_fee_cents is the fee the provider actually charged
+-- rather than our estimate from the fee schedule
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS processing_fee_reported BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,19 @@
+-- Sales tax from a tax provider: combined local rates can exceed 10%, and
+-- completed sales and refunds are reported to the provider for filing.
+
+ALTER TABLE transactions ALTER COLUMN tax_rate_percent TYPE NUMERIC(7, 4);
+
+-- When the sale was reported to the tax provider
+ALTER TABLE transactions ADD COLUMN IF NOT EXISTS tax_reported_at TIMESTAMPTZ;
+
+-- When the refund was reported to the tax provider
+ALTER TABLE refunds ADD COLUMN IF NOT EXISTS tax_reported_at TIMESTAMPTZ;
+
+-- Sales and refunds still to report
+CREATE INDEX IF NOT EXISTS idx_transactions_tax_unreported
+ON transactions(organization_id)
+WHERE tax_reported_at IS NULL AND tax_jurisdiction IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_refunds_tax_unreported
+ON refunds(organization_id)
+WHERE tax_reported_at IS NULL AND status = 'succeeded';
@@ -0,0 +1,93 @@
+-- Walker earnings: each walker's share of what the organization is paid,
+-- recorded per transaction, and the payouts that pay it to them.
+
+DO $$ BEGIN
+    CREATE TYPE revenue_split_type AS ENUM ('percent', 'flat');
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+DO $$ BEGIN
+    CREATE TYPE walker_earning_kind AS ENUM ('walk', 'tip', 'refund', 'dispute');
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+-- How much of a walk the walker earns. A row without a service applies to
+-- all of the walker's services; walkers without a split earn the whole
+-- service share. Tips always go to the walker in full.
+CREATE TABLE walker_revenue_splits (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    walker_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    service_id UUID REFERENCES services(id) ON DELETE CASCADE,
+    split_type revenue_split_type NOT NULL,
+    -- Share of the service price, for percent splits
+    percent NUMERIC(5, 2) CHECK (percent IS NULL OR (percent >= 0 AND percent <= 100)),
+    -- Amount per walk in the organization's currency, for flat splits
+    flat_cents INTEGER CHECK (flat_cents IS NULL OR flat_cents >= 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (
+        (split_type = 'percent' AND percent IS NOT NULL)
+        OR (split_type = 'flat' AND flat_cents IS NOT NULL)
+    )
+);
+
+CREATE UNIQUE INDEX idx_walker_revenue_splits_scope
+ON walker_revenue_splits(
+    organization_id,
+    walker_id,
+    COALESCE(service_id, '00000000-0000-0000-0000-000000000000'::uuid)
+);
+
+-- Stripe Connect accounts walkers are paid through
+CREATE TABLE walker_payout_accounts (
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    walker_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    stripe_account_id VARCHAR(255) NOT NULL,
+    payouts_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (organization_id, walker_id)
+);
+
+CREATE TABLE walker_payouts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    walker_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    amount_cents INTEGER NOT NULL DEFAULT 0,
+    currency VARCHAR(3) NOT NULL DEFAULT 'USD',
+    period_start TIMESTAMPTZ NOT NULL,
+    period_end TIMESTAMPTZ NOT NULL,
+    status payout_status NOT NULL DEFAULT 'pending',
+    stripe_transfer_id VARCHAR(255),
+    failure_message TEXT,
+    earning_count INTEGER NOT NULL DEFAULT 0,
+    initiated_at TIMESTAMPTZ,
+    completed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_walker_payouts_walker ON walker_payouts(organization_id, walker_id, created_at DESC);
+
+-- source_id is the transaction, refund or dispute the earning records; each
+-- is only recorded once. Refunds and lost disputes are negative.
+CREATE TABLE walker_earnings (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    walker_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    source_id UUID NOT NULL,
+    kind walker_earning_kind NOT NULL,
+    amount_cents INTEGER NOT NULL,
+    currency VARCHAR(3) NOT NULL DEFAULT 'USD',
+    walker_payout_id UUID REFERENCES walker_payouts(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (source_id, kind)
+);
+
+CREATE INDEX idx_walker_earnings_walker ON walker_earnings(organization_id, walker_id, created_at);
+CREATE INDEX idx_walker_earnings_unpaid ON walker_earnings(organization_id, walker_id)
+WHERE walker_payout_id IS NULL;
@@ -0,0 +1,12 @@
+-- Dispute evidence: the evidence assembled for a dispute, when it was sent to
+-- the provider, and which due-date reminder was last sent.
+
+ALTER TABLE disputes
+    ADD COLUMN IF NOT EXISTS evidence JSONB,
+    ADD COLUMN IF NOT EXISTS evidence_submitted_at TIMESTAMPTZ,
+    -- Days before the due date of the last reminder sent (7, 3 or 1)
+    ADD COLUMN IF NOT EXISTS last_reminder_days INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_disputes_evidence_due
+ON disputes(organization_id, evidence_due_by)
+WHERE status = 'needs_response' AND evidence_submitted = false;
@@ -0,0 +1,36 @@
+-- Durable webhook processing: events are stored when they arrive and
+-- processed by a worker, which retries failures with backoff and moves
+-- events that keep failing to a dead-letter state for review.
+
+DO $$ BEGIN
+    CREATE TYPE webhook_event_status AS ENUM ('pending', 'processing', 'processed', 'failed', 'dead');
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+ALTER TABLE payment_webhook_events
+    ADD COLUMN IF NOT EXISTS organization_id UUID REFERENCES organizations(id) ON DELETE CASCADE,
+    ADD COLUMN IF NOT EXISTS status webhook_event_status NOT NULL DEFAULT 'pending',
+    ADD COLUMN IF NOT EXISTS attempts INTEGER NOT NULL DEFAULT 0,
+    -- When a pending or failed event is next due to be processed
+    ADD COLUMN IF NOT EXISTS next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ADD COLUMN IF NOT EXISTS last_attempt_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+-- Events recorded before the worker existed were processed when they arrived
+UPDATE payment_webhook_events
+SET status = CASE
+        WHEN processed THEN 'processed'::webhook_event_status
+        ELSE 'dead'::webhook_event_status
+    END,
+    attempts = 1
+WHERE organization_id IS NULL;
+
+DROP INDEX IF EXISTS idx_webhook_events_unprocessed;
+
+CREATE INDEX IF NOT EXISTS idx_webhook_events_due
+ON payment_webhook_events(organization_id, next_attempt_at)
+WHERE status IN ('pending', 'processing', 'failed');
+
+CREATE INDEX IF NOT EXISTS idx_webhook_events_status
+ON payment_webhook_events(organization_id, status, created_at DESC);
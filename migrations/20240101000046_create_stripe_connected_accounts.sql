@@ -0,0 +1,24 @@
+-- The organization each Stripe Connect account belongs to, so events Stripe
+-- sends to the platform webhook can be routed to the right tenant. Lives in
+-- the main database; the accounts themselves are recorded in each tenant's.
+
+CREATE TABLE IF NOT EXISTS stripe_connected_accounts (
+    stripe_account_id VARCHAR(255) PRIMARY KEY,
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_stripe_connected_accounts_org
+ON stripe_connected_accounts(organization_id);
+
+-- Accounts connected before now, where they're in this database
+INSERT INTO stripe_connected_accounts (stripe_account_id, organization_id)
+SELECT stripe_account_id, organization_id
+FROM payment_providers
+WHERE stripe_account_id IS NOT NULL
+ON CONFLICT (stripe_account_id) DO NOTHING;
+
+INSERT INTO stripe_connected_accounts (stripe_account_id, organization_id)
+SELECT stripe_account_id, organization_id
+FROM walker_payout_accounts
+ON CONFLICT (stripe_account_id) DO NOTHING;
@@ -0,0 +1,32 @@
+-- Platform billing for tenants: the organization's plan is a Stripe Billing
+-- subscription on the platform account, kept in step by webhooks. A
+-- subscription whose payment fails is past due, and its paid features are
+-- restricted once the grace period runs out.
+
+ALTER TABLE tenant_subscriptions
+    -- When the subscription became past due, cleared once it's paid
+    ADD COLUMN IF NOT EXISTS past_due_since TIMESTAMPTZ,
+    -- When paid features were restricted for non-payment
+    ADD COLUMN IF NOT EXISTS restricted_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_tenant_subscriptions_stripe
+ON tenant_subscriptions(stripe_subscription_id)
+WHERE stripe_subscription_id IS NOT NULL;
+
+-- The organization each platform billing customer belongs to, so billing
+-- events Stripe sends to the platform can be routed to the right tenant.
+-- Lives in the main database, like stripe_connected_accounts.
+CREATE TABLE IF NOT EXISTS stripe_billing_customers (
+    stripe_customer_id VARCHAR(255) PRIMARY KEY,
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_stripe_billing_customers_org
+ON stripe_billing_customers(organization_id);
+
+INSERT INTO stripe_billing_customers (stripe_customer_id, organization_id)
+SELECT stripe_customer_id, organization_id
+FROM tenant_subscriptions
+WHERE stripe_customer_id IS NOT NULL
+ON CONFLICT (stripe_customer_id) DO NOTHING;
@@ -0,0 +1,59 @@
+-- Service packages: customer subscriptions billed each period that grant
+-- walk credits, which bookings draw down instead of being charged, and can
+-- schedule the period's walks on the customer's preferred day and time.
+
+ALTER TABLE customer_subscriptions
+    -- Walk credits granted each billing period; NULL for a plain recurring charge
+    ADD COLUMN IF NOT EXISTS walks_per_period INTEGER,
+    -- Walker who provides the package's walks and is credited with its payments
+    ADD COLUMN IF NOT EXISTS preferred_walker_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    -- Where scheduled walks take place
+    ADD COLUMN IF NOT EXISTS location_id UUID REFERENCES locations(id) ON DELETE SET NULL,
+    -- Timezone the preferred day and time are in
+    ADD COLUMN IF NOT EXISTS timezone VARCHAR(64) NOT NULL DEFAULT 'UTC',
+    -- Saved payment method each period is charged to
+    ADD COLUMN IF NOT EXISTS payment_method_id UUID REFERENCES customer_payment_methods(id) ON DELETE SET NULL,
+    -- Failed attempts to charge the next period, and when to try again
+    ADD COLUMN IF NOT EXISTS failed_payment_attempts INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS next_payment_attempt_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_customer_subscriptions_renewal
+ON customer_subscriptions(organization_id, current_period_end)
+WHERE status IN ('active', 'past_due');
+
+DO $$ BEGIN
+    CREATE TYPE package_credit_kind AS ENUM ('grant', 'redeem', 'restore', 'expire');
+EXCEPTION
+    WHEN duplicate_object THEN null;
+END $$;
+
+-- Walk credits granted, used and returned; a subscription's balance is the
+-- sum of its entries
+CREATE TABLE IF NOT EXISTS package_credits (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    subscription_id UUID NOT NULL REFERENCES customer_subscriptions(id) ON DELETE CASCADE,
+    kind package_credit_kind NOT NULL,
+    -- Positive when granted or restored, negative when redeemed or expired
+    credits INTEGER NOT NULL,
+    -- The payment that granted credits
+    transaction_id UUID REFERENCES transactions(id) ON DELETE SET NULL,
+    -- The booking a credit was redeemed for or restored from
+    booking_id UUID REFERENCES bookings(id) ON DELETE SET NULL,
+    -- End of the period credits were granted for, or expired at
+    period_end TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Each period is granted and expired once, and each booking redeems and
+-- restores at most one credit
+CREATE UNIQUE INDEX IF NOT EXISTS idx_package_credits_period
+ON package_credits(subscription_id, kind, period_end)
+WHERE kind IN ('grant', 'expire');
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_package_credits_booking
+ON package_credits(booking_id, kind)
+WHERE booking_id IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_package_credits_subscription
+ON package_credits(subscription_id, created_at DESC);
@@ -0,0 +1,9 @@
+-- Store walker fixes as DOUBLE PRECISION like every other coordinate column,
+-- so they decode into the f64 fields the API reads and writes.
+
+ALTER TABLE walker_locations
+    ALTER COLUMN latitude TYPE DOUBLE PRECISION,
+    ALTER COLUMN longitude TYPE DOUBLE PRECISION,
+    ALTER COLUMN accuracy_meters TYPE DOUBLE PRECISION,
+    ALTER COLUMN heading TYPE DOUBLE PRECISION,
+    ALTER COLUMN speed_mps TYPE DOUBLE PRECISION;
@@ -0,0 +1,7 @@
+-- The original transactions columns were superseded by customer_user_id and
+-- provider_payout_cents, which the API writes instead. Keep the old columns
+-- for existing rows but stop requiring them on new ones.
+
+ALTER TABLE transactions
+    ALTER COLUMN user_id DROP NOT NULL,
+    ALTER COLUMN net_amount_cents DROP NOT NULL;
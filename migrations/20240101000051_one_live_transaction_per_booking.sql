@@ -0,0 +1,6 @@
+-- A booking may only have one live payment at a time. Failed, refunded and
+-- released payments don't count, so the booking can be paid for again.
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_transactions_one_live_per_booking
+    ON transactions(booking_id)
+    WHERE booking_id IS NOT NULL AND status NOT IN ('failed', 'refunded', 'canceled');
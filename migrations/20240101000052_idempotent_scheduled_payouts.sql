@@ -0,0 +1,33 @@
+-- Scheduled payouts are keyed by the day they were made for, so the payout
+-- job running on several servers at once creates each payout only once.
+-- Instant payouts have no scheduled day.
+ALTER TABLE payouts
+    ADD COLUMN IF NOT EXISTS scheduled_for DATE;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_payouts_one_per_scheduled_day
+    ON payouts(organization_id, scheduled_for, currency)
+    WHERE scheduled_for IS NOT NULL;
+
+-- Refunds each payout deducted. A refund made after its transaction was paid
+-- out is deducted from the next payout, and only once.
+ALTER TABLE payouts
+    ADD COLUMN IF NOT EXISTS refund_ids UUID[] NOT NULL DEFAULT '{}';
+
+CREATE INDEX IF NOT EXISTS idx_payouts_refund_ids
+    ON payouts USING GIN(refund_ids);
+
+-- Existing payouts deducted the refunds made before them
+UPDATE payouts p
+SET refund_ids = ARRAY(
+    SELECT r.id FROM refunds r
+    WHERE r.transaction_id = ANY(p.transaction_ids)
+        AND r.created_at <= p.created_at
+        AND r.status IN ('pending', 'succeeded')
+);
+
+-- Providers that pay the merchant on their own schedule (Square) have no
+-- payout of ours to track
+ALTER TYPE payout_status ADD VALUE IF NOT EXISTS 'manual';
+
+COMMENT ON COLUMN payouts.scheduled_for IS 'Day a scheduled payout was made for; NULL for instant payouts';
+COMMENT ON COLUMN payouts.refund_ids IS 'Array of refund IDs deducted by this payout';
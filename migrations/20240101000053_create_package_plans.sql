@@ -0,0 +1,26 @@
+-- Package plans: the service packages a manager offers. Customers subscribe
+-- to a plan, so the price and walks each period are set by the organization
+-- rather than by the customer.
+
+CREATE TABLE IF NOT EXISTS package_plans (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    -- Bookings of this service redeem the plan's credits
+    service_id UUID NOT NULL REFERENCES services(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    description TEXT,
+    price_cents INTEGER NOT NULL CHECK (price_cents > 0),
+    interval VARCHAR(10) NOT NULL CHECK (interval IN ('week', 'month', 'year')),
+    interval_count INTEGER NOT NULL DEFAULT 1 CHECK (interval_count >= 1),
+    walks_per_period INTEGER NOT NULL CHECK (walks_per_period > 0),
+    -- Archived plans keep their subscribers but take no new ones
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_package_plans_org
+ON package_plans(organization_id, created_at DESC);
+
+ALTER TABLE customer_subscriptions
+    ADD COLUMN IF NOT EXISTS package_plan_id UUID REFERENCES package_plans(id) ON DELETE SET NULL;
@@ -0,0 +1,6 @@
+-- A package's credits only pay for bookings of its service, so packages
+-- must have one. Existing rows aren't checked; they no longer match any
+-- booking.
+ALTER TABLE customer_subscriptions
+    ADD CONSTRAINT customer_subscriptions_package_service
+    CHECK (walks_per_period IS NULL OR service_id IS NOT NULL) NOT VALID;
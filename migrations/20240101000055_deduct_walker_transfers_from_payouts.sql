@@ -0,0 +1,14 @@
+-- Walker earnings each payout deducted. Earnings transferred to a walker's
+-- connected account leave the balance the organization is paid out of, so
+-- they come off the organization's next payout, and only once.
+ALTER TABLE payouts
+    ADD COLUMN IF NOT EXISTS walker_earning_ids UUID[] NOT NULL DEFAULT '{}';
+
+CREATE INDEX IF NOT EXISTS idx_payouts_walker_earning_ids
+    ON payouts USING GIN(walker_earning_ids);
+
+-- Transfers to walkers and their reversals are posted to the ledger
+ALTER TYPE journal_entry_kind ADD VALUE IF NOT EXISTS 'walker_transfer';
+ALTER TYPE journal_entry_kind ADD VALUE IF NOT EXISTS 'walker_transfer_reversed';
+
+COMMENT ON COLUMN payouts.walker_earning_ids IS 'Array of walker earning IDs deducted by this payout';
@@ -0,0 +1,16 @@
+-- What a transaction's provider payment was requested with. A retry builds
+-- its request from these, so it sends the provider exactly what the first
+-- attempt did under the same idempotency key.
+ALTER TABLE transactions
+    ADD COLUMN IF NOT EXISTS idempotency_key VARCHAR(255),
+    ADD COLUMN IF NOT EXISTS manual_capture BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN IF NOT EXISTS off_session BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- One transaction per key, so a retried request finds the original
+CREATE UNIQUE INDEX IF NOT EXISTS idx_transactions_idempotency_key
+    ON transactions(organization_id, idempotency_key)
+    WHERE idempotency_key IS NOT NULL;
+
+COMMENT ON COLUMN transactions.idempotency_key IS 'Provider idempotency key the payment is requested with; the transaction ID is used when NULL';
+COMMENT ON COLUMN transactions.manual_capture IS 'Whether the payment is only authorized, to be captured later';
+COMMENT ON COLUMN transactions.off_session IS 'Whether the payment is made while the customer is not present';
@@ -0,0 +1,6 @@
+-- Country a location is in, so sales tax is calculated under the right
+-- country's rules. Existing locations were all treated as US.
+ALTER TABLE locations
+    ADD COLUMN IF NOT EXISTS country VARCHAR(2) NOT NULL DEFAULT 'US';
+
+COMMENT ON COLUMN locations.country IS 'ISO 3166-1 alpha-2 country code';
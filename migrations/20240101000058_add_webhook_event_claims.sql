@@ -0,0 +1,7 @@
+-- Which attempt holds a processing event. An event left processing past the
+-- timeout is claimed again under a new token, and only the attempt holding
+-- the current token records its outcome.
+ALTER TABLE payment_webhook_events
+    ADD COLUMN IF NOT EXISTS claimed_by UUID;
+
+COMMENT ON COLUMN payment_webhook_events.claimed_by IS 'Token of the attempt that last claimed the event';